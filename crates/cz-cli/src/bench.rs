@@ -0,0 +1,207 @@
+//! # cz bench — Ingest Load Generator
+//!
+//! Floods a sequencer's UDP port with well-formed `CausalEvent` packets
+//! (header + payload, checksummed exactly as the event loop verifies them)
+//! and reports the throughput achieved by the sender.
+
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+
+use cz_core::{compress, CausalEvent};
+
+/// Largest payload that still fits one UDP datagram behind the header.
+pub const MAX_PAYLOAD_SIZE: usize = 65_507 - CausalEvent::size_bytes();
+
+/// Build a packet the sequencer accepts: the event header, checksummed
+/// over `payload`, followed by the payload itself. The sequencer assigns
+/// `lamport_ts` and `payload_offset`, so both are sent as zero.
+pub fn build_packet(node_id: u32, stream_id: u16, payload: &[u8]) -> Vec<u8> {
+    let event = CausalEvent::new(0, node_id, stream_id, 0, crc32fast::hash(payload));
+    packet(&event, payload)
+}
+
+/// Like [`build_packet`], but the payload is sent as a
+/// [`cz_core::compress`] frame with `FLAG_COMPRESSED` set. The checksum
+/// covers the frame, the bytes actually on the wire.
+pub fn build_compressed_packet(node_id: u32, stream_id: u16, payload: &[u8]) -> Vec<u8> {
+    let mut frame = vec![0u8; compress::max_compressed_len(payload.len())];
+    let len = compress::compress(payload, &mut frame).expect("frame fits its bound");
+    frame.truncate(len);
+    let event = CausalEvent::new(0, node_id, stream_id, 0, crc32fast::hash(&frame)).compressed();
+    packet(&event, &frame)
+}
+
+fn packet(event: &CausalEvent, payload: &[u8]) -> Vec<u8> {
+    // SAFETY: `CausalEvent` is `#[repr(C)]` plain data of exactly
+    // `size_bytes()` bytes — the same reinterpretation the journal uses.
+    let header = unsafe {
+        std::slice::from_raw_parts(
+            event as *const CausalEvent as *const u8,
+            CausalEvent::size_bytes(),
+        )
+    };
+    let mut packet = Vec::with_capacity(header.len() + payload.len());
+    packet.extend_from_slice(header);
+    packet.extend_from_slice(payload);
+    packet
+}
+
+/// Throughput measured by one `cz bench` run.
+#[derive(Debug)]
+pub struct BenchReport {
+    pub events: u64,
+    pub bytes: u64,
+    pub elapsed: Duration,
+    pub p99_send: Duration,
+}
+
+impl BenchReport {
+    pub fn events_per_sec(&self) -> f64 {
+        self.events as f64 / self.elapsed.as_secs_f64()
+    }
+
+    pub fn bytes_per_sec(&self) -> f64 {
+        self.bytes as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+/// Send `count` events of `payload_size` bytes to `target` from
+/// `concurrency` sockets, each on its own thread and node id. With
+/// `compress`, payloads are sent compressed (see
+/// [`build_compressed_packet`]).
+pub fn run(
+    target: &str,
+    count: u64,
+    payload_size: usize,
+    concurrency: usize,
+    compress: bool,
+) -> io::Result<BenchReport> {
+    if payload_size > MAX_PAYLOAD_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("payload size must be at most {} bytes", MAX_PAYLOAD_SIZE),
+        ));
+    }
+    let target = target
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "target did not resolve"))?;
+    let concurrency = concurrency.max(1) as u64;
+
+    let started = Instant::now();
+    let workers: Vec<_> = (0..concurrency)
+        .map(|worker| {
+            let share = count / concurrency + u64::from(worker < count % concurrency);
+            std::thread::spawn(move || {
+                send_share(target, worker as u32, share, payload_size, compress)
+            })
+        })
+        .collect();
+
+    let mut events = 0;
+    let mut bytes = 0;
+    let mut latencies = Vec::with_capacity(count as usize);
+    for worker in workers {
+        let (sent, sent_bytes, worker_latencies) = worker
+            .join()
+            .map_err(|_| io::Error::other("bench worker panicked"))??;
+        events += sent;
+        bytes += sent_bytes;
+        latencies.extend(worker_latencies);
+    }
+    let elapsed = started.elapsed();
+
+    latencies.sort_unstable();
+    let p99_send = latencies
+        .get((latencies.len() * 99 / 100).min(latencies.len().saturating_sub(1)))
+        .copied()
+        .unwrap_or_default();
+
+    Ok(BenchReport {
+        events,
+        bytes,
+        elapsed,
+        p99_send,
+    })
+}
+
+/// One worker's loop: returns events sent, bytes sent, and per-send latency.
+fn send_share(
+    target: SocketAddr,
+    node_id: u32,
+    count: u64,
+    payload_size: usize,
+    compress: bool,
+) -> io::Result<(u64, u64, Vec<Duration>)> {
+    let local: SocketAddr = if target.is_ipv4() {
+        ([0, 0, 0, 0], 0).into()
+    } else {
+        ([0u16; 8], 0).into()
+    };
+    let socket = UdpSocket::bind(local)?;
+    socket.connect(target)?;
+
+    let payload: Vec<u8> = (0..payload_size).map(|i| i as u8).collect();
+    let packet = if compress {
+        build_compressed_packet(node_id, 0, &payload)
+    } else {
+        build_packet(node_id, 0, &payload)
+    };
+
+    let mut latencies = Vec::with_capacity(count as usize);
+    let mut bytes = 0;
+    for _ in 0..count {
+        let sent_at = Instant::now();
+        bytes += socket.send(&packet)? as u64;
+        latencies.push(sent_at.elapsed());
+    }
+    Ok((count, bytes, latencies))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_packet_has_header_payload_and_checksum() {
+        let payload = b"lacrimosa";
+        let packet = build_packet(7, 3, payload);
+        assert_eq!(packet.len(), CausalEvent::size_bytes() + payload.len());
+
+        let event = unsafe { std::ptr::read_unaligned(packet.as_ptr() as *const CausalEvent) };
+        assert_eq!(event.node_id, 7);
+        assert_eq!(event.stream_id, 3);
+        assert_eq!(&packet[CausalEvent::size_bytes()..], payload);
+        assert_eq!(
+            event.checksum,
+            crc32fast::hash(&packet[CausalEvent::size_bytes()..])
+        );
+    }
+
+    #[test]
+    fn test_compressed_packet_checksums_the_frame() {
+        let payload = [b'x'; 512];
+        let packet = build_compressed_packet(7, 3, &payload);
+        let event = CausalEvent::from_bytes(&packet).unwrap();
+        let frame = &packet[CausalEvent::size_bytes()..];
+        assert!(event.is_compressed());
+        assert!(frame.len() < payload.len());
+        assert_eq!(event.checksum, crc32fast::hash(frame));
+
+        let mut out = [0u8; 512];
+        assert_eq!(compress::decompress(frame, &mut out), Ok(payload.len()));
+        assert_eq!(out, payload);
+    }
+
+    #[test]
+    fn test_run_delivers_every_event() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let target = receiver.local_addr().unwrap().to_string();
+
+        let report = run(&target, 10, 16, 3, false).unwrap();
+        assert_eq!(report.events, 10);
+        assert_eq!(report.bytes, 10 * (CausalEvent::size_bytes() as u64 + 16));
+        assert!(run(&target, 1, MAX_PAYLOAD_SIZE + 1, 1, false).is_err());
+    }
+}
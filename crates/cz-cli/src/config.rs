@@ -0,0 +1,295 @@
+//! # CLI Configuration
+//!
+//! Where the remote commands find the hub and which API key they send.
+//! Each setting is taken from the first of these that has it:
+//!
+//! 1. `--base-url` / `--api-key`
+//! 2. `CZ_BASE_URL` / `CZ_API_KEY`
+//! 3. `~/.cz/config.toml`: the `[profile.NAME]` section picked with
+//!    `--profile`, then the top-level keys
+//!
+//! ```toml
+//! base_url = "http://127.0.0.1:3000"
+//! api_key = "cz_..."
+//!
+//! [profile.prod]
+//! base_url = "https://hub.example.com"
+//! api_key = "cz_..."
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+/// Hub address used when nothing else is configured.
+pub const DEFAULT_BASE_URL: &str = "http://127.0.0.1:3000";
+
+/// Settings given on the command line, which win over everything else.
+#[derive(Debug, Default, Clone)]
+pub struct Overrides {
+    pub profile: Option<String>,
+    pub base_url: Option<String>,
+    pub api_key: Option<String>,
+}
+
+/// The resolved settings the remote commands use.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientConfig {
+    pub base_url: String,
+    pub api_key: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Settings {
+    base_url: Option<String>,
+    api_key: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ConfigFile {
+    #[serde(flatten)]
+    defaults: Settings,
+    #[serde(default)]
+    profile: std::collections::BTreeMap<String, Settings>,
+}
+
+/// `~/.cz/config.toml`, if the home directory is known.
+pub fn default_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cz").join("config.toml"))
+}
+
+/// Resolve the client settings from `overrides`, the environment and the
+/// config file at `path` (a missing file counts as empty).
+pub fn load(path: Option<&Path>, overrides: &Overrides) -> Result<ClientConfig, String> {
+    let contents = match path {
+        Some(path) => match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(format!("Cannot read {}: {}", path.display(), e)),
+        },
+        None => String::new(),
+    };
+    resolve(&contents, overrides, |name| std::env::var(name).ok())
+}
+
+/// [`load`] with the file contents and environment passed in.
+fn resolve(
+    contents: &str,
+    overrides: &Overrides,
+    env: impl Fn(&str) -> Option<String>,
+) -> Result<ClientConfig, String> {
+    let file: ConfigFile =
+        toml::from_str(contents).map_err(|e| format!("Invalid CLI config: {}", e))?;
+
+    let profile = match &overrides.profile {
+        Some(name) => Some(
+            file.profile
+                .get(name)
+                .ok_or_else(|| format!("No [profile.{}] in the CLI config", name))?,
+        ),
+        None => None,
+    };
+    let from_file = |field: fn(&Settings) -> &Option<String>| {
+        profile
+            .and_then(|p| field(p).clone())
+            .or_else(|| field(&file.defaults).clone())
+    };
+
+    let base_url = overrides
+        .base_url
+        .clone()
+        .or_else(|| env("CZ_BASE_URL"))
+        .or_else(|| from_file(|s| &s.base_url))
+        .unwrap_or_else(|| DEFAULT_BASE_URL.to_string());
+    let api_key = overrides
+        .api_key
+        .clone()
+        .or_else(|| env("CZ_API_KEY"))
+        .or_else(|| from_file(|s| &s.api_key));
+
+    Ok(ClientConfig {
+        base_url: base_url.trim_end_matches('/').to_string(),
+        api_key,
+    })
+}
+
+/// Store `api_key` (and `base_url`, if given) in the config file at
+/// `path`, under `[profile.NAME]` when `profile` is set. Everything else
+/// in the file is kept. The file is created, or left, readable by its
+/// owner only.
+pub fn save_login(
+    path: &Path,
+    profile: Option<&str>,
+    api_key: &str,
+    base_url: Option<&str>,
+) -> Result<(), String> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("Cannot read {}: {}", path.display(), e)),
+    };
+    let mut doc: toml::Table =
+        toml::from_str(&contents).map_err(|e| format!("Invalid CLI config: {}", e))?;
+
+    let section = match profile {
+        Some(name) => {
+            let profiles = doc
+                .entry("profile")
+                .or_insert_with(|| toml::Table::new().into())
+                .as_table_mut()
+                .ok_or("`profile` in the CLI config is not a table")?;
+            profiles
+                .entry(name)
+                .or_insert_with(|| toml::Table::new().into())
+                .as_table_mut()
+                .ok_or_else(|| format!("`profile.{}` in the CLI config is not a table", name))?
+        }
+        None => &mut doc,
+    };
+    section.insert("api_key".into(), api_key.into());
+    if let Some(base_url) = base_url {
+        section.insert("base_url".into(), base_url.into());
+    }
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    }
+    write_private(path, toml::to_string(&doc).unwrap().as_bytes())
+        .map_err(|e| format!("Cannot write {}: {}", path.display(), e))
+}
+
+/// Write `bytes` to `path` with mode 0600, tightening an existing file.
+fn write_private(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    file.write_all(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &str = r#"
+base_url = "http://file:3000"
+api_key = "file-key"
+
+[profile.prod]
+base_url = "https://prod:443/"
+api_key = "prod-key"
+
+[profile.staging]
+base_url = "http://staging:3000"
+"#;
+
+    fn env(vars: &'static [(&'static str, &'static str)]) -> impl Fn(&str) -> Option<String> {
+        move |name| {
+            vars.iter()
+                .find(|(k, _)| *k == name)
+                .map(|(_, v)| v.to_string())
+        }
+    }
+
+    #[test]
+    fn test_flag_beats_env_beats_file() {
+        let none = Overrides::default();
+        let config = resolve(FILE, &none, env(&[])).unwrap();
+        assert_eq!(config.base_url, "http://file:3000");
+        assert_eq!(config.api_key.as_deref(), Some("file-key"));
+
+        let vars = env(&[
+            ("CZ_BASE_URL", "http://env:3000"),
+            ("CZ_API_KEY", "env-key"),
+        ]);
+        let config = resolve(FILE, &none, &vars).unwrap();
+        assert_eq!(config.base_url, "http://env:3000");
+        assert_eq!(config.api_key.as_deref(), Some("env-key"));
+
+        let flags = Overrides {
+            api_key: Some("flag-key".into()),
+            ..Default::default()
+        };
+        let config = resolve(FILE, &flags, &vars).unwrap();
+        assert_eq!(config.base_url, "http://env:3000");
+        assert_eq!(config.api_key.as_deref(), Some("flag-key"));
+
+        let config = resolve("", &none, env(&[])).unwrap();
+        assert_eq!(config.base_url, DEFAULT_BASE_URL);
+        assert_eq!(config.api_key, None);
+    }
+
+    #[test]
+    fn test_profile_selection() {
+        let profile = |name: &str| Overrides {
+            profile: Some(name.into()),
+            ..Default::default()
+        };
+
+        let prod = resolve(FILE, &profile("prod"), env(&[])).unwrap();
+        assert_eq!(prod.base_url, "https://prod:443");
+        assert_eq!(prod.api_key.as_deref(), Some("prod-key"));
+
+        // Settings a profile leaves out come from the top level.
+        let staging = resolve(FILE, &profile("staging"), env(&[])).unwrap();
+        assert_eq!(staging.base_url, "http://staging:3000");
+        assert_eq!(staging.api_key.as_deref(), Some("file-key"));
+
+        // The environment still wins over the selected profile.
+        let vars = env(&[("CZ_API_KEY", "env-key")]);
+        let prod = resolve(FILE, &profile("prod"), vars).unwrap();
+        assert_eq!(prod.api_key.as_deref(), Some("env-key"));
+
+        let err = resolve(FILE, &profile("dev"), env(&[])).unwrap_err();
+        assert!(err.contains("[profile.dev]"), "{}", err);
+    }
+
+    #[test]
+    fn test_login_writes_private_file_and_keeps_other_settings() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("cz-config-{}", std::process::id()));
+        let path = dir.join(".cz").join("config.toml");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, FILE).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        save_login(&path, Some("prod"), "new-prod-key", None).unwrap();
+        save_login(&path, Some("dev"), "dev-key", Some("http://dev:3000")).unwrap();
+
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        let load_profile = |name: Option<&str>| {
+            let overrides = Overrides {
+                profile: name.map(Into::into),
+                ..Default::default()
+            };
+            resolve(
+                &std::fs::read_to_string(&path).unwrap(),
+                &overrides,
+                env(&[]),
+            )
+            .unwrap()
+        };
+        let prod = load_profile(Some("prod"));
+        assert_eq!(prod.api_key.as_deref(), Some("new-prod-key"));
+        assert_eq!(prod.base_url, "https://prod:443");
+        assert_eq!(load_profile(Some("dev")).base_url, "http://dev:3000");
+        assert_eq!(load_profile(None).api_key.as_deref(), Some("file-key"));
+
+        // A first login creates the directory and file.
+        std::fs::remove_dir_all(&dir).unwrap();
+        save_login(&path, None, "only-key", None).unwrap();
+        assert_eq!(load_profile(None).api_key.as_deref(), Some("only-key"));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
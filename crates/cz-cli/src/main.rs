@@ -3,17 +3,32 @@
 //! Minimal CLI interface for the distributed sequencer.
 //!
 //! - `cz start --journal <path>` — Boot the io_uring event loop.
-//! - `cz verify` — Run Kani proofs.
+//! - `cz verify [--json]` — Run Kani proofs; `--json` prints a per-harness report.
 //! - `cz status` — Report system metrics.
+//! - `cz ctl flush|rotate|stats` — Control a running sequencer.
+//! - `cz bench --target <addr>` — Flood a sequencer and report throughput.
+//! - `cz query <cql> --journal <path>` — Query a journal file offline.
+//! - `cz dump --journal <path> --slot N` — Inspect raw Index Ring slots.
+//! - `cz login --key <key>` — Save an API key to `~/.cz/config.toml`.
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
 use clap::{Parser, Subcommand};
 
+mod bench;
+mod config;
+mod dump;
+mod output;
+mod replay;
+mod tail;
+
+use cz_hub::verify::{self, HarnessStatus};
 use cz_io::cursor::Cursor;
 use cz_io::event_loop::{EventLoop, EventLoopConfig};
-use cz_io::journal::Journal;
+use cz_io::ipc::IpcCommand;
+use cz_io::journal::{Journal, JournalError, ScanReport};
+use output::OutputFormat;
 
 /// 🧬 LACRIMOSA — A hyper-efficient, formally verified distributed sequencer.
 #[derive(Parser)]
@@ -21,6 +36,26 @@ use cz_io::journal::Journal;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Output format for list-shaped results.
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Table)]
+    output: OutputFormat,
+
+    /// Shortcut for `--output json`.
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// `[profile.NAME]` section of ~/.cz/config.toml to use.
+    #[arg(long, global = true)]
+    profile: Option<String>,
+
+    /// Hub URL (overrides CZ_BASE_URL and the config file).
+    #[arg(long, global = true)]
+    base_url: Option<String>,
+
+    /// API key (overrides CZ_API_KEY and the config file).
+    #[arg(long, global = true)]
+    api_key: Option<String>,
 }
 
 #[derive(Subcommand)]
@@ -35,12 +70,27 @@ enum Commands {
         #[arg(long, default_value_t = 100)]
         size_gib: u64,
 
+        /// Index Ring size in MiB when creating the journal (default: 1024);
+        /// an existing journal keeps the ring it was created with.
+        #[arg(long, default_value_t = 1024)]
+        ring_mib: usize,
+
+        /// Split a new journal's Index Ring into this many sub-rings, one
+        /// per `stream_id % partitions` (default: 1, a single ring).
+        #[arg(long, default_value_t = 1)]
+        partitions: usize,
+
         /// UDP bind address (default: 0.0.0.0:9000).
         #[arg(long, default_value = "0.0.0.0:9000")]
         bind: String,
+
+        /// IPC socket: a path, or `@name` for the abstract namespace
+        /// (default: derived from the journal path).
+        #[arg(long)]
+        ipc_socket: Option<String>,
     },
 
-    /// Run Kani formal verification proofs.
+    /// Run Kani formal verification proofs (`--json` for a per-harness report).
     Verify,
 
     /// Report system status as JSON.
@@ -72,6 +122,84 @@ enum Commands {
         seq_bind: String,
     },
 
+    /// Send a control command to the running sequencer.
+    Ctl {
+        /// Journal whose sequencer to address; its socket is read from the
+        /// journal header.
+        #[arg(long, default_value = "journal.db")]
+        journal: PathBuf,
+
+        /// Explicit IPC socket, overriding the journal lookup.
+        #[arg(long)]
+        socket: Option<String>,
+
+        #[command(subcommand)]
+        action: CtlCmd,
+    },
+
+    /// Flood a sequencer with valid events and report throughput as JSON.
+    Bench {
+        /// Sequencer UDP address.
+        #[arg(long, default_value = "127.0.0.1:9000")]
+        target: String,
+
+        /// Total number of events to send.
+        #[arg(long, default_value_t = 100_000)]
+        count: u64,
+
+        /// Payload bytes per event (after the 32-byte header).
+        #[arg(long, default_value_t = 64)]
+        payload_size: usize,
+
+        /// Number of concurrent sending sockets.
+        #[arg(long, default_value_t = 1)]
+        concurrency: usize,
+
+        /// Send payloads LZ4-compressed, with `FLAG_COMPRESSED` set.
+        #[arg(long)]
+        compress: bool,
+    },
+
+    /// Print decoded (and optionally hex) Index Ring slots from a journal.
+    Dump {
+        /// Journal file to inspect.
+        #[arg(long)]
+        journal: PathBuf,
+
+        /// A single slot to show.
+        #[arg(long, conflicts_with = "range", required_unless_present = "range")]
+        slot: Option<usize>,
+
+        /// Slots to show, as `A..B` (end exclusive).
+        #[arg(long)]
+        range: Option<String>,
+
+        /// Add hex + ASCII views of the header and payload bytes.
+        #[arg(long)]
+        hex: bool,
+
+        /// Payload bytes to include in the hex view.
+        #[arg(long, default_value_t = 64)]
+        payload_bytes: usize,
+
+        /// Skip slots that were never written.
+        #[arg(long)]
+        live_only: bool,
+    },
+
+    /// Check every live event's payload against its checksum.
+    Fsck {
+        /// Journal file to check.
+        #[arg(long)]
+        journal: PathBuf,
+    },
+
+    /// Save an API key to ~/.cz/config.toml (under `--profile`, if given).
+    Login {
+        #[arg(long)]
+        key: String,
+    },
+
     /// Manage connectors (list, add, remove).
     Connectors {
         #[command(subcommand)]
@@ -79,10 +207,63 @@ enum Commands {
     },
 
     /// Run a Causal Query Language (CQL) query.
-    Query { query: String },
+    Query {
+        query: String,
+
+        /// Query this journal file directly instead of a running hub.
+        #[arg(long)]
+        journal: Option<PathBuf>,
+
+        /// Describe how the hub would run the query instead of running it:
+        /// the streams it reads and why, where each field is looked up and
+        /// the time window in effect. Same as an `EXPLAIN` prefix.
+        #[arg(long, conflicts_with = "journal")]
+        explain: bool,
+    },
+
+    /// Replay a slot range from one journal onto the head of another.
+    Replay {
+        /// Source journal (the hub's default journal if omitted).
+        #[arg(long)]
+        from: Option<PathBuf>,
+
+        /// Target journal (the hub's default journal if omitted).
+        #[arg(long)]
+        to: Option<PathBuf>,
+
+        /// First source slot to replay.
+        #[arg(long)]
+        start: usize,
+
+        /// Last source slot to replay (inclusive).
+        #[arg(long)]
+        end: usize,
+
+        /// Restamp replayed events after the target's newest `lamport_ts`.
+        #[arg(long)]
+        resequence: bool,
+
+        /// Replay between the journal files directly instead of via the hub.
+        #[arg(long, requires_all = ["from", "to"])]
+        local: bool,
+    },
+
+    /// Live tail a stream (a stream id, or `all`) over the hub WebSocket.
+    Tail {
+        stream: String,
 
-    /// Live tail a stream.
-    Tail { stream: String },
+        /// Backfill from this ring slot up to the head before going live.
+        #[arg(long)]
+        since_slot: Option<u64>,
+
+        /// Only follow this journal (a path the hub serves).
+        #[arg(long)]
+        journal: Option<String>,
+
+        /// Page `/api/events` instead of subscribing over the WebSocket.
+        #[arg(long)]
+        poll: bool,
+    },
 
     /// List active incidents.
     Incidents,
@@ -96,6 +277,16 @@ enum Commands {
     },
 }
 
+#[derive(Subcommand)]
+enum CtlCmd {
+    /// Flush the journal to disk.
+    Flush,
+    /// Archive the journal to a file and start the ring over empty.
+    Rotate { path: PathBuf },
+    /// Print sequencer counters as JSON.
+    Stats,
+}
+
 #[derive(Subcommand)]
 enum ConnectorCmd {
     List,
@@ -111,12 +302,25 @@ enum ConnectorCmd {
 
 fn main() {
     let cli = Cli::parse();
+    // Warnings from the sequencer; stdout stays for command output.
+    tracing_subscriber::fmt()
+        .with_max_level(tracing_subscriber::filter::LevelFilter::WARN)
+        .with_writer(std::io::stderr)
+        .init();
+    let format = if cli.json {
+        OutputFormat::Json
+    } else {
+        cli.output
+    };
 
     match cli.command {
         Commands::Start {
             journal: journal_path,
             size_gib,
+            ring_mib,
+            partitions,
             bind,
+            ipc_socket,
         } => {
             eprintln!("🧬 LACRIMOSA: Booting sequencer...");
             eprintln!("   Journal: {}", journal_path.display());
@@ -125,13 +329,28 @@ fn main() {
 
             let size = size_gib * 1024 * 1024 * 1024;
 
-            let mut journal = Journal::open(&journal_path, size).expect("Failed to open journal");
+            let mut journal =
+                open_journal_or_exit(&journal_path, size, ring_mib * 1024 * 1024, partitions);
+            eprintln!("   Ring:    {} slots", journal.index_ring_capacity());
+            if journal.partitions() > 1 {
+                eprintln!(
+                    "   Parts:   {} × {} slots",
+                    journal.partitions(),
+                    journal.partition_capacity()
+                );
+            }
+
+            let cursors = recover_cursors(&journal);
 
-            let mut cursor = Cursor::for_index_ring();
+            let ipc_socket =
+                ipc_socket.unwrap_or_else(|| cz_io::ipc::socket_for_journal(&journal_path));
+            eprintln!("   IPC:     {}", ipc_socket);
 
             let config = EventLoopConfig {
                 bind_addr: bind,
                 ring_depth: 256,
+                ipc_socket: Some(ipc_socket),
+                ..Default::default()
             };
 
             let mut event_loop =
@@ -139,61 +358,79 @@ fn main() {
 
             eprintln!("🧬 LACRIMOSA: Sequencer running. Press Ctrl+C to stop.");
 
-            event_loop
-                .run(&mut journal, &mut cursor)
-                .expect("Event loop failed");
+            if let Err(e) = event_loop.run_partitioned(&mut journal, cursors) {
+                eprintln!("Event loop failed: {}", e);
+                std::process::exit(1);
+            }
         }
 
         Commands::Verify => {
+            if format == OutputFormat::Json {
+                let report = verify::run_kani(&verify::KANI_PACKAGES, Path::new("."));
+                println!("{}", serde_json::to_string_pretty(&report).unwrap());
+                std::process::exit(if report.success { 0 } else { 1 });
+            }
+
             eprintln!("🧬 LACRIMOSA: Running formal verification...");
             eprintln!("   Tool: Kani Model Checker");
-            eprintln!("   Targets: cz-verify (ordering proofs), cz-io (ring invariants)");
+            eprintln!(
+                "   Targets: cz-verify (ordering, slot and HLC proofs), cz-io (ring invariants)"
+            );
+            eprintln!("   Runtime: cz-verify property tests (same properties, larger inputs)");
             eprintln!();
 
-            // Run Kani on cz-verify
-            let verify_status = Command::new("cargo")
-                .args(["kani", "--package", "cz-verify"])
-                .status();
-
-            let verify_passed = match verify_status {
-                Ok(status) => {
-                    if status.success() {
-                        eprintln!("   ✅ cz-verify: ALL PROOFS PASSED");
-                        true
-                    } else {
-                        eprintln!("   ❌ cz-verify: PROOF FAILURE");
-                        false
+            let report = verify::run_kani(&verify::KANI_PACKAGES, Path::new("."));
+            for harness in &report.harnesses {
+                let mark = match harness.status {
+                    HarnessStatus::Passed => "✅",
+                    HarnessStatus::Failed => "❌",
+                    HarnessStatus::Unknown => "⚠️ ",
+                };
+                let time = harness
+                    .duration_ms
+                    .map(|ms| format!(" ({} ms)", ms))
+                    .unwrap_or_default();
+                eprintln!("   {} {}::{}{}", mark, harness.package, harness.name, time);
+                if let Some(counterexample) = &harness.counterexample {
+                    for line in counterexample.lines() {
+                        eprintln!("        {}", line);
                     }
                 }
-                Err(e) => {
-                    eprintln!("   ⚠️  Kani not found: {}", e);
-                    eprintln!("   Install with: cargo install kani-verifier && cargo kani setup");
-                    false
-                }
-            };
-
-            // Run Kani on cz-io
-            let io_status = Command::new("cargo")
-                .args(["kani", "--package", "cz-io"])
+            }
+            for error in &report.errors {
+                eprintln!("   ⚠️  {}", error);
+            }
+            if report.harnesses.is_empty() {
+                eprintln!("   Install with: cargo install kani-verifier && cargo kani setup");
+            }
+            eprintln!(
+                "   Kani: {} of {} harnesses passed",
+                report.passed(),
+                report.harnesses.len()
+            );
+
+            // Run the property tests that mirror the proofs at scale
+            let props_status = Command::new("cargo")
+                .args(["test", "--package", "cz-verify"])
                 .status();
 
-            let io_passed = match io_status {
+            let props_passed = match props_status {
                 Ok(status) => {
                     if status.success() {
-                        eprintln!("   ✅ cz-io: ALL PROOFS PASSED");
+                        eprintln!("   ✅ cz-verify properties: ALL PASSED");
                         true
                     } else {
-                        eprintln!("   ❌ cz-io: PROOF FAILURE");
+                        eprintln!("   ❌ cz-verify properties: FAILURE");
                         false
                     }
                 }
                 Err(e) => {
-                    eprintln!("   ⚠️  Kani not found: {}", e);
+                    eprintln!("   ⚠️  cargo not found: {}", e);
                     false
                 }
             };
 
-            if verify_passed && io_passed {
+            if report.success && props_passed {
                 eprintln!();
                 eprintln!("🧬 VERIFICATION COMPLETE: Mathematical Safety Confirmed.");
                 std::process::exit(0);
@@ -276,15 +513,17 @@ fn main() {
             let s_bind = seq_bind.clone();
             std::thread::spawn(move || {
                 let size = 100 * 1024 * 1024 * 1024; // Default 100GB
-                let mut journal = Journal::open(&j_path, size).expect("Failed to open journal");
-                let mut cursor = Cursor::for_index_ring();
+                let mut journal =
+                    open_journal_or_exit(&j_path, size, cz_io::journal::DEFAULT_INDEX_RING_SIZE, 1);
+                let cursors = recover_cursors(&journal);
                 let config = EventLoopConfig {
                     bind_addr: s_bind,
                     ring_depth: 256,
+                    ..Default::default()
                 };
                 let mut event_loop = EventLoop::new(&config).expect("Failed to create event loop");
                 event_loop
-                    .run(&mut journal, &mut cursor)
+                    .run_partitioned(&mut journal, cursors)
                     .expect("Sequencer failed");
             });
 
@@ -318,27 +557,353 @@ fn main() {
             }
         }
 
+        Commands::Ctl {
+            journal,
+            socket,
+            action,
+        } => {
+            let command = match action {
+                CtlCmd::Flush => IpcCommand::Flush,
+                CtlCmd::Rotate { path } => IpcCommand::Rollover {
+                    path: path.display().to_string(),
+                },
+                CtlCmd::Stats => IpcCommand::QueryStats,
+            };
+
+            let socket = socket.unwrap_or_else(|| cz_io::ipc::resolve_socket(&journal));
+            let reply =
+                match cz_io::ipc::send_command(&socket, command, std::time::Duration::from_secs(5))
+                {
+                    Ok(reply) => reply,
+                    Err(e) => {
+                        eprintln!("Failed to reach sequencer at {}: {}", socket, e);
+                        std::process::exit(1);
+                    }
+                };
+            if !reply.ok {
+                eprintln!("Sequencer rejected command: {}", reply.detail);
+                std::process::exit(1);
+            }
+
+            match reply.stats {
+                Some(stats) => {
+                    let status = serde_json::json!({
+                        "events_processed": stats.events_processed,
+                        "bytes_processed": stats.bytes_processed,
+                        "ring_len": stats.ring_len,
+                    });
+                    println!("{}", serde_json::to_string_pretty(&status).unwrap());
+                }
+                None => println!("{}", reply.detail),
+            }
+        }
+
+        Commands::Bench {
+            target,
+            count,
+            payload_size,
+            concurrency,
+            compress,
+        } => {
+            eprintln!(
+                "🧬 LACRIMOSA: Sending {} events of {} bytes to {} ({} sockets)...",
+                count, payload_size, target, concurrency
+            );
+            let report = match bench::run(&target, count, payload_size, concurrency, compress) {
+                Ok(report) => report,
+                Err(e) => {
+                    eprintln!("Benchmark failed: {}", e);
+                    std::process::exit(1);
+                }
+            };
+
+            let summary = serde_json::json!({
+                "events": report.events,
+                "bytes": report.bytes,
+                "elapsed_secs": report.elapsed.as_secs_f64(),
+                "events_per_sec": report.events_per_sec(),
+                "bytes_per_sec": report.bytes_per_sec(),
+                "p99_send_us": report.p99_send.as_secs_f64() * 1e6,
+            });
+            println!("{}", serde_json::to_string_pretty(&summary).unwrap());
+        }
+
+        Commands::Query {
+            query,
+            journal: Some(journal),
+            ..
+        } => match query_journal(&journal, &query) {
+            Ok(result) => {
+                let result = serde_json::to_value(result).unwrap();
+                println!("{}", output::render(&result, format));
+            }
+            Err(e) => {
+                eprintln!("Query failed: {}", e);
+                std::process::exit(1);
+            }
+        },
+
+        Commands::Dump {
+            journal,
+            slot,
+            range,
+            hex,
+            payload_bytes,
+            live_only,
+        } => {
+            let output = open_existing_journal(&journal).and_then(|journal| {
+                let slots = match (slot, range) {
+                    (Some(slot), _) => slot..slot + 1,
+                    (None, Some(range)) => {
+                        dump::parse_range(&range, journal.index_ring_capacity())?
+                    }
+                    (None, None) => unreachable!("clap requires --slot or --range"),
+                };
+                let options = dump::DumpOptions {
+                    hex,
+                    payload_bytes,
+                    live_only,
+                };
+                Ok(dump::dump(&journal, slots, options))
+            });
+            match output {
+                Ok(output) => print!("{}", output),
+                Err(e) => {
+                    eprintln!("Dump failed: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        Commands::Fsck { journal } => {
+            let journal = match open_existing_journal(&journal) {
+                Ok(journal) => journal,
+                Err(e) => {
+                    eprintln!("Fsck failed: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            let report = journal.integrity_scan(&journal.live_cursor_view());
+            print!("{}", fsck_summary(&report));
+            if !report.is_clean() {
+                std::process::exit(1);
+            }
+        }
+
+        Commands::Replay {
+            from: Some(from),
+            to: Some(to),
+            start,
+            end,
+            resequence,
+            local: true,
+        } => {
+            let bar = indicatif::ProgressBar::new(0);
+            let summary =
+                replay::replay_local(&from, &to, start, end, resequence, |done, total| {
+                    bar.set_length(total as u64);
+                    bar.set_position(done as u64);
+                });
+            bar.finish_and_clear();
+            match summary {
+                Ok(summary) => print_replay_summary(&summary, format),
+                Err(e) => {
+                    eprintln!("Replay failed: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        Commands::Login { key } => {
+            let Some(path) = config::default_path() else {
+                eprintln!("Login failed: HOME is not set");
+                std::process::exit(1);
+            };
+            let saved =
+                config::save_login(&path, cli.profile.as_deref(), &key, cli.base_url.as_deref());
+            match saved {
+                Ok(()) => eprintln!("Saved API key to {}", path.display()),
+                Err(e) => {
+                    eprintln!("Login failed: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+
         // Async Commands
         cmd => {
+            let overrides = config::Overrides {
+                profile: cli.profile,
+                base_url: cli.base_url,
+                api_key: cli.api_key,
+            };
+            let client_config = match config::load(config::default_path().as_deref(), &overrides) {
+                Ok(client_config) => client_config,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            };
+
             let rt = tokio::runtime::Builder::new_current_thread()
                 .enable_all()
                 .build()
                 .expect("Failed to build tokio runtime");
 
-            rt.block_on(async_main(cmd));
+            rt.block_on(async_main(cmd, format, client_config));
         }
     }
 }
 
-async fn async_main(cmd: Commands) {
-    let client = reqwest::Client::new();
-    let base_url =
-        std::env::var("CZ_BASE_URL").unwrap_or_else(|_| "http://127.0.0.1:3000".to_string());
+/// `/api/events` for the journal and stream in `filter`, one page: the
+/// first, or the one after `cursor` (a page's `next_cursor`).
+fn events_url(base_url: &str, filter: &tail::StreamFilter, cursor: Option<&str>) -> String {
+    let mut url = format!("{}/api/events?limit=100", base_url);
+    if let Some(cursor) = cursor {
+        url.push_str(&format!("&cursor={}", cursor));
+    }
+    if let Some(stream_id) = filter.stream_id {
+        url.push_str(&format!("&stream_id={}", stream_id));
+    }
+    if let Some(journal) = &filter.journal {
+        url.push_str(&format!("&journal={}", journal));
+    }
+    url
+}
+
+/// Tail by paging `/api/events` every 500ms.
+async fn poll_tail(
+    client: &reqwest::Client,
+    base_url: &str,
+    api_key: Option<&str>,
+    filter: &tail::StreamFilter,
+) -> Result<(), String> {
+    let mut cursor: Option<String> = None;
+    loop {
+        let url = events_url(base_url, filter, cursor.as_deref());
+        match get_request(client, &url, api_key).await {
+            Ok(resp) => {
+                if let Ok(json) = resp.json::<serde_json::Value>().await {
+                    if let Some(events) = json.get("events").and_then(|e| e.as_array()) {
+                        if events.is_empty() {
+                            tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+                            continue;
+                        }
+                        for event in events {
+                            println!("{}", serde_json::to_string(event).unwrap());
+                        }
+                        if let Some(next) = json["next_cursor"].as_str() {
+                            cursor = Some(next.to_string());
+                        }
+                    }
+                }
+            }
+            Err(_) => tokio::time::sleep(tokio::time::Duration::from_secs(1)).await,
+        }
+    }
+}
+
+/// Tail over `/ws`, backfilling from `since_slot` first if given.
+async fn live_tail(
+    client: &reqwest::Client,
+    base_url: &str,
+    api_key: Option<&str>,
+    filter: &tail::StreamFilter,
+    since_slot: Option<u64>,
+) -> Result<(), String> {
+    use futures::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    // Subscribe before reading the ring, so nothing committed during the
+    // backfill is missed; `Backfill` drops what both of them saw.
+    let url = tail::ws_url(base_url);
+    let (mut socket, _) = tokio_tungstenite::connect_async(url.as_str())
+        .await
+        .map_err(|e| format!("Cannot open {}: {} (try --poll)", url, e))?;
+    socket
+        .send(Message::text(tail::subscribe_message(filter)))
+        .await
+        .map_err(|e| format!("Cannot subscribe on {}: {}", url, e))?;
+
+    let mut backfill = None;
+    if let Some(since_slot) = since_slot {
+        let mut ring_url = format!("{}/api/ring", base_url);
+        if let Some(journal) = &filter.journal {
+            ring_url.push_str(&format!("?journal={}", journal));
+        }
+        let ring: serde_json::Value = get_request(client, &ring_url, api_key)
+            .await
+            .map_err(|e| e.to_string())?
+            .json()
+            .await
+            .map_err(|e| e.to_string())?;
+        let (Some(tail), Some(capacity)) = (ring["tail"].as_u64(), ring["capacity"].as_u64())
+        else {
+            return Err(format!("Unexpected /api/ring response: {}", ring));
+        };
+        let mut window = tail::Backfill::new(since_slot, tail, capacity);
+
+        let mut cursor: Option<String> = None;
+        loop {
+            let page: serde_json::Value = get_request(
+                client,
+                &events_url(base_url, filter, cursor.as_deref()),
+                api_key,
+            )
+            .await
+            .map_err(|e| e.to_string())?
+            .json()
+            .await
+            .map_err(|e| e.to_string())?;
+            let events = page["events"].as_array().cloned().unwrap_or_default();
+            if events.is_empty() {
+                break;
+            }
+            // A hub without cursors would hand back the first page forever.
+            let Some(next) = page["next_cursor"].as_str() else {
+                break;
+            };
+            cursor = Some(next.to_string());
+            for event in events {
+                let (Some(slot), Some(ts)) = (event["slot"].as_u64(), event["lamport_ts"].as_u64())
+                else {
+                    continue;
+                };
+                if window.includes(slot) && filter.matches(&event) {
+                    window.record(slot, ts);
+                    println!("{}", event);
+                }
+            }
+        }
+        backfill = Some(window);
+    }
+
+    while let Some(message) = socket.next().await {
+        let text = match message.map_err(|e| e.to_string())? {
+            Message::Text(text) => text,
+            Message::Close(_) => break,
+            _ => continue,
+        };
+        let Some(event) = tail::live_event(&text) else {
+            continue;
+        };
+        if !filter.matches(&event) {
+            continue;
+        }
+        let (slot, ts) = (event["slot"].as_u64(), event["lamport_ts"].as_u64());
+        if let (Some(window), Some(slot), Some(ts)) = (backfill.as_mut(), slot, ts) {
+            if !window.is_new(slot, ts) {
+                continue;
+            }
+        }
+        println!("{}", event);
+    }
+    Err("Hub closed the WebSocket".into())
+}
 
-    // Check if implicit key exists in environment
-    // For MVP we assume NO AUTH in CLI or we need to pass headers.
-    // Ideally we load from ~/.cz/config or env CZ_API_KEY.
-    let api_key = std::env::var("CZ_API_KEY").ok();
+async fn async_main(cmd: Commands, format: OutputFormat, client_config: config::ClientConfig) {
+    let client = reqwest::Client::new();
+    let config::ClientConfig { base_url, api_key } = client_config;
 
     match cmd {
         Commands::Connectors { action } => match action {
@@ -346,9 +911,8 @@ async fn async_main(cmd: Commands) {
                 let url = format!("{}/api/connectors", base_url);
                 match get_request(&client, &url, api_key.as_deref()).await {
                     Ok(resp) => {
-                        // Parse and print table
                         if let Ok(json) = resp.json::<serde_json::Value>().await {
-                            println!("{}", serde_json::to_string_pretty(&json).unwrap());
+                            println!("{}", output::render(&json, format));
                         }
                     }
                     Err(e) => eprintln!("Error: {}", e),
@@ -396,61 +960,94 @@ async fn async_main(cmd: Commands) {
             }
         },
 
-        Commands::Query { query } => {
+        Commands::Query { query, explain, .. } => {
             let url = format!("{}/api/query", base_url);
-            let payload = serde_json::json!({ "query": query });
+            let payload = serde_json::json!({ "query": query, "explain": explain });
             match post_request(&client, &url, api_key.as_deref(), &payload).await {
                 Ok(resp) => {
                     if let Ok(json) = resp.json::<serde_json::Value>().await {
-                        // TODO: Pretty print table
-                        println!("{}", serde_json::to_string_pretty(&json).unwrap());
+                        println!("{}", output::render(&json, format));
                     }
                 }
                 Err(e) => eprintln!("Error: {}", e),
             }
         }
 
-        Commands::Tail { stream } => {
-            // Polling tail
-            let mut offset = 0;
-            let stream_id_filter = stream.parse::<u16>().ok();
-            loop {
-                let url = if let Some(stream_id) = stream_id_filter {
-                    format!(
-                        "{}/api/events?limit=100&offset={}&stream_id={}",
-                        base_url, offset, stream_id
-                    )
-                } else {
-                    format!("{}/api/events?limit=100&offset={}", base_url, offset)
-                };
+        Commands::Replay {
+            from,
+            to,
+            start,
+            end,
+            resequence,
+            ..
+        } => {
+            if let Err(e) = replay::validate_range(start, end) {
+                eprintln!("Replay failed: {}", e);
+                std::process::exit(1);
+            }
+            let url = format!("{}/api/replay", base_url);
+            let payload = serde_json::json!({
+                "journal": from,
+                "target_journal": to,
+                "start_slot": start,
+                "end_slot": end,
+                "resequence": resequence,
+            });
 
-                match get_request(&client, &url, api_key.as_deref()).await {
-                    Ok(resp) => {
-                        if let Ok(json) = resp.json::<serde_json::Value>().await {
-                            if let Some(events) = json.get("events").and_then(|e| e.as_array()) {
-                                if events.is_empty() {
-                                    tokio::time::sleep(tokio::time::Duration::from_millis(500))
-                                        .await;
-                                    continue;
-                                }
-                                for event in events {
-                                    println!("{}", serde_json::to_string(event).unwrap());
-                                }
-                                offset += events.len();
-                            }
-                        }
-                    }
-                    Err(_) => tokio::time::sleep(tokio::time::Duration::from_secs(1)).await,
+            // The hub answers once the whole range is written.
+            let spinner = indicatif::ProgressBar::new_spinner();
+            spinner.set_message(format!("Replaying slots {}..={}", start, end));
+            spinner.enable_steady_tick(std::time::Duration::from_millis(100));
+            let response = post_request(&client, &url, api_key.as_deref(), &payload).await;
+            spinner.finish_and_clear();
+
+            let result = match response {
+                Ok(resp) if resp.status().is_success() => resp
+                    .json::<replay::ReplaySummary>()
+                    .await
+                    .map_err(|e| e.to_string()),
+                Ok(resp) => {
+                    let status = resp.status();
+                    let body = resp.json::<serde_json::Value>().await.unwrap_or_default();
+                    Err(body["error"]
+                        .as_str()
+                        .map_or_else(|| status.to_string(), ToString::to_string))
+                }
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(summary) => print_replay_summary(&summary, format),
+                Err(e) => {
+                    eprintln!("Replay failed: {}", e);
+                    std::process::exit(1);
                 }
             }
         }
 
+        Commands::Tail {
+            stream,
+            since_slot,
+            journal,
+            poll,
+        } => {
+            let filter = tail::StreamFilter::new(&stream, journal);
+            let result = if poll {
+                poll_tail(&client, &base_url, api_key.as_deref(), &filter).await
+            } else {
+                live_tail(&client, &base_url, api_key.as_deref(), &filter, since_slot).await
+            };
+            if let Err(e) = result {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+
         Commands::Incidents => {
             let url = format!("{}/api/alerts/incidents", base_url);
             match get_request(&client, &url, api_key.as_deref()).await {
                 Ok(resp) => {
                     if let Ok(json) = resp.json::<serde_json::Value>().await {
-                        println!("{}", serde_json::to_string_pretty(&json).unwrap());
+                        println!("{}", output::render(&json, format));
                     }
                 }
                 Err(e) => eprintln!("Error: {}", e),
@@ -473,7 +1070,7 @@ async fn async_main(cmd: Commands) {
             match get_request(&client, &url, api_key.as_deref()).await {
                 Ok(resp) => {
                     if let Ok(json) = resp.json::<serde_json::Value>().await {
-                        println!("{}", serde_json::to_string_pretty(&json).unwrap());
+                        println!("{}", output::render(&json, format));
                     }
                 }
                 Err(e) => eprintln!("Error: {}", e),
@@ -484,6 +1081,122 @@ async fn async_main(cmd: Commands) {
     }
 }
 
+/// Run `text` against a journal file in place, with no hub involved.
+fn query_journal(
+    path: &std::path::Path,
+    text: &str,
+) -> Result<cz_hub::query::JournalQueryResult, String> {
+    let query = cz_hub::query::parser::parse(text).map_err(|e| e.to_string())?;
+    let journal = open_existing_journal(path)?;
+    cz_hub::query::executor::execute_journal(&query, &journal, &journal.live_cursor_view())
+}
+
+fn print_replay_summary(summary: &replay::ReplaySummary, format: OutputFormat) {
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(summary).unwrap()),
+        OutputFormat::Table | OutputFormat::Csv => println!("{}", summary),
+    }
+}
+
+/// Open (or create, with an Index Ring of `index_ring_size` bytes) the
+/// journal a sequencer writes, exiting with a readable message if it
+/// cannot be used.
+fn open_journal_or_exit(
+    path: &std::path::Path,
+    size: u64,
+    index_ring_size: usize,
+    partitions: usize,
+) -> Journal {
+    match Journal::open_partitioned(path, size, index_ring_size, partitions) {
+        Ok(journal) => journal,
+        Err(e) => {
+            eprintln!("Cannot open journal {}: {}", path.display(), e);
+            if let JournalError::TooSmall { required, .. } = e {
+                eprintln!(
+                    "   A journal needs at least {} MiB for its header and Index Ring.",
+                    required.div_ceil(1024 * 1024)
+                );
+            }
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Open a journal for inspection. The file must already exist; it is
+/// mapped at its current size and its cursor is the one the writer last
+/// published.
+/// `cz fsck` output: the tallies, then each failing slot.
+fn fsck_summary(report: &ScanReport) -> String {
+    let mut out = format!(
+        "Scanned {} events: {} ok, {} checksum mismatches, {} unreadable\n",
+        report.scanned, report.matched, report.mismatched.count, report.unreadable.count
+    );
+    for (kind, failures) in [
+        ("checksum mismatch", &report.mismatched),
+        ("unreadable", &report.unreadable),
+    ] {
+        for slot in &failures.slots {
+            out.push_str(&format!("  slot {}: {}\n", slot, kind));
+        }
+        let unlisted = failures.count - failures.slots.len() as u64;
+        if unlisted > 0 {
+            out.push_str(&format!("  ... and {} more {}\n", unlisted, kind));
+        }
+    }
+    out
+}
+
+fn open_existing_journal(path: &std::path::Path) -> Result<Journal, String> {
+    let size = std::fs::metadata(path)
+        .map_err(|e| format!("{}: {}", path.display(), e))?
+        .len();
+    Journal::open(path, size).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Restore the ring cursor from the journal's last valid checkpoint, or
+/// start fresh if there is none.
+/// Cursors to resume sequencing with, one per partition. A single ring
+/// recovers from its last checkpoint; partitions, which have none, resume
+/// from the cursors last published, with the Lamport clock past the newest
+/// event in any of them.
+fn recover_cursors(journal: &Journal) -> Vec<Cursor> {
+    if journal.partitions() == 1 {
+        return vec![recover_cursor(journal)];
+    }
+    let cursors: Vec<Cursor> = (0..journal.partitions())
+        .filter_map(|p| journal.partition_cursor_view(p))
+        .collect();
+    let newest = cursors
+        .iter()
+        .filter_map(Cursor::peek_head_slot)
+        .filter_map(|slot| journal.event_at(slot))
+        .map(|event| event.lamport_ts)
+        .max();
+    if let Some(ts) = newest {
+        eprintln!(
+            "   Resumed {} partitions (lamport={})",
+            cursors.len(),
+            ts + 1
+        );
+        cz_io::event_loop::restore_lamport(ts + 1);
+    }
+    cursors
+}
+
+fn recover_cursor(journal: &Journal) -> Cursor {
+    match journal.recover_checkpoint() {
+        Some((slot, checkpoint)) => {
+            eprintln!(
+                "   Recovered checkpoint at slot {} (head={}, tail={}, lamport={})",
+                slot, checkpoint.head, checkpoint.tail, checkpoint.lamport_watermark
+            );
+            cz_io::event_loop::restore_lamport(checkpoint.lamport_watermark);
+            Cursor::from_checkpoint(&checkpoint, journal.index_ring_capacity())
+        }
+        None => Cursor::new(journal.index_ring_capacity()),
+    }
+}
+
 async fn get_request(
     client: &reqwest::Client,
     url: &str,
@@ -0,0 +1,159 @@
+//! # Output Formatting
+//!
+//! Renders API responses for the terminal. List-shaped responses become a
+//! table (or CSV) with one column per field; anything else is printed as
+//! pretty JSON.
+
+use clap::ValueEnum;
+use serde_json::{Map, Value};
+use tabled::builder::Builder;
+use tabled::settings::Style;
+
+/// How command output is printed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Json,
+    Csv,
+}
+
+/// Render `value` in `format`, falling back to JSON if it is not tabular.
+pub fn render(value: &Value, format: OutputFormat) -> String {
+    let rows = match format {
+        OutputFormat::Json => None,
+        OutputFormat::Table | OutputFormat::Csv => rows(value),
+    };
+    let Some(rows) = rows else {
+        return serde_json::to_string_pretty(value).unwrap();
+    };
+
+    let columns = columns(&rows);
+    let cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| columns.iter().map(|c| cell(row.get(c))).collect())
+        .collect();
+
+    if format == OutputFormat::Csv {
+        let mut out = csv_line(&columns);
+        for record in cells {
+            out.push('\n');
+            out.push_str(&csv_line(&record));
+        }
+        return out;
+    }
+
+    let mut builder = Builder::new();
+    builder.push_record(columns);
+    for record in cells {
+        builder.push_record(record);
+    }
+    builder.build().with(Style::sharp()).to_string()
+}
+
+/// The records to tabulate: the value itself if it is a list of objects,
+/// or else the one field of an object that is.
+fn rows(value: &Value) -> Option<Vec<&Map<String, Value>>> {
+    fn as_rows(v: &Value) -> Option<Vec<&Map<String, Value>>> {
+        v.as_array()?.iter().map(Value::as_object).collect()
+    }
+    match value {
+        Value::Array(_) => as_rows(value),
+        Value::Object(map) => {
+            let mut lists = map
+                .values()
+                .filter_map(|v| as_rows(v).filter(|r| !r.is_empty()));
+            let only = lists.next()?;
+            lists.next().is_none().then_some(only)
+        }
+        _ => None,
+    }
+}
+
+/// Column names in order of first appearance across `rows` (keys within
+/// one object come out sorted).
+fn columns(rows: &[&Map<String, Value>]) -> Vec<String> {
+    let mut columns: Vec<String> = Vec::new();
+    for key in rows.iter().flat_map(|row| row.keys()) {
+        if !columns.contains(key) {
+            columns.push(key.clone());
+        }
+    }
+    columns
+}
+
+fn cell(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.clone(),
+        Some(other) => other.to_string(),
+    }
+}
+
+fn csv_line(fields: &[String]) -> String {
+    fields
+        .iter()
+        .map(|f| {
+            if f.contains([',', '"', '\n']) {
+                format!("\"{}\"", f.replace('"', "\"\""))
+            } else {
+                f.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sample() -> Value {
+        json!([
+            {"id": "c1", "kind": "kafka", "events": 10},
+            {"id": "c2", "kind": "webhook", "events": 3, "error": "timeout, retrying"},
+        ])
+    }
+
+    #[test]
+    fn test_table_has_a_column_per_field() {
+        let table = render(&sample(), OutputFormat::Table);
+        let lines: Vec<&str> = table.lines().collect();
+        assert!(lines[1].contains("id") && lines[1].contains("error"));
+        let row = lines.iter().find(|l| l.contains("c2")).unwrap();
+        assert!(row.contains("webhook") && row.contains("timeout, retrying"));
+        // The first row has no `error` field and renders an empty cell.
+        let first = lines.iter().find(|l| l.contains("c1")).unwrap();
+        assert_eq!(first.matches('│').count(), 5);
+    }
+
+    #[test]
+    fn test_table_unwraps_the_list_field_of_an_object() {
+        let response = json!({"events": sample(), "total": 2, "streams_searched": ["a"]});
+        let table = render(&response, OutputFormat::Table);
+        assert!(table.contains("kafka") && !table.contains("total"));
+    }
+
+    #[test]
+    fn test_csv_quotes_fields() {
+        let csv = render(&sample(), OutputFormat::Csv);
+        assert_eq!(
+            csv,
+            "events,id,kind,error\n10,c1,kafka,\n3,c2,webhook,\"timeout, retrying\""
+        );
+    }
+
+    #[test]
+    fn test_non_tabular_falls_back_to_json() {
+        let value = json!({"status": "ok", "count": 1});
+        assert_eq!(
+            render(&value, OutputFormat::Table),
+            serde_json::to_string_pretty(&value).unwrap()
+        );
+        assert_eq!(
+            render(&sample(), OutputFormat::Json),
+            serde_json::to_string_pretty(&sample()).unwrap()
+        );
+    }
+}
@@ -0,0 +1,182 @@
+//! # Replay
+//!
+//! `cz replay` copies a slot range from one journal onto the head of
+//! another: through the hub's `/api/replay`, or with `--local` straight
+//! between two journal files using the same code the hub runs. A local
+//! replay writes the target's published cursor, so no sequencer may be
+//! running on the target meanwhile.
+
+use std::path::Path;
+
+use cz_hub::replay;
+use cz_io::journal::Journal;
+use serde::{Deserialize, Serialize};
+
+/// Final result of a replay, as printed and as `/api/replay` returns it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplaySummary {
+    /// `preserved` or `resequenced`.
+    pub mode: String,
+    pub events_replayed: usize,
+    pub new_head: usize,
+    pub skipped: usize,
+    pub resequenced: usize,
+}
+
+impl std::fmt::Display for ReplaySummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Replayed {} events, timestamps {} (skipped {}, resequenced {}); new head {}",
+            self.events_replayed, self.mode, self.skipped, self.resequenced, self.new_head
+        )
+    }
+}
+
+/// Reject a range the hub would refuse, before anything is sent.
+pub fn validate_range(start: usize, end: usize) -> Result<(), String> {
+    if start > end {
+        return Err(format!(
+            "--start ({}) must not be after --end ({})",
+            start, end
+        ));
+    }
+    Ok(())
+}
+
+/// Replay slots `start..=end` of the journal at `from` onto the journal at
+/// `to`, creating `to` with the sizes of `from` if it does not exist.
+/// `progress` is called with the events written so far and the total.
+pub fn replay_local(
+    from: &Path,
+    to: &Path,
+    start: usize,
+    end: usize,
+    resequence: bool,
+    mut progress: impl FnMut(usize, usize),
+) -> Result<ReplaySummary, String> {
+    validate_range(start, end)?;
+    if from == to {
+        return Err("--from and --to must be different journals".into());
+    }
+
+    let source = crate::open_existing_journal(from)?;
+    let mut target = if to.exists() {
+        crate::open_existing_journal(to)?
+    } else {
+        Journal::open_with_ring_size(to, source.size(), source.index_ring_size())
+            .map_err(|e| format!("{}: {}", to.display(), e))?
+    };
+
+    let slots = replay::slot_range(start, end, source.live_cursor_view().capacity())
+        .map_err(|e| e.to_string())?;
+    let mut cursor = target.live_cursor_view();
+    let floor = replay::lamport_floor(&target, &cursor);
+    let batch = replay::prepare(&source, slots, cursor.available(), floor, resequence)
+        .map_err(|e| e.to_string())?;
+
+    let total = batch.events.len();
+    let reservation = cursor
+        .reserve(total)
+        .expect("reservation sized to available slots");
+    let new_head = replay::write(&mut target, reservation, &batch.events, |written| {
+        progress(written, total)
+    });
+    target.publish_cursor(&cursor);
+    target
+        .flush()
+        .map_err(|e| format!("{}: {}", to.display(), e))?;
+
+    Ok(ReplaySummary {
+        mode: batch.mode.as_str().to_string(),
+        events_replayed: total,
+        new_head,
+        skipped: batch.skipped(),
+        resequenced: batch.resequenced,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cz_core::CausalEvent;
+    use cz_io::cursor::Cursor;
+    use cz_io::journal::MIN_JOURNAL_SIZE;
+
+    fn journal_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("cz-replay-{}-{}.db", name, std::process::id()))
+    }
+
+    /// A journal holding events with these timestamps in slots 0, 1, ...
+    fn journal_with(name: &str, timestamps: &[u64]) -> std::path::PathBuf {
+        let path = journal_path(name);
+        let size = MIN_JOURNAL_SIZE + 4096;
+        let mut journal = Journal::open(&path, size).unwrap();
+        let mut cursor = Cursor::new(journal.index_ring_capacity());
+        for &ts in timestamps {
+            let slot = cursor.advance_head().unwrap();
+            unsafe { journal.write_event_at(slot, &CausalEvent::new(ts, 1, 2, 0, 0xC0FFEE)) };
+        }
+        journal.publish_cursor(&cursor);
+        path
+    }
+
+    fn timestamps(path: &Path) -> Vec<u64> {
+        let journal = crate::open_existing_journal(path).unwrap();
+        let cursor = journal.live_cursor_view();
+        cursor
+            .slots()
+            .map(|slot| journal.event_at(slot).unwrap().lamport_ts)
+            .collect()
+    }
+
+    #[test]
+    fn test_validate_range() {
+        assert!(validate_range(0, 0).is_ok());
+        assert!(validate_range(3, 9).is_ok());
+        let err = validate_range(9, 3).unwrap_err();
+        assert!(err.contains("--start (9)"), "{}", err);
+    }
+
+    #[test]
+    fn test_local_replay_between_temp_journals() {
+        let source = journal_with("source", &[10, 11, 12, 13, 14]);
+        let target = journal_path("target");
+        std::fs::remove_file(&target).ok();
+
+        // A fresh target is created; slot 6 is empty and is skipped.
+        let mut calls = Vec::new();
+        let summary = replay_local(&source, &target, 1, 6, false, |done, total| {
+            calls.push((done, total))
+        })
+        .unwrap();
+        assert_eq!(
+            summary,
+            ReplaySummary {
+                mode: "preserved".into(),
+                events_replayed: 4,
+                new_head: 4,
+                skipped: 2,
+                resequenced: 0,
+            }
+        );
+        assert_eq!(calls, [(1, 4), (2, 4), (3, 4), (4, 4)]);
+        assert_eq!(timestamps(&target), [11, 12, 13, 14]);
+
+        // Replaying older events again would go backward in the target.
+        let err = replay_local(&source, &target, 0, 1, false, |_, _| {}).unwrap_err();
+        assert!(err.contains("resequence"), "{}", err);
+        assert_eq!(timestamps(&target), [11, 12, 13, 14]);
+
+        let summary = replay_local(&source, &target, 0, 1, true, |_, _| {}).unwrap();
+        assert_eq!(summary.mode, "resequenced");
+        assert_eq!((summary.events_replayed, summary.resequenced), (2, 2));
+        assert_eq!(timestamps(&target), [11, 12, 13, 14, 15, 16]);
+
+        assert!(replay_local(&source, &target, 2, 1, false, |_, _| {}).is_err());
+        assert!(replay_local(&source, &source, 0, 1, false, |_, _| {}).is_err());
+
+        std::fs::remove_file(&source).ok();
+        std::fs::remove_file(&target).ok();
+    }
+}
@@ -0,0 +1,196 @@
+//! # Live Tail
+//!
+//! `cz tail` subscribes to the `events` channel of the hub's `/ws` feed and
+//! prints each committed event as it arrives. With `--since-slot` it first
+//! backfills the ring from that slot up to the head over `/api/events`,
+//! then switches to the live feed without printing an event twice.
+//! `--poll` keeps the old behaviour of paging `/api/events` for hubs the
+//! client cannot reach over a WebSocket.
+
+use std::collections::HashSet;
+
+use serde_json::Value;
+
+/// Which events a tail prints.
+#[derive(Debug, Clone, Default)]
+pub struct StreamFilter {
+    /// `None` follows every stream.
+    pub stream_id: Option<u16>,
+    /// `None` follows every journal the hub serves.
+    pub journal: Option<String>,
+}
+
+impl StreamFilter {
+    /// Parse the `stream` argument: a stream id, or anything else (such as
+    /// `all` or `*`) for every stream.
+    pub fn new(stream: &str, journal: Option<String>) -> Self {
+        Self {
+            stream_id: stream.parse().ok(),
+            journal,
+        }
+    }
+
+    /// Whether `event` (a live `data` object or an `/api/events` record)
+    /// passes the filter. A live event the hub could not read back carries
+    /// no `stream_id` and never matches a stream filter; records without a
+    /// `journal` field come from the journal that was asked for.
+    pub fn matches(&self, event: &Value) -> bool {
+        let stream_ok = self
+            .stream_id
+            .is_none_or(|id| event["stream_id"].as_u64() == Some(id as u64));
+        let journal_ok = match (&self.journal, event["journal"].as_str()) {
+            (Some(want), Some(got)) => want == got,
+            _ => true,
+        };
+        stream_ok && journal_ok
+    }
+}
+
+/// The backfill window and the events printed from it.
+///
+/// Slots are compared by their position in the ring counted from the tail
+/// seen when the tail started, so a window that wraps past the end of the
+/// ring still runs from `since_slot` to the head.
+#[derive(Debug)]
+pub struct Backfill {
+    capacity: u64,
+    tail: u64,
+    since: u64,
+    printed: HashSet<(u64, u64)>,
+}
+
+impl Backfill {
+    /// Backfill from `since_slot` in a ring of `capacity` slots whose tail
+    /// is at `tail`.
+    pub fn new(since_slot: u64, tail: u64, capacity: u64) -> Self {
+        let mut backfill = Self {
+            capacity: capacity.max(1),
+            tail,
+            since: 0,
+            printed: HashSet::new(),
+        };
+        backfill.since = backfill.position(since_slot);
+        backfill
+    }
+
+    /// Distance of `slot` from the tail, in ring order.
+    fn position(&self, slot: u64) -> u64 {
+        (slot % self.capacity + self.capacity - self.tail % self.capacity) % self.capacity
+    }
+
+    /// Whether the ring record in `slot` belongs to the backfill.
+    pub fn includes(&self, slot: u64) -> bool {
+        self.position(slot) >= self.since
+    }
+
+    /// Remember a backfilled event so the live feed does not repeat it.
+    pub fn record(&mut self, slot: u64, lamport_ts: u64) {
+        self.printed.insert((slot, lamport_ts));
+    }
+
+    /// Whether a live event still has to be printed: the feed is opened
+    /// before the backfill starts, so its first events may already have
+    /// been read from the ring.
+    pub fn is_new(&mut self, slot: u64, lamport_ts: u64) -> bool {
+        !self.printed.remove(&(slot, lamport_ts))
+    }
+}
+
+/// `/ws` on the hub at `base_url`.
+pub fn ws_url(base_url: &str) -> String {
+    let rest = base_url
+        .strip_prefix("https://")
+        .map(|rest| format!("wss://{}", rest))
+        .or_else(|| {
+            base_url
+                .strip_prefix("http://")
+                .map(|rest| format!("ws://{}", rest))
+        })
+        .unwrap_or_else(|| base_url.to_string());
+    format!("{}/ws", rest)
+}
+
+/// Control message asking the hub for the `events` channel only, limited
+/// to the filter's journal if it has one.
+pub fn subscribe_message(filter: &StreamFilter) -> String {
+    serde_json::json!({"subscribe": ["events"], "journal": filter.journal}).to_string()
+}
+
+/// The event in a WS message, if it is an event notification.
+pub fn live_event(message: &str) -> Option<Value> {
+    let mut value: Value = serde_json::from_str(message).ok()?;
+    if value["type"] != "event" {
+        return None;
+    }
+    Some(value["data"].take())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_stream_filter() {
+        let live =
+            json!({"journal": "a.db", "slot": 4, "lamport_ts": 9, "node_id": 1, "stream_id": 3});
+        let unread = json!({"journal": "a.db", "slot": 5, "lamport_ts": 10});
+        let record = json!({"slot": 4, "lamport_ts": 9, "node_id": 1, "stream_id": 3});
+
+        let all = StreamFilter::new("all", None);
+        assert!(all.matches(&live) && all.matches(&unread) && all.matches(&record));
+
+        let three = StreamFilter::new("3", None);
+        assert!(three.matches(&live) && three.matches(&record));
+        assert!(!three.matches(&unread));
+        assert!(!StreamFilter::new("4", None).matches(&live));
+
+        let other_journal = StreamFilter::new("3", Some("b.db".into()));
+        assert!(!other_journal.matches(&live));
+        assert!(other_journal.matches(&record));
+        assert!(StreamFilter::new("3", Some("a.db".into())).matches(&live));
+    }
+
+    #[test]
+    fn test_backfill_boundary_and_handoff() {
+        // Tail at 6 in an 8-slot ring: ring order is 6, 7, 0, 1, ...
+        let mut backfill = Backfill::new(7, 6, 8);
+        assert!(!backfill.includes(6));
+        assert!(backfill.includes(7));
+        assert!(backfill.includes(0) && backfill.includes(2));
+
+        // Without wrapping, the boundary slot itself is included.
+        let unwrapped = Backfill::new(3, 0, 8);
+        assert!(!unwrapped.includes(2));
+        assert!(unwrapped.includes(3) && unwrapped.includes(7));
+
+        // Events seen in the backfill are dropped from the live feed once;
+        // a later write to the same slot is new.
+        backfill.record(0, 41);
+        assert!(!backfill.is_new(0, 41));
+        assert!(backfill.is_new(0, 41));
+        assert!(backfill.is_new(1, 42));
+    }
+
+    #[test]
+    fn test_ws_messages() {
+        assert_eq!(ws_url("http://127.0.0.1:3000"), "ws://127.0.0.1:3000/ws");
+        assert_eq!(
+            ws_url("https://hub.example.com"),
+            "wss://hub.example.com/ws"
+        );
+
+        let event = live_event(r#"{"type":"event","data":{"slot":1,"lamport_ts":2}}"#);
+        assert_eq!(event, Some(json!({"slot": 1, "lamport_ts": 2})));
+        assert_eq!(live_event(r#"{"type":"metrics","data":{}}"#), None);
+        assert_eq!(live_event("not json"), None);
+        assert_eq!(
+            live_event(r#"{"type":"subscribed","channels":["events"]}"#),
+            None
+        );
+
+        let filter = StreamFilter::new("all", Some("a.db".into()));
+        let request: Value = serde_json::from_str(&subscribe_message(&filter)).unwrap();
+        assert_eq!(request, json!({"subscribe": ["events"], "journal": "a.db"}));
+    }
+}
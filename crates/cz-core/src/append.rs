@@ -0,0 +1,163 @@
+//! # Ordered Appends
+//!
+//! An Index Ring is sorted by `lamport_ts` from tail to head, and every
+//! reader (the `ts_min` seek, checkpoints, the causal ordering proofs)
+//! relies on it. Events appended from outside the sequencer, such as a
+//! replay of another journal's slots, have to keep it that way. This
+//! module stamps such a batch before it is written:
+//!
+//! - [`AppendMode::Preserve`] keeps the events' own timestamps and refuses
+//!   the batch if they would go backward, either within the batch or
+//!   relative to the newest event already in the ring.
+//! - [`AppendMode::Resequence`] gives every event a fresh timestamp after
+//!   the ring's newest one, in batch order, and sets
+//!   [`FLAG_RESEQUENCED`] on each event whose timestamp changed. A slot
+//!   has no room for a second timestamp, so the flag is what tells
+//!   readers the event no longer carries the one it was committed with.
+
+use crate::{CausalEvent, FLAG_RESEQUENCED};
+
+/// How a batch appended to a ring is stamped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppendMode {
+    Preserve,
+    Resequence,
+}
+
+impl AppendMode {
+    /// Name of the mode as reported by the replay API.
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            AppendMode::Preserve => "preserved",
+            AppendMode::Resequence => "resequenced",
+        }
+    }
+}
+
+/// A preserved batch would put `lamport_ts` after `after`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutOfOrder {
+    pub lamport_ts: u64,
+    pub after: u64,
+}
+
+/// Stamp `events` for appending, in order, to a ring whose newest event
+/// has timestamp `floor` (`None` for an empty ring). On success the
+/// sequence `floor, events[0].lamport_ts, events[1].lamport_ts, ...` is
+/// non-decreasing; returns how many events got a new timestamp. A refused
+/// batch is left untouched.
+pub fn stamp_for_append(
+    events: &mut [CausalEvent],
+    floor: Option<u64>,
+    mode: AppendMode,
+) -> Result<usize, OutOfOrder> {
+    match mode {
+        AppendMode::Preserve => {
+            let mut previous = floor;
+            for event in events.iter() {
+                if let Some(after) = previous.filter(|&ts| event.lamport_ts < ts) {
+                    return Err(OutOfOrder {
+                        lamport_ts: event.lamport_ts,
+                        after,
+                    });
+                }
+                previous = Some(event.lamport_ts);
+            }
+            Ok(0)
+        }
+        AppendMode::Resequence => {
+            // Strictly after the floor where the timestamp space allows;
+            // at `u64::MAX` the batch shares the floor's timestamp, which
+            // still keeps the ring non-decreasing.
+            let mut next = floor.map_or(0, |ts| ts.saturating_add(1));
+            let mut restamped = 0;
+            for event in events.iter_mut() {
+                if event.lamport_ts != next {
+                    event.lamport_ts = next;
+                    event.flags |= FLAG_RESEQUENCED;
+                    restamped += 1;
+                }
+                next = next.saturating_add(1);
+            }
+            Ok(restamped)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn batch(timestamps: [u64; 3]) -> [CausalEvent; 3] {
+        timestamps.map(|ts| CausalEvent::new(ts, 1, 1, 0, 0))
+    }
+
+    fn timestamps(events: &[CausalEvent]) -> [u64; 3] {
+        [
+            events[0].lamport_ts,
+            events[1].lamport_ts,
+            events[2].lamport_ts,
+        ]
+    }
+
+    #[test]
+    fn test_preserve_accepts_ordered_batches_only() {
+        let mut events = batch([5, 5, 9]);
+        assert_eq!(
+            stamp_for_append(&mut events, Some(5), AppendMode::Preserve),
+            Ok(0)
+        );
+        assert_eq!(
+            stamp_for_append(&mut events, None, AppendMode::Preserve),
+            Ok(0)
+        );
+        assert_eq!(timestamps(&events), [5, 5, 9]);
+
+        let refused = stamp_for_append(&mut events, Some(6), AppendMode::Preserve);
+        assert_eq!(
+            refused,
+            Err(OutOfOrder {
+                lamport_ts: 5,
+                after: 6
+            })
+        );
+
+        let mut events = batch([3, 8, 7]);
+        let refused = stamp_for_append(&mut events, None, AppendMode::Preserve);
+        assert_eq!(
+            refused,
+            Err(OutOfOrder {
+                lamport_ts: 7,
+                after: 8
+            })
+        );
+        assert_eq!(timestamps(&events), [3, 8, 7]);
+    }
+
+    #[test]
+    fn test_resequence_stamps_after_the_floor() {
+        let mut events = batch([3, 9, 7]);
+        assert_eq!(
+            stamp_for_append(&mut events, Some(7), AppendMode::Resequence),
+            Ok(2)
+        );
+        assert_eq!(timestamps(&events), [8, 9, 10]);
+        let flagged = events.map(|e| e.is_resequenced());
+        assert_eq!(flagged, [true, false, true]);
+
+        let mut events = batch([0, 1, 2]);
+        assert_eq!(
+            stamp_for_append(&mut events, None, AppendMode::Resequence),
+            Ok(0)
+        );
+        assert!(events.iter().all(|e| !e.is_resequenced()));
+
+        let mut events = batch([0, 0, 0]);
+        let floor = Some(u64::MAX - 1);
+        assert_eq!(
+            stamp_for_append(&mut events, floor, AppendMode::Resequence),
+            Ok(3)
+        );
+        assert_eq!(timestamps(&events), [u64::MAX; 3]);
+    }
+}
@@ -0,0 +1,215 @@
+//! # Payload Compression
+//!
+//! A sender may compress an event's payload and set [`FLAG_COMPRESSED`] in
+//! its header. The compressed payload is a standard LZ4 block (encoded by
+//! `lz4_flex`) behind its decompressed length, the layout of
+//! `lz4_flex::compress_prepend_size`:
+//!
+//! | Offset | Size | Field                          |
+//! |--------|------|--------------------------------|
+//! | 0      | 4    | decompressed length (LE)       |
+//! | 4      | n    | LZ4 block, to the end          |
+//!
+//! The frame is the payload on the wire, so the event checksum covers the
+//! compressed bytes, exactly as the sequencer verifies them. The length
+//! tells a reader how large a buffer to decompress into.
+//!
+//! Everything works on caller-provided slices: no allocation.
+//!
+//! [`FLAG_COMPRESSED`]: crate::FLAG_COMPRESSED
+
+use lz4_flex::block;
+
+/// Size of the length in front of the LZ4 block.
+pub const FRAME_HEADER_SIZE: usize = 4;
+
+/// Why a frame could not be decompressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecompressError {
+    /// The frame or its block ends early.
+    Truncated,
+    /// A match refers before the start of the output.
+    BadOffset,
+    /// The output buffer is smaller than the decompressed length.
+    OutputTooSmall,
+    /// The block does not decode to the length the header records.
+    LengthMismatch,
+}
+
+impl core::fmt::Display for DecompressError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let reason = match self {
+            DecompressError::Truncated => "compressed payload is truncated",
+            DecompressError::BadOffset => "compressed payload refers outside its output",
+            DecompressError::OutputTooSmall => "output buffer is too small for the payload",
+            DecompressError::LengthMismatch => "compressed payload does not match its length",
+        };
+        f.write_str(reason)
+    }
+}
+
+impl From<block::DecompressError> for DecompressError {
+    fn from(e: block::DecompressError) -> Self {
+        match e {
+            block::DecompressError::ExpectedAnotherByte
+            | block::DecompressError::LiteralOutOfBounds => DecompressError::Truncated,
+            block::DecompressError::OffsetZero | block::DecompressError::OffsetOutOfBounds => {
+                DecompressError::BadOffset
+            }
+            // The output is exactly the recorded length, so running out of
+            // it means the block decodes to more.
+            _ => DecompressError::LengthMismatch,
+        }
+    }
+}
+
+/// The length recorded in a frame header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameHeader {
+    pub decompressed_len: usize,
+}
+
+/// Largest frame [`compress`] can produce for `len` input bytes.
+pub const fn max_compressed_len(len: usize) -> usize {
+    FRAME_HEADER_SIZE + block::get_maximum_output_size(len)
+}
+
+/// Read the header at the start of `frame`.
+pub fn read_header(frame: &[u8]) -> Option<FrameHeader> {
+    let header = frame.get(..FRAME_HEADER_SIZE)?;
+    Some(FrameHeader {
+        decompressed_len: u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize,
+    })
+}
+
+/// Compress `src` into a frame at the start of `dst`, returning the frame
+/// length, or `None` if `dst` is too small (at most
+/// [`max_compressed_len`] bytes are needed) or `src` exceeds 4 GiB.
+pub fn compress(src: &[u8], dst: &mut [u8]) -> Option<usize> {
+    let decompressed_len = u32::try_from(src.len()).ok()?;
+    let len = block::compress_into(src, dst.get_mut(FRAME_HEADER_SIZE..)?).ok()?;
+    dst[..FRAME_HEADER_SIZE].copy_from_slice(&decompressed_len.to_le_bytes());
+    Some(FRAME_HEADER_SIZE + len)
+}
+
+/// Decompress `frame`, which runs to the end of the slice, into `dst`,
+/// returning the decompressed length.
+pub fn decompress(frame: &[u8], dst: &mut [u8]) -> Result<usize, DecompressError> {
+    let header = read_header(frame).ok_or(DecompressError::Truncated)?;
+    let out = dst
+        .get_mut(..header.decompressed_len)
+        .ok_or(DecompressError::OutputTooSmall)?;
+    match block::decompress_into(&frame[FRAME_HEADER_SIZE..], out)? {
+        len if len == header.decompressed_len => Ok(len),
+        _ => Err(DecompressError::LengthMismatch),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(src: &[u8]) -> usize {
+        let mut frame = [0u8; 4096];
+        let len = compress(src, &mut frame).unwrap();
+        assert!(len <= max_compressed_len(src.len()));
+        let header = read_header(&frame).unwrap();
+        assert_eq!(header.decompressed_len, src.len());
+
+        let mut out = [0u8; 2048];
+        assert_eq!(decompress(&frame[..len], &mut out), Ok(src.len()));
+        assert_eq!(&out[..src.len()], src);
+        len
+    }
+
+    #[test]
+    fn test_round_trips_payloads() {
+        let json = br#"{"level":"info","msg":"request served","status":200},{"level":"info","msg":"request served","status":200},{"level":"info","msg":"request served","status":404}"#;
+        assert!(round_trip(json) < json.len(), "repetitive JSON shrinks");
+        assert!(round_trip(&[b'a'; 1000]) < 32);
+
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let noise: [u8; 700] = core::array::from_fn(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        });
+        round_trip(&noise);
+        for len in [0, 1, 5, 12, 13, 17] {
+            round_trip(&json[..len]);
+        }
+    }
+
+    /// Blocks assembled by hand from the LZ4 block format, so the decoder
+    /// is held to the format rather than to our own encoder.
+    #[test]
+    fn test_decodes_reference_blocks() {
+        let mut out = [0u8; 32];
+        // "abc", then a 9-byte match at offset 3 that overlaps the bytes
+        // it produces, then the closing literals.
+        let frame = [
+            17, 0, 0, 0, 0x35, b'a', b'b', b'c', 3, 0, 0x50, b'v', b'w', b'x', b'y', b'z',
+        ];
+        assert_eq!(decompress(&frame, &mut out), Ok(17));
+        assert_eq!(&out[..17], b"abcabcabcabcvwxyz");
+
+        // 15 + 3 literals: the token's 15 is extended by the next byte.
+        let mut long = [0u8; 4 + 2 + 18];
+        long[..4].copy_from_slice(&18u32.to_le_bytes());
+        long[4] = 0xF0;
+        long[5] = 3;
+        long[6..].copy_from_slice(b"eighteen literals!");
+        assert_eq!(decompress(&long, &mut out), Ok(18));
+        assert_eq!(&out[..18], b"eighteen literals!");
+    }
+
+    #[test]
+    fn test_rejects_malformed_frames() {
+        let src = [b'z'; 100];
+        let mut frame = [0u8; max_compressed_len(100)];
+        let len = compress(&src, &mut frame).unwrap();
+        let mut out = [0u8; 100];
+
+        assert_eq!(
+            decompress(&frame[..len - 1], &mut out),
+            Err(DecompressError::Truncated)
+        );
+        assert_eq!(
+            decompress(&frame[..3], &mut out),
+            Err(DecompressError::Truncated)
+        );
+        assert_eq!(
+            decompress(&frame[..len], &mut out[..99]),
+            Err(DecompressError::OutputTooSmall)
+        );
+
+        // Claim one byte fewer, or more, than the block produces.
+        let mut short = frame;
+        short[..4].copy_from_slice(&99u32.to_le_bytes());
+        assert_eq!(
+            decompress(&short[..len], &mut out),
+            Err(DecompressError::LengthMismatch)
+        );
+        let mut long = frame;
+        long[..4].copy_from_slice(&101u32.to_le_bytes());
+        let mut roomy = [0u8; 101];
+        assert_eq!(
+            decompress(&long[..len], &mut roomy),
+            Err(DecompressError::LengthMismatch)
+        );
+
+        // A match reaching back past everything written so far, and one
+        // with offset 0.
+        for offset in [2u8, 0] {
+            let bad = [5, 0, 0, 0, 0x10, b'a', offset, 0, 0x00];
+            assert_eq!(decompress(&bad, &mut out), Err(DecompressError::BadOffset));
+        }
+
+        // A literal run longer than the block.
+        let cut = [5, 0, 0, 0, 0x50, b'a', b'b'];
+        assert_eq!(decompress(&cut, &mut out), Err(DecompressError::Truncated));
+
+        assert!(compress(&src, &mut frame[..10]).is_none());
+    }
+}
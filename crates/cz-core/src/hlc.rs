@@ -0,0 +1,169 @@
+//! # Hybrid Logical Clock
+//!
+//! A Lamport clock that stays close to wall time. Each timestamp packs
+//! 48 bits of Unix milliseconds above a 16-bit logical counter, so packed
+//! timestamps compare as plain `u64`s and a counter overflow simply carries
+//! into the next millisecond.
+//!
+//! The clock guarantees two things for every timestamp it hands out:
+//! it is strictly greater than the previous one, and its millisecond part
+//! is never more than `max_drift_ms` ahead of the wall clock reading it was
+//! given — even when that wall clock steps backwards. When both cannot
+//! hold, the clock refuses and leaves its state untouched.
+
+/// Bits of the packed timestamp holding the logical counter.
+pub const COUNTER_BITS: u32 = 16;
+
+/// Largest wall-clock millisecond value a timestamp can carry.
+pub const MAX_MILLIS: u64 = (1 << (64 - COUNTER_BITS)) - 1;
+
+/// Pack `millis` (truncated to 48 bits) and `counter` into one timestamp.
+#[inline]
+pub const fn pack(millis: u64, counter: u16) -> u64 {
+    ((millis & MAX_MILLIS) << COUNTER_BITS) | counter as u64
+}
+
+/// Millisecond part of a packed timestamp.
+#[inline]
+pub const fn millis(ts: u64) -> u64 {
+    ts >> COUNTER_BITS
+}
+
+/// Logical counter part of a packed timestamp.
+#[inline]
+pub const fn counter(ts: u64) -> u16 {
+    ts as u16
+}
+
+/// Why the clock refused to produce a timestamp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HlcError {
+    /// The next timestamp would run `ahead_ms` past the allowed drift.
+    DriftExceeded { ahead_ms: u64 },
+    /// The 64-bit timestamp space is used up.
+    Exhausted,
+}
+
+/// A hybrid logical clock with bounded drift from wall time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HlcClock {
+    last: u64,
+    max_drift_ms: u64,
+}
+
+impl HlcClock {
+    /// A fresh clock that has not issued any timestamp yet.
+    pub const fn new(max_drift_ms: u64) -> Self {
+        Self::resume(0, max_drift_ms)
+    }
+
+    /// A clock continuing after `last`, e.g. recovered from a journal.
+    pub const fn resume(last: u64, max_drift_ms: u64) -> Self {
+        Self { last, max_drift_ms }
+    }
+
+    /// The most recent timestamp issued (0 if none).
+    #[inline]
+    pub const fn last(&self) -> u64 {
+        self.last
+    }
+
+    /// Timestamp a local or outgoing event at wall time `wall_ms`.
+    pub fn tick(&mut self, wall_ms: u64) -> Result<u64, HlcError> {
+        self.advance(self.last, wall_ms)
+    }
+
+    /// Merge a timestamp received from another node at wall time
+    /// `wall_ms`, returning one that is after both it and everything this
+    /// clock issued before.
+    pub fn observe(&mut self, remote: u64, wall_ms: u64) -> Result<u64, HlcError> {
+        self.advance(self.last.max(remote), wall_ms)
+    }
+
+    /// Issue the smallest timestamp above `floor` that is not behind wall
+    /// time, if it stays within the drift bound.
+    fn advance(&mut self, floor: u64, wall_ms: u64) -> Result<u64, HlcError> {
+        let after_floor = floor.checked_add(1).ok_or(HlcError::Exhausted)?;
+        let next = after_floor.max(pack(wall_ms.min(MAX_MILLIS), 0));
+        let limit = wall_ms.saturating_add(self.max_drift_ms);
+        if millis(next) > limit {
+            return Err(HlcError::DriftExceeded {
+                ahead_ms: millis(next) - wall_ms,
+            });
+        }
+        self.last = next;
+        Ok(next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_packing_round_trips_edges() {
+        for (ms, c) in [
+            (0, 0),
+            (1, u16::MAX),
+            (MAX_MILLIS, 0),
+            (MAX_MILLIS, u16::MAX),
+        ] {
+            let ts = pack(ms, c);
+            assert_eq!((millis(ts), counter(ts)), (ms, c));
+        }
+        assert_eq!(pack(MAX_MILLIS, u16::MAX), u64::MAX);
+        assert!(pack(5, u16::MAX) < pack(6, 0));
+    }
+
+    #[test]
+    fn test_tick_follows_wall_time_then_counts() {
+        let mut clock = HlcClock::new(100);
+        assert_eq!(clock.tick(1_000), Ok(pack(1_000, 0)));
+        assert_eq!(clock.tick(1_000), Ok(pack(1_000, 1)));
+        assert_eq!(clock.tick(1_001), Ok(pack(1_001, 0)));
+        // Wall clock stepped back: keep counting from the last timestamp.
+        assert_eq!(clock.tick(990), Ok(pack(1_001, 1)));
+    }
+
+    #[test]
+    fn test_counter_overflow_rolls_into_millis() {
+        let mut clock = HlcClock::resume(pack(1_000, u16::MAX), 5);
+        assert_eq!(clock.tick(1_000), Ok(pack(1_001, 0)));
+
+        let mut clock = HlcClock::new(5);
+        assert_eq!(
+            clock.observe(pack(1_000, u16::MAX), 1_000),
+            Ok(pack(1_001, 0))
+        );
+    }
+
+    #[test]
+    fn test_observe_moves_past_remote() {
+        let mut clock = HlcClock::resume(pack(1_000, 3), 50);
+        assert_eq!(clock.observe(pack(1_020, 7), 1_010), Ok(pack(1_020, 8)));
+        assert_eq!(clock.observe(pack(900, 0), 1_010), Ok(pack(1_020, 9)));
+    }
+
+    #[test]
+    fn test_drift_is_bounded_and_refusals_leave_state() {
+        let mut clock = HlcClock::resume(pack(1_000, 0), 10);
+        let before = clock;
+        assert_eq!(
+            clock.observe(pack(1_011, 0), 1_000),
+            Err(HlcError::DriftExceeded { ahead_ms: 11 })
+        );
+        assert_eq!(
+            clock.tick(989),
+            Err(HlcError::DriftExceeded { ahead_ms: 11 })
+        );
+        assert_eq!(clock, before);
+        assert_eq!(clock.observe(pack(1_010, 0), 1_000), Ok(pack(1_010, 1)));
+    }
+
+    #[test]
+    fn test_exhausted_clock_refuses() {
+        let mut clock = HlcClock::resume(u64::MAX, u64::MAX);
+        assert_eq!(clock.tick(0), Err(HlcError::Exhausted));
+        assert_eq!(clock.last(), u64::MAX);
+    }
+}
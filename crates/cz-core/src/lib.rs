@@ -10,6 +10,11 @@
 
 use core::cmp::Ordering;
 
+pub mod append;
+pub mod compress;
+pub mod hlc;
+pub mod slot;
+
 /// The fundamental event atom of the LACRIMOSA sequencer.
 ///
 /// This struct is `#[repr(C)]` — deterministic field layout, zero-copy safe.
@@ -62,6 +67,10 @@ pub struct CausalEvent {
 }
 
 pub const FLAG_CHECKPOINT: u16 = 0x1;
+/// The event was appended with a new `lamport_ts` (see [`append`]).
+pub const FLAG_RESEQUENCED: u16 = 0x2;
+/// The payload is a [`compress`] frame; the checksum covers the frame.
+pub const FLAG_COMPRESSED: u16 = 0x4;
 
 // =============================================================================
 // The Immutable Truth: Manual Ord on (lamport_ts, node_id, stream_id)
@@ -148,6 +157,63 @@ impl CausalEvent {
         (self.flags & FLAG_CHECKPOINT) != 0
     }
 
+    /// Check if the event was restamped when appended.
+    #[inline]
+    pub fn is_resequenced(&self) -> bool {
+        (self.flags & FLAG_RESEQUENCED) != 0
+    }
+
+    /// Check if the payload was sent compressed.
+    #[inline]
+    pub fn is_compressed(&self) -> bool {
+        (self.flags & FLAG_COMPRESSED) != 0
+    }
+
+    /// The same event marked as carrying a [`compress`] frame payload.
+    #[inline]
+    pub const fn compressed(self) -> Self {
+        Self {
+            flags: self.flags | FLAG_COMPRESSED,
+            ..self
+        }
+    }
+
+    /// Decode the event header at the start of `bytes` (a UDP packet or a
+    /// journal slot), reading each field little-endian at its layout
+    /// offset. Bytes past the header, such as a packet's payload, are
+    /// ignored; the trailing pad is never read.
+    ///
+    /// Returns `None` if `bytes` is shorter than [`Self::size_bytes`].
+    #[inline]
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let header: &[u8; 32] = bytes.first_chunk()?;
+        let u64_at = |at: usize| u64::from_le_bytes(header[at..at + 8].try_into().unwrap());
+        let u32_at = |at: usize| u32::from_le_bytes(header[at..at + 4].try_into().unwrap());
+        let u16_at = |at: usize| u16::from_le_bytes([header[at], header[at + 1]]);
+        Some(Self::with_flags(
+            u64_at(0),
+            u32_at(8),
+            u16_at(12),
+            u64_at(16),
+            u32_at(24),
+            u16_at(14),
+        ))
+    }
+
+    /// Encode the event in its 32-byte layout, little-endian, with the
+    /// trailing pad zeroed. Inverse of [`Self::from_bytes`].
+    #[inline]
+    pub fn to_bytes(&self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        bytes[0..8].copy_from_slice(&self.lamport_ts.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.node_id.to_le_bytes());
+        bytes[12..14].copy_from_slice(&self.stream_id.to_le_bytes());
+        bytes[14..16].copy_from_slice(&self.flags.to_le_bytes());
+        bytes[16..24].copy_from_slice(&self.payload_offset.to_le_bytes());
+        bytes[24..28].copy_from_slice(&self.checksum.to_le_bytes());
+        bytes
+    }
+
     /// Returns the size of this struct in bytes.
     /// 32 bytes with `#[repr(C)]` deterministic layout.
     #[inline]
@@ -166,6 +232,23 @@ mod tests {
         assert_eq!(CausalEvent::size_bytes(), 32);
     }
 
+    #[test]
+    fn test_bytes_round_trip_and_short_input() {
+        let event = CausalEvent::with_flags(0x0102_0304_0506_0708, 9, 3, 4096, 0xBEEF, 1);
+        let mut packet = [0xAAu8; 40];
+        packet[..32].copy_from_slice(&event.to_bytes());
+
+        let decoded = CausalEvent::from_bytes(&packet).unwrap();
+        assert_eq!(
+            (decoded.flags, decoded.payload_offset, decoded.checksum),
+            (1, 4096, 0xBEEF)
+        );
+        assert_eq!(decoded, event);
+        assert_eq!(packet[0], 0x08, "lamport_ts is little-endian");
+        assert_eq!(event.to_bytes()[28..], [0; 4]);
+        assert!(CausalEvent::from_bytes(&packet[..31]).is_none());
+    }
+
     #[test]
     fn test_ordering_by_lamport_ts_first() {
         let a = CausalEvent::new(1, 0, 0, 0, 0);
@@ -2,9 +2,17 @@
 //!
 //! Rule-based alerting with incident lifecycle management and notification dispatch.
 
+use cz_hub::store::StateStore;
 use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
-use tokio::sync::RwLock;
+use std::sync::Arc;
+use tokio::sync::{broadcast, RwLock};
+
+/// Collection the alert rules are saved under.
+const STORE_KEY: &str = "alert_rules";
+
+/// Opened incidents buffered per subscriber before it starts lagging.
+const OPENED_BUFFER: usize = 64;
 
 /// Incident status lifecycle.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -87,6 +95,9 @@ pub struct AlertEngine {
     pub channels: RwLock<Vec<NotificationChannel>>,
     pub incident_history: RwLock<VecDeque<Incident>>,
     history_capacity: usize,
+    /// Every incident as it is opened.
+    opened: broadcast::Sender<Incident>,
+    store: Option<Arc<StateStore>>,
 }
 
 impl AlertEngine {
@@ -97,9 +108,32 @@ impl AlertEngine {
             channels: RwLock::new(Vec::new()),
             incident_history: RwLock::new(VecDeque::with_capacity(history_capacity)),
             history_capacity,
+            opened: broadcast::channel(OPENED_BUFFER).0,
+            store: None,
         }
     }
 
+    /// Start from the rules saved in `store` and save every rule added.
+    pub fn with_store(mut self, store: Arc<StateStore>) -> Self {
+        self.rules = RwLock::new(store.load(STORE_KEY).unwrap_or_default());
+        self.store = Some(store);
+        self
+    }
+
+    /// Add a rule.
+    pub async fn add_rule(&self, rule: AlertRuleV2) {
+        let mut rules = self.rules.write().await;
+        rules.push(rule);
+        if let Some(store) = &self.store {
+            store.save(STORE_KEY, &*rules);
+        }
+    }
+
+    /// Follow incidents as they are opened.
+    pub fn subscribe(&self) -> broadcast::Receiver<Incident> {
+        self.opened.subscribe()
+    }
+
     /// Create a new incident from an alert rule trigger.
     pub async fn create_incident(&self, rule: &AlertRuleV2, message: String) -> Incident {
         let now = chrono::Utc::now().to_rfc3339();
@@ -124,6 +158,8 @@ impl AlertEngine {
 
         let mut incidents = self.incidents.write().await;
         incidents.push(incident.clone());
+        // No subscribers right now is normal.
+        let _ = self.opened.send(incident.clone());
 
         // Dispatch notifications
         self.dispatch_notification(&incident, &rule.notification_channels)
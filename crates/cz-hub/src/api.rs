@@ -3,34 +3,90 @@
 //! Axum handlers for the new Control Center capabilities.
 
 use crate::alerts::{AlertRuleV2, Incident};
-use crate::auth::CreateApiKeyRequest;
-use crate::connectors::{ConnectorConfig, ConnectorInfo};
+use crate::auth::{ApiKey, CreateApiKeyRequest};
+use crate::connectors::signature::SignatureError;
+use crate::connectors::{ConnectorConfig, ConnectorInfo, HealthReport};
 use crate::dashboards::{CreateDashboardRequest, Dashboard, UpdateDashboardRequest};
+use crate::metrics::RejectReason;
 use crate::pipelines::{CreatePipelineRequest, Pipeline, UpdatePipelineRequest};
-use crate::query::{QueryRequest, QueryResult};
-use crate::traces::{ServiceDependency, SpanIngestionRequest, Trace, TraceSearchParams};
+use crate::query::cache::QueryStats;
+use crate::query::executor::JournalSource;
+use crate::query::explain::QueryExplain;
+use crate::query::parser::ParseError;
+use crate::query::subscriptions::SubscribeError;
+use crate::query::{Query as DslQuery, QueryRequest, QueryResult};
+use crate::saved_queries::{SaveQueryRequest, SavedQuery};
+use crate::traces::analytics::{AnalyticsParams, AnalyticsReport};
+use crate::traces::sampling::SamplingPolicy;
+use crate::traces::tree;
+use crate::traces::{
+    ServiceDependency, SpanIngestionRequest, Trace, TraceDetail, TraceSearchParams,
+};
 use crate::AppState;
 use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
-    Json,
+    response::{IntoResponse, Response},
+    Extension, Json,
 };
 use std::collections::HashMap;
 use std::sync::Arc;
 
+/// Actor recorded in timelines and audit entries. The auth middleware
+/// attaches the caller's key; public routes have none.
+fn actor(key: &Option<Extension<ApiKey>>) -> String {
+    key.as_ref()
+        .map_or_else(|| "anonymous".to_string(), |Extension(key)| key.actor())
+}
+
 // =============================================================================
 // Connectors
 // =============================================================================
 
-pub async fn list_connectors(State(state): State<Arc<AppState>>) -> Json<Vec<ConnectorInfo>> {
-    let connectors = state.connector_registry.list().await;
+pub async fn list_connectors(
+    State(state): State<Arc<AppState>>,
+    Extension(key): Extension<ApiKey>,
+) -> Json<Vec<ConnectorInfo>> {
+    let connectors = state
+        .connector_registry
+        .list()
+        .await
+        .into_iter()
+        .filter(|c| {
+            state
+                .auth_layer
+                .can_access(&key, &format!("connector:{}", c.id))
+        })
+        .collect();
     Json(connectors)
 }
 
+/// Reject with 403 unless `key` may access connector `id`.
+fn check_connector_access(
+    state: &AppState,
+    key: &ApiKey,
+    id: &str,
+) -> Result<(), (StatusCode, String)> {
+    if state
+        .auth_layer
+        .can_access(key, &format!("connector:{}", id))
+    {
+        Ok(())
+    } else {
+        Err((
+            StatusCode::FORBIDDEN,
+            format!("Key is not permitted to access connector '{}'", id),
+        ))
+    }
+}
+
 pub async fn create_connector(
     State(state): State<Arc<AppState>>,
+    Extension(key): Extension<ApiKey>,
     Json(config): Json<ConnectorConfig>,
 ) -> Result<Json<ConnectorInfo>, (StatusCode, String)> {
+    // The id is only assigned on creation, so the key must cover all connectors.
+    check_connector_access(&state, &key, "*")?;
     match state.connector_registry.create_from_config(config).await {
         Ok(info) => Ok(Json(info)),
         Err(e) => Err((StatusCode::BAD_REQUEST, e.to_string())),
@@ -39,25 +95,51 @@ pub async fn create_connector(
 
 pub async fn delete_connector(
     State(state): State<Arc<AppState>>,
+    Extension(key): Extension<ApiKey>,
     Path(id): Path<String>,
 ) -> Result<StatusCode, (StatusCode, String)> {
+    check_connector_access(&state, &key, &id)?;
     match state.connector_registry.remove(&id).await {
         Ok(_) => Ok(StatusCode::NO_CONTENT),
         Err(e) => Err((StatusCode::NOT_FOUND, e.to_string())),
     }
 }
 
-pub async fn ingest_webhook(
+/// Probe a connector: liveness, last event age and error rate.
+pub async fn connector_health(
     State(state): State<Arc<AppState>>,
+    Extension(key): Extension<ApiKey>,
     Path(id): Path<String>,
-    headers: axum::http::HeaderMap,
-    Json(payload): Json<serde_json::Value>,
-) -> Result<StatusCode, (StatusCode, String)> {
+) -> Result<Json<HealthReport>, (StatusCode, String)> {
+    check_connector_access(&state, &key, &id)?;
     let connector = state
         .connector_registry
         .get(&id)
         .await
         .ok_or((StatusCode::NOT_FOUND, "Connector not found".to_string()))?;
+    Ok(Json(connector.health_check().await))
+}
+
+pub async fn ingest_webhook(
+    State(state): State<Arc<AppState>>,
+    key: Option<Extension<ApiKey>>,
+    Path(id): Path<String>,
+    headers: axum::http::HeaderMap,
+    body: axum::body::Bytes,
+) -> Result<StatusCode, (StatusCode, String)> {
+    if let Some(Extension(key)) = &key {
+        check_connector_access(&state, key, &id)?;
+    }
+    let connector = state.connector_registry.get(&id).await.ok_or_else(|| {
+        state
+            .metrics
+            .record_rejected(RejectReason::UnknownConnector);
+        (StatusCode::NOT_FOUND, "Connector not found".to_string())
+    })?;
+    // Without a key, the signature check in `ingest` is the authentication.
+    if key.is_none() && !connector.verifies_signatures() {
+        return Err((StatusCode::UNAUTHORIZED, "Missing API key".to_string()));
+    }
 
     let normalized_headers: HashMap<String, String> = headers
         .iter()
@@ -69,9 +151,19 @@ pub async fn ingest_webhook(
         .collect();
 
     connector
-        .ingest(payload, normalized_headers)
+        .ingest(&body, normalized_headers)
         .await
-        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+        .map_err(|e| {
+            if e.is::<SignatureError>() {
+                state
+                    .metrics
+                    .record_rejected(RejectReason::InvalidSignature);
+                (StatusCode::UNAUTHORIZED, e.to_string())
+            } else {
+                state.metrics.record_rejected(RejectReason::InvalidPayload);
+                (StatusCode::BAD_REQUEST, e.to_string())
+            }
+        })?;
 
     Ok(StatusCode::ACCEPTED)
 }
@@ -80,20 +172,264 @@ pub async fn ingest_webhook(
 // Query
 // =============================================================================
 
-pub async fn execute_query(
-    State(state): State<Arc<AppState>>,
-    Json(req): Json<QueryRequest>,
-) -> Result<Json<QueryResult>, (StatusCode, String)> {
-    let query = if let Some(q) = req.structured {
-        q
+/// A failed query request. A query that does not parse is answered 400
+/// with the [`ParseError`] as JSON, so its span can be shown; anything else
+/// as a status and plain message.
+pub enum QueryError {
+    Parse(ParseError),
+    Status(StatusCode, String),
+}
+
+impl From<ParseError> for QueryError {
+    fn from(e: ParseError) -> Self {
+        QueryError::Parse(e)
+    }
+}
+
+impl From<(StatusCode, String)> for QueryError {
+    fn from((status, message): (StatusCode, String)) -> Self {
+        QueryError::Status(status, message)
+    }
+}
+
+impl IntoResponse for QueryError {
+    fn into_response(self) -> Response {
+        match self {
+            QueryError::Parse(e) => (StatusCode::BAD_REQUEST, Json(e)).into_response(),
+            QueryError::Status(status, message) => (status, message).into_response(),
+        }
+    }
+}
+
+/// The query a request carries, structured or as text, with the parser's
+/// warnings about text it ignored.
+fn request_query(req: &QueryRequest) -> Result<(DslQuery, Vec<String>), QueryError> {
+    let (mut query, warnings) = if let Some(q) = &req.structured {
+        (q.clone(), Vec::new())
     } else if let Some(text) = &req.query {
-        crate::query::parser::parse(text).map_err(|e| (StatusCode::BAD_REQUEST, e))?
+        crate::query::parser::parse_with_warnings(text)?
     } else {
-        return Err((StatusCode::BAD_REQUEST, "Missing query".into()));
+        return Err((StatusCode::BAD_REQUEST, "Missing query".to_string()).into());
     };
+    if req.cursor.is_some() {
+        query.cursor = req.cursor;
+    }
+    Ok((query, warnings))
+}
+
+/// Run a query, or with `explain` set or an `EXPLAIN` query, answer as
+/// `POST /api/query/explain`.
+pub async fn execute_query(
+    State(state): State<Arc<AppState>>,
+    Extension(key): Extension<ApiKey>,
+    Json(req): Json<QueryRequest>,
+) -> Result<Response, QueryError> {
+    let (query, warnings) = request_query(&req)?;
+    if req.explain || query.explain {
+        return Ok(Json(explain(&state, &key, query, warnings).await).into_response());
+    }
+    Ok(Json(run_query(&state, &key, &query).await?).into_response())
+}
+
+/// Describe how a query would run: its parse tree, the streams and
+/// journals it would scan, and warnings about parts that match nothing.
+pub async fn explain_query(
+    State(state): State<Arc<AppState>>,
+    Extension(key): Extension<ApiKey>,
+    Json(req): Json<QueryRequest>,
+) -> Result<Json<QueryExplain>, QueryError> {
+    let (query, warnings) = request_query(&req)?;
+    Ok(Json(explain(&state, &key, query, warnings).await))
+}
 
-    let result = crate::query::executor::execute(&query, &state.connector_registry).await;
-    Ok(Json(result))
+/// Totals over every query run: count, cache hits, rows scanned and
+/// matched, and mean time.
+pub async fn query_stats(State(state): State<Arc<AppState>>) -> Json<QueryStats> {
+    Json(state.query_cache.stats())
+}
+
+/// Register a query whose new matches are streamed to the WS `query`
+/// channel. Answers `{"id": ...}`; a WS connection of the same key then
+/// sends `{"subscribe": ["query"], "query": id}` to attach it.
+pub async fn subscribe_query(
+    State(state): State<Arc<AppState>>,
+    Extension(key): Extension<ApiKey>,
+    Json(req): Json<QueryRequest>,
+) -> Result<Json<serde_json::Value>, QueryError> {
+    let (query, _) = request_query(&req)?;
+    match state.query_subscriptions.create(&key.id, query) {
+        Ok(id) => Ok(Json(serde_json::json!({ "id": id }))),
+        Err(e @ SubscribeError::TooMany(_)) => {
+            Err((StatusCode::TOO_MANY_REQUESTS, e.to_string()).into())
+        }
+        Err(e) => Err((StatusCode::BAD_REQUEST, e.to_string()).into()),
+    }
+}
+
+/// Open journals a query naming streams in `FROM` might read.
+async fn query_journals(state: &AppState, query: &DslQuery) -> Vec<Arc<crate::JournalState>> {
+    if query.from.is_empty() {
+        return Vec::new();
+    }
+    state
+        .journals
+        .read()
+        .await
+        .values()
+        .filter(|j| !j.is_closing() && j.connector_id.is_some())
+        .cloned()
+        .collect()
+}
+
+/// [`JournalSource`]s over `open` journals, read through `guards`.
+fn journal_sources<'a>(
+    open: &'a [Arc<crate::JournalState>],
+    guards: &'a [tokio::sync::RwLockReadGuard<'_, cz_io::journal::Journal>],
+) -> Vec<JournalSource<'a>> {
+    open.iter()
+        .zip(guards)
+        .map(|(journal_state, journal)| JournalSource {
+            connector_id: journal_state.connector_id.clone().unwrap_or_default(),
+            name: crate::connectors::journal::stream_name(&journal_state.path),
+            journal,
+        })
+        .collect()
+}
+
+/// Explain `query` over the streams `key` may read.
+async fn explain(
+    state: &AppState,
+    key: &ApiKey,
+    query: DslQuery,
+    warnings: Vec<String>,
+) -> QueryExplain {
+    let open = query_journals(state, &query).await;
+    let mut guards = Vec::with_capacity(open.len());
+    for journal_state in &open {
+        guards.push(journal_state.journal.read().await);
+    }
+    let sources = journal_sources(&open, &guards);
+    crate::query::explain::explain(
+        query,
+        warnings,
+        &state.connector_registry,
+        &sources,
+        |stream| {
+            state
+                .auth_layer
+                .can_access(key, &format!("stream:{}", stream))
+        },
+    )
+    .await
+}
+
+/// Execute `query` over the streams `key` may read, timing it. Open
+/// journals the query names in `FROM` are read from their index rings.
+async fn run_query(
+    state: &AppState,
+    key: &ApiKey,
+    query: &DslQuery,
+) -> Result<QueryResult, (StatusCode, String)> {
+    let open = query_journals(state, query).await;
+    let mut guards = Vec::with_capacity(open.len());
+    for journal_state in &open {
+        guards.push(journal_state.journal.read().await);
+    }
+    let sources = journal_sources(&open, &guards);
+    let result = state
+        .query_cache
+        .execute(
+            query,
+            &state.connector_registry,
+            &sources,
+            &key.id,
+            |stream| {
+                state
+                    .auth_layer
+                    .can_access(key, &format!("stream:{}", stream))
+            },
+        )
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    state
+        .metrics
+        .query_duration
+        .observe(result.query_time_ms as f64 / 1000.0);
+    Ok(result)
+}
+
+pub async fn list_saved_queries(State(state): State<Arc<AppState>>) -> Json<Vec<SavedQuery>> {
+    Json(state.saved_queries.list().await)
+}
+
+pub async fn create_saved_query(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<SaveQueryRequest>,
+) -> Result<Json<SavedQuery>, (StatusCode, String)> {
+    let query = state
+        .saved_queries
+        .create(req.name, req.query_text)
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    Ok(Json(query))
+}
+
+pub async fn get_saved_query(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<SavedQuery>, (StatusCode, String)> {
+    let query = state
+        .saved_queries
+        .get(&id)
+        .await
+        .ok_or((StatusCode::NOT_FOUND, "Saved query not found".to_string()))?;
+    Ok(Json(query))
+}
+
+pub async fn update_saved_query(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Json(req): Json<SaveQueryRequest>,
+) -> Result<Json<SavedQuery>, (StatusCode, String)> {
+    let query = state
+        .saved_queries
+        .update(&id, req.name, req.query_text)
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?
+        .ok_or((StatusCode::NOT_FOUND, "Saved query not found".to_string()))?;
+    Ok(Json(query))
+}
+
+pub async fn delete_saved_query(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    state
+        .saved_queries
+        .delete(&id)
+        .await
+        .map_err(|e| (StatusCode::NOT_FOUND, e))?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Parse and execute a saved query's current text, or explain it if it is
+/// an `EXPLAIN` query. Streams it names that do not exist (any more)
+/// simply match nothing.
+pub async fn run_saved_query(
+    State(state): State<Arc<AppState>>,
+    Extension(key): Extension<ApiKey>,
+    Path(id): Path<String>,
+) -> Result<Response, QueryError> {
+    let saved = state
+        .saved_queries
+        .get(&id)
+        .await
+        .ok_or((StatusCode::NOT_FOUND, "Saved query not found".to_string()))?;
+    let (query, warnings) = crate::query::parser::parse_with_warnings(&saved.query_text)?;
+    if query.explain {
+        return Ok(Json(explain(&state, &key, query, warnings).await).into_response());
+    }
+    Ok(Json(run_query(&state, &key, &query).await?).into_response())
 }
 
 // =============================================================================
@@ -107,23 +443,25 @@ pub async fn list_incidents(State(state): State<Arc<AppState>>) -> Json<Vec<Inci
 
 pub async fn acknowledge_incident(
     State(state): State<Arc<AppState>>,
+    key: Option<Extension<ApiKey>>,
     Path(id): Path<String>,
 ) -> Result<Json<Incident>, (StatusCode, String)> {
     let incident = state
         .alert_engine
-        .acknowledge_incident(&id, "admin")
-        .await // hardcoded actor for now
+        .acknowledge_incident(&id, &actor(&key))
+        .await
         .map_err(|e| (StatusCode::NOT_FOUND, e))?;
     Ok(Json(incident))
 }
 
 pub async fn resolve_incident(
     State(state): State<Arc<AppState>>,
+    key: Option<Extension<ApiKey>>,
     Path(id): Path<String>,
 ) -> Result<Json<Incident>, (StatusCode, String)> {
     let incident = state
         .alert_engine
-        .resolve_incident(&id, "admin")
+        .resolve_incident(&id, &actor(&key))
         .await
         .map_err(|e| (StatusCode::NOT_FOUND, e))?;
     Ok(Json(incident))
@@ -154,8 +492,7 @@ pub async fn create_alert_rule(
     State(state): State<Arc<AppState>>,
     Json(rule): Json<AlertRuleV2>,
 ) -> Json<String> {
-    let mut rules = state.alert_engine.rules.write().await;
-    rules.push(rule);
+    state.alert_engine.add_rule(rule).await;
     Json("created".into())
 }
 
@@ -174,9 +511,17 @@ pub async fn list_traces(
 pub async fn get_trace(
     State(state): State<Arc<AppState>>,
     Path(id): Path<String>,
-) -> Json<Option<Trace>> {
-    let trace = state.trace_store.get_trace(&id).await;
-    Json(trace)
+) -> Json<Option<TraceDetail>> {
+    let Some(trace) = state.trace_store.get_trace(&id).await else {
+        return Json(None);
+    };
+    let events = state.correlated_events(&id).await;
+    let tree = tree::build(&trace.spans);
+    Json(Some(TraceDetail {
+        trace,
+        tree,
+        events,
+    }))
 }
 
 pub async fn get_service_graph(State(state): State<Arc<AppState>>) -> Json<Vec<ServiceDependency>> {
@@ -184,6 +529,33 @@ pub async fn get_service_graph(State(state): State<Arc<AppState>>) -> Json<Vec<S
     Json(graph)
 }
 
+pub async fn trace_analytics(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<AnalyticsParams>,
+) -> Result<Json<AnalyticsReport>, (StatusCode, String)> {
+    match state.trace_store.analytics(&params).await {
+        Ok(report) => Ok(Json(report)),
+        Err(e) => Err((StatusCode::BAD_REQUEST, e)),
+    }
+}
+
+pub async fn get_trace_sampling(State(state): State<Arc<AppState>>) -> Json<SamplingPolicy> {
+    Json(state.trace_store.sampling())
+}
+
+/// Replace the sampling policy until the next restart, or a config reload
+/// that changes `[traces]`.
+pub async fn set_trace_sampling(
+    State(state): State<Arc<AppState>>,
+    Json(policy): Json<SamplingPolicy>,
+) -> Result<Json<SamplingPolicy>, (StatusCode, String)> {
+    policy
+        .validate()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    state.trace_store.set_sampling(policy.clone());
+    Ok(Json(policy))
+}
+
 pub async fn ingest_spans(
     State(state): State<Arc<AppState>>,
     Json(body): Json<SpanIngestionRequest>,
@@ -325,10 +697,15 @@ pub async fn delete_dashboard(
 
 pub async fn create_api_key(
     State(state): State<Arc<AppState>>,
+    key: Option<Extension<ApiKey>>,
     Json(req): Json<CreateApiKeyRequest>,
-) -> Json<crate::auth::ApiKey> {
-    let key = state.auth_layer.create_key(req).await;
-    Json(key)
+) -> Result<Json<crate::auth::CreatedApiKey>, (StatusCode, String)> {
+    let created = state
+        .auth_layer
+        .create_key(req, &actor(&key))
+        .await
+        .map_err(|e| (StatusCode::CONFLICT, e))?;
+    Ok(Json(created))
 }
 
 pub async fn list_api_keys(State(state): State<Arc<AppState>>) -> Json<Vec<crate::auth::ApiKey>> {
@@ -338,11 +715,12 @@ pub async fn list_api_keys(State(state): State<Arc<AppState>>) -> Json<Vec<crate
 
 pub async fn revoke_api_key(
     State(state): State<Arc<AppState>>,
+    key: Option<Extension<ApiKey>>,
     Path(id): Path<String>,
 ) -> Result<StatusCode, (StatusCode, String)> {
     state
         .auth_layer
-        .revoke_key(&id)
+        .revoke_key(&id, &actor(&key))
         .await
         .map_err(|e| (StatusCode::NOT_FOUND, e))?;
     Ok(StatusCode::NO_CONTENT)
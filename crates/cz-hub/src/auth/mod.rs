@@ -1,11 +1,32 @@
 //! # Access Control & Audit
 //!
 //! API key management, scope-based authorization, and audit logging.
+//!
+//! Keys carry coarse [`Scope`]s plus optional resource scopes: glob patterns
+//! such as `stream:orders*` or `connector:webhook-*` that restrict which
+//! streams and connectors the key can see. A key with no resource scopes is
+//! unrestricted.
+//!
+//! Each key also draws from a token bucket refilled at `requests_per_minute`
+//! (per key, falling back to the layer default) so a leaked key cannot
+//! flood the hub; see [`crate::limits`] for the per-IP and expensive-route
+//! buckets.
 
+use chrono::{DateTime, Utc};
+use cz_hub::store::StateStore;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
+use tokio::time::Instant;
+
+use crate::limits::{BucketState, Class, TokenBucket};
+
+/// Collection the API keys (hashes only) are saved under.
+const STORE_KEY: &str = "api_keys";
 
 /// Permission scopes.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -16,22 +37,54 @@ pub enum Scope {
     Admin,
 }
 
-/// An API key.
+/// An API key as stored. Only the hash is kept; the raw value cannot be
+/// recovered from it.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ApiKey {
     pub id: String,
     pub label: String,
-    /// The actual key value (only shown once at creation).
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub key: Option<String>,
     /// SHA-256 hash of the key (stored for comparison).
     pub key_hash: String,
     pub scopes: Vec<Scope>,
+    /// Glob patterns over `stream:<name>` / `connector:<id>` (empty = all).
+    #[serde(default)]
+    pub resource_scopes: Vec<String>,
+    /// Per-key rate limit; `None` uses the layer default.
+    #[serde(default)]
+    pub requests_per_minute: Option<u32>,
     pub created_at: String,
     pub last_used_at: Option<String>,
+    /// After this instant the key is rejected like a revoked one.
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Subject CN of the client certificates that authenticate as this key.
+    /// No two live keys share one.
+    #[serde(default)]
+    pub tls_subject: Option<String>,
     pub revoked: bool,
 }
 
+impl ApiKey {
+    /// Identity recorded as the actor in audit entries and timelines.
+    pub fn actor(&self) -> String {
+        format!("{} ({})", self.label, self.id)
+    }
+
+    /// Returns `true` if the key has an expiry and it has passed.
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|at| now >= at)
+    }
+}
+
+/// Result of creating a key — the only place the raw value ever appears.
+#[derive(Debug, Clone, Serialize)]
+pub struct CreatedApiKey {
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+    /// The raw key value (shown once, never stored).
+    pub key: String,
+}
+
 /// Audit log entry.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuditEntry {
@@ -49,45 +102,108 @@ pub struct AuditEntry {
 pub struct CreateApiKeyRequest {
     pub label: String,
     pub scopes: Vec<Scope>,
+    #[serde(default)]
+    pub resource_scopes: Vec<String>,
+    #[serde(default)]
+    pub requests_per_minute: Option<u32>,
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub tls_subject: Option<String>,
 }
 
+/// Default per-key limit when neither the key nor the config sets one.
+pub const DEFAULT_REQUESTS_PER_MINUTE: u32 = 600;
+
 /// The auth layer state.
 pub struct AuthLayer {
     pub api_keys: RwLock<Vec<ApiKey>>,
     pub audit_log: RwLock<VecDeque<AuditEntry>>,
     audit_capacity: usize,
+    /// Rate-limit buckets keyed by API key id.
+    rate_buckets: RwLock<HashMap<String, TokenBucket>>,
+    default_requests_per_minute: AtomicU32,
+    store: Option<Arc<StateStore>>,
 }
 
 impl AuthLayer {
-    pub fn new(audit_capacity: usize) -> Self {
+    /// Create an auth layer whose keys default to `requests_per_minute`.
+    pub fn new(audit_capacity: usize, requests_per_minute: u32) -> Self {
         Self {
             api_keys: RwLock::new(Vec::new()),
             audit_log: RwLock::new(VecDeque::with_capacity(audit_capacity)),
             audit_capacity,
+            rate_buckets: RwLock::new(HashMap::new()),
+            default_requests_per_minute: AtomicU32::new(requests_per_minute),
+            store: None,
         }
     }
 
-    /// Create a new API key. Returns the key with the raw value (shown once).
-    pub async fn create_key(&self, req: CreateApiKeyRequest) -> ApiKey {
+    /// Change the budget of keys without their own, e.g. on config reload.
+    pub fn set_default_requests_per_minute(&self, requests_per_minute: u32) {
+        self.default_requests_per_minute
+            .store(requests_per_minute, Ordering::Relaxed);
+    }
+
+    /// Start from the keys saved in `store` and save every key created or
+    /// revoked. Only [`ApiKey`]s are saved, so raw keys never reach disk.
+    pub fn with_store(mut self, store: Arc<StateStore>) -> Self {
+        self.api_keys = RwLock::new(store.load(STORE_KEY).unwrap_or_default());
+        self.store = Some(store);
+        self
+    }
+
+    fn persist(&self, keys: &[ApiKey]) {
+        if let Some(store) = &self.store {
+            store.save(STORE_KEY, keys);
+        }
+    }
+
+    /// Create a new API key on behalf of `actor`. The raw value is returned
+    /// once and only its hash is stored.
+    ///
+    /// Fails if `req.tls_subject` is already bound to another live key.
+    pub async fn create_key(
+        &self,
+        req: CreateApiKeyRequest,
+        actor: &str,
+    ) -> Result<CreatedApiKey, String> {
         let raw_key = format!("cz_{}", uuid::Uuid::new_v4().as_simple());
         let key_hash = sha256_hex(&raw_key);
 
         let api_key = ApiKey {
             id: format!("key-{}", uuid::Uuid::new_v4().as_simple()),
             label: req.label,
-            key: Some(raw_key.clone()),
             key_hash,
             scopes: req.scopes,
+            resource_scopes: req.resource_scopes,
+            requests_per_minute: req.requests_per_minute,
             created_at: chrono::Utc::now().to_rfc3339(),
             last_used_at: None,
+            expires_at: req.expires_at,
+            tls_subject: req.tls_subject,
             revoked: false,
         };
 
-        let mut keys = self.api_keys.write().await;
-        keys.push(api_key.clone());
+        {
+            let mut keys = self.api_keys.write().await;
+            if let Some(subject) = &api_key.tls_subject {
+                let now = Utc::now();
+                if let Some(bound) = keys.iter().find(|k| {
+                    k.tls_subject.as_ref() == Some(subject) && !k.revoked && !k.is_expired(now)
+                }) {
+                    return Err(format!(
+                        "Client certificate CN '{}' is already bound to key '{}'",
+                        subject, bound.id
+                    ));
+                }
+            }
+            keys.push(api_key.clone());
+            self.persist(&keys);
+        }
 
         self.log_audit(
-            "system".into(),
+            actor.to_string(),
             "create_key".into(),
             format!("api_key:{}", api_key.id),
             format!("Created API key '{}'", api_key.label),
@@ -95,43 +211,66 @@ impl AuthLayer {
         )
         .await;
 
-        api_key
+        Ok(CreatedApiKey {
+            api_key,
+            key: raw_key,
+        })
     }
 
-    /// Revoke an API key.
-    pub async fn revoke_key(&self, key_id: &str) -> Result<(), String> {
-        let mut keys = self.api_keys.write().await;
-        let key = keys
-            .iter_mut()
-            .find(|k| k.id == key_id)
-            .ok_or_else(|| format!("Key '{}' not found", key_id))?;
-        key.revoked = true;
+    /// Revoke an API key on behalf of `actor`.
+    pub async fn revoke_key(&self, key_id: &str, actor: &str) -> Result<(), String> {
+        let label = {
+            let mut keys = self.api_keys.write().await;
+            let key = keys
+                .iter_mut()
+                .find(|k| k.id == key_id)
+                .ok_or_else(|| format!("Key '{}' not found", key_id))?;
+            key.revoked = true;
+            let label = key.label.clone();
+            self.persist(&keys);
+            label
+        };
+        self.rate_buckets.write().await.remove(key_id);
+
+        self.log_audit(
+            actor.to_string(),
+            "revoke_key".into(),
+            format!("api_key:{}", key_id),
+            format!("Revoked API key '{}'", label),
+            None,
+        )
+        .await;
         Ok(())
     }
 
-    /// List all API keys (without raw values).
+    /// List all API keys.
     pub async fn list_keys(&self) -> Vec<ApiKey> {
-        let keys = self.api_keys.read().await;
-        keys.iter()
-            .map(|k| {
-                let mut k = k.clone();
-                k.key = None; // Never expose raw key after creation
-                k
-            })
-            .collect()
+        self.api_keys.read().await.clone()
     }
 
-    /// Validate a bearer token. Returns the API key if valid.
+    /// Validate a bearer token. Returns the API key if it exists and is
+    /// neither revoked nor expired.
     pub async fn validate_token(&self, token: &str) -> Option<ApiKey> {
         let hash = sha256_hex(token);
+        let now = Utc::now();
         let mut keys = self.api_keys.write().await;
         let key = keys
             .iter_mut()
-            .find(|k| constant_time_eq(&k.key_hash, &hash) && !k.revoked)?;
-        key.last_used_at = Some(chrono::Utc::now().to_rfc3339());
-        let mut result = key.clone();
-        result.key = None;
-        Some(result)
+            .find(|k| constant_time_eq(&k.key_hash, &hash) && !k.revoked && !k.is_expired(now))?;
+        key.last_used_at = Some(now.to_rfc3339());
+        Some(key.clone())
+    }
+
+    /// The live key bound to `subject`, for clients authenticated by a TLS
+    /// certificate with that CN.
+    pub async fn key_for_tls_subject(&self, subject: &str) -> Option<ApiKey> {
+        let now = Utc::now();
+        let mut keys = self.api_keys.write().await;
+        let key = keys.iter_mut().find(|k| {
+            k.tls_subject.as_deref() == Some(subject) && !k.revoked && !k.is_expired(now)
+        })?;
+        key.last_used_at = Some(now.to_rfc3339());
+        Some(key.clone())
     }
 
     pub fn has_scope(&self, key: &ApiKey, required: Scope) -> bool {
@@ -141,6 +280,63 @@ impl AuthLayer {
         key.scopes.contains(&required)
     }
 
+    /// Consume one request from `key`'s bucket.
+    ///
+    /// Returns `Err(retry_after)` when the key has exhausted its limit.
+    pub async fn check_rate_limit(&self, key: &ApiKey) -> Result<(), Duration> {
+        self.check_rate_limit_at(key, Instant::now()).await
+    }
+
+    async fn check_rate_limit_at(&self, key: &ApiKey, now: Instant) -> Result<(), Duration> {
+        let capacity = key
+            .requests_per_minute
+            .unwrap_or(self.default_requests_per_minute.load(Ordering::Relaxed))
+            .max(1);
+        let mut buckets = self.rate_buckets.write().await;
+        buckets
+            .entry(key.id.clone())
+            .or_insert_with(|| TokenBucket::full(capacity, now))
+            .try_take(capacity, now)
+    }
+
+    /// The per-key buckets with their tokens now, for `/api/limits`.
+    pub async fn rate_buckets(&self) -> Vec<BucketState> {
+        let now = Instant::now();
+        let keys = self.api_keys.read().await;
+        let buckets = self.rate_buckets.read().await;
+        let mut states: Vec<BucketState> = buckets
+            .iter()
+            .map(|(id, bucket)| {
+                let capacity = keys
+                    .iter()
+                    .find(|k| &k.id == id)
+                    .and_then(|k| k.requests_per_minute)
+                    .unwrap_or(self.default_requests_per_minute.load(Ordering::Relaxed))
+                    .max(1);
+                BucketState {
+                    subject: format!("key:{}", id),
+                    class: Class::General,
+                    capacity,
+                    tokens: bucket.available(capacity, now),
+                }
+            })
+            .collect();
+        states.sort_by(|a, b| a.subject.cmp(&b.subject));
+        states
+    }
+
+    /// Whether `key` may touch `resource` (e.g. `stream:orders`).
+    ///
+    /// Admin keys and keys without resource scopes can access everything.
+    pub fn can_access(&self, key: &ApiKey, resource: &str) -> bool {
+        if key.scopes.contains(&Scope::Admin) || key.resource_scopes.is_empty() {
+            return true;
+        }
+        key.resource_scopes
+            .iter()
+            .any(|pattern| glob_match(pattern, resource))
+    }
+
     /// Log an audit entry.
     pub async fn log_audit(
         &self,
@@ -179,6 +375,33 @@ fn sha256_hex(input: &str) -> String {
     format!("{:x}", digest)
 }
 
+/// Match `text` against a pattern where `*` matches any run of characters
+/// and `?` matches exactly one.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    let (mut pi, mut ti) = (0, 0);
+    // Position of the last `*` seen and the text index it was tried at.
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            backtrack = Some((pi, ti));
+            pi += 1;
+        } else if let Some((star, matched)) = backtrack {
+            pi = star + 1;
+            ti = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
 fn constant_time_eq(a: &str, b: &str) -> bool {
     if a.len() != b.len() {
         return false;
@@ -189,3 +412,200 @@ fn constant_time_eq(a: &str, b: &str) -> bool {
     }
     diff == 0
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(expires_at: Option<DateTime<Utc>>) -> CreateApiKeyRequest {
+        CreateApiKeyRequest {
+            label: "test".into(),
+            scopes: vec![Scope::Read],
+            resource_scopes: Vec::new(),
+            requests_per_minute: None,
+            expires_at,
+            tls_subject: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stored_key_never_contains_raw_value() {
+        let auth = AuthLayer::new(10, DEFAULT_REQUESTS_PER_MINUTE);
+        let created = auth.create_key(request(None), "test").await.unwrap();
+
+        let stored = serde_json::to_string(&*auth.api_keys.read().await).unwrap();
+        assert!(!stored.contains(&created.key));
+        let listed = serde_json::to_string(&auth.list_keys().await).unwrap();
+        assert!(!listed.contains(&created.key));
+
+        let validated = auth.validate_token(&created.key).await.unwrap();
+        assert_eq!(validated.id, created.api_key.id);
+        assert!(!serde_json::to_string(&validated)
+            .unwrap()
+            .contains(&created.key));
+    }
+
+    #[tokio::test]
+    async fn test_expired_key_is_rejected() {
+        let auth = AuthLayer::new(10, DEFAULT_REQUESTS_PER_MINUTE);
+        let past = Utc::now() - chrono::Duration::seconds(1);
+        let future = Utc::now() + chrono::Duration::hours(1);
+
+        let expired = auth.create_key(request(Some(past)), "test").await.unwrap();
+        let live = auth
+            .create_key(request(Some(future)), "test")
+            .await
+            .unwrap();
+
+        assert!(auth.validate_token(&expired.key).await.is_none());
+        assert!(auth.validate_token(&live.key).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_revoked_key_is_rejected() {
+        let auth = AuthLayer::new(10, DEFAULT_REQUESTS_PER_MINUTE);
+        let created = auth.create_key(request(None), "test").await.unwrap();
+        auth.revoke_key(&created.api_key.id, "test").await.unwrap();
+        assert!(auth.validate_token(&created.key).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_stream_scoped_key_is_denied_other_streams() {
+        let auth = AuthLayer::new(10, DEFAULT_REQUESTS_PER_MINUTE);
+        let created = auth
+            .create_key(
+                CreateApiKeyRequest {
+                    resource_scopes: vec!["stream:orders*".into()],
+                    ..request(None)
+                },
+                "test",
+            )
+            .await
+            .unwrap();
+        let key = created.api_key;
+
+        assert!(auth.can_access(&key, "stream:orders"));
+        assert!(auth.can_access(&key, "stream:orders-eu"));
+        assert!(!auth.can_access(&key, "stream:payments"));
+        assert!(!auth.can_access(&key, "connector:webhook-1"));
+    }
+
+    #[tokio::test]
+    async fn test_admin_and_unscoped_keys_bypass_resource_scopes() {
+        let auth = AuthLayer::new(10, DEFAULT_REQUESTS_PER_MINUTE);
+        let admin = auth
+            .create_key(
+                CreateApiKeyRequest {
+                    scopes: vec![Scope::Admin],
+                    resource_scopes: vec!["stream:orders".into()],
+                    ..request(None)
+                },
+                "test",
+            )
+            .await
+            .unwrap();
+        let open = auth.create_key(request(None), "test").await.unwrap();
+
+        assert!(auth.can_access(&admin.api_key, "stream:payments"));
+        assert!(auth.can_access(&open.api_key, "connector:kafka-1"));
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("connector:webhook-*", "connector:webhook-abc"));
+        assert!(glob_match("stream:*", "stream:"));
+        assert!(glob_match("stream:ord?rs", "stream:orders"));
+        assert!(glob_match("*:orders", "stream:orders"));
+        assert!(glob_match("a*b*c", "axxbyybzc"));
+        assert!(!glob_match("stream:orders", "stream:orders-eu"));
+        assert!(!glob_match("a*b*c", "axxbyy"));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_exhausts_then_recovers() {
+        let auth = AuthLayer::new(10, 60);
+        let key = auth
+            .create_key(
+                CreateApiKeyRequest {
+                    requests_per_minute: Some(3),
+                    ..request(None)
+                },
+                "test",
+            )
+            .await
+            .unwrap()
+            .api_key;
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            assert!(auth.check_rate_limit_at(&key, start).await.is_ok());
+        }
+        let retry_after = auth.check_rate_limit_at(&key, start).await.unwrap_err();
+        assert_eq!(retry_after.as_secs(), 20);
+
+        // One token has refilled after 20s; a full window restores the bucket.
+        let later = start + Duration::from_secs(20);
+        assert!(auth.check_rate_limit_at(&key, later).await.is_ok());
+        assert!(auth.check_rate_limit_at(&key, later).await.is_err());
+
+        let next_window = later + Duration::from_secs(60);
+        for _ in 0..3 {
+            assert!(auth.check_rate_limit_at(&key, next_window).await.is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_is_per_key() {
+        let auth = AuthLayer::new(10, 1);
+        let a = auth
+            .create_key(request(None), "test")
+            .await
+            .unwrap()
+            .api_key;
+        let b = auth
+            .create_key(request(None), "test")
+            .await
+            .unwrap()
+            .api_key;
+        let now = Instant::now();
+
+        assert!(auth.check_rate_limit_at(&a, now).await.is_ok());
+        assert!(auth.check_rate_limit_at(&a, now).await.is_err());
+        assert!(auth.check_rate_limit_at(&b, now).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_tls_subject_binds_one_live_key() {
+        let auth = AuthLayer::new(10, DEFAULT_REQUESTS_PER_MINUTE);
+        let bound = |label: &str| CreateApiKeyRequest {
+            label: label.into(),
+            tls_subject: Some("ci-runner".into()),
+            ..request(None)
+        };
+        // A label is only a name: it never authenticates a certificate.
+        let mut labelled = request(None);
+        labelled.label = "ci-runner".into();
+        auth.create_key(labelled, "test").await.unwrap();
+        assert!(auth.key_for_tls_subject("ci-runner").await.is_none());
+
+        let first = auth.create_key(bound("ci"), "test").await.unwrap().api_key;
+        let err = auth.create_key(bound("ci-2"), "test").await.unwrap_err();
+        assert!(err.contains(&first.id), "{}", err);
+        assert_eq!(
+            auth.key_for_tls_subject("ci-runner").await.unwrap().id,
+            first.id
+        );
+
+        // Revoking frees the CN for a new key.
+        auth.revoke_key(&first.id, "test").await.unwrap();
+        let second = auth
+            .create_key(bound("ci-2"), "test")
+            .await
+            .unwrap()
+            .api_key;
+        assert_eq!(
+            auth.key_for_tls_subject("ci-runner").await.unwrap().id,
+            second.id
+        );
+    }
+}
@@ -0,0 +1,128 @@
+//! # Avro Decoder (optional — requires `--features avro`)
+//!
+//! Decodes single Avro binary datums (no object container, no schema
+//! fingerprint prefix) into JSON, given the writer schema in a connector's
+//! `params["avro_schema"]`. Schema parsing and decoding are `apache-avro`'s.
+//!
+//! Records become objects, enums their symbol, unions the value of the
+//! chosen branch, and `bytes`/`fixed` lowercase hex strings. Other logical
+//! types follow `apache-avro`'s JSON mapping, e.g. dates and timestamps as
+//! numbers and UUIDs as strings.
+
+use super::decode::{DecodeError, Decoder, PayloadFormat};
+use apache_avro::{types::Value as AvroValue, Schema};
+use serde_json::Value;
+
+fn error(message: impl Into<String>) -> DecodeError {
+    DecodeError(message.into())
+}
+
+/// A decoded datum as JSON, with bytes as hex rather than number arrays.
+fn to_json(value: AvroValue) -> Result<Value, DecodeError> {
+    Ok(match value {
+        AvroValue::Bytes(bytes) | AvroValue::Fixed(_, bytes) => Value::String(hex::encode(bytes)),
+        AvroValue::Float(f) => float(f.into()),
+        AvroValue::Double(d) => float(d),
+        AvroValue::Union(_, value) => to_json(*value)?,
+        AvroValue::Array(items) => {
+            Value::Array(items.into_iter().map(to_json).collect::<Result<_, _>>()?)
+        }
+        AvroValue::Map(entries) => Value::Object(
+            entries
+                .into_iter()
+                .map(|(key, value)| Ok((key, to_json(value)?)))
+                .collect::<Result<_, DecodeError>>()?,
+        ),
+        AvroValue::Record(fields) => Value::Object(
+            fields
+                .into_iter()
+                .map(|(name, value)| Ok((name, to_json(value)?)))
+                .collect::<Result<_, DecodeError>>()?,
+        ),
+        other => Value::try_from(other).map_err(|e| error(e.to_string()))?,
+    })
+}
+
+/// A float as a JSON number, or null for NaN and infinities.
+fn float(value: f64) -> Value {
+    serde_json::Number::from_f64(value).map_or(Value::Null, Value::Number)
+}
+
+pub struct AvroDecoder {
+    schema: Schema,
+}
+
+impl AvroDecoder {
+    /// Build a decoder for datums written with the JSON `schema`.
+    pub fn new(schema: &str) -> Result<Self, DecodeError> {
+        Ok(Self {
+            schema: Schema::parse_str(schema)
+                .map_err(|e| error(format!("invalid Avro schema: {}", e)))?,
+        })
+    }
+}
+
+impl Decoder for AvroDecoder {
+    fn format(&self) -> PayloadFormat {
+        PayloadFormat::Avro
+    }
+
+    fn decode(&self, mut bytes: &[u8]) -> Result<Value, DecodeError> {
+        let value = apache_avro::from_avro_datum(&self.schema, &mut bytes, None)
+            .map_err(|e| error(format!("invalid Avro datum: {}", e)))?;
+        if !bytes.is_empty() {
+            return Err(error(format!(
+                "{} trailing bytes after Avro datum",
+                bytes.len()
+            )));
+        }
+        to_json(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: &str = r#"{
+        "type": "record",
+        "name": "Order",
+        "fields": [
+            {"name": "id", "type": "long"},
+            {"name": "sku", "type": "string"},
+            {"name": "tags", "type": {"type": "array", "items": "string"}},
+            {"name": "state", "type": {"type": "enum", "name": "State", "symbols": ["OPEN", "PAID"]}},
+            {"name": "note", "type": ["null", "string"]},
+            {"name": "price", "type": "double"},
+            {"name": "digest", "type": {"type": "fixed", "name": "Digest", "size": 2}}
+        ]
+    }"#;
+
+    #[test]
+    fn test_decodes_a_record() {
+        let decoder = AvroDecoder::new(SCHEMA).unwrap();
+        let mut datum = vec![0x54]; // id 42
+        datum.extend([0x06, b'a', b'b', b'c']); // sku
+        datum.extend([0x02, 0x02, b'x', 0x00]); // tags ["x"]
+        datum.push(0x02); // state PAID
+        datum.push(0x00); // note null
+        datum.extend(2.5f64.to_le_bytes());
+        datum.extend([0xbe, 0xef]); // digest
+
+        assert_eq!(
+            decoder.decode(&datum).unwrap(),
+            serde_json::json!({
+                "id": 42, "sku": "abc", "tags": ["x"], "state": "PAID", "note": null,
+                "price": 2.5, "digest": "beef"
+            })
+        );
+        assert!(decoder.decode(&datum[..datum.len() - 1]).is_err());
+        assert!(decoder.decode(&[datum.as_slice(), &[0]].concat()).is_err());
+    }
+
+    #[test]
+    fn test_rejects_bad_schema() {
+        assert!(AvroDecoder::new(r#"{"type": "record"}"#).is_err());
+        assert!(AvroDecoder::new(r#""Missing""#).is_err());
+    }
+}
@@ -0,0 +1,190 @@
+//! # Reconnect Backoff
+//!
+//! Shared retry loop for connectors that hold a connection to an external
+//! source. [`reconnect_with_backoff`] connects, consumes until the session
+//! ends, and reconnects after an exponentially growing, jittered delay,
+//! keeping the connector's [`ConnectorStatus`] in step:
+//! `Connecting → Connected → Error → Connecting → …`.
+
+use super::ConnectorStatus;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+/// Delays between reconnect attempts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BackoffPolicy {
+    /// Delay after the first failure.
+    pub initial: Duration,
+    /// Cap on any single delay.
+    pub max: Duration,
+    /// Growth per consecutive failure.
+    pub multiplier: f64,
+    /// Fraction of each delay that is randomized away (0 disables jitter),
+    /// so connectors failing together don't retry in lockstep.
+    pub jitter: f64,
+}
+
+impl Default for BackoffPolicy {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_millis(250),
+            max: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.2,
+        }
+    }
+}
+
+impl BackoffPolicy {
+    /// Delay before retrying after `failures` consecutive failures (at
+    /// least 1). `sample` in `[0, 1)` picks the jitter.
+    pub fn delay(&self, failures: u32, sample: f64) -> Duration {
+        let exponent = failures.saturating_sub(1).min(63) as i32;
+        let base = self.initial.as_secs_f64() * self.multiplier.powi(exponent);
+        let capped = base.min(self.max.as_secs_f64());
+        let jitter = self.jitter.clamp(0.0, 1.0) * sample.clamp(0.0, 1.0);
+        Duration::from_secs_f64(capped * (1.0 - jitter))
+    }
+}
+
+/// The connector state [`reconnect_with_backoff`] reads and updates.
+pub struct Supervised<'a> {
+    /// Cleared by `stop()`; the loop exits once it sees it.
+    pub running: &'a AtomicBool,
+    pub status: &'a RwLock<ConnectorStatus>,
+    /// Connection attempts after the first.
+    pub reconnect_attempts: &'a AtomicU64,
+    pub errors_total: &'a AtomicU64,
+}
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Run `consume` over sessions opened by `connect` until the connector is
+/// stopped. A failed connect or a session that ends while still running
+/// counts as an error and is retried after `policy`'s delay; the delay
+/// resets once a connection succeeds.
+pub async fn reconnect_with_backoff<S, C, CF, R, RF>(
+    policy: &BackoffPolicy,
+    supervised: Supervised<'_>,
+    mut connect: C,
+    mut consume: R,
+) where
+    C: FnMut() -> CF,
+    CF: Future<Output = Result<S, BoxError>>,
+    R: FnMut(S) -> RF,
+    RF: Future<Output = Result<(), BoxError>>,
+{
+    let mut failures = 0u32;
+    let mut first = true;
+    while supervised.running.load(Ordering::Relaxed) {
+        if !std::mem::take(&mut first) {
+            supervised
+                .reconnect_attempts
+                .fetch_add(1, Ordering::Relaxed);
+        }
+        *supervised.status.write().await = ConnectorStatus::Connecting;
+
+        let error = match connect().await {
+            Ok(session) => {
+                *supervised.status.write().await = ConnectorStatus::Connected;
+                failures = 0;
+                match consume(session).await {
+                    Ok(()) if !supervised.running.load(Ordering::Relaxed) => break,
+                    Ok(()) => "session ended".into(),
+                    Err(e) => e.to_string(),
+                }
+            }
+            Err(e) => e.to_string(),
+        };
+
+        supervised.errors_total.fetch_add(1, Ordering::Relaxed);
+        *supervised.status.write().await = ConnectorStatus::Error;
+        failures = failures.saturating_add(1);
+        let delay = policy.delay(failures, rand::random());
+        tracing::warn!(
+            "Connector session failed ({}); retrying in {:?}",
+            error,
+            delay
+        );
+        tokio::time::sleep(delay).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use tokio::time::Instant;
+
+    #[test]
+    fn test_delay_doubles_up_to_the_cap() {
+        let policy = BackoffPolicy {
+            initial: Duration::from_millis(100),
+            max: Duration::from_millis(500),
+            multiplier: 2.0,
+            jitter: 0.5,
+        };
+        let delays: Vec<u128> = (1..=5).map(|n| policy.delay(n, 0.0).as_millis()).collect();
+        assert_eq!(delays, [100, 200, 400, 500, 500]);
+        // Jitter only ever shortens a delay, by at most `jitter`.
+        assert_eq!(policy.delay(2, 0.999).as_millis(), 100);
+        assert!(policy.delay(40, 0.5) <= policy.max);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_fails_twice_then_stays_connected() {
+        let policy = BackoffPolicy {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(60),
+            multiplier: 2.0,
+            jitter: 0.0,
+        };
+        let running = AtomicBool::new(true);
+        let status = RwLock::new(ConnectorStatus::Stopped);
+        let (reconnect_attempts, errors_total) = (AtomicU64::new(0), AtomicU64::new(0));
+        let attempts = Mutex::new(Vec::new());
+        let start = Instant::now();
+
+        reconnect_with_backoff(
+            &policy,
+            Supervised {
+                running: &running,
+                status: &status,
+                reconnect_attempts: &reconnect_attempts,
+                errors_total: &errors_total,
+            },
+            || {
+                let mut attempts = attempts.lock().unwrap();
+                attempts.push(start.elapsed());
+                let n = attempts.len();
+                async move {
+                    if n <= 2 {
+                        Err(format!("refused #{}", n).into())
+                    } else {
+                        Ok(n)
+                    }
+                }
+            },
+            |_session| async {
+                assert_eq!(*status.read().await, ConnectorStatus::Connected);
+                running.store(false, Ordering::Relaxed);
+                Ok(())
+            },
+        )
+        .await;
+
+        assert_eq!(
+            *attempts.lock().unwrap(),
+            [
+                Duration::ZERO,
+                Duration::from_secs(1),
+                Duration::from_secs(3)
+            ]
+        );
+        assert_eq!(*status.read().await, ConnectorStatus::Connected);
+        assert_eq!(reconnect_attempts.load(Ordering::Relaxed), 2);
+        assert_eq!(errors_total.load(Ordering::Relaxed), 2);
+    }
+}
@@ -0,0 +1,215 @@
+//! # Payload Decoding
+//!
+//! Every connector turns the bytes it receives into a [`StreamEvent`]
+//! payload through a [`Decoder`], chosen by the connector's
+//! `params["format"]`:
+//!
+//! | `format`   | payload                                          |
+//! |------------|--------------------------------------------------|
+//! | `json`     | the parsed JSON document (default)               |
+//! | `raw_hex`  | the bytes as a lowercase hex string              |
+//! | `base64`   | the bytes as a standard base64 string            |
+//! | `avro`     | the Avro datum as JSON, with `params["avro_schema"]` as the writer schema (`--features avro`) |
+//!
+//! Input a decoder rejects is not dropped: [`decode_or_fallback`] counts it
+//! in the connector's `errors_total` and emits
+//! `{"raw_hex": "...", "decode_error": "..."}` instead.
+//!
+//! [`StreamEvent`]: super::StreamEvent
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Why a decoder rejected its input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeError(pub String);
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// The wire formats a connector can be configured with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PayloadFormat {
+    #[default]
+    Json,
+    RawHex,
+    Base64,
+    Avro,
+}
+
+impl fmt::Display for PayloadFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Json => write!(f, "json"),
+            Self::RawHex => write!(f, "raw_hex"),
+            Self::Base64 => write!(f, "base64"),
+            Self::Avro => write!(f, "avro"),
+        }
+    }
+}
+
+impl std::str::FromStr for PayloadFormat {
+    type Err = DecodeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Self::Json),
+            "raw_hex" => Ok(Self::RawHex),
+            "base64" => Ok(Self::Base64),
+            "avro" => Ok(Self::Avro),
+            other => Err(DecodeError(format!(
+                "unknown payload format '{}' (expected json, raw_hex, base64 or avro)",
+                other
+            ))),
+        }
+    }
+}
+
+/// Turns received bytes into an event payload.
+pub trait Decoder: Send + Sync {
+    fn format(&self) -> PayloadFormat;
+
+    fn decode(&self, bytes: &[u8]) -> Result<serde_json::Value, DecodeError>;
+}
+
+pub struct JsonDecoder;
+
+impl Decoder for JsonDecoder {
+    fn format(&self) -> PayloadFormat {
+        PayloadFormat::Json
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<serde_json::Value, DecodeError> {
+        serde_json::from_slice(bytes).map_err(|e| DecodeError(format!("invalid JSON: {}", e)))
+    }
+}
+
+pub struct RawHexDecoder;
+
+impl Decoder for RawHexDecoder {
+    fn format(&self) -> PayloadFormat {
+        PayloadFormat::RawHex
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<serde_json::Value, DecodeError> {
+        Ok(serde_json::Value::String(to_hex(bytes)))
+    }
+}
+
+pub struct Base64Decoder;
+
+impl Decoder for Base64Decoder {
+    fn format(&self) -> PayloadFormat {
+        PayloadFormat::Base64
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<serde_json::Value, DecodeError> {
+        use base64::Engine;
+        Ok(serde_json::Value::String(
+            base64::engine::general_purpose::STANDARD.encode(bytes),
+        ))
+    }
+}
+
+/// Build the decoder a connector's `params` ask for. Unknown formats, and
+/// `avro` without a valid `avro_schema` (or without `--features avro`),
+/// are errors so a misconfigured connector is refused at creation.
+pub fn decoder_from_params(
+    params: &HashMap<String, String>,
+) -> Result<Box<dyn Decoder>, DecodeError> {
+    let format = match params.get("format") {
+        Some(format) => format.parse()?,
+        None => PayloadFormat::default(),
+    };
+    match format {
+        PayloadFormat::Json => Ok(Box::new(JsonDecoder)),
+        PayloadFormat::RawHex => Ok(Box::new(RawHexDecoder)),
+        PayloadFormat::Base64 => Ok(Box::new(Base64Decoder)),
+        #[cfg(feature = "avro")]
+        PayloadFormat::Avro => {
+            let schema = params
+                .get("avro_schema")
+                .ok_or_else(|| DecodeError("format 'avro' needs an 'avro_schema' param".into()))?;
+            Ok(Box::new(super::avro::AvroDecoder::new(schema)?))
+        }
+        #[cfg(not(feature = "avro"))]
+        PayloadFormat::Avro => Err(DecodeError(
+            "Avro support not compiled. Rebuild with --features avro".into(),
+        )),
+    }
+}
+
+/// Decode `bytes`, or on failure count it in `errors_total` and keep the
+/// raw bytes (as hex) alongside the reason.
+pub fn decode_or_fallback(
+    decoder: &dyn Decoder,
+    bytes: &[u8],
+    errors_total: &AtomicU64,
+) -> serde_json::Value {
+    decoder
+        .decode(bytes)
+        .unwrap_or_else(|e| fallback(bytes, &e, errors_total))
+}
+
+/// The payload kept for `bytes` a decoder rejected with `error`, counted
+/// in `errors_total`.
+pub fn fallback(bytes: &[u8], error: &DecodeError, errors_total: &AtomicU64) -> serde_json::Value {
+    errors_total.fetch_add(1, Ordering::Relaxed);
+    tracing::debug!("Undecodable payload kept as hex: {}", error);
+    serde_json::json!({
+        "raw_hex": to_hex(bytes),
+        "decode_error": error.to_string(),
+    })
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decoder(format: &str) -> Box<dyn Decoder> {
+        decoder_from_params(&HashMap::from([("format".into(), format.into())])).unwrap()
+    }
+
+    #[test]
+    fn test_each_format_decodes() {
+        let json = decoder_from_params(&HashMap::new()).unwrap();
+        assert_eq!(json.format(), PayloadFormat::Json);
+        assert_eq!(
+            json.decode(br#"{"a": [1, 2]}"#).unwrap(),
+            serde_json::json!({ "a": [1, 2] })
+        );
+        assert_eq!(
+            decoder("raw_hex").decode(&[0x00, 0xab, 0x10]).unwrap(),
+            "00ab10"
+        );
+        assert_eq!(decoder("base64").decode(b"hi!").unwrap(), "aGkh");
+        assert!(decoder_from_params(&HashMap::from([("format".into(), "xml".into())])).is_err());
+    }
+
+    #[test]
+    fn test_malformed_input_falls_back_to_hex() {
+        let errors = AtomicU64::new(0);
+        let payload = decode_or_fallback(&JsonDecoder, b"{\"a\":", &errors);
+        assert_eq!(errors.load(Ordering::Relaxed), 1);
+        assert_eq!(payload["raw_hex"], "7b2261223a");
+        assert!(payload["decode_error"]
+            .as_str()
+            .unwrap()
+            .starts_with("invalid JSON"));
+
+        decode_or_fallback(&JsonDecoder, b"true", &errors);
+        assert_eq!(errors.load(Ordering::Relaxed), 1);
+    }
+}
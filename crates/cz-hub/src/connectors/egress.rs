@@ -0,0 +1,151 @@
+//! # Egress
+//!
+//! How a [`StreamEvent`] leaves the hub through a producing connector
+//! (Kafka, NATS) — the write side used by pipeline `Sink` nodes.
+//!
+//! Every producer sends the same [`OutboundMessage`]:
+//!
+//! - the payload is the event's JSON payload, serialized;
+//! - the key is the payload value at `params["key_field"]` (a JSON
+//!   pointer such as `/order/id`), else the event's `key` metadata, else
+//!   none; string values are used as-is, others as their JSON text (NATS
+//!   has no message key and sends it in the `cz-key` header);
+//! - the headers are the event's metadata plus `cz-event-id`,
+//!   `cz-connector-id`, `cz-stream`, `cz-sequence` and `cz-timestamp`.
+//!
+//! The destination is `params["produce_topic"]` (Kafka) or
+//! `params["produce_subject"]` (NATS), defaulting to the topic/subject the
+//! connector consumes.
+
+use super::StreamEvent;
+use std::collections::{BTreeMap, HashMap};
+
+/// A message ready to publish.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutboundMessage {
+    /// Topic or subject.
+    pub destination: String,
+    pub key: Option<String>,
+    pub payload: Vec<u8>,
+    pub headers: BTreeMap<String, String>,
+}
+
+/// Where and how a connector produces, from its `params`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EgressConfig {
+    pub destination: String,
+    /// JSON pointer into the payload for the message key.
+    pub key_field: Option<String>,
+}
+
+impl EgressConfig {
+    /// Read the destination from `params[destination_param]`, falling back
+    /// to `default_destination`.
+    pub fn from_params(
+        params: &HashMap<String, String>,
+        destination_param: &str,
+        default_destination: &str,
+    ) -> Self {
+        Self {
+            destination: params
+                .get(destination_param)
+                .cloned()
+                .unwrap_or_else(|| default_destination.to_string()),
+            key_field: params.get("key_field").cloned(),
+        }
+    }
+
+    /// The message `event` is published as.
+    pub fn outbound(&self, event: &StreamEvent) -> OutboundMessage {
+        let key = self
+            .key_field
+            .as_deref()
+            .and_then(|pointer| event.payload.pointer(pointer))
+            .map(|value| match value {
+                serde_json::Value::String(s) => s.clone(),
+                other => other.to_string(),
+            })
+            .or_else(|| event.metadata.get("key").cloned());
+
+        let mut headers: BTreeMap<String, String> = event
+            .metadata
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        headers.insert("cz-event-id".into(), event.id.clone());
+        headers.insert("cz-connector-id".into(), event.connector_id.clone());
+        headers.insert("cz-stream".into(), event.stream.clone());
+        headers.insert("cz-sequence".into(), event.sequence.to_string());
+        headers.insert("cz-timestamp".into(), event.timestamp.clone());
+
+        OutboundMessage {
+            destination: self.destination.clone(),
+            key,
+            // Serializing a `Value` cannot fail.
+            payload: serde_json::to_vec(&event.payload).unwrap_or_default(),
+            headers,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event() -> StreamEvent {
+        StreamEvent {
+            id: "webhook-1-7".into(),
+            connector_id: "webhook-1".into(),
+            stream: "orders".into(),
+            sequence: 7,
+            timestamp: "2026-01-01T00:00:00+00:00".into(),
+            payload: serde_json::json!({ "order": { "id": "o-42", "qty": 3 } }),
+            metadata: HashMap::from([
+                ("key".into(), "from-metadata".into()),
+                ("traceparent".into(), "00-abc-def-01".into()),
+            ]),
+        }
+    }
+
+    #[test]
+    fn test_event_maps_to_outbound_message() {
+        let params = HashMap::from([
+            ("topic".into(), "in".into()),
+            ("produce_topic".into(), "out".into()),
+            ("key_field".into(), "/order/id".into()),
+        ]);
+        let config = EgressConfig::from_params(&params, "produce_topic", "in");
+        let message = config.outbound(&event());
+
+        assert_eq!(message.destination, "out");
+        assert_eq!(message.key.as_deref(), Some("o-42"));
+        let payload: serde_json::Value = serde_json::from_slice(&message.payload).unwrap();
+        assert_eq!(payload, event().payload);
+        assert_eq!(message.headers["traceparent"], "00-abc-def-01");
+        assert_eq!(message.headers["cz-event-id"], "webhook-1-7");
+        assert_eq!(message.headers["cz-sequence"], "7");
+
+        // Non-string keys use their JSON text; a missing field falls back
+        // to the `key` metadata, and then to no key.
+        let by_qty = EgressConfig {
+            key_field: Some("/order/qty".into()),
+            ..config.clone()
+        };
+        assert_eq!(by_qty.outbound(&event()).key.as_deref(), Some("3"));
+        let missing = EgressConfig {
+            key_field: Some("/customer".into()),
+            ..config.clone()
+        };
+        assert_eq!(
+            missing.outbound(&event()).key.as_deref(),
+            Some("from-metadata")
+        );
+        let mut bare = event();
+        bare.metadata.clear();
+        assert_eq!(missing.outbound(&bare).key, None);
+        assert_eq!(
+            EgressConfig::from_params(&HashMap::new(), "produce_topic", "in").destination,
+            "in"
+        );
+    }
+}
@@ -4,12 +4,23 @@
 //! it with external data sources under the same abstraction.
 
 use super::{
-    ConnectorInfo, ConnectorKind, ConnectorMetrics, ConnectorStatus, StreamConnector, StreamEvent,
+    ConnectorInfo, ConnectorKind, ConnectorMetrics, ConnectorStatus, Health, HealthReport,
+    StreamConnector, StreamEvent,
 };
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use tokio::sync::{broadcast, RwLock};
 
+/// Stream name of the journal at `path`: `journal:<file name>`.
+pub fn stream_name(path: &Path) -> String {
+    format!(
+        "journal:{}",
+        path.file_name()
+            .map(|f| f.to_string_lossy().to_string())
+            .unwrap_or_else(|| "unknown".into())
+    )
+}
+
 pub struct JournalConnector {
     id: String,
     name: String,
@@ -25,12 +36,7 @@ pub struct JournalConnector {
 impl JournalConnector {
     pub fn new(path: PathBuf) -> Self {
         let (tx, _) = broadcast::channel(2048);
-        let name = format!(
-            "journal:{}",
-            path.file_name()
-                .map(|f| f.to_string_lossy().to_string())
-                .unwrap_or_else(|| "unknown".into())
-        );
+        let name = stream_name(&path);
         let id = format!("journal-{}", uuid::Uuid::new_v4().as_simple());
 
         Self {
@@ -45,7 +51,6 @@ impl JournalConnector {
             created_at: chrono::Utc::now().to_rfc3339(),
         }
     }
-
 }
 
 #[async_trait::async_trait]
@@ -76,6 +81,28 @@ impl StreamConnector for JournalConnector {
         Ok(())
     }
 
+    /// The journal is local and passive: live while running, and an idle
+    /// ring is not a fault.
+    async fn health_check(&self) -> HealthReport {
+        let live = self.running.load(Ordering::Relaxed);
+        HealthReport {
+            health: if live {
+                Health::Healthy
+            } else {
+                Health::Unhealthy
+            },
+            live,
+            last_event_at: None,
+            last_event_age_secs: None,
+            error_rate: 0.0,
+            reasons: if live {
+                Vec::new()
+            } else {
+                vec!["connector is stopped".into()]
+            },
+        }
+    }
+
     fn subscribe(&self) -> broadcast::Receiver<StreamEvent> {
         self.tx.subscribe()
     }
@@ -2,14 +2,28 @@
 //!
 //! Consumes from a Kafka topic and emits events as [`StreamEvent`]s.
 //! Uses `rdkafka` under the hood. Supports consumer group offsets,
-//! auto-reconnection, and configurable deserialization.
+//! auto-reconnection, and configurable deserialization. Also publishes
+//! events to `produce_topic` for pipeline sinks.
 
+use super::backoff::{reconnect_with_backoff, BackoffPolicy, Supervised};
+use super::decode::{self, Decoder};
+use super::egress::EgressConfig;
 use super::{
-    ConnectorInfo, ConnectorKind, ConnectorMetrics, ConnectorStatus, StreamConnector, StreamEvent,
+    probe_tcp, ConnectorInfo, ConnectorKind, ConnectorMetrics, ConnectorStatus, HealthReport,
+    LastEvent, StreamConnector, StreamEvent,
 };
+use rdkafka::message::{Header, OwnedHeaders};
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::ClientConfig;
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use tokio::sync::{broadcast, RwLock};
+use std::time::Duration;
+use tokio::sync::{broadcast, OnceCell, RwLock};
+
+/// How long a health check waits for the upstream to accept a connection.
+const HEALTH_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+/// How long a produced message may wait in the local queue.
+const PRODUCE_TIMEOUT: Duration = Duration::from_secs(5);
 
 pub struct KafkaConnector {
     id: String,
@@ -22,6 +36,14 @@ pub struct KafkaConnector {
     events_total: AtomicU64,
     bytes_total: AtomicU64,
     errors_total: AtomicU64,
+    reconnect_attempts: AtomicU64,
+    produced_total: AtomicU64,
+    last_event: LastEvent,
+    backoff: BackoffPolicy,
+    decoder: Box<dyn Decoder>,
+    egress: EgressConfig,
+    /// Producer, created on the first `produce`.
+    producer: OnceCell<FutureProducer>,
     tx: broadcast::Sender<StreamEvent>,
     created_at: String,
 }
@@ -30,6 +52,16 @@ impl KafkaConnector {
     pub fn new(name: String, params: HashMap<String, String>) -> Self {
         let (tx, _) = broadcast::channel(4096);
         let id = format!("kafka-{}", uuid::Uuid::new_v4().as_simple());
+        let decoder = decode::decoder_from_params(&params).unwrap_or_else(|e| {
+            tracing::warn!("Kafka connector '{}': {}; decoding as JSON", name, e);
+            Box::new(decode::JsonDecoder)
+        });
+
+        let topic = params
+            .get("topic")
+            .cloned()
+            .unwrap_or_else(|| "events".into());
+        let egress = EgressConfig::from_params(&params, "produce_topic", &topic);
 
         Self {
             id,
@@ -38,10 +70,7 @@ impl KafkaConnector {
                 .get("brokers")
                 .cloned()
                 .unwrap_or_else(|| "localhost:9092".into()),
-            topic: params
-                .get("topic")
-                .cloned()
-                .unwrap_or_else(|| "events".into()),
+            topic,
             group_id: params
                 .get("group_id")
                 .cloned()
@@ -51,10 +80,23 @@ impl KafkaConnector {
             events_total: AtomicU64::new(0),
             bytes_total: AtomicU64::new(0),
             errors_total: AtomicU64::new(0),
+            reconnect_attempts: AtomicU64::new(0),
+            produced_total: AtomicU64::new(0),
+            last_event: LastEvent::default(),
+            backoff: BackoffPolicy::default(),
+            decoder,
+            egress,
+            producer: OnceCell::new(),
             tx,
             created_at: chrono::Utc::now().to_rfc3339(),
         }
     }
+
+    /// Use `id` instead of a generated one, e.g. for a restored connector.
+    pub fn with_id(mut self, id: String) -> Self {
+        self.id = id;
+        self
+    }
 }
 
 #[async_trait::async_trait]
@@ -64,16 +106,14 @@ impl StreamConnector for KafkaConnector {
     }
 
     fn status(&self) -> ConnectorStatus {
-        if self.running.load(Ordering::Relaxed) {
-            ConnectorStatus::Connected
-        } else {
-            ConnectorStatus::Stopped
-        }
+        // Only held across a store, so a busy lock means a transition.
+        self.status
+            .try_read()
+            .map_or(ConnectorStatus::Connecting, |status| *status)
     }
 
     async fn start(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         self.running.store(true, Ordering::Relaxed);
-        *self.status.write().await = ConnectorStatus::Connecting;
 
         tracing::info!(
             "Kafka connector '{}' connecting to {} topic '{}'",
@@ -82,22 +122,37 @@ impl StreamConnector for KafkaConnector {
             self.topic
         );
 
-        // TODO: Replace with actual rdkafka consumer loop
-        // For now, mark as connected after a brief delay to simulate connection
-        *self.status.write().await = ConnectorStatus::Connected;
-
-        // Placeholder: in production, this would be a StreamConsumer loop:
-        // let consumer: StreamConsumer = ClientConfig::new()
-        //     .set("group.id", &self.group_id)
-        //     .set("bootstrap.servers", &self.brokers)
-        //     .create()?;
-        // consumer.subscribe(&[&self.topic])?;
-        // while self.running.load(Ordering::Relaxed) {
-        //     if let Some(msg) = consumer.recv().await? {
-        //         let event = StreamEvent { ... };
-        //         self.tx.send(event);
-        //     }
-        // }
+        reconnect_with_backoff(
+            &self.backoff,
+            Supervised {
+                running: &self.running,
+                status: &self.status,
+                reconnect_attempts: &self.reconnect_attempts,
+                errors_total: &self.errors_total,
+            },
+            // TODO: Replace with an actual rdkafka consumer:
+            // let consumer: StreamConsumer = ClientConfig::new()
+            //     .set("group.id", &self.group_id)
+            //     .set("bootstrap.servers", &self.brokers)
+            //     .create()?;
+            // consumer.subscribe(&[&self.topic])?;
+            || async { Ok(()) },
+            // Placeholder: in production, this would be the StreamConsumer loop:
+            // while self.running.load(Ordering::Relaxed) {
+            //     let msg = consumer.recv().await?;
+            //     let payload = decode::decode_or_fallback(&*self.decoder, msg.payload().unwrap_or_default(), &self.errors_total);
+            //     let event = StreamEvent { payload, ... };
+            //     self.last_event.touch();
+            //     self.tx.send(event);
+            // }
+            |()| async {
+                while self.running.load(Ordering::Relaxed) {
+                    tokio::time::sleep(Duration::from_millis(250)).await;
+                }
+                Ok(())
+            },
+        )
+        .await;
 
         Ok(())
     }
@@ -108,6 +163,58 @@ impl StreamConnector for KafkaConnector {
         Ok(())
     }
 
+    /// Degraded/unhealthy per the metrics, and unhealthy when no TCP
+    /// connection can be opened to the upstream.
+    async fn health_check(&self) -> HealthReport {
+        let brokers = self.brokers.split(',').map(str::trim);
+        let report = HealthReport::from_metrics(self.status(), &self.metrics(), chrono::Utc::now());
+        match probe_tcp(brokers, HEALTH_PROBE_TIMEOUT).await {
+            Ok(()) => report,
+            Err(e) => report.unreachable(format!("no broker reachable ({})", e)),
+        }
+    }
+
+    async fn produce(
+        &self,
+        event: &StreamEvent,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let message = self.egress.outbound(event);
+        let published = async {
+            let producer = self
+                .producer
+                .get_or_try_init(|| async {
+                    ClientConfig::new()
+                        .set("bootstrap.servers", &self.brokers)
+                        .create::<FutureProducer>()
+                })
+                .await?;
+            let mut headers = OwnedHeaders::new();
+            for (name, value) in &message.headers {
+                headers = headers.insert(Header {
+                    key: name,
+                    value: Some(value.as_str()),
+                });
+            }
+            let mut record = FutureRecord::to(&message.destination)
+                .payload(&message.payload)
+                .headers(headers);
+            if let Some(key) = &message.key {
+                record = record.key(key.as_str());
+            }
+            producer
+                .send(record, PRODUCE_TIMEOUT)
+                .await
+                .map_err(|(e, _)| e)?;
+            Ok::<_, Box<dyn std::error::Error + Send + Sync>>(())
+        }
+        .await;
+        match &published {
+            Ok(()) => self.produced_total.fetch_add(1, Ordering::Relaxed),
+            Err(_) => self.errors_total.fetch_add(1, Ordering::Relaxed),
+        };
+        published
+    }
+
     fn subscribe(&self) -> broadcast::Receiver<StreamEvent> {
         self.tx.subscribe()
     }
@@ -117,6 +224,9 @@ impl StreamConnector for KafkaConnector {
             events_total: self.events_total.load(Ordering::Relaxed),
             bytes_total: self.bytes_total.load(Ordering::Relaxed),
             errors_total: self.errors_total.load(Ordering::Relaxed),
+            reconnect_attempts: self.reconnect_attempts.load(Ordering::Relaxed),
+            produced_total: self.produced_total.load(Ordering::Relaxed),
+            last_event_at: self.last_event.rfc3339(),
             ..Default::default()
         }
     }
@@ -131,9 +241,68 @@ impl StreamConnector for KafkaConnector {
                 "brokers": self.brokers,
                 "topic": self.topic,
                 "group_id": self.group_id,
+                "produce_topic": self.egress.destination,
+                "key_field": self.egress.key_field,
+                "format": self.decoder.format(),
             }),
             metrics: self.metrics(),
             created_at: self.created_at.clone(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rdkafka::consumer::{Consumer, StreamConsumer};
+    use rdkafka::message::Headers;
+    use rdkafka::Message;
+
+    /// Needs a Kafka broker; run with
+    /// `KAFKA_BROKERS=localhost:9092 cargo test --features kafka -- --ignored`.
+    #[tokio::test]
+    #[ignore = "needs a Kafka broker at KAFKA_BROKERS"]
+    async fn test_produce_publishes_payload_and_key() {
+        let brokers = std::env::var("KAFKA_BROKERS").unwrap_or_else(|_| "localhost:9092".into());
+        let topic = format!("cz-hub-test-{}", uuid::Uuid::new_v4().as_simple());
+        let connector = KafkaConnector::new(
+            "sink".into(),
+            HashMap::from([
+                ("brokers".into(), brokers.clone()),
+                ("produce_topic".into(), topic.clone()),
+                ("key_field".into(), "/order".into()),
+            ]),
+        );
+        let event = StreamEvent {
+            id: "e-1".into(),
+            connector_id: "webhook-1".into(),
+            stream: "orders".into(),
+            sequence: 1,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            payload: serde_json::json!({ "order": "o-42" }),
+            metadata: HashMap::new(),
+        };
+        connector.produce(&event).await.unwrap();
+
+        let consumer: StreamConsumer = ClientConfig::new()
+            .set("bootstrap.servers", &brokers)
+            .set("group.id", &topic)
+            .set("auto.offset.reset", "earliest")
+            .create()
+            .unwrap();
+        consumer.subscribe(&[&topic]).unwrap();
+        let message = tokio::time::timeout(Duration::from_secs(10), consumer.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        let payload: serde_json::Value =
+            serde_json::from_slice(message.payload().unwrap()).unwrap();
+        assert_eq!(payload, event.payload);
+        assert_eq!(message.key(), Some("o-42".as_bytes()));
+        let headers = message.headers().unwrap();
+        assert!(headers
+            .iter()
+            .any(|h| h.key == "cz-event-id" && h.value == Some("e-1".as_bytes())));
+        assert_eq!(connector.metrics().produced_total, 1);
+    }
+}
@@ -3,9 +3,17 @@
 //! The universal abstraction for all data sources flowing through the
 //! LACRIMOSA Control Center. Every data stream — internal journal,
 //! Kafka topic, NATS subject, webhook endpoint — implements [`StreamConnector`].
+//! Kafka and NATS connectors can also publish events back out (see
+//! [`egress`]).
 
+#[cfg(feature = "avro")]
+pub mod avro;
+pub mod backoff;
+pub mod decode;
+pub mod egress;
 pub mod journal;
 pub mod registry;
+pub mod signature;
 pub mod webhook;
 
 #[cfg(feature = "kafka")]
@@ -13,8 +21,11 @@ pub mod kafka;
 #[cfg(feature = "nats")]
 pub mod nats;
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::Duration;
 use tokio::sync::broadcast;
 
 // =============================================================================
@@ -59,7 +70,153 @@ pub struct ConnectorMetrics {
     pub bytes_total: u64,
     pub bytes_per_sec: f64,
     pub errors_total: u64,
+    /// Events dropped because the registry fell behind this connector's
+    /// channel; filled in by the registry, not the connector.
+    #[serde(default)]
+    pub lagged_total: u64,
+    /// Connection attempts after the first, for connectors that reconnect.
+    #[serde(default)]
+    pub reconnect_attempts: u64,
+    /// Events published through [`StreamConnector::produce`].
+    #[serde(default)]
+    pub produced_total: u64,
+    pub last_event_at: Option<String>,
+}
+
+/// When a connector last emitted an event, shared lock-free with the
+/// ingest path.
+#[derive(Debug, Default)]
+pub struct LastEvent {
+    /// Unix milliseconds; 0 until the first event.
+    millis: AtomicI64,
+}
+
+impl LastEvent {
+    pub fn touch(&self) {
+        self.millis
+            .store(Utc::now().timestamp_millis(), Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> Option<DateTime<Utc>> {
+        match self.millis.load(Ordering::Relaxed) {
+            0 => None,
+            millis => DateTime::from_timestamp_millis(millis),
+        }
+    }
+
+    /// For [`ConnectorMetrics::last_event_at`].
+    pub fn rfc3339(&self) -> Option<String> {
+        self.get().map(|at| at.to_rfc3339())
+    }
+}
+
+/// Connector health, worst last.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Health {
+    Healthy,
+    /// Running, but idle for too long or failing too often.
+    Degraded,
+    /// Not running, or its upstream is unreachable.
+    Unhealthy,
+}
+
+/// A connector idle for longer than this is degraded.
+pub const STALE_AFTER: Duration = Duration::from_secs(300);
+/// Share of failed events above which a connector is degraded.
+pub const MAX_ERROR_RATE: f64 = 0.1;
+
+/// What [`StreamConnector::health_check`] found.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HealthReport {
+    pub health: Health,
+    /// Whether the connector is running and its upstream answers.
+    pub live: bool,
     pub last_event_at: Option<String>,
+    pub last_event_age_secs: Option<f64>,
+    /// Errors per event seen (errors + events), 0 before any.
+    pub error_rate: f64,
+    /// Why the connector is not healthy.
+    pub reasons: Vec<String>,
+}
+
+impl HealthReport {
+    /// Health derived from a connector's status and metrics as of `now`:
+    /// unhealthy unless connected, degraded when idle past
+    /// [`STALE_AFTER`] or erroring above [`MAX_ERROR_RATE`].
+    pub fn from_metrics(
+        status: ConnectorStatus,
+        metrics: &ConnectorMetrics,
+        now: DateTime<Utc>,
+    ) -> Self {
+        let last_event = metrics
+            .last_event_at
+            .as_deref()
+            .and_then(|at| DateTime::parse_from_rfc3339(at).ok());
+        let age = last_event.map(|at| (now - at.with_timezone(&Utc)).to_std().unwrap_or_default());
+        let seen = metrics.events_total + metrics.errors_total;
+        let error_rate = if seen == 0 {
+            0.0
+        } else {
+            metrics.errors_total as f64 / seen as f64
+        };
+
+        let live = status == ConnectorStatus::Connected;
+        let mut health = Health::Healthy;
+        let mut reasons = Vec::new();
+        if !live {
+            health = Health::Unhealthy;
+            reasons.push(format!("connector is {:?}", status).to_lowercase());
+        }
+        match age {
+            None => reasons.push("no events received yet".into()),
+            Some(age) if age > STALE_AFTER => {
+                reasons.push(format!("no events for {}s", age.as_secs()))
+            }
+            Some(_) => {}
+        }
+        if error_rate > MAX_ERROR_RATE {
+            reasons.push(format!("{:.0}% of events failed", error_rate * 100.0));
+        }
+        if !reasons.is_empty() {
+            health = health.max(Health::Degraded);
+        }
+
+        Self {
+            health,
+            live,
+            last_event_at: metrics.last_event_at.clone(),
+            last_event_age_secs: age.map(|age| age.as_secs_f64()),
+            error_rate,
+            reasons,
+        }
+    }
+
+    /// Mark the report unhealthy because the upstream did not answer.
+    pub fn unreachable(mut self, reason: String) -> Self {
+        self.health = Health::Unhealthy;
+        self.live = false;
+        self.reasons.insert(0, reason);
+        self
+    }
+}
+
+/// Open a TCP connection to the first of `addrs` that accepts one within
+/// `timeout` — a cheap liveness probe for brokers and servers.
+#[cfg(any(feature = "kafka", feature = "nats"))]
+pub(crate) async fn probe_tcp<'a>(
+    addrs: impl IntoIterator<Item = &'a str>,
+    timeout: Duration,
+) -> Result<(), String> {
+    let mut last_error = "no address configured".to_string();
+    for addr in addrs {
+        match tokio::time::timeout(timeout, tokio::net::TcpStream::connect(addr)).await {
+            Ok(Ok(_)) => return Ok(()),
+            Ok(Err(e)) => last_error = format!("{}: {}", addr, e),
+            Err(_) => last_error = format!("{}: timed out after {:?}", addr, timeout),
+        }
+    }
+    Err(last_error)
 }
 
 /// Connector type descriptor — used for the creation wizard.
@@ -98,11 +255,16 @@ pub struct ConnectorInfo {
 }
 
 /// Configuration for creating a new connector.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConnectorConfig {
+    /// Id to give the connector; assigned when absent. Saved configs carry
+    /// the assigned id so a restored connector keeps it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
     pub name: String,
     pub kind: ConnectorKind,
-    /// Connector-specific configuration (brokers, topic, subject, etc).
+    /// Connector-specific configuration (brokers, topic, subject, etc),
+    /// including the payload `format` (see [`decode`]).
     #[serde(default)]
     pub params: HashMap<String, String>,
 }
@@ -134,12 +296,74 @@ pub trait StreamConnector: Send + Sync {
     /// Get serializable info.
     fn info(&self) -> ConnectorInfo;
 
-    /// Ingest a payload (for push-based connectors like Webhooks).
+    /// Check whether the connector is actually working, not just started.
+    /// By default this is derived from its status, event age and error
+    /// rate; connectors with an upstream also probe it.
+    async fn health_check(&self) -> HealthReport {
+        HealthReport::from_metrics(self.status(), &self.metrics(), Utc::now())
+    }
+
+    /// Ingest a request body (for push-based connectors like Webhooks),
+    /// decoded per the connector's payload format.
     async fn ingest(
         &self,
-        _payload: serde_json::Value,
+        _body: &[u8],
         _headers: HashMap<String, String>,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         Err("Ingestion not supported by this connector".into())
     }
+
+    /// Whether [`ingest`](Self::ingest) checks a provider signature on every
+    /// body. A delivery it accepts is then authenticated by the signature,
+    /// so the ingest route asks no API key for it.
+    fn verifies_signatures(&self) -> bool {
+        false
+    }
+
+    /// Publish an event (for connectors that can write out, like Kafka and
+    /// NATS), mapped per [`egress`].
+    async fn produce(
+        &self,
+        _event: &StreamEvent,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Err("Producing not supported by this connector".into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics(events: u64, errors: u64, last_event_at: Option<DateTime<Utc>>) -> ConnectorMetrics {
+        ConnectorMetrics {
+            events_total: events,
+            errors_total: errors,
+            last_event_at: last_event_at.map(|at| at.to_rfc3339()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_idle_connector_is_degraded() {
+        let now = Utc::now();
+        let fresh = metrics(10, 0, Some(now - chrono::Duration::seconds(5)));
+        let report = HealthReport::from_metrics(ConnectorStatus::Connected, &fresh, now);
+        assert_eq!(report.health, Health::Healthy);
+        assert_eq!(report.last_event_age_secs, Some(5.0));
+
+        let idle = metrics(10, 0, Some(now - chrono::Duration::minutes(10)));
+        let report = HealthReport::from_metrics(ConnectorStatus::Connected, &idle, now);
+        assert_eq!(report.health, Health::Degraded);
+        assert!(report.live);
+        assert_eq!(report.reasons, ["no events for 600s"]);
+
+        let failing = metrics(5, 5, Some(now));
+        let report = HealthReport::from_metrics(ConnectorStatus::Connected, &failing, now);
+        assert_eq!(report.health, Health::Degraded);
+        assert_eq!(report.error_rate, 0.5);
+
+        let report = HealthReport::from_metrics(ConnectorStatus::Error, &fresh, now);
+        assert_eq!(report.health, Health::Unhealthy);
+        assert!(!report.live);
+    }
 }
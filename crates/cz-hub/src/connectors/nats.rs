@@ -1,14 +1,27 @@
 //! # NATS Connector (optional — requires `--features nats`)
 //!
 //! Subscribes to a NATS subject (or JetStream consumer) and emits events
-//! as [`StreamEvent`]s.
+//! as [`StreamEvent`]s, and publishes events to `produce_subject` for
+//! pipeline sinks. NATS messages have no key, so the key travels in the
+//! `cz-key` header.
 
+use super::backoff::{reconnect_with_backoff, BackoffPolicy, Supervised};
+use super::decode::{self, Decoder};
+use super::egress::EgressConfig;
 use super::{
-    ConnectorInfo, ConnectorKind, ConnectorMetrics, ConnectorStatus, StreamConnector, StreamEvent,
+    probe_tcp, ConnectorInfo, ConnectorKind, ConnectorMetrics, ConnectorStatus, HealthReport,
+    LastEvent, StreamConnector, StreamEvent,
 };
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use tokio::sync::{broadcast, RwLock};
+use std::time::Duration;
+use tokio::sync::{broadcast, OnceCell, RwLock};
+
+/// Header carrying the message key of produced events.
+pub const KEY_HEADER: &str = "cz-key";
+
+/// How long a health check waits for the upstream to accept a connection.
+const HEALTH_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
 
 pub struct NatsConnector {
     id: String,
@@ -20,6 +33,14 @@ pub struct NatsConnector {
     events_total: AtomicU64,
     bytes_total: AtomicU64,
     errors_total: AtomicU64,
+    reconnect_attempts: AtomicU64,
+    produced_total: AtomicU64,
+    last_event: LastEvent,
+    backoff: BackoffPolicy,
+    decoder: Box<dyn Decoder>,
+    egress: EgressConfig,
+    /// Publishing client, connected on the first `produce`.
+    producer: OnceCell<async_nats::Client>,
     tx: broadcast::Sender<StreamEvent>,
     created_at: String,
 }
@@ -28,6 +49,13 @@ impl NatsConnector {
     pub fn new(name: String, params: HashMap<String, String>) -> Self {
         let (tx, _) = broadcast::channel(4096);
         let id = format!("nats-{}", uuid::Uuid::new_v4().as_simple());
+        let decoder = decode::decoder_from_params(&params).unwrap_or_else(|e| {
+            tracing::warn!("NATS connector '{}': {}; decoding as JSON", name, e);
+            Box::new(decode::JsonDecoder)
+        });
+
+        let subject = params.get("subject").cloned().unwrap_or_else(|| ">".into());
+        let egress = EgressConfig::from_params(&params, "produce_subject", &subject);
 
         Self {
             id,
@@ -36,16 +64,29 @@ impl NatsConnector {
                 .get("url")
                 .cloned()
                 .unwrap_or_else(|| "nats://localhost:4222".into()),
-            subject: params.get("subject").cloned().unwrap_or_else(|| ">".into()),
+            subject,
             status: RwLock::new(ConnectorStatus::Stopped),
             running: AtomicBool::new(false),
             events_total: AtomicU64::new(0),
             bytes_total: AtomicU64::new(0),
             errors_total: AtomicU64::new(0),
+            reconnect_attempts: AtomicU64::new(0),
+            produced_total: AtomicU64::new(0),
+            last_event: LastEvent::default(),
+            backoff: BackoffPolicy::default(),
+            decoder,
+            egress,
+            producer: OnceCell::new(),
             tx,
             created_at: chrono::Utc::now().to_rfc3339(),
         }
     }
+
+    /// Use `id` instead of a generated one, e.g. for a restored connector.
+    pub fn with_id(mut self, id: String) -> Self {
+        self.id = id;
+        self
+    }
 }
 
 #[async_trait::async_trait]
@@ -55,16 +96,14 @@ impl StreamConnector for NatsConnector {
     }
 
     fn status(&self) -> ConnectorStatus {
-        if self.running.load(Ordering::Relaxed) {
-            ConnectorStatus::Connected
-        } else {
-            ConnectorStatus::Stopped
-        }
+        // Only held across a store, so a busy lock means a transition.
+        self.status
+            .try_read()
+            .map_or(ConnectorStatus::Connecting, |status| *status)
     }
 
     async fn start(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         self.running.store(true, Ordering::Relaxed);
-        *self.status.write().await = ConnectorStatus::Connecting;
 
         tracing::info!(
             "NATS connector '{}' connecting to {} subject '{}'",
@@ -73,17 +112,34 @@ impl StreamConnector for NatsConnector {
             self.subject
         );
 
-        // TODO: Replace with actual async-nats subscription loop
-        // let client = async_nats::connect(&self.url).await?;
-        // let mut sub = client.subscribe(self.subject.clone()).await?;
-        // while self.running.load(Ordering::Relaxed) {
-        //     if let Some(msg) = sub.next().await {
-        //         let event = StreamEvent { ... };
-        //         self.tx.send(event);
-        //     }
-        // }
-
-        *self.status.write().await = ConnectorStatus::Connected;
+        reconnect_with_backoff(
+            &self.backoff,
+            Supervised {
+                running: &self.running,
+                status: &self.status,
+                reconnect_attempts: &self.reconnect_attempts,
+                errors_total: &self.errors_total,
+            },
+            // TODO: Replace with an actual async-nats subscription:
+            // let client = async_nats::connect(&self.url).await?;
+            // let sub = client.subscribe(self.subject.clone()).await?;
+            || async { Ok(()) },
+            // while self.running.load(Ordering::Relaxed) {
+            //     let msg = sub.next().await.ok_or("subscription closed")?;
+            //     let payload = decode::decode_or_fallback(&*self.decoder, &msg.payload, &self.errors_total);
+            //     let event = StreamEvent { payload, ... };
+            //     self.last_event.touch();
+            //     self.tx.send(event);
+            // }
+            |()| async {
+                while self.running.load(Ordering::Relaxed) {
+                    tokio::time::sleep(Duration::from_millis(250)).await;
+                }
+                Ok(())
+            },
+        )
+        .await;
+
         Ok(())
     }
 
@@ -93,6 +149,47 @@ impl StreamConnector for NatsConnector {
         Ok(())
     }
 
+    /// Degraded/unhealthy per the metrics, and unhealthy when no TCP
+    /// connection can be opened to the upstream.
+    async fn health_check(&self) -> HealthReport {
+        let addr = self.url.split("://").last().unwrap_or(&self.url);
+        let report = HealthReport::from_metrics(self.status(), &self.metrics(), chrono::Utc::now());
+        match probe_tcp([addr], HEALTH_PROBE_TIMEOUT).await {
+            Ok(()) => report,
+            Err(e) => report.unreachable(format!("server unreachable ({})", e)),
+        }
+    }
+
+    async fn produce(
+        &self,
+        event: &StreamEvent,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let message = self.egress.outbound(event);
+        let published = async {
+            let client = self
+                .producer
+                .get_or_try_init(|| async_nats::connect(self.url.as_str()))
+                .await?;
+            let mut headers = async_nats::HeaderMap::new();
+            for (name, value) in &message.headers {
+                headers.insert(name.as_str(), value.as_str());
+            }
+            if let Some(key) = &message.key {
+                headers.insert(KEY_HEADER, key.as_str());
+            }
+            client
+                .publish_with_headers(message.destination, headers, message.payload.into())
+                .await?;
+            Ok::<_, Box<dyn std::error::Error + Send + Sync>>(())
+        }
+        .await;
+        match &published {
+            Ok(()) => self.produced_total.fetch_add(1, Ordering::Relaxed),
+            Err(_) => self.errors_total.fetch_add(1, Ordering::Relaxed),
+        };
+        published
+    }
+
     fn subscribe(&self) -> broadcast::Receiver<StreamEvent> {
         self.tx.subscribe()
     }
@@ -102,6 +199,9 @@ impl StreamConnector for NatsConnector {
             events_total: self.events_total.load(Ordering::Relaxed),
             bytes_total: self.bytes_total.load(Ordering::Relaxed),
             errors_total: self.errors_total.load(Ordering::Relaxed),
+            reconnect_attempts: self.reconnect_attempts.load(Ordering::Relaxed),
+            produced_total: self.produced_total.load(Ordering::Relaxed),
+            last_event_at: self.last_event.rfc3339(),
             ..Default::default()
         }
     }
@@ -115,9 +215,60 @@ impl StreamConnector for NatsConnector {
             config: serde_json::json!({
                 "url": self.url,
                 "subject": self.subject,
+                "produce_subject": self.egress.destination,
+                "key_field": self.egress.key_field,
+                "format": self.decoder.format(),
             }),
             metrics: self.metrics(),
             created_at: self.created_at.clone(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::StreamExt;
+
+    /// Needs a NATS server; run with
+    /// `NATS_URL=nats://localhost:4222 cargo test --features nats -- --ignored`.
+    #[tokio::test]
+    #[ignore = "needs a NATS server at NATS_URL"]
+    async fn test_produce_publishes_payload_and_key() {
+        let url = std::env::var("NATS_URL").unwrap_or_else(|_| "nats://localhost:4222".into());
+        let subject = format!("cz-hub-test.{}", uuid::Uuid::new_v4().as_simple());
+        let observer = async_nats::connect(url.as_str()).await.unwrap();
+        let mut received = observer.subscribe(subject.clone()).await.unwrap();
+        observer.flush().await.unwrap();
+
+        let connector = NatsConnector::new(
+            "sink".into(),
+            HashMap::from([
+                ("url".into(), url),
+                ("produce_subject".into(), subject),
+                ("key_field".into(), "/order".into()),
+            ]),
+        );
+        let event = StreamEvent {
+            id: "e-1".into(),
+            connector_id: "webhook-1".into(),
+            stream: "orders".into(),
+            sequence: 1,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            payload: serde_json::json!({ "order": "o-42" }),
+            metadata: HashMap::new(),
+        };
+        connector.produce(&event).await.unwrap();
+
+        let message = tokio::time::timeout(Duration::from_secs(5), received.next())
+            .await
+            .unwrap()
+            .unwrap();
+        let payload: serde_json::Value = serde_json::from_slice(&message.payload).unwrap();
+        assert_eq!(payload, event.payload);
+        let headers = message.headers.unwrap();
+        assert_eq!(headers.get(KEY_HEADER).unwrap().as_str(), "o-42");
+        assert_eq!(headers.get("cz-event-id").unwrap().as_str(), "e-1");
+        assert_eq!(connector.metrics().produced_total, 1);
+    }
+}
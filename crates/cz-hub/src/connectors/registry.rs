@@ -2,22 +2,75 @@
 //!
 //! Thread-safe manager for all active [`StreamConnector`] instances.
 //! Handles creation, lifecycle, event fan-out, and metrics aggregation.
+//!
+//! With a [`StateStore`] attached, the configs of connectors created through
+//! [`ConnectorRegistry::create_from_config`] are saved, and
+//! [`ConnectorRegistry::restore`] recreates them after a restart. Journal
+//! connectors are not saved; they follow the journals the hub opens.
 
-use super::{
-    ConnectorConfig, ConnectorInfo, ConnectorKind, StreamConnector, StreamEvent,
-};
+use super::{ConnectorConfig, ConnectorInfo, ConnectorKind, StreamConnector, StreamEvent};
+use crate::store::StateStore;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use tokio::sync::{broadcast, RwLock};
 
+/// A registered connector and what the registry observes about it.
+struct Registered {
+    connector: Arc<dyn StreamConnector>,
+    /// Events the forwarding task missed because it fell behind the
+    /// connector's channel.
+    lagged: Arc<AtomicU64>,
+    /// The config it was created from, if it came through
+    /// `create_from_config`; only those are saved.
+    config: Option<ConnectorConfig>,
+}
+
+/// Recent events kept for the query engine, with a version per source.
+#[derive(Default)]
+struct EventBuffer {
+    events: Vec<StreamEvent>,
+    /// Per `(connector_id, stream)`: bumped whenever one of its events
+    /// enters or leaves the buffer, so a cached result over it can tell
+    /// it is out of date. Sources are never forgotten.
+    versions: HashMap<(String, String), u64>,
+}
+
+impl EventBuffer {
+    fn bump(&mut self, event: &StreamEvent) {
+        *self
+            .versions
+            .entry((event.connector_id.clone(), event.stream.clone()))
+            .or_default() += 1;
+    }
+}
+
+/// Versions of the buffered sources one query reads, from
+/// [`ConnectorRegistry::source_versions`]. Equal snapshots mean the events
+/// the query can see are unchanged.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceVersions {
+    /// `(connector_id, stream, version)`, sorted.
+    versions: Vec<(String, String, u64)>,
+    /// Sources of any kind; a new one may be one the query reads.
+    sources: usize,
+}
+
+/// Collection the connector configs are saved under.
+const STORE_KEY: &str = "connectors";
+
 /// Central registry for all active connectors.
 pub struct ConnectorRegistry {
-    connectors: RwLock<HashMap<String, Arc<dyn StreamConnector>>>,
+    connectors: RwLock<HashMap<String, Registered>>,
     /// Unified event bus — all connectors fan-in here.
     event_tx: broadcast::Sender<StreamEvent>,
     /// Buffer of recent events for query engine access.
-    event_buffer: Arc<RwLock<Vec<StreamEvent>>>,
+    event_buffer: Arc<RwLock<EventBuffer>>,
     buffer_capacity: usize,
+    /// Events appended to the buffer so far, which numbers their
+    /// positions for query cursors.
+    generation: Arc<AtomicU64>,
+    store: Option<Arc<StateStore>>,
 }
 
 impl ConnectorRegistry {
@@ -26,8 +79,50 @@ impl ConnectorRegistry {
         Self {
             connectors: RwLock::new(HashMap::new()),
             event_tx,
-            event_buffer: Arc::new(RwLock::new(Vec::with_capacity(buffer_capacity))),
+            event_buffer: Arc::new(RwLock::new(EventBuffer {
+                events: Vec::with_capacity(buffer_capacity),
+                versions: HashMap::new(),
+            })),
             buffer_capacity,
+            generation: Arc::new(AtomicU64::new(0)),
+            store: None,
+        }
+    }
+
+    /// Save the configs of created connectors to `store`. Call
+    /// [`restore`](Self::restore) to bring back the ones saved earlier.
+    pub fn with_store(mut self, store: Arc<StateStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Recreate and start the connectors saved in the store. Returns how
+    /// many were restored; ones that can no longer be built are logged and
+    /// dropped.
+    pub async fn restore(&self) -> usize {
+        let Some(store) = &self.store else {
+            return 0;
+        };
+        let configs: Vec<ConnectorConfig> = store.load(STORE_KEY).unwrap_or_default();
+        let mut restored = 0;
+        for config in configs {
+            let name = config.name.clone();
+            match self.create_from_config(config).await {
+                Ok(_) => restored += 1,
+                Err(e) => tracing::warn!("Cannot restore connector '{}': {}", name, e),
+            }
+        }
+        restored
+    }
+
+    fn persist(&self, connectors: &HashMap<String, Registered>) {
+        if let Some(store) = &self.store {
+            let mut configs: Vec<&ConnectorConfig> = connectors
+                .values()
+                .filter_map(|c| c.config.as_ref())
+                .collect();
+            configs.sort_by(|a, b| a.id.cmp(&b.id));
+            store.save(STORE_KEY, &configs);
         }
     }
 
@@ -35,18 +130,42 @@ impl ConnectorRegistry {
     pub async fn add(
         &self,
         connector: Arc<dyn StreamConnector>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.register(connector, None).await
+    }
+
+    async fn register(
+        &self,
+        connector: Arc<dyn StreamConnector>,
+        config: Option<ConnectorConfig>,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let id = connector.id().to_string();
+        let lagged = Arc::new(AtomicU64::new(0));
 
         // Store it
         {
             let mut connectors = self.connectors.write().await;
-            connectors.insert(id.clone(), connector.clone());
+            if connectors.contains_key(&id) {
+                return Err(format!("Connector '{}' already exists", id).into());
+            }
+            let saved = config.is_some();
+            connectors.insert(
+                id.clone(),
+                Registered {
+                    connector: connector.clone(),
+                    lagged: lagged.clone(),
+                    config,
+                },
+            );
+            if saved {
+                self.persist(&connectors);
+            }
         }
 
         // Spawn a task that forwards events to the unified bus
         let tx = self.event_tx.clone();
         let buffer = self.event_buffer.clone();
+        let generation = self.generation.clone();
         let cap = self.buffer_capacity;
         let mut rx = connector.subscribe();
 
@@ -56,15 +175,18 @@ impl ConnectorRegistry {
                     Ok(event) => {
                         let _ = tx.send(event.clone());
                         // Buffer for query engine
-                        let mut buf: tokio::sync::RwLockWriteGuard<Vec<StreamEvent>> =
-                            buffer.write().await;
-                        if buf.len() >= cap {
-                            buf.remove(0);
+                        let mut buf = buffer.write().await;
+                        if buf.events.len() >= cap {
+                            let evicted = buf.events.remove(0);
+                            buf.bump(&evicted);
                         }
-                        buf.push(event);
+                        buf.bump(&event);
+                        buf.events.push(event);
+                        generation.fetch_add(1, Ordering::Release);
                     }
                     Err(broadcast::error::RecvError::Lagged(n)) => {
-                        tracing::warn!("Connector event bus lagged by {} events", n);
+                        lagged.fetch_add(n, Ordering::Relaxed);
+                        tracing::warn!("Connector {} event bus lagged by {} events", id, n);
                     }
                     Err(broadcast::error::RecvError::Closed) => break,
                 }
@@ -86,58 +208,140 @@ impl ConnectorRegistry {
     pub async fn remove(&self, id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let connector = {
             let mut connectors = self.connectors.write().await;
-            connectors.remove(id)
+            let removed = connectors.remove(id);
+            if removed.as_ref().is_some_and(|c| c.config.is_some()) {
+                self.persist(&connectors);
+            }
+            removed
         };
 
         if let Some(c) = connector {
-            c.stop().await?;
+            c.connector.stop().await?;
             Ok(())
         } else {
             Err(format!("Connector '{}' not found", id).into())
         }
     }
 
-    /// List all connectors with their current info.
+    /// List all connectors with their current info, including the events
+    /// each one's forwarding task has lost to lag.
     pub async fn list(&self) -> Vec<ConnectorInfo> {
         let connectors = self.connectors.read().await;
-        connectors.values().map(|c| c.info()).collect()
+        connectors
+            .values()
+            .map(|c| {
+                let mut info = c.connector.info();
+                info.metrics.lagged_total = c.lagged.load(Ordering::Relaxed);
+                info
+            })
+            .collect()
     }
 
     /// Get a specific connector.
     pub async fn get(&self, id: &str) -> Option<Arc<dyn StreamConnector>> {
         let connectors = self.connectors.read().await;
-        connectors.get(id).cloned()
+        connectors.get(id).map(|c| c.connector.clone())
+    }
+
+    /// Publish `event` through connector `id`, e.g. from a pipeline `Sink`
+    /// node.
+    pub async fn produce(
+        &self,
+        id: &str,
+        event: &StreamEvent,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let connector = self
+            .get(id)
+            .await
+            .ok_or_else(|| format!("Connector '{}' not found", id))?;
+        connector.produce(event).await
+    }
+
+    /// Receive every event from every connector as it arrives.
+    pub fn subscribe(&self) -> broadcast::Receiver<StreamEvent> {
+        self.event_tx.subscribe()
+    }
+
+    /// Bumped for every event added to the buffer.
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
     }
 
     /// Get the buffered events (for query engine).
     pub async fn buffered_events(&self) -> Vec<StreamEvent> {
-        self.event_buffer.read().await.clone()
+        self.event_buffer.read().await.events.clone()
+    }
+
+    /// Run `f` over the buffered events without copying them, passing the
+    /// position of the first one: the n-th event ever buffered has
+    /// position n - 1. New events wait until `f` returns.
+    pub async fn with_buffered_events<R>(&self, f: impl FnOnce(u64, &[StreamEvent]) -> R) -> R {
+        let buf = self.event_buffer.read().await;
+        // Bumped under the write lock, so it matches the buffer we hold.
+        let first = self.generation() - buf.events.len() as u64;
+        f(first, &buf.events)
+    }
+
+    /// Versions of the buffered sources `reads(connector_id, stream)`
+    /// accepts. They change when an event of one of those sources enters or
+    /// leaves the buffer, or a new source appears, but not for events of
+    /// other sources.
+    pub async fn source_versions(&self, reads: impl Fn(&str, &str) -> bool) -> SourceVersions {
+        let buf = self.event_buffer.read().await;
+        let mut versions: Vec<(String, String, u64)> = buf
+            .versions
+            .iter()
+            .filter(|((connector_id, stream), _)| reads(connector_id, stream))
+            .map(|((connector_id, stream), v)| (connector_id.clone(), stream.clone(), *v))
+            .collect();
+        versions.sort();
+        SourceVersions {
+            versions,
+            sources: buf.versions.len(),
+        }
     }
 
     /// Create a connector from config and register it.
     pub async fn create_from_config(
         &self,
-        config: ConnectorConfig,
+        mut config: ConnectorConfig,
     ) -> Result<ConnectorInfo, Box<dyn std::error::Error + Send + Sync>> {
+        super::decode::decoder_from_params(&config.params)?;
         let connector: Arc<dyn StreamConnector> = match config.kind {
-            ConnectorKind::Webhook => Arc::new(super::webhook::WebhookConnector::new(
-                config.name.clone(),
-                config.params.clone(),
-            )),
+            ConnectorKind::Webhook => {
+                if config.params.contains_key("secret") {
+                    super::signature::Scheme::from_params(&config.params)?;
+                }
+                let c = super::webhook::WebhookConnector::new(
+                    config.name.clone(),
+                    config.params.clone(),
+                );
+                Arc::new(match config.id.clone() {
+                    Some(id) => c.with_id(id),
+                    None => c,
+                })
+            }
             #[cfg(feature = "kafka")]
-            ConnectorKind::Kafka => Arc::new(super::kafka::KafkaConnector::new(
-                config.name.clone(),
-                config.params.clone(),
-            )),
+            ConnectorKind::Kafka => {
+                let c =
+                    super::kafka::KafkaConnector::new(config.name.clone(), config.params.clone());
+                Arc::new(match config.id.clone() {
+                    Some(id) => c.with_id(id),
+                    None => c,
+                })
+            }
             #[cfg(not(feature = "kafka"))]
             ConnectorKind::Kafka => {
                 return Err("Kafka support not compiled. Rebuild with --features kafka".into());
             }
             #[cfg(feature = "nats")]
-            ConnectorKind::Nats => Arc::new(super::nats::NatsConnector::new(
-                config.name.clone(),
-                config.params.clone(),
-            )),
+            ConnectorKind::Nats => {
+                let c = super::nats::NatsConnector::new(config.name.clone(), config.params.clone());
+                Arc::new(match config.id.clone() {
+                    Some(id) => c.with_id(id),
+                    None => c,
+                })
+            }
             #[cfg(not(feature = "nats"))]
             ConnectorKind::Nats => {
                 return Err("NATS support not compiled. Rebuild with --features nats".into());
@@ -151,7 +355,8 @@ impl ConnectorRegistry {
         };
 
         let info = connector.info();
-        self.add(connector).await?;
+        config.id = Some(info.id.clone());
+        self.register(connector, Some(config)).await?;
         Ok(info)
     }
 }
@@ -2,11 +2,17 @@
 //!
 //! HTTP ingestion endpoint that receives POST payloads and emits them
 //! as [`StreamEvent`]s. Supports JSON, form, and raw body formats.
-//! Provider-specific schema mapping (GitHub, Stripe, PagerDuty) normalizes
-//! incoming payloads to a common structure.
+//! Provider-specific schema mapping (GitHub, GitLab, Stripe, PagerDuty,
+//! Datadog, CloudEvents) normalizes incoming payloads to a common
+//! structure. With `params["secret"]`,
+//! GitHub and Stripe deliveries must carry a valid signature (see
+//! [`signature`](super::signature)), which then stands in for an API key.
 
+use super::decode::{self, Decoder};
+use super::signature::{Scheme, SignatureError};
 use super::{
-    ConnectorInfo, ConnectorKind, ConnectorMetrics, ConnectorStatus, StreamConnector, StreamEvent,
+    ConnectorInfo, ConnectorKind, ConnectorMetrics, ConnectorStatus, LastEvent, StreamConnector,
+    StreamEvent,
 };
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
@@ -20,8 +26,13 @@ pub struct WebhookConnector {
     events_total: AtomicU64,
     bytes_total: AtomicU64,
     errors_total: AtomicU64,
+    last_event: LastEvent,
     tx: broadcast::Sender<StreamEvent>,
     params: HashMap<String, String>,
+    decoder: Box<dyn Decoder>,
+    /// Secret and scheme deliveries are verified with. A scheme error
+    /// refuses every delivery rather than accepting unsigned ones.
+    signature: Option<(String, Result<Scheme, SignatureError>)>,
     created_at: String,
     sequence: AtomicU64,
 }
@@ -30,6 +41,17 @@ impl WebhookConnector {
     pub fn new(name: String, params: HashMap<String, String>) -> Self {
         let (tx, _) = broadcast::channel(2048);
         let id = format!("webhook-{}", uuid::Uuid::new_v4().as_simple());
+        let decoder = decode::decoder_from_params(&params).unwrap_or_else(|e| {
+            tracing::warn!("Webhook connector '{}': {}; decoding as JSON", name, e);
+            Box::new(decode::JsonDecoder)
+        });
+        let signature = params.get("secret").map(|secret| {
+            let scheme = Scheme::from_params(&params);
+            if let Err(e) = &scheme {
+                tracing::error!("Webhook connector '{}': {}; refusing deliveries", name, e);
+            }
+            (secret.clone(), scheme)
+        });
 
         Self {
             id,
@@ -39,19 +61,37 @@ impl WebhookConnector {
             events_total: AtomicU64::new(0),
             bytes_total: AtomicU64::new(0),
             errors_total: AtomicU64::new(0),
+            last_event: LastEvent::default(),
             tx,
             params,
+            decoder,
+            signature,
             created_at: chrono::Utc::now().to_rfc3339(),
             sequence: AtomicU64::new(0),
         }
     }
 
-    /// Ingest a webhook payload. Called by the HTTP route handler.
+    /// Use `id` instead of a generated one, e.g. for a restored connector.
+    pub fn with_id(mut self, id: String) -> Self {
+        self.id = id;
+        self
+    }
+
+    /// Ingest a webhook body. Called by the HTTP route handler.
     fn ingest_payload(
         &self,
-        payload: serde_json::Value,
+        body: &[u8],
         headers: HashMap<String, String>,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if let Some((secret, scheme)) = &self.signature {
+            let verified = scheme.clone().and_then(|scheme| {
+                scheme.verify(secret, body, &headers, chrono::Utc::now().timestamp())
+            });
+            if let Err(e) = verified {
+                self.errors_total.fetch_add(1, Ordering::Relaxed);
+                return Err(Box::new(e));
+            }
+        }
         let seq = self.sequence.fetch_add(1, Ordering::Relaxed);
         let provider = self
             .params
@@ -59,8 +99,14 @@ impl WebhookConnector {
             .cloned()
             .unwrap_or_else(|| "generic".into());
 
-        // Normalize payload based on provider
-        let normalized = self.normalize_payload(&provider, &payload, &headers);
+        // Normalize decoded JSON based on provider
+        let normalized = match self.decoder.decode(body) {
+            Ok(payload) if self.decoder.format() == decode::PayloadFormat::Json => {
+                self.normalize_payload(&provider, &payload, &headers)
+            }
+            Ok(payload) => payload,
+            Err(e) => decode::fallback(body, &e, &self.errors_total),
+        };
 
         let event = StreamEvent {
             id: format!("{}-{}", self.id, seq),
@@ -72,9 +118,10 @@ impl WebhookConnector {
             metadata: headers,
         };
 
-        let payload_size = event.payload.to_string().len() as u64;
         self.events_total.fetch_add(1, Ordering::Relaxed);
-        self.bytes_total.fetch_add(payload_size, Ordering::Relaxed);
+        self.last_event.touch();
+        self.bytes_total
+            .fetch_add(body.len() as u64, Ordering::Relaxed);
 
         let _ = self.tx.send(event);
         Ok(())
@@ -84,7 +131,7 @@ impl WebhookConnector {
         &self,
         provider: &str,
         payload: &serde_json::Value,
-        _headers: &HashMap<String, String>,
+        headers: &HashMap<String, String>,
     ) -> serde_json::Value {
         match provider {
             "github" => {
@@ -112,6 +159,57 @@ impl WebhookConnector {
                     "incident": payload.pointer("/event/data").unwrap_or(&serde_json::Value::Null),
                 })
             }
+            "gitlab" => {
+                // Push events carry the user flat, the other hooks nested
+                let user = payload
+                    .pointer("/user/username")
+                    .or_else(|| payload.get("user_username"))
+                    .unwrap_or(&serde_json::Value::Null);
+                serde_json::json!({
+                    "provider": "gitlab",
+                    "action": payload.get("object_kind").unwrap_or(&serde_json::Value::Null),
+                    "project": payload.pointer("/project/path_with_namespace").unwrap_or(&serde_json::Value::Null),
+                    "user": user,
+                    "raw": payload,
+                })
+            }
+            "datadog" => {
+                serde_json::json!({
+                    "provider": "datadog",
+                    "action": payload.get("alert_type").unwrap_or(&serde_json::Value::Null),
+                    "title": payload.get("title").unwrap_or(&serde_json::Value::Null),
+                    "body": payload.get("body").unwrap_or(&serde_json::Value::Null),
+                    "raw": payload,
+                })
+            }
+            "cloudevents" => {
+                if payload.get("specversion").is_some() {
+                    // Structured mode: the envelope is the body
+                    serde_json::json!({
+                        "provider": "cloudevents",
+                        "action": payload.get("type").unwrap_or(&serde_json::Value::Null),
+                        "specversion": payload.get("specversion").unwrap_or(&serde_json::Value::Null),
+                        "source": payload.get("source").unwrap_or(&serde_json::Value::Null),
+                        "id": payload.get("id").unwrap_or(&serde_json::Value::Null),
+                        "data": payload.get("data").unwrap_or(&serde_json::Value::Null),
+                    })
+                } else {
+                    // Binary mode: attributes in `ce-*` headers, data is the body
+                    let attribute = |name: &str| {
+                        headers
+                            .get(&format!("ce-{}", name))
+                            .map_or(serde_json::Value::Null, |v| v.as_str().into())
+                    };
+                    serde_json::json!({
+                        "provider": "cloudevents",
+                        "action": attribute("type"),
+                        "specversion": attribute("specversion"),
+                        "source": attribute("source"),
+                        "id": attribute("id"),
+                        "data": payload,
+                    })
+                }
+            }
             _ => {
                 // Generic — pass through
                 payload.clone()
@@ -157,6 +255,7 @@ impl StreamConnector for WebhookConnector {
             events_total: self.events_total.load(Ordering::Relaxed),
             bytes_total: self.bytes_total.load(Ordering::Relaxed),
             errors_total: self.errors_total.load(Ordering::Relaxed),
+            last_event_at: self.last_event.rfc3339(),
             ..Default::default()
         }
     }
@@ -164,7 +263,8 @@ impl StreamConnector for WebhookConnector {
     fn info(&self) -> ConnectorInfo {
         let mut config = serde_json::Map::new();
         for (k, v) in &self.params {
-            config.insert(k.clone(), serde_json::Value::String(v.clone()));
+            let v = if k == "secret" { "********" } else { v };
+            config.insert(k.clone(), serde_json::Value::String(v.to_string()));
         }
         ConnectorInfo {
             id: self.id.clone(),
@@ -179,9 +279,124 @@ impl StreamConnector for WebhookConnector {
 
     async fn ingest(
         &self,
-        payload: serde_json::Value,
+        body: &[u8],
         headers: HashMap<String, String>,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        self.ingest_payload(payload, headers)
+        self.ingest_payload(body, headers)
+    }
+
+    fn verifies_signatures(&self) -> bool {
+        self.signature.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn normalize(provider: &str, payload: serde_json::Value) -> serde_json::Value {
+        normalize_with(provider, payload, HashMap::new())
+    }
+
+    fn normalize_with(
+        provider: &str,
+        payload: serde_json::Value,
+        headers: HashMap<String, String>,
+    ) -> serde_json::Value {
+        let params = HashMap::from([("provider".to_string(), provider.to_string())]);
+        WebhookConnector::new("test".into(), params).normalize_payload(provider, &payload, &headers)
+    }
+
+    #[test]
+    fn test_gitlab_merge_request_and_push() {
+        let mr = normalize(
+            "gitlab",
+            json!({
+                "object_kind": "merge_request",
+                "user": { "name": "Ada", "username": "ada" },
+                "project": { "id": 15, "path_with_namespace": "infra/lacrimosa" },
+                "object_attributes": { "iid": 7, "action": "open" },
+            }),
+        );
+        assert_eq!(mr["provider"], "gitlab");
+        assert_eq!(mr["action"], "merge_request");
+        assert_eq!(mr["project"], "infra/lacrimosa");
+        assert_eq!(mr["user"], "ada");
+        assert_eq!(mr["raw"]["object_attributes"]["iid"], 7);
+
+        let push = normalize(
+            "gitlab",
+            json!({
+                "object_kind": "push",
+                "user_username": "grace",
+                "project": { "path_with_namespace": "infra/lacrimosa" },
+                "commits": [],
+            }),
+        );
+        assert_eq!(push["action"], "push");
+        assert_eq!(push["user"], "grace");
+    }
+
+    #[test]
+    fn test_datadog_alert() {
+        let alert = normalize(
+            "datadog",
+            json!({
+                "id": "6839104883",
+                "alert_type": "error",
+                "title": "[Triggered] CPU high on web-1",
+                "body": "%%% cpu > 90 for 5m %%%",
+                "tags": "env:prod,service:web",
+            }),
+        );
+        assert_eq!(alert["provider"], "datadog");
+        assert_eq!(alert["action"], "error");
+        assert_eq!(alert["title"], "[Triggered] CPU high on web-1");
+        assert_eq!(alert["body"], "%%% cpu > 90 for 5m %%%");
+        assert_eq!(alert["raw"]["tags"], "env:prod,service:web");
+    }
+
+    #[test]
+    fn test_cloudevents_structured_and_binary() {
+        let structured = normalize(
+            "cloudevents",
+            json!({
+                "specversion": "1.0",
+                "type": "com.example.order.created",
+                "source": "/orders",
+                "id": "A234-1234-1234",
+                "datacontenttype": "application/json",
+                "data": { "order_id": "o-42" },
+            }),
+        );
+        assert_eq!(structured["provider"], "cloudevents");
+        assert_eq!(structured["action"], "com.example.order.created");
+        assert_eq!(structured["specversion"], "1.0");
+        assert_eq!(structured["source"], "/orders");
+        assert_eq!(structured["id"], "A234-1234-1234");
+        assert_eq!(structured["data"], json!({ "order_id": "o-42" }));
+
+        let headers = HashMap::from([
+            ("ce-specversion".to_string(), "1.0".to_string()),
+            (
+                "ce-type".to_string(),
+                "com.example.order.created".to_string(),
+            ),
+            ("ce-source".to_string(), "/orders".to_string()),
+            ("ce-id".to_string(), "B234".to_string()),
+        ]);
+        let binary = normalize_with("cloudevents", json!({ "order_id": "o-42" }), headers);
+        assert_eq!(
+            binary,
+            json!({
+                "provider": "cloudevents",
+                "action": "com.example.order.created",
+                "specversion": "1.0",
+                "source": "/orders",
+                "id": "B234",
+                "data": { "order_id": "o-42" },
+            })
+        );
     }
 }
@@ -2,10 +2,14 @@
 //!
 //! Customizable visualization layouts for monitoring streams and metrics.
 
+use cz_hub::store::StateStore;
 use serde::{Deserialize, Serialize};
-
+use std::sync::Arc;
 use tokio::sync::RwLock;
 
+/// Collection the dashboards are saved under.
+const STORE_KEY: &str = "dashboards";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Dashboard {
     pub id: String,
@@ -68,12 +72,27 @@ pub struct UpdateDashboardRequest {
 
 pub struct DashboardManager {
     dashboards: RwLock<Vec<Dashboard>>,
+    store: Option<Arc<StateStore>>,
 }
 
 impl DashboardManager {
     pub fn new() -> Self {
         Self {
             dashboards: RwLock::new(Vec::new()),
+            store: None,
+        }
+    }
+
+    /// Start from the dashboards saved in `store` and save every change.
+    pub fn with_store(mut self, store: Arc<StateStore>) -> Self {
+        self.dashboards = RwLock::new(store.load(STORE_KEY).unwrap_or_default());
+        self.store = Some(store);
+        self
+    }
+
+    fn persist(&self, dashboards: &[Dashboard]) {
+        if let Some(store) = &self.store {
+            store.save(STORE_KEY, dashboards);
         }
     }
 
@@ -92,7 +111,9 @@ impl DashboardManager {
             created_at: now.clone(),
             updated_at: now,
         };
-        self.dashboards.write().await.push(dashboard.clone());
+        let mut dashboards = self.dashboards.write().await;
+        dashboards.push(dashboard.clone());
+        self.persist(&dashboards);
         dashboard
     }
 
@@ -119,7 +140,9 @@ impl DashboardManager {
         dashboard.layout = layout;
         dashboard.widgets = widgets;
         dashboard.updated_at = chrono::Utc::now().to_rfc3339();
-        Ok(dashboard.clone())
+        let updated = dashboard.clone();
+        self.persist(&dashboards);
+        Ok(updated)
     }
 
     pub async fn delete(&self, id: &str) -> Result<(), String> {
@@ -129,6 +152,7 @@ impl DashboardManager {
             .position(|d| d.id == id)
             .ok_or("Dashboard not found")?;
         dashboards.remove(idx);
+        self.persist(&dashboards);
         Ok(())
     }
 }
@@ -0,0 +1,238 @@
+//! # IPC Client
+//!
+//! Tokio-native reader for the sequencers' framed push sockets, one client
+//! per journal. Decoded messages are tagged with their journal and
+//! published on a broadcast channel so any number of subscribers (incident
+//! handling, WebSocket sessions) can follow every sequencer without each
+//! holding its own connections.
+
+use cz_io::ipc::{FrameDecoder, IpcMessage};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+use tokio::net::UnixStream;
+use tokio::sync::broadcast;
+
+/// A sequencer notification and the journal it concerns.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SequencerEvent {
+    pub journal: PathBuf,
+    pub message: IpcMessage,
+}
+
+/// Reconnecting subscriber to the IPC socket of one journal's sequencer.
+pub struct IpcClient {
+    journal: PathBuf,
+    /// Configured address; without one the address is looked up in the
+    /// journal header on every connect, so a restarted sequencer that
+    /// moved is still found.
+    socket: Option<String>,
+    /// Delay before reconnecting; doubles per failure up to `max_backoff`
+    /// and resets once a connection succeeds.
+    min_backoff: Duration,
+    max_backoff: Duration,
+    /// Whether a connection to the sequencer is currently open.
+    connected: Arc<AtomicBool>,
+}
+
+impl IpcClient {
+    pub fn new(journal: impl Into<PathBuf>, socket: Option<String>) -> Self {
+        Self {
+            journal: journal.into(),
+            socket,
+            min_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(5),
+            connected: Arc::default(),
+        }
+    }
+
+    /// Flag that follows whether the client is connected, for readiness.
+    pub fn connected(&self) -> Arc<AtomicBool> {
+        self.connected.clone()
+    }
+
+    fn address(&self) -> String {
+        self.socket
+            .clone()
+            .unwrap_or_else(|| cz_io::ipc::resolve_socket(&self.journal))
+    }
+
+    /// Connect, decode, and publish forever.
+    ///
+    /// Send errors are ignored: having no subscribers right now is normal.
+    pub async fn run(self, tx: broadcast::Sender<SequencerEvent>) {
+        let mut backoff = self.min_backoff;
+        loop {
+            let address = self.address();
+            match connect(&address) {
+                Ok(stream) => {
+                    tracing::info!(
+                        "Connected to cz-io push socket at {} for {}",
+                        address,
+                        self.journal.display()
+                    );
+                    backoff = self.min_backoff;
+                    self.connected.store(true, Ordering::Release);
+                    self.read_frames(stream, &tx).await;
+                    self.connected.store(false, Ordering::Release);
+                }
+                Err(e) => {
+                    tracing::debug!("cz-io push socket {} unavailable: {}", address, e);
+                }
+            }
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(self.max_backoff);
+        }
+    }
+
+    /// Publish messages until the connection ends or the sequencer shuts down.
+    async fn read_frames(&self, mut stream: UnixStream, tx: &broadcast::Sender<SequencerEvent>) {
+        let mut decoder = FrameDecoder::new();
+        let mut buf = [0u8; 4096];
+        loop {
+            match stream.read(&mut buf).await {
+                Ok(0) | Err(_) => break,
+                Ok(n) => decoder.feed(&buf[..n]),
+            }
+            while let Some(message) = decoder.next_message() {
+                let shutdown = message == IpcMessage::Shutdown;
+                let _ = tx.send(SequencerEvent {
+                    journal: self.journal.clone(),
+                    message,
+                });
+                if shutdown {
+                    tracing::info!("cz-io for {} announced shutdown", self.journal.display());
+                    return;
+                }
+            }
+        }
+        if decoder.skipped_bytes() > 0 {
+            tracing::warn!(
+                "Discarded {} unframed bytes from cz-io socket",
+                decoder.skipped_bytes()
+            );
+        }
+    }
+}
+
+/// Connecting a Unix socket completes immediately, so the blocking std
+/// connect (which understands abstract addresses) is fine here.
+fn connect(address: &str) -> std::io::Result<UnixStream> {
+    let stream = cz_io::ipc::connect(address)?;
+    stream.set_nonblocking(true)?;
+    UnixStream::from_std(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::UnixListener;
+
+    #[tokio::test]
+    async fn test_client_publishes_and_reconnects() {
+        let path = std::env::temp_dir().join(format!("cz-hub-ipc-{}.sock", std::process::id()));
+        std::fs::remove_file(&path).ok();
+        let listener = UnixListener::bind(&path).unwrap();
+
+        let (tx, mut rx) = broadcast::channel(16);
+        let client = IpcClient {
+            min_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(50),
+            ..IpcClient::new("journal.db", Some(path.to_str().unwrap().to_string()))
+        };
+        let task = tokio::spawn(client.run(tx));
+
+        // First connection: noise before a frame, then the peer hangs up.
+        let (mut conn, _) = listener.accept().await.unwrap();
+        conn.write_all(&7u64.to_le_bytes()).await.unwrap();
+        let first = IpcMessage::SlotCommitted {
+            slot: 1,
+            lamport_ts: 10,
+        };
+        conn.write_all(&first.encode()).await.unwrap();
+        assert_eq!(rx.recv().await.unwrap().message, first);
+        drop(conn);
+
+        // The client comes back on its own.
+        let (mut conn, _) = listener.accept().await.unwrap();
+        let second = IpcMessage::SlotCommitted {
+            slot: 2,
+            lamport_ts: 11,
+        };
+        conn.write_all(&second.encode()).await.unwrap();
+        assert_eq!(rx.recv().await.unwrap().message, second);
+
+        task.abort();
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[tokio::test]
+    async fn test_clients_follow_their_own_sequencer() {
+        use cz_io::ipc::{socket_for_journal, IpcServer};
+        use cz_io::journal::{Journal, MIN_JOURNAL_SIZE};
+
+        let dir = std::env::temp_dir().join(format!("cz-hub-ipc-pair-{}", std::process::id()));
+        let journals = [dir.join("a/journal.db"), dir.join("b/journal.db")];
+        let (tx, mut rx) = broadcast::channel(16);
+        let mut servers = Vec::new();
+        let mut tasks = Vec::new();
+        for path in &journals {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            let size = MIN_JOURNAL_SIZE + 4096;
+            let mut journal = Journal::open(path, size).unwrap();
+            // The sequencer records where it listens; the client reads it back.
+            let address = socket_for_journal(path);
+            servers.push(IpcServer::start(&address).unwrap());
+            journal.publish_ipc_address(&address).unwrap();
+            journal.flush().unwrap();
+
+            let client = IpcClient {
+                min_backoff: Duration::from_millis(10),
+                max_backoff: Duration::from_millis(50),
+                ..IpcClient::new(path, None)
+            };
+            tasks.push(tokio::spawn(client.run(tx.clone())));
+        }
+
+        // Keep announcing until both clients have connected and been heard.
+        let mut seen = std::collections::HashMap::new();
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+        while seen.len() < 2 {
+            for (slot, server) in servers.iter().enumerate() {
+                server.broadcast(&IpcMessage::SlotCommitted {
+                    slot: slot as u64,
+                    lamport_ts: 1,
+                });
+            }
+            if let Ok(Ok(event)) = tokio::time::timeout_at(
+                deadline.min(tokio::time::Instant::now() + Duration::from_millis(20)),
+                rx.recv(),
+            )
+            .await
+            {
+                seen.insert(event.journal, event.message);
+            }
+            assert!(
+                tokio::time::Instant::now() < deadline,
+                "clients never connected"
+            );
+        }
+        for (slot, path) in journals.iter().enumerate() {
+            assert_eq!(
+                seen[path],
+                IpcMessage::SlotCommitted {
+                    slot: slot as u64,
+                    lamport_ts: 1
+                }
+            );
+        }
+
+        for task in tasks {
+            task.abort();
+        }
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,16 @@
+//! # cz-hub — Library Surface
+//!
+//! The parts of the Control Center that need no running server: the
+//! connector model, the query engine, journal replay, causality anomaly
+//! checks, payload decoding, state persistence and Kani verification
+//! reports. The `cz-hub` binary is built on top of them, and
+//! `cz query --journal`, `cz replay --local` and `cz verify --json` use them
+//! directly.
+
+pub mod anomaly;
+pub mod connectors;
+pub mod payload;
+pub mod query;
+pub mod replay;
+pub mod store;
+pub mod verify;
@@ -0,0 +1,486 @@
+//! # Request Limits
+//!
+//! Token buckets that keep one client from monopolizing the hub. On top of
+//! the per-key budget the auth layer enforces, every API request draws from
+//! a bucket for its client IP, and the endpoints that scan the ring —
+//! export, aggregate and the integrity scrub — draw from separate, smaller
+//! buckets per key and per IP.
+//!
+//! Budgets come from the `[limits]` section of `cz-hub.toml`; a budget of
+//! 0 disables that bucket. Buckets refill continuously over a minute.
+//!
+//! The client IP is the socket peer. `X-Forwarded-For` is only believed
+//! when that peer is one of `trusted_proxies`, since anyone can send it.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tokio::time::Instant;
+
+/// Buckets kept before idle (full) ones are dropped.
+const MAX_BUCKETS: usize = 10_000;
+
+/// Token bucket holding up to one minute's worth of requests.
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    pub fn full(capacity: u32, now: Instant) -> Self {
+        Self {
+            tokens: capacity as f64,
+            last_refill: now,
+        }
+    }
+
+    /// Tokens available at `now`.
+    fn refill(&mut self, capacity: u32, now: Instant) -> f64 {
+        let per_sec = capacity as f64 / 60.0;
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * per_sec).min(capacity as f64);
+        self.last_refill = now;
+        self.tokens
+    }
+
+    /// How long until a token is available, once refilled.
+    fn wait(&self, capacity: u32) -> Duration {
+        let per_sec = capacity as f64 / 60.0;
+        Duration::from_secs_f64((1.0 - self.tokens).max(0.0) / per_sec)
+    }
+
+    /// Take one token, or return how long until one is available.
+    pub fn try_take(&mut self, capacity: u32, now: Instant) -> Result<(), Duration> {
+        if self.refill(capacity, now) >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(self.wait(capacity))
+        }
+    }
+
+    /// Tokens available at `now`, without taking any.
+    pub fn available(&self, capacity: u32, now: Instant) -> f64 {
+        self.clone().refill(capacity, now)
+    }
+}
+
+/// `[limits]` in `cz-hub.toml`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// Requests per minute from one client IP, across all API routes.
+    #[serde(default = "default_ip_requests_per_minute")]
+    pub ip_requests_per_minute: u32,
+    /// Expensive requests per minute for one API key.
+    #[serde(default = "default_expensive_requests_per_minute")]
+    pub expensive_requests_per_minute: u32,
+    /// Expensive requests per minute from one client IP.
+    #[serde(default = "default_expensive_ip_requests_per_minute")]
+    pub expensive_ip_requests_per_minute: u32,
+    /// Reverse proxies whose `X-Forwarded-For` names the client.
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            ip_requests_per_minute: default_ip_requests_per_minute(),
+            expensive_requests_per_minute: default_expensive_requests_per_minute(),
+            expensive_ip_requests_per_minute: default_expensive_ip_requests_per_minute(),
+            trusted_proxies: Vec::new(),
+        }
+    }
+}
+
+fn default_ip_requests_per_minute() -> u32 {
+    1200
+}
+fn default_expensive_requests_per_minute() -> u32 {
+    30
+}
+fn default_expensive_ip_requests_per_minute() -> u32 {
+    60
+}
+
+/// Which budget a request draws from besides the general one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Class {
+    General,
+    /// Routes that scan the ring.
+    Expensive,
+}
+
+impl Class {
+    pub fn of(path: &str) -> Self {
+        match path {
+            "/api/export" | "/api/events/aggregate" | "/api/journal/verify" => Self::Expensive,
+            _ => Self::General,
+        }
+    }
+}
+
+/// One bucket as shown at `/api/limits`.
+#[derive(Debug, Clone, Serialize)]
+pub struct BucketState {
+    /// `key:<id>` or `ip:<addr>`.
+    pub subject: String,
+    pub class: Class,
+    pub capacity: u32,
+    pub tokens: f64,
+}
+
+pub struct RateLimiter {
+    /// Replaced on config reload; buckets keep their tokens.
+    config: std::sync::RwLock<LimitsConfig>,
+    buckets: RwLock<HashMap<(String, Class), TokenBucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: LimitsConfig) -> Self {
+        Self {
+            config: std::sync::RwLock::new(config),
+            buckets: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn config(&self) -> LimitsConfig {
+        self.config.read().unwrap().clone()
+    }
+
+    /// Apply new budgets to existing and future buckets.
+    pub fn set_config(&self, config: LimitsConfig) {
+        *self.config.write().unwrap() = config;
+    }
+
+    /// The client behind a connection from `peer`: the nearest
+    /// `X-Forwarded-For` hop that is not a trusted proxy, if `peer` is one,
+    /// otherwise `peer` itself. A malformed hop ends the walk at the proxy
+    /// that added it.
+    pub fn client_ip(&self, peer: Option<IpAddr>, forwarded_for: Option<&str>) -> Option<IpAddr> {
+        let config = self.config.read().unwrap();
+        let mut client = peer?;
+        for hop in forwarded_for.unwrap_or_default().rsplit(',') {
+            if !config.trusted_proxies.contains(&client) {
+                break;
+            }
+            match hop.trim().parse() {
+                Ok(ip) => client = ip,
+                Err(_) => break,
+            }
+        }
+        Some(client)
+    }
+
+    /// Take a token from every bucket a `class` request by `key_id` from
+    /// `ip` draws from, or none of them if any is empty; then the error is
+    /// the longest wait among the empty ones.
+    pub async fn check(
+        &self,
+        key_id: Option<&str>,
+        ip: Option<&str>,
+        class: Class,
+    ) -> Result<(), Duration> {
+        self.check_at(key_id, ip, class, Instant::now()).await
+    }
+
+    async fn check_at(
+        &self,
+        key_id: Option<&str>,
+        ip: Option<&str>,
+        class: Class,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        let config = self.config();
+        let mut wanted = Vec::new();
+        if let Some(ip) = ip {
+            wanted.push((
+                format!("ip:{}", ip),
+                Class::General,
+                config.ip_requests_per_minute,
+            ));
+        }
+        if class == Class::Expensive {
+            if let Some(key_id) = key_id {
+                wanted.push((
+                    format!("key:{}", key_id),
+                    Class::Expensive,
+                    config.expensive_requests_per_minute,
+                ));
+            }
+            if let Some(ip) = ip {
+                wanted.push((
+                    format!("ip:{}", ip),
+                    Class::Expensive,
+                    config.expensive_ip_requests_per_minute,
+                ));
+            }
+        }
+        wanted.retain(|(_, _, capacity)| *capacity > 0);
+
+        let mut buckets = self.buckets.write().await;
+        if buckets.len() + wanted.len() > MAX_BUCKETS {
+            buckets.retain(|(subject, class), bucket| {
+                let capacity = capacity_of(&config, subject, *class);
+                bucket.available(capacity, now) < capacity as f64
+            });
+        }
+        let mut wait = Duration::ZERO;
+        for (subject, class, capacity) in &wanted {
+            let bucket = buckets
+                .entry((subject.clone(), *class))
+                .or_insert_with(|| TokenBucket::full(*capacity, now));
+            if bucket.refill(*capacity, now) < 1.0 {
+                wait = wait.max(bucket.wait(*capacity));
+            }
+        }
+        if wait > Duration::ZERO {
+            return Err(wait);
+        }
+        for (subject, class, _) in &wanted {
+            if let Some(bucket) = buckets.get_mut(&(subject.clone(), *class)) {
+                bucket.tokens -= 1.0;
+            }
+        }
+        Ok(())
+    }
+
+    /// Every bucket with its tokens at `now`, by subject.
+    pub async fn snapshot(&self) -> Vec<BucketState> {
+        let now = Instant::now();
+        let config = self.config();
+        let buckets = self.buckets.read().await;
+        let mut states: Vec<BucketState> = buckets
+            .iter()
+            .map(|((subject, class), bucket)| {
+                let capacity = capacity_of(&config, subject, *class);
+                BucketState {
+                    subject: subject.clone(),
+                    class: *class,
+                    capacity,
+                    tokens: bucket.available(capacity, now),
+                }
+            })
+            .collect();
+        states.sort_by(|a, b| (&a.subject, a.class as u8).cmp(&(&b.subject, b.class as u8)));
+        states
+    }
+}
+
+fn capacity_of(config: &LimitsConfig, subject: &str, class: Class) -> u32 {
+    match (subject.starts_with("ip:"), class) {
+        (true, Class::General) => config.ip_requests_per_minute,
+        (true, Class::Expensive) => config.expensive_ip_requests_per_minute,
+        (false, _) => config.expensive_requests_per_minute,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(ip: u32, expensive: u32, expensive_ip: u32) -> RateLimiter {
+        RateLimiter::new(LimitsConfig {
+            ip_requests_per_minute: ip,
+            expensive_requests_per_minute: expensive,
+            expensive_ip_requests_per_minute: expensive_ip,
+            trusted_proxies: vec!["10.0.0.1".parse().unwrap(), "10.0.0.2".parse().unwrap()],
+        })
+    }
+
+    #[tokio::test]
+    async fn test_ip_bucket_exhausts_then_recovers() {
+        let limits = limiter(2, 0, 0);
+        let now = Instant::now();
+        for _ in 0..2 {
+            assert!(limits
+                .check_at(None, Some("10.0.0.1"), Class::General, now)
+                .await
+                .is_ok());
+        }
+        let wait = limits
+            .check_at(None, Some("10.0.0.1"), Class::General, now)
+            .await
+            .unwrap_err();
+        assert_eq!(wait.as_secs(), 30);
+        assert!(limits
+            .check_at(None, Some("10.0.0.2"), Class::General, now)
+            .await
+            .is_ok());
+
+        let later = now + Duration::from_secs(30);
+        assert!(limits
+            .check_at(None, Some("10.0.0.1"), Class::General, later)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_refused_request_takes_no_tokens() {
+        let limits = limiter(10, 1, 0);
+        let now = Instant::now();
+        let request = |class| limits.check_at(Some("k"), Some("10.0.0.1"), class, now);
+        assert!(request(Class::Expensive).await.is_ok());
+        assert!(request(Class::Expensive).await.is_err());
+
+        let snapshot = limits.snapshot().await;
+        let ip = snapshot
+            .iter()
+            .find(|b| b.subject == "ip:10.0.0.1" && b.class == Class::General)
+            .unwrap();
+        assert_eq!(ip.tokens.round(), 9.0);
+        assert_eq!(Class::of("/api/export"), Class::Expensive);
+        assert_eq!(Class::of("/api/events"), Class::General);
+    }
+
+    #[test]
+    fn test_forwarded_for_is_only_believed_from_trusted_proxies() {
+        let limits = limiter(0, 0, 0);
+        let ip = |peer: &str, xff| {
+            limits
+                .client_ip(Some(peer.parse().unwrap()), xff)
+                .map(|ip| ip.to_string())
+        };
+        // A client cannot pick its own bucket.
+        assert_eq!(
+            ip("198.51.100.9", Some("203.0.113.7")).unwrap(),
+            "198.51.100.9"
+        );
+        // Through two trusted proxies the nearest untrusted hop is the
+        // client, whatever it claimed before that.
+        assert_eq!(
+            ip("10.0.0.1", Some("1.2.3.4, 203.0.113.7, 10.0.0.2")).unwrap(),
+            "203.0.113.7"
+        );
+        assert_eq!(ip("10.0.0.1", None).unwrap(), "10.0.0.1");
+        assert_eq!(ip("10.0.0.1", Some("junk")).unwrap(), "10.0.0.1");
+        assert_eq!(limits.client_ip(None, Some("203.0.113.7")), None);
+    }
+
+    /// The limits as the hub's middleware applies them to requests.
+    mod middleware {
+        use crate::tests::{create_key, create_key_with, protected, test_state_with};
+        use crate::*;
+        use axum::body::Body;
+        use tower::ServiceExt;
+
+        #[tokio::test(start_paused = true)]
+        async fn test_expensive_route_limit_returns_429_then_recovers() {
+            let state = test_state_with(Config {
+                limits: limits::LimitsConfig {
+                    expensive_requests_per_minute: 2,
+                    ..Default::default()
+                },
+                ..Default::default()
+            });
+            let key = create_key(&state, "scraper").await;
+            let admin = create_key_with(&state, "admin", vec![auth::Scope::Admin]).await;
+            let app = protected(
+                &state,
+                Router::new()
+                    .route("/api/export", get(|| async { "rows" }))
+                    .route("/api/limits", get(api_limits))
+                    .layer(middleware::from_fn_with_state(
+                        state.clone(),
+                        rate_limit_middleware,
+                    )),
+            );
+            let get = |uri: &str, token: &str| {
+                Request::get(uri)
+                    .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                    .body(Body::empty())
+                    .unwrap()
+            };
+
+            for _ in 0..2 {
+                let response = app
+                    .clone()
+                    .oneshot(get("/api/export", &key.key))
+                    .await
+                    .unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+            }
+            let response = app
+                .clone()
+                .oneshot(get("/api/export", &key.key))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+            assert_eq!(response.headers()[header::RETRY_AFTER], "30");
+
+            let response = app
+                .clone()
+                .oneshot(get("/api/limits", &admin.key))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let limits: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            let bucket = limits["buckets"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|b| {
+                    b["subject"] == format!("key:{}", key.api_key.id) && b["class"] == "expensive"
+                })
+                .unwrap();
+            assert_eq!(bucket["capacity"], 2);
+            assert!(bucket["tokens"].as_f64().unwrap() < 1.0);
+
+            // A window later the key's expensive budget is back.
+            tokio::time::advance(std::time::Duration::from_secs(60)).await;
+            for _ in 0..2 {
+                let response = app
+                    .clone()
+                    .oneshot(get("/api/export", &key.key))
+                    .await
+                    .unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+            }
+        }
+
+        #[tokio::test]
+        async fn test_ip_limit_applies_before_auth_and_ignores_spoofed_forwarding() {
+            let state = test_state_with(Config {
+                limits: limits::LimitsConfig {
+                    ip_requests_per_minute: 2,
+                    ..Default::default()
+                },
+                ..Default::default()
+            });
+            let app = router(state.clone(), PathBuf::from("missing-dist"));
+            let anonymous = |spoofed: &str| {
+                let mut request = Request::get("/api/ring")
+                    .header("x-forwarded-for", spoofed)
+                    .body(Body::empty())
+                    .unwrap();
+                request
+                    .extensions_mut()
+                    .insert(ConnectInfo(SocketAddr::from(([198, 51, 100, 9], 50000))));
+                request
+            };
+
+            // Without a key each request is refused, but still counted; a new
+            // X-Forwarded-For on each does not earn a new bucket.
+            for spoofed in ["203.0.113.1", "203.0.113.2"] {
+                let response = app.clone().oneshot(anonymous(spoofed)).await.unwrap();
+                assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            }
+            let response = app.clone().oneshot(anonymous("203.0.113.3")).await.unwrap();
+            assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+            let buckets = state.limiter.snapshot().await;
+            assert_eq!(buckets.len(), 1);
+            assert_eq!(buckets[0].subject, "ip:198.51.100.9");
+        }
+    }
+}
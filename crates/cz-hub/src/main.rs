@@ -2,17 +2,20 @@ use axum::{
     extract::Request,
     extract::{
         ws::{Message, WebSocket, WebSocketUpgrade},
-        Query, State,
+        ConnectInfo, Query, State,
     },
     http::{header, Method, StatusCode},
     middleware::{self, Next},
-    response::{IntoResponse, Response},
+    response::{
+        sse::{Event as SseEvent, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
     routing::{get, post},
     Json, Router,
 };
 use clap::Parser;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::atomic::Ordering;
@@ -24,16 +27,27 @@ use tower_http::services::ServeDir;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 use cz_core::CausalEvent;
-use cz_io::cursor::Cursor;
-use cz_io::journal::{Journal, INDEX_RING_CAPACITY, INDEX_RING_SIZE};
+use cz_hub::anomaly::{Anomaly, AnomalyKind, AnomalyScanner};
+use cz_hub::query::executor::{causal_event_field, is_empty_event};
+use cz_hub::store::StateStore;
+use cz_hub::verify::{self, VerificationReport};
+use cz_hub::{connectors, payload, query, replay};
+use cz_io::checkpoint;
+use cz_io::cursor::MultiCursor;
+use cz_io::ipc::{IpcCommand, IpcMessage, SequencerStats};
+use cz_io::journal::{Journal, DEFAULT_JOURNAL_SIZE, HEADER_SIZE, MAX_CONSUMER_NAME_LEN};
+use cz_io::watermark::{WatermarkCrossing, WatermarkPolicy, Watermarks};
 
 mod alerts;
 mod api;
 mod auth;
-mod connectors;
 mod dashboards;
+mod ipc;
+mod limits;
+mod metrics;
 mod pipelines;
-mod query;
+mod saved_queries;
+mod tls;
 mod traces;
 
 // =============================================================================
@@ -41,18 +55,14 @@ mod traces;
 // =============================================================================
 
 #[derive(Parser)]
-#[command(
-    name = "cz-hub",
-    version = "0.3.0",
-    about = "LACRIMOSA Control Center"
-)]
+#[command(name = "cz-hub", version = "0.3.0", about = "LACRIMOSA Control Center")]
 struct Args {
     /// Path to the journal file(s)
     #[arg(long, default_value = "journal.db")]
     journals: Vec<PathBuf>,
 
     /// Journal size in bytes (only used if creating a new journal)
-    #[arg(long, default_value_t = 2 * 1024 * 1024)]
+    #[arg(long, default_value_t = DEFAULT_JOURNAL_SIZE)]
     journal_size: u64,
 
     /// Server bind address
@@ -62,21 +72,83 @@ struct Args {
     /// Path to config file
     #[arg(long, default_value = "cz-hub.toml")]
     config: PathBuf,
+
+    /// Directory to persist dashboards, saved queries, pipelines, alert
+    /// rules, connectors and API keys in; without it they live only in memory
+    #[arg(long)]
+    data_dir: Option<PathBuf>,
 }
 
 // =============================================================================
 // Config
 // =============================================================================
 
-#[derive(Deserialize, Default, Clone)]
+/// `cz-hub.toml`. Unknown keys are errors, so a misspelt setting is
+/// reported instead of silently left at its default.
+#[derive(Deserialize, Serialize, Default, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
 struct Config {
     #[serde(default)]
     alerts: AlertConfig,
     #[serde(default)]
     server: ServerConfig,
+    #[serde(default)]
+    auth: AuthConfig,
+    #[serde(default)]
+    ipc: IpcConfig,
+    #[serde(default)]
+    limits: limits::LimitsConfig,
+    #[serde(default)]
+    traces: TraceConfig,
+    /// Serve HTTPS instead of HTTP (`--features tls`).
+    #[serde(default)]
+    tls: Option<tls::TlsConfig>,
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct IpcConfig {
+    /// Follow each journal's sequencer. With `false` no IPC clients run and
+    /// readiness does not wait for them.
+    #[serde(default = "default_true")]
+    enabled: bool,
+    /// Sequencer socket per journal path, overriding the address the
+    /// sequencer recorded in the journal header.
+    #[serde(default)]
+    sockets: HashMap<PathBuf, String>,
+}
+
+impl Default for IpcConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            sockets: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct AuthConfig {
+    /// Default per-key request budget; keys may override it.
+    #[serde(default = "default_requests_per_minute")]
+    requests_per_minute: u32,
+    /// Also audit GET/HEAD requests (mutating requests are always audited).
+    #[serde(default)]
+    audit_reads: bool,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            requests_per_minute: auth::DEFAULT_REQUESTS_PER_MINUTE,
+            audit_reads: false,
+        }
+    }
 }
 
-#[derive(Deserialize, Clone)]
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
 struct AlertConfig {
     #[serde(default = "default_ring_threshold")]
     ring_utilization_warn: f64,
@@ -100,7 +172,8 @@ impl Default for AlertConfig {
     }
 }
 
-#[derive(Deserialize, Clone)]
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
 struct ServerConfig {
     #[serde(default = "default_metrics_interval")]
     metrics_interval_ms: u64,
@@ -117,6 +190,129 @@ impl Default for ServerConfig {
     }
 }
 
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct TraceConfig {
+    /// Traces held; the least recently updated are dropped beyond it.
+    #[serde(default = "default_max_traces")]
+    max_traces: usize,
+    /// Seconds a trace is held after its last span.
+    #[serde(default = "default_trace_max_age")]
+    max_age_secs: u64,
+    /// Tail sampling of complete traces, off by default.
+    #[serde(default)]
+    sampling: traces::sampling::SamplingPolicy,
+}
+
+impl Default for TraceConfig {
+    fn default() -> Self {
+        Self {
+            max_traces: traces::DEFAULT_MAX_TRACES,
+            max_age_secs: traces::DEFAULT_MAX_AGE.as_secs(),
+            sampling: Default::default(),
+        }
+    }
+}
+
+impl TraceConfig {
+    fn max_age(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.max_age_secs)
+    }
+}
+
+impl Config {
+    /// Parse the config at `path`; a missing file gives the defaults. The
+    /// error names the line, column and key at fault.
+    fn load(path: &std::path::Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        toml::from_str(&content).map_err(|e| format!("Invalid {}: {}", path.display(), e))
+    }
+
+    /// Reject settings the hub cannot run with.
+    fn validate(&self) -> Result<(), String> {
+        let alerts = &self.alerts;
+        for (name, pct) in [
+            ("ring_utilization_warn", alerts.ring_utilization_warn),
+            (
+                "ring_utilization_critical",
+                alerts.ring_utilization_critical,
+            ),
+            ("tps_drop_threshold", alerts.tps_drop_threshold),
+        ] {
+            if !(0.0..=100.0).contains(&pct) {
+                return Err(format!(
+                    "alerts.{} must be within 0..=100, got {}",
+                    name, pct
+                ));
+            }
+        }
+        if alerts.ring_utilization_warn >= alerts.ring_utilization_critical {
+            return Err(
+                "alerts.ring_utilization_warn must be below ring_utilization_critical".into(),
+            );
+        }
+        if self.server.metrics_interval_ms == 0 {
+            return Err("server.metrics_interval_ms must be at least 1".into());
+        }
+        if self.server.history_capacity == 0 {
+            return Err("server.history_capacity must be at least 1".into());
+        }
+        if self.traces.max_traces == 0 {
+            return Err("traces.max_traces must be at least 1".into());
+        }
+        if self.traces.max_age_secs == 0 {
+            return Err("traces.max_age_secs must be at least 1".into());
+        }
+        self.traces
+            .sampling
+            .validate()
+            .map_err(|e| format!("traces.sampling.{}", e))?;
+        if let Some(tls) = &self.tls {
+            tls.validate()?;
+        }
+        Ok(())
+    }
+}
+
+/// The config in effect and where it came from, for `GET /api/config`.
+struct ActiveConfig {
+    config: Arc<Config>,
+    loaded_at: String,
+    /// Modification time of the file it was read from, if it exists.
+    modified_at: Option<String>,
+}
+
+impl ActiveConfig {
+    fn new(config: Config, path: &std::path::Path) -> Self {
+        let modified_at = std::fs::metadata(path)
+            .and_then(|m| m.modified())
+            .ok()
+            .map(|t| chrono::DateTime::<chrono::Utc>::from(t).to_rfc3339());
+        Self {
+            config: Arc::new(config),
+            loaded_at: chrono::Utc::now().to_rfc3339(),
+            modified_at,
+        }
+    }
+}
+
+/// What `POST /api/config/reload` applied.
+#[derive(Serialize)]
+struct ConfigReload {
+    /// Sections and settings whose values changed.
+    changed: Vec<&'static str>,
+    /// Changes that need a restart and were not applied.
+    ignored: Vec<String>,
+    loaded_at: String,
+}
+
+fn default_true() -> bool {
+    true
+}
 fn default_ring_threshold() -> f64 {
     70.0
 }
@@ -129,34 +325,100 @@ fn default_tps_drop() -> f64 {
 fn default_idle_timeout() -> u64 {
     30
 }
+fn default_requests_per_minute() -> u32 {
+    auth::DEFAULT_REQUESTS_PER_MINUTE
+}
 fn default_metrics_interval() -> u64 {
     200
 }
 fn default_history_capacity() -> usize {
     3600
 }
+fn default_max_traces() -> usize {
+    traces::DEFAULT_MAX_TRACES
+}
+fn default_trace_max_age() -> u64 {
+    traces::DEFAULT_MAX_AGE.as_secs()
+}
 
 // =============================================================================
 // Application State
 // =============================================================================
 
+/// Lamport ticks revealed per second when replaying at 1× speed.
+const REPLAY_TICKS_PER_SEC: f64 = 1000.0;
+
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 #[serde(rename_all = "snake_case")]
 enum PlaybackMode {
     #[default]
     RealTime,
-    Paused {
-        at_slot: usize,
-        at_ts: u64,
+    /// Freeze on a historical moment: `at_ts` if non-zero, else `at_slot`.
+    Paused { at_slot: usize, at_ts: u64 },
+    /// Reveal history from `from_ts` onwards at `speed` × real time.
+    Replaying {
+        from_ts: u64,
+        speed: f64,
+        #[serde(default)]
+        started_at_ms: u64,
     },
 }
 
+/// How far into the ring clients may see under the current playback mode.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PlaybackHorizon {
+    Live,
+    /// Events with `lamport_ts` at or below this value.
+    UpToTs(u64),
+    /// Events up to and including this slot, in ring order.
+    UpToSlot(usize),
+}
+
+impl PlaybackMode {
+    fn horizon(&self, now_ms: u64) -> PlaybackHorizon {
+        match *self {
+            PlaybackMode::RealTime => PlaybackHorizon::Live,
+            PlaybackMode::Paused { at_ts, .. } if at_ts > 0 => PlaybackHorizon::UpToTs(at_ts),
+            PlaybackMode::Paused { at_slot, .. } => PlaybackHorizon::UpToSlot(at_slot),
+            PlaybackMode::Replaying {
+                from_ts,
+                speed,
+                started_at_ms,
+            } => {
+                let elapsed_secs = now_ms.saturating_sub(started_at_ms) as f64 / 1000.0;
+                let revealed = (elapsed_secs * speed * REPLAY_TICKS_PER_SEC) as u64;
+                PlaybackHorizon::UpToTs(from_ts.saturating_add(revealed))
+            }
+        }
+    }
+
+    fn is_paused(&self) -> bool {
+        matches!(self, PlaybackMode::Paused { .. })
+    }
+}
+
+fn unix_millis() -> u64 {
+    chrono::Utc::now().timestamp_millis().max(0) as u64
+}
+
 struct AppState {
     journals: RwLock<HashMap<PathBuf, Arc<JournalState>>>,
     playback: RwLock<PlaybackMode>,
     start_time: Instant,
-    config: Config,
+    /// The config file, re-read by `POST /api/config/reload` and SIGHUP.
+    config_path: PathBuf,
+    /// The settings in effect; read through [`AppState::config`].
+    active_config: std::sync::RwLock<ActiveConfig>,
     metrics_history: RwLock<VecDeque<MetricsSnapshot>>,
+    /// Messages decoded from the sequencers' push sockets.
+    ipc_events: tokio::sync::broadcast::Sender<ipc::SequencerEvent>,
+    /// Serialized `metrics` WS messages from the collector, shared by
+    /// every subscriber.
+    metrics_feed: tokio::sync::broadcast::Sender<Arc<str>>,
+    /// Query latency and ingest rejections, exported at `/metrics`.
+    metrics: metrics::HubMetrics,
+    /// Kani runs started through `POST /api/verify`.
+    verify_jobs: RwLock<VerifyJobs>,
 
     // Legacy fields (will migrate to new modules)
     alerts: RwLock<Vec<Alert>>,
@@ -166,51 +428,634 @@ struct AppState {
     connector_registry: Arc<connectors::registry::ConnectorRegistry>,
     alert_engine: Arc<alerts::AlertEngine>,
     trace_store: Arc<traces::TraceStore>,
+    /// Journal and connector events by the trace they name.
+    trace_correlations: traces::correlation::CorrelationIndex,
     pipeline_manager: Arc<pipelines::PipelineManager>,
     dashboard_manager: Arc<dashboards::DashboardManager>,
+    saved_queries: Arc<saved_queries::SavedQueryManager>,
+    /// Recent query results, dropped whenever a new event arrives.
+    query_cache: cz_hub::query::cache::QueryCache,
+    /// Live queries created over HTTP and streamed to a WS connection.
+    query_subscriptions: cz_hub::query::subscriptions::QuerySubscriptions,
     auth_layer: Arc<auth::AuthLayer>,
+    /// Per-IP and expensive-route request budgets.
+    limiter: limits::RateLimiter,
+    /// Where hub state is persisted, with `--data-dir`.
+    state_store: Option<Arc<StateStore>>,
+    /// Unix millis of the metrics collector's last tick (0 before the
+    /// first), read by `/readyz`.
+    last_tick: std::sync::atomic::AtomicI64,
+    /// Unix millis when the collector last saw `EVENTS_PROCESSED` grow (0
+    /// before it has), read by `/readyz`.
+    last_event_advance: std::sync::atomic::AtomicI64,
+    /// Journals given with `--journal`, which `/readyz` expects open.
+    configured_journals: Vec<PathBuf>,
+    /// Certificates HTTPS connections are accepted with, when serving TLS.
+    #[cfg(feature = "tls")]
+    tls: Option<Arc<tls::TlsAcceptorHandle>>,
 }
 
 #[derive(Deserialize)]
 struct PlaybackSetParams {
-    mode: String, // "real_time" or "paused"
+    mode: String, // "real_time", "paused" or "replaying"
     slot: Option<usize>,
     ts: Option<u64>,
+    speed: Option<f64>,
 }
 
 impl AppState {
-    async fn get_journal(&self, path: Option<String>) -> Option<Arc<JournalState>> {
+    /// The settings in effect, including reloaded ones.
+    fn config(&self) -> Arc<Config> {
+        self.active_config.read().unwrap().config.clone()
+    }
+
+    /// Re-read the config file and apply what can change at runtime:
+    /// alert thresholds, metrics interval, history capacity, rate limits
+    /// and TLS certificates. `[ipc]` changes and turning TLS on or off are
+    /// ignored with a warning; an unreadable or invalid file (or
+    /// certificate) leaves the active config untouched.
+    async fn reload_config(&self) -> Result<ConfigReload, String> {
+        let mut config = Config::load(&self.config_path)?;
+        config.validate()?;
+        let old = self.config();
+
+        let mut ignored = Vec::new();
+        let mut ignore = |warning: &str| {
+            tracing::warn!("Config reload: {}", warning);
+            ignored.push(warning.to_string());
+        };
+        if config.ipc != old.ipc {
+            ignore("[ipc] changed; restart the hub to apply it");
+            config.ipc = old.ipc.clone();
+        }
+        if config.tls.is_some() != old.tls.is_some() {
+            ignore("[tls] added or removed; restart the hub to switch protocols");
+            config.tls = old.tls.clone();
+        }
+
+        let mut changed = Vec::new();
+        #[cfg(feature = "tls")]
+        if let (Some(handle), Some(tls)) = (&self.tls, &config.tls) {
+            handle.reload(tls)?;
+            changed.push("tls");
+        }
+        if config.alerts != old.alerts {
+            changed.push("alerts");
+            let defaults = default_alert_rules(&config.alerts);
+            for rule in self.alert_rules.write().await.iter_mut() {
+                if let Some(default) = defaults.iter().find(|d| d.name == rule.name) {
+                    rule.threshold = default.threshold;
+                }
+            }
+            let policy = config.alerts.watermark_policy();
+            for journal in self.journals.read().await.values() {
+                journal.watermarks.lock().await.set_policy(policy);
+            }
+        }
+        if config.server.metrics_interval_ms != old.server.metrics_interval_ms {
+            changed.push("server.metrics_interval_ms");
+        }
+        if config.server.history_capacity != old.server.history_capacity {
+            changed.push("server.history_capacity");
+            let mut history = self.metrics_history.write().await;
+            let excess = history.len().saturating_sub(config.server.history_capacity);
+            history.drain(..excess);
+        }
+        if config.auth != old.auth {
+            changed.push("auth");
+            self.auth_layer
+                .set_default_requests_per_minute(config.auth.requests_per_minute);
+        }
+        if config.limits != old.limits {
+            changed.push("limits");
+            self.limiter.set_config(config.limits.clone());
+        }
+        if config.traces != old.traces {
+            changed.push("traces");
+            self.trace_store
+                .set_limits(config.traces.max_traces, config.traces.max_age())
+                .await;
+            self.trace_store
+                .set_sampling(config.traces.sampling.clone());
+        }
+
+        let active = ActiveConfig::new(config, &self.config_path);
+        let loaded_at = active.loaded_at.clone();
+        *self.active_config.write().unwrap() = active;
+        tracing::info!(
+            "Config reloaded from {:?}: {:?} changed",
+            self.config_path,
+            changed
+        );
+        Ok(ConfigReload {
+            changed,
+            ignored,
+            loaded_at,
+        })
+    }
+
+    /// Address of the sequencer writing `journal`: configured, else as
+    /// recorded in the journal header, else the derived default.
+    fn sequencer_socket(&self, journal: &std::path::Path) -> String {
+        self.config()
+            .ipc
+            .sockets
+            .get(journal)
+            .cloned()
+            .unwrap_or_else(|| cz_io::ipc::resolve_socket(journal))
+    }
+
+    /// The event a `SlotCommitted` notification refers to, read from the
+    /// journal it names.
+    async fn committed_event(&self, event: &ipc::SequencerEvent) -> Option<CausalEvent> {
+        let IpcMessage::SlotCommitted { slot, .. } = event.message else {
+            return None;
+        };
+        let journal_state = self.journals.read().await.get(&event.journal).cloned()?;
+        let journal = journal_state.journal.read().await;
+        journal.event_at(slot as usize)
+    }
+
+    /// A committed journal event as its connector's [`StreamEvent`],
+    /// stamped with the time it was seen, for live queries to match.
+    async fn committed_stream_event(
+        &self,
+        event: &ipc::SequencerEvent,
+    ) -> Option<connectors::StreamEvent> {
+        let IpcMessage::SlotCommitted { slot, .. } = event.message else {
+            return None;
+        };
+        let journal_state = self.journals.read().await.get(&event.journal).cloned()?;
+        let connector_id = journal_state.connector_id.clone()?;
+        let journal = journal_state.journal.read().await;
+        let committed = journal.event_at(slot as usize)?;
+        let source = query::executor::JournalSource {
+            connector_id,
+            name: connectors::journal::stream_name(&journal_state.path),
+            journal: &journal,
+        };
+        let mut event = query::executor::journal_stream_event(&source, slot as usize, &committed);
+        event.timestamp = chrono::Utc::now().to_rfc3339();
+        Some(event)
+    }
+
+    /// Index the trace named by the journal event at `slot`, if any.
+    async fn correlate_journal_event(
+        &self,
+        journal_state: &JournalState,
+        journal: &Journal,
+        slot: usize,
+        event: &CausalEvent,
+    ) {
+        let source = query::executor::JournalSource {
+            connector_id: journal_state.connector_id.clone().unwrap_or_default(),
+            name: connectors::journal::stream_name(&journal_state.path),
+            journal,
+        };
+        let stream_event = query::executor::journal_stream_event(&source, slot, event);
+        if let Some(trace_id) =
+            traces::correlation::trace_id_of(&stream_event.payload, &stream_event.metadata)
+        {
+            let correlated = traces::correlation::CorrelatedEvent::Journal {
+                journal: journal_state.path.display().to_string(),
+                slot,
+                lamport_ts: event.lamport_ts,
+            };
+            self.trace_correlations.record(trace_id, correlated).await;
+        }
+    }
+
+    /// Index the traces named by the events in a journal's live window.
+    async fn correlate_journal(&self, journal_state: &JournalState) {
+        let journal = journal_state.journal.read().await;
+        let cursor = journal.live_cursor_view();
+        for slot in cursor.slots() {
+            if let Some(event) = journal.event_at(slot) {
+                self.correlate_journal_event(journal_state, &journal, slot, &event)
+                    .await;
+            }
+        }
+    }
+
+    /// Index the trace named by the event a `SlotCommitted` refers to.
+    async fn correlate_commit(&self, commit: &ipc::SequencerEvent) {
+        let IpcMessage::SlotCommitted { slot, .. } = commit.message else {
+            return;
+        };
+        let Some(journal_state) = self.journals.read().await.get(&commit.journal).cloned() else {
+            return;
+        };
+        let journal = journal_state.journal.read().await;
+        if let Some(event) = journal.event_at(slot as usize) {
+            self.correlate_journal_event(&journal_state, &journal, slot as usize, &event)
+                .await;
+        }
+    }
+
+    /// The events correlated with `trace_id` that are still where they were
+    /// indexed: a journal slot the ring has since reused is left out.
+    async fn correlated_events(&self, trace_id: &str) -> Vec<traces::correlation::CorrelatedEvent> {
+        let mut events = self.trace_correlations.events(trace_id).await;
+        let journals = self.journals.read().await.clone();
+        let mut live = Vec::with_capacity(events.len());
+        for event in events.drain(..) {
+            if let traces::correlation::CorrelatedEvent::Journal {
+                journal,
+                slot,
+                lamport_ts,
+            } = &event
+            {
+                let Some(journal_state) = journals.get(std::path::Path::new(journal)) else {
+                    continue;
+                };
+                let current = journal_state.journal.read().await.event_at(*slot);
+                if current.is_none_or(|e| e.lamport_ts != *lamport_ts) {
+                    continue;
+                }
+            }
+            live.push(event);
+        }
+        live
+    }
+
+    /// The journal named by id or path; without one, the open journal with
+    /// the lowest path.
+    async fn get_journal(&self, journal: Option<String>) -> Option<Arc<JournalState>> {
         let journals = self.journals.read().await;
-        if let Some(p) = path {
-            journals.get(&PathBuf::from(p)).cloned()
-        } else {
-            journals.values().next().cloned()
+        match journal {
+            Some(name) => journals
+                .get(std::path::Path::new(&name))
+                .or_else(|| journals.values().find(|j| j.id == name))
+                .cloned(),
+            None => journals
+                .values()
+                .filter(|j| !j.is_closing())
+                .min_by(|a, b| a.path.cmp(&b.path))
+                .cloned(),
+        }
+    }
+
+    /// [`Self::get_journal`] for handlers: 404 if unknown, 409 while it is
+    /// being removed.
+    async fn resolve_journal(
+        &self,
+        journal: Option<String>,
+    ) -> Result<Arc<JournalState>, (StatusCode, Json<ApiError>)> {
+        let journal_state = self.get_journal(journal).await.ok_or((
+            StatusCode::NOT_FOUND,
+            Json(ApiError {
+                error: "Journal not found".into(),
+            }),
+        ))?;
+        journal_state.ensure_open()?;
+        Ok(journal_state)
+    }
+
+    /// Open the journal at `path`, register its connector and follow its
+    /// sequencer. A read-only journal must already exist.
+    async fn open_journal(
+        &self,
+        path: PathBuf,
+        size: u64,
+        readonly: bool,
+    ) -> Result<Arc<JournalState>, (StatusCode, Json<ApiError>)> {
+        let error = |status, error: String| (status, Json(ApiError { error }));
+        if self.journals.read().await.contains_key(&path) {
+            return Err(error(
+                StatusCode::CONFLICT,
+                format!("Journal {} is already open", path.display()),
+            ));
+        }
+        if readonly && !path.exists() {
+            return Err(error(
+                StatusCode::NOT_FOUND,
+                format!("Journal {} does not exist", path.display()),
+            ));
+        }
+        let journal = Journal::open(&path, size).map_err(|e| {
+            error(
+                StatusCode::BAD_REQUEST,
+                format!("Failed to open journal at {}: {}", path.display(), e),
+            )
+        })?;
+
+        let mut journal_state = JournalState::new(
+            path.clone(),
+            journal,
+            self.config().alerts.watermark_policy(),
+        );
+        journal_state.readonly = readonly;
+
+        let mut journals = self.journals.write().await;
+        if journals.contains_key(&path) {
+            return Err(error(
+                StatusCode::CONFLICT,
+                format!("Journal {} is already open", path.display()),
+            ));
+        }
+        let connector = Arc::new(connectors::journal::JournalConnector::new(path.clone()));
+        journal_state.connector_id =
+            Some(connectors::StreamConnector::id(connector.as_ref()).to_string());
+        self.connector_registry.add(connector).await.ok();
+        if self.config().ipc.enabled {
+            let socket = self.config().ipc.sockets.get(&path).cloned();
+            let client = ipc::IpcClient::new(path.clone(), socket);
+            journal_state.ipc_connected = Some(client.connected());
+            journal_state.ipc_task =
+                Some(tokio::spawn(client.run(self.ipc_events.clone())).abort_handle());
+        }
+
+        let journal_state = Arc::new(journal_state);
+        journals.insert(path, journal_state.clone());
+        Ok(journal_state)
+    }
+
+    /// Unregister a journal: new lookups get 409 until it is gone, in-flight
+    /// reads finish before it is flushed, and the file is unmapped once
+    /// the last request holding it completes.
+    async fn remove_journal(
+        &self,
+        journal: &str,
+    ) -> Result<Arc<JournalState>, (StatusCode, Json<ApiError>)> {
+        let journal_state = self.resolve_journal(Some(journal.to_string())).await?;
+        if journal_state.closing.swap(true, Ordering::AcqRel) {
+            return Err((
+                StatusCode::CONFLICT,
+                Json(ApiError {
+                    error: format!("Journal {} is being removed", journal_state.id),
+                }),
+            ));
+        }
+
+        if let Err(e) = journal_state.journal.write().await.flush() {
+            tracing::warn!("Failed to flush {:?} on removal: {}", journal_state.path, e);
+        }
+        if let Some(task) = &journal_state.ipc_task {
+            task.abort();
+        }
+        if let Some(id) = &journal_state.connector_id {
+            self.connector_registry.remove(id).await.ok();
+        }
+        self.journals.write().await.remove(&journal_state.path);
+        Ok(journal_state)
+    }
+
+    /// Ring occupancy summed over every journal, with a per-journal
+    /// breakdown sorted by path.
+    async fn aggregate_ring_state(&self) -> RingAggregate {
+        let journals: Vec<Arc<JournalState>> =
+            self.journals.read().await.values().cloned().collect();
+        let mut breakdown = Vec::with_capacity(journals.len());
+        for journal_state in journals {
+            let cursor = journal_state.journal.read().await.live_cursor_view();
+            breakdown.push(JournalRingUsage {
+                path: journal_state.path.display().to_string(),
+                head: cursor.head(),
+                tail: cursor.tail(),
+                used: cursor.len(),
+                capacity: cursor.capacity(),
+                utilization_pct: cursor.utilization_pct(),
+            });
+        }
+        breakdown.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let used = breakdown.iter().map(|j| j.used).sum();
+        let capacity = breakdown.iter().map(|j| j.capacity).sum();
+        RingAggregate {
+            used,
+            capacity,
+            // With no journals open there is nothing to fill.
+            utilization_pct: if capacity > 0 {
+                used as f64 / capacity as f64 * 100.0
+            } else {
+                0.0
+            },
+            journals: breakdown,
         }
     }
 }
 
+/// A rate or percentage to two decimals, as the API reports them.
+fn round(v: f64) -> f64 {
+    (v * 100.0).round() / 100.0
+}
+
+/// Index Ring occupancy across all journals.
+struct RingAggregate {
+    used: usize,
+    capacity: usize,
+    utilization_pct: f64,
+    journals: Vec<JournalRingUsage>,
+}
+
+#[derive(Serialize, Clone)]
+struct JournalRingUsage {
+    path: String,
+    head: usize,
+    tail: usize,
+    used: usize,
+    capacity: usize,
+    utilization_pct: f64,
+}
+
 struct JournalState {
+    /// Opaque handle clients may use instead of the path.
+    id: String,
     path: PathBuf,
+    /// Refuse hub writes (simulate, replay target, checkpoints, consumers).
+    readonly: bool,
+    /// Set once `DELETE /api/journals/{id}` starts; lookups then get 409.
+    closing: std::sync::atomic::AtomicBool,
     journal: RwLock<Journal>,
-    cursor: RwLock<Cursor>,
+    cursor: RwLock<MultiCursor>,
+    watermarks: tokio::sync::Mutex<Watermarks>,
+    /// One scanner per partition, checking new slots for anomalies.
+    anomalies: tokio::sync::Mutex<Vec<AnomalyScanner>>,
+    /// The journal's entry in the connector registry.
+    connector_id: Option<String>,
+    /// Client following the journal's sequencer, stopped on removal.
+    ipc_task: Option<tokio::task::AbortHandle>,
+    /// Whether that client is connected; `None` without a client.
+    ipc_connected: Option<Arc<std::sync::atomic::AtomicBool>>,
+}
+
+impl JournalState {
+    /// Wrap an open journal, picking up where the sequencer (or a previous
+    /// hub) left the ring and its consumers.
+    fn new(path: PathBuf, journal: Journal, policy: WatermarkPolicy) -> Self {
+        let cursor =
+            MultiCursor::with_consumers(journal.live_cursor_view(), journal.consumer_offsets());
+        Self {
+            id: format!("jnl-{}", uuid::Uuid::new_v4().as_simple()),
+            path,
+            readonly: false,
+            closing: Default::default(),
+            journal: RwLock::new(journal),
+            cursor: RwLock::new(cursor),
+            watermarks: tokio::sync::Mutex::new(Watermarks::new(policy)),
+            anomalies: Default::default(),
+            connector_id: None,
+            ipc_task: None,
+            ipc_connected: None,
+        }
+    }
+
+    fn is_closing(&self) -> bool {
+        self.closing.load(Ordering::Acquire)
+    }
+
+    /// 409 once the journal is being removed.
+    fn ensure_open(&self) -> Result<(), (StatusCode, Json<ApiError>)> {
+        if self.is_closing() {
+            return Err((
+                StatusCode::CONFLICT,
+                Json(ApiError {
+                    error: format!("Journal {} is being removed", self.id),
+                }),
+            ));
+        }
+        Ok(())
+    }
+
+    /// 409 unless the hub may write to the journal.
+    fn ensure_writable(&self) -> Result<(), (StatusCode, Json<ApiError>)> {
+        self.ensure_open()?;
+        if self.readonly {
+            return Err((
+                StatusCode::CONFLICT,
+                Json(ApiError {
+                    error: format!("Journal {} is read-only", self.id),
+                }),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl AlertConfig {
+    /// Ring utilization watermarks: raise at `critical`, clear at `warn`.
+    fn watermark_policy(&self) -> WatermarkPolicy {
+        let policy = WatermarkPolicy {
+            low_pct: self.ring_utilization_warn,
+            high_pct: self.ring_utilization_critical,
+        };
+        if policy.low_pct < policy.high_pct {
+            policy
+        } else {
+            tracing::warn!("ring_utilization_warn must be below ring_utilization_critical; using defaults for watermarks");
+            WatermarkPolicy::default()
+        }
+    }
 }
 
 // =============================================================================
 // Types
 // =============================================================================
 
+/// Version of the `MetricsSnapshot` payload. Version 2 replaced the
+/// primary journal's `head`/`tail` with the per-journal `journals` map.
+const METRICS_SCHEMA: u32 = 2;
+
 #[derive(Serialize, Clone)]
 struct MetricsSnapshot {
+    schema: u32,
     timestamp: String,
     events: u64,
     bytes: u64,
     tps: f64,
+    /// Mean and standard deviation of `tps` over the last
+    /// [`TPS_BASELINE_WINDOW`] recorded snapshots; `None` until there are
+    /// that many.
+    tps_mean: Option<f64>,
+    tps_stddev: Option<f64>,
+    /// Standard deviations `tps` lies from `tps_mean`; 0 without a
+    /// baseline or when the rate has not varied.
+    tps_zscore: f64,
     bps: f64,
-    head: usize,
-    tail: usize,
+    /// Utilization across all journals.
     utilization_pct: f64,
     uptime_seconds: u64,
     playback_mode: PlaybackMode,
+    /// Ring stats keyed by journal path.
+    journals: BTreeMap<String, JournalRingStats>,
+}
+
+/// Recorded snapshots the TPS baseline spans: five minutes at one a second.
+const TPS_BASELINE_WINDOW: usize = 300;
+
+/// Rolling mean and standard deviation of recorded TPS, kept as running
+/// sums so each sample costs the same however long the window.
+struct TpsBaseline {
+    window: usize,
+    samples: VecDeque<f64>,
+    sum: f64,
+    sum_sq: f64,
+}
+
+impl TpsBaseline {
+    fn new(window: usize) -> Self {
+        Self {
+            window,
+            samples: VecDeque::with_capacity(window),
+            sum: 0.0,
+            sum_sq: 0.0,
+        }
+    }
+
+    /// Add a sample, dropping the oldest once the window is full.
+    fn push(&mut self, tps: f64) {
+        if self.samples.len() == self.window {
+            if let Some(old) = self.samples.pop_front() {
+                self.sum -= old;
+                self.sum_sq -= old * old;
+            }
+        }
+        self.samples.push_back(tps);
+        self.sum += tps;
+        self.sum_sq += tps * tps;
+    }
+
+    /// Mean and population standard deviation, once the window is full.
+    fn stats(&self) -> Option<(f64, f64)> {
+        if self.samples.len() < self.window {
+            return None;
+        }
+        let n = self.samples.len() as f64;
+        let mean = self.sum / n;
+        // Running sums can drift a hair below zero for a flat series.
+        let variance = (self.sum_sq / n - mean * mean).max(0.0);
+        Some((mean, variance.sqrt()))
+    }
+
+    /// How far `tps` lies from the mean, in standard deviations.
+    fn zscore(&self, tps: f64) -> f64 {
+        match self.stats() {
+            Some((mean, stddev)) if stddev > f64::EPSILON => (tps - mean) / stddev,
+            _ => 0.0,
+        }
+    }
+}
+
+/// One journal's Index Ring in a metrics snapshot.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+struct JournalRingStats {
+    head: usize,
+    tail: usize,
+    used: usize,
+    capacity: usize,
+    utilization_pct: f64,
+}
+
+impl From<&JournalRingUsage> for JournalRingStats {
+    fn from(usage: &JournalRingUsage) -> Self {
+        Self {
+            head: usage.head,
+            tail: usage.tail,
+            used: usage.used,
+            capacity: usage.capacity,
+            utilization_pct: round(usage.utilization_pct),
+        }
+    }
 }
 #[derive(Serialize, Clone)]
 struct Alert {
@@ -246,6 +1091,10 @@ struct SystemStatus {
     bytes_processed: u64,
     current_tps: f64,
     current_bps: f64,
+    ring_used: usize,
+    ring_capacity: usize,
+    ring_utilization_pct: f64,
+    journals: Vec<JournalRingUsage>,
 }
 
 #[derive(Serialize)]
@@ -259,6 +1108,42 @@ struct RingState {
     is_empty: bool,
     bytes_per_slot: usize,
     total_bytes: usize,
+    /// Sub-rings the Index Ring is split into; the fields above describe
+    /// the one requested.
+    partitions: usize,
+}
+
+/// Event flag bits by the name the API uses for them.
+const EVENT_FLAGS: [(&str, u16); 3] = [
+    ("checkpoint", cz_core::FLAG_CHECKPOINT),
+    ("resequenced", cz_core::FLAG_RESEQUENCED),
+    ("compressed", cz_core::FLAG_COMPRESSED),
+];
+
+/// Names of the known bits set in `flags`.
+fn flag_names(flags: u16) -> Vec<&'static str> {
+    EVENT_FLAGS
+        .iter()
+        .filter(|(_, bit)| flags & bit != 0)
+        .map(|(name, _)| *name)
+        .collect()
+}
+
+/// Bits of a comma-separated list of flag names, e.g. `checkpoint,compressed`.
+fn parse_flags(list: &str) -> Result<u16, String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .try_fold(0, |bits, name| {
+            EVENT_FLAGS
+                .iter()
+                .find(|(known, _)| *known == name)
+                .map(|(_, bit)| bits | bit)
+                .ok_or_else(|| {
+                    let known: Vec<&str> = EVENT_FLAGS.iter().map(|(name, _)| *name).collect();
+                    format!("Unknown flag '{}' (expected {})", name, known.join(", "))
+                })
+        })
 }
 
 #[derive(Serialize)]
@@ -270,15 +1155,52 @@ struct EventRecord {
     payload_offset: u64,
     checksum: u32,
     checkpoint: bool,
+    /// Stored payload bytes, from the length prefix in Blob Storage.
+    payload_size: usize,
+    flags: u16,
+    /// Known bits of `flags`, see [`EVENT_FLAGS`].
+    flag_names: Vec<&'static str>,
+    /// Whether the payload matches `checksum`; only checked on request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    checksum_valid: Option<bool>,
 }
 
-#[derive(Serialize)]
-struct EventDetailRecord {
-    #[serde(flatten)]
-    event: EventRecord,
-    payload_hex: String,
+impl EventRecord {
+    /// With `verify`, the payload is also checked against the checksum.
+    fn new(journal: &Journal, slot: usize, event: &CausalEvent, verify: bool) -> Self {
+        Self {
+            slot,
+            lamport_ts: event.lamport_ts,
+            node_id: event.node_id,
+            stream_id: event.stream_id,
+            payload_offset: event.payload_offset,
+            checksum: event.checksum,
+            checkpoint: event.is_checkpoint(),
+            payload_size: journal.read_payload(event).len(),
+            flags: event.flags,
+            flag_names: flag_names(event.flags),
+            checksum_valid: verify.then(|| checksum_valid(journal, event)),
+        }
+    }
+}
+
+fn checksum_valid(journal: &Journal, event: &CausalEvent) -> bool {
+    journal.check_payload(event) == cz_io::journal::PayloadCheck::Matched
+}
+
+#[derive(Serialize)]
+struct EventDetailRecord {
+    #[serde(flatten)]
+    event: EventRecord,
+    payload_hex: String,
     payload_ascii: String,
-    payload_size: usize,
+    /// The payload was sent compressed; the bytes above are decompressed.
+    compressed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    decompressed_size: Option<usize>,
+    /// Trace the event names, see `GET /api/traces/:id`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trace_id: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -287,6 +1209,8 @@ struct EventListResponse {
     total: usize,
     offset: usize,
     limit: usize,
+    /// Pass back as `cursor` for the page after this one.
+    next_cursor: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -296,24 +1220,338 @@ struct EventQueryParams {
     stream_id: Option<u16>,
     ts_min: Option<u64>,
     ts_max: Option<u64>,
+    /// Rows to skip. Unstable: rows shift as the ring's tail advances, so
+    /// pages can repeat or miss events; page with `cursor` instead.
     offset: Option<usize>,
     limit: Option<usize>,
-    query: Option<String>, // e.g. "node_id == 1 && stream_id > 0"
+    query: Option<String>, // WHERE clause, e.g. "node_id = 1 AND stream_id > 0"
+    /// `next_cursor` of the previous page.
+    cursor: Option<String>,
+    /// Sub-ring of a partitioned journal; see [`partition_view`].
+    partition: Option<usize>,
+    /// Only events with all of these flags, e.g. `checkpoint,compressed`.
+    flags: Option<String>,
+    /// Only events whose payload does (`true`) or does not match their
+    /// checksum; implies `verify`.
+    checksum_valid: Option<bool>,
+    /// Check each returned event's payload against its checksum.
+    verify: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct EventDetailParams {
+    /// Check the payload against the checksum.
+    verify: Option<bool>,
+}
+
+/// Keyset position in `/api/events`: the last event a page returned. It
+/// names the event rather than a row number, so a page follows on from
+/// the last one however far the ring moved in between.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct EventsCursor {
+    lamport_ts: u64,
+    node_id: u32,
+    stream_id: u16,
+    slot: usize,
+}
+
+impl EventsCursor {
+    const ENCODED_LEN: usize = 22;
+
+    fn after(slot: usize, event: &CausalEvent) -> Self {
+        Self {
+            lamport_ts: event.lamport_ts,
+            node_id: event.node_id,
+            stream_id: event.stream_id,
+            slot,
+        }
+    }
+
+    /// Opaque to clients: URL-safe base64 of the fields, little-endian.
+    fn encode(&self) -> String {
+        use base64::Engine;
+        let mut bytes = Vec::with_capacity(Self::ENCODED_LEN);
+        bytes.extend(self.lamport_ts.to_le_bytes());
+        bytes.extend(self.node_id.to_le_bytes());
+        bytes.extend(self.stream_id.to_le_bytes());
+        bytes.extend((self.slot as u64).to_le_bytes());
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+    }
+
+    fn decode(raw: &str) -> Option<Self> {
+        use base64::Engine;
+        let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(raw)
+            .ok()?;
+        let bytes: [u8; Self::ENCODED_LEN] = bytes.try_into().ok()?;
+        Some(Self {
+            lamport_ts: u64::from_le_bytes(bytes[0..8].try_into().ok()?),
+            node_id: u32::from_le_bytes(bytes[8..12].try_into().ok()?),
+            stream_id: u16::from_le_bytes(bytes[12..14].try_into().ok()?),
+            slot: u64::from_le_bytes(bytes[14..22].try_into().ok()?) as usize,
+        })
+    }
+
+    /// Logical index of the first event after this one in `cursor`'s
+    /// window.
+    fn resume_index(&self, journal: &Journal, cursor: &cz_io::cursor::Cursor) -> usize {
+        if let Some(index) = cursor.logical_index(self.slot) {
+            // SAFETY: live slots are in range and fully written.
+            let event = unsafe { journal.read_event_at(self.slot) };
+            if Self::after(self.slot, &event) == *self {
+                return index + 1;
+            }
+        }
+        // The event has left the window, and with it everything older:
+        // seek past its timestamp.
+        match self.lamport_ts.checked_add(1) {
+            Some(past) => journal.find_first_ge(cursor, past),
+            None => cursor.len(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct EventStreamParams {
+    journal: Option<String>,
+    node_id: Option<u32>,
+    stream_id: Option<u16>,
+}
+
+impl EventStreamParams {
+    fn matches(&self, event: &CausalEvent) -> bool {
+        self.node_id.is_none_or(|id| event.node_id == id)
+            && self.stream_id.is_none_or(|id| event.stream_id == id)
+    }
+}
+
+/// One `data:` line of `/api/events/stream`; the SSE id is the slot.
+#[derive(Serialize)]
+struct StreamedEvent {
+    slot: u64,
+    lamport_ts: u64,
+    node_id: u32,
+    stream_id: u16,
+    flags: u16,
 }
 
 #[derive(Deserialize)]
+struct EventSearchParams {
+    journal: Option<String>,
+    ts_min: Option<u64>,
+    ts_max: Option<u64>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    partition: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct EventSearchResponse {
+    events: Vec<EventRecord>,
+    /// Live events inside the range.
+    matched: usize,
+    /// Position of the first match counted from the ring tail.
+    first_index: usize,
+    offset: usize,
+    limit: usize,
+}
+
+/// Most buckets one aggregation may return.
+const MAX_AGGREGATE_BUCKETS: u64 = 10_000;
+/// Most distinct groups one aggregation may return.
+const MAX_AGGREGATE_GROUPS: usize = 1_000;
+
+#[derive(Deserialize, Default)]
+struct AggregateParams {
+    journal: Option<String>,
+    /// `stream_id`, `node_id`, or both separated by a comma.
+    group_by: Option<String>,
+    /// Bucket width: Lamport ticks, or a duration such as `1s` or `250ms`
+    /// at [`REPLAY_TICKS_PER_SEC`].
+    bucket: Option<String>,
+    /// `count` (default) or `bytes` of stored packets.
+    metric: Option<String>,
+    ts_min: Option<u64>,
+    ts_max: Option<u64>,
+    partition: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct AggregateBucket {
+    /// First Lamport timestamp the bucket covers.
+    ts: u64,
+    /// Metric per group key: `"7"`, or `"<node_id>:<stream_id>"` when
+    /// grouping by both.
+    groups: BTreeMap<String, u64>,
+}
+
+#[derive(Serialize)]
+struct AggregateResponse {
+    bucket_ticks: u64,
+    /// Live events inside the range.
+    matched: usize,
+    buckets: Vec<AggregateBucket>,
+}
+
+#[derive(Clone, Copy)]
+enum AggregateKey {
+    Stream,
+    Node,
+    NodeStream,
+}
+
+impl AggregateKey {
+    fn parse(group_by: &str) -> Option<Self> {
+        let mut fields: Vec<&str> = group_by.split(',').map(str::trim).collect();
+        fields.sort_unstable();
+        match fields[..] {
+            ["stream_id"] => Some(AggregateKey::Stream),
+            ["node_id"] => Some(AggregateKey::Node),
+            ["node_id", "stream_id"] => Some(AggregateKey::NodeStream),
+            _ => None,
+        }
+    }
+
+    fn of(self, event: &CausalEvent) -> String {
+        match self {
+            AggregateKey::Stream => event.stream_id.to_string(),
+            AggregateKey::Node => event.node_id.to_string(),
+            AggregateKey::NodeStream => format!("{}:{}", event.node_id, event.stream_id),
+        }
+    }
+}
+
+/// Parse a bucket width into Lamport ticks: a bare number is ticks, and
+/// `ms`, `s` and `m` suffixes convert at [`REPLAY_TICKS_PER_SEC`].
+fn parse_bucket_ticks(bucket: &str) -> Option<u64> {
+    let ticks_per_ms = REPLAY_TICKS_PER_SEC / 1000.0;
+    let (number, ticks_per_unit) = if let Some(n) = bucket.strip_suffix("ms") {
+        (n, ticks_per_ms)
+    } else if let Some(n) = bucket.strip_suffix('s') {
+        (n, REPLAY_TICKS_PER_SEC)
+    } else if let Some(n) = bucket.strip_suffix('m') {
+        (n, REPLAY_TICKS_PER_SEC * 60.0)
+    } else {
+        (bucket, 1.0)
+    };
+    let ticks = number.parse::<u64>().ok()? as f64 * ticks_per_unit;
+    (ticks >= 1.0 && ticks < u64::MAX as f64).then_some(ticks as u64)
+}
+
+#[derive(Deserialize, Default)]
 struct ExportParams {
     format: Option<String>,
     journal: Option<String>,
+    /// Most events to export; all matching events by default.
     limit: Option<usize>,
+    ts_min: Option<u64>,
+    ts_max: Option<u64>,
+    stream_id: Option<u16>,
+    /// `<slot>:<lamport_ts>` of the last record already received.
+    cursor: Option<String>,
+    partition: Option<usize>,
+    /// `zstd` to compress the stream.
+    compress: Option<String>,
+    /// Fill the `checksum_valid` column.
+    verify: Option<bool>,
+}
+
+/// Control command forwarded to the sequencer over IPC.
+#[derive(Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+enum SequencerCommandRequest {
+    Flush,
+    PersistCursor,
+    Rollover { path: String },
+    SetRetention { max_events: u64 },
+    QueryStats,
+}
+
+impl From<SequencerCommandRequest> for IpcCommand {
+    fn from(req: SequencerCommandRequest) -> Self {
+        match req {
+            SequencerCommandRequest::Flush => IpcCommand::Flush,
+            SequencerCommandRequest::PersistCursor => IpcCommand::PersistCursor,
+            SequencerCommandRequest::Rollover { path } => IpcCommand::Rollover { path },
+            SequencerCommandRequest::SetRetention { max_events } => {
+                IpcCommand::SetRetention { max_events }
+            }
+            SequencerCommandRequest::QueryStats => IpcCommand::QueryStats,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SequencerCommandParams {
+    journal: Option<String>,
 }
 
 #[derive(Serialize)]
-struct VerifyResult {
-    success: bool,
-    output: String,
-    duration_ms: u64,
-    timestamp: String,
+struct SequencerCommandResponse {
+    detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stats: Option<SequencerStatsRecord>,
+}
+
+#[derive(Serialize)]
+struct SequencerStatsRecord {
+    events_processed: u64,
+    bytes_processed: u64,
+    ring_len: u64,
+    queue_dropped: u64,
+    clients: Vec<IpcClientRecord>,
+}
+
+#[derive(Serialize)]
+struct IpcClientRecord {
+    client: u64,
+    queue_depth: u32,
+    dropped: u64,
+}
+
+impl From<SequencerStats> for SequencerStatsRecord {
+    fn from(stats: SequencerStats) -> Self {
+        Self {
+            events_processed: stats.events_processed,
+            bytes_processed: stats.bytes_processed,
+            ring_len: stats.ring_len,
+            queue_dropped: stats.queue_dropped,
+            clients: stats
+                .clients
+                .into_iter()
+                .map(|c| IpcClientRecord {
+                    client: c.client,
+                    queue_depth: c.queue_depth,
+                    dropped: c.dropped,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Verification jobs kept for polling, oldest dropped first.
+const VERIFY_JOB_HISTORY: usize = 16;
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum VerifyJobStatus {
+    Running,
+    Finished,
+}
+
+#[derive(Clone, Serialize)]
+struct VerifyJob {
+    id: String,
+    status: VerifyJobStatus,
+    started_at: chrono::DateTime<chrono::Utc>,
+    report: Option<VerificationReport>,
+}
+
+#[derive(Default)]
+struct VerifyJobs {
+    jobs: VecDeque<VerifyJob>,
+    /// Report of the most recent finished job, for the dashboard.
+    latest: Option<VerificationReport>,
 }
 
 #[derive(Deserialize)]
@@ -333,15 +1571,125 @@ struct SimulateResult {
 #[derive(Deserialize)]
 struct ReplayParams {
     journal: Option<String>,
-    start_slot: usize,
-    end_slot: usize,
+    start_slot: Option<usize>,
+    end_slot: Option<usize>,
+    /// Replay everything after a checkpoint up to the source head
+    /// (alternative to `start_slot`/`end_slot`).
+    from_checkpoint: Option<CheckpointRef>,
     target_journal: Option<String>,
+    /// Stamp replayed events with fresh timestamps after the target's
+    /// newest event instead of keeping the source's.
+    #[serde(default)]
+    resequence: bool,
+}
+
+/// A checkpoint slot, or `true` for the newest checkpoint in the journal.
+#[derive(Deserialize, Clone, Copy)]
+#[serde(untagged)]
+enum CheckpointRef {
+    Latest(bool),
+    Slot(usize),
+}
+
+#[derive(Deserialize)]
+struct CreateCheckpointRequest {
+    journal: Option<String>,
 }
 
 #[derive(Serialize)]
 struct ReplayResult {
+    /// `preserved` (source timestamps kept) or `resequenced`.
+    mode: &'static str,
     events_replayed: usize,
     new_head: usize,
+    /// Empty source slots plus events that did not fit in the target.
+    skipped: usize,
+    /// Events given a new `lamport_ts` (`resequence` only).
+    resequenced: usize,
+}
+
+#[derive(Serialize)]
+struct CheckpointRecord {
+    slot: usize,
+    lamport_ts: u64,
+    node_id: u32,
+    head: u64,
+    tail: u64,
+    lamport_watermark: u64,
+    segment_start: u64,
+    segment_crc: u32,
+    valid: bool,
+}
+
+#[derive(Serialize)]
+struct CheckpointListResponse {
+    checkpoints: Vec<CheckpointRecord>,
+    total: usize,
+}
+
+/// Outcome of an integrity scan over one journal's live events.
+#[derive(Serialize)]
+struct JournalScanResponse {
+    journal: String,
+    scanned: u64,
+    matched: u64,
+    mismatched: u64,
+    unreadable: u64,
+    /// Failing slots, up to `MAX_REPORTED_FAILURES` of each kind.
+    mismatched_slots: Vec<usize>,
+    unreadable_slots: Vec<usize>,
+    clean: bool,
+    duration_ms: u64,
+}
+
+/// Events `GET /api/anomalies` checks per page unless `limit` says
+/// otherwise, and the most it will check.
+const DEFAULT_ANOMALY_SCAN: usize = 10_000;
+const MAX_ANOMALY_SCAN: usize = 100_000;
+
+/// Events the metrics collector checks per journal partition each second.
+const ANOMALY_WATCH_BATCH: usize = 10_000;
+
+#[derive(Deserialize, Default)]
+struct AnomalyParams {
+    journal: Option<String>,
+    partition: Option<usize>,
+    /// `next_cursor` of the previous page.
+    cursor: Option<String>,
+    limit: Option<usize>,
+}
+
+/// Causality anomalies found in one page of a journal's live window.
+#[derive(Serialize)]
+struct AnomalyResponse {
+    journal: String,
+    scanned: usize,
+    /// Findings per kind, every kind listed.
+    counts: BTreeMap<&'static str, usize>,
+    findings: Vec<Anomaly>,
+    /// `<slot>:<lamport_ts>` to pass as `cursor` for the next page; absent
+    /// once the scan reached the head.
+    next_cursor: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ConsumerRecord {
+    name: String,
+    position: usize,
+    lag: usize,
+}
+
+#[derive(Serialize)]
+struct ConsumerListResponse {
+    consumers: Vec<ConsumerRecord>,
+    head: usize,
+    tail: usize,
+}
+
+#[derive(Deserialize)]
+struct RegisterConsumerRequest {
+    journal: Option<String>,
+    name: String,
 }
 
 #[derive(Serialize)]
@@ -376,9 +1724,75 @@ struct StreamsResponse {
     total_streams: usize,
 }
 
+/// Body of `POST /api/journals`.
+#[derive(Deserialize)]
+struct OpenJournalRequest {
+    path: PathBuf,
+    /// Only used if the journal has to be created.
+    #[serde(default = "default_journal_size")]
+    size: u64,
+    #[serde(default)]
+    readonly: bool,
+}
+
+/// Body of `POST /api/journal/snapshot`.
+#[derive(Deserialize)]
+struct SnapshotRequest {
+    journal: Option<String>,
+    /// Where to create the snapshot; must not exist yet.
+    path: PathBuf,
+}
+
+#[derive(Serialize)]
+struct SnapshotResult {
+    path: String,
+    events: usize,
+}
+
+fn default_journal_size() -> u64 {
+    DEFAULT_JOURNAL_SIZE
+}
+
+/// A journal the hub has open, as listed by `GET /api/journals`.
+#[derive(Serialize)]
+struct JournalInfo {
+    id: String,
+    path: String,
+    readonly: bool,
+    size_bytes: u64,
+    partitions: usize,
+    head: usize,
+    tail: usize,
+    used: usize,
+    capacity: usize,
+    utilization_pct: f64,
+    connector_id: Option<String>,
+}
+
+impl JournalInfo {
+    async fn of(journal_state: &JournalState) -> Self {
+        let journal = journal_state.journal.read().await;
+        let cursor = journal.live_cursor_view();
+        Self {
+            id: journal_state.id.clone(),
+            path: journal_state.path.display().to_string(),
+            readonly: journal_state.readonly,
+            size_bytes: journal.size(),
+            partitions: journal.partitions(),
+            head: cursor.head(),
+            tail: cursor.tail(),
+            used: cursor.len(),
+            capacity: cursor.capacity(),
+            utilization_pct: round(cursor.utilization_pct()),
+            connector_id: journal_state.connector_id.clone(),
+        }
+    }
+}
+
 #[derive(Serialize)]
 struct JournalLayout {
     total_size_bytes: u64,
+    header_size_bytes: usize,
     index_ring_start: usize,
     index_ring_end: usize,
     index_ring_size_bytes: usize,
@@ -406,6 +1820,39 @@ struct MetricsMessage {
     data: MetricsSnapshot,
 }
 
+/// Incident opened by the alert engine, sent as `{"type":"alert","data":{...}}`.
+#[derive(Serialize)]
+struct AlertMessage {
+    r#type: &'static str,
+    data: alerts::Incident,
+}
+
+/// Per-event WS push, sent as `{"type":"event","data":{...}}`.
+#[derive(Serialize)]
+struct EventMessage {
+    r#type: &'static str,
+    data: SlotEvent,
+}
+
+#[derive(Serialize)]
+struct SlotEvent {
+    journal: String,
+    slot: u64,
+    lamport_ts: u64,
+    /// Read from the committed slot; absent if the journal is not open
+    /// in this hub.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    node_id: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_id: Option<u16>,
+}
+
+/// Messages buffered per subscriber before slow WS clients start lagging.
+const IPC_EVENT_BUFFER: usize = 4096;
+
+/// Metrics snapshots buffered per WS subscriber; older ones are skipped.
+const METRICS_FEED_BUFFER: usize = 16;
+
 #[derive(Serialize)]
 struct ApiError {
     error: String,
@@ -426,103 +1873,138 @@ async fn main() {
 
     let args = Args::parse();
 
-    // Load config
-    let config = if args.config.exists() {
-        let content = std::fs::read_to_string(&args.config).unwrap_or_default();
-        toml::from_str(&content).unwrap_or_default()
-    } else {
-        Config::default()
+    // Load config; running on defaults would hide the mistake.
+    let config = match Config::load(&args.config).and_then(|c| c.validate().map(|()| c)) {
+        Ok(config) => config,
+        Err(e) => {
+            tracing::error!("{}. Exiting.", e);
+            std::process::exit(1);
+        }
     };
 
-    let mut journals = HashMap::new();
-    for path in &args.journals {
-        let journal = match Journal::open(path, args.journal_size) {
-            Ok(j) => j,
+    // Default alert rules
+    let default_rules = default_alert_rules(&config.alerts);
+
+    #[cfg(feature = "tls")]
+    let tls_acceptor = match &config.tls {
+        Some(tls) => match tls::TlsAcceptorHandle::new(tls) {
+            Ok(handle) => Some(Arc::new(handle)),
             Err(e) => {
-                tracing::error!("Failed to open journal at {:?}: {}", path, e);
-                continue;
+                tracing::error!("Cannot set up TLS: {}", e);
+                std::process::exit(1);
             }
-        };
-        let cursor = Cursor::for_index_ring();
-        journals.insert(
-            path.clone(),
-            Arc::new(JournalState {
-                path: path.clone(),
-                journal: RwLock::new(journal),
-                cursor: RwLock::new(cursor),
-            }),
-        );
-    }
-
-    if journals.is_empty() {
-        tracing::error!("No journals opened. Exiting.");
+        },
+        None => None,
+    };
+    #[cfg(not(feature = "tls"))]
+    if config.tls.is_some() {
+        tracing::error!("TLS support not compiled. Rebuild with --features tls");
         std::process::exit(1);
     }
 
-    // Initial alert rules container
-
-    // Default alert rules
-    let default_rules = vec![
-        AlertRule {
-            name: "Ring Utilization Warning".into(),
-            condition: "ring_utilization_gt".into(),
-            threshold: config.alerts.ring_utilization_warn,
-            severity: "warn".into(),
-            enabled: true,
+    let mut connector_registry = connectors::registry::ConnectorRegistry::new(1000);
+    let mut alert_engine = alerts::AlertEngine::new(100);
+    let trace_store = Arc::new(traces::TraceStore::new(
+        config.traces.max_traces,
+        config.traces.max_age(),
+    ));
+    trace_store.set_sampling(config.traces.sampling.clone());
+    let mut pipeline_manager = pipelines::PipelineManager::new();
+    let mut dashboard_manager = dashboards::DashboardManager::new();
+    let mut saved_queries = saved_queries::SavedQueryManager::new();
+    let mut auth_layer = auth::AuthLayer::new(1000, config.auth.requests_per_minute);
+
+    // Persisted hub state
+    let state_store = match &args.data_dir {
+        Some(dir) => match StateStore::open(dir) {
+            Ok(store) => {
+                tracing::info!("Persisting hub state in {:?}", dir);
+                Some(Arc::new(store))
+            }
+            Err(e) => {
+                tracing::error!("Cannot open data dir {:?}: {}", dir, e);
+                std::process::exit(1);
+            }
         },
-        AlertRule {
-            name: "Ring Utilization Critical".into(),
-            condition: "ring_utilization_gt".into(),
-            threshold: config.alerts.ring_utilization_critical,
-            severity: "critical".into(),
-            enabled: true,
-        },
-        AlertRule {
-            name: "TPS Drop".into(),
-            condition: "tps_drop_gt".into(),
-            threshold: config.alerts.tps_drop_threshold,
-            severity: "warn".into(),
-            enabled: true,
-        },
-    ];
-
-    let connector_registry = Arc::new(connectors::registry::ConnectorRegistry::new(1000));
-    let alert_engine = Arc::new(alerts::AlertEngine::new(100));
-    let trace_store = Arc::new(traces::TraceStore::new(1000));
-    let pipeline_manager = Arc::new(pipelines::PipelineManager::new());
-    let dashboard_manager = Arc::new(dashboards::DashboardManager::new());
-    let auth_layer = Arc::new(auth::AuthLayer::new(1000));
-
-    // Register internal journals as connectors
-    for (path, _j_state) in &journals {
-        let connector = Arc::new(connectors::journal::JournalConnector::new(path.clone()));
-        // Note: In a real implementation we'd probably want to share the journal access better,
-        // but for now the connector manages its own view or we'd refactor JournalState to use it.
-        // For MVP, we just register it so it shows up in the UI.
-        connector_registry.add(connector).await.ok();
+        None => None,
+    };
+    if let Some(store) = &state_store {
+        connector_registry = connector_registry.with_store(store.clone());
+        alert_engine = alert_engine.with_store(store.clone());
+        pipeline_manager = pipeline_manager.with_store(store.clone());
+        dashboard_manager = dashboard_manager.with_store(store.clone());
+        saved_queries = saved_queries.with_store(store.clone());
+        auth_layer = auth_layer.with_store(store.clone());
     }
+    let connector_registry = Arc::new(connector_registry);
+    let alert_engine = Arc::new(alert_engine);
+    let pipeline_manager = Arc::new(pipeline_manager);
+    let dashboard_manager = Arc::new(dashboard_manager);
+    let saved_queries = Arc::new(saved_queries);
+    let auth_layer = Arc::new(auth_layer);
 
     let state = Arc::new(AppState {
-        journals: RwLock::new(journals),
+        journals: RwLock::new(HashMap::new()),
         playback: RwLock::new(PlaybackMode::default()),
         start_time: Instant::now(),
-        config: config.clone(),
+        config_path: args.config.clone(),
+        active_config: std::sync::RwLock::new(ActiveConfig::new(config.clone(), &args.config)),
         metrics_history: RwLock::new(VecDeque::with_capacity(config.server.history_capacity)),
+        ipc_events: tokio::sync::broadcast::channel(IPC_EVENT_BUFFER).0,
+        metrics_feed: tokio::sync::broadcast::channel(METRICS_FEED_BUFFER).0,
+        metrics: metrics::HubMetrics::default(),
+        verify_jobs: RwLock::new(VerifyJobs::default()),
         alerts: RwLock::new(Vec::new()),
         alert_rules: RwLock::new(default_rules),
         connector_registry,
         alert_engine,
         trace_store,
+        trace_correlations: Default::default(),
         pipeline_manager,
         dashboard_manager,
+        saved_queries,
+        query_cache: Default::default(),
+        query_subscriptions: Default::default(),
         auth_layer,
+        limiter: limits::RateLimiter::new(config.limits.clone()),
+        state_store,
+        last_tick: Default::default(),
+        last_event_advance: Default::default(),
+        configured_journals: args.journals.clone(),
+        #[cfg(feature = "tls")]
+        tls: tls_acceptor,
     });
 
+    // Open each journal with its connector and IPC client
+    for path in &args.journals {
+        if let Err((_, Json(e))) = state
+            .open_journal(path.clone(), args.journal_size, false)
+            .await
+        {
+            tracing::error!("{}", e.error);
+        }
+    }
+    if state.journals.read().await.is_empty() {
+        tracing::error!("No journals opened. Exiting.");
+        std::process::exit(1);
+    }
+
+    let restored = state.connector_registry.restore().await;
+    if restored > 0 {
+        tracing::info!("Restored {} connector(s)", restored);
+    }
+
     // Spawn background metrics collector
     let bg_state = state.clone();
     tokio::spawn(async move { metrics_collector(bg_state).await });
+    tokio::spawn(expire_traces(state.clone()));
+    tokio::spawn(sample_traces(state.clone()));
+    tokio::spawn(correlate_traces(state.clone()));
 
-    // Spawn IPC listener
+    #[cfg(unix)]
+    tokio::spawn(reload_on_sighup(state.clone()));
+
+    // Spawn the incident handler that follows the sequencers
     let ipc_state = state.clone();
     tokio::spawn(async move { ipc_listener(ipc_state).await });
 
@@ -530,44 +2012,104 @@ async fn main() {
     {
         let root_key = state
             .auth_layer
-            .create_key(crate::auth::CreateApiKeyRequest {
-                label: "Root Key (Startup)".into(),
-                scopes: vec![
-                    crate::auth::Scope::Admin,
-                    crate::auth::Scope::Read,
-                    crate::auth::Scope::Write,
-                ],
-            })
-            .await;
+            .create_key(
+                crate::auth::CreateApiKeyRequest {
+                    label: "Root Key (Startup)".into(),
+                    scopes: vec![
+                        crate::auth::Scope::Admin,
+                        crate::auth::Scope::Read,
+                        crate::auth::Scope::Write,
+                    ],
+                    resource_scopes: Vec::new(),
+                    requests_per_minute: None,
+                    expires_at: None,
+                    tls_subject: None,
+                },
+                "system",
+            )
+            .await
+            .expect("the root key binds no client certificate");
 
-        tracing::info!(
-            "🔑 GENERATED ROOT API KEY: {}",
-            root_key.key.as_ref().unwrap()
-        );
+        tracing::info!("🔑 GENERATED ROOT API KEY: {}", root_key.key);
         tracing::warn!("⚠️  Copy this key! It will not be shown again.");
     }
 
-    let dist_path = PathBuf::from("crates/cz-hub/ui/dist");
+    let app = router(state.clone(), PathBuf::from("crates/cz-hub/ui/dist"));
 
-    let app = Router::new()
-        // Core APIs
-        .route("/api/status", get(api_status))
+    let addr: SocketAddr = args.bind.parse().expect("Invalid bind address");
+    let (http, ws) = if state.config().tls.is_some() {
+        ("https", "wss")
+    } else {
+        ("http", "ws")
+    };
+    tracing::info!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    tracing::info!("  🧬 LACRIMOSA v0.3.0");
+    tracing::info!("  Dashboard:  {}://{}", http, addr);
+    tracing::info!("  API:        {}://{}/api/status", http, addr);
+    tracing::info!("  WebSocket:  {}://{}/ws", ws, addr);
+    tracing::info!("  Journal:    {:?}", args.journals);
+    tracing::info!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+
+    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+    #[cfg(feature = "tls")]
+    if let Some(acceptor) = state.tls.clone() {
+        tls::serve(listener, acceptor, app).await;
+        return;
+    }
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .unwrap();
+}
+
+/// The hub's HTTP surface. Three groups, each with its own access rule:
+///
+/// - public: `/api/status`, `/healthz`, `/readyz` and `/metrics`, plus the
+///   UI assets served by the fallback;
+/// - `/ws`, which authenticates in [`ws_handler`] before upgrading, since
+///   browsers cannot set headers on a WebSocket handshake;
+/// - every other `/api` route, behind [`ip_limit_middleware`], then
+///   [`auth_middleware`] and then [`rate_limit_middleware`]. The IP budget
+///   comes first so unauthenticated floods are throttled too. These are
+///   `route_layer`s, so a request that matches no route is a 404 rather
+///   than a 401.
+///
+/// CORS wraps all three, answering preflights before any of them.
+fn router(state: Arc<AppState>, dist_path: PathBuf) -> Router {
+    let api = Router::new()
         .route("/api/ring", get(api_ring))
         .route("/api/events", get(api_events))
-        .route("/api/events/{slot}", get(api_event_detail))
+        .route("/api/events/search", get(api_events_search))
+        .route("/api/events/stream", get(api_events_stream))
+        .route("/api/events/aggregate", get(api_events_aggregate))
+        .route("/api/events/:slot", get(api_event_detail))
         .route("/api/verify", post(api_verify))
+        .route("/api/verify/latest", get(api_verify_latest))
+        .route("/api/verify/jobs/:id", get(api_verify_job))
         // New APIs
         .route("/api/simulate", post(api_simulate))
         .route("/api/topology", get(api_topology))
         .route("/api/streams", get(api_streams))
         .route("/api/journal/layout", get(api_journal_layout))
+        .route("/api/journal/snapshot", post(api_journal_snapshot))
+        .route(
+            "/api/journals",
+            get(api_journals_list).post(api_journals_open),
+        )
+        .route(
+            "/api/journals/:id",
+            axum::routing::delete(api_journals_remove),
+        )
         .route("/api/system", get(api_system))
+        .route("/api/config", get(api_config_get))
+        .route("/api/config/reload", post(api_config_reload))
         .route("/api/metrics/history", get(api_metrics_history))
         .route("/api/alerts", get(api_alerts_get))
         .route("/api/alerts/rules", get(api_alert_rules_get))
         .route("/api/alerts/rules", post(api_alert_rules_set))
         .route("/api/export", get(api_export))
-        .route("/metrics", get(api_metrics_prometheus))
         .route("/api/playback", get(api_playback_get))
         .route("/api/playback", post(api_playback_set))
         // New Capability APIs
@@ -579,8 +2121,22 @@ async fn main() {
             "/api/connectors/:id",
             axum::routing::delete(api::delete_connector),
         )
-        .route("/api/connectors/:id/ingest", post(api::ingest_webhook))
+        .route("/api/connectors/:id/health", get(api::connector_health))
         .route("/api/query", post(api::execute_query))
+        .route("/api/query/explain", post(api::explain_query))
+        .route("/api/query/stats", get(api::query_stats))
+        .route("/api/query/subscribe", post(api::subscribe_query))
+        .route(
+            "/api/queries/saved",
+            get(api::list_saved_queries).post(api::create_saved_query),
+        )
+        .route(
+            "/api/queries/saved/:id",
+            get(api::get_saved_query)
+                .put(api::update_saved_query)
+                .delete(api::delete_saved_query),
+        )
+        .route("/api/queries/saved/:id/run", post(api::run_saved_query))
         .route("/api/alerts/incidents", get(api::list_incidents))
         .route(
             "/api/alerts/incidents/test",
@@ -599,6 +2155,11 @@ async fn main() {
         .route("/api/traces/ingest", post(api::ingest_spans))
         .route("/api/traces/:id", get(api::get_trace))
         .route("/api/traces/service-graph", get(api::get_service_graph))
+        .route("/api/traces/analytics", get(api::trace_analytics))
+        .route(
+            "/api/traces/sampling",
+            get(api::get_trace_sampling).put(api::set_trace_sampling),
+        )
         .route(
             "/api/pipelines",
             get(api::list_pipelines).post(api::create_pipeline),
@@ -631,139 +2192,521 @@ async fn main() {
         )
         .route("/api/auth/audit", get(api::get_audit_log))
         .route("/api/replay", post(api_replay))
-        // Apply Auth Middleware to all API routes defined above
-        // Note: middleware applies to routes added BEFORE it if using .layer() on the router?
-        // No, .layer() wraps the *entire* router.
-        // To apply only to some, we should use .route_layer() or nested routers.
-        // For MVP simplicity: we apply to everything and let the middleware filter.
-        .layer(middleware::from_fn_with_state(
+        .route("/api/checkpoints", get(api_checkpoints))
+        .route("/api/journal/verify", get(api_journal_verify))
+        .route("/api/anomalies", get(api_anomalies))
+        .route("/api/checkpoint", post(api_checkpoint_create))
+        .route("/api/consumers", get(api_consumers_get))
+        .route("/api/consumers", post(api_consumers_register))
+        .route("/api/sequencer/command", post(api_sequencer_command))
+        .route("/api/limits", get(api_limits))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            rate_limit_middleware,
+        ))
+        .route_layer(middleware::from_fn_with_state(
             state.clone(),
             auth_middleware,
         ))
-        // WebSocket
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            ip_limit_middleware,
+        ));
+
+    // Providers cannot send an API key with their deliveries, so a signed
+    // webhook is authenticated by its signature instead.
+    let webhooks = Router::new()
+        .route("/api/connectors/:id/ingest", post(api::ingest_webhook))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            webhook_auth_middleware,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            ip_limit_middleware,
+        ));
+
+    let public = Router::new()
+        .route("/api/status", get(api_status))
+        // Probes for load balancers and orchestrators
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        // Prometheus scrapes without credentials
+        .route("/metrics", get(api_metrics_prometheus));
+
+    Router::new()
+        .merge(public)
+        .merge(api)
+        .merge(webhooks)
         .route("/ws", get(ws_handler))
         // Static UI
         .fallback_service(ServeDir::new(dist_path))
         .layer(CorsLayer::permissive())
-        .with_state(state);
-
-    let addr: SocketAddr = args.bind.parse().expect("Invalid bind address");
-    tracing::info!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-    tracing::info!("  🧬 LACRIMOSA v0.3.0");
-    tracing::info!("  Dashboard:  http://{}", addr);
-    tracing::info!("  API:        http://{}/api/status", addr);
-    tracing::info!("  WebSocket:  ws://{}/ws", addr);
-    tracing::info!("  Journal:    {:?}", args.journals);
-    tracing::info!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-
-    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+        .with_state(state)
 }
 
 // =============================================================================
-// Background Metrics Collector (1-second snapshots → history ring)
+// Background Metrics Collector (snapshots → WS feed, 1-second history ring)
 // =============================================================================
 
+/// How often traces past `traces.max_age_secs` are dropped.
+const TRACE_EXPIRY_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// How often complete traces are checked against the sampling policy.
+const TRACE_SAMPLING_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Applies the tail-sampling policy to traces that have gone quiet, every
+/// [`TRACE_SAMPLING_INTERVAL`].
+async fn sample_traces(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(TRACE_SAMPLING_INTERVAL);
+    loop {
+        interval.tick().await;
+        let dropped = state.trace_store.finalize().await;
+        if dropped > 0 {
+            tracing::debug!("Sampled out {} trace(s)", dropped);
+        }
+    }
+}
+
+/// Drops traces not updated within their configured age, every
+/// [`TRACE_EXPIRY_INTERVAL`].
+async fn expire_traces(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(TRACE_EXPIRY_INTERVAL);
+    loop {
+        interval.tick().await;
+        let expired = state.trace_store.expire().await;
+        if expired > 0 {
+            tracing::debug!("Expired {} trace(s)", expired);
+        }
+    }
+}
+
+/// Index the trace ids events carry: first those already in the open
+/// journals, then each journal commit and connector event as it arrives.
+async fn correlate_traces(state: Arc<AppState>) {
+    let mut commits = state.ipc_events.subscribe();
+    let mut stream_events = state.connector_registry.subscribe();
+    let journals: Vec<Arc<JournalState>> = state.journals.read().await.values().cloned().collect();
+    for journal_state in journals {
+        state.correlate_journal(&journal_state).await;
+    }
+    loop {
+        tokio::select! {
+            commit = commits.recv() => match commit {
+                Ok(commit) => state.correlate_commit(&commit).await,
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(missed)) => {
+                    tracing::warn!("Trace correlation lagged, skipped {} commits", missed);
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => return,
+            },
+            event = stream_events.recv() => match event {
+                Ok(event) => {
+                    if let Some(trace_id) =
+                        traces::correlation::trace_id_of(&event.payload, &event.metadata)
+                    {
+                        let correlated = traces::correlation::CorrelatedEvent::Stream {
+                            connector_id: event.connector_id,
+                            stream: event.stream,
+                            id: event.id,
+                            sequence: event.sequence,
+                        };
+                        state.trace_correlations.record(trace_id, correlated).await;
+                    }
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(missed)) => {
+                    tracing::warn!("Trace correlation lagged, skipped {} stream events", missed);
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => return,
+            },
+        }
+    }
+}
+
+/// Computes one metrics snapshot per `metrics_interval_ms` and publishes it
+/// to every WS `metrics` subscriber; once a second it is also recorded in
+/// the history ring and checked against the alert rules. A reloaded
+/// interval takes effect from the next tick.
 async fn metrics_collector(state: Arc<AppState>) {
-    let mut interval = tokio::time::interval(std::time::Duration::from_secs(1));
+    let mut interval_ms = state.config().server.metrics_interval_ms.max(1);
+    let mut interval = tokio::time::interval(std::time::Duration::from_millis(interval_ms));
     let mut prev_events: u64 = 0;
     let mut prev_bytes: u64 = 0;
+    let mut prev_time = Instant::now();
+    let mut last_recorded: Option<Instant> = None;
+    let mut last_snapshot: Option<MetricsSnapshot> = None;
     let mut prev_tps: f64 = 0.0;
+    let mut baseline = TpsBaseline::new(TPS_BASELINE_WINDOW);
     let mut alert_counter: u64 = 0;
 
     loop {
         interval.tick().await;
+        let configured_ms = state.config().server.metrics_interval_ms.max(1);
+        if configured_ms != interval_ms {
+            interval_ms = configured_ms;
+            let period = std::time::Duration::from_millis(interval_ms);
+            interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+        }
+        state
+            .last_tick
+            .store(chrono::Utc::now().timestamp_millis(), Ordering::Relaxed);
 
+        let now = Instant::now();
+        let dt = now.duration_since(prev_time).as_secs_f64();
         let events = cz_io::event_loop::EVENTS_PROCESSED.load(Ordering::Relaxed);
         let bytes = cz_io::event_loop::BYTES_PROCESSED.load(Ordering::Relaxed);
+        let rate = |delta: u64| if dt > 0.0 { delta as f64 / dt } else { 0.0 };
+        let tps = rate(events.saturating_sub(prev_events));
+        let bps = rate(bytes.saturating_sub(prev_bytes));
+        if events != prev_events {
+            state
+                .last_event_advance
+                .store(chrono::Utc::now().timestamp_millis(), Ordering::Relaxed);
+        }
+        prev_events = events;
+        prev_bytes = bytes;
+        prev_time = now;
 
-        let tps = (events.saturating_sub(prev_events)) as f64;
-        let bps = (bytes.saturating_sub(prev_bytes)) as f64;
-
-        // For now, metrics are aggregated or based on the primary (first) journal
-        let journals = state.journals.read().await;
-        let primary = journals.values().next().unwrap();
-
-        let cursor = primary.cursor.read().await;
-        let used = cursor.len();
-        let utilization = if INDEX_RING_CAPACITY > 0 {
-            (used as f64 / INDEX_RING_CAPACITY as f64) * 100.0
-        } else {
-            0.0
-        };
-
+        let ring = state.aggregate_ring_state().await;
+        let playback_mode = state.playback.read().await.clone();
+        let stats = baseline.stats();
         let snapshot = MetricsSnapshot {
+            schema: METRICS_SCHEMA,
             timestamp: chrono::Utc::now().to_rfc3339(),
             events,
             bytes,
-            tps,
-            bps,
-            head: cursor.head(),
-            tail: cursor.tail(),
-            utilization_pct: (used as f64 / INDEX_RING_CAPACITY as f64) * 100.0,
+            tps: round(tps),
+            tps_mean: stats.map(|(mean, _)| round(mean)),
+            tps_stddev: stats.map(|(_, stddev)| round(stddev)),
+            tps_zscore: round(baseline.zscore(tps)),
+            bps: round(bps),
+            utilization_pct: round(ring.utilization_pct),
             uptime_seconds: state.start_time.elapsed().as_secs(),
-            playback_mode: state.playback.read().await.clone(),
+            playback_mode: playback_mode.clone(),
+            journals: ring
+                .journals
+                .iter()
+                .map(|usage| (usage.path.clone(), usage.into()))
+                .collect(),
+        };
+
+        // Paused: keep sending the frozen frame so dashboards hold still.
+        let published = match last_snapshot {
+            Some(ref frozen) if playback_mode.is_paused() => MetricsSnapshot {
+                playback_mode,
+                ..frozen.clone()
+            },
+            _ => {
+                last_snapshot = Some(snapshot.clone());
+                snapshot.clone()
+            }
         };
+        publish_metrics(&state, published);
+
+        if last_recorded.is_some_and(|at| now.duration_since(at).as_millis() < 1000) {
+            continue;
+        }
+        last_recorded = Some(now);
 
         // Store in history
         {
             let mut history = state.metrics_history.write().await;
-            if history.len() >= state.config.server.history_capacity {
+            let capacity = state.config().server.history_capacity;
+            while history.len() >= capacity {
                 history.pop_front();
             }
             history.push_back(snapshot.clone());
         }
+        baseline.push(tps);
+
+        watch_anomalies(&state).await;
+        open_tps_anomaly_incidents(&state, &snapshot).await;
 
         // Check alert rules
         {
             let rules = state.alert_rules.read().await;
+            let triggered = triggered_alerts(&rules, &snapshot, prev_tps);
             let mut alerts = state.alerts.write().await;
+            for (rule, message) in triggered {
+                alert_counter += 1;
+                alerts.push(Alert {
+                    id: alert_counter,
+                    severity: rule.severity.clone(),
+                    message,
+                    timestamp: chrono::Utc::now().to_rfc3339(),
+                    rule_name: rule.name.clone(),
+                    resolved: false,
+                });
+            }
+            // Keep last 100 alerts
+            let alen = alerts.len();
+            if alen > 100 {
+                alerts.drain(0..alen - 100);
+            }
+        }
 
-            for rule in rules.iter().filter(|r| r.enabled) {
-                let triggered = match rule.condition.as_str() {
-                    "ring_utilization_gt" => utilization > rule.threshold,
-                    "tps_drop_gt" => {
-                        prev_tps > 0.0 && tps < prev_tps * (1.0 - rule.threshold / 100.0)
-                    }
-                    _ => false,
-                };
+        prev_tps = tps;
+    }
+}
+
+/// The built-in rules, with thresholds from `[alerts]`. A config reload
+/// updates the rules of the same name.
+fn default_alert_rules(alerts: &AlertConfig) -> Vec<AlertRule> {
+    vec![
+        AlertRule {
+            name: "Ring Utilization Warning".into(),
+            condition: "ring_utilization_gt".into(),
+            threshold: alerts.ring_utilization_warn,
+            severity: "warn".into(),
+            enabled: true,
+        },
+        AlertRule {
+            name: "Ring Utilization Critical".into(),
+            condition: "ring_utilization_gt".into(),
+            threshold: alerts.ring_utilization_critical,
+            severity: "critical".into(),
+            enabled: true,
+        },
+        AlertRule {
+            name: "TPS Drop".into(),
+            condition: "tps_drop_gt".into(),
+            threshold: alerts.tps_drop_threshold,
+            severity: "warn".into(),
+            enabled: true,
+        },
+    ]
+}
 
-                if triggered {
-                    alert_counter += 1;
-                    alerts.push(Alert {
-                        id: alert_counter,
-                        severity: rule.severity.clone(),
-                        message: format!("{}: threshold {:.1} exceeded", rule.name, rule.threshold),
-                        timestamp: chrono::Utc::now().to_rfc3339(),
-                        rule_name: rule.name.clone(),
-                        resolved: false,
-                    });
-                    // Keep last 100 alerts
-                    let alen = alerts.len();
-                    if alen > 100 {
-                        alerts.drain(0..alen - 100);
+/// The enabled rules `snapshot` trips, each with its alert message.
+/// Utilization rules are checked against every journal's ring; `prev_tps`
+/// is the rate recorded a second earlier.
+fn triggered_alerts<'a>(
+    rules: &'a [AlertRule],
+    snapshot: &MetricsSnapshot,
+    prev_tps: f64,
+) -> Vec<(&'a AlertRule, String)> {
+    let mut triggered = Vec::new();
+    for rule in rules.iter().filter(|r| r.enabled) {
+        match rule.condition.as_str() {
+            "ring_utilization_gt" => {
+                for (journal, ring) in &snapshot.journals {
+                    if ring.utilization_pct > rule.threshold {
+                        triggered.push((
+                            rule,
+                            format!(
+                                "{}: threshold {:.1} exceeded by {} ({:.1}%)",
+                                rule.name, rule.threshold, journal, ring.utilization_pct
+                            ),
+                        ));
                     }
                 }
             }
+            "tps_drop_gt"
+                if prev_tps > 0.0 && snapshot.tps < prev_tps * (1.0 - rule.threshold / 100.0) =>
+            {
+                triggered.push((
+                    rule,
+                    format!("{}: threshold {:.1} exceeded", rule.name, rule.threshold),
+                ));
+            }
+            _ => {}
         }
+    }
+    triggered
+}
 
-        prev_events = events;
-        prev_bytes = bytes;
-        prev_tps = tps;
+/// The enabled `anomaly` rules on `tps` that `snapshot` trips, each with
+/// its incident message: the rate is more than `threshold` standard
+/// deviations from its rolling mean, either way.
+fn tps_anomalies<'a>(
+    rules: &'a [alerts::AlertRuleV2],
+    snapshot: &MetricsSnapshot,
+) -> Vec<(&'a alerts::AlertRuleV2, String)> {
+    let Some(mean) = snapshot.tps_mean else {
+        return Vec::new();
+    };
+    rules
+        .iter()
+        .filter(|r| r.enabled && r.rule_type == alerts::RuleType::Anomaly && r.field == "tps")
+        .filter(|r| snapshot.tps_zscore.abs() > r.threshold)
+        .map(|rule| {
+            let message = format!(
+                "TPS {:.1} is {:.1} standard deviations from its mean of {:.1}",
+                snapshot.tps, snapshot.tps_zscore, mean
+            );
+            (rule, message)
+        })
+        .collect()
+}
+
+/// Open an incident for each [`tps_anomalies`] rule that has none active.
+async fn open_tps_anomaly_incidents(state: &AppState, snapshot: &MetricsSnapshot) {
+    let tripped: Vec<(alerts::AlertRuleV2, String)> = {
+        let rules = state.alert_engine.rules.read().await;
+        tps_anomalies(&rules, snapshot)
+            .into_iter()
+            .map(|(rule, message)| (rule.clone(), message))
+            .collect()
+    };
+    if tripped.is_empty() {
+        return;
+    }
+    let active = state.alert_engine.list_active().await;
+    for (rule, message) in tripped {
+        if !active.iter().any(|incident| incident.rule_id == rule.id) {
+            state.alert_engine.create_incident(&rule, message).await;
+        }
+    }
+}
+
+/// Serialize `snapshot` once and hand it to every `metrics` subscriber.
+fn publish_metrics(state: &AppState, snapshot: MetricsSnapshot) {
+    let msg = MetricsMessage {
+        r#type: "metrics",
+        data: snapshot,
+    };
+    if let Ok(json) = serde_json::to_string(&msg) {
+        // No subscribers right now is normal.
+        let _ = state.metrics_feed.send(json.into());
     }
 }
 
-async fn ipc_listener(_state: Arc<AppState>) {
+/// React to sequencer notifications that concern the hub itself.
+async fn ipc_listener(state: Arc<AppState>) {
+    let mut events = state.ipc_events.subscribe();
     loop {
-        if let Ok(mut stream) = tokio::net::UnixStream::connect("/tmp/cz-io.sock").await {
-            tracing::info!("Connected to cz-io real-time push socket");
-            let mut buf = [0u8; 4];
-            while let Ok(_) = tokio::io::AsyncReadExt::read_exact(&mut stream, &mut buf).await {
-                let _slot = u32::from_le_bytes(buf) as usize;
-                // V3: Push refresh signal to all WS clients
+        match events.recv().await {
+            Ok(ipc::SequencerEvent {
+                journal,
+                message: IpcMessage::WatermarkCrossed(crossing),
+            }) => {
+                handle_watermark_crossing(&state, &journal, crossing).await;
+            }
+            Ok(_) => {}
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(missed)) => {
+                tracing::warn!("IPC listener lagged, skipped {} messages", missed);
+            }
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => return,
+        }
+    }
+}
+
+/// Open an incident when a journal's ring crosses its high watermark and
+/// resolve it once utilization falls back to the low watermark.
+async fn handle_watermark_crossing(
+    state: &AppState,
+    journal: &std::path::Path,
+    crossing: WatermarkCrossing,
+) {
+    let rule_id = format!("ring-watermark:{}", journal.display());
+    match crossing {
+        WatermarkCrossing::High { utilization_pct } => {
+            let rule = alerts::AlertRuleV2 {
+                id: rule_id,
+                name: "Ring utilization high watermark".into(),
+                rule_type: alerts::RuleType::Threshold,
+                stream: Some(journal.display().to_string()),
+                field: "ring_utilization_pct".into(),
+                threshold: state.config().alerts.ring_utilization_critical,
+                duration_seconds: 0,
+                severity: "critical".into(),
+                enabled: true,
+                notification_channels: vec![],
+                runbook_url: None,
+            };
+            state
+                .alert_engine
+                .create_incident(
+                    &rule,
+                    format!(
+                        "Ring utilization of {} reached {:.1}%",
+                        journal.display(),
+                        utilization_pct
+                    ),
+                )
+                .await;
+        }
+        WatermarkCrossing::Low { .. } => {
+            for incident in state.alert_engine.list_active().await {
+                if incident.rule_id == rule_id {
+                    let _ = state
+                        .alert_engine
+                        .resolve_incident(&incident.id, "system")
+                        .await;
+                }
+            }
+        }
+    }
+}
+
+/// Check the slots written to every journal since the last pass, count
+/// what breaks causality and open an incident for the journal unless one
+/// is already active. Anomalies stay in the ring, so the incident is left
+/// for an operator to resolve.
+async fn watch_anomalies(state: &AppState) {
+    let journals: Vec<_> = state.journals.read().await.values().cloned().collect();
+    for entry in journals {
+        let found = {
+            let journal = entry.journal.read().await;
+            let mut scanners = entry.anomalies.lock().await;
+            scanners.resize_with(journal.partitions(), AnomalyScanner::default);
+            let mut found = Vec::new();
+            for (partition, scanner) in scanners.iter_mut().enumerate() {
+                if let Some(cursor) = journal.partition_cursor_view(partition) {
+                    found.extend(
+                        scanner
+                            .scan(&journal, &cursor, ANOMALY_WATCH_BATCH)
+                            .findings,
+                    );
+                }
             }
+            found
+        };
+        let Some(first) = found.first() else {
+            continue;
+        };
+        for anomaly in &found {
+            state.metrics.record_anomaly(anomaly.kind);
         }
-        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+
+        let rule_id = format!("causality-anomaly:{}", entry.path.display());
+        let active = state.alert_engine.list_active().await;
+        if active.iter().any(|incident| incident.rule_id == rule_id) {
+            continue;
+        }
+        let rule = alerts::AlertRuleV2 {
+            id: rule_id,
+            name: "Causality anomaly".into(),
+            rule_type: alerts::RuleType::Pattern,
+            stream: Some(entry.path.display().to_string()),
+            field: "anomalies".into(),
+            threshold: 0.0,
+            duration_seconds: 0,
+            severity: "critical".into(),
+            enabled: true,
+            notification_channels: vec![],
+            runbook_url: None,
+        };
+        state
+            .alert_engine
+            .create_incident(
+                &rule,
+                format!(
+                    "{} causality anomalies in {}, first a {} at slot {}",
+                    found.len(),
+                    entry.path.display(),
+                    first.kind.as_str(),
+                    first.slot
+                ),
+            )
+            .await;
+    }
+}
+
+/// Check a journal's watermarks after the hub itself wrote to it.
+async fn observe_watermarks(state: &AppState, journal: &JournalState, cursor: &MultiCursor) {
+    let crossing = journal.watermarks.lock().await.observe(cursor);
+    if let Some(crossing) = crossing {
+        handle_watermark_crossing(state, &journal.path, crossing).await;
     }
 }
 
@@ -771,104 +2714,394 @@ async fn ipc_listener(_state: Arc<AppState>) {
 // Core API Handlers
 // =============================================================================
 
-async fn api_status(State(state): State<Arc<AppState>>) -> Json<SystemStatus> {
-    let uptime = state.start_time.elapsed().as_secs();
-    let events = cz_io::event_loop::EVENTS_PROCESSED.load(Ordering::Relaxed);
-    let bytes = cz_io::event_loop::BYTES_PROCESSED.load(Ordering::Relaxed);
+#[derive(Serialize)]
+struct LimitsResponse {
+    /// Default per-key budget (`[auth] requests_per_minute`).
+    key_requests_per_minute: u32,
+    limits: limits::LimitsConfig,
+    /// Per-key buckets, then the per-IP and expensive-route ones.
+    buckets: Vec<limits::BucketState>,
+}
 
-    let (tps, bps) = {
-        let history = state.metrics_history.read().await;
-        history.back().map(|s| (s.tps, s.bps)).unwrap_or((0.0, 0.0))
-    };
+/// Request budgets and how full each bucket is, for debugging 429s.
+async fn api_limits(State(state): State<Arc<AppState>>) -> Json<LimitsResponse> {
+    let mut buckets = state.auth_layer.rate_buckets().await;
+    buckets.extend(state.limiter.snapshot().await);
+    Json(LimitsResponse {
+        key_requests_per_minute: state.config().auth.requests_per_minute,
+        limits: state.limiter.config(),
+        buckets,
+    })
+}
 
-    let primary = state.get_journal(None).await.unwrap();
-    let journal = primary.journal.read().await;
+#[derive(Serialize)]
+struct ConfigResponse {
+    source: PathBuf,
+    /// Modification time of `source` when it was loaded; `None` when the
+    /// file does not exist and the defaults are in effect.
+    source_modified_at: Option<String>,
+    loaded_at: String,
+    config: Config,
+}
 
-    Json(SystemStatus {
-        version: "0.3.0",
-        engine: "io_uring (pipelined, 16-deep)",
-        zero_copy: true,
-        uptime_seconds: uptime,
-        event_size_bytes: CausalEvent::size_bytes(),
-        journal_path: primary.path.display().to_string(),
-        journal_size_bytes: journal.size(),
-        index_ring_capacity: INDEX_RING_CAPACITY,
-        index_ring_size_bytes: INDEX_RING_SIZE,
-        events_processed: events,
-        bytes_processed: bytes,
-        current_tps: tps,
-        current_bps: bps,
+/// The config in effect and where it came from. No setting in it is
+/// secret, so nothing is redacted.
+async fn api_config_get(State(state): State<Arc<AppState>>) -> Json<ConfigResponse> {
+    let active = state.active_config.read().unwrap();
+    Json(ConfigResponse {
+        source: state.config_path.clone(),
+        source_modified_at: active.modified_at.clone(),
+        loaded_at: active.loaded_at.clone(),
+        config: (*active.config).clone(),
     })
 }
 
-async fn api_ring(
+/// Re-read the config file; 422 with the reason if it is invalid.
+async fn api_config_reload(
     State(state): State<Arc<AppState>>,
-    Query(params): Query<HashMap<String, String>>,
-) -> Result<Json<RingState>, (StatusCode, Json<ApiError>)> {
-    let journal_path = params.get("journal");
-    let primary = state.get_journal(journal_path.cloned()).await.ok_or((
-        StatusCode::NOT_FOUND,
-        Json(ApiError {
-            error: "Journal not found".into(),
-        }),
-    ))?;
-
-    let _journal = primary.journal.read().await;
-    let cursor = primary.cursor.read().await;
+) -> Result<Json<ConfigReload>, (StatusCode, Json<ApiError>)> {
+    state.reload_config().await.map(Json).map_err(|error| {
+        tracing::warn!("Config reload rejected: {}", error);
+        (StatusCode::UNPROCESSABLE_ENTITY, Json(ApiError { error }))
+    })
+}
 
-    let used = cursor.len();
-    let utilization = if INDEX_RING_CAPACITY > 0 {
-        (used as f64 / INDEX_RING_CAPACITY as f64) * 100.0
-    } else {
-        0.0
+/// Reload the config on every SIGHUP.
+#[cfg(unix)]
+async fn reload_on_sighup(state: Arc<AppState>) {
+    use tokio::signal::unix::{signal, SignalKind};
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(hangups) => hangups,
+        Err(e) => {
+            tracing::warn!("Cannot listen for SIGHUP: {}", e);
+            return;
+        }
     };
+    while hangups.recv().await.is_some() {
+        if let Err(e) = state.reload_config().await {
+            tracing::warn!("Config reload rejected: {}", e);
+        }
+    }
+}
 
-    Ok(Json(RingState {
-        head: cursor.head(),
-        tail: cursor.tail(),
-        capacity: INDEX_RING_CAPACITY,
-        used,
-        utilization_pct: (utilization * 100.0).round() / 100.0,
-        is_full: cursor.is_full(),
-        is_empty: cursor.is_empty(),
-        bytes_per_slot: CausalEvent::size_bytes(),
-        total_bytes: INDEX_RING_SIZE,
-    }))
+/// Liveness: the process is up and serving.
+async fn healthz() -> Json<serde_json::Value> {
+    Json(serde_json::json!({ "status": "ok" }))
 }
 
-async fn api_events(
+/// The metrics collector counts as stalled after this long without a tick.
+const COLLECTOR_STALL_MS: i64 = 5000;
+/// The event loop counts as stalled once `EVENTS_PROCESSED` has not grown
+/// for this long, and the hub has been up at least as long.
+const EVENT_LOOP_STALL_MS: i64 = 5 * 60 * 1000;
+
+#[derive(Serialize)]
+struct ComponentCheck {
+    ok: bool,
+    detail: String,
+}
+
+impl ComponentCheck {
+    fn new(ok: bool, detail: impl Into<String>) -> Self {
+        Self {
+            ok,
+            detail: detail.into(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Readiness {
+    ready: bool,
+    components: BTreeMap<&'static str, ComponentCheck>,
+}
+
+/// Readiness: configured journals open with their files intact, sequencers
+/// reachable, events flowing, collector ticking and state store writable;
+/// 503 with the failing components otherwise.
+async fn readyz(State(state): State<Arc<AppState>>) -> (StatusCode, Json<Readiness>) {
+    let mut components = BTreeMap::new();
+
+    let journals: Vec<Arc<JournalState>> = state
+        .journals
+        .read()
+        .await
+        .values()
+        .filter(|j| !j.is_closing())
+        .cloned()
+        .collect();
+    let mut problems: Vec<String> = state
+        .configured_journals
+        .iter()
+        .filter(|path| !journals.iter().any(|j| &j.path == *path))
+        .map(|path| format!("{} is not open", path.display()))
+        .collect();
+    for journal_state in &journals {
+        // Touching a mapping whose file shrank or vanished faults the process.
+        let mapped = journal_state.journal.read().await.size();
+        match std::fs::metadata(&journal_state.path) {
+            Ok(meta) if meta.len() >= mapped => {}
+            Ok(meta) => problems.push(format!(
+                "{} is {} bytes, {} mapped",
+                journal_state.path.display(),
+                meta.len(),
+                mapped
+            )),
+            Err(e) => problems.push(format!("{}: {}", journal_state.path.display(), e)),
+        }
+    }
+    components.insert(
+        "journals",
+        if journals.is_empty() {
+            ComponentCheck::new(false, "none open")
+        } else if problems.is_empty() {
+            ComponentCheck::new(true, format!("{} open", journals.len()))
+        } else {
+            ComponentCheck::new(false, problems.join("; "))
+        },
+    );
+
+    let ipc = if !state.config().ipc.enabled {
+        ComponentCheck::new(true, "disabled")
+    } else {
+        let disconnected: Vec<String> = journals
+            .iter()
+            .filter(|j| {
+                !j.ipc_connected
+                    .as_ref()
+                    .is_some_and(|c| c.load(Ordering::Acquire))
+            })
+            .map(|j| j.path.display().to_string())
+            .collect();
+        if disconnected.is_empty() {
+            ComponentCheck::new(true, "connected")
+        } else {
+            ComponentCheck::new(
+                false,
+                format!(
+                    "not connected to the sequencer of {}",
+                    disconnected.join(", ")
+                ),
+            )
+        }
+    };
+    components.insert("ipc", ipc);
+
+    let last_tick = state.last_tick.load(Ordering::Relaxed);
+    let since_tick = chrono::Utc::now().timestamp_millis() - last_tick;
+    components.insert(
+        "metrics_collector",
+        if last_tick == 0 {
+            ComponentCheck::new(false, "has not ticked yet")
+        } else {
+            ComponentCheck::new(
+                since_tick <= COLLECTOR_STALL_MS,
+                format!("last tick {}ms ago", since_tick.max(0)),
+            )
+        },
+    );
+
+    let uptime_ms = state.start_time.elapsed().as_millis() as i64;
+    let last_event = state.last_event_advance.load(Ordering::Relaxed);
+    components.insert(
+        "event_loop",
+        if last_event != 0 {
+            let since_event = (chrono::Utc::now().timestamp_millis() - last_event).max(0);
+            ComponentCheck::new(
+                since_event <= EVENT_LOOP_STALL_MS || uptime_ms <= EVENT_LOOP_STALL_MS,
+                format!("last event {}ms ago", since_event),
+            )
+        } else {
+            ComponentCheck::new(
+                uptime_ms <= EVENT_LOOP_STALL_MS,
+                format!("no events in {}s since start", uptime_ms / 1000),
+            )
+        },
+    );
+
+    components.insert(
+        "state_store",
+        match &state.state_store {
+            None => ComponentCheck::new(true, "not configured"),
+            Some(store) => match store.check_writable() {
+                Ok(()) => ComponentCheck::new(true, format!("{} writable", store.dir().display())),
+                Err(e) => ComponentCheck::new(false, format!("{}: {}", store.dir().display(), e)),
+            },
+        },
+    );
+
+    let ready = components.values().all(|c| c.ok);
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(Readiness { ready, components }))
+}
+
+async fn api_status(State(state): State<Arc<AppState>>) -> Json<SystemStatus> {
+    let uptime = state.start_time.elapsed().as_secs();
+    let events = cz_io::event_loop::EVENTS_PROCESSED.load(Ordering::Relaxed);
+    let bytes = cz_io::event_loop::BYTES_PROCESSED.load(Ordering::Relaxed);
+
+    let (tps, bps) = {
+        let history = state.metrics_history.read().await;
+        history.back().map(|s| (s.tps, s.bps)).unwrap_or((0.0, 0.0))
+    };
+
+    let ring = state.aggregate_ring_state().await;
+    // With every journal removed the status still answers, describing none.
+    let (journal_path, journal_size_bytes, index_ring_capacity, index_ring_size_bytes) =
+        match state.get_journal(None).await {
+            Some(primary) => {
+                let journal = primary.journal.read().await;
+                (
+                    primary.path.display().to_string(),
+                    journal.size(),
+                    journal.index_ring_capacity(),
+                    journal.index_ring_size(),
+                )
+            }
+            None => (String::new(), 0, 0, 0),
+        };
+
+    Json(SystemStatus {
+        version: "0.3.0",
+        engine: "io_uring (pipelined, 16-deep)",
+        zero_copy: true,
+        uptime_seconds: uptime,
+        event_size_bytes: CausalEvent::size_bytes(),
+        journal_path,
+        journal_size_bytes,
+        index_ring_capacity,
+        index_ring_size_bytes,
+        events_processed: events,
+        bytes_processed: bytes,
+        current_tps: tps,
+        current_bps: bps,
+        ring_used: ring.used,
+        ring_capacity: ring.capacity,
+        ring_utilization_pct: round(ring.utilization_pct),
+        journals: ring.journals,
+    })
+}
+
+async fn api_ring(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<RingState>, (StatusCode, Json<ApiError>)> {
+    let journal_path = params.get("journal");
+    let primary = state.resolve_journal(journal_path.cloned()).await?;
+
+    let journal = primary.journal.read().await;
+    let partition = match params.get("partition") {
+        Some(raw) => Some(raw.parse().map_err(|_| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ApiError {
+                    error: format!("Invalid partition '{}'", raw),
+                }),
+            )
+        })?),
+        None => None,
+    };
+    let cursor = partition_view(&journal, partition, None)?;
+
+    let used = cursor.len();
+
+    Ok(Json(RingState {
+        head: cursor.head(),
+        tail: cursor.tail(),
+        capacity: cursor.capacity(),
+        used,
+        utilization_pct: round(cursor.utilization_pct()),
+        is_full: cursor.is_full(),
+        is_empty: cursor.is_empty(),
+        bytes_per_slot: CausalEvent::size_bytes(),
+        total_bytes: journal.index_ring_size(),
+        partitions: journal.partitions(),
+    }))
+}
+
+async fn api_events(
     State(state): State<Arc<AppState>>,
     Query(params): Query<EventQueryParams>,
 ) -> Result<Json<EventListResponse>, (StatusCode, Json<ApiError>)> {
     let offset = params.offset.unwrap_or(0);
     let limit = params.limit.unwrap_or(50).min(500);
+    let filter = match params.query.as_deref() {
+        Some(q) => query::parser::parse_filter(q).map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ApiError {
+                    error: e.to_string(),
+                }),
+            )
+        })?,
+        None => None,
+    };
+    let required_flags = match &params.flags {
+        Some(list) => parse_flags(list)
+            .map_err(|error| (StatusCode::BAD_REQUEST, Json(ApiError { error })))?,
+        None => 0,
+    };
+    let verify = params.verify.unwrap_or(false) || params.checksum_valid.is_some();
+    let after = match &params.cursor {
+        Some(raw) => Some(EventsCursor::decode(raw).ok_or((
+            StatusCode::BAD_REQUEST,
+            Json(ApiError {
+                error: format!("Invalid events cursor '{}'", raw),
+            }),
+        ))?),
+        None => None,
+    };
 
     let journal_path = params.journal.clone();
-    let primary = state.get_journal(journal_path).await.ok_or((
-        StatusCode::NOT_FOUND,
-        Json(ApiError {
-            error: "Journal not found".into(),
-        }),
-    ))?;
+    let primary = state.resolve_journal(journal_path).await?;
 
+    let horizon = state.playback.read().await.horizon(unix_millis());
     let journal = primary.journal.read().await;
-    let cursor = primary.cursor.read().await;
+    let cursor = partition_view(&journal, params.partition, params.stream_id)?;
     let total = cursor.len();
 
+    // The ring is sorted by lamport_ts, so seek straight to `ts_min`, or
+    // past the previous page's last event.
+    let start = params
+        .ts_min
+        .map_or(0, |min| journal.find_first_ge(&cursor, min))
+        .max(after.map_or(0, |after| after.resume_index(&journal, &cursor)));
+
     let mut records = Vec::with_capacity(limit);
+    let mut last = None;
     let mut skipped = 0;
+    let mut reached_horizon = match horizon {
+        PlaybackHorizon::UpToSlot(at_slot) => cursor
+            .logical_index(at_slot)
+            .is_some_and(|index| index < start),
+        _ => false,
+    };
 
-    for i in 0..total {
-        if records.len() >= limit {
+    for slot in (start..total).filter_map(|i| cursor.slot_at(i)) {
+        if records.len() >= limit || reached_horizon {
             break;
         }
+        if let PlaybackHorizon::UpToSlot(at_slot) = horizon {
+            reached_horizon = slot == at_slot;
+        }
 
-        let slot = (cursor.tail() + i) % INDEX_RING_CAPACITY;
         let event = unsafe { journal.read_event_at(slot) };
 
         if is_empty_event(&event) {
             continue;
         }
+        if let PlaybackHorizon::UpToTs(at_ts) = horizon {
+            if event.lamport_ts > at_ts {
+                break;
+            }
+        }
+        if params.ts_max.is_some_and(|max| event.lamport_ts > max) {
+            break;
+        }
 
         // Core filters
         if let Some(nid) = params.node_id {
@@ -881,60 +3114,364 @@ async fn api_events(
                 continue;
             }
         }
-        if let Some(min) = params.ts_min {
-            if event.lamport_ts < min {
-                continue;
-            }
+        if event.flags & required_flags != required_flags {
+            continue;
         }
-        if let Some(max) = params.ts_max {
-            if event.lamport_ts > max {
-                continue;
-            }
+        if !query::executor::matches_filter(filter.as_ref(), |field| {
+            causal_event_field(&event, field)
+        }) {
+            continue;
         }
 
-        // DSL query (minimal evaluator for demo/expansion)
-        if let Some(ref q) = params.query {
-            if !evaluate_dsl(q, &event) {
-                continue;
-            }
+        let record = EventRecord::new(&journal, slot, &event, verify);
+        if params
+            .checksum_valid
+            .is_some_and(|wanted| record.checksum_valid != Some(wanted))
+        {
+            continue;
         }
-
         if skipped < offset {
             skipped += 1;
             continue;
         }
 
-        records.push(EventRecord {
+        records.push(record);
+        last = Some(EventsCursor::after(slot, &event));
+    }
+
+    Ok(Json(EventListResponse {
+        events: records,
+        total,
+        offset,
+        limit,
+        next_cursor: last.map(|last| last.encode()),
+    }))
+}
+
+/// Interval between `: heartbeat` comments on an idle event stream.
+const EVENT_STREAM_HEARTBEAT: std::time::Duration = std::time::Duration::from_secs(15);
+
+/// Server-Sent Events feed of one journal's committed events.
+///
+/// With a `Last-Event-ID` naming a slot still in the ring, the events after
+/// it are sent from the journal before the live feed takes over. The stream
+/// owns its broadcast receiver and spawns nothing, so a client that goes
+/// away simply drops it.
+async fn api_events_stream(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<EventStreamParams>,
+    headers: header::HeaderMap,
+) -> Result<
+    Sse<impl futures_util::Stream<Item = Result<SseEvent, std::convert::Infallible>>>,
+    (StatusCode, Json<ApiError>),
+> {
+    let journal_state = state.resolve_journal(params.journal.clone()).await?;
+
+    // Subscribe before reading the backfill so no commit falls in between.
+    let live = state.ipc_events.subscribe();
+    let last_slot = headers
+        .get("last-event-id")
+        .and_then(|id| id.to_str().ok())
+        .and_then(|id| id.trim().parse::<usize>().ok());
+    let mut backfill = VecDeque::new();
+    if let Some(last_slot) = last_slot {
+        let journal = journal_state.journal.read().await;
+        let cursor = journal.live_cursor_view();
+        if let Some(position) = cursor.logical_index(last_slot) {
+            backfill.extend(
+                (position + 1..cursor.len())
+                    .filter_map(|i| cursor.slot_at(i))
+                    .filter_map(|slot| Some((slot as u64, journal.event_at(slot)?))),
+            );
+        }
+    }
+
+    let session = EventStreamSession {
+        backfilled: backfill
+            .iter()
+            .map(|(slot, event)| (*slot, event.lamport_ts))
+            .collect(),
+        backfill,
+        journal: journal_state,
+        params,
+        live,
+    };
+    let events = futures_util::stream::unfold(session, |mut session| async move {
+        let event = session.next_event().await?;
+        Some((Ok(event), session))
+    });
+    Ok(Sse::new(events).keep_alive(
+        KeepAlive::new()
+            .interval(EVENT_STREAM_HEARTBEAT)
+            .text("heartbeat"),
+    ))
+}
+
+struct EventStreamSession {
+    journal: Arc<JournalState>,
+    params: EventStreamParams,
+    backfill: VecDeque<(u64, CausalEvent)>,
+    /// Backfilled `(slot, lamport_ts)` pairs the live feed may repeat.
+    backfilled: std::collections::HashSet<(u64, u64)>,
+    live: tokio::sync::broadcast::Receiver<ipc::SequencerEvent>,
+}
+
+impl EventStreamSession {
+    /// The next SSE event, or `None` once the hub shuts the feed down.
+    async fn next_event(&mut self) -> Option<SseEvent> {
+        use tokio::sync::broadcast::error::RecvError;
+
+        while let Some((slot, event)) = self.backfill.pop_front() {
+            if self.params.matches(&event) {
+                return Some(self.sse_event(slot, &event));
+            }
+        }
+        loop {
+            let message = match self.live.recv().await {
+                Ok(message) => message,
+                Err(RecvError::Lagged(missed)) => {
+                    return Some(SseEvent::default().comment(format!("lagged {}", missed)));
+                }
+                Err(RecvError::Closed) => return None,
+            };
+            if self.journal.is_closing() {
+                return None;
+            }
+            let IpcMessage::SlotCommitted { slot, lamport_ts } = message.message else {
+                continue;
+            };
+            if message.journal != self.journal.path || self.backfilled.remove(&(slot, lamport_ts)) {
+                continue;
+            }
+            let Some(event) = self.journal.journal.read().await.event_at(slot as usize) else {
+                continue;
+            };
+            if self.params.matches(&event) {
+                return Some(self.sse_event(slot, &event));
+            }
+        }
+    }
+
+    fn sse_event(&self, slot: u64, event: &CausalEvent) -> SseEvent {
+        let data = StreamedEvent {
             slot,
             lamport_ts: event.lamport_ts,
             node_id: event.node_id,
             stream_id: event.stream_id,
-            payload_offset: event.payload_offset,
-            checksum: event.checksum,
-            checkpoint: event.is_checkpoint(),
-        });
+            flags: event.flags,
+        };
+        SseEvent::default()
+            .id(slot.to_string())
+            .json_data(data)
+            .expect("StreamedEvent serializes")
     }
+}
 
-    Ok(Json(EventListResponse {
-        events: records,
-        total,
+/// Events with `ts_min <= lamport_ts <= ts_max`, located by binary search.
+///
+/// Both ends of the range are sought, so `matched` is exact without a scan
+/// and only the returned page of events is read.
+async fn api_events_search(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<EventSearchParams>,
+) -> Result<Json<EventSearchResponse>, (StatusCode, Json<ApiError>)> {
+    let offset = params.offset.unwrap_or(0);
+    let limit = params.limit.unwrap_or(50).min(500);
+    let primary = state.resolve_journal(params.journal).await?;
+
+    let horizon = state.playback.read().await.horizon(unix_millis());
+    let journal = primary.journal.read().await;
+    let cursor = partition_view(&journal, params.partition, None)?;
+    let std::ops::Range { start, end } =
+        visible_ts_range(&journal, &cursor, params.ts_min, params.ts_max, horizon);
+
+    let events = (start + offset..end)
+        .take(limit)
+        .filter_map(|i| cursor.slot_at(i))
+        .filter_map(|slot| {
+            let event = unsafe { journal.read_event_at(slot) };
+            (!is_empty_event(&event)).then(|| EventRecord::new(&journal, slot, &event, false))
+        })
+        .collect();
+
+    Ok(Json(EventSearchResponse {
+        events,
+        matched: end - start,
+        first_index: start,
         offset,
         limit,
     }))
 }
 
+/// Live window of the partition a request reads: `partition`, else the
+/// one `stream_id` is sequenced into, else partition 0, which is the whole
+/// ring of an unpartitioned journal.
+fn partition_view(
+    journal: &Journal,
+    partition: Option<usize>,
+    stream_id: Option<u16>,
+) -> Result<cz_io::cursor::Cursor, (StatusCode, Json<ApiError>)> {
+    let partition = partition
+        .or_else(|| stream_id.map(|id| journal.partition_of(id)))
+        .unwrap_or(0);
+    journal.partition_cursor_view(partition).ok_or_else(|| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ApiError {
+                error: format!(
+                    "Partition {} is out of range: the journal has {}",
+                    partition,
+                    journal.partitions()
+                ),
+            }),
+        )
+    })
+}
+
+/// Logical indices of the live events with `ts_min <= lamport_ts <= ts_max`
+/// that `horizon` lets through, found by binary search on both ends.
+fn visible_ts_range(
+    journal: &Journal,
+    cursor: &cz_io::cursor::Cursor,
+    ts_min: Option<u64>,
+    ts_max: Option<u64>,
+    horizon: PlaybackHorizon,
+) -> std::ops::Range<usize> {
+    let mut ts_max = ts_max.unwrap_or(u64::MAX);
+    if let PlaybackHorizon::UpToTs(at_ts) = horizon {
+        ts_max = ts_max.min(at_ts);
+    }
+    let start = journal.find_first_ge(cursor, ts_min.unwrap_or(0));
+    let mut end = match ts_max.checked_add(1) {
+        Some(past_max) => journal.find_first_ge(cursor, past_max),
+        None => cursor.len(),
+    };
+    if let PlaybackHorizon::UpToSlot(at_slot) = horizon {
+        if let Some(index) = cursor.logical_index(at_slot) {
+            end = end.min(index + 1);
+        }
+    }
+    start..end.max(start)
+}
+
+/// Event counts or stored bytes per group, in fixed-width Lamport buckets.
+///
+/// The range is sought by binary search and then scanned. Buckets run
+/// from the first matching event's to the last's, empty ones included,
+/// so the result can be charted as is.
+async fn api_events_aggregate(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<AggregateParams>,
+) -> Result<Json<AggregateResponse>, (StatusCode, Json<ApiError>)> {
+    let bad_request = |error: String| (StatusCode::BAD_REQUEST, Json(ApiError { error }));
+    let group_by = params.group_by.as_deref().unwrap_or("stream_id");
+    let key = AggregateKey::parse(group_by).ok_or_else(|| {
+        bad_request(format!(
+            "Unknown group_by '{}': use stream_id, node_id or node_id,stream_id",
+            group_by
+        ))
+    })?;
+    let bucket = params.bucket.as_deref().unwrap_or("1s");
+    let bucket_ticks = parse_bucket_ticks(bucket).ok_or_else(|| {
+        bad_request(format!(
+            "Invalid bucket '{}': use ticks or a duration such as 500ms, 1s or 5m",
+            bucket
+        ))
+    })?;
+    let bytes = match params.metric.as_deref().unwrap_or("count") {
+        "count" => false,
+        "bytes" => true,
+        other => {
+            return Err(bad_request(format!(
+                "Unknown metric '{}': use count or bytes",
+                other
+            )))
+        }
+    };
+    let primary = state.resolve_journal(params.journal).await?;
+
+    let horizon = state.playback.read().await.horizon(unix_millis());
+    let journal = primary.journal.read().await;
+    let cursor = partition_view(&journal, params.partition, None)?;
+    let range = visible_ts_range(&journal, &cursor, params.ts_min, params.ts_max, horizon);
+    let matched = range.len();
+    let ts_at = |i| {
+        cursor
+            .slot_at(i)
+            .map(|slot| unsafe { journal.read_event_at(slot) }.lamport_ts)
+    };
+    let bounds = if range.is_empty() {
+        None
+    } else {
+        ts_at(range.start).zip(ts_at(range.end - 1))
+    };
+    let Some((first_ts, last_ts)) = bounds else {
+        return Ok(Json(AggregateResponse {
+            bucket_ticks,
+            matched,
+            buckets: Vec::new(),
+        }));
+    };
+
+    let first_bucket = first_ts / bucket_ticks;
+    let bucket_count = last_ts / bucket_ticks - first_bucket + 1;
+    if bucket_count > MAX_AGGREGATE_BUCKETS {
+        return Err(bad_request(format!(
+            "{} buckets exceed the limit of {}: widen bucket or narrow ts_min/ts_max",
+            bucket_count, MAX_AGGREGATE_BUCKETS
+        )));
+    }
+
+    let mut buckets: Vec<AggregateBucket> = (0..bucket_count)
+        .map(|n| AggregateBucket {
+            ts: (first_bucket + n) * bucket_ticks,
+            groups: BTreeMap::new(),
+        })
+        .collect();
+    let mut groups = std::collections::HashSet::new();
+    for slot in range.filter_map(|i| cursor.slot_at(i)) {
+        let event = unsafe { journal.read_event_at(slot) };
+        if is_empty_event(&event) {
+            continue;
+        }
+        let group = key.of(&event);
+        if groups.len() == MAX_AGGREGATE_GROUPS && !groups.contains(&group) {
+            return Err(bad_request(format!(
+                "More than {} groups by {}: narrow ts_min/ts_max or group by fewer fields",
+                MAX_AGGREGATE_GROUPS, group_by
+            )));
+        }
+        groups.insert(group.clone());
+        let value = if bytes {
+            journal.read_payload(&event).len() as u64
+        } else {
+            1
+        };
+        let bucket = &mut buckets[(event.lamport_ts / bucket_ticks - first_bucket) as usize];
+        *bucket.groups.entry(group).or_default() += value;
+    }
+
+    Ok(Json(AggregateResponse {
+        bucket_ticks,
+        matched,
+        buckets,
+    }))
+}
+
 async fn api_event_detail(
     State(state): State<Arc<AppState>>,
     axum::extract::Path(slot): axum::extract::Path<usize>,
+    Query(params): Query<EventDetailParams>,
 ) -> Result<Json<EventDetailRecord>, (StatusCode, Json<ApiError>)> {
-    let primary = state.get_journal(None).await.unwrap();
+    let primary = state.resolve_journal(None).await?;
     let journal = primary.journal.read().await;
 
-    if slot >= INDEX_RING_CAPACITY {
+    // Slots outside the live window may still hold events from a previous lap.
+    if !primary.cursor.read().await.contains_slot(slot) {
         return Err((
             StatusCode::NOT_FOUND,
             Json(ApiError {
-                error: format!("Slot {} out of range", slot),
+                error: format!("Slot {} is not in the live window", slot),
             }),
         ));
     }
@@ -949,14 +3486,15 @@ async fn api_event_detail(
         ));
     }
 
-    let blob = journal.blob_storage();
-    let payload_start = event.payload_offset as usize;
-    let payload_end = (payload_start + 256).min(blob.len());
-    let payload_slice = if payload_start < blob.len() {
-        &blob[payload_start..payload_end]
-    } else {
-        &[]
-    };
+    let payload_slice =
+        payload::decode_packet(journal.read_payload(&event), &event).map_err(|e| {
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(ApiError {
+                    error: format!("Slot {} payload cannot be read: {}", slot, e),
+                }),
+            )
+        })?;
 
     let payload_hex = payload_slice
         .iter()
@@ -978,51 +3516,106 @@ async fn api_event_detail(
         })
         .collect();
 
+    let trace_id = state
+        .trace_correlations
+        .trace_of_slot(&primary.path.display().to_string(), slot, event.lamport_ts)
+        .await;
+
     Ok(Json(EventDetailRecord {
-        event: EventRecord {
-            slot,
-            lamport_ts: event.lamport_ts,
-            node_id: event.node_id,
-            stream_id: event.stream_id,
-            payload_offset: event.payload_offset,
-            checksum: event.checksum,
-            checkpoint: event.is_checkpoint(),
-        },
+        event: EventRecord::new(&journal, slot, &event, params.verify.unwrap_or(false)),
         payload_hex,
         payload_ascii,
-        payload_size: payload_slice.len(),
+        compressed: event.is_compressed(),
+        decompressed_size: event.is_compressed().then_some(payload_slice.len()),
+        trace_id,
     }))
 }
 
-async fn api_verify(State(_state): State<Arc<AppState>>) -> Json<VerifyResult> {
-    let start = Instant::now();
-    let timestamp = chrono::Utc::now().to_rfc3339();
+/// Start a Kani run in the background. Only one runs at a time: while a
+/// job is running, its id is returned instead of starting another.
+async fn api_verify(State(state): State<Arc<AppState>>) -> (StatusCode, Json<VerifyJob>) {
+    let job = start_verify_job(&state, || {
+        verify::run_kani(&verify::KANI_PACKAGES, std::path::Path::new("."))
+    })
+    .await;
+    (StatusCode::ACCEPTED, Json(job))
+}
 
-    let output = tokio::process::Command::new("cargo")
-        .args(["test", "--workspace", "--", "--quiet"])
-        .current_dir(".")
-        .output()
-        .await;
+async fn start_verify_job<F>(state: &Arc<AppState>, run: F) -> VerifyJob
+where
+    F: FnOnce() -> VerificationReport + Send + 'static,
+{
+    let mut jobs = state.verify_jobs.write().await;
+    if let Some(running) = jobs
+        .jobs
+        .iter()
+        .find(|job| job.status == VerifyJobStatus::Running)
+    {
+        return running.clone();
+    }
 
-    match output {
-        Ok(out) => {
-            let stdout = String::from_utf8_lossy(&out.stdout);
-            let stderr = String::from_utf8_lossy(&out.stderr);
-            let combined = format!("{}\n{}", stdout, stderr);
-            Json(VerifyResult {
-                success: out.status.success(),
-                output: combined.trim().to_string(),
-                duration_ms: start.elapsed().as_millis() as u64,
-                timestamp,
-            })
+    let job = VerifyJob {
+        id: uuid::Uuid::new_v4().to_string(),
+        status: VerifyJobStatus::Running,
+        started_at: chrono::Utc::now(),
+        report: None,
+    };
+    if jobs.jobs.len() == VERIFY_JOB_HISTORY {
+        jobs.jobs.pop_front();
+    }
+    jobs.jobs.push_back(job.clone());
+
+    let state = state.clone();
+    let id = job.id.clone();
+    tokio::spawn(async move {
+        let report = match tokio::task::spawn_blocking(run).await {
+            Ok(report) => report,
+            Err(e) => VerificationReport {
+                success: false,
+                started_at: chrono::Utc::now(),
+                duration_ms: 0,
+                harnesses: Vec::new(),
+                errors: vec![format!("Verification task failed: {}", e)],
+            },
+        };
+        let mut jobs = state.verify_jobs.write().await;
+        if let Some(job) = jobs.jobs.iter_mut().find(|job| job.id == id) {
+            job.status = VerifyJobStatus::Finished;
+            job.report = Some(report.clone());
         }
-        Err(e) => Json(VerifyResult {
-            success: false,
-            output: format!("Failed to execute: {}", e),
-            duration_ms: start.elapsed().as_millis() as u64,
-            timestamp,
+        jobs.latest = Some(report);
+    });
+    job
+}
+
+async fn api_verify_job(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<Json<VerifyJob>, (StatusCode, Json<ApiError>)> {
+    let jobs = state.verify_jobs.read().await;
+    jobs.jobs
+        .iter()
+        .find(|job| job.id == id)
+        .cloned()
+        .map(Json)
+        .ok_or((
+            StatusCode::NOT_FOUND,
+            Json(ApiError {
+                error: "Verification job not found".into(),
+            }),
+        ))
+}
+
+async fn api_verify_latest(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<VerificationReport>, (StatusCode, Json<ApiError>)> {
+    let jobs = state.verify_jobs.read().await;
+    jobs.latest.clone().map(Json).ok_or((
+        StatusCode::NOT_FOUND,
+        Json(ApiError {
+            error: "No verification run has finished yet".into(),
         }),
-    }
+    ))
 }
 
 // =============================================================================
@@ -1038,28 +3631,20 @@ async fn api_simulate(
     let base_stream = params.stream_id.unwrap_or(0);
 
     let journal_path = params.journal.clone();
-    let primary = state.get_journal(journal_path).await.ok_or((
-        StatusCode::NOT_FOUND,
-        Json(ApiError {
-            error: "Journal not found".into(),
-        }),
-    ))?;
+    let primary = state.resolve_journal(journal_path).await?;
+    primary.ensure_writable()?;
 
     let mut journal = primary.journal.write().await;
     let mut cursor = primary.cursor.write().await;
     let base_ts = cz_io::event_loop::EVENTS_PROCESSED.load(Ordering::Relaxed);
 
-    let mut created = 0;
-    for i in 0..count {
-        if cursor.is_full() {
-            break;
-        }
-
-        let slot = match cursor.advance_head() {
-            Some(s) => s,
-            None => break,
-        };
-
+    // Claim the whole burst up front so readers never see it half-written.
+    let created = count.min(cursor.available());
+    let reservation = cursor
+        .reserve(created)
+        .expect("reservation sized to available slots");
+    for i in 0..created {
+        let slot = reservation.slot(i);
         let event = CausalEvent::new(
             base_ts + i as u64 + 1,                    // monotonic-ish for simulation
             base_node + (i as u32 % 5),                // node_id: cycle through 5 nodes
@@ -1071,8 +3656,10 @@ async fn api_simulate(
         unsafe {
             journal.write_event_at(slot, &event);
         }
-        created += 1;
     }
+    let head_after = reservation.commit();
+    journal.publish_cursor(&cursor);
+    observe_watermarks(&state, &primary, &cursor).await;
 
     // Update global counters
     cz_io::event_loop::EVENTS_PROCESSED.fetch_add(created as u64, Ordering::Relaxed);
@@ -1083,7 +3670,7 @@ async fn api_simulate(
 
     Ok(Json(SimulateResult {
         events_created: created,
-        head_after: cursor.head(),
+        head_after,
     }))
 }
 
@@ -1107,44 +3694,90 @@ async fn api_replay(
                 error: "Target journal not found".into(),
             }),
         ))?;
+    source_primary.ensure_open()?;
+    target_primary.ensure_writable()?;
 
     let source_journal = source_primary.journal.read().await;
     let mut target_journal = target_primary.journal.write().await;
     let mut target_cursor = target_primary.cursor.write().await;
 
-    let start = params.start_slot.min(INDEX_RING_CAPACITY.saturating_sub(1));
-    let end = params.end_slot.min(INDEX_RING_CAPACITY.saturating_sub(1));
-    if start > end {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(ApiError {
-                error: "start_slot must be <= end_slot".into(),
-            }),
-        ));
-    }
-
-    let mut replayed = 0;
-    for slot in start..=end {
-        if target_cursor.is_full() {
-            break;
+    let source_cursor = source_primary.cursor.read().await;
+    let from_checkpoint = match params.from_checkpoint {
+        Some(CheckpointRef::Slot(slot)) => Some(slot),
+        Some(CheckpointRef::Latest(true)) => {
+            Some(source_journal.last_checkpoint(&source_cursor).ok_or((
+                StatusCode::NOT_FOUND,
+                Json(ApiError {
+                    error: "Journal has no checkpoint".into(),
+                }),
+            ))?)
         }
-
-        let event = unsafe { source_journal.read_event_at(slot) };
-        if is_empty_event(&event) {
-            continue;
-        } // Skip empty slots
-
-        let target_slot = match target_cursor.advance_head() {
-            Some(s) => s,
-            None => break,
+        Some(CheckpointRef::Latest(false)) | None => None,
+    };
+    let slots: Vec<usize> = if let Some(cp_slot) = from_checkpoint {
+        let event = if source_cursor.contains_slot(cp_slot) {
+            unsafe { source_journal.read_event_at(cp_slot) }
+        } else {
+            CausalEvent::new(0, 0, 0, 0, 0)
         };
-
-        // We preserve the original event content but it's re-sequenced at the head
-        unsafe {
-            target_journal.write_event_at(target_slot, &event);
+        if !event.is_checkpoint() {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ApiError {
+                    error: format!("Slot {} is not a checkpoint", cp_slot),
+                }),
+            ));
         }
-        replayed += 1;
-    }
+        source_cursor
+            .slots()
+            .skip_while(|&slot| slot != cp_slot)
+            .skip(1)
+            .collect()
+    } else {
+        let (Some(start_slot), Some(end_slot)) = (params.start_slot, params.end_slot) else {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ApiError {
+                    error: "Either from_checkpoint or start_slot/end_slot is required".into(),
+                }),
+            ));
+        };
+        replay::slot_range(start_slot, end_slot, source_cursor.capacity())
+            .map_err(|e| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    Json(ApiError {
+                        error: e.to_string(),
+                    }),
+                )
+            })?
+            .collect()
+    };
+
+    let floor = replay::lamport_floor(&target_journal, &target_cursor);
+    let batch = replay::prepare(
+        &source_journal,
+        slots,
+        target_cursor.available(),
+        floor,
+        params.resequence,
+    )
+    .map_err(|e| {
+        (
+            StatusCode::CONFLICT,
+            Json(ApiError {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    let replayed = batch.events.len();
+    let reservation = target_cursor
+        .reserve(replayed)
+        .expect("reservation sized to available slots");
+    let new_head = replay::write(&mut target_journal, reservation, &batch.events, |_| {});
+    target_journal.publish_cursor(&target_cursor);
+    observe_watermarks(&state, &target_primary, &target_cursor).await;
 
     // Update global counters
     cz_io::event_loop::EVENTS_PROCESSED.fetch_add(replayed as u64, Ordering::Relaxed);
@@ -1154,8 +3787,322 @@ async fn api_replay(
     );
 
     Ok(Json(ReplayResult {
+        mode: batch.mode.as_str(),
         events_replayed: replayed,
-        new_head: target_cursor.head(),
+        new_head,
+        skipped: batch.skipped(),
+        resequenced: batch.resequenced,
+    }))
+}
+
+/// Write a checkpoint marker at the head of a journal.
+///
+/// It covers the ring since the previous checkpoint and sorts after every
+/// event already written, like the ones the sequencer emits.
+async fn api_checkpoint_create(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<CreateCheckpointRequest>,
+) -> Result<(StatusCode, Json<CheckpointRecord>), (StatusCode, Json<ApiError>)> {
+    let primary = state.resolve_journal(req.journal).await?;
+    primary.ensure_writable()?;
+
+    let mut journal = primary.journal.write().await;
+    let mut cursor = primary.cursor.write().await;
+    let no_space = |error: &str| {
+        (
+            StatusCode::INSUFFICIENT_STORAGE,
+            Json(ApiError {
+                error: error.into(),
+            }),
+        )
+    };
+    // Checkpoint records are spread over blob storage by slot.
+    let blob_slots = journal.blob_capacity() / checkpoint::CHECKPOINT_RECORD_SIZE;
+    if blob_slots == 0 {
+        return Err(no_space("Blob storage cannot hold a checkpoint"));
+    }
+
+    // The segment runs from just past the last checkpoint (else from the
+    // tail) up to the slot about to be claimed.
+    let claimed = cursor.base() + cursor.head();
+    let segment_start = match journal.last_checkpoint(&cursor) {
+        Some(slot) => cursor
+            .logical_index(slot)
+            .and_then(|i| cursor.slot_at(i + 1))
+            .unwrap_or(claimed),
+        None => cursor.peek_tail_slot().unwrap_or(claimed),
+    };
+    let lamport_ts = replay::lamport_floor(&journal, &cursor).map_or(0, |ts| ts + 1);
+    let slot = cursor
+        .advance_head()
+        .ok_or_else(|| no_space("Ring is full"))?;
+    let blob_offset = (slot % blob_slots) * checkpoint::CHECKPOINT_RECORD_SIZE;
+    let cp = journal.write_checkpoint(
+        &cursor,
+        slot,
+        checkpoint::SEQUENCER_NODE_ID,
+        lamport_ts,
+        segment_start,
+        blob_offset,
+    );
+    journal.publish_cursor(&cursor);
+    observe_watermarks(&state, &primary, &cursor).await;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CheckpointRecord {
+            slot,
+            lamport_ts,
+            node_id: checkpoint::SEQUENCER_NODE_ID,
+            head: cp.head,
+            tail: cp.tail,
+            lamport_watermark: cp.lamport_watermark,
+            segment_start: cp.segment_start,
+            segment_crc: cp.segment_crc,
+            valid: true,
+        }),
+    ))
+}
+
+async fn api_checkpoints(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<CheckpointListResponse>, (StatusCode, Json<ApiError>)> {
+    let journal_path = params.get("journal");
+    let primary = state.resolve_journal(journal_path.cloned()).await?;
+
+    let journal = primary.journal.read().await;
+    let cursor = primary.cursor.read().await;
+
+    let mut checkpoints = Vec::new();
+    for slot in cursor.slots() {
+        let event = unsafe { journal.read_event_at(slot) };
+        if !event.is_checkpoint() {
+            continue;
+        }
+        let Some(cp) = checkpoint::read(journal.blob_storage(), &event) else {
+            continue;
+        };
+        let valid = checkpoint::verify(journal.index_ring(), journal.blob_storage(), slot, &event)
+            .is_some();
+        checkpoints.push(CheckpointRecord {
+            slot,
+            lamport_ts: event.lamport_ts,
+            node_id: event.node_id,
+            head: cp.head,
+            tail: cp.tail,
+            lamport_watermark: cp.lamport_watermark,
+            segment_start: cp.segment_start,
+            segment_crc: cp.segment_crc,
+            valid,
+        });
+    }
+
+    Ok(Json(CheckpointListResponse {
+        total: checkpoints.len(),
+        checkpoints,
+    }))
+}
+
+/// Check one page of a journal's live window for causality anomalies (see
+/// `cz_hub::anomaly`). Follow `next_cursor` to check the rest; the scan
+/// runs on a blocking thread.
+async fn api_anomalies(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<AnomalyParams>,
+) -> Result<Json<AnomalyResponse>, (StatusCode, Json<ApiError>)> {
+    let mut scanner = match params.cursor.as_deref() {
+        Some(raw) => {
+            let (slot, lamport_ts) = parse_export_cursor(raw).ok_or_else(|| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    Json(ApiError {
+                        error: format!("Invalid cursor '{}': expected <slot>:<lamport_ts>", raw),
+                    }),
+                )
+            })?;
+            AnomalyScanner::after(slot, lamport_ts)
+        }
+        None => AnomalyScanner::default(),
+    };
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_ANOMALY_SCAN)
+        .clamp(1, MAX_ANOMALY_SCAN);
+    let primary = state.resolve_journal(params.journal).await?;
+
+    let cursor = partition_view(&*primary.journal.read().await, params.partition, None)?;
+    let scanned = primary.clone();
+    let (page, position) = tokio::task::spawn_blocking(move || {
+        let page = scanner.scan(&scanned.journal.blocking_read(), &cursor, limit);
+        (page, scanner.position())
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiError {
+                error: format!("Anomaly scan failed: {}", e),
+            }),
+        )
+    })?;
+
+    let mut counts: BTreeMap<_, _> = AnomalyKind::ALL.iter().map(|k| (k.as_str(), 0)).collect();
+    for anomaly in &page.findings {
+        *counts.entry(anomaly.kind.as_str()).or_default() += 1;
+    }
+    Ok(Json(AnomalyResponse {
+        journal: primary.path.display().to_string(),
+        scanned: page.scanned,
+        counts,
+        findings: page.findings,
+        next_cursor: position
+            .filter(|_| page.more)
+            .map(|(slot, lamport_ts)| format!("{}:{}", slot, lamport_ts)),
+    }))
+}
+
+/// Recompute every live event's checksum from the payload on disk (see
+/// `Journal::integrity_scan`). The scan reads the whole live window, so it
+/// runs on a blocking thread.
+async fn api_journal_verify(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<JournalScanResponse>, (StatusCode, Json<ApiError>)> {
+    let journal_path = params.get("journal");
+    let primary = state.resolve_journal(journal_path.cloned()).await?;
+
+    let snapshot = {
+        let cursor = primary.cursor.read().await;
+        cz_io::cursor::Cursor::restore(cursor.capacity(), cursor.head(), cursor.tail())
+    };
+    let started = Instant::now();
+    let scanned = primary.clone();
+    let report = tokio::task::spawn_blocking(move || {
+        scanned.journal.blocking_read().integrity_scan(&snapshot)
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiError {
+                error: format!("Integrity scan failed: {}", e),
+            }),
+        )
+    })?;
+
+    Ok(Json(JournalScanResponse {
+        journal: primary.path.display().to_string(),
+        scanned: report.scanned,
+        matched: report.matched,
+        mismatched: report.mismatched.count,
+        unreadable: report.unreadable.count,
+        clean: report.is_clean(),
+        mismatched_slots: report.mismatched.slots,
+        unreadable_slots: report.unreadable.slots,
+        duration_ms: started.elapsed().as_millis() as u64,
+    }))
+}
+
+async fn api_consumers_get(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<ConsumerListResponse>, (StatusCode, Json<ApiError>)> {
+    let journal_path = params.get("journal");
+    let primary = state.resolve_journal(journal_path.cloned()).await?;
+
+    let cursor = primary.cursor.read().await;
+    let consumers = cursor
+        .consumers()
+        .iter()
+        .map(|c| ConsumerRecord {
+            name: c.name.clone(),
+            position: c.position,
+            lag: cursor.consumer_lag(&c.name).unwrap_or(0),
+        })
+        .collect();
+
+    Ok(Json(ConsumerListResponse {
+        consumers,
+        head: cursor.head(),
+        tail: cursor.tail(),
+    }))
+}
+
+async fn api_consumers_register(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<RegisterConsumerRequest>,
+) -> Result<StatusCode, (StatusCode, Json<ApiError>)> {
+    if req.name.is_empty() || req.name.len() > MAX_CONSUMER_NAME_LEN {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiError {
+                error: format!("Consumer name must be 1-{} bytes", MAX_CONSUMER_NAME_LEN),
+            }),
+        ));
+    }
+
+    let primary = state.resolve_journal(req.journal).await?;
+    primary.ensure_writable()?;
+
+    let mut journal = primary.journal.write().await;
+    let mut cursor = primary.cursor.write().await;
+    if !cursor.register_consumer(&req.name) {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(ApiError {
+                error: format!("Consumer '{}' already registered", req.name),
+            }),
+        ));
+    }
+
+    if let Err(e) = journal.persist_consumer_offsets(&cursor.consumer_offsets()) {
+        cursor.unregister_consumer(&req.name);
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiError {
+                error: format!("Failed to persist consumer: {}", e),
+            }),
+        ));
+    }
+
+    Ok(StatusCode::CREATED)
+}
+
+async fn api_sequencer_command(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<SequencerCommandParams>,
+    Json(req): Json<SequencerCommandRequest>,
+) -> Result<Json<SequencerCommandResponse>, (StatusCode, Json<ApiError>)> {
+    let journal = state.resolve_journal(params.journal).await?;
+    let socket = state.sequencer_socket(&journal.path);
+    let command = IpcCommand::from(req);
+    let reply = tokio::task::spawn_blocking(move || {
+        cz_io::ipc::send_command(&socket, command, std::time::Duration::from_secs(5))
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|r| r.map_err(|e| e.to_string()))
+    .map_err(|e| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ApiError {
+                error: format!("Sequencer unreachable: {}", e),
+            }),
+        )
+    })?;
+
+    if !reply.ok {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(ApiError {
+                error: reply.detail,
+            }),
+        ));
+    }
+    Ok(Json(SequencerCommandResponse {
+        detail: reply.detail,
+        stats: reply.stats.map(Into::into),
     }))
 }
 
@@ -1164,12 +4111,7 @@ async fn api_topology(
     Query(params): Query<HashMap<String, String>>,
 ) -> Result<Json<TopologyResponse>, (StatusCode, Json<ApiError>)> {
     let journal_path = params.get("journal");
-    let primary = state.get_journal(journal_path.cloned()).await.ok_or((
-        StatusCode::NOT_FOUND,
-        Json(ApiError {
-            error: "Journal not found".into(),
-        }),
-    ))?;
+    let primary = state.resolve_journal(journal_path.cloned()).await?;
 
     let journal = primary.journal.read().await;
     let cursor = primary.cursor.read().await;
@@ -1177,8 +4119,7 @@ async fn api_topology(
 
     let mut node_map: HashMap<u32, (usize, Vec<u16>, u64, u64)> = HashMap::new();
 
-    for i in 0..total.min(50000) {
-        let slot = (cursor.tail() + i) % INDEX_RING_CAPACITY;
+    for slot in cursor.slots().take(50000) {
         let event = unsafe { journal.read_event_at(slot) };
         if is_empty_event(&event) {
             continue;
@@ -1219,21 +4160,14 @@ async fn api_streams(
     Query(params): Query<HashMap<String, String>>,
 ) -> Result<Json<StreamsResponse>, (StatusCode, Json<ApiError>)> {
     let journal_path = params.get("journal");
-    let primary = state.get_journal(journal_path.cloned()).await.ok_or((
-        StatusCode::NOT_FOUND,
-        Json(ApiError {
-            error: "Journal not found".into(),
-        }),
-    ))?;
+    let primary = state.resolve_journal(journal_path.cloned()).await?;
 
     let journal = primary.journal.read().await;
     let cursor = primary.cursor.read().await;
-    let total = cursor.len();
 
     let mut stream_map: HashMap<u16, (usize, Vec<u32>, u64, u64)> = HashMap::new();
 
-    for i in 0..total.min(50000) {
-        let slot = (cursor.tail() + i) % INDEX_RING_CAPACITY;
+    for slot in cursor.slots().take(50000) {
         let event = unsafe { journal.read_event_at(slot) };
         if is_empty_event(&event) {
             continue;
@@ -1266,24 +4200,106 @@ async fn api_streams(
     }))
 }
 
-async fn api_journal_layout(State(state): State<Arc<AppState>>) -> Json<JournalLayout> {
-    let primary = state.get_journal(None).await.unwrap();
+async fn api_journal_layout(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<JournalLayout>, (StatusCode, Json<ApiError>)> {
+    let primary = state.resolve_journal(None).await?;
     let journal = primary.journal.read().await;
     let cursor = primary.cursor.read().await;
 
-    Json(JournalLayout {
+    Ok(Json(JournalLayout {
         total_size_bytes: journal.size(),
-        index_ring_start: 0,
-        index_ring_end: INDEX_RING_SIZE,
-        index_ring_size_bytes: INDEX_RING_SIZE,
-        index_ring_slot_count: INDEX_RING_CAPACITY,
+        header_size_bytes: HEADER_SIZE,
+        index_ring_start: HEADER_SIZE,
+        index_ring_end: HEADER_SIZE + journal.index_ring_size(),
+        index_ring_size_bytes: journal.index_ring_size(),
+        index_ring_slot_count: journal.index_ring_capacity(),
         index_ring_slot_size: CausalEvent::size_bytes(),
-        blob_storage_start: INDEX_RING_SIZE,
+        blob_storage_start: HEADER_SIZE + journal.index_ring_size(),
         blob_storage_end: journal.size(),
-        blob_storage_size_bytes: journal.size() - INDEX_RING_SIZE as u64,
+        blob_storage_size_bytes: journal.blob_capacity() as u64,
         slots_used: cursor.len(),
-        slots_free: INDEX_RING_CAPACITY - cursor.len(),
-    })
+        slots_free: journal.index_ring_capacity() - cursor.len(),
+    }))
+}
+
+/// Copy the live events of a journal and their payloads into a new journal
+/// file. The read lock keeps the hub's own writers out for the copy; the
+/// sequencer keeps appending, and only the window it had published when
+/// the copy started is taken.
+async fn api_journal_snapshot(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<SnapshotRequest>,
+) -> Result<Json<SnapshotResult>, (StatusCode, Json<ApiError>)> {
+    let error = |status, error: String| (status, Json(ApiError { error }));
+    let primary = state.resolve_journal(req.journal).await?;
+    primary.ensure_open()?;
+    if req.path.exists() {
+        return Err(error(
+            StatusCode::CONFLICT,
+            format!("{} already exists", req.path.display()),
+        ));
+    }
+
+    let journal = primary.journal.read().await;
+    let events = journal.copy_live_to(&req.path).map_err(|e| {
+        error(
+            StatusCode::BAD_REQUEST,
+            format!("Failed to snapshot to {}: {}", req.path.display(), e),
+        )
+    })?;
+    tracing::info!(
+        "Snapshotted {} events of {:?} to {:?}",
+        events,
+        primary.path,
+        req.path
+    );
+    Ok(Json(SnapshotResult {
+        path: req.path.display().to_string(),
+        events,
+    }))
+}
+
+async fn api_journals_list(State(state): State<Arc<AppState>>) -> Json<Vec<JournalInfo>> {
+    let mut journals: Vec<Arc<JournalState>> =
+        state.journals.read().await.values().cloned().collect();
+    journals.sort_by(|a, b| a.path.cmp(&b.path));
+    let mut infos = Vec::with_capacity(journals.len());
+    for journal_state in journals {
+        infos.push(JournalInfo::of(&journal_state).await);
+    }
+    Json(infos)
+}
+
+async fn api_journals_open(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<OpenJournalRequest>,
+) -> Result<(StatusCode, Json<JournalInfo>), (StatusCode, Json<ApiError>)> {
+    let journal_state = state.open_journal(req.path, req.size, req.readonly).await?;
+    let correlating = (state.clone(), journal_state.clone());
+    tokio::spawn(async move { correlating.0.correlate_journal(&correlating.1).await });
+    tracing::info!(
+        "Opened journal {} at {:?}",
+        journal_state.id,
+        journal_state.path
+    );
+    Ok((
+        StatusCode::CREATED,
+        Json(JournalInfo::of(&journal_state).await),
+    ))
+}
+
+async fn api_journals_remove(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<Json<JournalInfo>, (StatusCode, Json<ApiError>)> {
+    let journal_state = state.remove_journal(&id).await?;
+    tracing::info!(
+        "Removed journal {} at {:?}",
+        journal_state.id,
+        journal_state.path
+    );
+    Ok(Json(JournalInfo::of(&journal_state).await))
 }
 
 async fn api_system(State(state): State<Arc<AppState>>) -> Json<SystemResources> {
@@ -1297,14 +4313,12 @@ async fn api_system(State(state): State<Arc<AppState>>) -> Json<SystemResources>
         for line in content.lines() {
             if let Some(val) = line.strip_prefix("VmRSS:") {
                 rss = val
-                    .trim()
                     .split_whitespace()
                     .next()
                     .and_then(|v| v.parse().ok())
                     .unwrap_or(0);
             } else if let Some(val) = line.strip_prefix("VmSize:") {
                 vms = val
-                    .trim()
                     .split_whitespace()
                     .next()
                     .and_then(|v| v.parse().ok())
@@ -1324,28 +4338,135 @@ async fn api_system(State(state): State<Arc<AppState>>) -> Json<SystemResources>
     })
 }
 
+/// Snapshots of `/api/metrics/history` folded into one `bucket_seconds`
+/// window: rates averaged, utilization at its peak.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+struct MetricsBucket {
+    /// Start of the window, a multiple of `bucket_seconds` since the epoch.
+    timestamp: String,
+    /// Snapshots in the window.
+    samples: usize,
+    /// Whether the window reaches past the newest snapshot, so it is still
+    /// filling and its averages cover only `samples` seconds.
+    partial: bool,
+    /// Totals as of the window's last snapshot.
+    events: u64,
+    bytes: u64,
+    tps: f64,
+    bps: f64,
+    utilization_pct: f64,
+    /// Peak `utilization_pct` of each journal's ring, keyed by path.
+    journals: BTreeMap<String, f64>,
+}
+
+/// Fold `snapshots`, oldest first, into windows of `seconds` aligned to
+/// the epoch, so the same window has the same bounds on every request.
+fn bucket_snapshots(snapshots: &[MetricsSnapshot], seconds: u64) -> Vec<MetricsBucket> {
+    let seconds = seconds as i64;
+    let mut buckets: Vec<(i64, Vec<&MetricsSnapshot>)> = Vec::new();
+    let mut newest = None;
+    for snapshot in snapshots {
+        let Ok(at) = chrono::DateTime::parse_from_rfc3339(&snapshot.timestamp) else {
+            continue;
+        };
+        let at = at.timestamp();
+        newest = Some(at);
+        let start = at - at.rem_euclid(seconds);
+        match buckets.last_mut() {
+            Some((last, members)) if *last == start => members.push(snapshot),
+            _ => buckets.push((start, vec![snapshot])),
+        }
+    }
+    buckets
+        .into_iter()
+        .map(|(start, members)| {
+            let n = members.len() as f64;
+            let last = members[members.len() - 1];
+            let mut journals: BTreeMap<String, f64> = BTreeMap::new();
+            for (path, ring) in members.iter().flat_map(|s| &s.journals) {
+                let peak = journals.entry(path.clone()).or_insert(ring.utilization_pct);
+                *peak = peak.max(ring.utilization_pct);
+            }
+            MetricsBucket {
+                timestamp: chrono::DateTime::from_timestamp(start, 0)
+                    .unwrap_or_default()
+                    .to_rfc3339(),
+                samples: members.len(),
+                partial: newest.is_some_and(|newest| start + seconds > newest + 1),
+                events: last.events,
+                bytes: last.bytes,
+                tps: members.iter().map(|s| s.tps).sum::<f64>() / n,
+                bps: members.iter().map(|s| s.bps).sum::<f64>() / n,
+                utilization_pct: members
+                    .iter()
+                    .map(|s| s.utilization_pct)
+                    .fold(0.0, f64::max),
+                journals,
+            }
+        })
+        .collect()
+}
+
+/// The last `minutes` of snapshots, or with `bucket_seconds`, the same
+/// window as [`MetricsBucket`]s.
 async fn api_metrics_history(
     State(state): State<Arc<AppState>>,
     Query(params): Query<HashMap<String, String>>,
-) -> Json<Vec<MetricsSnapshot>> {
+) -> Result<Response, (StatusCode, Json<ApiError>)> {
     let minutes = params
         .get("minutes")
         .and_then(|v| v.parse::<usize>().ok())
         .unwrap_or(5);
+    let bucket_seconds = match params.get("bucket_seconds") {
+        Some(raw) => match raw.parse::<u64>() {
+            Ok(seconds @ 1..=3600) => Some(seconds),
+            _ => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    Json(ApiError {
+                        error: format!("bucket_seconds must be 1 to 3600, got '{}'", raw),
+                    }),
+                ))
+            }
+        },
+        None => None,
+    };
 
     let count = (minutes * 60).min(3600);
+    // While paused, hide snapshots collected after the freeze.
+    let paused = state.playback.read().await.is_paused();
+    // Snapshots key journals by path; accept an id too.
+    let journal = match params.get("journal") {
+        Some(name) => Some(
+            state
+                .get_journal(Some(name.clone()))
+                .await
+                .map_or_else(|| name.clone(), |j| j.path.display().to_string()),
+        ),
+        None => None,
+    };
     let history = state.metrics_history.read().await;
     let snapshots: Vec<MetricsSnapshot> = history
         .iter()
         .rev()
+        .filter(|s| !paused || !s.playback_mode.is_paused())
         .take(count)
         .cloned()
         .collect::<Vec<_>>()
         .into_iter()
         .rev()
+        .map(|mut snapshot| {
+            if let Some(journal) = &journal {
+                snapshot.journals.retain(|path, _| path == journal);
+            }
+            snapshot
+        })
         .collect();
 
-    Json(snapshots)
+    Ok(match bucket_seconds {
+        Some(seconds) => Json(bucket_snapshots(&snapshots, seconds)).into_response(),
+        None => Json(snapshots).into_response(),
+    })
 }
 
 async fn api_alerts_get(State(state): State<Arc<AppState>>) -> Json<Vec<Alert>> {
@@ -1367,196 +4488,865 @@ async fn api_alert_rules_set(
     Json(rules)
 }
 
-async fn api_export(
-    State(state): State<Arc<AppState>>,
-    Query(params): Query<ExportParams>,
-) -> impl IntoResponse {
-    let format = params.format.unwrap_or_else(|| "json".into());
-    let limit = params.limit.unwrap_or(1000).min(50000);
-
-    let journal_path = params.journal.clone();
-    let Some(primary) = state.get_journal(journal_path).await else {
-        return (
-            StatusCode::NOT_FOUND,
-            [(header::CONTENT_TYPE, "application/json")],
-            r#"{"error":"Journal not found"}"#.to_string(),
-        )
-            .into_response();
-    };
-
-    let journal = primary.journal.read().await;
-    let cursor = primary.cursor.read().await;
-    let total = cursor.len().min(limit);
+/// Events read from the journal per export chunk. The journal read lock is
+/// held only while a chunk is copied out.
+const EXPORT_CHUNK_EVENTS: usize = 1024;
+
+const EXPORT_CSV_HEADER: &str =
+    "slot,lamport_ts,node_id,stream_id,payload_offset,checksum,checkpoint,payload_size,flags,checksum_valid\n";
+
+/// Copy the non-empty events among `count` live slots starting `start`
+/// places after the snapshot's tail.
+fn export_records(
+    journal: &Journal,
+    snapshot: &cz_io::cursor::Cursor,
+    start: usize,
+    count: usize,
+    verify: bool,
+) -> Vec<EventRecord> {
+    (start..start + count)
+        .filter_map(|i| snapshot.slot_at(i))
+        .filter_map(|slot| {
+            let event = unsafe { journal.read_event_at(slot) };
+            (!is_empty_event(&event)).then(|| EventRecord::new(journal, slot, &event, verify))
+        })
+        .collect()
+}
 
-    let mut events = Vec::with_capacity(total);
-    for i in 0..total {
-        let slot = (cursor.tail() + i) % INDEX_RING_CAPACITY;
-        let event = unsafe { journal.read_event_at(slot) };
-        if is_empty_event(&event) {
-            continue;
-        }
-        events.push(EventRecord {
-            slot,
-            lamport_ts: event.lamport_ts,
-            node_id: event.node_id,
-            stream_id: event.stream_id,
-            payload_offset: event.payload_offset,
-            checksum: event.checksum,
-            checkpoint: event.is_checkpoint(),
-        });
-    }
-
-    match format.as_str() {
-        "csv" => {
-            let mut csv = String::from(
-                "slot,lamport_ts,node_id,stream_id,payload_offset,checksum,checkpoint\n",
-            );
-            for e in &events {
-                csv.push_str(&format!(
-                    "{},{},{},{},{},{},{}\n",
+/// Render one chunk of records. `first` marks the chunk that opens the
+/// JSON array.
+fn encode_export_chunk(format: &str, records: &[EventRecord], first: bool) -> Vec<u8> {
+    let mut out = Vec::new();
+    for (i, e) in records.iter().enumerate() {
+        match format {
+            "csv" => out.extend_from_slice(
+                format!(
+                    "{},{},{},{},{},{},{},{},{},{}\n",
                     e.slot,
                     e.lamport_ts,
                     e.node_id,
                     e.stream_id,
                     e.payload_offset,
                     e.checksum,
-                    e.checkpoint
-                ));
+                    e.checkpoint,
+                    e.payload_size,
+                    e.flags,
+                    // Empty when the export was not verified.
+                    e.checksum_valid
+                        .map_or(String::new(), |valid| valid.to_string())
+                )
+                .as_bytes(),
+            ),
+            "ndjson" => {
+                out.extend(serde_json::to_vec(e).unwrap_or_default());
+                out.push(b'\n');
             }
-            (
-                StatusCode::OK,
-                [
-                    (header::CONTENT_TYPE, "text/csv"),
-                    (
-                        header::CONTENT_DISPOSITION,
-                        "attachment; filename=\"causal-events.csv\"",
-                    ),
-                ],
-                csv,
+            _ => {
+                if !(first && i == 0) {
+                    out.push(b',');
+                }
+                out.extend_from_slice(b"\n  ");
+                out.extend(serde_json::to_vec(e).unwrap_or_default());
+            }
+        }
+    }
+    out
+}
+
+/// Most events a Parquet export holds: its footer describes every row
+/// group, so the file is built in memory rather than streamed.
+#[cfg(feature = "parquet")]
+const PARQUET_EXPORT_LIMIT: usize = 50_000;
+
+/// Where an interrupted export resumes: `<slot>:<lamport_ts>` of the last
+/// record the client received, both of which every format carries.
+fn parse_export_cursor(raw: &str) -> Option<(usize, u64)> {
+    let (slot, lamport_ts) = raw.split_once(':')?;
+    Some((slot.parse().ok()?, lamport_ts.parse().ok()?))
+}
+
+fn export_error(status: StatusCode, error: String) -> Response {
+    (status, Json(ApiError { error })).into_response()
+}
+
+/// Delivers encoded chunks to the response body, through a zstd encoder
+/// when the export is compressed.
+struct ExportSink {
+    tx: tokio::sync::mpsc::Sender<Vec<u8>>,
+    zstd: Option<zstd::stream::write::Encoder<'static, Vec<u8>>>,
+}
+
+impl ExportSink {
+    /// Returns `false` once the client has hung up.
+    async fn send(&mut self, chunk: Vec<u8>) -> bool {
+        let chunk = match &mut self.zstd {
+            Some(encoder) => {
+                use std::io::Write;
+                if encoder.write_all(&chunk).is_err() {
+                    return false;
+                }
+                // The encoder emits whole blocks as its window fills; pass
+                // on whatever it has produced so far.
+                std::mem::take(encoder.get_mut())
+            }
+            None => chunk,
+        };
+        chunk.is_empty() || self.tx.send(chunk).await.is_ok()
+    }
+
+    async fn finish(self) {
+        if let Some(encoder) = self.zstd {
+            if let Ok(rest) = encoder.finish() {
+                let _ = self.tx.send(rest).await;
+            }
+        }
+    }
+}
+
+/// Export the live window as JSON, NDJSON or CSV, streamed in chunks of
+/// [`EXPORT_CHUNK_EVENTS`] so memory stays bounded however many events
+/// match; optionally zstd-compressed. `ts_min`/`ts_max` seek the window,
+/// `stream_id` filters it, and `cursor` resumes after a record already
+/// received. Parquet is built whole and capped at `PARQUET_EXPORT_LIMIT`.
+async fn api_export(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<ExportParams>,
+) -> Response {
+    let format = params.format.clone().unwrap_or_else(|| "json".into());
+    let compress = match params.compress.as_deref() {
+        None => false,
+        Some("zstd") if format != "parquet" => true,
+        Some(other) => {
+            return export_error(
+                StatusCode::BAD_REQUEST,
+                format!("Unsupported compression '{}' for {} export", other, format),
             )
-                .into_response()
         }
-        _ => {
-            let json = serde_json::to_string_pretty(&events).unwrap_or_default();
-            (
+    };
+
+    let primary = match state.resolve_journal(params.journal.clone()).await {
+        Ok(primary) => primary,
+        Err((status, Json(e))) => return export_error(status, e.error),
+    };
+
+    // Later writes must not shift the exported window mid-stream.
+    let snapshot = {
+        let journal = primary.journal.read().await;
+        if journal.partitions() == 1 {
+            let cursor = primary.cursor.read().await;
+            cz_io::cursor::Cursor::restore(cursor.capacity(), cursor.head(), cursor.tail())
+        } else {
+            match partition_view(&journal, params.partition, params.stream_id) {
+                Ok(view) => view,
+                Err((status, Json(e))) => return export_error(status, e.error),
+            }
+        }
+    };
+    let window = {
+        let journal = primary.journal.read().await;
+        let mut start = journal.find_first_ge(&snapshot, params.ts_min.unwrap_or(0));
+        let end = params
+            .ts_max
+            .and_then(|ts_max| ts_max.checked_add(1))
+            .map_or(snapshot.len(), |past_max| {
+                journal.find_first_ge(&snapshot, past_max)
+            });
+        if let Some(raw) = &params.cursor {
+            let Some((slot, lamport_ts)) = parse_export_cursor(raw) else {
+                return export_error(
+                    StatusCode::BAD_REQUEST,
+                    format!("Export cursor '{}' is not <slot>:<lamport_ts>", raw),
+                );
+            };
+            // The slot must still hold the event the client saw last.
+            let resume = snapshot.logical_index(slot).filter(|_| {
+                let event = unsafe { journal.read_event_at(slot) };
+                event.lamport_ts == lamport_ts
+            });
+            let Some(index) = resume else {
+                return export_error(
+                    StatusCode::GONE,
+                    format!("Export cursor '{}' is no longer in the ring", raw),
+                );
+            };
+            start = start.max(index + 1);
+        }
+        start..end.max(start)
+    };
+    let stream_id = params.stream_id;
+    let verify = params.verify.unwrap_or(false);
+    let matches = move |e: &EventRecord| stream_id.is_none_or(|id| e.stream_id == id);
+
+    #[cfg(feature = "parquet")]
+    if format == "parquet" {
+        let mut events = export_records(
+            &*primary.journal.read().await,
+            &snapshot,
+            window.start,
+            window.len(),
+            verify,
+        );
+        events.retain(matches);
+        events.truncate(params.limit.unwrap_or(usize::MAX).min(PARQUET_EXPORT_LIMIT));
+        return match events_to_parquet(&events) {
+            Ok(bytes) => (
                 StatusCode::OK,
                 [
-                    (header::CONTENT_TYPE, "application/json"),
+                    (header::CONTENT_TYPE, "application/vnd.apache.parquet"),
                     (
                         header::CONTENT_DISPOSITION,
-                        "attachment; filename=\"causal-events.json\"",
+                        "attachment; filename=\"causal-events.parquet\"",
                     ),
                 ],
-                json,
+                bytes,
             )
-                .into_response()
-        }
+                .into_response(),
+            Err(e) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Parquet export failed: {}", e),
+            )
+                .into_response(),
+        };
     }
+
+    let (content_type, filename) = match format.as_str() {
+        "csv" => ("text/csv", "causal-events.csv"),
+        "ndjson" => ("application/x-ndjson", "causal-events.ndjson"),
+        _ => ("application/json", "causal-events.json"),
+    };
+    let (content_type, filename) = if compress {
+        ("application/zstd", format!("{}.zst", filename))
+    } else {
+        (content_type, filename.to_string())
+    };
+    let zstd = if compress {
+        match zstd::stream::write::Encoder::new(Vec::new(), 0) {
+            Ok(encoder) => Some(encoder),
+            Err(e) => {
+                return export_error(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("zstd encoder failed: {}", e),
+                )
+            }
+        }
+    } else {
+        None
+    };
+
+    let (tx, rx) = tokio::sync::mpsc::channel::<Vec<u8>>(4);
+    tokio::spawn(async move {
+        let mut sink = ExportSink { tx, zstd };
+        let opening: &[u8] = match format.as_str() {
+            "csv" => EXPORT_CSV_HEADER.as_bytes(),
+            "ndjson" => b"",
+            _ => b"[",
+        };
+        if !sink.send(opening.to_vec()).await {
+            return;
+        }
+
+        let mut remaining = params.limit.unwrap_or(usize::MAX);
+        let mut first = true;
+        for start in window.clone().step_by(EXPORT_CHUNK_EVENTS) {
+            if remaining == 0 {
+                break;
+            }
+            let count = EXPORT_CHUNK_EVENTS.min(window.end - start);
+            let mut records = {
+                let journal = primary.journal.read().await;
+                export_records(&journal, &snapshot, start, count, verify)
+            };
+            records.retain(matches);
+            records.truncate(remaining);
+            remaining -= records.len();
+
+            let chunk = encode_export_chunk(&format, &records, first);
+            first &= records.is_empty();
+            if !sink.send(chunk).await {
+                return;
+            }
+        }
+
+        if !matches!(format.as_str(), "csv" | "ndjson") && !sink.send(b"\n]\n".to_vec()).await {
+            return;
+        }
+        sink.finish().await;
+    });
+
+    let body = futures_util::stream::unfold(rx, |mut rx| async move {
+        rx.recv()
+            .await
+            .map(|chunk| (Ok::<_, std::convert::Infallible>(chunk), rx))
+    });
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        axum::body::Body::from_stream(body),
+    )
+        .into_response()
 }
 
 // =============================================================================
 // WebSocket Handler
 // =============================================================================
 
-async fn ws_handler(ws: WebSocketUpgrade, State(state): State<Arc<AppState>>) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handle_socket(socket, state))
+/// Channels a WS client can subscribe to.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+enum WsChannel {
+    Metrics,
+    Events,
+    Alerts,
+    Query,
 }
 
-async fn handle_socket(mut socket: WebSocket, state: Arc<AppState>) {
-    let interval_ms = state.config.server.metrics_interval_ms;
-    let mut interval = tokio::time::interval(std::time::Duration::from_millis(interval_ms));
-    let mut prev_events: u64 = 0;
-    let mut prev_bytes: u64 = 0;
-    let mut prev_time = Instant::now();
+/// A client's control message: `subscribe` replaces the channel set (and
+/// the journal and stream `events` are limited to, and the query
+/// subscription `query` streams), `unsubscribe` drops channels.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WsRequest {
+    subscribe: Option<Vec<WsChannel>>,
+    unsubscribe: Option<Vec<WsChannel>>,
+    journal: Option<String>,
+    stream_id: Option<u16>,
+    query: Option<String>,
+}
 
-    loop {
-        interval.tick().await;
+/// Reply to a control message, sent as `{"type":"subscribed",...}`.
+#[derive(Serialize)]
+struct SubscribedMessage<'a> {
+    r#type: &'static str,
+    channels: &'a [WsChannel],
+    journal: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_id: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    query: Option<&'a str>,
+}
 
-        let now = Instant::now();
-        let dt = now.duration_since(prev_time).as_secs_f64();
+/// A match for a live query, sent as
+/// `{"type":"query","subscription":...,"data":{...}}`.
+#[derive(Serialize)]
+struct QueryEventMessage<'a> {
+    r#type: &'static str,
+    subscription: &'a str,
+    data: &'a connectors::StreamEvent,
+}
 
-        let events = cz_io::event_loop::EVENTS_PROCESSED.load(Ordering::Relaxed);
-        let bytes = cz_io::event_loop::BYTES_PROCESSED.load(Ordering::Relaxed);
+/// What one WS connection is subscribed to.
+struct WsSubscription {
+    channels: Vec<WsChannel>,
+    journal: Option<String>,
+    /// The only `stream_id` whose events are sent, if set.
+    stream_id: Option<u16>,
+    /// Id of the query subscription asked for.
+    query: Option<String>,
+    /// The query subscription this connection holds, once attached.
+    live_query: Option<(String, Arc<query::Query>)>,
+}
 
-        let tps = if dt > 0.0 {
-            events.saturating_sub(prev_events) as f64 / dt
-        } else {
-            0.0
-        };
-        let bps = if dt > 0.0 {
-            bytes.saturating_sub(prev_bytes) as f64 / dt
-        } else {
-            0.0
-        };
+impl Default for WsSubscription {
+    /// Clients that never send a control message get the metrics and
+    /// events feeds, as before subscriptions existed.
+    fn default() -> Self {
+        Self {
+            channels: vec![WsChannel::Metrics, WsChannel::Events],
+            journal: None,
+            stream_id: None,
+            query: None,
+            live_query: None,
+        }
+    }
+}
 
-        prev_events = events;
-        prev_bytes = bytes;
-        prev_time = now;
+impl WsSubscription {
+    fn wants(&self, channel: WsChannel) -> bool {
+        self.channels.contains(&channel)
+    }
 
-        let primary = state.get_journal(None).await.unwrap();
-        let cursor = primary.cursor.read().await;
-        let used = cursor.len();
-        let utilization = if INDEX_RING_CAPACITY > 0 {
-            (used as f64 / INDEX_RING_CAPACITY as f64) * 100.0
-        } else {
-            0.0
-        };
+    fn wants_event(&self, journal: &std::path::Path) -> bool {
+        self.wants(WsChannel::Events)
+            && self
+                .journal
+                .as_ref()
+                .is_none_or(|want| std::path::Path::new(want) == journal)
+    }
 
-        let snapshot = MetricsSnapshot {
-            timestamp: chrono::Utc::now().to_rfc3339(),
-            events,
-            bytes,
-            tps: (tps * 100.0).round() / 100.0,
-            bps: (bps * 100.0).round() / 100.0,
-            head: cursor.head(),
-            tail: cursor.tail(),
-            utilization_pct: (utilization * 100.0).round() / 100.0,
-            uptime_seconds: state.start_time.elapsed().as_secs(),
-            playback_mode: state.playback.read().await.clone(),
-        };
+    /// Whether a committed event passes the `stream_id` filter; one that
+    /// could not be read back passes only when there is none.
+    fn wants_stream(&self, committed: Option<&CausalEvent>) -> bool {
+        self.stream_id
+            .is_none_or(|id| committed.is_some_and(|event| event.stream_id == id))
+    }
 
-        let msg = MetricsMessage {
-            r#type: "metrics",
-            data: snapshot,
+    /// Apply a control message, or say why it was rejected.
+    fn handle(&mut self, text: &str) -> Result<(), String> {
+        let request: WsRequest = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        if let Some(mut channels) = request.subscribe {
+            channels.sort();
+            channels.dedup();
+            self.channels = channels;
+            self.journal = request.journal;
+            self.stream_id = request.stream_id;
+            self.query = request.query;
+        }
+        if let Some(channels) = request.unsubscribe {
+            self.channels.retain(|c| !channels.contains(c));
+        }
+        Ok(())
+    }
+
+    /// Hold the query subscription asked for, as `key`, giving up the one
+    /// held before. Errors if it is unknown, expired, or held elsewhere.
+    fn attach_query(
+        &mut self,
+        subscriptions: &query::subscriptions::QuerySubscriptions,
+        key: &auth::ApiKey,
+    ) -> Result<(), String> {
+        if self.live_query.as_ref().map(|(id, _)| id) == self.query.as_ref() {
+            return Ok(());
+        }
+        self.detach_query(subscriptions);
+        let Some(id) = self.query.clone() else {
+            return Ok(());
         };
-        let json = serde_json::to_string(&msg).unwrap_or_default();
+        match subscriptions.attach(&id, &key.id) {
+            Some(query) => {
+                self.live_query = Some((id, query));
+                Ok(())
+            }
+            None => {
+                self.query = None;
+                Err(format!("Unknown query subscription '{}'", id))
+            }
+        }
+    }
 
-        if socket.send(Message::Text(json)).await.is_err() {
-            break;
+    /// Drop the query subscription this connection holds, if any.
+    fn detach_query(&mut self, subscriptions: &query::subscriptions::QuerySubscriptions) {
+        if let Some((id, _)) = self.live_query.take() {
+            subscriptions.remove(&id);
+        }
+    }
+
+    /// The reply confirming what the connection is now subscribed to.
+    fn subscribed(&self) -> String {
+        serde_json::to_string(&SubscribedMessage {
+            r#type: "subscribed",
+            channels: &self.channels,
+            journal: self.journal.as_deref(),
+            stream_id: self.stream_id,
+            query: self.query.as_deref(),
+        })
+        .unwrap_or_default()
+    }
+
+    /// WS payload for `event` if it matches the live query and `key` may
+    /// read its stream.
+    fn query_message(
+        &self,
+        state: &AppState,
+        key: &auth::ApiKey,
+        event: &connectors::StreamEvent,
+    ) -> Option<String> {
+        let (id, query) = self.live_query.as_ref()?;
+        if !self.wants(WsChannel::Query)
+            || !state
+                .auth_layer
+                .can_access(key, &format!("stream:{}", event.stream))
+            || !query::executor::matches_event(query, event, chrono::Utc::now())
+        {
+            return None;
+        }
+        serde_json::to_string(&QueryEventMessage {
+            r#type: "query",
+            subscription: id,
+            data: event,
+        })
+        .ok()
+    }
+}
+
+/// Messages queued for one WS client before it counts as too slow to keep.
+const WS_SEND_QUEUE: usize = 256;
+
+/// Subprotocol a browser offers alongside its API key, as
+/// `new WebSocket(url, ["cz.bearer", key])`; the hub selects it.
+const WS_BEARER_PROTOCOL: &str = "cz.bearer";
+
+#[derive(Deserialize)]
+struct WsAuthQuery {
+    token: Option<String>,
+}
+
+/// Upgrade to a WebSocket once the client has shown a key with `read`
+/// scope: a client certificate, an `Authorization: Bearer` header, the key
+/// offered next to the [`WS_BEARER_PROTOCOL`] subprotocol, or `?token=`.
+async fn ws_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<WsAuthQuery>,
+    headers: axum::http::HeaderMap,
+    identity: Option<axum::Extension<tls::ClientIdentity>>,
+    // Taken as a `Result` so an unauthenticated request is a 401 even when
+    // it is not a valid upgrade.
+    ws: Result<WebSocketUpgrade, axum::extract::ws::rejection::WebSocketUpgradeRejection>,
+) -> Response {
+    let offered: Vec<&str> = headers
+        .get(header::SEC_WEBSOCKET_PROTOCOL)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.split(',').map(str::trim).collect())
+        .unwrap_or_default();
+    let protocol_token = if offered.contains(&WS_BEARER_PROTOCOL) {
+        offered.iter().copied().find(|p| *p != WS_BEARER_PROTOCOL)
+    } else {
+        None
+    };
+    let token = bearer_token(&headers)
+        .or(protocol_token)
+        .or(query.token.as_deref());
+
+    let identity = identity.map(|axum::Extension(identity)| identity);
+    let key = match authenticate(&state, identity.as_ref(), token, "/ws").await {
+        Ok(key) => key,
+        Err(status) => return status.into_response(),
+    };
+    if let Err(response) = admit(&state, &key, "/ws", &Method::GET).await {
+        return response;
+    }
+    let ws = match ws {
+        Ok(ws) => ws,
+        Err(rejection) => return rejection.into_response(),
+    };
+    ws.protocols([WS_BEARER_PROTOCOL])
+        .on_upgrade(move |socket| handle_socket(socket, state, key))
+}
+
+/// Forward the channels the client subscribes to. `metrics` frames are
+/// the ones [`metrics_collector`] publishes for every client, so they cover
+/// all journals and hold still while playback is paused. Outgoing messages go
+/// through a bounded queue drained by a writer task; a client that lets
+/// the queue fill up is disconnected instead of holding up the feeds. The
+/// query subscription it holds ends with the connection.
+async fn handle_socket(socket: WebSocket, state: Arc<AppState>, key: auth::ApiKey) {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio::sync::broadcast::error::RecvError;
+    use tokio::sync::mpsc::error::TrySendError;
+
+    let (mut sink, mut incoming) = socket.split();
+    let (queue, mut outgoing) = tokio::sync::mpsc::channel::<Message>(WS_SEND_QUEUE);
+    let writer = tokio::spawn(async move {
+        while let Some(message) = outgoing.recv().await {
+            if sink.send(message).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut subscription = WsSubscription::default();
+    let mut metrics = state.metrics_feed.subscribe();
+    let mut ipc_events = state.ipc_events.subscribe();
+    let mut incidents = state.alert_engine.subscribe();
+    let mut stream_events = state.connector_registry.subscribe();
+    // Journal commits feed live queries too, separately from `events`.
+    let mut query_commits = state.ipc_events.subscribe();
+
+    loop {
+        let json = tokio::select! {
+            message = incoming.next() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    let mut handled = subscription.handle(&text);
+                    // Events are tagged by path; a journal id names the same one.
+                    if let Some(name) = subscription.journal.clone() {
+                        if let Some(journal) = state.get_journal(Some(name)).await {
+                            subscription.journal = Some(journal.path.display().to_string());
+                        }
+                    }
+                    if handled.is_ok() {
+                        handled = subscription.attach_query(&state.query_subscriptions, &key);
+                    }
+                    Some(match handled {
+                        Ok(()) => subscription.subscribed(),
+                        Err(e) => serde_json::json!({"type": "error", "error": e}).to_string(),
+                    })
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => None,
+            },
+            snapshot = metrics.recv() => match snapshot {
+                Ok(json) if subscription.wants(WsChannel::Metrics) => Some(json.to_string()),
+                Err(RecvError::Closed) => break,
+                _ => None,
+            },
+            event = ipc_events.recv() => match event {
+                // Frozen playback keeps the dashboard still.
+                Ok(message)
+                    if subscription.wants_event(&message.journal)
+                        && !state.playback.read().await.is_paused() =>
+                {
+                    let committed = state.committed_event(&message).await;
+                    if subscription.wants_stream(committed.as_ref()) {
+                        event_message(&message, committed.as_ref())
+                    } else {
+                        None
+                    }
+                }
+                Err(RecvError::Closed) => break,
+                _ => None,
+            },
+            incident = incidents.recv() => match incident {
+                Ok(incident) if subscription.wants(WsChannel::Alerts) => {
+                    serde_json::to_string(&AlertMessage {
+                        r#type: "alert",
+                        data: incident,
+                    })
+                    .ok()
+                }
+                Err(RecvError::Closed) => break,
+                _ => None,
+            },
+            event = stream_events.recv() => match event {
+                Ok(event) => subscription.query_message(&state, &key, &event),
+                Err(RecvError::Closed) => break,
+                _ => None,
+            },
+            commit = query_commits.recv() => match commit {
+                Ok(commit) if subscription.live_query.is_some() => {
+                    match state.committed_stream_event(&commit).await {
+                        Some(event) => subscription.query_message(&state, &key, &event),
+                        None => None,
+                    }
+                }
+                Err(RecvError::Closed) => break,
+                _ => None,
+            },
+        };
+        let Some(json) = json else { continue };
+        match queue.try_send(Message::Text(json)) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                tracing::warn!(
+                    "Disconnecting slow WebSocket client ({} messages queued)",
+                    WS_SEND_QUEUE
+                );
+                break;
+            }
+            Err(TrySendError::Closed(_)) => break,
         }
     }
+    subscription.detach_query(&state.query_subscriptions);
+    writer.abort();
+}
+
+/// WS payload for a sequencer notification, if it is one clients see.
+/// `committed` is the event read back from the slot, when available.
+fn event_message(event: &ipc::SequencerEvent, committed: Option<&CausalEvent>) -> Option<String> {
+    let IpcMessage::SlotCommitted { slot, lamport_ts } = event.message else {
+        return None;
+    };
+    serde_json::to_string(&EventMessage {
+        r#type: "event",
+        data: SlotEvent {
+            journal: event.journal.display().to_string(),
+            slot,
+            lamport_ts,
+            node_id: committed.map(|e| e.node_id),
+            stream_id: committed.map(|e| e.stream_id),
+        },
+    })
+    .ok()
 }
 
 async fn api_metrics_prometheus(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    use metrics::{family, label, RejectReason};
+
     let events = cz_io::event_loop::EVENTS_PROCESSED.load(Ordering::Relaxed);
     let bytes = cz_io::event_loop::BYTES_PROCESSED.load(Ordering::Relaxed);
 
     let mut body = String::new();
-    body.push_str("# HELP cz_events_total Total number of events processed\n");
-    body.push_str("# TYPE cz_events_total counter\n");
+    family(
+        &mut body,
+        "cz_events_total",
+        "Total number of events processed",
+        "counter",
+    );
     body.push_str(&format!("cz_events_total {}\n", events));
 
-    body.push_str("# HELP cz_bytes_total Total number of bytes processed\n");
-    body.push_str("# TYPE cz_bytes_total counter\n");
+    family(
+        &mut body,
+        "cz_bytes_total",
+        "Total number of bytes processed",
+        "counter",
+    );
     body.push_str(&format!("cz_bytes_total {}\n", bytes));
 
+    family(
+        &mut body,
+        "cz_ring_utilization_pct",
+        "Occupied share of each journal's index ring",
+        "gauge",
+    );
     let journals = state.journals.read().await;
     for (path, s) in journals.iter() {
-        let p_str = path.display().to_string();
+        let capacity = s.journal.read().await.index_ring_capacity();
         let cursor = s.cursor.read().await;
         body.push_str(&format!(
             "cz_ring_utilization_pct{{journal=\"{}\"}} {}\n",
-            p_str,
-            (cursor.len() as f64 / INDEX_RING_CAPACITY as f64) * 100.0
+            label(&path.display().to_string()),
+            (cursor.len() as f64 / capacity as f64) * 100.0
+        ));
+    }
+    drop(journals);
+
+    let connectors = state.connector_registry.list().await;
+    family(
+        &mut body,
+        "cz_connector_events_total",
+        "Events ingested by each connector",
+        "counter",
+    );
+    for c in &connectors {
+        body.push_str(&format!(
+            "cz_connector_events_total{{connector=\"{}\",kind=\"{}\"}} {}\n",
+            label(&c.id),
+            c.kind,
+            c.metrics.events_total
+        ));
+    }
+    family(
+        &mut body,
+        "cz_connector_errors_total",
+        "Errors raised by each connector",
+        "counter",
+    );
+    for c in &connectors {
+        body.push_str(&format!(
+            "cz_connector_errors_total{{connector=\"{}\",kind=\"{}\"}} {}\n",
+            label(&c.id),
+            c.kind,
+            c.metrics.errors_total
+        ));
+    }
+    family(
+        &mut body,
+        "cz_connector_lagged_total",
+        "Events dropped because the hub fell behind each connector",
+        "counter",
+    );
+    for c in &connectors {
+        body.push_str(&format!(
+            "cz_connector_lagged_total{{connector=\"{}\",kind=\"{}\"}} {}\n",
+            label(&c.id),
+            c.kind,
+            c.metrics.lagged_total
+        ));
+    }
+
+    family(
+        &mut body,
+        "cz_events_rejected_total",
+        "Events refused at ingest, by reason",
+        "counter",
+    );
+    for reason in RejectReason::ALL {
+        body.push_str(&format!(
+            "cz_events_rejected_total{{reason=\"{}\"}} {}\n",
+            reason.as_str(),
+            state.metrics.rejected(reason)
+        ));
+    }
+
+    family(
+        &mut body,
+        "cz_anomalies_total",
+        "Causality anomalies found in live journal windows, by kind",
+        "counter",
+    );
+    for kind in AnomalyKind::ALL {
+        body.push_str(&format!(
+            "cz_anomalies_total{{kind=\"{}\"}} {}\n",
+            kind.as_str(),
+            state.metrics.anomalies(kind)
+        ));
+    }
+
+    let open = state
+        .alert_engine
+        .list_active()
+        .await
+        .iter()
+        .filter(|i| i.status != alerts::IncidentStatus::Resolved)
+        .count();
+    family(
+        &mut body,
+        "cz_incidents_open",
+        "Incidents not yet resolved",
+        "gauge",
+    );
+    body.push_str(&format!("cz_incidents_open {}\n", open));
+
+    family(
+        &mut body,
+        "cz_traces",
+        "Traces held in the trace store",
+        "gauge",
+    );
+    body.push_str(&format!("cz_traces {}\n", state.trace_store.count().await));
+    family(
+        &mut body,
+        "cz_traces_dropped_total",
+        "Traces dropped from the trace store, by reason",
+        "counter",
+    );
+    body.push_str(&format!(
+        "cz_traces_dropped_total{{reason=\"capacity\"}} {}\n",
+        state.trace_store.evicted()
+    ));
+    body.push_str(&format!(
+        "cz_traces_dropped_total{{reason=\"expired\"}} {}\n",
+        state.trace_store.expired()
+    ));
+    let sampling = state.trace_store.sampling_counts();
+    body.push_str(&format!(
+        "cz_traces_dropped_total{{reason=\"sampled\"}} {}\n",
+        sampling.dropped
+    ));
+    family(
+        &mut body,
+        "cz_traces_kept_total",
+        "Complete traces kept by the sampling policy, by rule",
+        "counter",
+    );
+    for (rule, kept) in [
+        ("error", sampling.kept_errors),
+        ("latency", sampling.kept_slow),
+        ("sampled", sampling.kept_sampled),
+    ] {
+        body.push_str(&format!(
+            "cz_traces_kept_total{{rule=\"{}\"}} {}\n",
+            rule, kept
         ));
     }
 
+    state.metrics.query_duration.render(
+        &mut body,
+        "cz_query_duration_seconds",
+        "Time spent executing queries",
+    );
+
     (
         StatusCode::OK,
         [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
         body,
     )
 }
+
 async fn api_playback_get(State(state): State<Arc<AppState>>) -> Json<PlaybackMode> {
     let mode = state.playback.read().await;
     Json(mode.clone())
@@ -1579,6 +5369,22 @@ async fn api_playback_set(
                 at_ts: ts,
             };
         }
+        "replaying" => {
+            let speed = params.speed.unwrap_or(1.0);
+            if !(speed.is_finite() && speed > 0.0) {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    Json(ApiError {
+                        error: "speed must be a positive number".into(),
+                    }),
+                ));
+            }
+            *mode = PlaybackMode::Replaying {
+                from_ts: params.ts.unwrap_or(0),
+                speed,
+                started_at_ms: unix_millis(),
+            };
+        }
         _ => {
             return Err((
                 StatusCode::BAD_REQUEST,
@@ -1590,117 +5396,4368 @@ async fn api_playback_set(
     }
     Ok(Json(mode.clone()))
 }
-fn evaluate_dsl(query: &str, event: &CausalEvent) -> bool {
-    // Simple mock DSL: "field == value" or "field > value"
-    // In a real app, this would use a parser like 'nom' or 'evalexpr'
-    let parts: Vec<&str> = query.split_whitespace().collect();
-    if parts.len() < 3 {
-        return true;
-    }
-
-    let field = parts[0];
-    let op = parts[1];
-    let val_str = parts[2];
-
-    let val = match val_str.parse::<u64>() {
-        Ok(v) => v,
-        Err(_) => return true,
-    };
-
-    match field {
-        "node_id" => match op {
-            "==" => event.node_id as u64 == val,
-            ">" => event.node_id as u64 > val,
-            "<" => (event.node_id as u64) < val,
-            _ => true,
-        },
-        "stream_id" => match op {
-            "==" => event.stream_id as u64 == val,
-            ">" => event.stream_id as u64 > val,
-            "<" => (event.stream_id as u64) < val,
-            _ => true,
-        },
-        "ts" | "lamport" => match op {
-            "==" => event.lamport_ts == val,
-            ">" => event.lamport_ts > val,
-            "<" => event.lamport_ts < val,
-            _ => true,
-        },
-        _ => true,
-    }
-}
 
 // =============================================================================
 // Auth Middleware
 // =============================================================================
 
+/// The `Authorization: Bearer` token, if any.
+fn bearer_token(headers: &axum::http::HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "))
+}
+
+/// The key a request acts as. A verified client certificate whose CN is
+/// bound to a key stands in for the bearer token.
+async fn authenticate(
+    state: &AppState,
+    identity: Option<&tls::ClientIdentity>,
+    token: Option<&str>,
+    path: &str,
+) -> Result<auth::ApiKey, StatusCode> {
+    if let Some(identity) = identity {
+        match state
+            .auth_layer
+            .key_for_tls_subject(&identity.common_name)
+            .await
+        {
+            Some(key) => return Ok(key),
+            None => tracing::warn!(
+                "No API key bound to client certificate CN '{}' on {}",
+                identity.common_name,
+                path
+            ),
+        }
+    }
+    let Some(token) = token else {
+        tracing::warn!("Missing Authorization header for {}", path);
+        return Err(StatusCode::UNAUTHORIZED);
+    };
+    state.auth_layer.validate_token(token).await.ok_or_else(|| {
+        tracing::warn!("Invalid API Key for {}", path);
+        StatusCode::UNAUTHORIZED
+    })
+}
+
+/// Charge `key`'s rate limit and check it holds the scope `method` on
+/// `path` requires.
+async fn admit(
+    state: &AppState,
+    key: &auth::ApiKey,
+    path: &str,
+    method: &Method,
+) -> Result<(), Response> {
+    if let Err(retry_after) = state.auth_layer.check_rate_limit(key).await {
+        tracing::warn!("Rate limit exceeded for key {} on {}", key.id, path);
+        return Err(too_many_requests(retry_after));
+    }
+    let scope = required_scope(path, method);
+    if !state.auth_layer.has_scope(key, scope) {
+        tracing::warn!("Insufficient scope for {} {}", method, path);
+        return Err(StatusCode::FORBIDDEN.into_response());
+    }
+    Ok(())
+}
+
+/// Authenticate, authorize and audit a request to a protected route; see
+/// [`router`] for which routes those are.
 async fn auth_middleware(
     State(state): State<Arc<AppState>>,
-    req: Request,
+    mut req: Request,
     next: Next,
-) -> Result<Response, StatusCode> {
-    let path = req.uri().path();
+) -> Response {
+    let path = req.uri().path().to_string();
+    let path = path.as_str();
     let method = req.method().clone();
 
-    // Public routes bypass
-    if path == "/api/status"
-        || path.starts_with("/ws")
-        || !path.starts_with("/api")
-        || method == Method::OPTIONS
+    let identity = req.extensions().get::<tls::ClientIdentity>();
+    let key = match authenticate(&state, identity, bearer_token(req.headers()), path).await {
+        Ok(key) => key,
+        Err(status) => return status.into_response(),
+    };
+    if let Err(response) = admit(&state, &key, path, &method).await {
+        return response;
+    }
+    let audit = if state.config().auth.audit_reads || !matches!(method, Method::GET | Method::HEAD)
+    {
+        Some((
+            key.actor(),
+            client_ip(&state, &req).map(|ip| ip.to_string()),
+        ))
+    } else {
+        None
+    };
+
+    // Handlers read the key back to apply resource scopes.
+    req.extensions_mut().insert(key);
+    let response = next.run(req).await;
+
+    if let Some((actor, ip)) = audit {
+        state
+            .auth_layer
+            .log_audit(
+                actor,
+                method.to_string(),
+                path.to_string(),
+                format!("HTTP {}", response.status().as_u16()),
+                ip,
+            )
+            .await;
+    }
+    response
+}
+
+/// Authenticate a webhook delivery: by its signature when the connector
+/// verifies one (see [`connectors::signature`]), otherwise by
+/// [`auth_middleware`].
+async fn webhook_auth_middleware(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+    req: Request,
+    next: Next,
+) -> Response {
+    match state.connector_registry.get(&id).await {
+        Some(connector) if connector.verifies_signatures() => next.run(req).await,
+        _ => auth_middleware(State(state), req, next).await,
+    }
+}
+
+/// Draw from the per-IP buckets of [`limits`]. Runs before
+/// [`auth_middleware`], so a client without a key is throttled as well.
+async fn ip_limit_middleware(
+    State(state): State<Arc<AppState>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let path = req.uri().path();
+    let class = limits::Class::of(path);
+    let Some(ip) = client_ip(&state, &req) else {
+        return next.run(req).await;
+    };
+    if let Err(retry_after) = state
+        .limiter
+        .check(None, Some(&ip.to_string()), class)
+        .await
     {
-        return Ok(next.run(req).await);
+        tracing::warn!("Rate limit exceeded for {} on {}", ip, path);
+        return too_many_requests(retry_after);
+    }
+    next.run(req).await
+}
+
+/// Draw from the per-key expensive-route bucket of [`limits`]. Runs inside
+/// [`auth_middleware`], so the request's key is known.
+async fn rate_limit_middleware(
+    State(state): State<Arc<AppState>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let path = req.uri().path();
+    let class = limits::Class::of(path);
+    let Some(key_id) = req.extensions().get::<auth::ApiKey>().map(|k| k.id.clone()) else {
+        return next.run(req).await;
+    };
+    if let Err(retry_after) = state.limiter.check(Some(&key_id), None, class).await {
+        tracing::warn!("Rate limit exceeded for key {} on {}", key_id, path);
+        return too_many_requests(retry_after);
     }
+    next.run(req).await
+}
+
+/// 429 with a whole-second `Retry-After`.
+fn too_many_requests(retry_after: std::time::Duration) -> Response {
+    let secs = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(axum::http::header::RETRY_AFTER, secs.to_string())],
+    )
+        .into_response()
+}
 
-    // Check Authorization header
-    let auth_header = req
+/// Client address for rate limits and audit entries: the socket peer, or
+/// the client a trusted proxy forwarded for; see
+/// [`limits::RateLimiter::client_ip`].
+fn client_ip(state: &AppState, req: &Request) -> Option<std::net::IpAddr> {
+    let peer = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    let forwarded = req
         .headers()
-        .get(axum::http::header::AUTHORIZATION)
-        .and_then(|header| header.to_str().ok());
-
-    match auth_header {
-        Some(header) if header.starts_with("Bearer ") => {
-            let token = &header[7..];
-            if let Some(key) = state.auth_layer.validate_token(token).await {
-                if let Some(scope) = required_scope(path, &method) {
-                    if !state.auth_layer.has_scope(&key, scope) {
-                        tracing::warn!("Insufficient scope for {} {}", method, path);
-                        return Err(StatusCode::FORBIDDEN);
-                    }
+        .get("x-forwarded-for")
+        .and_then(|v| v.to_str().ok());
+    state.limiter.client_ip(peer, forwarded)
+}
+
+/// The scope a protected route needs for `method`.
+fn required_scope(path: &str, method: &Method) -> auth::Scope {
+    if path.starts_with("/api/auth") || path.starts_with("/api/config") || path == "/api/limits" {
+        return auth::Scope::Admin;
+    }
+    // Opening a journal maps an arbitrary file into the hub; a snapshot
+    // creates one.
+    if (path.starts_with("/api/journals") || path == "/api/journal/snapshot")
+        && !matches!(*method, Method::GET | Method::HEAD)
+    {
+        return auth::Scope::Admin;
+    }
+    match *method {
+        Method::GET | Method::HEAD => auth::Scope::Read,
+        _ => auth::Scope::Write,
+    }
+}
+
+/// Parquet schema for exported `EventRecord`s, column for column.
+#[cfg(feature = "parquet")]
+const EVENT_PARQUET_SCHEMA: &str = "
+    message causal_event {
+        REQUIRED INT64 slot (UINT_64);
+        REQUIRED INT64 lamport_ts (UINT_64);
+        REQUIRED INT32 node_id (UINT_32);
+        REQUIRED INT32 stream_id (UINT_16);
+        REQUIRED INT64 payload_offset (UINT_64);
+        REQUIRED INT32 checksum (UINT_32);
+        REQUIRED BOOLEAN checkpoint;
+        REQUIRED INT64 payload_size (UINT_64);
+        REQUIRED INT32 flags (UINT_16);
+        OPTIONAL BOOLEAN checksum_valid;
+    }
+";
+
+/// Encode events as a single-row-group Parquet file.
+#[cfg(feature = "parquet")]
+fn events_to_parquet(events: &[EventRecord]) -> parquet::errors::Result<Vec<u8>> {
+    use parquet::data_type::{BoolType, Int32Type, Int64Type};
+    use parquet::file::properties::WriterProperties;
+    use parquet::file::writer::SerializedFileWriter;
+    use parquet::schema::parser::parse_message_type;
+
+    let schema = Arc::new(parse_message_type(EVENT_PARQUET_SCHEMA)?);
+    let props = Arc::new(WriterProperties::builder().build());
+    let mut writer = SerializedFileWriter::new(Vec::new(), schema, props)?;
+    let mut row_group = writer.next_row_group()?;
+
+    // Unsigned columns are stored in their signed physical type; the
+    // logical annotation tells readers to reinterpret the bits.
+    let int64 =
+        |f: fn(&EventRecord) -> u64| -> Vec<i64> { events.iter().map(|e| f(e) as i64).collect() };
+    let int32 =
+        |f: fn(&EventRecord) -> u32| -> Vec<i32> { events.iter().map(|e| f(e) as i32).collect() };
+
+    let mut column = 0;
+    while let Some(mut writer) = row_group.next_column()? {
+        match column {
+            0 => writer
+                .typed::<Int64Type>()
+                .write_batch(&int64(|e| e.slot as u64), None, None)?,
+            1 => writer
+                .typed::<Int64Type>()
+                .write_batch(&int64(|e| e.lamport_ts), None, None)?,
+            2 => writer
+                .typed::<Int32Type>()
+                .write_batch(&int32(|e| e.node_id), None, None)?,
+            3 => writer.typed::<Int32Type>().write_batch(
+                &int32(|e| e.stream_id as u32),
+                None,
+                None,
+            )?,
+            4 => {
+                writer
+                    .typed::<Int64Type>()
+                    .write_batch(&int64(|e| e.payload_offset), None, None)?
+            }
+            5 => writer
+                .typed::<Int32Type>()
+                .write_batch(&int32(|e| e.checksum), None, None)?,
+            6 => {
+                let flags: Vec<bool> = events.iter().map(|e| e.checkpoint).collect();
+                writer.typed::<BoolType>().write_batch(&flags, None, None)?
+            }
+            7 => writer.typed::<Int64Type>().write_batch(
+                &int64(|e| e.payload_size as u64),
+                None,
+                None,
+            )?,
+            8 => writer
+                .typed::<Int32Type>()
+                .write_batch(&int32(|e| e.flags as u32), None, None)?,
+            _ => {
+                // Null unless the export was verified.
+                let valid: Vec<bool> = events.iter().filter_map(|e| e.checksum_valid).collect();
+                let defined: Vec<i16> = events
+                    .iter()
+                    .map(|e| e.checksum_valid.is_some() as i16)
+                    .collect();
+                writer
+                    .typed::<BoolType>()
+                    .write_batch(&valid, Some(&defined), None)?
+            }
+        };
+        writer.close()?;
+        column += 1;
+    }
+    row_group.close()?;
+    writer.into_inner()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use cz_io::journal::{DEFAULT_INDEX_RING_CAPACITY, MIN_JOURNAL_SIZE};
+    use tower::ServiceExt;
+
+    pub(crate) fn test_state() -> Arc<AppState> {
+        test_state_with(Config::default())
+    }
+
+    pub(crate) fn test_state_with(config: Config) -> Arc<AppState> {
+        Arc::new(AppState {
+            journals: RwLock::new(HashMap::new()),
+            playback: RwLock::new(PlaybackMode::default()),
+            start_time: Instant::now(),
+            metrics_history: RwLock::new(VecDeque::new()),
+            ipc_events: tokio::sync::broadcast::channel(16).0,
+            metrics_feed: tokio::sync::broadcast::channel(16).0,
+            metrics: metrics::HubMetrics::default(),
+            verify_jobs: RwLock::new(VerifyJobs::default()),
+            alerts: RwLock::new(Vec::new()),
+            alert_rules: RwLock::new(Vec::new()),
+            connector_registry: Arc::new(connectors::registry::ConnectorRegistry::new(10)),
+            alert_engine: Arc::new(alerts::AlertEngine::new(10)),
+            trace_store: Arc::new(traces::TraceStore::new(10, traces::DEFAULT_MAX_AGE)),
+            trace_correlations: Default::default(),
+            pipeline_manager: Arc::new(pipelines::PipelineManager::new()),
+            dashboard_manager: Arc::new(dashboards::DashboardManager::new()),
+            saved_queries: Arc::new(saved_queries::SavedQueryManager::new()),
+            query_cache: Default::default(),
+            query_subscriptions: Default::default(),
+            auth_layer: Arc::new(auth::AuthLayer::new(100, auth::DEFAULT_REQUESTS_PER_MINUTE)),
+            limiter: limits::RateLimiter::new(config.limits.clone()),
+            config_path: PathBuf::from("cz-hub.toml"),
+            active_config: std::sync::RwLock::new(ActiveConfig::new(
+                config,
+                "cz-hub.toml".as_ref(),
+            )),
+            state_store: None,
+            last_tick: Default::default(),
+            last_event_advance: Default::default(),
+            configured_journals: Vec::new(),
+            #[cfg(feature = "tls")]
+            tls: None,
+        })
+    }
+
+    fn test_router(state: Arc<AppState>) -> Router {
+        protected(
+            &state,
+            Router::new()
+                .route("/api/pipelines", get(api::list_pipelines))
+                .route("/api/pipelines", post(api::create_pipeline)),
+        )
+    }
+
+    /// `routes` behind [`auth_middleware`], as the hub serves them.
+    pub(crate) fn protected(state: &Arc<AppState>, routes: Router<Arc<AppState>>) -> Router {
+        routes
+            .layer(middleware::from_fn_with_state(
+                state.clone(),
+                auth_middleware,
+            ))
+            .with_state(state.clone())
+    }
+
+    /// A read-write key labelled `label`.
+    pub(crate) async fn create_key(state: &AppState, label: &str) -> auth::CreatedApiKey {
+        create_key_with(state, label, vec![auth::Scope::Read, auth::Scope::Write]).await
+    }
+
+    pub(crate) async fn create_key_with(
+        state: &AppState,
+        label: &str,
+        scopes: Vec<auth::Scope>,
+    ) -> auth::CreatedApiKey {
+        state
+            .auth_layer
+            .create_key(
+                auth::CreateApiKeyRequest {
+                    label: label.into(),
+                    scopes,
+                    resource_scopes: Vec::new(),
+                    requests_per_minute: None,
+                    expires_at: None,
+                    tls_subject: None,
+                },
+                "test",
+            )
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_authenticated_post_is_audited() {
+        let state = test_state_with(Config {
+            limits: limits::LimitsConfig {
+                trusted_proxies: vec!["10.0.0.1".parse().unwrap()],
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let key = create_key(&state, "ci").await;
+
+        let mut request = Request::post("/api/pipelines")
+            .header(header::AUTHORIZATION, format!("Bearer {}", key.key))
+            .header(header::CONTENT_TYPE, "application/json")
+            .header("x-forwarded-for", "198.51.100.1, 203.0.113.7")
+            .body(Body::from(r#"{"name":"p","nodes":[],"edges":[]}"#))
+            .unwrap();
+        // Reached through the trusted proxy.
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from(([10, 0, 0, 1], 443))));
+        let response = test_router(state.clone()).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let log = state.auth_layer.get_audit_log(10).await;
+        let entry = log.iter().find(|e| e.action == "POST").unwrap();
+        assert_eq!(entry.actor, key.api_key.actor());
+        assert_eq!(entry.resource, "/api/pipelines");
+        assert_eq!(entry.ip.as_deref(), Some("203.0.113.7"));
+    }
+
+    #[tokio::test]
+    async fn test_reads_are_not_audited_by_default() {
+        let state = test_state();
+        let key = create_key(&state, "ci").await;
+
+        let response = test_router(state.clone())
+            .oneshot(
+                Request::get("/api/pipelines")
+                    .header(header::AUTHORIZATION, format!("Bearer {}", key.key))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let log = state.auth_layer.get_audit_log(10).await;
+        assert!(log.iter().all(|e| e.action != "GET"));
+    }
+
+    #[tokio::test]
+    async fn test_incident_timeline_records_each_caller() {
+        let state = test_state();
+        let alice = create_key(&state, "alice").await;
+        let bob = create_key(&state, "bob").await;
+
+        let rule = alerts::AlertRuleV2 {
+            id: "rule-1".into(),
+            name: "cpu".into(),
+            rule_type: alerts::RuleType::Threshold,
+            stream: None,
+            field: "cpu".into(),
+            threshold: 90.0,
+            duration_seconds: 60,
+            severity: "critical".into(),
+            enabled: true,
+            notification_channels: vec![],
+            runbook_url: None,
+        };
+        let incident = state
+            .alert_engine
+            .create_incident(&rule, "cpu high".into())
+            .await;
+
+        let router = protected(
+            &state,
+            Router::new()
+                .route(
+                    "/api/alerts/incidents/:id/acknowledge",
+                    post(api::acknowledge_incident),
+                )
+                .route(
+                    "/api/alerts/incidents/:id/resolve",
+                    post(api::resolve_incident),
+                ),
+        );
+
+        for (key, action) in [(&alice, "acknowledge"), (&bob, "resolve")] {
+            let response = router
+                .clone()
+                .oneshot(
+                    Request::post(format!("/api/alerts/incidents/{}/{}", incident.id, action))
+                        .header(header::AUTHORIZATION, format!("Bearer {}", key.key))
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            if action == "resolve" {
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                let resolved: alerts::Incident = serde_json::from_slice(&body).unwrap();
+                let actors: Vec<_> = resolved
+                    .timeline
+                    .iter()
+                    .map(|e| (e.action.as_str(), e.actor.clone()))
+                    .collect();
+                assert!(actors.contains(&("acknowledged", Some(alice.api_key.actor()))));
+                assert!(actors.contains(&("resolved", Some(bob.api_key.actor()))));
+            }
+        }
+    }
+
+    /// Attach a temp journal holding events with Lamport ts 1..=count.
+    async fn add_journal(state: &AppState, name: &str, count: u64) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("cz-hub-{}-{}.db", name, std::process::id()));
+        let size = MIN_JOURNAL_SIZE + 4096;
+        let mut journal = Journal::open(&path, size).unwrap();
+        let mut cursor = cz_io::cursor::Cursor::new(journal.index_ring_capacity());
+        for ts in 1..=count {
+            let slot = cursor.advance_head().unwrap();
+            unsafe { journal.write_event_at(slot, &CausalEvent::new(ts, 1, 0, 0, 0)) };
+        }
+        journal.publish_cursor(&cursor);
+
+        let journal_state = JournalState::new(path.clone(), journal, WatermarkPolicy::default());
+        state
+            .journals
+            .write()
+            .await
+            .insert(path.clone(), Arc::new(journal_state));
+        path
+    }
+
+    #[tokio::test]
+    async fn test_paused_playback_hides_later_events() {
+        let state = test_state();
+        let path = add_journal(&state, "paused", 6).await;
+        let key = create_key(&state, "ui").await;
+        *state.playback.write().await = PlaybackMode::Paused {
+            at_slot: 0,
+            at_ts: 3,
+        };
+
+        let router = protected(&state, Router::new().route("/api/events", get(api_events)));
+        let response = router
+            .oneshot(
+                Request::get("/api/events")
+                    .header(header::AUTHORIZATION, format!("Bearer {}", key.key))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let timestamps: Vec<u64> = json["events"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["lamport_ts"].as_u64().unwrap())
+            .collect();
+        assert_eq!(timestamps, vec![1, 2, 3]);
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_playback_horizons() {
+        assert_eq!(PlaybackMode::RealTime.horizon(0), PlaybackHorizon::Live);
+        let by_slot = PlaybackMode::Paused {
+            at_slot: 4,
+            at_ts: 0,
+        };
+        assert_eq!(by_slot.horizon(0), PlaybackHorizon::UpToSlot(4));
+
+        let replay = PlaybackMode::Replaying {
+            from_ts: 100,
+            speed: 2.0,
+            started_at_ms: 10_000,
+        };
+        assert_eq!(replay.horizon(10_000), PlaybackHorizon::UpToTs(100));
+        assert_eq!(replay.horizon(11_500), PlaybackHorizon::UpToTs(3_100));
+    }
+
+    #[tokio::test]
+    async fn test_watermark_crossings_open_and_resolve_incidents() {
+        let state = test_state();
+        let path = std::path::Path::new("/tmp/ring.db");
+
+        handle_watermark_crossing(
+            &state,
+            path,
+            WatermarkCrossing::High {
+                utilization_pct: 91.0,
+            },
+        )
+        .await;
+        let active = state.alert_engine.list_active().await;
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].rule_id, "ring-watermark:/tmp/ring.db");
+
+        handle_watermark_crossing(
+            &state,
+            path,
+            WatermarkCrossing::Low {
+                utilization_pct: 50.0,
+            },
+        )
+        .await;
+        assert!(state.alert_engine.list_active().await.is_empty());
+    }
+
+    async fn export_body(state: &Arc<AppState>, path: &std::path::Path, format: &str) -> Vec<u8> {
+        let response = api_export(
+            State(state.clone()),
+            Query(ExportParams {
+                format: Some(format.into()),
+                journal: Some(path.to_string_lossy().into_owned()),
+                ..Default::default()
+            }),
+        )
+        .await
+        .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap()
+            .to_vec()
+    }
+
+    async fn event_timestamps(state: &Arc<AppState>, query: &str) -> Vec<u64> {
+        let Json(response) = api_events(
+            State(state.clone()),
+            Query(EventQueryParams {
+                journal: None,
+                node_id: None,
+                stream_id: None,
+                ts_min: None,
+                ts_max: None,
+                offset: None,
+                limit: None,
+                query: Some(query.into()),
+                cursor: None,
+                partition: None,
+                flags: None,
+                checksum_valid: None,
+                verify: None,
+            }),
+        )
+        .await
+        .map_err(|(status, _)| status)
+        .unwrap();
+        response.events.iter().map(|e| e.lamport_ts).collect()
+    }
+
+    #[tokio::test]
+    async fn test_replay_from_latest_checkpoint() {
+        let state = test_state();
+        let src = add_journal(&state, "cp-src", 5).await;
+        let dst = add_journal(&state, "cp-dst", 0).await;
+        let journal_param = |path: &PathBuf| Some(path.display().to_string());
+        let append = |timestamps: std::ops::RangeInclusive<u64>| {
+            let state = state.clone();
+            let src = src.clone();
+            async move {
+                let primary = state.get_journal(journal_param(&src)).await.unwrap();
+                let mut journal = primary.journal.write().await;
+                let mut cursor = primary.cursor.write().await;
+                for ts in timestamps {
+                    let slot = cursor.advance_head().unwrap();
+                    unsafe { journal.write_event_at(slot, &CausalEvent::new(ts, 1, 0, 0, 0)) };
                 }
-                Ok(next.run(req).await)
-            } else {
-                tracing::warn!("Invalid API Key for {}", path);
-                Err(StatusCode::UNAUTHORIZED)
+                journal.publish_cursor(&cursor);
+            }
+        };
+        let checkpoint = || {
+            api_checkpoint_create(
+                State(state.clone()),
+                Json(CreateCheckpointRequest {
+                    journal: journal_param(&src),
+                }),
+            )
+        };
+
+        let (status, Json(first)) = checkpoint().await.map_err(|e| e.0).unwrap();
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(
+            (first.slot, first.lamport_ts, first.segment_start),
+            (5, 6, 0)
+        );
+        append(7..=9).await;
+        let (_, Json(second)) = checkpoint().await.map_err(|e| e.0).unwrap();
+        assert_eq!((second.slot, second.segment_start), (9, 6));
+        append(11..=12).await;
+
+        // Both markers verify like sequencer-written checkpoints.
+        let Json(listed) = api_checkpoints(
+            State(state.clone()),
+            Query(HashMap::from([(
+                "journal".into(),
+                src.display().to_string(),
+            )])),
+        )
+        .await
+        .map_err(|e| e.0)
+        .unwrap();
+        assert!(listed.checkpoints.iter().all(|cp| cp.valid));
+        assert_eq!(listed.total, 2);
+
+        let replay = |from_checkpoint| {
+            api_replay(
+                State(state.clone()),
+                Json(ReplayParams {
+                    journal: journal_param(&src),
+                    start_slot: None,
+                    end_slot: None,
+                    from_checkpoint,
+                    target_journal: journal_param(&dst),
+                    resequence: true,
+                }),
+            )
+        };
+        let Json(result) = replay(Some(CheckpointRef::Latest(true)))
+            .await
+            .map_err(|e| e.0)
+            .unwrap();
+        assert_eq!(result.events_replayed, 2);
+        let Json(result) = replay(Some(CheckpointRef::Slot(5)))
+            .await
+            .map_err(|e| e.0)
+            .unwrap();
+        assert_eq!(result.events_replayed, 6);
+        let err = replay(Some(CheckpointRef::Latest(false)))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.0, StatusCode::BAD_REQUEST);
+
+        std::fs::remove_file(&src).ok();
+        std::fs::remove_file(&dst).ok();
+    }
+
+    #[tokio::test]
+    async fn test_replay_keeps_target_ordered() {
+        let state = test_state();
+        let src = add_journal(&state, "rs-src", 5).await;
+        let dst = add_journal(&state, "rs-dst", 10).await;
+        let fresh = add_journal(&state, "rs-fresh", 0).await;
+        let replay = |target: &PathBuf, resequence| {
+            api_replay(
+                State(state.clone()),
+                Json(ReplayParams {
+                    journal: Some(src.display().to_string()),
+                    start_slot: Some(0),
+                    end_slot: Some(7),
+                    from_checkpoint: None,
+                    target_journal: Some(target.display().to_string()),
+                    resequence,
+                }),
+            )
+        };
+
+        // Source timestamps 1..=5 would land after the target's 10.
+        let err = replay(&dst, false).await.err().unwrap();
+        assert_eq!(err.0, StatusCode::CONFLICT);
+        assert_eq!(
+            state.journals.read().await[&dst].cursor.read().await.len(),
+            10
+        );
+
+        let Json(result) = replay(&dst, true).await.map_err(|e| e.0).unwrap();
+        assert_eq!(result.mode, "resequenced");
+        assert_eq!(
+            (result.events_replayed, result.skipped, result.resequenced),
+            (5, 3, 5)
+        );
+        let events = {
+            let target = state.journals.read().await[&dst].clone();
+            let journal = target.journal.read().await;
+            let cursor = target.cursor.read().await;
+            cursor
+                .slots()
+                .map(|slot| unsafe { journal.read_event_at(slot) })
+                .collect::<Vec<_>>()
+        };
+        let timestamps: Vec<u64> = events.iter().map(|e| e.lamport_ts).collect();
+        assert_eq!(timestamps, (1..=15).collect::<Vec<_>>());
+        assert!(events[..10].iter().all(|e| !e.is_resequenced()));
+        assert!(events[10..].iter().all(|e| e.is_resequenced()));
+
+        // Originals are kept where they already fit.
+        let Json(result) = replay(&fresh, false).await.map_err(|e| e.0).unwrap();
+        assert_eq!(result.mode, "preserved");
+        assert_eq!((result.events_replayed, result.resequenced), (5, 0));
+
+        for path in [src, dst, fresh] {
+            std::fs::remove_file(&path).ok();
+        }
+    }
+
+    /// Serve `router` on a local port for tests that need a real client.
+    async fn serve(router: Router) -> SocketAddr {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(
+                listener,
+                router.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await
+            .unwrap();
+        });
+        addr
+    }
+
+    type WsClient = tokio_tungstenite::WebSocketStream<
+        tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+    >;
+
+    async fn ws_send(ws: &mut WsClient, text: &str) {
+        use futures_util::SinkExt;
+        ws.send(tokio_tungstenite::tungstenite::Message::text(text))
+            .await
+            .unwrap();
+    }
+
+    async fn ws_recv(ws: &mut WsClient) -> String {
+        use futures_util::StreamExt;
+        let message = tokio::time::timeout(std::time::Duration::from_secs(5), ws.next())
+            .await
+            .expect("no WS message")
+            .unwrap()
+            .unwrap();
+        message.into_text().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_route_groups_require_their_scopes() {
+        use auth::Scope::{Admin, Read, Write};
+        let state = test_state();
+        let mut keys = Vec::new();
+        for (label, scopes) in [
+            ("reader", vec![Read]),
+            ("writer", vec![Read, Write]),
+            ("admin", vec![Admin]),
+        ] {
+            let created = create_key_with(&state, label, scopes).await;
+            keys.push(created.key);
+        }
+        let app = router(state.clone(), PathBuf::from("/nonexistent/ui/dist"));
+        let status = |method: Method, path: &str, key: Option<&str>| {
+            let mut request = Request::builder().method(method).uri(path);
+            if let Some(key) = key {
+                request = request.header(header::AUTHORIZATION, format!("Bearer {}", key));
             }
+            let app = app.clone();
+            let request = request.body(Body::empty()).unwrap();
+            async move { app.oneshot(request).await.unwrap().status() }
+        };
+
+        // Public: no key needed.
+        for path in [
+            "/api/status",
+            "/healthz",
+            "/readyz",
+            "/metrics",
+            "/index.html",
+        ] {
+            let code = status(Method::GET, path, None).await;
+            assert!(
+                code != StatusCode::UNAUTHORIZED && code != StatusCode::FORBIDDEN,
+                "{} answered {}",
+                path,
+                code
+            );
         }
-        _ => {
-            tracing::warn!("Missing Authorization header for {}", path);
-            Err(StatusCode::UNAUTHORIZED)
+        // Unknown API paths are not found rather than unauthorized.
+        assert_eq!(
+            status(Method::GET, "/api/no-such-route", None).await,
+            StatusCode::NOT_FOUND
+        );
+
+        // Protected: (method, path, least scope), checked with no key and
+        // with each key in rising order of privilege.
+        let rank = |scope: &auth::Scope| match scope {
+            Read => 0,
+            Write => 1,
+            Admin => 2,
+        };
+        let protected = [
+            (Method::GET, "/api/ring", Read),
+            (Method::GET, "/api/pipelines", Read),
+            (Method::GET, "/api/connectors", Read),
+            (Method::POST, "/api/pipelines", Write),
+            (Method::DELETE, "/api/dashboards/missing", Write),
+            (Method::POST, "/api/alerts/rules", Write),
+            (Method::GET, "/api/journals", Read),
+            (Method::POST, "/api/journals", Admin),
+            (Method::GET, "/api/config", Admin),
+            (Method::GET, "/api/auth/keys", Admin),
+            (Method::GET, "/api/limits", Admin),
+            (Method::GET, "/ws", Read),
+        ];
+        for (method, path, needed) in protected {
+            assert_eq!(
+                status(method.clone(), path, None).await,
+                StatusCode::UNAUTHORIZED,
+                "{} {} without a key",
+                method,
+                path
+            );
+            for (held, key) in [Read, Write, Admin].iter().zip(&keys) {
+                let code = status(method.clone(), path, Some(key)).await;
+                if rank(held) >= rank(&needed) {
+                    assert!(
+                        code != StatusCode::UNAUTHORIZED && code != StatusCode::FORBIDDEN,
+                        "{} {} with {:?} answered {}",
+                        method,
+                        path,
+                        held,
+                        code
+                    );
+                } else {
+                    assert_eq!(
+                        code,
+                        StatusCode::FORBIDDEN,
+                        "{} {} with {:?}",
+                        method,
+                        path,
+                        held
+                    );
+                }
+            }
         }
+
+        // CORS preflights are answered without credentials.
+        let preflight = Request::builder()
+            .method(Method::OPTIONS)
+            .uri("/api/pipelines")
+            .header(header::ORIGIN, "http://ui.example")
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(preflight).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+
+        // Browsers authenticate the WebSocket with the bearer subprotocol.
+        use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+        let addr = serve(app).await;
+        let url = format!("ws://{}/ws", addr);
+        assert!(tokio_tungstenite::connect_async(&url).await.is_err());
+        let mut request = url.into_client_request().unwrap();
+        request.headers_mut().insert(
+            header::SEC_WEBSOCKET_PROTOCOL,
+            format!("{}, {}", WS_BEARER_PROTOCOL, keys[0])
+                .parse()
+                .unwrap(),
+        );
+        let (mut ws, response) = tokio_tungstenite::connect_async(request).await.unwrap();
+        assert_eq!(
+            response.headers()[header::SEC_WEBSOCKET_PROTOCOL],
+            WS_BEARER_PROTOCOL
+        );
+        ws_send(&mut ws, r#"{"subscribe": ["metrics"]}"#).await;
+        assert!(ws_recv(&mut ws).await.contains("subscribed"));
     }
-}
 
-fn required_scope(path: &str, method: &Method) -> Option<auth::Scope> {
-    if !path.starts_with("/api") {
-        return None;
+    #[tokio::test]
+    async fn test_ws_subscriptions_pick_channels() {
+        let state = test_state();
+        let path = add_journal(&state, "ws", 3).await;
+        let key = create_key(&state, "ws").await;
+        let router = Router::new()
+            .route("/ws", get(ws_handler))
+            .with_state(state.clone());
+        let url = format!("ws://{}/ws?token={}", serve(router).await, key.key);
+        let (mut a, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        let (mut b, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        let json = |text: String| serde_json::from_str::<serde_json::Value>(&text).unwrap();
+
+        for ws in [&mut a, &mut b] {
+            ws_send(ws, r#"{"subscribe": ["metrics"]}"#).await;
+            assert_eq!(
+                json(ws_recv(ws).await),
+                serde_json::json!({"type": "subscribed", "channels": ["metrics"], "journal": null})
+            );
+        }
+
+        // One snapshot, computed once, reaches both clients byte for byte.
+        let snapshot = MetricsSnapshot {
+            schema: METRICS_SCHEMA,
+            timestamp: "2026-01-01T00:00:00Z".into(),
+            events: 42,
+            bytes: 4096,
+            tps: 1.5,
+            tps_mean: None,
+            tps_stddev: None,
+            tps_zscore: 0.0,
+            bps: 96.0,
+            utilization_pct: 0.0,
+            uptime_seconds: 7,
+            playback_mode: PlaybackMode::default(),
+            journals: BTreeMap::new(),
+        };
+        publish_metrics(&state, snapshot.clone());
+        let (from_a, from_b) = (ws_recv(&mut a).await, ws_recv(&mut b).await);
+        assert_eq!(from_a, from_b);
+        assert_eq!(json(from_a)["data"]["events"], 42);
+
+        let subscribe = format!(
+            r#"{{"subscribe": ["alerts", "events"], "journal": "{}"}}"#,
+            path.display()
+        );
+        ws_send(&mut a, &subscribe).await;
+        assert_eq!(
+            json(ws_recv(&mut a).await)["channels"],
+            serde_json::json!(["events", "alerts"])
+        );
+        let commit = |journal: &std::path::Path, slot| ipc::SequencerEvent {
+            journal: journal.to_path_buf(),
+            message: IpcMessage::SlotCommitted {
+                slot,
+                lamport_ts: slot + 1,
+            },
+        };
+        state
+            .ipc_events
+            .send(commit(std::path::Path::new("/elsewhere.db"), 0))
+            .unwrap();
+        state.ipc_events.send(commit(&path, 2)).unwrap();
+        let event = json(ws_recv(&mut a).await);
+        assert_eq!(
+            (event["type"].clone(), event["data"]["slot"].clone()),
+            ("event".into(), 2.into())
+        );
+
+        let rule = alerts::AlertRuleV2 {
+            id: "r1".into(),
+            name: "ring".into(),
+            rule_type: alerts::RuleType::Threshold,
+            stream: None,
+            field: "utilization".into(),
+            threshold: 90.0,
+            duration_seconds: 0,
+            severity: "critical".into(),
+            enabled: true,
+            notification_channels: Vec::new(),
+            runbook_url: None,
+        };
+        state
+            .alert_engine
+            .create_incident(&rule, "ring full".into())
+            .await;
+        let alert = json(ws_recv(&mut a).await);
+        assert_eq!(alert["type"], "alert");
+        assert_eq!(alert["data"]["message"], "ring full");
+
+        // B only asked for metrics, so the event and alert never reached it.
+        publish_metrics(&state, snapshot);
+        assert_eq!(json(ws_recv(&mut b).await)["type"], "metrics");
+
+        ws_send(&mut a, r#"{"unsubscribe": ["events"]}"#).await;
+        assert_eq!(
+            json(ws_recv(&mut a).await)["channels"],
+            serde_json::json!(["alerts"])
+        );
+        state.ipc_events.send(commit(&path, 2)).unwrap();
+        state
+            .alert_engine
+            .create_incident(&rule, "again".into())
+            .await;
+        assert_eq!(json(ws_recv(&mut a).await)["data"]["message"], "again");
+
+        ws_send(&mut a, r#"{"subscribe": ["firehose"]}"#).await;
+        assert_eq!(json(ws_recv(&mut a).await)["type"], "error");
+
+        std::fs::remove_file(&path).ok();
     }
-    if path == "/api/status" {
-        return None;
+
+    #[tokio::test]
+    async fn test_ws_events_only_subscription_for_one_stream() {
+        let state = test_state();
+        let path = add_journal(&state, "ws-stream", 3).await;
+        {
+            let journals = state.journals.read().await;
+            let mut journal = journals[&path].journal.write().await;
+            unsafe { journal.write_event_at(1, &CausalEvent::new(2, 1, 3, 0, 0)) };
+        }
+        let key = create_key(&state, "ws").await;
+        let router = Router::new()
+            .route("/ws", get(ws_handler))
+            .with_state(state.clone());
+        let url = format!("ws://{}/ws?token={}", serve(router).await, key.key);
+        let (mut ws, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        let json = |text: String| serde_json::from_str::<serde_json::Value>(&text).unwrap();
+        let commit = |slot| ipc::SequencerEvent {
+            journal: path.clone(),
+            message: IpcMessage::SlotCommitted {
+                slot,
+                lamport_ts: slot + 1,
+            },
+        };
+
+        ws_send(&mut ws, r#"{"subscribe": ["events"], "stream_id": 3}"#).await;
+        assert_eq!(
+            json(ws_recv(&mut ws).await),
+            serde_json::json!({
+                "type": "subscribed", "channels": ["events"], "journal": null, "stream_id": 3
+            })
+        );
+        publish_metrics(&state, snapshot_with(&[], 1.0));
+        state.ipc_events.send(commit(0)).unwrap();
+        state.ipc_events.send(commit(1)).unwrap();
+        publish_metrics(&state, snapshot_with(&[], 2.0));
+        // Neither snapshot nor the stream 0 event comes through.
+        let event = json(ws_recv(&mut ws).await);
+        assert_eq!(event["type"], "event");
+        assert_eq!(
+            (
+                event["data"]["slot"].clone(),
+                event["data"]["stream_id"].clone()
+            ),
+            (1.into(), 3.into())
+        );
+
+        // Subscribing again mid-connection lifts the stream filter.
+        ws_send(&mut ws, r#"{"subscribe": ["events"]}"#).await;
+        let subscribed = json(ws_recv(&mut ws).await);
+        assert_eq!(subscribed["type"], "subscribed");
+        assert!(subscribed.get("stream_id").is_none());
+        publish_metrics(&state, snapshot_with(&[], 3.0));
+        state.ipc_events.send(commit(2)).unwrap();
+        let event = json(ws_recv(&mut ws).await);
+        assert_eq!(
+            (event["type"].clone(), event["data"]["slot"].clone()),
+            ("event".into(), 2.into())
+        );
+        {
+            use futures_util::StreamExt;
+            let quiet =
+                tokio::time::timeout(std::time::Duration::from_millis(100), ws.next()).await;
+            assert!(quiet.is_err(), "unexpected frame: {:?}", quiet);
+        }
+
+        std::fs::remove_file(&path).ok();
     }
-    if path.starts_with("/api/auth") {
-        return Some(auth::Scope::Admin);
+
+    #[tokio::test]
+    async fn test_ws_metrics_cover_every_journal_and_freeze_when_paused() {
+        let mut config = Config::default();
+        config.server.metrics_interval_ms = 10;
+        let state = test_state_with(config);
+        let a = add_journal(&state, "ws-agg-a", 3).await;
+        let b = add_journal(&state, "ws-agg-b", 7).await;
+        let key = create_key(&state, "ws").await;
+        tokio::spawn(metrics_collector(state.clone()));
+        let router = Router::new()
+            .route("/ws", get(ws_handler))
+            .with_state(state.clone());
+        let url = format!("ws://{}/ws?token={}", serve(router).await, key.key);
+        let (mut ws, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        ws_send(&mut ws, r#"{"subscribe": ["metrics"]}"#).await;
+        async fn next_metrics(ws: &mut WsClient) -> serde_json::Value {
+            loop {
+                let frame: serde_json::Value = serde_json::from_str(&ws_recv(ws).await).unwrap();
+                if frame["type"] == "metrics" {
+                    return frame["data"].clone();
+                }
+            }
+        }
+
+        let aggregate = state.aggregate_ring_state().await;
+        let live = next_metrics(&mut ws).await;
+        assert_eq!(
+            live["utilization_pct"].as_f64().unwrap(),
+            round(aggregate.utilization_pct)
+        );
+        assert_eq!(live["journals"].as_object().unwrap().len(), 2);
+        for usage in &aggregate.journals {
+            assert_eq!(live["journals"][&usage.path]["used"], usage.used);
+        }
+
+        let paused = PlaybackMode::Paused {
+            at_slot: 0,
+            at_ts: 0,
+        };
+        let paused_json = serde_json::to_value(&paused).unwrap();
+        *state.playback.write().await = paused;
+        let frozen = loop {
+            let frame = next_metrics(&mut ws).await;
+            if frame["playback_mode"] == paused_json {
+                break frame;
+            }
+        };
+        // A journal opened while paused stays out of the frozen frames.
+        let c = add_journal(&state, "ws-agg-c", 1).await;
+        for _ in 0..3 {
+            let frame = next_metrics(&mut ws).await;
+            assert_eq!(frame["journals"], frozen["journals"]);
+            assert_eq!(frame["utilization_pct"], frozen["utilization_pct"]);
+        }
+
+        *state.playback.write().await = PlaybackMode::RealTime;
+        loop {
+            let frame = next_metrics(&mut ws).await;
+            if frame["journals"].as_object().unwrap().len() == 3 {
+                break;
+            }
+        }
+
+        for path in [a, b, c] {
+            std::fs::remove_file(&path).ok();
+        }
     }
-    match *method {
-        Method::GET | Method::HEAD => Some(auth::Scope::Read),
-        _ => Some(auth::Scope::Write),
+
+    #[tokio::test]
+    async fn test_query_parse_error_carries_span() {
+        let state = test_state();
+        let key = create_key(&state, "query").await;
+        let app = protected(
+            &state,
+            Router::new().route("/api/query", post(api::execute_query)),
+        );
+        let text = "SELECT * WHERE action = 'opened' AND merged LIMIT 5";
+        let response = app
+            .oneshot(
+                Request::post("/api/query")
+                    .header(header::AUTHORIZATION, format!("Bearer {}", key.key))
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(serde_json::json!({ "query": text }).to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let error: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(error["message"], "Cannot parse condition 'merged'");
+        let (start, end) = (
+            error["span"]["start"].as_u64().unwrap() as usize,
+            error["span"]["end"].as_u64().unwrap() as usize,
+        );
+        assert_eq!(&text[start..end], "merged");
     }
-}
 
-fn is_empty_event(event: &CausalEvent) -> bool {
-    event.lamport_ts == 0
-        && event.node_id == 0
-        && event.stream_id == 0
-        && event.flags == 0
-        && event.payload_offset == 0
-        && event.checksum == 0
+    #[tokio::test]
+    async fn test_trace_sampling_policy_api() {
+        let state = test_state();
+        let key = create_key(&state, "sampling").await;
+        let app = router(state.clone(), PathBuf::from("/nonexistent/ui/dist"));
+        let call = |method: Method, body: Option<serde_json::Value>| {
+            let mut request = Request::builder()
+                .method(method)
+                .uri("/api/traces/sampling")
+                .header(header::AUTHORIZATION, format!("Bearer {}", key.key));
+            let body = match body {
+                Some(json) => {
+                    request = request.header(header::CONTENT_TYPE, "application/json");
+                    Body::from(json.to_string())
+                }
+                None => Body::empty(),
+            };
+            let app = app.clone();
+            let request = request.body(body).unwrap();
+            async move {
+                let response = app.oneshot(request).await.unwrap();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                (status, body)
+            }
+        };
+
+        let (status, body) = call(Method::GET, None).await;
+        assert_eq!(status, StatusCode::OK);
+        let policy: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(policy["enabled"], false);
+        assert_eq!(policy["keep_errors"], true);
+
+        let (status, body) = call(
+            Method::PUT,
+            Some(serde_json::json!({"enabled": true, "sample_rate": 2.0})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(std::str::from_utf8(&body).unwrap().contains("sample_rate"));
+        assert!(!state.trace_store.sampling().enabled);
+
+        let (status, _) = call(
+            Method::PUT,
+            Some(serde_json::json!({
+                "enabled": true,
+                "latency_threshold_ms": 250,
+                "sample_rate": 0.05,
+                "services": {"payments": {"sample_rate": 1.0}},
+            })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let active = state.trace_store.sampling();
+        assert!(active.enabled);
+        assert_eq!(active.latency_threshold_ms, Some(250));
+        assert_eq!(active.services["payments"].sample_rate, Some(1.0));
+
+        // The same policy in cz-hub.toml is checked the same way.
+        let config: Config =
+            toml::from_str("[traces.sampling]\nenabled = true\nsample_rate = -1.0\n").unwrap();
+        assert_eq!(
+            config.validate(),
+            Err("traces.sampling.sample_rate must be within 0..=1, got -1".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_trace_detail_nests_spans_with_events_and_links() {
+        let state = test_state();
+        let key = create_key(&state, "waterfall").await;
+        let app = router(state.clone(), PathBuf::from("/nonexistent/ui/dist"));
+        let span = |id: &str, parent: Option<&str>, start_ms: u64, end_ms: u64| {
+            serde_json::json!({
+                "trace_id": "t1",
+                "span_id": id,
+                "parent_span_id": parent,
+                "name": format!("op-{}", id),
+                "service_name": "shop",
+                "start_time_unix_nano": start_ms * 1_000_000,
+                "end_time_unix_nano": end_ms * 1_000_000,
+                "attributes": {},
+                "status": "Ok",
+            })
+        };
+        let mut charge = span("charge", Some("checkout"), 10, 40);
+        charge["events"] = serde_json::json!([
+            {"ts": 20_000_000, "name": "retry", "attrs": {"attempt": 2}},
+        ]);
+        charge["links"] = serde_json::json!([{"trace_id": "t0", "span_id": "enqueue"}]);
+        let body = serde_json::json!({"spans": [
+            span("root", None, 0, 100),
+            span("checkout", Some("root"), 5, 50),
+            charge,
+            span("late", Some("never-sent"), 60, 70),
+        ]});
+        let request = Request::post("/api/traces/ingest")
+            .header(header::AUTHORIZATION, format!("Bearer {}", key.key))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+        let request = Request::get("/api/traces/t1")
+            .header(header::AUTHORIZATION, format!("Bearer {}", key.key))
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let trace: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let stored = &trace["spans"][2];
+        assert_eq!(stored["events"][0]["time_unix_nano"], 20_000_000);
+        assert_eq!(stored["events"][0]["attributes"]["attempt"], 2);
+        assert_eq!(stored["links"][0]["span_id"], "enqueue");
+        assert_eq!(trace["spans"][0]["events"], serde_json::json!([]));
+
+        let tree = trace["tree"].as_array().unwrap();
+        assert_eq!(tree.len(), 2);
+        let checkout = &tree[0]["children"][0];
+        assert_eq!(checkout["span_id"], "checkout");
+        assert_eq!(checkout["self_time_ms"], 15.0);
+        assert_eq!(checkout["children"][0]["span_id"], "charge");
+        assert_eq!(tree[1]["span_id"], serde_json::Value::Null);
+        assert_eq!(tree[1]["children"][0]["span_id"], "late");
+    }
+
+    #[tokio::test]
+    async fn test_traces_and_events_link_both_ways() {
+        let state = test_state();
+        let key = create_key(&state, "traces").await;
+        let path = add_journal(&state, "correlate", 0).await;
+        {
+            // A journal event naming the trace in its payload, then one
+            // naming none.
+            let primary = state.get_journal(None).await.unwrap();
+            let mut journal = primary.journal.write().await;
+            let mut cursor = cz_io::cursor::Cursor::new(journal.index_ring_capacity());
+            let mut blob_offset = 0;
+            let bodies: [&[u8]; 2] = [br#"{"trace_id": "trace-1", "step": "charge"}"#, b"{}"];
+            for (ts, body) in (1..).zip(bodies) {
+                let header = CausalEvent::new(0, 1, 0, 0, crc32fast::hash(body));
+                let mut packet = header.to_bytes().to_vec();
+                packet.extend(body);
+                let offset = journal.write_payload(blob_offset, &packet);
+                blob_offset = offset as usize + packet.len();
+                let slot = cursor.advance_head().unwrap();
+                let stored = CausalEvent::new(ts, 1, 0, offset, header.checksum);
+                unsafe { journal.write_event_at(slot, &stored) };
+            }
+            journal.publish_cursor(&cursor);
+            *primary.cursor.write().await = MultiCursor::new(journal.live_cursor_view());
+        }
+        // The scan of the open journals follows the subscriptions.
+        tokio::spawn(correlate_traces(state.clone()));
+        while state.trace_correlations.events("trace-1").await.is_empty() {
+            tokio::task::yield_now().await;
+        }
+
+        let webhook = Arc::new(connectors::webhook::WebhookConnector::new(
+            "payments".into(),
+            HashMap::new(),
+        ));
+        state.connector_registry.add(webhook.clone()).await.unwrap();
+        connectors::StreamConnector::ingest(
+            webhook.as_ref(),
+            br#"{"trace_id": "trace-1", "action": "paid"}"#,
+            HashMap::new(),
+        )
+        .await
+        .unwrap();
+        while state.trace_correlations.events("trace-1").await.len() < 2 {
+            tokio::task::yield_now().await;
+        }
+        state
+            .trace_store
+            .ingest(vec![traces::Span {
+                name: "checkout".into(),
+                service_name: "shop".into(),
+                end_time_unix_nano: 9_000_000,
+                ..traces::test_span("trace-1", "root")
+            }])
+            .await;
+
+        let app = router(state.clone(), PathBuf::from("/nonexistent/ui/dist"));
+        let get = |uri: String| {
+            let request = Request::get(uri)
+                .header(header::AUTHORIZATION, format!("Bearer {}", key.key))
+                .body(Body::empty())
+                .unwrap();
+            let app = app.clone();
+            async move {
+                let response = app.oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+            }
+        };
+
+        // Trace to events.
+        let trace = get("/api/traces/trace-1".into()).await;
+        assert_eq!(trace["spans"].as_array().unwrap().len(), 1);
+        let events = trace["events"].as_array().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0]["source"], "journal");
+        assert_eq!(events[0]["journal"], path.display().to_string());
+        assert_eq!(
+            (events[0]["slot"].clone(), events[0]["lamport_ts"].clone()),
+            (0.into(), 1.into())
+        );
+        let buffered = state.connector_registry.buffered_events().await;
+        assert_eq!(events[1]["source"], "stream");
+        assert_eq!(events[1]["id"], buffered[0].id);
+
+        // Events to their trace.
+        let linked = get("/api/events/0".into()).await;
+        assert_eq!(linked["trace_id"], "trace-1");
+        assert!(get("/api/events/1".into()).await.get("trace_id").is_none());
+        let named = traces::correlation::trace_id_of(&buffered[0].payload, &buffered[0].metadata);
+        let trace = get(format!("/api/traces/{}", named.unwrap())).await;
+        assert_eq!(trace["trace_id"], "trace-1");
+
+        // Once the ring reuses slot 0, it no longer belongs to the trace.
+        {
+            let primary = state.get_journal(None).await.unwrap();
+            let mut journal = primary.journal.write().await;
+            let reused = journal.event_at(0).unwrap();
+            let reused = CausalEvent::new(50, 1, 0, reused.payload_offset, reused.checksum);
+            unsafe { journal.write_event_at(0, &reused) };
+        }
+        let trace = get("/api/traces/trace-1".into()).await;
+        assert_eq!(trace["events"].as_array().unwrap().len(), 1);
+        assert!(get("/api/events/0".into()).await.get("trace_id").is_none());
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[tokio::test]
+    async fn test_explain_prefix_answers_with_the_plan() {
+        let state = test_state();
+        let key = create_key(&state, "query").await;
+        let webhook = Arc::new(connectors::webhook::WebhookConnector::new(
+            "github".into(),
+            HashMap::new(),
+        ));
+        state.connector_registry.add(webhook.clone()).await.unwrap();
+        connectors::StreamConnector::ingest(
+            webhook.as_ref(),
+            br#"{"action": "opened"}"#,
+            HashMap::new(),
+        )
+        .await
+        .unwrap();
+        while state.connector_registry.buffered_events().await.is_empty() {
+            tokio::task::yield_now().await;
+        }
+        let app = protected(
+            &state,
+            Router::new().route("/api/query", post(api::execute_query)),
+        );
+        let text = "EXPLAIN SELECT * FROM webhook WHERE acton = 'opened'";
+        let response = app
+            .oneshot(
+                Request::post("/api/query")
+                    .header(header::AUTHORIZATION, format!("Bearer {}", key.key))
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(serde_json::json!({ "query": text }).to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let explain: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(explain.get("events").is_none());
+        assert_eq!(explain["estimated_scan"], 1);
+        assert_eq!(explain["conditions"][0]["present"], 0);
+        assert!(explain["warnings"][0]
+            .as_str()
+            .unwrap()
+            .starts_with("Field 'acton' resolved to nothing"));
+    }
+
+    #[tokio::test]
+    async fn test_ws_query_subscription_streams_only_matches() {
+        let state = test_state();
+        let key = create_key(&state, "live").await;
+        let webhook = Arc::new(connectors::webhook::WebhookConnector::new(
+            "github".into(),
+            HashMap::new(),
+        ));
+        state.connector_registry.add(webhook.clone()).await.unwrap();
+        let app = Router::new()
+            .route("/api/query/subscribe", post(api::subscribe_query))
+            .layer(middleware::from_fn_with_state(
+                state.clone(),
+                auth_middleware,
+            ))
+            .route("/ws", get(ws_handler))
+            .with_state(state.clone());
+        let subscribe = |query: &str| {
+            Request::post("/api/query/subscribe")
+                .header(header::AUTHORIZATION, format!("Bearer {}", key.key))
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(
+                    serde_json::json!({ "query": query }).to_string(),
+                ))
+                .unwrap()
+        };
+        let json = |text: String| serde_json::from_str::<serde_json::Value>(&text).unwrap();
+
+        let response = app
+            .clone()
+            .oneshot(subscribe(
+                r#"SELECT * FROM webhook WHERE action = "opened""#,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let id = serde_json::from_slice::<serde_json::Value>(&body).unwrap()["id"]
+            .as_str()
+            .unwrap()
+            .to_string();
+
+        let url = format!("ws://{}/ws?token={}", serve(app.clone()).await, key.key);
+        let (mut a, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        let attach = format!(r#"{{"subscribe": ["query"], "query": "{}"}}"#, id);
+        ws_send(&mut a, &attach).await;
+        assert_eq!(
+            json(ws_recv(&mut a).await),
+            serde_json::json!({
+                "type": "subscribed", "channels": ["query"], "journal": null, "query": id
+            })
+        );
+        // Only one connection may stream a subscription.
+        let (mut b, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        ws_send(&mut b, &attach).await;
+        assert_eq!(json(ws_recv(&mut b).await)["type"], "error");
+
+        for body in [
+            r#"{"action": "closed", "n": 1}"#,
+            r#"{"action": "opened", "n": 2}"#,
+            r#"{"action": "labeled", "n": 3}"#,
+            r#"{"action": "opened", "n": 4}"#,
+        ] {
+            connectors::StreamConnector::ingest(webhook.as_ref(), body.as_bytes(), HashMap::new())
+                .await
+                .unwrap();
+        }
+        for n in [2, 4] {
+            let message = json(ws_recv(&mut a).await);
+            assert_eq!(
+                (&message["type"], &message["subscription"]),
+                (&"query".into(), &id.clone().into())
+            );
+            assert_eq!(message["data"]["payload"]["n"], n);
+        }
+
+        // The cap counts every subscription the key holds.
+        for _ in 1..cz_hub::query::subscriptions::DEFAULT_MAX_PER_KEY {
+            let response = app.clone().oneshot(subscribe("SELECT *")).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+        let response = app.clone().oneshot(subscribe("SELECT *")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let response = app
+            .clone()
+            .oneshot(subscribe("SELECT count(*) FROM webhook"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // Disconnecting drops the subscription and frees its place.
+        let held = state.query_subscriptions.count(&key.api_key.id);
+        drop(a);
+        for _ in 0..100 {
+            if state.query_subscriptions.count(&key.api_key.id) < held {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(state.query_subscriptions.count(&key.api_key.id), held - 1);
+        let response = app.oneshot(subscribe("SELECT *")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_event_stream_resumes_and_follows_commits() {
+        use http_body_util::BodyExt;
+
+        let state = test_state();
+        let path = add_journal(&state, "sse", 5).await;
+        let key = create_key(&state, "sse").await;
+        let router = protected(
+            &state,
+            Router::new().route("/api/events/stream", get(api_events_stream)),
+        );
+        let addr = serve(router).await;
+
+        let stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let (mut sender, connection) =
+            hyper::client::conn::http1::handshake(hyper_util::rt::TokioIo::new(stream))
+                .await
+                .unwrap();
+        let connection = tokio::spawn(connection);
+        let request = Request::get("/api/events/stream?node_id=1")
+            .header(header::HOST, addr.to_string())
+            .header(header::AUTHORIZATION, format!("Bearer {}", key.key))
+            .header("last-event-id", "2")
+            .body(Body::empty())
+            .unwrap();
+        let response = sender.send_request(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/event-stream"
+        );
+        assert_eq!(state.ipc_events.receiver_count(), 1);
+
+        // Slot 4 was backfilled, so its notification is not sent again;
+        // slot 5 is from another node.
+        {
+            let journal_state = state.journals.read().await[&path].clone();
+            let mut journal = journal_state.journal.write().await;
+            unsafe {
+                journal.write_event_at(5, &CausalEvent::new(6, 2, 0, 0, 0));
+                journal.write_event_at(6, &CausalEvent::new(7, 1, 3, 0, 0));
+            }
+        }
+        for (slot, lamport_ts) in [(4, 5), (5, 6), (6, 7)] {
+            let event = ipc::SequencerEvent {
+                journal: path.clone(),
+                message: IpcMessage::SlotCommitted { slot, lamport_ts },
+            };
+            state.ipc_events.send(event).unwrap();
+        }
+
+        let mut body = response.into_body();
+        let mut text = String::new();
+        while text.matches("data:").count() < 3 {
+            let frame = tokio::time::timeout(std::time::Duration::from_secs(5), body.frame())
+                .await
+                .expect("event stream stalled")
+                .unwrap()
+                .unwrap();
+            if let Ok(bytes) = frame.into_data() {
+                text.push_str(std::str::from_utf8(&bytes).unwrap());
+            }
+        }
+        let events: Vec<(String, serde_json::Value)> = text
+            .split("\n\n")
+            .filter(|block| block.contains("data:"))
+            .map(|block| {
+                let field = |name: &str| {
+                    block
+                        .lines()
+                        .find_map(|line| line.strip_prefix(name))
+                        .unwrap()
+                        .trim()
+                        .to_string()
+                };
+                (field("id:"), serde_json::from_str(&field("data:")).unwrap())
+            })
+            .collect();
+        let slots: Vec<&str> = events.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(slots, ["3", "4", "6"]);
+        assert_eq!(
+            events[2].1,
+            serde_json::json!({
+                "slot": 6, "lamport_ts": 7, "node_id": 1, "stream_id": 3, "flags": 0
+            })
+        );
+
+        // Hanging up drops the stream and its subscription.
+        drop(body);
+        drop(sender);
+        connection.abort();
+        let deadline = Instant::now() + std::time::Duration::from_secs(5);
+        while state.ipc_events.receiver_count() > 0 {
+            assert!(
+                Instant::now() < deadline,
+                "event stream outlived its client"
+            );
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[tokio::test]
+    async fn test_event_detail_reads_exact_payloads() {
+        let state = test_state();
+        let path = add_journal(&state, "payloads", 0).await;
+        let plain = b"plain payload";
+        let json = br#"{"order":42,"status":"shipped"},{"order":43,"status":"shipped"}"#;
+        let mut frame = vec![0u8; cz_core::compress::max_compressed_len(json.len())];
+        let frame_len = cz_core::compress::compress(json, &mut frame).unwrap();
+        frame.truncate(frame_len);
+
+        // Store the packets and index them the way the sequencer would:
+        // header copy and payload behind a length prefix, with the
+        // checksum over the compressed bytes.
+        let sent = [
+            (
+                CausalEvent::new(0, 3, 1, 0, crc32fast::hash(plain)),
+                &plain[..],
+            ),
+            (
+                CausalEvent::new(0, 3, 1, 0, crc32fast::hash(&frame)).compressed(),
+                &frame[..],
+            ),
+        ];
+        {
+            let primary = state.get_journal(None).await.unwrap();
+            let mut journal = primary.journal.write().await;
+            let mut cursor = cz_io::cursor::Cursor::new(journal.index_ring_capacity());
+            let mut blob_offset = 0;
+            for (ts, (header, payload)) in (1..).zip(sent) {
+                let mut packet = header.to_bytes().to_vec();
+                packet.extend(payload);
+                let offset = journal.write_payload(blob_offset, &packet);
+                blob_offset = offset as usize + packet.len();
+
+                let slot = cursor.advance_head().unwrap();
+                let stored =
+                    CausalEvent::with_flags(ts, 3, 1, offset, header.checksum, header.flags);
+                unsafe { journal.write_event_at(slot, &stored) };
+            }
+            journal.publish_cursor(&cursor);
+            *primary.cursor.write().await = MultiCursor::new(journal.live_cursor_view());
+        }
+
+        let detail = |slot| {
+            api_event_detail(
+                State(state.clone()),
+                axum::extract::Path(slot),
+                Query(EventDetailParams { verify: None }),
+            )
+        };
+        let Json(first) = detail(0).await.map_err(|e| e.0).unwrap();
+        assert_eq!(first.event.payload_size, 32 + plain.len());
+        assert!(first.payload_ascii.ends_with("plain payload"));
+        assert_eq!((first.compressed, first.decompressed_size), (false, None));
+
+        let Json(second) = detail(1).await.map_err(|e| e.0).unwrap();
+        assert_eq!(second.event.payload_size, 32 + frame_len);
+        assert_eq!(second.decompressed_size, Some(32 + json.len()));
+        assert!(second
+            .payload_ascii
+            .ends_with(std::str::from_utf8(json).unwrap()));
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[tokio::test]
+    async fn test_journal_verify_reports_corrupted_slot() {
+        let state = test_state();
+        let path = add_journal(&state, "verify-scan", 0).await;
+        let corrupted_offset = {
+            let primary = state.get_journal(None).await.unwrap();
+            let mut journal = primary.journal.write().await;
+            let mut cursor = cz_io::cursor::Cursor::new(journal.index_ring_capacity());
+            let mut blob_offset = 0;
+            let mut offsets = Vec::new();
+            for ts in 1..=3u64 {
+                let payload = format!("event {}", ts).into_bytes();
+                let mut packet = CausalEvent::new(0, 1, 0, 0, 0).to_bytes().to_vec();
+                packet.extend(&payload);
+                let offset = journal.write_payload(blob_offset, &packet);
+                blob_offset = offset as usize + packet.len();
+                offsets.push(offset as usize);
+
+                let slot = cursor.advance_head().unwrap();
+                let event = CausalEvent::new(ts, 1, 0, offset, crc32fast::hash(&payload));
+                unsafe { journal.write_event_at(slot, &event) };
+            }
+            journal.publish_cursor(&cursor);
+            *primary.cursor.write().await = MultiCursor::new(journal.live_cursor_view());
+            offsets[1]
+        };
+        let verify = || api_journal_verify(State(state.clone()), Query(HashMap::new()));
+
+        let Json(report) = verify().await.map_err(|e| e.0).unwrap();
+        assert!(report.clean);
+        assert_eq!((report.scanned, report.matched), (3, 3));
+
+        state
+            .get_journal(None)
+            .await
+            .unwrap()
+            .journal
+            .write()
+            .await
+            .blob_storage_mut()[corrupted_offset + 32] ^= 0x20;
+        let Json(report) = verify().await.map_err(|e| e.0).unwrap();
+        assert!(!report.clean);
+        assert_eq!(report.mismatched_slots, [1]);
+        assert_eq!((report.matched, report.unreadable), (2, 0));
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[tokio::test]
+    async fn test_journal_snapshot_reopens_with_the_live_events() {
+        let state = test_state();
+        let path = add_journal(&state, "snapshot-source", 0).await;
+        {
+            let primary = state.get_journal(None).await.unwrap();
+            let mut journal = primary.journal.write().await;
+            let mut cursor = cz_io::cursor::Cursor::new(journal.index_ring_capacity());
+            let mut blob_offset = 0;
+            for ts in 1..=4u64 {
+                let payload = format!("event {}", ts).into_bytes();
+                let mut packet = CausalEvent::new(0, 1, 0, 0, 0).to_bytes().to_vec();
+                packet.extend(&payload);
+                let offset = journal.write_payload(blob_offset, &packet);
+                blob_offset = offset as usize + packet.len();
+
+                let slot = cursor.advance_head().unwrap();
+                let event = CausalEvent::new(ts, 1, 0, offset, crc32fast::hash(&payload));
+                unsafe { journal.write_event_at(slot, &event) };
+            }
+            // The oldest event has been evicted.
+            cursor.advance_tail();
+            journal.publish_cursor(&cursor);
+            *primary.cursor.write().await = MultiCursor::new(journal.live_cursor_view());
+        }
+        let dest =
+            std::env::temp_dir().join(format!("cz-hub-snapshot-copy-{}.db", std::process::id()));
+        let snapshot = || {
+            api_journal_snapshot(
+                State(state.clone()),
+                Json(SnapshotRequest {
+                    journal: None,
+                    path: dest.clone(),
+                }),
+            )
+        };
+
+        let Json(result) = snapshot().await.map_err(|e| e.0).unwrap();
+        assert_eq!(result.events, 3);
+        assert_eq!(result.path, dest.display().to_string());
+        assert_eq!(snapshot().await.err().unwrap().0, StatusCode::CONFLICT);
+
+        let source = state.get_journal(None).await.unwrap();
+        let source = source.journal.read().await;
+        let copy = Journal::open(&dest, 0).unwrap();
+        let live = copy.live_cursor_view();
+        assert_eq!(live.slots().collect::<Vec<_>>(), [1, 2, 3]);
+        for slot in live.slots() {
+            let (a, b) = (source.event_at(slot).unwrap(), copy.event_at(slot).unwrap());
+            assert_eq!(a.to_bytes(), b.to_bytes());
+            assert_eq!(source.read_payload(&a), copy.read_payload(&b));
+        }
+        let scan = copy.integrity_scan(&live);
+        assert!(scan.is_clean());
+        assert_eq!(scan.matched, 3);
+        assert_eq!(
+            required_scope("/api/journal/snapshot", &Method::POST),
+            auth::Scope::Admin
+        );
+        std::fs::remove_file(&path).ok();
+        std::fs::remove_file(&dest).ok();
+    }
+
+    #[tokio::test]
+    async fn test_events_expose_flags_and_checksum_validity() {
+        let state = test_state();
+        let path = add_journal(&state, "flags", 0).await;
+        {
+            let primary = state.get_journal(None).await.unwrap();
+            let mut journal = primary.journal.write().await;
+            let mut cursor = cz_io::cursor::Cursor::new(journal.index_ring_capacity());
+            let mut blob_offset = 0;
+            let mut offsets = Vec::new();
+            for (ts, flags) in [(1u64, 0), (2, cz_core::FLAG_RESEQUENCED), (3, 0)] {
+                let payload = format!("event {}", ts).into_bytes();
+                let mut packet = CausalEvent::new(0, 1, 0, 0, 0).to_bytes().to_vec();
+                packet.extend(&payload);
+                let offset = journal.write_payload(blob_offset, &packet);
+                blob_offset = offset as usize + packet.len();
+                offsets.push(offset as usize);
+
+                let slot = cursor.advance_head().unwrap();
+                let event =
+                    CausalEvent::with_flags(ts, 1, 0, offset, crc32fast::hash(&payload), flags);
+                unsafe { journal.write_event_at(slot, &event) };
+            }
+            // Corrupt the last event's payload on disk.
+            journal.blob_storage_mut()[offsets[2] + 32] ^= 0x20;
+            journal.publish_cursor(&cursor);
+            *primary.cursor.write().await = MultiCursor::new(journal.live_cursor_view());
+        }
+        let events = |flags: Option<&str>, checksum_valid, verify| {
+            api_events(
+                State(state.clone()),
+                Query(EventQueryParams {
+                    journal: None,
+                    node_id: None,
+                    stream_id: None,
+                    ts_min: None,
+                    ts_max: None,
+                    offset: None,
+                    limit: None,
+                    query: None,
+                    cursor: None,
+                    partition: None,
+                    flags: flags.map(str::to_string),
+                    checksum_valid,
+                    verify,
+                }),
+            )
+        };
+
+        // Checksums are only checked when asked for.
+        let Json(page) = events(None, None, None).await.map_err(|e| e.0).unwrap();
+        assert!(page.events.iter().all(|e| e.checksum_valid.is_none()));
+        assert_eq!(page.events[1].flags, cz_core::FLAG_RESEQUENCED);
+        assert_eq!(page.events[1].flag_names, ["resequenced"]);
+        assert!(serde_json::to_value(&page.events[0])
+            .unwrap()
+            .get("checksum_valid")
+            .is_none());
+
+        let Json(page) = events(None, None, Some(true))
+            .await
+            .map_err(|e| e.0)
+            .unwrap();
+        let valid: Vec<Option<bool>> = page.events.iter().map(|e| e.checksum_valid).collect();
+        assert_eq!(valid, [Some(true), Some(true), Some(false)]);
+
+        let Json(page) = events(None, Some(false), None)
+            .await
+            .map_err(|e| e.0)
+            .unwrap();
+        let timestamps: Vec<u64> = page.events.iter().map(|e| e.lamport_ts).collect();
+        assert_eq!(timestamps, [3]);
+
+        let Json(page) = events(Some("resequenced"), None, None)
+            .await
+            .map_err(|e| e.0)
+            .unwrap();
+        let timestamps: Vec<u64> = page.events.iter().map(|e| e.lamport_ts).collect();
+        assert_eq!(timestamps, [2]);
+        let Err((status, Json(error))) = events(Some("resequenced,tombstone"), None, None).await
+        else {
+            panic!("unknown flag accepted");
+        };
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(error.error.contains("tombstone"));
+
+        let Json(detail) = api_event_detail(
+            State(state.clone()),
+            axum::extract::Path(2),
+            Query(EventDetailParams { verify: Some(true) }),
+        )
+        .await
+        .map_err(|e| e.0)
+        .unwrap();
+        assert_eq!(detail.event.checksum_valid, Some(false));
+
+        let response = api_export(
+            State(state.clone()),
+            Query(ExportParams {
+                format: Some("csv".into()),
+                verify: Some(true),
+                ..Default::default()
+            }),
+        )
+        .await
+        .into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let csv = String::from_utf8(body.to_vec()).unwrap();
+        let rows: Vec<&str> = csv.lines().collect();
+        assert!(rows[0].ends_with(",flags,checksum_valid"));
+        assert!(rows[2].ends_with(",2,true"));
+        assert!(rows[3].ends_with(",0,false"));
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[tokio::test]
+    async fn test_events_search_seeks_timestamp_range() {
+        let state = test_state();
+        let path = add_journal(&state, "search", 20).await;
+        let search = |ts_min, ts_max, offset| {
+            api_events_search(
+                State(state.clone()),
+                Query(EventSearchParams {
+                    journal: None,
+                    ts_min,
+                    ts_max,
+                    offset,
+                    limit: Some(3),
+                    partition: None,
+                }),
+            )
+        };
+
+        let Json(page) = search(Some(5), Some(9), None)
+            .await
+            .map_err(|e| e.0)
+            .unwrap();
+        assert_eq!((page.matched, page.first_index), (5, 4));
+        let timestamps: Vec<u64> = page.events.iter().map(|e| e.lamport_ts).collect();
+        assert_eq!(timestamps, [5, 6, 7]);
+
+        let Json(page) = search(Some(5), Some(9), Some(3))
+            .await
+            .map_err(|e| e.0)
+            .unwrap();
+        let timestamps: Vec<u64> = page.events.iter().map(|e| e.lamport_ts).collect();
+        assert_eq!(timestamps, [8, 9]);
+
+        let Json(page) = search(Some(30), None, None).await.map_err(|e| e.0).unwrap();
+        assert_eq!((page.matched, page.first_index), (0, 20));
+
+        // The plain listing seeks the same way.
+        let Json(listing) = api_events(
+            State(state.clone()),
+            Query(EventQueryParams {
+                journal: None,
+                node_id: None,
+                stream_id: None,
+                ts_min: Some(18),
+                ts_max: None,
+                offset: None,
+                limit: None,
+                query: None,
+                cursor: None,
+                partition: None,
+                flags: None,
+                checksum_valid: None,
+                verify: None,
+            }),
+        )
+        .await
+        .map_err(|e| e.0)
+        .unwrap();
+        let timestamps: Vec<u64> = listing.events.iter().map(|e| e.lamport_ts).collect();
+        assert_eq!(timestamps, [18, 19, 20]);
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[tokio::test]
+    async fn test_anomalies_page_through_injected_faults_and_alert_once() {
+        let state = test_state();
+        let path = add_journal(&state, "anomalies", 0).await;
+        {
+            let primary = state.get_journal(None).await.unwrap();
+            let mut journal = primary.journal.write().await;
+            let mut cursor = cz_io::cursor::Cursor::new(journal.index_ring_capacity());
+            let mut blob_offset = 0;
+            // (lamport_ts, node_id, checksum is right, payload is in bounds)
+            let events = [
+                (10, 1, true, true),
+                (20, 1, true, true),
+                (20, 1, true, true), // duplicate of slot 1
+                (15, 2, true, true), // before slot 2
+                (30, 1, false, true),
+                (40, 1, true, false),
+                (50, 1, true, true),
+            ];
+            for (ts, node_id, checksum_ok, in_bounds) in events {
+                let payload = [ts as u8; 8];
+                let mut packet = CausalEvent::new(0, node_id, 0, 0, 0).to_bytes().to_vec();
+                packet.extend(payload);
+                let mut offset = journal.write_payload(blob_offset, &packet);
+                blob_offset = offset as usize + packet.len();
+                if !in_bounds {
+                    offset = journal.blob_capacity() as u64 + 8;
+                }
+                let checksum = crc32fast::hash(&payload) ^ u32::from(!checksum_ok);
+                let slot = cursor.advance_head().unwrap();
+                let event = CausalEvent::new(ts, node_id, 0, offset, checksum);
+                unsafe { journal.write_event_at(slot, &event) };
+            }
+            journal.publish_cursor(&cursor);
+        }
+        let anomalies = |query: &str| {
+            let uri = format!("/api/anomalies?{}", query).parse().unwrap();
+            api_anomalies(State(state.clone()), Query::try_from_uri(&uri).unwrap())
+        };
+
+        let mut found = Vec::new();
+        let mut query = "limit=3".to_string();
+        let mut pages = 0;
+        loop {
+            let Json(page) = anomalies(&query).await.map_err(|e| e.0).unwrap();
+            pages += 1;
+            found.extend(
+                page.findings
+                    .iter()
+                    .map(|a| (a.kind, a.slot, a.related_slot)),
+            );
+            match page.next_cursor {
+                Some(next) => query = format!("limit=3&cursor={}", next),
+                None => break,
+            }
+        }
+        assert_eq!(pages, 3);
+        assert_eq!(
+            found,
+            [
+                (AnomalyKind::DuplicateKey, 2, Some(1)),
+                (AnomalyKind::OrderViolation, 3, Some(2)),
+                (AnomalyKind::ChecksumMismatch, 4, None),
+                (AnomalyKind::PayloadOutOfBounds, 5, None),
+            ]
+        );
+
+        let Json(all) = anomalies("").await.map_err(|e| e.0).unwrap();
+        assert_eq!((all.scanned, all.next_cursor), (7, None));
+        assert_eq!(all.counts.values().sum::<usize>(), 4);
+        assert_eq!(all.counts["duplicate_key"], 1);
+        let bad = anomalies("cursor=nope").await.err().unwrap();
+        assert_eq!(bad.0, StatusCode::BAD_REQUEST);
+
+        // The collector counts each anomaly once and opens one incident.
+        watch_anomalies(&state).await;
+        watch_anomalies(&state).await;
+        for kind in AnomalyKind::ALL {
+            assert_eq!(state.metrics.anomalies(kind), 1, "{}", kind.as_str());
+        }
+        let incidents = state.alert_engine.list_active().await;
+        assert_eq!(incidents.len(), 1);
+        assert_eq!(
+            incidents[0].rule_id,
+            format!("causality-anomaly:{}", path.display())
+        );
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[tokio::test]
+    async fn test_events_aggregate_buckets_known_distribution() {
+        let state = test_state();
+        let path = add_journal(&state, "aggregate", 0).await;
+        {
+            // Event i lands at ts 100·i on stream [7, 7, 9][i % 3] from
+            // node 1 + i % 2, with a packet of 32 + stream_id bytes.
+            let primary = state.get_journal(None).await.unwrap();
+            let mut journal = primary.journal.write().await;
+            let mut cursor = cz_io::cursor::Cursor::new(journal.index_ring_capacity());
+            let mut blob_offset = 0;
+            for i in 1..=40u64 {
+                let stream_id = [7, 7, 9][i as usize % 3];
+                let packet = vec![0u8; 32 + stream_id as usize];
+                let offset = journal.write_payload(blob_offset, &packet);
+                blob_offset = offset as usize + packet.len();
+                let event = CausalEvent::new(i * 100, 1 + i as u32 % 2, stream_id, offset, 0);
+                let slot = cursor.advance_head().unwrap();
+                unsafe { journal.write_event_at(slot, &event) };
+            }
+            journal.publish_cursor(&cursor);
+        }
+        let aggregate = |query: &str| {
+            let uri = format!("/api/events/aggregate?{}", query).parse().unwrap();
+            api_events_aggregate(State(state.clone()), Query::try_from_uri(&uri).unwrap())
+        };
+        fn groups(bucket: &AggregateBucket) -> Vec<(&str, u64)> {
+            bucket
+                .groups
+                .iter()
+                .map(|(group, value)| (group.as_str(), *value))
+                .collect()
+        }
+
+        let Json(by_stream) = aggregate("").await.map_err(|e| e.0).unwrap();
+        assert_eq!((by_stream.bucket_ticks, by_stream.matched), (1000, 40));
+        let starts: Vec<u64> = by_stream.buckets.iter().map(|b| b.ts).collect();
+        assert_eq!(starts, [0, 1000, 2000, 3000, 4000]);
+        assert_eq!(groups(&by_stream.buckets[0]), [("7", 6), ("9", 3)]);
+        assert_eq!(groups(&by_stream.buckets[4]), [("7", 1)]);
+        let total: u64 = by_stream
+            .buckets
+            .iter()
+            .flat_map(|b| b.groups.values())
+            .sum();
+        assert_eq!(total, 40);
+
+        let Json(bytes) = aggregate("metric=bytes").await.map_err(|e| e.0).unwrap();
+        assert_eq!(groups(&bytes.buckets[0]), [("7", 6 * 39), ("9", 3 * 41)]);
+
+        let Json(both) = aggregate("group_by=stream_id,node_id&bucket=1s")
+            .await
+            .map_err(|e| e.0)
+            .unwrap();
+        assert_eq!(
+            groups(&both.buckets[0]),
+            [("1:7", 2), ("1:9", 2), ("2:7", 4), ("2:9", 1)]
+        );
+
+        let Json(window) = aggregate("group_by=node_id&bucket=500&ts_min=1000&ts_max=1999")
+            .await
+            .map_err(|e| e.0)
+            .unwrap();
+        assert_eq!(window.matched, 10);
+        assert_eq!(window.buckets.len(), 2);
+        assert_eq!(groups(&window.buckets[0]), [("1", 3), ("2", 2)]);
+        assert_eq!(groups(&window.buckets[1]), [("1", 2), ("2", 3)]);
+
+        let Json(empty) = aggregate("ts_min=5000").await.map_err(|e| e.0).unwrap();
+        assert!(empty.buckets.is_empty());
+
+        for query in ["group_by=checksum", "bucket=0", "bucket=1h", "metric=avg"] {
+            let (status, _) = aggregate(query).await.err().unwrap();
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{}", query);
+        }
+
+        // Far more streams, and a far longer span, than the caps allow.
+        {
+            let primary = state.get_journal(None).await.unwrap();
+            let mut journal = primary.journal.write().await;
+            let mut cursor = journal.live_cursor_view();
+            for i in 0..=MAX_AGGREGATE_GROUPS as u64 {
+                let event = CausalEvent::new(100_000 + i, 1, i as u16, 0, 0);
+                let slot = cursor.advance_head().unwrap();
+                unsafe { journal.write_event_at(slot, &event) };
+            }
+            journal.publish_cursor(&cursor);
+        }
+        let (status, Json(error)) = aggregate("ts_min=100000").await.err().unwrap();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(error.error.contains("groups"), "{}", error.error);
+        let (status, Json(error)) = aggregate("bucket=1").await.err().unwrap();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(error.error.contains("buckets exceed"), "{}", error.error);
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[tokio::test]
+    async fn test_events_read_partitions_of_a_partitioned_journal() {
+        let state = test_state();
+        let path =
+            std::env::temp_dir().join(format!("cz-hub-partitioned-{}.db", std::process::id()));
+        std::fs::remove_file(&path).ok();
+        let ring = 64 * CausalEvent::size_bytes();
+        let mut journal =
+            Journal::open_partitioned(&path, (HEADER_SIZE + ring) as u64, ring, 2).unwrap();
+        let mut cursors: Vec<_> = (0..2)
+            .map(|p| journal.partition_cursor_view(p).unwrap())
+            .collect();
+        for ts in 1..=10u64 {
+            let stream_id = ts as u16 % 2;
+            let cursor = &mut cursors[journal.partition_of(stream_id)];
+            let slot = cursor.advance_head().unwrap();
+            unsafe { journal.write_event_at(slot, &CausalEvent::new(ts, 1, stream_id, 0, 0)) };
+            journal.publish_cursor(cursor);
+        }
+        state.journals.write().await.insert(
+            path.clone(),
+            Arc::new(JournalState::new(
+                path.clone(),
+                journal,
+                WatermarkPolicy::default(),
+            )),
+        );
+        let events = |partition, stream_id| {
+            api_events(
+                State(state.clone()),
+                Query(EventQueryParams {
+                    journal: None,
+                    node_id: None,
+                    stream_id,
+                    ts_min: None,
+                    ts_max: None,
+                    offset: None,
+                    limit: None,
+                    query: None,
+                    cursor: None,
+                    partition,
+                    flags: None,
+                    checksum_valid: None,
+                    verify: None,
+                }),
+            )
+        };
+        let timestamps = |listing: &EventListResponse| -> Vec<u64> {
+            listing.events.iter().map(|e| e.lamport_ts).collect()
+        };
+
+        // Partition 0 by default; a stream filter reads its own partition.
+        let Json(even) = events(None, None).await.map_err(|e| e.0).unwrap();
+        assert_eq!(timestamps(&even), [2, 4, 6, 8, 10]);
+        let Json(odd) = events(None, Some(1)).await.map_err(|e| e.0).unwrap();
+        assert_eq!(timestamps(&odd), [1, 3, 5, 7, 9]);
+        assert!(odd.events.iter().all(|e| e.slot >= 32));
+        let Json(odd) = events(Some(1), None).await.map_err(|e| e.0).unwrap();
+        assert_eq!(odd.total, 5);
+
+        let (status, _) = events(Some(2), None).await.err().unwrap();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let ring_state = |query: &str| {
+            let uri = format!("/api/ring?{}", query).parse().unwrap();
+            api_ring(State(state.clone()), Query::try_from_uri(&uri).unwrap())
+        };
+        let Json(ring) = ring_state("partition=1").await.map_err(|e| e.0).unwrap();
+        assert_eq!((ring.partitions, ring.capacity, ring.used), (2, 32, 5));
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[tokio::test]
+    async fn test_half_full_ring_reports_fifty_percent() {
+        let state = test_state();
+        let capacity = DEFAULT_INDEX_RING_CAPACITY;
+        let path = add_journal(&state, "half", capacity as u64 / 2).await;
+        let expected = round((capacity / 2) as f64 / capacity as f64 * 100.0);
+        assert!((expected - 50.0).abs() < 1.0);
+
+        let Json(ring) = api_ring(State(state.clone()), Query(HashMap::new()))
+            .await
+            .map_err(|e| e.0)
+            .unwrap();
+        assert_eq!(ring.utilization_pct, expected);
+        let Json(journals) = api_journals_list(State(state.clone())).await;
+        assert_eq!(journals[0].utilization_pct, expected);
+        let aggregate = state.aggregate_ring_state().await;
+        assert_eq!(round(aggregate.utilization_pct), expected);
+        assert_eq!(
+            JournalRingStats::from(&aggregate.journals[0]).utilization_pct,
+            expected
+        );
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[tokio::test]
+    async fn test_events_cursor_pages_survive_ring_movement() {
+        let state = test_state();
+        let path = add_journal(&state, "keyset", 10).await;
+        let page = |cursor: Option<String>| {
+            api_events(
+                State(state.clone()),
+                Query(EventQueryParams {
+                    journal: None,
+                    node_id: None,
+                    stream_id: None,
+                    ts_min: None,
+                    ts_max: None,
+                    offset: None,
+                    limit: Some(3),
+                    query: None,
+                    cursor,
+                    partition: None,
+                    flags: None,
+                    checksum_valid: None,
+                    verify: None,
+                }),
+            )
+        };
+
+        let mut seen = Vec::new();
+        let mut cursor = None;
+        let mut next_ts = 11;
+        for round in 0.. {
+            let Json(listing) = page(cursor.clone()).await.map_err(|e| e.0).unwrap();
+            if listing.events.is_empty() {
+                break;
+            }
+            seen.extend(listing.events.iter().map(|e| e.lamport_ts));
+            cursor = listing.next_cursor;
+
+            // Between pages the sequencer appends and retention drops
+            // events already read, shifting every row number.
+            if round < 5 {
+                let primary = state.get_journal(None).await.unwrap();
+                let mut journal = primary.journal.write().await;
+                let mut live = journal.live_cursor_view();
+                for _ in 0..2 {
+                    live.advance_tail().unwrap();
+                    let slot = live.advance_head().unwrap();
+                    let event = CausalEvent::new(next_ts, 1, 0, 0, 0);
+                    unsafe { journal.write_event_at(slot, &event) };
+                    next_ts += 1;
+                }
+                journal.publish_cursor(&live);
+            }
+        }
+        assert_eq!(seen, (1..next_ts).collect::<Vec<_>>());
+
+        // A cursor whose event has been dropped resumes past its
+        // timestamp; one that does not decode is rejected.
+        let dropped = EventsCursor::after(0, &CausalEvent::new(1, 1, 0, 0, 0)).encode();
+        let Json(listing) = page(Some(dropped)).await.map_err(|e| e.0).unwrap();
+        assert_eq!(listing.events[0].lamport_ts, 11);
+        let rejected = page(Some("not a cursor".into())).await.err().unwrap();
+        assert_eq!(rejected.0, StatusCode::BAD_REQUEST);
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[tokio::test]
+    async fn test_events_query_honors_compound_conditions() {
+        let state = test_state();
+        let path = add_journal(&state, "events-query", 6).await;
+
+        // The old evaluator only looked at the first `field op value`.
+        assert_eq!(event_timestamps(&state, "ts > 2 AND ts < 5").await, [3, 4]);
+        assert_eq!(
+            event_timestamps(&state, "node_id == 1 && ts >= 6").await,
+            [6]
+        );
+        assert!(event_timestamps(&state, "node_id = 1 AND stream_id > 0")
+            .await
+            .is_empty());
+        // Unknown fields no longer pass everything through.
+        assert!(event_timestamps(&state, "bogus > 1").await.is_empty());
+        assert_eq!(
+            event_timestamps(&state, "ts < 2 OR (node_id = 1 AND NOT ts < 5)").await,
+            [1, 5, 6]
+        );
+
+        let err = api_events(
+            State(state.clone()),
+            Query(EventQueryParams {
+                journal: None,
+                node_id: None,
+                stream_id: None,
+                ts_min: None,
+                ts_max: None,
+                offset: None,
+                limit: None,
+                query: Some("ts > 1 OR node_id".into()),
+                cursor: None,
+                partition: None,
+                flags: None,
+                checksum_valid: None,
+                verify: None,
+            }),
+        )
+        .await
+        .err()
+        .unwrap();
+        assert_eq!(err.0, StatusCode::BAD_REQUEST);
+        assert_eq!(err.1.error, "Cannot parse condition 'node_id' at 10..17");
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[tokio::test]
+    async fn test_aggregate_ring_state_spans_journals() {
+        let state = test_state();
+        let a = add_journal(&state, "aggregate-a", 10).await;
+        let b = add_journal(&state, "aggregate-b", 30).await;
+
+        let ring = state.aggregate_ring_state().await;
+        assert_eq!(ring.used, 40);
+        assert_eq!(ring.capacity, 2 * DEFAULT_INDEX_RING_CAPACITY);
+        let expected = 40.0 / (2 * DEFAULT_INDEX_RING_CAPACITY) as f64 * 100.0;
+        assert!((ring.utilization_pct - expected).abs() < 1e-9);
+
+        let used: Vec<usize> = ring.journals.iter().map(|j| j.used).collect();
+        assert_eq!(used, [10, 30]);
+        assert!(ring.journals[1].utilization_pct > ring.journals[0].utilization_pct);
+
+        std::fs::remove_file(&a).ok();
+        std::fs::remove_file(&b).ok();
+    }
+
+    fn ring_stats(head: usize, tail: usize, capacity: usize) -> JournalRingStats {
+        JournalRingStats {
+            head,
+            tail,
+            used: head - tail,
+            capacity,
+            utilization_pct: cz_io::cursor::Cursor::restore(capacity, head, tail).utilization_pct(),
+        }
+    }
+
+    fn snapshot_with(journals: &[(&str, JournalRingStats)], tps: f64) -> MetricsSnapshot {
+        MetricsSnapshot {
+            schema: METRICS_SCHEMA,
+            timestamp: "2026-01-01T00:00:00+00:00".into(),
+            events: 10,
+            bytes: 320,
+            tps,
+            tps_mean: None,
+            tps_stddev: None,
+            tps_zscore: 0.0,
+            bps: 0.0,
+            utilization_pct: 0.0,
+            uptime_seconds: 5,
+            playback_mode: PlaybackMode::RealTime,
+            journals: journals
+                .iter()
+                .map(|(path, stats)| (path.to_string(), stats.clone()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_metrics_snapshot_schema_2() {
+        let snapshot = snapshot_with(
+            &[
+                ("a.db", ring_stats(30, 10, 100)),
+                ("b.db", ring_stats(4, 0, 8)),
+            ],
+            1.5,
+        );
+        let json = serde_json::to_value(&snapshot).unwrap();
+        assert_eq!(json["schema"], 2);
+        assert!(json.get("head").is_none() && json.get("tail").is_none());
+        assert_eq!(
+            json["journals"],
+            serde_json::json!({
+                "a.db": {"head": 30, "tail": 10, "used": 20, "capacity": 100, "utilization_pct": 20.0},
+                "b.db": {"head": 4, "tail": 0, "used": 4, "capacity": 8, "utilization_pct": 50.0}
+            })
+        );
+
+        let empty = serde_json::to_value(snapshot_with(&[], 0.0)).unwrap();
+        assert_eq!(empty["journals"], serde_json::json!({}));
+    }
+
+    #[tokio::test]
+    async fn test_metrics_history_buckets() {
+        let state = test_state();
+        {
+            let mut history = state.metrics_history.write().await;
+            // Seven seconds from 00:00:10; buckets of 3 start at 09, 12, 15.
+            for second in 0..7u64 {
+                let mut snapshot = snapshot_with(
+                    &[("a.db", ring_stats(second as usize, 0, 10))],
+                    second as f64,
+                );
+                snapshot.timestamp = format!("2026-01-01T00:00:{:02}+00:00", 10 + second);
+                snapshot.events = 100 * second;
+                snapshot.bps = 10.0 * second as f64;
+                snapshot.utilization_pct =
+                    [5.0, 40.0, 15.0, 20.0, 60.0, 10.0, 30.0][second as usize];
+                history.push_back(snapshot);
+            }
+        }
+        let router = Router::new()
+            .route("/api/metrics/history", get(api_metrics_history))
+            .with_state(state);
+        let get_json = |uri: &'static str| {
+            let router = router.clone();
+            async move {
+                let response = router
+                    .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                    .await
+                    .unwrap();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                (
+                    status,
+                    serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+                )
+            }
+        };
+
+        let (status, json) = get_json("/api/metrics/history?bucket_seconds=3").await;
+        assert_eq!(status, StatusCode::OK);
+        let bucket = |timestamp: &str, samples, partial, events, tps, bps, peak, ring| {
+            serde_json::json!({
+                "timestamp": timestamp, "samples": samples, "partial": partial,
+                "events": events, "bytes": 320, "tps": tps, "bps": bps,
+                "utilization_pct": peak, "journals": {"a.db": ring},
+            })
+        };
+        assert_eq!(
+            json,
+            serde_json::json!([
+                // 00:00:09 is before the first snapshot: two samples.
+                bucket(
+                    "2026-01-01T00:00:09+00:00",
+                    2,
+                    false,
+                    100,
+                    0.5,
+                    5.0,
+                    40.0,
+                    10.0
+                ),
+                bucket(
+                    "2026-01-01T00:00:12+00:00",
+                    3,
+                    false,
+                    400,
+                    3.0,
+                    30.0,
+                    60.0,
+                    40.0
+                ),
+                // 00:00:15 to 00:00:18 has seen only 00:00:15 and 00:00:16.
+                bucket(
+                    "2026-01-01T00:00:15+00:00",
+                    2,
+                    true,
+                    600,
+                    5.5,
+                    55.0,
+                    30.0,
+                    60.0
+                ),
+            ])
+        );
+
+        // Without buckets, the raw snapshots.
+        let (_, json) = get_json("/api/metrics/history").await;
+        assert_eq!(json.as_array().unwrap().len(), 7);
+        assert_eq!(json[0]["schema"], 2);
+
+        let (status, json) = get_json("/api/metrics/history?bucket_seconds=0").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(json["error"].as_str().unwrap().contains("bucket_seconds"));
+    }
+
+    #[test]
+    fn test_alert_rules_check_every_journal() {
+        let rule = |condition: &str, threshold| AlertRule {
+            name: condition.into(),
+            condition: condition.into(),
+            threshold,
+            severity: "warn".into(),
+            enabled: true,
+        };
+        let rules = vec![rule("ring_utilization_gt", 40.0), rule("tps_drop_gt", 50.0)];
+        let snapshot = snapshot_with(
+            &[
+                ("a.db", ring_stats(30, 10, 100)),
+                ("b.db", ring_stats(4, 0, 8)),
+            ],
+            10.0,
+        );
+
+        let triggered = triggered_alerts(&rules, &snapshot, 0.0);
+        assert_eq!(triggered.len(), 1);
+        assert!(triggered[0].1.contains("b.db"));
+
+        let triggered = triggered_alerts(&rules, &snapshot, 100.0);
+        let conditions: Vec<&str> = triggered
+            .iter()
+            .map(|(r, _)| r.condition.as_str())
+            .collect();
+        assert_eq!(conditions, ["ring_utilization_gt", "tps_drop_gt"]);
+    }
+
+    #[test]
+    fn test_tps_baseline_rolling_stats() {
+        let mut baseline = TpsBaseline::new(4);
+        // Warming up: no baseline, no z-score.
+        for tps in [10.0, 12.0, 14.0] {
+            baseline.push(tps);
+            assert!(baseline.stats().is_none());
+            assert_eq!(baseline.zscore(100.0), 0.0);
+        }
+        baseline.push(16.0);
+        let (mean, stddev) = baseline.stats().unwrap();
+        assert!((mean - 13.0).abs() < 1e-9);
+        assert!((stddev - 5f64.sqrt()).abs() < 1e-9);
+        assert!((baseline.zscore(20.0) - 7.0 / 5f64.sqrt()).abs() < 1e-9);
+        assert!((baseline.zscore(6.0) + 7.0 / 5f64.sqrt()).abs() < 1e-9);
+
+        // The oldest sample leaves as a new one arrives.
+        baseline.push(18.0);
+        let (mean, stddev) = baseline.stats().unwrap();
+        assert!((mean - 15.0).abs() < 1e-9);
+        assert!((stddev - 5f64.sqrt()).abs() < 1e-9);
+
+        // A rate that never varies is never anomalous.
+        let mut flat = TpsBaseline::new(3);
+        for _ in 0..10 {
+            flat.push(0.1);
+        }
+        assert_eq!(flat.stats().unwrap().1, 0.0);
+        assert_eq!(flat.zscore(0.1), 0.0);
+        assert_eq!(flat.zscore(50.0), 0.0);
+    }
+
+    #[test]
+    fn test_tps_baseline_matches_recomputation() {
+        let series: Vec<f64> = (0..2000)
+            .map(|i| 500.0 + 250.0 * ((i as f64) * 0.37).sin() + (i % 7) as f64)
+            .collect();
+        let mut baseline = TpsBaseline::new(TPS_BASELINE_WINDOW);
+        for tps in &series {
+            baseline.push(*tps);
+        }
+        let window = &series[series.len() - TPS_BASELINE_WINDOW..];
+        let n = window.len() as f64;
+        let mean = window.iter().sum::<f64>() / n;
+        let stddev = (window.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n).sqrt();
+        let (got_mean, got_stddev) = baseline.stats().unwrap();
+        assert!((got_mean - mean).abs() < 1e-6, "{} vs {}", got_mean, mean);
+        assert!(
+            (got_stddev - stddev).abs() < 1e-6,
+            "{} vs {}",
+            got_stddev,
+            stddev
+        );
+        assert!((baseline.zscore(1000.0) - (1000.0 - mean) / stddev).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_anomaly_rules_trip_on_tps_zscore() {
+        let rule = |id: &str, field: &str, threshold| alerts::AlertRuleV2 {
+            id: id.into(),
+            name: id.into(),
+            rule_type: alerts::RuleType::Anomaly,
+            stream: None,
+            field: field.into(),
+            threshold,
+            duration_seconds: 0,
+            severity: "warn".into(),
+            enabled: true,
+            notification_channels: vec![],
+            runbook_url: None,
+        };
+        let mut threshold_rule = rule("threshold", "tps", 1.0);
+        threshold_rule.rule_type = alerts::RuleType::Threshold;
+        let mut disabled = rule("disabled", "tps", 1.0);
+        disabled.enabled = false;
+        let rules = vec![
+            rule("loose", "tps", 2.0),
+            rule("strict", "tps", 5.0),
+            rule("other-field", "bps", 1.0),
+            threshold_rule,
+            disabled,
+        ];
+
+        let mut snapshot = snapshot_with(&[], 40.0);
+        snapshot.tps_zscore = 3.0;
+        // Still warming up: nothing to deviate from.
+        assert!(tps_anomalies(&rules, &snapshot).is_empty());
+
+        snapshot.tps_mean = Some(13.0);
+        snapshot.tps_stddev = Some(9.0);
+        let tripped = tps_anomalies(&rules, &snapshot);
+        let ids: Vec<&str> = tripped.iter().map(|(r, _)| r.id.as_str()).collect();
+        assert_eq!(ids, ["loose"]);
+        assert!(tripped[0].1.contains("3.0 standard deviations"));
+
+        // A drop is as anomalous as a spike.
+        snapshot.tps_zscore = -6.0;
+        let ids: Vec<&str> = tps_anomalies(&rules, &snapshot)
+            .iter()
+            .map(|(r, _)| r.id.as_str())
+            .collect();
+        assert_eq!(ids, ["loose", "strict"]);
+    }
+
+    #[tokio::test]
+    async fn test_status_without_journals_does_not_panic() {
+        let state = test_state();
+        let Json(status) = api_status(State(state.clone())).await;
+        assert_eq!(status.journal_path, "");
+        assert!(status.journals.is_empty());
+        assert!(api_journal_layout(State(state)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_journals_add_use_remove_lifecycle() {
+        let state = test_state();
+        let path = std::env::temp_dir().join(format!("cz-hub-hotadd-{}.db", std::process::id()));
+        std::fs::remove_file(&path).ok();
+        let app = Router::new()
+            .route(
+                "/api/journals",
+                get(api_journals_list).post(api_journals_open),
+            )
+            .route(
+                "/api/journals/:id",
+                axum::routing::delete(api_journals_remove),
+            )
+            .route("/api/ring", get(api_ring))
+            .with_state(state.clone());
+        let call = |request: Request<Body>| {
+            let app = app.clone();
+            async move {
+                let response = app.oneshot(request).await.unwrap();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                (
+                    status,
+                    serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+                )
+            }
+        };
+        let open = |readonly: bool| {
+            Request::post("/api/journals")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(
+                    serde_json::json!({
+                        "path": path,
+                        "size": MIN_JOURNAL_SIZE + 4096,
+                        "readonly": readonly,
+                    })
+                    .to_string(),
+                ))
+                .unwrap()
+        };
+
+        // A read-only journal must already exist.
+        let (status, _) = call(open(true)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, added) = call(open(false)).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let id = added["id"].as_str().unwrap().to_string();
+        assert_eq!(added["path"], path.display().to_string());
+        assert_eq!(state.connector_registry.list().await.len(), 1);
+        let (status, _) = call(open(false)).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        // Either the id or the path names it.
+        for name in [id.clone(), path.display().to_string()] {
+            let (status, ring) = call(
+                Request::get(format!("/api/ring?journal={}", name))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(ring["capacity"], DEFAULT_INDEX_RING_CAPACITY);
+        }
+        let (_, listed) = call(Request::get("/api/journals").body(Body::empty()).unwrap()).await;
+        assert_eq!(listed[0]["id"], id);
+
+        // Removal waits for an in-flight read; requests arriving meanwhile get 409.
+        let journal_state = state.get_journal(Some(id.clone())).await.unwrap();
+        let in_flight = journal_state.journal.read().await;
+        let remove = tokio::spawn(call(
+            Request::delete(format!("/api/journals/{}", id))
+                .body(Body::empty())
+                .unwrap(),
+        ));
+        while !journal_state.is_closing() {
+            tokio::task::yield_now().await;
+        }
+        let (status, _) = call(
+            Request::get(format!("/api/ring?journal={}", id))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let (status, _) = call(
+            Request::delete(format!("/api/journals/{}", id))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert!(!remove.is_finished());
+        drop(in_flight);
+        let (status, removed) = remove.await.unwrap();
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(removed["id"], id);
+
+        let (status, _) = call(
+            Request::get(format!("/api/ring?journal={}", id))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (_, listed) = call(Request::get("/api/journals").body(Body::empty()).unwrap()).await;
+        assert_eq!(listed, serde_json::json!([]));
+        assert!(state.connector_registry.list().await.is_empty());
+
+        // Reopened read-only, the hub refuses to write to it.
+        let (status, _) = call(open(true)).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let err = api_checkpoint_create(
+            State(state.clone()),
+            Json(CreateCheckpointRequest { journal: None }),
+        )
+        .await
+        .err()
+        .unwrap();
+        assert_eq!(err.0, StatusCode::CONFLICT);
+
+        drop(journal_state);
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[tokio::test]
+    async fn test_verify_job_runs_once_and_can_be_polled() {
+        let state = test_state();
+        let (release, gate) = std::sync::mpsc::channel::<()>();
+        let job = start_verify_job(&state, move || {
+            gate.recv().unwrap();
+            let output = "Checking harness verify_monotonicity...\nVERIFICATION:- SUCCESSFUL\nVerification Time: 0.5s\n";
+            VerificationReport {
+                success: true,
+                started_at: chrono::Utc::now(),
+                duration_ms: 500,
+                harnesses: verify::parse_kani_output("cz-verify", output),
+                errors: Vec::new(),
+            }
+        })
+        .await;
+        // A second request while the first is running joins it.
+        let again = start_verify_job(&state, || unreachable!()).await;
+        assert_eq!(again.id, job.id);
+
+        let app = Router::new()
+            .route("/api/verify/latest", get(api_verify_latest))
+            .route("/api/verify/jobs/:id", get(api_verify_job))
+            .with_state(state.clone());
+        let get_json = |uri: String| {
+            let app = app.clone();
+            async move {
+                let response = app
+                    .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                    .await
+                    .unwrap();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                (
+                    status,
+                    serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+                )
+            }
+        };
+
+        let job_uri = format!("/api/verify/jobs/{}", job.id);
+        let (status, body) = get_json(job_uri.clone()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "running");
+        let (status, _) = get_json("/api/verify/latest".into()).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        release.send(()).unwrap();
+        let mut body = body;
+        for _ in 0..100 {
+            body = get_json(job_uri.clone()).await.1;
+            if body["status"] == "finished" {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(body["status"], "finished");
+        assert_eq!(
+            body["report"]["harnesses"][0]["name"],
+            "verify_monotonicity"
+        );
+        assert_eq!(body["report"]["harnesses"][0]["status"], "passed");
+
+        let (status, latest) = get_json("/api/verify/latest".into()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(latest["success"], true);
+        let (status, _) = get_json("/api/verify/jobs/nope".into()).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_metrics_scrape_exposes_typed_families() {
+        let state = test_state();
+        let journal = add_journal(&state, "metrics-scrape", 5).await;
+        let connector = Arc::new(connectors::journal::JournalConnector::new(journal.clone()));
+        let id = connectors::StreamConnector::id(connector.as_ref()).to_string();
+        state.connector_registry.add(connector).await.unwrap();
+        state.metrics.query_duration.observe(0.02);
+        state
+            .metrics
+            .record_rejected(metrics::RejectReason::UnknownConnector);
+
+        let response = Router::new()
+            .route("/metrics", get(api_metrics_prometheus))
+            .with_state(state)
+            .oneshot(Request::get("/metrics").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+
+        for (name, kind) in [
+            ("cz_events_total", "counter"),
+            ("cz_bytes_total", "counter"),
+            ("cz_ring_utilization_pct", "gauge"),
+            ("cz_connector_events_total", "counter"),
+            ("cz_connector_errors_total", "counter"),
+            ("cz_connector_lagged_total", "counter"),
+            ("cz_events_rejected_total", "counter"),
+            ("cz_anomalies_total", "counter"),
+            ("cz_incidents_open", "gauge"),
+            ("cz_traces", "gauge"),
+            ("cz_traces_dropped_total", "counter"),
+            ("cz_traces_kept_total", "counter"),
+            ("cz_query_duration_seconds", "histogram"),
+        ] {
+            assert!(body.contains(&format!("# HELP {} ", name)), "{}", name);
+            assert!(
+                body.contains(&format!("# TYPE {} {}\n", name, kind)),
+                "{}",
+                name
+            );
+        }
+        assert!(body.contains(&format!(
+            "cz_connector_events_total{{connector=\"{}\",kind=\"journal\"}} 0\n",
+            id
+        )));
+        assert!(body.contains("cz_events_rejected_total{reason=\"unknown_connector\"} 1\n"));
+        assert!(body.contains("cz_incidents_open 0\n"));
+        assert!(body.contains("cz_traces 0\n"));
+        assert!(body.contains("cz_traces_dropped_total{reason=\"expired\"} 0\n"));
+        assert!(body.contains("cz_query_duration_seconds_bucket{le=\"0.025\"} 1\n"));
+        assert!(body.contains("cz_query_duration_seconds_count 1\n"));
+
+        std::fs::remove_file(&journal).ok();
+    }
+
+    #[tokio::test]
+    async fn test_overrun_connector_counts_lag_in_scrape() {
+        let state = test_state();
+        let connector = Arc::new(connectors::webhook::WebhookConnector::new(
+            "burst".into(),
+            HashMap::new(),
+        ));
+        let id = connectors::StreamConnector::id(connector.as_ref()).to_string();
+        state
+            .connector_registry
+            .add(connector.clone())
+            .await
+            .unwrap();
+
+        // The single-threaded test runtime can't run the forwarding task
+        // until we yield, so this overruns the connector's channel.
+        for i in 0..3000 {
+            connectors::StreamConnector::ingest(
+                connector.as_ref(),
+                format!("{{\"i\": {}}}", i).as_bytes(),
+                HashMap::new(),
+            )
+            .await
+            .unwrap();
+        }
+        let mut lagged = 0;
+        for _ in 0..100 {
+            tokio::task::yield_now().await;
+            lagged = state.connector_registry.list().await[0]
+                .metrics
+                .lagged_total;
+            if lagged > 0 {
+                break;
+            }
+        }
+        assert!(lagged >= 3000 - 2048, "lagged {}", lagged);
+
+        let response = api_metrics_prometheus(State(state)).await.into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains(&format!(
+            "cz_connector_lagged_total{{connector=\"{}\",kind=\"webhook\"}} {}\n",
+            id, lagged
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_readiness_checks_journal_files_and_event_progress() {
+        let mut config = Config::default();
+        config.ipc.enabled = false;
+        let mut state = test_state_with(config);
+        let path = std::env::temp_dir().join(format!("cz-hub-ready-{}.db", std::process::id()));
+        {
+            let state = Arc::get_mut(&mut state).unwrap();
+            state.configured_journals = vec![path.clone()];
+            // Past the grace period a fresh hub gets.
+            state.start_time =
+                Instant::now() - std::time::Duration::from_millis(2 * EVENT_LOOP_STALL_MS as u64);
+        }
+        let router = Router::new()
+            .route("/healthz", get(healthz))
+            .route("/readyz", get(readyz))
+            .with_state(state.clone());
+        let get = |uri: &'static str| {
+            let router = router.clone();
+            async move {
+                let response = router
+                    .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                    .await
+                    .unwrap();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                (
+                    status,
+                    serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+                )
+            }
+        };
+        let now = || chrono::Utc::now().timestamp_millis();
+        state.last_tick.store(now(), Ordering::Relaxed);
+
+        // The configured journal is not open and no event ever arrived.
+        let (status, json) = get("/readyz").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(json["components"]["journals"]["ok"], false);
+        assert_eq!(json["components"]["event_loop"]["ok"], false);
+
+        let size = MIN_JOURNAL_SIZE + 4096;
+        let journal = Journal::open(&path, size).unwrap();
+        state.journals.write().await.insert(
+            path.clone(),
+            Arc::new(JournalState::new(
+                path.clone(),
+                journal,
+                WatermarkPolicy::default(),
+            )),
+        );
+        state.last_event_advance.store(now(), Ordering::Relaxed);
+        let (status, json) = get("/readyz").await;
+        assert_eq!(status, StatusCode::OK, "{}", json);
+        assert_eq!(json["ready"], true);
+
+        // Events stopped long ago.
+        state
+            .last_event_advance
+            .store(now() - 2 * EVENT_LOOP_STALL_MS, Ordering::Relaxed);
+        let (status, json) = get("/readyz").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(json["components"]["event_loop"]["ok"], false);
+        assert_eq!(json["components"]["journals"]["ok"], true);
+        state.last_event_advance.store(now(), Ordering::Relaxed);
+
+        // The file under the mapping shrank.
+        std::fs::OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(4096)
+            .unwrap();
+        let (status, json) = get("/readyz").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(json["components"]["journals"]["ok"], false);
+        assert!(json["components"]["journals"]["detail"]
+            .as_str()
+            .unwrap()
+            .contains("4096 bytes"));
+
+        // Liveness does not care.
+        let (status, json) = get("/healthz").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json, serde_json::json!({"status": "ok"}));
+
+        state.journals.write().await.clear();
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[tokio::test]
+    async fn test_readiness_flips_when_the_sequencer_socket_dies() {
+        let state = test_state();
+        let router = Router::new()
+            .route("/healthz", get(healthz))
+            .route("/readyz", get(readyz))
+            .with_state(state.clone());
+        let readyz = || async {
+            let response = router
+                .clone()
+                .oneshot(Request::get("/readyz").body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            let status = response.status();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            (status, json)
+        };
+        let tick = || {
+            state
+                .last_tick
+                .store(chrono::Utc::now().timestamp_millis(), Ordering::Relaxed)
+        };
+
+        // Nothing mapped and the collector never ticked.
+        let (status, json) = readyz().await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(json["components"]["journals"]["ok"], false);
+        assert_eq!(json["components"]["metrics_collector"]["ok"], false);
+        assert_eq!(json["components"]["state_store"]["ok"], true);
+
+        let path = std::env::temp_dir().join(format!("cz-hub-readyz-{}.db", std::process::id()));
+        let size = MIN_JOURNAL_SIZE + 4096;
+        let address = cz_io::ipc::socket_for_journal(&path);
+        let server = cz_io::ipc::IpcServer::start(&address).unwrap();
+        Journal::open(&path, size)
+            .unwrap()
+            .publish_ipc_address(&address)
+            .unwrap();
+        assert!(state.open_journal(path.clone(), size, false).await.is_ok());
+
+        let mut ready = false;
+        for _ in 0..200 {
+            tick();
+            let (status, json) = readyz().await;
+            if status == StatusCode::OK {
+                assert_eq!(json["ready"], true);
+                ready = true;
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert!(ready, "hub never became ready");
+
+        drop(server);
+        let mut json = serde_json::Value::Null;
+        for _ in 0..200 {
+            tick();
+            let (status, body) = readyz().await;
+            if status == StatusCode::SERVICE_UNAVAILABLE {
+                json = body;
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(json["ready"], false);
+        assert_eq!(json["components"]["ipc"]["ok"], false);
+        assert_eq!(json["components"]["journals"]["ok"], true);
+        assert_eq!(json["components"]["metrics_collector"]["ok"], true);
+
+        let response = router
+            .clone()
+            .oneshot(Request::get("/healthz").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        if let Some(task) = &state.journals.read().await[&path].ipc_task {
+            task.abort();
+        }
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[tokio::test]
+    async fn test_sequencer_command_reaches_the_journals_sequencer() {
+        let state = test_state();
+        let mut sequencers = Vec::new();
+        let mut journals = Vec::new();
+        for (name, events_processed) in [("seq-a", 1), ("seq-b", 2)] {
+            let path = add_journal(&state, name, 0).await;
+            let address = cz_io::ipc::socket_for_journal(&path);
+            let server = cz_io::ipc::IpcServer::start(&address).unwrap();
+            state.journals.read().await[&path]
+                .journal
+                .write()
+                .await
+                .publish_ipc_address(&address)
+                .unwrap();
+
+            // Stand-in event loop answering a single stats query.
+            sequencers.push(std::thread::spawn(move || {
+                let pending = loop {
+                    let commands = server.take_commands();
+                    if !commands.is_empty() {
+                        break commands;
+                    }
+                    std::thread::sleep(std::time::Duration::from_millis(1));
+                };
+                for pending in pending {
+                    server.respond(
+                        pending.client,
+                        &IpcMessage::StatsSnapshot(SequencerStats {
+                            events_processed,
+                            ..Default::default()
+                        }),
+                    );
+                    server.respond(
+                        pending.client,
+                        &IpcMessage::CommandResult {
+                            id: pending.id,
+                            ok: true,
+                            detail: "stats".into(),
+                        },
+                    );
+                }
+            }));
+            journals.push((path, events_processed));
+        }
+
+        let router = Router::new()
+            .route("/api/sequencer/command", post(api_sequencer_command))
+            .with_state(state.clone());
+        for (path, events_processed) in &journals {
+            let response = router
+                .clone()
+                .oneshot(
+                    Request::post(format!("/api/sequencer/command?journal={}", path.display()))
+                        .header(header::CONTENT_TYPE, "application/json")
+                        .body(Body::from(r#"{"command":"query_stats"}"#))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(json["stats"]["events_processed"], *events_processed);
+        }
+
+        for sequencer in sequencers {
+            sequencer.join().unwrap();
+        }
+        for (path, _) in journals {
+            std::fs::remove_file(&path).ok();
+        }
+    }
+
+    #[test]
+    fn test_ws_event_message_schema() {
+        let event = |message| ipc::SequencerEvent {
+            journal: PathBuf::from("a.db"),
+            message,
+        };
+        let committed = event(IpcMessage::SlotCommitted {
+            slot: 4,
+            lamport_ts: 42,
+        });
+        let json = event_message(&committed, None).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "type": "event",
+                "data": {"journal": "a.db", "slot": 4, "lamport_ts": 42}
+            })
+        );
+        let slot_event = CausalEvent::new(42, 7, 3, 0, 0);
+        let json = event_message(&committed, Some(&slot_event)).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            (
+                value["data"]["node_id"].as_u64(),
+                value["data"]["stream_id"].as_u64()
+            ),
+            (Some(7), Some(3))
+        );
+        assert!(event_message(&event(IpcMessage::Shutdown), None).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_ndjson_export_has_one_line_per_event() {
+        let state = test_state();
+        let path = add_journal(&state, "ndjson", 7).await;
+
+        let body = export_body(&state, &path, "ndjson").await;
+        let lines: Vec<serde_json::Value> = String::from_utf8(body)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 7);
+        assert_eq!(lines[6]["lamport_ts"], 7);
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[tokio::test]
+    async fn test_streamed_export_matches_buffered_records() {
+        let state = test_state();
+        let count = 3 * EXPORT_CHUNK_EVENTS as u64 + 17;
+        let path = add_journal(&state, "stream-export", count).await;
+
+        let buffered = {
+            let primary = state.get_journal(None).await.unwrap();
+            let journal = primary.journal.read().await;
+            let snapshot = journal.live_cursor_view();
+            serde_json::to_value(export_records(
+                &journal,
+                &snapshot,
+                0,
+                count as usize,
+                false,
+            ))
+            .unwrap()
+        };
+        assert_eq!(buffered.as_array().unwrap().len(), count as usize);
+
+        let response = api_export(
+            State(state.clone()),
+            Query(ExportParams {
+                limit: Some(count as usize),
+                ..Default::default()
+            }),
+        )
+        .await
+        .into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let streamed: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(streamed, buffered);
+
+        let csv = String::from_utf8(export_body(&state, &path, "csv").await).unwrap();
+        let rows: Vec<&str> = csv.lines().skip(1).collect();
+        assert_eq!(rows.len(), count as usize, "no limit by default");
+        for (row, record) in rows.iter().zip(buffered.as_array().unwrap()) {
+            let fields: Vec<&str> = row.split(',').collect();
+            assert_eq!(fields[0], record["slot"].to_string());
+            assert_eq!(fields[1], record["lamport_ts"].to_string());
+        }
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[tokio::test]
+    async fn test_export_streams_past_the_old_limit() {
+        let state = test_state();
+        let count = 60_000;
+        let path = add_journal(&state, "big-export", count).await;
+
+        let body = export_body(&state, &path, "ndjson").await;
+        let text = String::from_utf8(body.clone()).unwrap();
+        assert_eq!(text.lines().count(), count as usize);
+        let last: serde_json::Value = serde_json::from_str(text.lines().last().unwrap()).unwrap();
+        assert_eq!(last["lamport_ts"], count);
+
+        let response = api_export(
+            State(state.clone()),
+            Query(ExportParams {
+                format: Some("ndjson".into()),
+                compress: Some("zstd".into()),
+                ..Default::default()
+            }),
+        )
+        .await;
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/zstd");
+        let compressed = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(compressed.len() < body.len() / 4);
+        assert_eq!(zstd::decode_all(&compressed[..]).unwrap(), body);
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[tokio::test]
+    async fn test_export_filters_and_resumes_from_cursor() {
+        let state = test_state();
+        let path = add_journal(&state, "resume-export", 10).await;
+        let export = |params: ExportParams| {
+            let state = state.clone();
+            async move {
+                let response = api_export(State(state), Query(params)).await;
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                let timestamps: Vec<u64> = String::from_utf8(body.to_vec())
+                    .unwrap()
+                    .lines()
+                    .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+                    .filter_map(|record| record["lamport_ts"].as_u64())
+                    .collect();
+                (status, timestamps)
+            }
+        };
+        let ndjson = |ts_min, ts_max, cursor: Option<&str>| ExportParams {
+            format: Some("ndjson".into()),
+            ts_min,
+            ts_max,
+            cursor: cursor.map(String::from),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            export(ndjson(Some(3), Some(8), None)).await,
+            (StatusCode::OK, vec![3, 4, 5, 6, 7, 8])
+        );
+        // Slot 4 holds lamport_ts 5: resume right after it.
+        assert_eq!(
+            export(ndjson(Some(3), Some(8), Some("4:5"))).await,
+            (StatusCode::OK, vec![6, 7, 8])
+        );
+        assert_eq!(
+            export(ExportParams {
+                stream_id: Some(1),
+                ..ndjson(None, None, None)
+            })
+            .await,
+            (StatusCode::OK, vec![])
+        );
+
+        assert_eq!(
+            export(ndjson(None, None, Some("4:6"))).await.0,
+            StatusCode::GONE
+        );
+        assert_eq!(
+            export(ndjson(None, None, Some("four"))).await.0,
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            export(ExportParams {
+                compress: Some("gzip".into()),
+                ..Default::default()
+            })
+            .await
+            .0,
+            StatusCode::BAD_REQUEST
+        );
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[cfg(feature = "parquet")]
+    #[tokio::test]
+    async fn test_parquet_export_reads_back() {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+        use parquet::record::RowAccessor;
+
+        let state = test_state();
+        let path = add_journal(&state, "parquet", 5).await;
+        let body = export_body(&state, &path, "parquet").await;
+        let out = path.with_extension("parquet");
+        std::fs::write(&out, body).unwrap();
+
+        let reader = SerializedFileReader::new(std::fs::File::open(&out).unwrap()).unwrap();
+        let schema = reader.metadata().file_metadata().schema_descr_ptr();
+        let columns: Vec<&str> = schema.columns().iter().map(|c| c.name()).collect();
+        assert_eq!(
+            columns,
+            [
+                "slot",
+                "lamport_ts",
+                "node_id",
+                "stream_id",
+                "payload_offset",
+                "checksum",
+                "checkpoint",
+                "payload_size",
+                "flags",
+                "checksum_valid"
+            ]
+        );
+        let rows: Vec<_> = reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(|row| row.unwrap())
+            .collect();
+        let timestamps: Vec<u64> = rows.iter().map(|row| row.get_ulong(1).unwrap()).collect();
+        assert_eq!(timestamps, vec![1, 2, 3, 4, 5]);
+        // Not verified, so `checksum_valid` is null.
+        assert!(rows.iter().all(|row| row.get_bool(9).is_err()));
+
+        std::fs::remove_file(&path).ok();
+        std::fs::remove_file(&out).ok();
+    }
+
+    fn state_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("cz-hub-state-{}-{}", name, std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        dir
+    }
+
+    #[tokio::test]
+    async fn test_dashboards_and_pipelines_survive_restart() {
+        let dir = state_dir("managers");
+        let store = Arc::new(StateStore::open(&dir).unwrap());
+        let dashboards = dashboards::DashboardManager::new().with_store(store.clone());
+        let pipelines = pipelines::PipelineManager::new().with_store(store.clone());
+        let kept = dashboards.create("Ops".into(), None).await;
+        let gone = dashboards.create("Scratch".into(), None).await;
+        dashboards.delete(&gone.id).await.unwrap();
+        let pipeline = pipelines
+            .create(pipelines::CreatePipelineRequest {
+                name: "ingest".into(),
+                description: None,
+                nodes: vec![],
+                edges: vec![],
+            })
+            .await;
+        pipelines
+            .set_status(&pipeline.id, pipelines::PipelineStatus::Running)
+            .await
+            .unwrap();
+        store.flush();
+
+        let store = Arc::new(StateStore::open(&dir).unwrap());
+        let dashboards = dashboards::DashboardManager::new().with_store(store.clone());
+        let pipelines = pipelines::PipelineManager::new().with_store(store);
+        let names: Vec<String> = dashboards
+            .list()
+            .await
+            .into_iter()
+            .map(|d| d.name)
+            .collect();
+        assert_eq!(names, ["Ops"]);
+        assert_eq!(dashboards.get(&kept.id).await.unwrap().id, kept.id);
+        let restored = pipelines.list().await;
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored[0].id, pipeline.id);
+        assert_eq!(restored[0].status, pipelines::PipelineStatus::Running);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_saved_queries_run_and_survive_restart() {
+        let dir = state_dir("saved-queries");
+        let store = Arc::new(StateStore::open(&dir).unwrap());
+        let mut state = test_state();
+        Arc::get_mut(&mut state).unwrap().saved_queries =
+            Arc::new(saved_queries::SavedQueryManager::new().with_store(store.clone()));
+        let key = create_key(&state, "analyst").await;
+
+        let config = serde_json::from_value(serde_json::json!({
+            "name": "orders",
+            "kind": "webhook",
+            "params": { "provider": "orders" },
+        }))
+        .unwrap();
+        let info = state
+            .connector_registry
+            .create_from_config(config)
+            .await
+            .unwrap();
+        let connector = state.connector_registry.get(&info.id).await.unwrap();
+        for amount in [250, 4000] {
+            let body = serde_json::json!({ "amount": amount }).to_string();
+            connector
+                .ingest(body.as_bytes(), HashMap::new())
+                .await
+                .unwrap();
+        }
+        while state.connector_registry.buffered_events().await.len() < 2 {
+            tokio::task::yield_now().await;
+        }
+
+        let app = protected(
+            &state,
+            Router::new()
+                .route(
+                    "/api/queries/saved",
+                    get(api::list_saved_queries).post(api::create_saved_query),
+                )
+                .route(
+                    "/api/queries/saved/:id",
+                    get(api::get_saved_query).delete(api::delete_saved_query),
+                )
+                .route("/api/queries/saved/:id/run", post(api::run_saved_query)),
+        );
+        let call = |method: Method, uri: String, body: Option<serde_json::Value>| {
+            let request = Request::builder()
+                .method(method)
+                .uri(uri)
+                .header(header::AUTHORIZATION, format!("Bearer {}", key.key))
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(body.map_or_else(Body::empty, |b| Body::from(b.to_string())))
+                .unwrap();
+            let app = app.clone();
+            async move {
+                let response = app.oneshot(request).await.unwrap();
+                let status = response.status();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                (
+                    status,
+                    serde_json::from_slice(&bytes).unwrap_or(serde_json::Value::Null),
+                )
+            }
+        };
+        let save = |name: &str, text: &str| {
+            call(
+                Method::POST,
+                "/api/queries/saved".into(),
+                Some(serde_json::json!({ "name": name, "query_text": text })),
+            )
+        };
+        let run = |id: &str| call(Method::POST, format!("/api/queries/saved/{}/run", id), None);
+
+        let (status, big) = save("big orders", "SELECT * FROM orders WHERE amount > 1000").await;
+        assert_eq!(status, StatusCode::OK);
+        let big_id = big["id"].as_str().unwrap().to_string();
+        assert_eq!(
+            big["query_text"],
+            "SELECT * FROM orders WHERE amount > 1000"
+        );
+        let (status, _) = save("broken", "SELECT * FROM orders WHERE amount").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, result) = run(&big_id).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(result["total"], 1);
+        assert_eq!(result["events"][0]["payload"]["amount"], 4000);
+
+        // A stream that does not exist matches nothing; it is not an error.
+        let (_, ghost) = save("ghost", "SELECT * FROM no_such_stream").await;
+        let ghost_id = ghost["id"].as_str().unwrap().to_string();
+        let (status, result) = run(&ghost_id).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(result["total"], 0);
+
+        let (status, _) = call(
+            Method::DELETE,
+            format!("/api/queries/saved/{}", ghost_id),
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        assert_eq!(run(&ghost_id).await.0, StatusCode::NOT_FOUND);
+        let (_, listed) = call(Method::GET, "/api/queries/saved".into(), None).await;
+        assert_eq!(listed.as_array().unwrap().len(), 1);
+
+        store.flush();
+        let restored = saved_queries::SavedQueryManager::new()
+            .with_store(Arc::new(StateStore::open(&dir).unwrap()));
+        let restored = restored.list().await;
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored[0].id, big_id);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_alert_rules_survive_restart() {
+        let dir = state_dir("alerts");
+        let store = Arc::new(StateStore::open(&dir).unwrap());
+        let engine = alerts::AlertEngine::new(10).with_store(store.clone());
+        engine
+            .add_rule(alerts::AlertRuleV2 {
+                id: "rule-1".into(),
+                name: "Lag".into(),
+                rule_type: alerts::RuleType::Threshold,
+                stream: None,
+                field: "lag".into(),
+                threshold: 5.0,
+                duration_seconds: 30,
+                severity: "warn".into(),
+                enabled: true,
+                notification_channels: vec![],
+                runbook_url: None,
+            })
+            .await;
+        store.flush();
+
+        let engine =
+            alerts::AlertEngine::new(10).with_store(Arc::new(StateStore::open(&dir).unwrap()));
+        let rules = engine.rules.read().await;
+        assert_eq!(rules.len(), 1);
+        assert_eq!((rules[0].id.as_str(), rules[0].threshold), ("rule-1", 5.0));
+        drop(rules);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_api_keys_survive_restart_as_hashes_only() {
+        let dir = state_dir("auth");
+        let store = Arc::new(StateStore::open(&dir).unwrap());
+        let auth =
+            auth::AuthLayer::new(10, auth::DEFAULT_REQUESTS_PER_MINUTE).with_store(store.clone());
+        let request = || auth::CreateApiKeyRequest {
+            label: "ci".into(),
+            scopes: vec![auth::Scope::Read],
+            resource_scopes: Vec::new(),
+            requests_per_minute: None,
+            expires_at: None,
+            tls_subject: None,
+        };
+        let kept = auth.create_key(request(), "test").await.unwrap();
+        let revoked = auth.create_key(request(), "test").await.unwrap();
+        auth.revoke_key(&revoked.api_key.id, "test").await.unwrap();
+        store.flush();
+
+        let saved = std::fs::read_to_string(dir.join("api_keys.json")).unwrap();
+        assert!(!saved.contains(&kept.key));
+        assert!(saved.contains(&kept.api_key.key_hash));
+
+        let auth = auth::AuthLayer::new(10, auth::DEFAULT_REQUESTS_PER_MINUTE)
+            .with_store(Arc::new(StateStore::open(&dir).unwrap()));
+        assert_eq!(auth.list_keys().await.len(), 2);
+        assert!(auth.validate_token(&kept.key).await.is_some());
+        assert!(auth.validate_token(&revoked.key).await.is_none());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_connectors_are_recreated_after_restart() {
+        let dir = state_dir("connectors");
+        let store = Arc::new(StateStore::open(&dir).unwrap());
+        let registry = connectors::registry::ConnectorRegistry::new(10).with_store(store.clone());
+        let config: connectors::ConnectorConfig = serde_json::from_value(serde_json::json!({
+            "name": "hooks",
+            "kind": "webhook",
+            "params": { "path": "/ingest" },
+        }))
+        .unwrap();
+        let kept = registry.create_from_config(config.clone()).await.unwrap();
+        let gone = registry.create_from_config(config).await.unwrap();
+        registry.remove(&gone.id).await.unwrap();
+        store.flush();
+
+        let registry = connectors::registry::ConnectorRegistry::new(10)
+            .with_store(Arc::new(StateStore::open(&dir).unwrap()));
+        assert_eq!(registry.restore().await, 1);
+        let restored = registry.list().await;
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored[0].id, kept.id);
+        assert_eq!(restored[0].name, "hooks");
+        assert_eq!(restored[0].config["path"], "/ingest");
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_webhook_rejects_forged_signatures() {
+        let state = test_state();
+        let secret = "s3cret";
+        let mut ids = HashMap::new();
+        for provider in ["github", "stripe"] {
+            let config = serde_json::from_value(serde_json::json!({
+                "name": provider,
+                "kind": "webhook",
+                "params": { "provider": provider, "secret": secret },
+            }))
+            .unwrap();
+            let info = state
+                .connector_registry
+                .create_from_config(config)
+                .await
+                .unwrap();
+            assert_eq!(info.config["secret"], "********");
+            ids.insert(provider, info.id);
+        }
+        let unsigned = serde_json::from_value(serde_json::json!({
+            "name": "generic",
+            "kind": "webhook",
+            "params": { "secret": secret },
+        }))
+        .unwrap();
+        assert!(state
+            .connector_registry
+            .create_from_config(unsigned)
+            .await
+            .is_err());
+
+        // Deliveries carry no API key: the signature authenticates them.
+        let app = router(state.clone(), PathBuf::from("/nonexistent/ui/dist"));
+        let deliver = |id: &str, body: &'static str, signature: (&str, String)| {
+            app.clone().oneshot(
+                Request::post(format!("/api/connectors/{}/ingest", id))
+                    .header(signature.0, signature.1)
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+        };
+        let sign = |message: &[u8]| {
+            use hmac::Mac;
+            let mut mac = hmac::Hmac::<sha2::Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+            mac.update(message);
+            hex::encode(mac.finalize().into_bytes())
+        };
+
+        let body = r#"{"action":"opened"}"#;
+        let github = (
+            "x-hub-signature-256",
+            format!("sha256={}", sign(body.as_bytes())),
+        );
+        let response = deliver(&ids["github"], body, github.clone()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let response = deliver(&ids["github"], r#"{"action":"closed"}"#, github)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let body = r#"{"id":"evt_1","type":"charge.succeeded"}"#;
+        let t = chrono::Utc::now().timestamp();
+        let stripe = (
+            "stripe-signature",
+            format!("t={},v1={}", t, sign(format!("{}.{}", t, body).as_bytes())),
+        );
+        let response = deliver(&ids["stripe"], body, stripe.clone()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let response = deliver(
+            &ids["stripe"],
+            r#"{"id":"evt_1","type":"charge.refunded"}"#,
+            stripe,
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        for id in ids.values() {
+            let metrics = state.connector_registry.get(id).await.unwrap().metrics();
+            assert_eq!((metrics.events_total, metrics.errors_total), (1, 1));
+        }
+        assert_eq!(
+            state
+                .metrics
+                .rejected(metrics::RejectReason::InvalidSignature),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unsigned_webhook_still_needs_an_api_key() {
+        let state = test_state();
+        let key = create_key(&state, "hooks").await;
+        let config = serde_json::from_value(serde_json::json!({
+            "name": "hooks",
+            "kind": "webhook",
+            "params": {},
+        }))
+        .unwrap();
+        let info = state
+            .connector_registry
+            .create_from_config(config)
+            .await
+            .unwrap();
+
+        let app = router(state.clone(), PathBuf::from("/nonexistent/ui/dist"));
+        let deliver = |bearer: Option<&str>| {
+            let mut request = Request::post(format!("/api/connectors/{}/ingest", info.id));
+            if let Some(bearer) = bearer {
+                request = request.header(header::AUTHORIZATION, format!("Bearer {}", bearer));
+            }
+            app.clone()
+                .oneshot(request.body(Body::from(r#"{"ok":true}"#)).unwrap())
+        };
+        let response = deliver(None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = deliver(Some(&key.key)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+    }
+
+    #[tokio::test]
+    async fn test_webhook_keeps_undecodable_body_as_hex() {
+        let state = test_state();
+        let key = create_key(&state, "hooks").await;
+        let config = |format: &str| -> connectors::ConnectorConfig {
+            serde_json::from_value(serde_json::json!({
+                "name": "hooks",
+                "kind": "webhook",
+                "params": { "format": format },
+            }))
+            .unwrap()
+        };
+        assert!(state
+            .connector_registry
+            .create_from_config(config("xml"))
+            .await
+            .is_err());
+        let info = state
+            .connector_registry
+            .create_from_config(config("json"))
+            .await
+            .unwrap();
+        let mut events = state
+            .connector_registry
+            .get(&info.id)
+            .await
+            .unwrap()
+            .subscribe();
+
+        let app = protected(
+            &state,
+            Router::new().route("/api/connectors/:id/ingest", post(api::ingest_webhook)),
+        );
+        let response = app
+            .oneshot(
+                Request::post(format!("/api/connectors/{}/ingest", info.id))
+                    .header(header::AUTHORIZATION, format!("Bearer {}", key.key))
+                    .body(Body::from(&b"\xff{"[..]))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+        let event = events.recv().await.unwrap();
+        assert_eq!(event.payload["raw_hex"], "ff7b");
+        assert!(event.payload["decode_error"].is_string());
+        let listed = state.connector_registry.list().await;
+        assert_eq!(listed[0].metrics.errors_total, 1);
+        assert_eq!(listed[0].metrics.events_total, 1);
+    }
+
+    #[tokio::test]
+    async fn test_config_reload_shrinks_history_capacity() {
+        let path = std::env::temp_dir().join(format!("cz-hub-reload-{}.toml", std::process::id()));
+        let mut state = test_state();
+        Arc::get_mut(&mut state).unwrap().config_path = path.clone();
+        {
+            let mut history = state.metrics_history.write().await;
+            for tps in 0..10 {
+                history.push_back(snapshot_with(&[], tps as f64));
+            }
+        }
+        let router = Router::new()
+            .route("/api/config", get(api_config_get))
+            .route("/api/config/reload", post(api_config_reload))
+            .with_state(state.clone());
+        let reload = || async {
+            let response = router
+                .clone()
+                .oneshot(
+                    Request::post("/api/config/reload")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            let status = response.status();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            (
+                status,
+                serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+            )
+        };
+
+        std::fs::write(
+            &path,
+            "[server]\nhistory_capacity = 4\n[alerts]\nring_utilization_critical = 95.0\n[ipc]\nenabled = false\n",
+        )
+        .unwrap();
+        let (status, json) = reload().await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            json["changed"],
+            serde_json::json!(["alerts", "server.history_capacity"])
+        );
+        assert_eq!(json["ignored"].as_array().unwrap().len(), 1);
+
+        let tps: Vec<f64> = state
+            .metrics_history
+            .read()
+            .await
+            .iter()
+            .map(|s| s.tps)
+            .collect();
+        assert_eq!(tps, [6.0, 7.0, 8.0, 9.0]);
+        let config = state.config();
+        assert_eq!(config.server.history_capacity, 4);
+        assert!(config.ipc.enabled);
+
+        let response = router
+            .clone()
+            .oneshot(Request::get("/api/config").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["config"]["server"]["history_capacity"], 4);
+        assert_eq!(json["config"]["alerts"]["ring_utilization_critical"], 95.0);
+        assert!(json["source_modified_at"].is_string());
+
+        // An invalid file is refused and the active config kept.
+        std::fs::write(&path, "[server]\nhistory_capacity = 0\n").unwrap();
+        let (status, json) = reload().await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(json["error"].as_str().unwrap().contains("history_capacity"));
+        assert_eq!(state.config().server.history_capacity, 4);
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_config_errors_name_the_setting() {
+        let path = std::env::temp_dir().join(format!("cz-hub-config-{}.toml", std::process::id()));
+        let load = |content: &str| {
+            std::fs::write(&path, content).unwrap();
+            Config::load(&path).and_then(|c| c.validate().map(|()| c))
+        };
+
+        let config = load("[alerts]\nring_utilization_warn = 60.0\n")
+            .ok()
+            .unwrap();
+        assert_eq!(config.alerts.ring_utilization_warn, 60.0);
+
+        // Malformed: the line and column of the fault.
+        let err = load("[server]\nmetrics_interval_ms = 200\nhistory_capacity = \"lots\"\n")
+            .err()
+            .unwrap();
+        assert!(err.contains("line 3"), "{}", err);
+        assert!(err.contains("history_capacity"), "{}", err);
+        let err = load("[alerts\nring_utilization_warn = 60.0\n")
+            .err()
+            .unwrap();
+        assert!(err.contains("line 1"), "{}", err);
+
+        // A misspelt key is refused rather than left at its default.
+        for (content, key) in [
+            (
+                "[alerts]\nring_utilization_wrn = 60.0\n",
+                "ring_utilization_wrn",
+            ),
+            ("[limit]\nip_requests_per_minute = 5\n", "limit"),
+            ("[limits]\nip_requests_per_min = 5\n", "ip_requests_per_min"),
+        ] {
+            let err = load(content).err().unwrap();
+            assert!(err.contains(&format!("unknown field `{}`", key)), "{}", err);
+        }
+
+        // Each value is in range, but not together.
+        let err =
+            load("[alerts]\nring_utilization_warn = 95.0\nring_utilization_critical = 90.0\n")
+                .err()
+                .unwrap();
+        assert_eq!(
+            err,
+            "alerts.ring_utilization_warn must be below ring_utilization_critical"
+        );
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[tokio::test]
+    async fn test_connector_without_recent_events_reports_degraded() {
+        let state = test_state();
+        let key = create_key(&state, "ops").await;
+        let connector = Arc::new(connectors::webhook::WebhookConnector::new(
+            "quiet".into(),
+            HashMap::new(),
+        ));
+        let id = connectors::StreamConnector::id(connector.as_ref()).to_string();
+        connectors::StreamConnector::start(connector.as_ref())
+            .await
+            .unwrap();
+        state.connector_registry.add(connector).await.unwrap();
+
+        let app = protected(
+            &state,
+            Router::new().route("/api/connectors/:id/health", get(api::connector_health)),
+        );
+        let health = |id: &str| {
+            Request::get(format!("/api/connectors/{}/health", id))
+                .header(header::AUTHORIZATION, format!("Bearer {}", key.key))
+                .body(Body::empty())
+                .unwrap()
+        };
+        let response = app.clone().oneshot(health(&id)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let report: connectors::HealthReport = serde_json::from_slice(&body).unwrap();
+        assert_eq!(report.health, connectors::Health::Degraded);
+        assert!(report.live);
+        assert_eq!(report.last_event_at, None);
+        assert_eq!(report.reasons, ["no events received yet"]);
+
+        let response = app.oneshot(health("webhook-missing")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[cfg(feature = "tls")]
+    mod tls_tests {
+        use super::*;
+        use rcgen::{BasicConstraints, Certificate, CertificateParams, DnType, IsCa};
+        use tokio_rustls::rustls;
+
+        fn cert(cn: &str, is_ca: bool) -> Certificate {
+            let mut params = CertificateParams::new(vec![cn.to_string()]);
+            params.distinguished_name.push(DnType::CommonName, cn);
+            if is_ca {
+                params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            }
+            Certificate::from_params(params).unwrap()
+        }
+
+        fn write(dir: &std::path::Path, name: &str, pem: String) -> PathBuf {
+            let path = dir.join(name);
+            std::fs::write(&path, pem).unwrap();
+            path
+        }
+
+        fn pem_certs(pem: &str) -> Vec<rustls::Certificate> {
+            rustls_pemfile::certs(&mut pem.as_bytes())
+                .unwrap()
+                .into_iter()
+                .map(rustls::Certificate)
+                .collect()
+        }
+
+        async fn serve_tls(state: Arc<AppState>, config: &tls::TlsConfig) -> SocketAddr {
+            let acceptor = Arc::new(tls::TlsAcceptorHandle::new(config).unwrap());
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(tls::serve(listener, acceptor, test_router(state)));
+            addr
+        }
+
+        /// GET /api/pipelines over TLS, presenting `identity` if given.
+        async fn get(
+            addr: SocketAddr,
+            server_pem: &str,
+            identity: Option<(&str, rustls::PrivateKey)>,
+            bearer: Option<&str>,
+        ) -> Result<StatusCode, String> {
+            let mut roots = rustls::RootCertStore::empty();
+            for cert in pem_certs(server_pem) {
+                roots.add(&cert).unwrap();
+            }
+            let builder = rustls::ClientConfig::builder()
+                .with_safe_defaults()
+                .with_root_certificates(roots);
+            let config = match identity {
+                Some((chain, key)) => builder
+                    .with_client_auth_cert(pem_certs(chain), key)
+                    .unwrap(),
+                None => builder.with_no_client_auth(),
+            };
+            let stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+            let stream = tokio_rustls::TlsConnector::from(Arc::new(config))
+                .connect("localhost".try_into().unwrap(), stream)
+                .await
+                .map_err(|e| e.to_string())?;
+            let (mut sender, connection) =
+                hyper::client::conn::http1::handshake(hyper_util::rt::TokioIo::new(stream))
+                    .await
+                    .map_err(|e| e.to_string())?;
+            tokio::spawn(connection);
+            let mut request = Request::get("/api/pipelines").header(header::HOST, "localhost");
+            if let Some(token) = bearer {
+                request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+            }
+            let response = sender
+                .send_request(request.body(Body::empty()).unwrap())
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(response.status())
+        }
+
+        #[tokio::test]
+        async fn test_tls_and_client_certificates() {
+            let dir = std::env::temp_dir().join(format!("cz-hub-tls-{}", std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            let server = cert("localhost", false);
+            let server_pem = server.serialize_pem().unwrap();
+            let ca = cert("cz-hub test CA", true);
+            let trusted = cert("ci-runner", false);
+            let trusted_pem = trusted.serialize_pem_with_signer(&ca).unwrap();
+            let rogue = cert("ci-runner", false);
+            let rogue_pem = rogue.serialize_pem().unwrap();
+            let key = |c: &Certificate| rustls::PrivateKey(c.serialize_private_key_der());
+
+            let mut config = tls::TlsConfig {
+                cert_path: write(&dir, "server.pem", server_pem.clone()),
+                key_path: write(&dir, "server.key", server.serialize_private_key_pem()),
+                client_ca_path: None,
+                require_client_cert: false,
+            };
+            let state = test_state();
+            let api_key = state
+                .auth_layer
+                .create_key(
+                    auth::CreateApiKeyRequest {
+                        label: "ci".into(),
+                        scopes: vec![auth::Scope::Read],
+                        resource_scopes: Vec::new(),
+                        requests_per_minute: None,
+                        expires_at: None,
+                        tls_subject: Some("ci-runner".into()),
+                    },
+                    "test",
+                )
+                .await
+                .unwrap();
+
+            // Server-only TLS: the bearer token still decides.
+            let addr = serve_tls(state.clone(), &config).await;
+            assert_eq!(
+                get(addr, &server_pem, None, Some(&api_key.key)).await,
+                Ok(StatusCode::OK)
+            );
+            assert_eq!(
+                get(addr, &server_pem, None, None).await,
+                Ok(StatusCode::UNAUTHORIZED)
+            );
+
+            // mTLS: a certificate from the CA authenticates as the key its
+            // CN is bound to; one from elsewhere is refused at the handshake.
+            config.client_ca_path = Some(write(&dir, "ca.pem", ca.serialize_pem().unwrap()));
+            let addr = serve_tls(state.clone(), &config).await;
+            assert_eq!(
+                get(addr, &server_pem, Some((&trusted_pem, key(&trusted))), None).await,
+                Ok(StatusCode::OK)
+            );
+            assert!(
+                get(addr, &server_pem, Some((&rogue_pem, key(&rogue))), None)
+                    .await
+                    .is_err()
+            );
+            assert_eq!(
+                get(addr, &server_pem, None, Some(&api_key.key)).await,
+                Ok(StatusCode::OK)
+            );
+
+            config.require_client_cert = true;
+            let addr = serve_tls(state.clone(), &config).await;
+            assert!(get(addr, &server_pem, None, Some(&api_key.key))
+                .await
+                .is_err());
+
+            std::fs::remove_dir_all(&dir).ok();
+        }
+    }
 }
@@ -0,0 +1,170 @@
+//! # Prometheus Exposition
+//!
+//! Counters and histograms the hub records about itself, plus helpers for
+//! writing metric families in the Prometheus text format served at
+//! `/metrics`.
+
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use cz_hub::anomaly::AnomalyKind;
+
+/// Upper bounds (seconds) of the query latency histogram buckets.
+pub const QUERY_DURATION_BUCKETS: [f64; 10] =
+    [0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 5.0];
+
+/// Why an event was turned away before reaching a connector's stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectReason {
+    /// Ingest addressed a connector that does not exist.
+    UnknownConnector,
+    /// The connector refused the payload.
+    InvalidPayload,
+    /// A webhook delivery without a valid signature.
+    InvalidSignature,
+}
+
+impl RejectReason {
+    pub const ALL: [RejectReason; 3] = [
+        Self::UnknownConnector,
+        Self::InvalidPayload,
+        Self::InvalidSignature,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::UnknownConnector => "unknown_connector",
+            Self::InvalidPayload => "invalid_payload",
+            Self::InvalidSignature => "invalid_signature",
+        }
+    }
+}
+
+/// Fixed-bucket histogram safe to update from any task.
+pub struct Histogram {
+    bounds: &'static [f64],
+    /// Per-bucket (non-cumulative) counts; the last entry is `+Inf`.
+    buckets: Vec<AtomicU64>,
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+}
+
+impl Histogram {
+    pub fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            buckets: (0..=bounds.len()).map(|_| AtomicU64::new(0)).collect(),
+            count: AtomicU64::new(0),
+            sum_micros: AtomicU64::new(0),
+        }
+    }
+
+    pub fn observe(&self, seconds: f64) {
+        let bucket = self
+            .bounds
+            .iter()
+            .position(|&bound| seconds <= bound)
+            .unwrap_or(self.bounds.len());
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros
+            .fetch_add((seconds * 1e6) as u64, Ordering::Relaxed);
+    }
+
+    /// Write the `_bucket`, `_sum`, and `_count` series for `name`.
+    pub fn render(&self, out: &mut String, name: &str, help: &str) {
+        family(out, name, help, "histogram");
+        let mut cumulative = 0;
+        for (i, bucket) in self.buckets.iter().enumerate() {
+            cumulative += bucket.load(Ordering::Relaxed);
+            let le = self
+                .bounds
+                .get(i)
+                .map_or_else(|| "+Inf".to_string(), |bound| bound.to_string());
+            let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, le, cumulative);
+        }
+        let sum = self.sum_micros.load(Ordering::Relaxed) as f64 / 1e6;
+        let _ = writeln!(out, "{}_sum {}", name, sum);
+        let _ = writeln!(out, "{}_count {}", name, self.count.load(Ordering::Relaxed));
+    }
+}
+
+/// Metrics the hub records as requests happen (as opposed to the gauges
+/// sampled from state at scrape time).
+pub struct HubMetrics {
+    pub query_duration: Histogram,
+    rejected: [AtomicU64; RejectReason::ALL.len()],
+    /// Found by the metrics collector's incremental scan only, so an event
+    /// is counted once however often `/api/anomalies` reports it.
+    anomalies: [AtomicU64; AnomalyKind::ALL.len()],
+}
+
+impl Default for HubMetrics {
+    fn default() -> Self {
+        Self {
+            query_duration: Histogram::new(&QUERY_DURATION_BUCKETS),
+            rejected: Default::default(),
+            anomalies: Default::default(),
+        }
+    }
+}
+
+impl HubMetrics {
+    pub fn record_rejected(&self, reason: RejectReason) {
+        self.rejected[reason as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn rejected(&self, reason: RejectReason) -> u64 {
+        self.rejected[reason as usize].load(Ordering::Relaxed)
+    }
+
+    pub fn record_anomaly(&self, kind: AnomalyKind) {
+        self.anomalies[kind as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn anomalies(&self, kind: AnomalyKind) -> u64 {
+        self.anomalies[kind as usize].load(Ordering::Relaxed)
+    }
+}
+
+/// Write the `# HELP` and `# TYPE` lines that open a metric family.
+pub fn family(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// Escape a label value per the exposition format.
+pub fn label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram_buckets_are_cumulative() {
+        let histogram = Histogram::new(&[0.01, 0.1]);
+        for seconds in [0.005, 0.05, 0.05, 2.0] {
+            histogram.observe(seconds);
+        }
+        let mut out = String::new();
+        histogram.render(&mut out, "q", "Query time");
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "# HELP q Query time",
+                "# TYPE q histogram",
+                "q_bucket{le=\"0.01\"} 1",
+                "q_bucket{le=\"0.1\"} 3",
+                "q_bucket{le=\"+Inf\"} 4",
+                "q_sum 2.105",
+                "q_count 4",
+            ]
+        );
+    }
+}
@@ -0,0 +1,99 @@
+//! # Payloads
+//!
+//! Reads an event's packet back out of blob storage. The sequencer stores
+//! each packet as it arrived: the sender's 32-byte header, then the
+//! payload. A payload sent with `FLAG_COMPRESSED` is a
+//! [`cz_core::compress`] frame; readers here get it back decompressed, so
+//! the flag is invisible past this module.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use cz_core::compress::{self, DecompressError};
+use cz_core::CausalEvent;
+
+/// Largest decompressed payload the hub will allocate for. The frame
+/// header is read from storage, so its length is not trusted blindly.
+pub const MAX_DECOMPRESSED_LEN: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PayloadError {
+    /// The frame header claims more than [`MAX_DECOMPRESSED_LEN`] bytes.
+    TooLarge(usize),
+    Decompress(DecompressError),
+}
+
+impl fmt::Display for PayloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PayloadError::TooLarge(len) => write!(
+                f,
+                "compressed payload claims {} bytes, more than the {} allowed",
+                len, MAX_DECOMPRESSED_LEN
+            ),
+            PayloadError::Decompress(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for PayloadError {}
+
+/// Decompress the [`cz_core::compress`] frame at the start of `frame`.
+pub fn decompress(frame: &[u8]) -> Result<Vec<u8>, PayloadError> {
+    let header =
+        compress::read_header(frame).ok_or(PayloadError::Decompress(DecompressError::Truncated))?;
+    if header.decompressed_len > MAX_DECOMPRESSED_LEN {
+        return Err(PayloadError::TooLarge(header.decompressed_len));
+    }
+    let mut out = vec![0u8; header.decompressed_len];
+    compress::decompress(frame, &mut out).map_err(PayloadError::Decompress)?;
+    Ok(out)
+}
+
+/// `event`'s stored packet, exactly as `Journal::read_payload` returns
+/// it, with a compressed payload expanded as if it had been sent plain.
+pub fn decode_packet<'a>(
+    stored: &'a [u8],
+    event: &CausalEvent,
+) -> Result<Cow<'a, [u8]>, PayloadError> {
+    if !event.is_compressed() {
+        return Ok(Cow::Borrowed(stored));
+    }
+
+    let header_len = CausalEvent::size_bytes().min(stored.len());
+    let mut packet = stored[..header_len].to_vec();
+    packet.extend(decompress(&stored[header_len..])?);
+    Ok(Cow::Owned(packet))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compressed_packet_decodes_to_the_plain_packet() {
+        let payload = br#"{"user":"ada","action":"login"},{"user":"ada","action":"logout"}"#;
+        let mut frame = vec![0u8; compress::max_compressed_len(payload.len())];
+        let frame_len = compress::compress(payload, &mut frame).unwrap();
+        frame.truncate(frame_len);
+
+        // The packet as the sequencer stores it: the sender's header, then
+        // the frame.
+        let header = CausalEvent::new(0, 7, 1, 0, 0x5EED).compressed();
+        let mut stored = header.to_bytes().to_vec();
+        stored.extend(&frame);
+
+        let event = CausalEvent::with_flags(1, 7, 1, 40, header.checksum, header.flags);
+        let packet = decode_packet(&stored, &event).unwrap();
+        assert_eq!(&packet[..32], &header.to_bytes());
+        assert_eq!(&packet[32..], payload);
+
+        // Without the flag the stored bytes come back as they are.
+        let plain = CausalEvent::new(1, 7, 1, 40, header.checksum);
+        assert_eq!(decode_packet(&stored, &plain).unwrap(), &stored[..]);
+
+        // A corrupt frame is an error, not garbage.
+        stored[32] ^= 0xFF;
+        assert!(decode_packet(&stored, &event).is_err());
+    }
+}
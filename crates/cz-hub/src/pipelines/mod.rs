@@ -3,9 +3,14 @@
 //! Visual pipeline builder backend: define filter → join → aggregate chains
 //! that process events from one or more connectors in real-time.
 
+use cz_hub::store::StateStore;
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 use tokio::sync::RwLock;
 
+/// Collection the pipelines are saved under.
+const STORE_KEY: &str = "pipelines";
+
 /// Pipeline status.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
@@ -58,7 +63,8 @@ pub enum PipelineNodeType {
     Join,
     /// Aggregate: rolling window statistics
     Aggregate,
-    /// Sink: writes to a connector or output
+    /// Sink: writes to the connector named by `config.connector_id` (see
+    /// `ConnectorRegistry::produce`)
     Sink,
 }
 
@@ -87,12 +93,27 @@ pub struct UpdatePipelineRequest {
 /// The pipeline manager.
 pub struct PipelineManager {
     pub pipelines: RwLock<Vec<Pipeline>>,
+    store: Option<Arc<StateStore>>,
 }
 
 impl PipelineManager {
     pub fn new() -> Self {
         Self {
             pipelines: RwLock::new(Vec::new()),
+            store: None,
+        }
+    }
+
+    /// Start from the pipelines saved in `store` and save every change.
+    pub fn with_store(mut self, store: Arc<StateStore>) -> Self {
+        self.pipelines = RwLock::new(store.load(STORE_KEY).unwrap_or_default());
+        self.store = Some(store);
+        self
+    }
+
+    fn persist(&self, pipelines: &[Pipeline]) {
+        if let Some(store) = &self.store {
+            store.save(STORE_KEY, pipelines);
         }
     }
 
@@ -111,6 +132,7 @@ impl PipelineManager {
 
         let mut pipelines = self.pipelines.write().await;
         pipelines.push(pipeline.clone());
+        self.persist(&pipelines);
         pipeline
     }
 
@@ -134,6 +156,7 @@ impl PipelineManager {
             .position(|p| p.id == id)
             .ok_or_else(|| format!("Pipeline '{}' not found", id))?;
         pipelines.remove(idx);
+        self.persist(&pipelines);
         Ok(())
     }
 
@@ -144,7 +167,9 @@ impl PipelineManager {
             .find(|p| p.id == id)
             .ok_or_else(|| format!("Pipeline '{}' not found", id))?;
         pipeline.status = status;
-        Ok(pipeline.clone())
+        let updated = pipeline.clone();
+        self.persist(&pipelines);
+        Ok(updated)
     }
 
     pub async fn update_graph(
@@ -160,6 +185,8 @@ impl PipelineManager {
             .ok_or_else(|| format!("Pipeline '{}' not found", id))?;
         pipeline.nodes = nodes;
         pipeline.edges = edges;
-        Ok(pipeline.clone())
+        let updated = pipeline.clone();
+        self.persist(&pipelines);
+        Ok(updated)
     }
 }
@@ -0,0 +1,455 @@
+//! # Query Result Cache
+//!
+//! Dashboards re-run the same queries every few seconds. [`QueryCache`]
+//! keeps recent [`QueryResult`]s, keyed by the caller, the structured
+//! query (so spelling and spacing of the text do not matter) and the
+//! current minute, and serves them again until one of:
+//!
+//! - the TTL runs out,
+//! - an event of a source the query reads enters or leaves the registry
+//!   buffer, or a new source appears (see [`SourceVersions`]), or
+//! - a relative `SINCE`/`UNTIL` window moves past an event in the buffer,
+//!   which changes the result without any new event arriving.
+//!
+//! When full, the least recently used entry is evicted. Queries that read
+//! a journal are never cached: its ring grows without the registry noticing.
+//!
+//! Every query run through the cache is counted in its [`QueryStats`].
+
+use super::executor::{self, JournalSource};
+use super::{Query, QueryResult};
+use crate::connectors::registry::{ConnectorRegistry, SourceVersions};
+use chrono::Utc;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Results kept by [`QueryCache::default`].
+pub const DEFAULT_CAPACITY: usize = 64;
+/// How long [`QueryCache::default`] serves a result for.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    /// Who ran the query; results are filtered by what they may see.
+    scope: String,
+    minute: i64,
+    query: String,
+}
+
+struct Entry {
+    result: QueryResult,
+    /// Versions of the sources the result was computed from.
+    versions: SourceVersions,
+    expires: Instant,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct CacheState {
+    /// Bumped on every lookup and insert, for LRU ordering.
+    tick: u64,
+    entries: HashMap<CacheKey, Entry>,
+}
+
+/// Totals over the queries run through a [`QueryCache`], as served at
+/// `GET /api/query/stats`. A cache hit scans nothing, so it adds no rows.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct QueryStats {
+    pub queries: u64,
+    pub cache_hits: u64,
+    /// Queries refused as invalid or too large.
+    pub errors: u64,
+    pub rows_scanned: u64,
+    pub rows_matched: u64,
+    /// Mean time per query, cache hits included.
+    pub avg_query_time_ms: f64,
+    /// Results cached right now, including expired ones not yet evicted.
+    pub cached_results: usize,
+    #[serde(skip)]
+    total_time_ms: u64,
+}
+
+pub struct QueryCache {
+    capacity: usize,
+    ttl: Duration,
+    state: Mutex<CacheState>,
+    stats: Mutex<QueryStats>,
+}
+
+impl Default for QueryCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY, DEFAULT_TTL)
+    }
+}
+
+impl QueryCache {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            capacity,
+            ttl,
+            state: Mutex::new(CacheState::default()),
+            stats: Mutex::new(QueryStats::default()),
+        }
+    }
+
+    /// Totals over every query run so far.
+    pub fn stats(&self) -> QueryStats {
+        let mut stats = self.stats.lock().unwrap().clone();
+        if stats.queries > 0 {
+            stats.avg_query_time_ms = stats.total_time_ms as f64 / stats.queries as f64;
+        }
+        stats.cached_results = self.len();
+        stats
+    }
+
+    fn record(&self, result: &Result<QueryResult, String>) {
+        let mut stats = self.stats.lock().unwrap();
+        stats.queries += 1;
+        match result {
+            Ok(result) if result.cache_hit => stats.cache_hits += 1,
+            Ok(result) => {
+                stats.rows_scanned += result.rows_scanned as u64;
+                stats.rows_matched += result.rows_matched as u64;
+            }
+            Err(_) => stats.errors += 1,
+        }
+        if let Ok(result) = result {
+            stats.total_time_ms += result.query_time_ms;
+        }
+    }
+
+    /// Number of cached results, including expired ones not yet evicted.
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Like [`executor::execute`], but served from the cache when possible.
+    /// `scope` identifies the caller: results are only shared between
+    /// callers that pass the same `scope` and `stream_allowed`. Errors are
+    /// not cached.
+    pub async fn execute(
+        &self,
+        query: &Query,
+        registry: &Arc<ConnectorRegistry>,
+        journals: &[JournalSource<'_>],
+        scope: &str,
+        stream_allowed: impl Fn(&str) -> bool,
+    ) -> Result<QueryResult, String> {
+        let result = self
+            .execute_cached(query, registry, journals, scope, stream_allowed)
+            .await;
+        self.record(&result);
+        result
+    }
+
+    async fn execute_cached(
+        &self,
+        query: &Query,
+        registry: &Arc<ConnectorRegistry>,
+        journals: &[JournalSource<'_>],
+        scope: &str,
+        stream_allowed: impl Fn(&str) -> bool,
+    ) -> Result<QueryResult, String> {
+        let start = Instant::now();
+        if executor::reads_journals(query, journals) {
+            return executor::execute(query, registry, journals, stream_allowed).await;
+        }
+        let now = Utc::now();
+        let key = CacheKey {
+            scope: scope.to_string(),
+            minute: now.timestamp().div_euclid(60),
+            query: serde_json::to_string(query).unwrap_or_default(),
+        };
+        // Read before the snapshot: an event landing in between makes the
+        // stored versions stale, so the entry is dropped on next lookup.
+        let versions = registry
+            .source_versions(|connector_id, stream| {
+                executor::reads_source(query, connector_id, stream)
+            })
+            .await;
+
+        if let Some(mut result) = self.lookup(&key, &versions) {
+            result.cache_hit = true;
+            result.query_time_ms = start.elapsed().as_millis() as u64;
+            return Ok(result);
+        }
+
+        let (mut result, valid_until) = registry
+            .with_buffered_events(|first, events| {
+                executor::evaluate(query, events, first, &[], now, stream_allowed)
+            })
+            .await?;
+        result.query_time_ms = start.elapsed().as_millis() as u64;
+
+        let mut ttl = self.ttl;
+        if let Some(valid_until) = valid_until {
+            let remaining = (valid_until - now).to_std().unwrap_or_default();
+            ttl = ttl.min(remaining);
+        }
+        if !ttl.is_zero() {
+            self.insert(key, versions, result.clone(), start + ttl);
+        }
+        Ok(result)
+    }
+
+    fn lookup(&self, key: &CacheKey, versions: &SourceVersions) -> Option<QueryResult> {
+        let mut state = self.state.lock().unwrap();
+        state.tick += 1;
+        let tick = state.tick;
+        match state.entries.get_mut(key) {
+            Some(entry) if entry.expires > Instant::now() && entry.versions == *versions => {
+                entry.last_used = tick;
+                Some(entry.result.clone())
+            }
+            Some(_) => {
+                state.entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert(
+        &self,
+        key: CacheKey,
+        versions: SourceVersions,
+        result: QueryResult,
+        expires: Instant,
+    ) {
+        let mut state = self.state.lock().unwrap();
+        if self.capacity == 0 {
+            return;
+        }
+        if state.entries.len() >= self.capacity && !state.entries.contains_key(&key) {
+            let now = Instant::now();
+            state.entries.retain(|_, e| e.expires > now);
+            if state.entries.len() >= self.capacity {
+                let oldest = state
+                    .entries
+                    .iter()
+                    .min_by_key(|(_, e)| e.last_used)
+                    .map(|(k, _)| k.clone());
+                if let Some(oldest) = oldest {
+                    state.entries.remove(&oldest);
+                }
+            }
+        }
+        state.tick += 1;
+        let last_used = state.tick;
+        state.entries.insert(
+            key,
+            Entry {
+                result,
+                versions,
+                expires,
+                last_used,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connectors::webhook::WebhookConnector;
+    use crate::connectors::{StreamConnector, StreamEvent};
+    use crate::query::parser::parse;
+
+    async fn registry_with_webhook() -> (Arc<ConnectorRegistry>, Arc<WebhookConnector>) {
+        let registry = Arc::new(ConnectorRegistry::new(100));
+        let webhook = Arc::new(WebhookConnector::new("orders".into(), HashMap::new()));
+        registry.add(webhook.clone()).await.unwrap();
+        (registry, webhook)
+    }
+
+    async fn ingest(registry: &ConnectorRegistry, webhook: &WebhookConnector, body: &str) {
+        let before = registry.generation();
+        webhook
+            .ingest(body.as_bytes(), HashMap::new())
+            .await
+            .unwrap();
+        for _ in 0..100 {
+            if registry.generation() != before {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("event never reached the registry buffer");
+    }
+
+    #[tokio::test]
+    async fn test_hit_then_miss_after_ttl() {
+        let (registry, webhook) = registry_with_webhook().await;
+        ingest(&registry, &webhook, r#"{"amount": 5}"#).await;
+        let cache = QueryCache::new(8, Duration::from_millis(200));
+        let query = parse("SELECT * WHERE amount > 1").unwrap();
+
+        let first = cache
+            .execute(&query, &registry, &[], "key-a", |_| true)
+            .await
+            .unwrap();
+        let second = cache
+            .execute(&query, &registry, &[], "key-a", |_| true)
+            .await
+            .unwrap();
+        assert!(!first.cache_hit);
+        assert!(second.cache_hit);
+        assert_eq!((first.total, second.total), (1, 1));
+
+        // Another caller may see different streams, so it does not share.
+        let other = cache
+            .execute(&query, &registry, &[], "key-b", |_| false)
+            .await
+            .unwrap();
+        assert!(!other.cache_hit);
+        assert_eq!(other.total, 0);
+
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        let expired = cache
+            .execute(&query, &registry, &[], "key-a", |_| true)
+            .await
+            .unwrap();
+        assert!(!expired.cache_hit);
+    }
+
+    #[tokio::test]
+    async fn test_new_event_invalidates() {
+        let (registry, webhook) = registry_with_webhook().await;
+        ingest(&registry, &webhook, r#"{"amount": 5}"#).await;
+        let cache = QueryCache::default();
+        let query = parse("SELECT * WHERE amount > 1").unwrap();
+
+        cache
+            .execute(&query, &registry, &[], "key-a", |_| true)
+            .await
+            .unwrap();
+        assert!(
+            cache
+                .execute(&query, &registry, &[], "key-a", |_| true)
+                .await
+                .unwrap()
+                .cache_hit
+        );
+
+        ingest(&registry, &webhook, r#"{"amount": 7}"#).await;
+        let fresh = cache
+            .execute(&query, &registry, &[], "key-a", |_| true)
+            .await
+            .unwrap();
+        assert!(!fresh.cache_hit);
+        assert_eq!(fresh.total, 2);
+    }
+
+    #[tokio::test]
+    async fn test_only_events_of_read_sources_invalidate() {
+        let (registry, orders) = registry_with_webhook().await;
+        let payments = Arc::new(WebhookConnector::new("payments".into(), HashMap::new()));
+        registry.add(payments.clone()).await.unwrap();
+        ingest(&registry, &orders, r#"{"amount": 5}"#).await;
+        ingest(&registry, &payments, r#"{"amount": 9}"#).await;
+        let orders_id = orders.id().to_string();
+        let cache = QueryCache::default();
+        let query = parse(&format!("SELECT * FROM {} WHERE amount > 1", orders_id)).unwrap();
+        let run = || cache.execute(&query, &registry, &[], "key-a", |_| true);
+
+        let first = run().await.unwrap();
+        assert_eq!(
+            (first.cache_hit, first.rows_scanned, first.rows_matched),
+            (false, 2, 1)
+        );
+        // The same query, spelled differently, is the same entry.
+        let respelled = parse(&format!("select *  from {} where amount>1", orders_id)).unwrap();
+        assert!(
+            cache
+                .execute(&respelled, &registry, &[], "key-a", |_| true)
+                .await
+                .unwrap()
+                .cache_hit
+        );
+
+        ingest(&registry, &payments, r#"{"amount": 11}"#).await;
+        assert!(run().await.unwrap().cache_hit);
+        ingest(&registry, &orders, r#"{"amount": 7}"#).await;
+        let fresh = run().await.unwrap();
+        assert_eq!((fresh.cache_hit, fresh.total), (false, 2));
+
+        let stats = cache.stats();
+        assert_eq!((stats.queries, stats.cache_hits, stats.errors), (4, 2, 0));
+        assert_eq!((stats.rows_scanned, stats.rows_matched), (2 + 4, 1 + 2));
+        assert_eq!(stats.cached_results, 1);
+        // ORDER BY without an aggregate cannot run.
+        let mut bad = parse("SELECT *").unwrap();
+        bad.order_by = Some(crate::query::OrderBy {
+            column: "amount".into(),
+            descending: false,
+        });
+        assert!(cache
+            .execute(&bad, &registry, &[], "key-a", |_| true)
+            .await
+            .is_err());
+        assert_eq!(cache.stats().errors, 1);
+    }
+
+    #[tokio::test]
+    async fn test_relative_window_expires_with_its_events() {
+        let (registry, webhook) = registry_with_webhook().await;
+        ingest(&registry, &webhook, r#"{"amount": 5}"#).await;
+        let cache = QueryCache::new(8, Duration::from_secs(60));
+        let query = parse("SELECT * SINCE 1s").unwrap();
+
+        let first = cache
+            .execute(&query, &registry, &[], "key-a", |_| true)
+            .await
+            .unwrap();
+        assert_eq!(first.total, 1);
+
+        // The event leaves the window well within the TTL, so the cached
+        // result must not outlive it.
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        let later = cache
+            .execute(&query, &registry, &[], "key-a", |_| true)
+            .await
+            .unwrap();
+        assert!(!later.cache_hit);
+        assert_eq!(later.total, 0);
+    }
+
+    #[test]
+    fn test_least_recently_used_is_evicted() {
+        let cache = QueryCache::new(2, DEFAULT_TTL);
+        let expires = Instant::now() + DEFAULT_TTL;
+        let key = |q: &str| CacheKey {
+            scope: "key-a".into(),
+            minute: 0,
+            query: q.into(),
+        };
+        let result = QueryResult {
+            events: Vec::<StreamEvent>::new(),
+            table: None,
+            pairs: Vec::new(),
+            total: 0,
+            query_time_ms: 0,
+            streams_searched: Vec::new(),
+            has_more: false,
+            next_cursor: None,
+            rows_scanned: 0,
+            rows_matched: 0,
+            cache_hit: false,
+        };
+        let versions = SourceVersions::default();
+        cache.insert(key("a"), versions.clone(), result.clone(), expires);
+        cache.insert(key("b"), versions.clone(), result.clone(), expires);
+        assert!(cache.lookup(&key("a"), &versions).is_some());
+        cache.insert(key("c"), versions.clone(), result, expires);
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.lookup(&key("a"), &versions).is_some());
+        assert!(cache.lookup(&key("b"), &versions).is_none());
+    }
+}
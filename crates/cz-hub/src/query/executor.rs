@@ -1,56 +1,354 @@
 //! # Query Executor
 //!
-//! Evaluates parsed queries against the [`ConnectorRegistry`] event buffer.
+//! Evaluates parsed queries against the [`ConnectorRegistry`] event buffer,
+//! or directly against a journal's Index Ring. A registry query that names
+//! a journal's connector in `FROM` reads that journal's ring as well.
 
-use super::{CompareOp, Condition, Query, QueryResult};
+use super::{
+    AggregateFn, CompareOp, Expr, Join, JoinedPair, JournalEventRecord, JournalQueryResult, Query,
+    QueryCursor, QueryResult, QueryTable, SelectItem,
+};
 use crate::connectors::registry::ConnectorRegistry;
 use crate::connectors::StreamEvent;
 use chrono::{DateTime, Duration, Utc};
-use std::sync::Arc;
+use cz_core::CausalEvent;
+use cz_io::cursor::Cursor;
+use cz_io::journal::Journal;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, LazyLock, Mutex};
 use std::time::Instant;
 
-/// Execute a query against the connector registry's buffered events.
-pub async fn execute(query: &Query, registry: &Arc<ConnectorRegistry>) -> QueryResult {
-    let start = Instant::now();
-    let now = Utc::now();
+/// Most groups an aggregate query may produce before it is refused.
+pub const MAX_GROUPS: usize = 10_000;
+/// Most events of a joined stream one key is paired with; later ones with
+/// the same key are left out.
+pub const MAX_JOIN_MATCHES_PER_KEY: usize = 100;
+/// Most pairs a `JOIN` query may produce before it is refused.
+pub const MAX_JOIN_PAIRS: usize = 10_000;
 
-    let all_events = registry.buffered_events().await;
+/// A journal a registry query can read by naming its connector in `FROM`.
+pub struct JournalSource<'a> {
+    /// Id and name of the journal's connector, matched against `FROM`
+    /// like any stream.
+    pub connector_id: String,
+    pub name: String,
+    pub journal: &'a Journal,
+}
 
-    // Filter by source streams
-    let stream_filtered: Vec<&StreamEvent> = if query.from.is_empty() {
-        all_events.iter().collect()
-    } else {
-        all_events
+impl JournalSource<'_> {
+    pub(crate) fn named_by(&self, query: &Query) -> bool {
+        query
+            .from
             .iter()
-            .filter(|e| {
-                query
-                    .from
-                    .iter()
-                    .any(|f| e.stream.contains(f) || e.connector_id.contains(f))
+            .any(|f| self.name.contains(f.as_str()) || self.connector_id.contains(f.as_str()))
+    }
+
+    /// Whether this is the stream `query` joins.
+    pub(crate) fn joined_by(&self, query: &Query) -> bool {
+        query
+            .join
+            .as_ref()
+            .is_some_and(|join| join.names(&self.connector_id, &self.name))
+    }
+}
+
+/// Whether `query` reads any of `journals`. A query over all streams reads
+/// none, rather than scanning every ring.
+pub fn reads_journals(query: &Query, journals: &[JournalSource<'_>]) -> bool {
+    journals
+        .iter()
+        .any(|j| j.named_by(query) || j.joined_by(query))
+}
+
+/// Execute a query against the connector registry's buffered events, and
+/// against the rings of the `journals` it names in `FROM`.
+///
+/// Events whose stream is rejected by `stream_allowed` are dropped before
+/// any other filtering, so they never count towards `total`. Fails if the
+/// query's clauses do not fit together or it has too many groups.
+pub async fn execute(
+    query: &Query,
+    registry: &Arc<ConnectorRegistry>,
+    journals: &[JournalSource<'_>],
+    stream_allowed: impl Fn(&str) -> bool,
+) -> Result<QueryResult, String> {
+    let start = Instant::now();
+    let journal_events = scan_journals(query, journals, &stream_allowed)?;
+    let (mut result, _) = registry
+        .with_buffered_events(|first, events| {
+            evaluate(
+                query,
+                events,
+                first,
+                &journal_events,
+                Utc::now(),
+                stream_allowed,
+            )
+        })
+        .await?;
+    result.query_time_ms = start.elapsed().as_millis() as u64;
+    Ok(result)
+}
+
+/// The events of the `journals` that `query` names or joins, in
+/// `lamport_ts` order.
+///
+/// The ring is sorted by `lamport_ts`, so bounds on it in the `WHERE`
+/// clause are found by binary search rather than a scan. A joined journal
+/// is read whole, as `WHERE` only filters the `FROM` side. Journal events
+/// carry no wall-clock time, so `SINCE`/`UNTIL` exclude them, and they
+/// have no buffer position, so a cursor cannot page through them.
+pub(crate) fn scan_journals(
+    query: &Query,
+    journals: &[JournalSource<'_>],
+    stream_allowed: &impl Fn(&str) -> bool,
+) -> Result<Vec<StreamEvent>, String> {
+    let named: Vec<&JournalSource> = journals
+        .iter()
+        .filter(|j| j.named_by(query) || j.joined_by(query))
+        .collect();
+    if named.is_empty() {
+        return Ok(Vec::new());
+    }
+    if query.cursor.is_some() {
+        return Err(
+            "A cursor cannot page through journal sources; use OFFSET or lamport_ts bounds".into(),
+        );
+    }
+    let mut events = Vec::new();
+    for source in named {
+        if !stream_allowed(&source.name) {
+            continue;
+        }
+        let filter = match source.joined_by(query) {
+            true => None,
+            false => query.filter.as_ref(),
+        };
+        let (low, high) = lamport_bounds(filter);
+        let journal = source.journal;
+        let mut found = Vec::new();
+        for partition in 0..journal.partitions() {
+            let Some(cursor) = journal.partition_cursor_view(partition) else {
+                continue;
+            };
+            let start = journal.find_first_ge(&cursor, low);
+            for slot in (start..cursor.len()).filter_map(|i| cursor.slot_at(i)) {
+                // SAFETY: `slot` comes from the cursor, so it is within the ring.
+                let event = unsafe { journal.read_event_at(slot) };
+                if is_empty_event(&event) {
+                    continue;
+                }
+                if event.lamport_ts > high {
+                    break;
+                }
+                let event = journal_stream_event(source, slot, &event);
+                if matches_filter(filter, |field| extract_field(&event, field)) {
+                    found.push(event);
+                }
+            }
+        }
+        // Partitions are each sorted; merge them.
+        found.sort_by_key(|e| e.sequence);
+        events.extend(found);
+    }
+    Ok(events)
+}
+
+/// A journal event as a [`StreamEvent`] of its connector: the
+/// [`CausalEvent`] fields and `slot` as metadata, and the payload as JSON
+/// when it is valid JSON, else as a hex string (`null` for checkpoints and
+/// unreadable payloads).
+pub fn journal_stream_event(
+    source: &JournalSource<'_>,
+    slot: usize,
+    event: &CausalEvent,
+) -> StreamEvent {
+    let packet = crate::payload::decode_packet(source.journal.read_payload(event), event);
+    let payload = match packet
+        .as_deref()
+        .ok()
+        .and_then(|p| p.get(CausalEvent::size_bytes()..))
+    {
+        Some(bytes) if !event.is_checkpoint() => {
+            serde_json::from_slice(bytes).unwrap_or_else(|_| {
+                serde_json::Value::String(bytes.iter().map(|b| format!("{:02x}", b)).collect())
             })
-            .collect()
+        }
+        _ => serde_json::Value::Null,
+    };
+    let metadata = [
+        ("lamport_ts", event.lamport_ts.to_string()),
+        ("node_id", event.node_id.to_string()),
+        ("stream_id", event.stream_id.to_string()),
+        ("flags", event.flags.to_string()),
+        ("payload_offset", event.payload_offset.to_string()),
+        ("checksum", event.checksum.to_string()),
+        ("slot", slot.to_string()),
+    ]
+    .into_iter()
+    .map(|(k, v)| (k.to_string(), v))
+    .collect();
+    StreamEvent {
+        id: format!("{}-{}", source.connector_id, slot),
+        connector_id: source.connector_id.clone(),
+        stream: source.name.clone(),
+        sequence: event.lamport_ts,
+        timestamp: String::new(),
+        payload,
+        metadata,
+    }
+}
+
+/// Whether one new `event` matches `query` as of `now`: it comes from a
+/// stream in `FROM`, passes `WHERE` and falls within `SINCE`/`UNTIL`.
+/// Live query subscriptions use this in place of [`execute`].
+pub fn matches_event(query: &Query, event: &StreamEvent, now: DateTime<Utc>) -> bool {
+    if !in_sources(query, event)
+        || !matches_filter(query.filter.as_ref(), |field| extract_field(event, field))
+    {
+        return false;
+    }
+    match time_bounds(query, now) {
+        (None, None) => true,
+        (since, until) => parse_event_timestamp(event)
+            .is_some_and(|ts| since.is_none_or(|s| ts >= s) && until.is_none_or(|u| ts <= u)),
+    }
+}
+
+/// Whether `event` comes from a stream in `FROM`.
+pub(crate) fn in_sources(query: &Query, event: &StreamEvent) -> bool {
+    query.from.is_empty()
+        || query
+            .from
+            .iter()
+            .any(|f| event.stream.contains(f.as_str()) || event.connector_id.contains(f.as_str()))
+}
+
+/// Whether `query` reads `stream` of connector `connector_id`, in `FROM`
+/// or as the stream it joins.
+pub fn reads_source(query: &Query, connector_id: &str, stream: &str) -> bool {
+    query.from.is_empty()
+        || query
+            .from
+            .iter()
+            .any(|f| stream.contains(f.as_str()) || connector_id.contains(f.as_str()))
+        || query
+            .join
+            .as_ref()
+            .is_some_and(|join| join.names(connector_id, stream))
+}
+
+/// `SINCE` and `UNTIL` of `query` as instants, as of `now`.
+pub(crate) fn time_bounds(
+    query: &Query,
+    now: DateTime<Utc>,
+) -> (Option<DateTime<Utc>>, Option<DateTime<Utc>>) {
+    let bound = |value: &Option<String>| {
+        value
+            .as_deref()
+            .and_then(|value| parse_time_expr(value, now))
     };
+    (bound(&query.since), bound(&query.until))
+}
+
+/// Inclusive `lamport_ts` range every event matching `filter` lies in,
+/// from comparisons joined by `AND` at its top level.
+pub(crate) fn lamport_bounds(filter: Option<&Expr>) -> (u64, u64) {
+    let (mut low, mut high) = (0u64, u64::MAX);
+    let mut terms: Vec<&Expr> = filter.into_iter().collect();
+    while let Some(term) = terms.pop() {
+        match term {
+            Expr::And(inner) => terms.extend(inner),
+            Expr::Condition(cond)
+                if cond.field == "lamport_ts" && matches!(cond.op, CompareOp::In) =>
+            {
+                let Some(values) = cond.value.as_array() else {
+                    continue;
+                };
+                let Some(values) = values.iter().map(value_to_f64).collect::<Option<Vec<_>>>()
+                else {
+                    continue;
+                };
+                let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+                let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+                low = low.max(min.ceil() as u64);
+                high = high.min(max.floor() as u64);
+            }
+            Expr::Condition(cond) if cond.field == "lamport_ts" => {
+                let Some(v) = value_to_f64(&cond.value) else {
+                    continue;
+                };
+                // Saturating float-to-int casts keep out-of-range bounds sane.
+                match cond.op {
+                    CompareOp::Eq => {
+                        low = low.max(v.ceil() as u64);
+                        high = high.min(v.floor() as u64);
+                    }
+                    CompareOp::Gt => low = low.max((v.floor() as u64).saturating_add(1)),
+                    CompareOp::Gte => low = low.max(v.ceil() as u64),
+                    CompareOp::Lt => high = high.min((v.ceil() as u64).saturating_sub(1)),
+                    CompareOp::Lte => high = high.min(v.floor() as u64),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+    (low, high)
+}
+
+/// Run `query` over `events`, the first at buffer position `first`, and
+/// then over `journal_events`, as of `now`. Also returns when the result
+/// stops being valid because a relative `SINCE`/`UNTIL` window has moved
+/// past an event, or `None` if the window cannot change it.
+pub(crate) fn evaluate(
+    query: &Query,
+    events: &[StreamEvent],
+    first: u64,
+    journal_events: &[StreamEvent],
+    now: DateTime<Utc>,
+    stream_allowed: impl Fn(&str) -> bool,
+) -> Result<(QueryResult, Option<DateTime<Utc>>), String> {
+    query.validate()?;
+    let visible = (first..)
+        .map(Some)
+        .zip(events)
+        .chain(journal_events.iter().map(|e| (None, e)))
+        .filter(|(_, e)| stream_allowed(&e.stream));
+
+    // Filter by source streams
+    let mut rows_scanned = 0;
+    let stream_filtered: Vec<(Option<u64>, &StreamEvent)> = visible
+        .inspect(|_| rows_scanned += 1)
+        .filter(|(_, e)| in_sources(query, e))
+        .collect();
 
     // Apply WHERE conditions
-    let condition_filtered: Vec<&StreamEvent> = stream_filtered
+    let condition_filtered: Vec<(Option<u64>, &StreamEvent)> = stream_filtered
         .into_iter()
-        .filter(|e| evaluate_conditions(e, &query.conditions))
+        .filter(|(_, e)| matches_filter(query.filter.as_ref(), |field| extract_field(e, field)))
         .collect();
 
     // Apply temporal filters
-    let since = query
-        .since
-        .as_deref()
-        .and_then(|value| parse_time_expr(value, now));
-    let until = query
-        .until
-        .as_deref()
-        .and_then(|value| parse_time_expr(value, now));
+    let (since, until) = time_bounds(query, now);
+    let since_window = query.since.as_deref().and_then(relative_window);
+    let until_window = query.until.as_deref().and_then(relative_window);
+
+    // A relative SINCE drops an event once it is older than the window; a
+    // relative UNTIL admits one once it is. Either changes the result.
+    let mut valid_until: Option<DateTime<Utc>> = None;
+    let mut expire_at = |at: DateTime<Utc>| {
+        valid_until = Some(valid_until.map_or(at, |v| v.min(at)));
+    };
 
-    let temporal_filtered: Vec<&StreamEvent> = if since.is_some() || until.is_some() {
+    let temporal_filtered: Vec<(Option<u64>, &StreamEvent)> = if since.is_some() || until.is_some()
+    {
         condition_filtered
             .into_iter()
-            .filter(|e| {
+            .filter(|(_, e)| {
                 let event_ts = parse_event_timestamp(e);
                 match event_ts {
                     Some(ts) => {
@@ -62,6 +360,12 @@ pub async fn execute(query: &Query, registry: &Arc<ConnectorRegistry>) -> QueryR
                             Some(u) => ts <= u,
                             None => true,
                         };
+                        if let (true, Some(window)) = (since_ok, since_window) {
+                            expire_at(ts + window);
+                        }
+                        if let (false, Some(window)) = (until_ok, until_window) {
+                            expire_at(ts + window);
+                        }
                         since_ok && until_ok
                     }
                     None => false,
@@ -73,29 +377,394 @@ pub async fn execute(query: &Query, registry: &Arc<ConnectorRegistry>) -> QueryR
     };
 
     let total = temporal_filtered.len();
+    let rows_matched = total;
 
     // Collect unique streams searched
     let streams_searched: Vec<String> = {
-        let mut s: Vec<String> = temporal_filtered.iter().map(|e| e.stream.clone()).collect();
+        let mut s: Vec<String> = temporal_filtered
+            .iter()
+            .map(|(_, e)| e.stream.clone())
+            .collect();
         s.sort();
         s.dedup();
         s
     };
 
-    // Pagination
-    let paginated: Vec<StreamEvent> = temporal_filtered
+    if query.is_aggregate() {
+        let events: Vec<&StreamEvent> = temporal_filtered.into_iter().map(|(_, e)| e).collect();
+        let mut table = aggregate(query, &events)?;
+        let total = table.rows.len();
+        let has_more = total > query.offset.saturating_add(query.limit);
+        table.rows = table
+            .rows
+            .into_iter()
+            .skip(query.offset)
+            .take(query.limit)
+            .collect();
+        let result = QueryResult {
+            events: Vec::new(),
+            table: Some(table),
+            pairs: Vec::new(),
+            total,
+            query_time_ms: 0,
+            streams_searched,
+            has_more,
+            next_cursor: None,
+            rows_scanned,
+            rows_matched,
+            cache_hit: false,
+        };
+        return Ok((result, valid_until));
+    }
+
+    if let Some(join) = &query.join {
+        let left: Vec<&StreamEvent> = temporal_filtered.into_iter().map(|(_, e)| e).collect();
+        let right: Vec<&StreamEvent> = events
+            .iter()
+            .chain(journal_events)
+            .filter(|e| stream_allowed(&e.stream) && join.names(&e.connector_id, &e.stream))
+            .collect();
+        let pairs = join_events(join, &left, &right)?;
+        let total = pairs.len();
+        let mut streams_searched = streams_searched;
+        streams_searched.extend(pairs.iter().map(|(_, r)| r.stream.clone()));
+        streams_searched.sort();
+        streams_searched.dedup();
+        let result = QueryResult {
+            events: Vec::new(),
+            table: None,
+            pairs: pairs
+                .into_iter()
+                .skip(query.offset)
+                .take(query.limit)
+                .map(|(left, right)| JoinedPair {
+                    left: left.clone(),
+                    right: right.clone(),
+                })
+                .collect(),
+            total,
+            query_time_ms: 0,
+            streams_searched,
+            has_more: total > query.offset.saturating_add(query.limit),
+            next_cursor: None,
+            rows_scanned,
+            rows_matched,
+            cache_hit: false,
+        };
+        return Ok((result, valid_until));
+    }
+
+    // Pagination: events are in buffer order, so a cursor continues right
+    // after the last event of the previous page. Journal events have no
+    // position, so no cursor is handed out once they are read.
+    let mut remaining = temporal_filtered
         .into_iter()
-        .skip(query.offset)
-        .take(query.limit)
-        .cloned()
-        .collect();
+        .skip_while(|(pos, _)| query.cursor.is_some_and(|c| pos.is_some_and(|p| p <= c.0)))
+        .skip(query.offset);
+    let page: Vec<(Option<u64>, &StreamEvent)> = remaining.by_ref().take(query.limit).collect();
+    let has_more = remaining.next().is_some();
+    let next_cursor = if journal_events.is_empty() {
+        page.last()
+            .and_then(|(pos, _)| pos.map(QueryCursor))
+            .or(query.cursor)
+    } else {
+        None
+    };
 
-    QueryResult {
-        events: paginated,
+    let result = QueryResult {
+        events: page.into_iter().map(|(_, e)| e.clone()).collect(),
+        table: None,
+        pairs: Vec::new(),
         total,
-        query_time_ms: start.elapsed().as_millis() as u64,
+        query_time_ms: 0,
         streams_searched,
+        has_more,
+        next_cursor,
+        rows_scanned,
+        rows_matched,
+        cache_hit: false,
+    };
+    Ok((result, valid_until))
+}
+
+/// Hash join of `left` and `right` on `join.on`, in `left` order. Each
+/// key keeps its first [`MAX_JOIN_MATCHES_PER_KEY`] `right` events, and an
+/// event is never paired with itself. With `WITHIN`, events without a
+/// timestamp pair with nothing.
+fn join_events<'a>(
+    join: &Join,
+    left: &[&'a StreamEvent],
+    right: &[&'a StreamEvent],
+) -> Result<Vec<(&'a StreamEvent, &'a StreamEvent)>, String> {
+    let key = |event: &StreamEvent| {
+        extract_field(event, &join.on)
+            .filter(|v| !v.is_null())
+            .map(|v| value_to_string(&v))
+    };
+    let within = join.within.as_deref().and_then(relative_window);
+
+    let mut index: HashMap<String, Vec<&StreamEvent>> = HashMap::new();
+    for &event in right {
+        let Some(key) = key(event) else { continue };
+        let matches = index.entry(key).or_default();
+        if matches.len() < MAX_JOIN_MATCHES_PER_KEY {
+            matches.push(event);
+        }
+    }
+
+    let mut pairs = Vec::new();
+    for &l in left {
+        let Some(matches) = key(l).and_then(|k| index.get(&k)) else {
+            continue;
+        };
+        let l_ts = parse_event_timestamp(l);
+        for &r in matches {
+            if std::ptr::eq(l, r) {
+                continue;
+            }
+            if let Some(window) = within {
+                match (l_ts, parse_event_timestamp(r)) {
+                    (Some(a), Some(b)) if (a - b).abs() <= window => {}
+                    _ => continue,
+                }
+            }
+            if pairs.len() == MAX_JOIN_PAIRS {
+                return Err(format!(
+                    "JOIN produced more than {} pairs; narrow it with WHERE or WITHIN",
+                    MAX_JOIN_PAIRS
+                ));
+            }
+            pairs.push((l, r));
+        }
+    }
+    Ok(pairs)
+}
+
+/// Running state of one aggregate within one group.
+#[derive(Debug, Clone, Default)]
+struct Accumulator {
+    /// Events counted, or values seen for sum/avg/min/max.
+    count: u64,
+    sum: f64,
+    min: Option<f64>,
+    max: Option<f64>,
+}
+
+impl Accumulator {
+    fn add(&mut self, value: f64) {
+        self.count += 1;
+        self.sum += value;
+        self.min = Some(self.min.map_or(value, |m| m.min(value)));
+        self.max = Some(self.max.map_or(value, |m| m.max(value)));
+    }
+
+    fn finish(&self, func: AggregateFn) -> serde_json::Value {
+        match func {
+            AggregateFn::Count => self.count.into(),
+            _ if self.count == 0 => serde_json::Value::Null,
+            AggregateFn::Sum => serde_json::json!(self.sum),
+            AggregateFn::Avg => serde_json::json!(self.sum / self.count as f64),
+            AggregateFn::Min => serde_json::json!(self.min),
+            AggregateFn::Max => serde_json::json!(self.max),
+        }
+    }
+}
+
+/// Group `events` by the query's `GROUP BY` fields (a missing field groups
+/// as `null`) and compute its `SELECT` columns, one row per group, ordered
+/// by `ORDER BY` or else by first appearance. Non-numeric values are
+/// skipped by sum/avg/min/max; `count(field)` counts events that have it.
+fn aggregate(query: &Query, events: &[&StreamEvent]) -> Result<QueryTable, String> {
+    let columns = query.columns()?;
+    let implicit_count = query.select.is_empty();
+
+    let mut index: HashMap<String, usize> = HashMap::new();
+    let mut groups: Vec<(Vec<serde_json::Value>, Vec<Accumulator>)> = Vec::new();
+    for event in events {
+        let key: Vec<serde_json::Value> = query
+            .group_by
+            .iter()
+            .map(|field| extract_field(event, field).unwrap_or(serde_json::Value::Null))
+            .collect();
+        let id = serde_json::to_string(&key).unwrap_or_default();
+        let slot = match index.get(&id) {
+            Some(&slot) => slot,
+            None => {
+                if groups.len() >= MAX_GROUPS {
+                    return Err(format!(
+                        "GROUP BY produced more than {} groups; narrow the query",
+                        MAX_GROUPS
+                    ));
+                }
+                index.insert(id, groups.len());
+                let accumulators = query.select.len().max(1);
+                groups.push((key, vec![Accumulator::default(); accumulators]));
+                groups.len() - 1
+            }
+        };
+        let accumulators = &mut groups[slot].1;
+        if implicit_count {
+            accumulators[0].count += 1;
+            continue;
+        }
+        for (item, acc) in query.select.iter().zip(accumulators.iter_mut()) {
+            let SelectItem::Aggregate(agg) = item else {
+                continue;
+            };
+            let value = agg
+                .field
+                .as_deref()
+                .map(|field| extract_field(event, field));
+            match (agg.func, value) {
+                (AggregateFn::Count, None) => acc.count += 1,
+                (AggregateFn::Count, Some(Some(v))) if !v.is_null() => acc.count += 1,
+                (_, Some(Some(v))) => {
+                    if let Some(n) = value_to_f64(&v) {
+                        acc.add(n);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut rows: Vec<Vec<serde_json::Value>> = groups
+        .into_iter()
+        .map(|(key, accumulators)| {
+            let value_of = |field: &String| {
+                let i = query.group_by.iter().position(|g| g == field).unwrap_or(0);
+                key.get(i).cloned().unwrap_or(serde_json::Value::Null)
+            };
+            if implicit_count {
+                let mut row = key.clone();
+                row.push(accumulators[0].finish(AggregateFn::Count));
+                return row;
+            }
+            query
+                .select
+                .iter()
+                .zip(&accumulators)
+                .map(|(item, acc)| match item {
+                    SelectItem::Field(field) => value_of(field),
+                    SelectItem::Aggregate(agg) => acc.finish(agg.func),
+                })
+                .collect()
+        })
+        .collect();
+
+    if let Some(order) = &query.order_by {
+        let col = columns.iter().position(|c| *c == order.column).unwrap_or(0);
+        rows.sort_by(|a, b| {
+            let ord = compare_cells(&a[col], &b[col]);
+            // Nulls stay last either way.
+            if order.descending && !a[col].is_null() && !b[col].is_null() {
+                ord.reverse()
+            } else {
+                ord
+            }
+        });
+    }
+    Ok(QueryTable { columns, rows })
+}
+
+/// Order table cells: numbers numerically, otherwise as text; nulls last.
+fn compare_cells(a: &serde_json::Value, b: &serde_json::Value) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+    match (a.is_null(), b.is_null()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        (false, false) => {
+            numeric_cmp(a, b).unwrap_or_else(|| value_to_string(a).cmp(&value_to_string(b)))
+        }
+    }
+}
+
+/// Execute a query against the live window of `journal` described by
+/// `cursor`, oldest event first, without a hub or registry.
+///
+/// `FROM` names numeric stream ids. Journal events carry no wall-clock
+/// time, so `SINCE`/`UNTIL` are rejected rather than silently ignored.
+pub fn execute_journal(
+    query: &Query,
+    journal: &Journal,
+    cursor: &Cursor,
+) -> Result<JournalQueryResult, String> {
+    let start = Instant::now();
+    if query.explain {
+        return Err("EXPLAIN is answered by a running hub, not over journal files".into());
+    }
+    if query.since.is_some() || query.until.is_some() {
+        return Err("SINCE/UNTIL need wall-clock timestamps, which journal events lack".into());
+    }
+    if query.is_aggregate() {
+        return Err("Aggregates and GROUP BY are not supported over journal files".into());
+    }
+    let streams = query
+        .from
+        .iter()
+        .map(|s| {
+            s.parse::<u16>()
+                .map_err(|_| format!("Journal streams are numeric ids, got '{}'", s))
+        })
+        .collect::<Result<Vec<u16>, String>>()?;
+
+    let mut events = Vec::new();
+    let mut total = 0;
+    for slot in cursor.slots() {
+        // SAFETY: `slot` comes from the cursor, so it is within the ring.
+        let event = unsafe { journal.read_event_at(slot) };
+        if is_empty_event(&event)
+            || (!streams.is_empty() && !streams.contains(&event.stream_id))
+            || !matches_filter(query.filter.as_ref(), |field| {
+                causal_event_field(&event, field)
+            })
+        {
+            continue;
+        }
+        if total >= query.offset && events.len() < query.limit {
+            events.push(JournalEventRecord {
+                slot,
+                lamport_ts: event.lamport_ts,
+                node_id: event.node_id,
+                stream_id: event.stream_id,
+                flags: event.flags,
+                payload_offset: event.payload_offset,
+                checksum: event.checksum,
+            });
+        }
+        total += 1;
     }
+
+    Ok(JournalQueryResult {
+        events,
+        total,
+        query_time_ms: start.elapsed().as_millis() as u64,
+    })
+}
+
+/// Resolve a query field on a journal event, for [`matches_filter`].
+pub fn causal_event_field(event: &CausalEvent, field: &str) -> Option<serde_json::Value> {
+    Some(match field {
+        "node_id" => event.node_id.into(),
+        "stream_id" => event.stream_id.into(),
+        "ts" | "lamport" | "lamport_ts" => event.lamport_ts.into(),
+        "payload_offset" => event.payload_offset.into(),
+        "checksum" => event.checksum.into(),
+        "flags" => event.flags.into(),
+        "checkpoint" => event.is_checkpoint().into(),
+        _ => return None,
+    })
+}
+
+/// Whether a ring slot still holds the all-zero bytes of an unwritten event.
+pub fn is_empty_event(event: &CausalEvent) -> bool {
+    event.lamport_ts == 0
+        && event.node_id == 0
+        && event.stream_id == 0
+        && event.flags == 0
+        && event.payload_offset == 0
+        && event.checksum == 0
 }
 
 fn parse_event_timestamp(event: &StreamEvent) -> Option<DateTime<Utc>> {
@@ -104,7 +773,10 @@ fn parse_event_timestamp(event: &StreamEvent) -> Option<DateTime<Utc>> {
         .map(|dt| dt.with_timezone(&Utc))
 }
 
-fn parse_time_expr(raw: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+/// Resolve a `SINCE`/`UNTIL` value: an RFC3339 timestamp, a `YYYY-MM-DD`
+/// date (midnight UTC), `now`, or an offset back from now such as `5m` or
+/// `-1h` (the sign is optional).
+pub(crate) fn parse_time_expr(raw: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
     let value = raw.trim();
     if value.is_empty() {
         return None;
@@ -112,36 +784,54 @@ fn parse_time_expr(raw: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
     if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
         return Some(dt.with_timezone(&Utc));
     }
+    if let Ok(date) = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Some(date.and_hms_opt(0, 0, 0)?.and_utc());
+    }
+    relative_window(value).map(|duration| now - duration)
+}
 
-    let (number, unit) = value.split_at(value.len().saturating_sub(1));
+/// How far back a relative time expression such as `5m` or `now` reaches,
+/// or `None` for an absolute timestamp.
+pub(crate) fn relative_window(raw: &str) -> Option<Duration> {
+    let value = raw.trim();
+    if value.eq_ignore_ascii_case("now") {
+        return Some(Duration::zero());
+    }
+    let value = value.strip_prefix('-').unwrap_or(value);
+    let (number, unit) = value.split_at(value.char_indices().last()?.0);
     let amount: i64 = number.parse().ok()?;
-    let duration = match unit {
-        "s" => Duration::seconds(amount),
-        "m" => Duration::minutes(amount),
-        "h" => Duration::hours(amount),
-        "d" => Duration::days(amount),
-        _ => return None,
-    };
-    Some(now - duration)
+    match unit {
+        "s" => Some(Duration::seconds(amount)),
+        "m" => Some(Duration::minutes(amount)),
+        "h" => Some(Duration::hours(amount)),
+        "d" => Some(Duration::days(amount)),
+        _ => None,
+    }
 }
 
-fn evaluate_conditions(event: &StreamEvent, conditions: &[Condition]) -> bool {
-    conditions
-        .iter()
-        .all(|cond| evaluate_condition(event, cond))
+/// Evaluate `filter` against any record, with `field` resolving a field
+/// name to its value. No filter matches everything; a condition on an
+/// unknown field never matches (so `NOT` of it always does).
+pub fn matches_filter(
+    filter: Option<&Expr>,
+    field: impl Fn(&str) -> Option<serde_json::Value>,
+) -> bool {
+    filter.is_none_or(|expr| evaluate_expr(expr, &field))
 }
 
-fn evaluate_condition(event: &StreamEvent, cond: &Condition) -> bool {
-    // Try to extract value from event payload or metadata
-    let event_value = extract_field(event, &cond.field);
-
-    match &event_value {
-        Some(val) => compare(val, &cond.op, &cond.value),
-        None => false,
+fn evaluate_expr(expr: &Expr, field: &impl Fn(&str) -> Option<serde_json::Value>) -> bool {
+    match expr {
+        Expr::Condition(cond) => match field(&cond.field) {
+            Some(val) => compare(&val, &cond.op, &cond.value),
+            None => false,
+        },
+        Expr::And(terms) => terms.iter().all(|t| evaluate_expr(t, field)),
+        Expr::Or(terms) => terms.iter().any(|t| evaluate_expr(t, field)),
+        Expr::Not(inner) => !evaluate_expr(inner, field),
     }
 }
 
-fn extract_field(event: &StreamEvent, field: &str) -> Option<serde_json::Value> {
+pub(crate) fn extract_field(event: &StreamEvent, field: &str) -> Option<serde_json::Value> {
     // Check top-level event fields
     match field {
         "id" => return Some(serde_json::Value::String(event.id.clone())),
@@ -152,30 +842,88 @@ fn extract_field(event: &StreamEvent, field: &str) -> Option<serde_json::Value>
         _ => {}
     }
 
-    // Check metadata
-    if let Some(val) = event.metadata.get(field) {
+    // Check metadata, by bare key or as `metadata.key`
+    let key = field.strip_prefix("metadata.").unwrap_or(field);
+    if let Some(val) = event.metadata.get(key) {
         return Some(serde_json::Value::String(val.clone()));
     }
 
     // Check payload using JSON pointer syntax (e.g., "payload.amount" → "/amount")
+    event.payload.pointer(&payload_pointer(field)).cloned()
+}
+
+/// Where [`extract_field`] may find `field` in an event, in the order it
+/// looks: an event field such as `stream` is only ever that, anything else
+/// is a metadata key or, failing that, a payload pointer.
+pub(crate) fn field_paths(field: &str) -> Vec<FieldPath> {
+    if EVENT_FIELDS.contains(&field) {
+        return vec![FieldPath::Event {
+            name: field.to_string(),
+        }];
+    }
+    vec![
+        FieldPath::Metadata {
+            key: field.strip_prefix("metadata.").unwrap_or(field).to_string(),
+        },
+        FieldPath::Payload {
+            pointer: payload_pointer(field),
+        },
+    ]
+}
+
+/// Fields of [`StreamEvent`] itself that queries can name.
+const EVENT_FIELDS: [&str; 5] = ["id", "connector_id", "stream", "sequence", "timestamp"];
+
+/// One place [`extract_field`] looks for a field.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum FieldPath {
+    Event { name: String },
+    Metadata { key: String },
+    Payload { pointer: String },
+}
+
+impl FieldPath {
+    /// Whether `event` has a value here.
+    pub fn resolves(&self, event: &StreamEvent) -> bool {
+        match self {
+            FieldPath::Event { .. } => true,
+            FieldPath::Metadata { key } => event.metadata.contains_key(key),
+            FieldPath::Payload { pointer } => event.payload.pointer(pointer).is_some(),
+        }
+    }
+}
+
+impl fmt::Display for FieldPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldPath::Event { name } => write!(f, "event field '{}'", name),
+            FieldPath::Metadata { key } => write!(f, "metadata key '{}'", key),
+            FieldPath::Payload { pointer } => write!(f, "payload pointer '{}'", pointer),
+        }
+    }
+}
+
+/// `field` as a JSON pointer into the payload: `payload.amount` and
+/// `amount` are both `/amount`, and a field that is already a pointer is
+/// kept.
+fn payload_pointer(field: &str) -> String {
     let field = field.strip_prefix("payload.").unwrap_or(field);
-    let pointer = if field.starts_with('/') {
+    if field.starts_with('/') {
         field.to_string()
     } else {
         format!("/{}", field.replace('.', "/"))
-    };
-
-    event.payload.pointer(&pointer).cloned()
+    }
 }
 
 fn compare(a: &serde_json::Value, op: &CompareOp, b: &serde_json::Value) -> bool {
     match op {
         CompareOp::Eq => values_equal(a, b),
         CompareOp::Neq => !values_equal(a, b),
-        CompareOp::Gt => numeric_cmp(a, b).map_or(false, |o| o == std::cmp::Ordering::Greater),
-        CompareOp::Gte => numeric_cmp(a, b).map_or(false, |o| o != std::cmp::Ordering::Less),
-        CompareOp::Lt => numeric_cmp(a, b).map_or(false, |o| o == std::cmp::Ordering::Less),
-        CompareOp::Lte => numeric_cmp(a, b).map_or(false, |o| o != std::cmp::Ordering::Greater),
+        CompareOp::Gt => numeric_cmp(a, b) == Some(std::cmp::Ordering::Greater),
+        CompareOp::Gte => numeric_cmp(a, b).is_some_and(|o| o != std::cmp::Ordering::Less),
+        CompareOp::Lt => numeric_cmp(a, b) == Some(std::cmp::Ordering::Less),
+        CompareOp::Lte => numeric_cmp(a, b).is_some_and(|o| o != std::cmp::Ordering::Greater),
         CompareOp::Contains => {
             let a_str = value_to_string(a);
             let b_str = value_to_string(b);
@@ -186,9 +934,38 @@ fn compare(a: &serde_json::Value, op: &CompareOp, b: &serde_json::Value) -> bool
             let b_str = value_to_string(b);
             a_str.starts_with(&b_str)
         }
+        CompareOp::In => b
+            .as_array()
+            .is_some_and(|values| values.iter().any(|b| values_equal(a, b))),
+        CompareOp::Matches => b
+            .as_str()
+            .and_then(compiled_pattern)
+            .is_some_and(|re| re.is_match(&value_to_string(a))),
     }
 }
 
+/// Most `MATCHES` patterns kept compiled; past this, the cache starts over.
+const MAX_CACHED_PATTERNS: usize = 256;
+
+static PATTERNS: LazyLock<Mutex<HashMap<String, Option<Regex>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// `pattern` compiled, once per pattern rather than once per event. `None`
+/// if it does not compile, which parsing or [`Query::validate`] has
+/// already reported.
+fn compiled_pattern(pattern: &str) -> Option<Regex> {
+    let mut patterns = PATTERNS.lock().unwrap();
+    if let Some(re) = patterns.get(pattern) {
+        return re.clone();
+    }
+    if patterns.len() >= MAX_CACHED_PATTERNS {
+        patterns.clear();
+    }
+    let re = Regex::new(pattern).ok();
+    patterns.insert(pattern.to_string(), re.clone());
+    re
+}
+
 fn values_equal(a: &serde_json::Value, b: &serde_json::Value) -> bool {
     // Try numeric comparison first
     if let (Some(an), Some(bn)) = (value_to_f64(a), value_to_f64(b)) {
@@ -218,3 +995,584 @@ fn value_to_string(v: &serde_json::Value) -> String {
         other => other.to_string(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::parser::parse;
+    use cz_io::journal::MIN_JOURNAL_SIZE;
+
+    #[test]
+    fn test_journal_query_filters_and_paginates() {
+        let path = std::env::temp_dir().join(format!("cz-query-{}.db", std::process::id()));
+        let size = MIN_JOURNAL_SIZE + 4096;
+        let mut journal = Journal::open(&path, size).unwrap();
+        let mut cursor = Cursor::new(journal.index_ring_capacity());
+        for ts in 1..=10u64 {
+            let slot = cursor.advance_head().unwrap();
+            let event = CausalEvent::new(ts, (ts % 2) as u32, (ts % 3) as u16, 0, 0);
+            unsafe { journal.write_event_at(slot, &event) };
+        }
+        journal.publish_cursor(&cursor);
+
+        let query = parse("SELECT * FROM 1 WHERE node_id = 1 AND lamport_ts > 1").unwrap();
+        let result = execute_journal(&query, &journal, &journal.live_cursor_view()).unwrap();
+        // Stream 1 holds ts 1, 4, 7, 10; node 1 keeps 1 and 7; ts > 1 keeps 7.
+        let ts: Vec<u64> = result.events.iter().map(|e| e.lamport_ts).collect();
+        assert_eq!((result.total, ts), (1, vec![7]));
+
+        let query = parse("SELECT * WHERE node_id = 0 OFFSET 2").unwrap();
+        let result = execute_journal(&query, &journal, &journal.live_cursor_view()).unwrap();
+        let ts: Vec<u64> = result.events.iter().map(|e| e.lamport_ts).collect();
+        assert_eq!((result.total, ts), (5, vec![6, 8, 10]));
+
+        let since = parse("SELECT * SINCE 5m").unwrap();
+        assert!(execute_journal(&since, &journal, &cursor).is_err());
+        let named = parse("SELECT * FROM orders").unwrap();
+        assert!(execute_journal(&named, &journal, &cursor).is_err());
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_journal_query_boolean_expressions() {
+        let path = std::env::temp_dir().join(format!("cz-query-bool-{}.db", std::process::id()));
+        let size = MIN_JOURNAL_SIZE + 4096;
+        let mut journal = Journal::open(&path, size).unwrap();
+        let mut cursor = Cursor::new(journal.index_ring_capacity());
+        for ts in 1..=10u64 {
+            let slot = cursor.advance_head().unwrap();
+            let event = CausalEvent::new(ts, (ts % 2) as u32, (ts % 3) as u16, 0, 0);
+            unsafe { journal.write_event_at(slot, &event) };
+        }
+        journal.publish_cursor(&cursor);
+
+        let run = |text: &str| -> Vec<u64> {
+            let query = parse(text).unwrap();
+            let result = execute_journal(&query, &journal, &journal.live_cursor_view()).unwrap();
+            result.events.iter().map(|e| e.lamport_ts).collect()
+        };
+        // Node 1 holds the odd timestamps; stream 0 holds 3, 6, 9.
+        assert_eq!(run("SELECT * WHERE ts < 3 OR ts > 8"), [1, 2, 9, 10]);
+        assert_eq!(
+            run("SELECT * WHERE node_id = 1 AND stream_id = 0 OR ts = 2"),
+            [2, 3, 9]
+        );
+        assert_eq!(
+            run("SELECT * WHERE node_id = 1 AND (stream_id = 0 OR ts = 2)"),
+            [3, 9]
+        );
+        assert_eq!(run("SELECT * WHERE NOT node_id = 1 AND ts > 6"), [8, 10]);
+        assert_eq!(
+            run("SELECT * WHERE NOT (node_id = 1 OR stream_id = 0)"),
+            [2, 4, 8, 10]
+        );
+        // A condition on an unknown field never matches, so its negation
+        // always does.
+        assert!(run("SELECT * WHERE bogus = 1").is_empty());
+        assert_eq!(run("SELECT * WHERE NOT bogus = 1").len(), 10);
+        // BETWEEN includes both bounds and combines like any condition.
+        assert_eq!(run("SELECT * WHERE ts BETWEEN 3 AND 5"), [3, 4, 5]);
+        assert_eq!(
+            run("SELECT * WHERE ts BETWEEN 2 AND 8 AND node_id = 0 OR ts = 10"),
+            [2, 4, 6, 8, 10]
+        );
+        assert_eq!(run("SELECT * WHERE NOT ts BETWEEN 2 AND 9"), [1, 10]);
+        assert!(run("SELECT * WHERE ts BETWEEN 5 AND 3").is_empty());
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_parse_time_expr() {
+        let now = DateTime::parse_from_rfc3339("2024-03-10T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let at = |s: &str| parse_time_expr(s, now).map(|t| t.to_rfc3339());
+        assert_eq!(at("now"), Some(now.to_rfc3339()));
+        assert_eq!(at(" NOW "), Some(now.to_rfc3339()));
+        assert_eq!(at("-1h"), Some("2024-03-10T11:00:00+00:00".into()));
+        assert_eq!(at("1h"), at("-1h"));
+        assert_eq!(at("5m"), Some("2024-03-10T11:55:00+00:00".into()));
+        assert_eq!(at("2024-01-01"), Some("2024-01-01T00:00:00+00:00".into()));
+        assert_eq!(
+            at("2024-01-01T06:30:00+02:00"),
+            Some("2024-01-01T04:30:00+00:00".into())
+        );
+        for bad in ["", "soon", "5w", "2024-13-01", "ß", "-"] {
+            assert_eq!(at(bad), None, "{}", bad);
+        }
+        // `now` and offsets move with the clock; dates do not.
+        assert_eq!(relative_window("now"), Some(Duration::zero()));
+        assert_eq!(relative_window("-1h"), Some(Duration::hours(1)));
+        assert_eq!(relative_window("2024-01-01"), None);
+    }
+
+    #[test]
+    fn test_since_until_literals() {
+        let mut events: Vec<StreamEvent> = (0..3).map(stream_event).collect();
+        events[0].timestamp = "2023-12-31T23:00:00Z".into();
+        events[1].timestamp = (Utc::now() - Duration::minutes(30)).to_rfc3339();
+        events[2].timestamp = (Utc::now() + Duration::hours(1)).to_rfc3339();
+        let run = |text: &str| -> Vec<u64> {
+            let query = parse(text).unwrap();
+            let (result, _) = evaluate(&query, &events, 0, &[], Utc::now(), |_| true).unwrap();
+            result.events.iter().map(|e| e.sequence).collect()
+        };
+        assert_eq!(run("SELECT * SINCE -1h UNTIL now"), [1]);
+        assert_eq!(run("SELECT * SINCE 2024-01-01"), [1, 2]);
+        assert_eq!(run("SELECT * UNTIL 2024-01-01"), [0]);
+    }
+
+    fn stream_event(n: u64) -> StreamEvent {
+        StreamEvent {
+            id: format!("webhook-{}", n),
+            connector_id: "webhook".into(),
+            stream: "webhook:generic".into(),
+            sequence: n,
+            timestamp: Utc::now().to_rfc3339(),
+            payload: serde_json::json!({ "n": n, "even": n.is_multiple_of(2) }),
+            metadata: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_cursor_pages_are_stable_while_events_arrive() {
+        // A ring of 8 like the registry's, fed two events per page read,
+        // with every event ever buffered numbered by position.
+        const CAPACITY: usize = 8;
+        let mut buffer: Vec<StreamEvent> = Vec::new();
+        let mut first = 0u64;
+        let mut produced = 0u64;
+        let mut append = |buffer: &mut Vec<StreamEvent>, first: &mut u64, count: u64| {
+            for _ in 0..count {
+                if buffer.len() == CAPACITY {
+                    buffer.remove(0);
+                    *first += 1;
+                }
+                buffer.push(stream_event(produced));
+                produced += 1;
+            }
+        };
+        append(&mut buffer, &mut first, 6);
+
+        let mut query = parse("SELECT * WHERE even = true LIMIT 2").unwrap();
+        let mut seen = Vec::new();
+        for _ in 0..6 {
+            let (page, _) = evaluate(&query, &buffer, first, &[], Utc::now(), |_| true).unwrap();
+            seen.extend(page.events.iter().map(|e| e.sequence));
+            query.cursor = page.next_cursor;
+            append(&mut buffer, &mut first, 2);
+        }
+        // Drain what is left.
+        loop {
+            let (page, _) = evaluate(&query, &buffer, first, &[], Utc::now(), |_| true).unwrap();
+            seen.extend(page.events.iter().map(|e| e.sequence));
+            query.cursor = page.next_cursor;
+            if !page.has_more {
+                break;
+            }
+        }
+        let expected: Vec<u64> = (0..18u64).step_by(2).collect();
+        assert_eq!(seen, expected);
+
+        // A caught-up cursor returns nothing until more events arrive.
+        let (page, _) = evaluate(&query, &buffer, first, &[], Utc::now(), |_| true).unwrap();
+        assert!(page.events.is_empty());
+        assert!(!page.has_more);
+        assert_eq!(page.next_cursor, query.cursor);
+    }
+
+    fn order(n: u64, stream: &str, amount: serde_json::Value) -> StreamEvent {
+        StreamEvent {
+            stream: stream.into(),
+            payload: serde_json::json!({ "amount": amount }),
+            ..stream_event(n)
+        }
+    }
+
+    #[test]
+    fn test_aggregate_group_by_and_order_by() {
+        let events = vec![
+            order(0, "orders:eu", 10.into()),
+            order(1, "orders:us", 4.into()),
+            order(2, "orders:eu", 20.into()),
+            order(3, "orders:us", "6".into()),
+            order(4, "orders:us", "n/a".into()),
+            order(5, "orders:apac", serde_json::Value::Null),
+        ];
+        let run = |text: &str| {
+            let query = parse(text).unwrap();
+            evaluate(&query, &events, 0, &[], Utc::now(), |_| true)
+                .unwrap()
+                .0
+        };
+
+        let result = run(
+            "SELECT stream, count(*), count(payload.amount), avg(payload.amount), \
+             min(amount), max(amount), sum(amount) GROUP BY stream ORDER BY count DESC",
+        );
+        let table = result.table.unwrap();
+        assert_eq!(
+            table.columns,
+            [
+                "stream",
+                "count",
+                "count(payload.amount)",
+                "avg(payload.amount)",
+                "min(amount)",
+                "max(amount)",
+                "sum(amount)"
+            ]
+        );
+        // Numeric strings count as numbers; other values are skipped, and
+        // a group with no numbers aggregates to null.
+        assert_eq!(
+            table.rows,
+            [
+                serde_json::json!(["orders:us", 3, 3, 5.0, 4.0, 6.0, 10.0]),
+                serde_json::json!(["orders:eu", 2, 2, 15.0, 10.0, 20.0, 30.0]),
+                serde_json::json!(["orders:apac", 1, 0, null, null, null, null]),
+            ]
+            .map(|row| row.as_array().unwrap().clone())
+        );
+        assert!(result.events.is_empty());
+        assert_eq!(result.total, 3);
+
+        // ORDER BY an aggregate ascending, then LIMIT/OFFSET over rows.
+        let result = run("SELECT stream, sum(amount) AS total WHERE amount > 0 \
+             GROUP BY stream ORDER BY total LIMIT 1 OFFSET 1");
+        let table = result.table.unwrap();
+        assert_eq!(
+            table.rows,
+            [serde_json::json!(["orders:eu", 30.0])
+                .as_array()
+                .unwrap()
+                .clone()]
+        );
+        assert_eq!((result.total, result.has_more), (2, false));
+
+        // No GROUP BY: one row over every matching event.
+        let table = run("SELECT count(*), max(amount)").table.unwrap();
+        assert_eq!(
+            table.rows,
+            [vec![serde_json::json!(6), serde_json::json!(20.0)]]
+        );
+    }
+
+    #[test]
+    fn test_aggregate_group_cardinality_is_capped() {
+        let events: Vec<StreamEvent> = (0..=MAX_GROUPS as u64).map(stream_event).collect();
+        let query = parse("SELECT count(*) GROUP BY payload.n").unwrap();
+        let err = evaluate(&query, &events, 0, &[], Utc::now(), |_| true).unwrap_err();
+        assert!(err.contains("more than 10000 groups"), "{}", err);
+
+        let query = parse("SELECT count(*) GROUP BY payload.even").unwrap();
+        let (result, _) = evaluate(&query, &events, 0, &[], Utc::now(), |_| true).unwrap();
+        assert_eq!(result.total, 2);
+    }
+
+    #[test]
+    fn test_in_matches_and_between_operators() {
+        let events: Vec<StreamEvent> = (1..=6)
+            .map(|n| {
+                let mut event = stream_event(n);
+                event.payload["path"] = serde_json::json!(format!("/api/v{}/items", n));
+                event.payload["label"] = serde_json::json!(format!("{}", n * 10));
+                event
+            })
+            .collect();
+        let run = |text: &str| -> Vec<u64> {
+            let query = parse(text).unwrap();
+            let (result, _) = evaluate(&query, &events, 0, &[], Utc::now(), |_| true).unwrap();
+            result.events.iter().map(|e| e.sequence).collect()
+        };
+
+        // Numeric and string operands compare by value either way.
+        assert_eq!(run("SELECT * WHERE n IN (2, 4, 9)"), [2, 4]);
+        assert_eq!(run("SELECT * WHERE n IN ('3', \"5\")"), [3, 5]);
+        assert_eq!(run("SELECT * WHERE label IN (10, '60')"), [1, 6]);
+        assert_eq!(run("SELECT * WHERE id IN ('webhook-2', 'other')"), [2]);
+        assert_eq!(run("SELECT * WHERE NOT n IN (1, 2, 3)"), [4, 5, 6]);
+
+        assert_eq!(run("SELECT * WHERE path MATCHES \"^/api/v[2-3]/\""), [2, 3]);
+        assert_eq!(run("SELECT * WHERE n MATCHES '^[45]$'"), [4, 5]);
+        assert_eq!(
+            run("SELECT * WHERE path MATCHES 'items$' AND even = true"),
+            [2, 4, 6]
+        );
+
+        assert_eq!(run("SELECT * WHERE n BETWEEN 2 AND 4"), [2, 3, 4]);
+        assert_eq!(
+            run("SELECT * WHERE label BETWEEN '25' AND \"50\""),
+            [3, 4, 5]
+        );
+
+        // A pattern that does not compile is refused, not a silent miss.
+        let mut query = parse("SELECT *").unwrap();
+        query.filter = Some(Expr::Condition(super::super::Condition {
+            field: "path".into(),
+            op: CompareOp::Matches,
+            value: serde_json::json!("(unclosed"),
+        }));
+        assert!(evaluate(&query, &events, 0, &[], Utc::now(), |_| true).is_err());
+    }
+
+    #[test]
+    fn test_lamport_bounds_of_in_list() {
+        let query = parse("SELECT * WHERE lamport_ts IN (7, 3, 12) AND n > 1").unwrap();
+        assert_eq!(lamport_bounds(query.filter.as_ref()), (3, 12));
+        let query = parse("SELECT * WHERE lamport_ts IN (3, 'x')").unwrap();
+        assert_eq!(lamport_bounds(query.filter.as_ref()), (0, u64::MAX));
+    }
+
+    #[test]
+    fn test_matches_event() {
+        let now = Utc::now();
+        let event = stream_event(4);
+        let matches = |text: &str| matches_event(&parse(text).unwrap(), &event, now);
+        assert!(matches("SELECT * FROM webhook WHERE even = true SINCE 5m"));
+        assert!(!matches("SELECT * FROM orders"));
+        assert!(!matches("SELECT * WHERE n > 4"));
+        assert!(!matches("SELECT * UNTIL -1h"));
+
+        // Without a timestamp, an event falls outside every time window.
+        let mut undated = event.clone();
+        undated.timestamp.clear();
+        let query = parse("SELECT * WHERE n = 4").unwrap();
+        assert!(matches_event(&query, &undated, now));
+        let query = parse("SELECT * WHERE n = 4 SINCE 5m").unwrap();
+        assert!(!matches_event(&query, &undated, now));
+    }
+
+    #[test]
+    fn test_journal_source_matches_buffered_events() {
+        let path = std::env::temp_dir().join(format!("cz-query-src-{}.db", std::process::id()));
+        let mut journal = Journal::open(&path, MIN_JOURNAL_SIZE + 4096).unwrap();
+        let mut cursor = Cursor::new(journal.index_ring_capacity());
+        let mut blob_offset = 0;
+        let mut buffered = Vec::new();
+        for ts in 1..=8u64 {
+            let payload = serde_json::json!({
+                "kind": if ts % 3 == 0 { "sell" } else { "buy" },
+                "amount": ts * 10,
+            });
+            let body = serde_json::to_vec(&payload).unwrap();
+            let mut packet = CausalEvent::new(0, 1, 0, 0, 0).to_bytes().to_vec();
+            packet.extend(&body);
+            let offset = journal.write_payload(blob_offset, &packet);
+            blob_offset = offset as usize + packet.len();
+            let slot = cursor.advance_head().unwrap();
+            let event = CausalEvent::new(ts, 1, 0, offset, crc32fast::hash(&body));
+            unsafe { journal.write_event_at(slot, &event) };
+            buffered.push(StreamEvent {
+                id: format!("orders-{}", ts),
+                connector_id: "orders".into(),
+                stream: "journal:orders.db".into(),
+                sequence: ts,
+                timestamp: Utc::now().to_rfc3339(),
+                payload,
+                metadata: [("lamport_ts".to_string(), ts.to_string())].into(),
+            });
+        }
+        // A raw payload that is not JSON reads back as hex.
+        let mut packet = CausalEvent::new(0, 1, 0, 0, 0).to_bytes().to_vec();
+        packet.extend([0xca, 0xfe]);
+        let offset = journal.write_payload(blob_offset, &packet);
+        let slot = cursor.advance_head().unwrap();
+        let raw = CausalEvent::new(9, 1, 0, offset, 0);
+        unsafe { journal.write_event_at(slot, &raw) };
+        journal.publish_cursor(&cursor);
+
+        let sources = [JournalSource {
+            connector_id: "journal-1".into(),
+            name: "journal:orders.db".into(),
+            journal: &journal,
+        }];
+        let run_both = |text: &str| {
+            let query = parse(text).unwrap();
+            let scanned = scan_journals(&query, &sources, &|_: &str| true).unwrap();
+            let (from_journal, _) =
+                evaluate(&query, &[], 0, &scanned, Utc::now(), |_| true).unwrap();
+            let (from_buffer, _) =
+                evaluate(&query, &buffered, 0, &[], Utc::now(), |_| true).unwrap();
+            let payloads = |r: &QueryResult| -> Vec<serde_json::Value> {
+                r.events.iter().map(|e| e.payload.clone()).collect()
+            };
+            assert_eq!(payloads(&from_journal), payloads(&from_buffer), "{}", text);
+            assert_eq!(
+                serde_json::to_value(&from_journal.table).unwrap(),
+                serde_json::to_value(&from_buffer.table).unwrap(),
+                "{}",
+                text
+            );
+            (scanned.len(), from_journal)
+        };
+
+        let (scanned, result) =
+            run_both("SELECT * FROM orders WHERE amount > 40 AND lamport_ts < 9");
+        assert_eq!((scanned, result.total), (4, 4));
+        // Bounds on lamport_ts seek into the ring instead of scanning it.
+        let (scanned, result) =
+            run_both("SELECT * FROM orders WHERE lamport_ts BETWEEN 3 AND 6 AND kind = 'buy'");
+        assert_eq!((scanned, result.total), (2, 2));
+        assert_eq!(
+            lamport_bounds(
+                parse("SELECT * WHERE lamport_ts BETWEEN 3 AND 6")
+                    .unwrap()
+                    .filter
+                    .as_ref()
+            ),
+            (3, 6)
+        );
+        assert_eq!(
+            lamport_bounds(
+                parse("SELECT * WHERE lamport_ts > 3 OR lamport_ts < 2")
+                    .unwrap()
+                    .filter
+                    .as_ref()
+            ),
+            (0, u64::MAX)
+        );
+        let (_, result) = run_both(
+            "SELECT kind, count(*), sum(amount) FROM orders WHERE lamport_ts <= 8 GROUP BY kind ORDER BY kind",
+        );
+        assert_eq!(result.table.unwrap().rows.len(), 2);
+
+        let query = parse("SELECT * FROM journal:orders WHERE lamport_ts = 9").unwrap();
+        let scanned = scan_journals(&query, &sources, &|_: &str| true).unwrap();
+        assert_eq!(scanned[0].payload, serde_json::json!("cafe"));
+        assert_eq!(scanned[0].metadata["slot"], slot.to_string());
+
+        // Journals are only read when named, and only if visible.
+        let all = parse("SELECT *").unwrap();
+        assert!(scan_journals(&all, &sources, &|_: &str| true)
+            .unwrap()
+            .is_empty());
+        let named = parse("SELECT * FROM orders").unwrap();
+        assert!(scan_journals(&named, &sources, &|_: &str| false)
+            .unwrap()
+            .is_empty());
+        let mut paged = named.clone();
+        paged.cursor = Some(QueryCursor(3));
+        assert!(scan_journals(&paged, &sources, &|_: &str| true).is_err());
+
+        // A joined journal is read whole: WHERE only filters the FROM side.
+        let hooks: Vec<StreamEvent> = [2u64, 5, 12]
+            .into_iter()
+            .map(|ts| {
+                let mut event = stream_event(ts);
+                event.metadata = [("lamport_ts".to_string(), ts.to_string())].into();
+                event
+            })
+            .collect();
+        let query =
+            parse("SELECT * FROM webhook JOIN orders ON lamport_ts WHERE lamport_ts > 4").unwrap();
+        assert!(reads_journals(&query, &sources));
+        let scanned = scan_journals(&query, &sources, &|_: &str| true).unwrap();
+        assert_eq!(scanned.len(), 9);
+        let (result, _) = evaluate(&query, &hooks, 0, &scanned, Utc::now(), |_| true).unwrap();
+        assert_eq!(result.total, 1);
+        assert_eq!(result.pairs[0].left.sequence, 5);
+        assert_eq!(result.pairs[0].right.stream, "journal:orders.db");
+        assert_eq!(result.pairs[0].right.payload["amount"], 50);
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    fn correlated(
+        connector: &str,
+        stream: &str,
+        n: u64,
+        trace: &str,
+        at: DateTime<Utc>,
+    ) -> StreamEvent {
+        StreamEvent {
+            id: format!("{}-{}", connector, n),
+            connector_id: connector.into(),
+            stream: stream.into(),
+            sequence: n,
+            timestamp: at.to_rfc3339(),
+            payload: serde_json::json!({ "n": n }),
+            metadata: [("trace_id".to_string(), trace.to_string())].into(),
+        }
+    }
+
+    #[test]
+    fn test_join_pairs_events_by_key_within_window() {
+        let t0 = Utc::now() - Duration::minutes(30);
+        let at = |minutes| t0 + Duration::minutes(minutes);
+        let events = vec![
+            correlated("github", "webhook:github", 1, "t-1", at(0)),
+            correlated("deploys", "kafka:deploys", 2, "t-1", at(2)),
+            // No deploy carries t-2.
+            correlated("github", "webhook:github", 3, "t-2", at(3)),
+            correlated("github", "webhook:github", 4, "t-3", at(4)),
+            // Too late for the push at minute 4, in time for the one at 12.
+            correlated("deploys", "kafka:deploys", 5, "t-3", at(10)),
+            correlated("github", "webhook:github", 6, "t-3", at(12)),
+            // Two deploys of t-1; the second is 20 minutes after the push.
+            correlated("deploys", "kafka:deploys", 7, "t-1", at(20)),
+            correlated("deploys", "kafka:deploys", 8, "t-4", at(21)),
+        ];
+        let run = |text: &str| {
+            let query = parse(text).unwrap();
+            evaluate(&query, &events, 0, &[], Utc::now(), |_| true)
+                .unwrap()
+                .0
+        };
+        let pairs = |result: &QueryResult| -> Vec<(u64, u64)> {
+            result
+                .pairs
+                .iter()
+                .map(|p| (p.left.sequence, p.right.sequence))
+                .collect()
+        };
+
+        let result =
+            run("SELECT * FROM webhook:github JOIN kafka:deploys ON metadata.trace_id WITHIN 5m");
+        assert_eq!(pairs(&result), [(1, 2), (6, 5)]);
+        assert!(result.events.is_empty());
+        assert_eq!(result.streams_searched, ["kafka:deploys", "webhook:github"]);
+
+        // Without a window every shared key pairs; a key only one side has
+        // pairs with nothing.
+        let result = run("SELECT * FROM github JOIN deploys ON trace_id");
+        assert_eq!(pairs(&result), [(1, 2), (1, 7), (4, 5), (6, 5)]);
+        assert_eq!((result.total, result.has_more), (4, false));
+
+        // WHERE filters the FROM side, and pairs page like events.
+        let result =
+            run("SELECT * FROM github JOIN deploys ON trace_id WHERE n > 1 LIMIT 1 OFFSET 1");
+        assert_eq!(pairs(&result), [(6, 5)]);
+        assert_eq!((result.total, result.has_more), (2, false));
+
+        // An event is never its own pair, even joining a stream to itself.
+        let result = run("SELECT * FROM deploys JOIN deploys ON trace_id");
+        assert_eq!(pairs(&result), [(2, 7), (7, 2)]);
+    }
+
+    #[test]
+    fn test_join_cardinality_is_bounded() {
+        let now = Utc::now();
+        let mut events: Vec<StreamEvent> = (0..MAX_JOIN_MATCHES_PER_KEY as u64 + 20)
+            .map(|n| correlated("deploys", "kafka:deploys", n, "hot", now))
+            .collect();
+        events.push(correlated("github", "webhook:github", 1000, "hot", now));
+        let query = parse("SELECT * FROM github JOIN deploys ON trace_id LIMIT 5").unwrap();
+        let (result, _) = evaluate(&query, &events, 0, &[], now, |_| true).unwrap();
+        // Matches per key are capped; the rest of a key's events are dropped.
+        assert_eq!(result.total, MAX_JOIN_MATCHES_PER_KEY);
+        assert_eq!(result.pairs.len(), 5);
+        assert!(result.has_more);
+
+        // Past the pair cap the query is refused rather than materialized.
+        let pushes = MAX_JOIN_PAIRS / MAX_JOIN_MATCHES_PER_KEY + 1;
+        events.extend(
+            (0..pushes as u64)
+                .map(|n| correlated("github", "webhook:github", 2000 + n, "hot", now)),
+        );
+        let err = evaluate(&query, &events, 0, &[], now, |_| true).unwrap_err();
+        assert!(err.contains("more than 10000 pairs"), "{}", err);
+    }
+}
@@ -0,0 +1,440 @@
+//! # Query Explain
+//!
+//! Shows how a query would run without running it: the parsed structure,
+//! the streams and journals it reads, why, and how many events that means,
+//! where each condition's field is looked up, the time window in effect,
+//! and warnings for anything that would quietly match nothing, such as a
+//! field none of the scanned events has. A query asks for this with an
+//! `EXPLAIN` prefix.
+
+use super::executor::{self, FieldPath, JournalSource};
+use super::{Condition, Expr, Query};
+use crate::connectors::registry::ConnectorRegistry;
+use crate::connectors::StreamEvent;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// What [`explain`] found out about a query.
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryExplain {
+    /// The query as parsed.
+    pub query: Query,
+    /// Buffered streams the query reads, with their event counts.
+    pub streams: Vec<SourceScan>,
+    /// Journals named in `FROM`, with their events within the query's
+    /// `lamport_ts` bounds.
+    pub journals: Vec<SourceScan>,
+    /// Events the query would look at, over `streams` and `journals`.
+    pub estimated_scan: usize,
+    /// `SINCE` and `UNTIL` as instants; `None` when absent or not a time.
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// Every comparison in `WHERE`, in order.
+    pub conditions: Vec<ConditionScan>,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceScan {
+    pub stream: String,
+    pub events: usize,
+    /// Why the query reads it.
+    pub selected_by: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConditionScan {
+    #[serde(flatten)]
+    pub condition: Condition,
+    /// Scanned buffered events that have the field at all.
+    pub present: usize,
+    /// Where the field is looked up, in order, with how many scanned
+    /// buffered events it was found at each.
+    pub paths: Vec<PathScan>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PathScan {
+    #[serde(flatten)]
+    pub path: FieldPath,
+    pub events: usize,
+}
+
+/// Explain `query` against the registry buffer and `journals` as of now,
+/// seen as a caller allowed the streams `stream_allowed` accepts.
+/// `warnings` from [`parse_with_warnings`](super::parser::parse_with_warnings)
+/// are passed through.
+pub async fn explain(
+    query: Query,
+    warnings: Vec<String>,
+    registry: &Arc<ConnectorRegistry>,
+    journals: &[JournalSource<'_>],
+    stream_allowed: impl Fn(&str) -> bool,
+) -> QueryExplain {
+    registry
+        .with_buffered_events(|_, events| {
+            explain_events(
+                query,
+                warnings,
+                events,
+                journals,
+                Utc::now(),
+                stream_allowed,
+            )
+        })
+        .await
+}
+
+fn explain_events(
+    query: Query,
+    mut warnings: Vec<String>,
+    events: &[StreamEvent],
+    journals: &[JournalSource<'_>],
+    now: DateTime<Utc>,
+    stream_allowed: impl Fn(&str) -> bool,
+) -> QueryExplain {
+    if let Err(e) = query.validate() {
+        warnings.push(e);
+    }
+
+    let scanned: Vec<&StreamEvent> = events
+        .iter()
+        .filter(|e| stream_allowed(&e.stream) && executor::in_sources(&query, e))
+        .collect();
+    let mut per_stream: BTreeMap<&str, (&str, usize)> = BTreeMap::new();
+    for event in &scanned {
+        per_stream
+            .entry(&event.stream)
+            .or_insert((&event.connector_id, 0))
+            .1 += 1;
+    }
+    let streams: Vec<SourceScan> = per_stream
+        .into_iter()
+        .map(|(stream, (connector_id, events))| SourceScan {
+            stream: stream.to_string(),
+            events,
+            selected_by: selected_by(&query, connector_id, stream),
+        })
+        .collect();
+
+    let read: Vec<&JournalSource> = journals
+        .iter()
+        .filter(|j| j.named_by(&query) && stream_allowed(&j.name))
+        .collect();
+    let (low, high) = executor::lamport_bounds(query.filter.as_ref());
+    let journal_scans: Vec<SourceScan> = read
+        .iter()
+        .map(|source| SourceScan {
+            stream: source.name.clone(),
+            events: events_between(source, low, high),
+            selected_by: selected_by(&query, &source.connector_id, &source.name),
+        })
+        .collect();
+
+    for from in &query.from {
+        let matched = scanned
+            .iter()
+            .any(|e| e.stream.contains(from.as_str()) || e.connector_id.contains(from.as_str()))
+            || read
+                .iter()
+                .any(|j| j.name.contains(from.as_str()) || j.connector_id.contains(from.as_str()));
+        if !matched {
+            warnings.push(format!(
+                "FROM '{}' matches no buffered stream or open journal",
+                from
+            ));
+        }
+    }
+
+    let mut conditions = Vec::new();
+    collect_conditions(query.filter.as_ref(), &mut conditions);
+    let conditions: Vec<ConditionScan> = conditions
+        .into_iter()
+        .map(|condition| {
+            let mut paths: Vec<PathScan> = executor::field_paths(&condition.field)
+                .into_iter()
+                .map(|path| PathScan { path, events: 0 })
+                .collect();
+            for event in &scanned {
+                if let Some(scan) = paths.iter_mut().find(|scan| scan.path.resolves(event)) {
+                    scan.events += 1;
+                }
+            }
+            ConditionScan {
+                present: paths.iter().map(|scan| scan.events).sum(),
+                paths,
+                condition: condition.clone(),
+            }
+        })
+        .collect();
+    // Journal events are not sampled, so only an all-buffer scan can tell.
+    if !scanned.is_empty() && read.is_empty() {
+        let mut unknown: Vec<&ConditionScan> = Vec::new();
+        for c in conditions.iter().filter(|c| c.present == 0) {
+            if !unknown
+                .iter()
+                .any(|u| u.condition.field == c.condition.field)
+            {
+                unknown.push(c);
+            }
+        }
+        for c in unknown {
+            let tried: Vec<String> = c.paths.iter().map(|scan| scan.path.to_string()).collect();
+            warnings.push(format!(
+                "Field '{}' resolved to nothing in the {} scanned events: no {}",
+                c.condition.field,
+                scanned.len(),
+                tried.join(" or ")
+            ));
+        }
+    }
+
+    for (clause, value) in [("SINCE", &query.since), ("UNTIL", &query.until)] {
+        let Some(value) = value else { continue };
+        if executor::parse_time_expr(value, now).is_none() {
+            warnings.push(format!(
+                "{} '{}' is not a time and is ignored",
+                clause, value
+            ));
+        } else if !read.is_empty() {
+            warnings.push(format!(
+                "{} excludes journal events, which have no wall-clock time",
+                clause
+            ));
+        }
+    }
+
+    let estimated_scan = scanned.len() + journal_scans.iter().map(|j| j.events).sum::<usize>();
+    let (since, until) = executor::time_bounds(&query, now);
+    QueryExplain {
+        query,
+        streams,
+        journals: journal_scans,
+        estimated_scan,
+        since,
+        until,
+        conditions,
+        warnings,
+    }
+}
+
+/// Why `query` reads the stream `name` of connector `connector_id`.
+fn selected_by(query: &Query, connector_id: &str, name: &str) -> String {
+    if query.from.is_empty() {
+        return "No FROM, so every stream".into();
+    }
+    query
+        .from
+        .iter()
+        .find_map(|from| {
+            if name.contains(from.as_str()) {
+                Some(format!("FROM '{}' is part of the stream name", from))
+            } else if connector_id.contains(from.as_str()) {
+                Some(format!(
+                    "FROM '{}' is part of its connector id '{}'",
+                    from, connector_id
+                ))
+            } else {
+                None
+            }
+        })
+        .unwrap_or_default()
+}
+
+/// The comparisons in `filter`, left to right.
+fn collect_conditions<'a>(filter: Option<&'a Expr>, out: &mut Vec<&'a Condition>) {
+    match filter {
+        None => {}
+        Some(Expr::Condition(condition)) => out.push(condition),
+        Some(Expr::And(terms) | Expr::Or(terms)) => {
+            for term in terms {
+                collect_conditions(Some(term), out);
+            }
+        }
+        Some(Expr::Not(term)) => collect_conditions(Some(term), out),
+    }
+}
+
+/// Ring entries of `source` with `low <= lamport_ts <= high`, found by
+/// binary search in each partition.
+fn events_between(source: &JournalSource<'_>, low: u64, high: u64) -> usize {
+    let journal = source.journal;
+    (0..journal.partitions())
+        .filter_map(|p| journal.partition_cursor_view(p))
+        .map(|cursor| {
+            let start = journal.find_first_ge(&cursor, low);
+            let end = match high.checked_add(1) {
+                Some(past) => journal.find_first_ge(&cursor, past),
+                None => cursor.len(),
+            };
+            end.saturating_sub(start)
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::parser::{parse, parse_with_warnings};
+    use crate::query::CompareOp;
+
+    fn event(stream: &str, payload: serde_json::Value) -> StreamEvent {
+        StreamEvent {
+            id: format!("{}-1", stream),
+            connector_id: stream.replace(':', "-"),
+            stream: stream.into(),
+            sequence: 1,
+            timestamp: Utc::now().to_rfc3339(),
+            payload,
+            metadata: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_explain_lists_conditions_and_flags_unknown_fields() {
+        let events = [
+            event("webhook:github", serde_json::json!({"action": "opened"})),
+            event("webhook:github", serde_json::json!({"action": "closed"})),
+            event("kafka:orders", serde_json::json!({"amount": 5})),
+        ];
+        let (query, warnings) = parse_with_warnings(
+            "SELECT * FROM github WHERE action = 'opened' AND NOT (acton = 'x' OR sequence > 0) \
+             SINCE yesterday LIMIT all",
+        )
+        .unwrap();
+        let explain = explain_events(query, warnings, &events, &[], Utc::now(), |_| true);
+
+        let conditions: Vec<(&str, &CompareOp, usize)> = explain
+            .conditions
+            .iter()
+            .map(|c| (c.condition.field.as_str(), &c.condition.op, c.present))
+            .collect();
+        assert_eq!(
+            conditions,
+            [
+                ("action", &CompareOp::Eq, 2),
+                ("acton", &CompareOp::Eq, 0),
+                ("sequence", &CompareOp::Gt, 2),
+            ]
+        );
+        assert_eq!(explain.streams.len(), 1);
+        assert_eq!(
+            (
+                explain.streams[0].stream.as_str(),
+                explain.streams[0].events
+            ),
+            ("webhook:github", 2)
+        );
+        assert_eq!(
+            explain.streams[0].selected_by,
+            "FROM 'github' is part of the stream name"
+        );
+        assert_eq!(explain.estimated_scan, 2);
+        // `action` is in the payload, `sequence` is the event's own.
+        let paths = |i: usize| -> Vec<serde_json::Value> {
+            explain.conditions[i]
+                .paths
+                .iter()
+                .map(|scan| serde_json::json!([scan.path, scan.events]))
+                .collect()
+        };
+        assert_eq!(
+            paths(0),
+            [
+                serde_json::json!([{"source": "metadata", "key": "action"}, 0]),
+                serde_json::json!([{"source": "payload", "pointer": "/action"}, 2]),
+            ]
+        );
+        assert_eq!(
+            paths(2),
+            [serde_json::json!([{"source": "event", "name": "sequence"}, 2])]
+        );
+        assert_eq!((explain.since, explain.until), (None, None));
+        assert_eq!(
+            explain.warnings,
+            [
+                "LIMIT 'all' is not a number; using 100",
+                "Field 'acton' resolved to nothing in the 2 scanned events: no metadata key \
+                 'acton' or payload pointer '/acton'",
+                "SINCE 'yesterday' is not a time and is ignored",
+            ]
+        );
+
+        // Streams the caller may not read are not scanned.
+        let query = parse("SELECT * FROM orders WHERE amount > 1").unwrap();
+        let explain = explain_events(query, Vec::new(), &events, &[], Utc::now(), |s| {
+            s != "kafka:orders"
+        });
+        assert_eq!(explain.estimated_scan, 0);
+        assert_eq!(
+            explain.warnings,
+            ["FROM 'orders' matches no buffered stream or open journal"]
+        );
+
+        // A typo'd field says so, whatever the stream was matched by.
+        let now = Utc::now();
+        let query = parse(
+            "EXPLAIN SELECT * FROM kafka-orders WHERE metadata.tenant = 'a' SINCE 1h UNTIL 5m",
+        )
+        .unwrap();
+        let explain = explain_events(query, Vec::new(), &events, &[], now, |_| true);
+        assert!(explain.query.explain);
+        assert_eq!(
+            explain.streams[0].selected_by,
+            "FROM 'kafka-orders' is part of its connector id 'kafka-orders'"
+        );
+        assert_eq!(
+            explain.warnings,
+            [
+                "Field 'metadata.tenant' resolved to nothing in the 1 scanned events: \
+                 no metadata key 'tenant' or payload pointer '/metadata/tenant'"
+            ]
+        );
+        assert_eq!(
+            (explain.since, explain.until),
+            (
+                Some(now - chrono::Duration::hours(1)),
+                Some(now - chrono::Duration::minutes(5))
+            )
+        );
+    }
+
+    #[test]
+    fn test_explain_counts_journal_events_within_bounds() {
+        use cz_core::CausalEvent;
+        use cz_io::cursor::Cursor;
+        use cz_io::journal::{Journal, MIN_JOURNAL_SIZE};
+
+        let path = std::env::temp_dir().join(format!("cz-explain-{}.db", std::process::id()));
+        let mut journal = Journal::open(&path, MIN_JOURNAL_SIZE + 4096).unwrap();
+        let mut cursor = Cursor::new(journal.index_ring_capacity());
+        for ts in 1..=10u64 {
+            let slot = cursor.advance_head().unwrap();
+            unsafe { journal.write_event_at(slot, &CausalEvent::new(ts, 1, 0, 0, 0)) };
+        }
+        journal.publish_cursor(&cursor);
+        let sources = [JournalSource {
+            connector_id: "journal-1".into(),
+            name: "journal:node.db".into(),
+            journal: &journal,
+        }];
+
+        let query = parse("SELECT * FROM journal:node WHERE lamport_ts BETWEEN 3 AND 6").unwrap();
+        let explain = explain_events(query, Vec::new(), &[], &sources, Utc::now(), |_| true);
+        assert_eq!(explain.journals.len(), 1);
+        assert_eq!((explain.journals[0].events, explain.estimated_scan), (4, 4));
+        assert!(explain.warnings.is_empty(), "{:?}", explain.warnings);
+
+        let query = parse("SELECT * FROM journal:node WHERE lamport_ts > 8 SINCE 1h").unwrap();
+        let explain = explain_events(query, Vec::new(), &[], &sources, Utc::now(), |_| true);
+        assert_eq!(explain.estimated_scan, 2);
+        assert_eq!(
+            explain.warnings,
+            ["SINCE excludes journal events, which have no wall-clock time"]
+        );
+
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -2,20 +2,27 @@
 //!
 //! Simple query DSL for searching and filtering events across all connected
 //! data streams. Supports field comparisons, temporal ranges, and cross-stream
-//! correlation by trace_id.
+//! correlation by a shared key such as a trace id, with `JOIN`.
 
+pub mod cache;
 pub mod executor;
+pub mod explain;
 pub mod parser;
+pub mod subscriptions;
 
 use serde::{Deserialize, Serialize};
 
 /// A parsed query.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Query {
+    /// `SELECT` columns (empty = `*`, the matching events themselves).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub select: Vec<SelectItem>,
     /// Stream names to search (empty = all).
     pub from: Vec<String>,
-    /// Filter conditions.
-    pub conditions: Vec<Condition>,
+    /// `WHERE` filter (none = every event).
+    #[serde(default)]
+    pub filter: Option<Expr>,
     /// Temporal range.
     pub since: Option<String>,
     pub until: Option<String>,
@@ -23,15 +30,313 @@ pub struct Query {
     pub limit: usize,
     /// Offset for pagination.
     pub offset: usize,
+    /// Continue after the page that returned this cursor.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<QueryCursor>,
+    /// Fields whose values split matching events into groups.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub group_by: Vec<String>,
+    /// Row order of an aggregate query (default: first group seen first).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order_by: Option<OrderBy>,
+    /// Pair each matching event with events of another stream.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub join: Option<Join>,
+    /// `EXPLAIN` prefix: describe how the query would run instead of
+    /// running it.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub explain: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl Query {
+    /// Whether the query answers with a [`QueryTable`] rather than events.
+    pub fn is_aggregate(&self) -> bool {
+        !self.group_by.is_empty()
+            || self
+                .select
+                .iter()
+                .any(|item| matches!(item, SelectItem::Aggregate(_)))
+    }
+
+    /// Column names of the table an aggregate query answers with. Plain
+    /// `SELECT` fields must be grouped on; without a `SELECT` list, the
+    /// grouped fields are followed by `count`.
+    pub fn columns(&self) -> Result<Vec<String>, String> {
+        let mut columns = Vec::new();
+        if self.select.is_empty() {
+            columns.extend(self.group_by.iter().cloned());
+            columns.push("count".to_string());
+        }
+        for item in &self.select {
+            if let SelectItem::Field(field) = item {
+                if !self.group_by.contains(field) {
+                    return Err(format!("'{}' must appear in GROUP BY", field));
+                }
+            }
+            columns.push(item.column_name());
+        }
+        if let Some(order) = &self.order_by {
+            if !columns.contains(&order.column) {
+                return Err(format!(
+                    "ORDER BY '{}' names no column; columns are {}",
+                    order.column,
+                    columns.join(", ")
+                ));
+            }
+        }
+        Ok(columns)
+    }
+
+    /// Reject clauses that do not fit together, e.g. `ORDER BY` on a
+    /// query that lists events.
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(filter) = &self.filter {
+            filter.check()?;
+        }
+        if let Some(join) = &self.join {
+            join.check()?;
+            if self.is_aggregate() {
+                return Err("JOIN cannot be combined with aggregates or GROUP BY".into());
+            }
+            if self.cursor.is_some() {
+                return Err("A cursor cannot page through JOIN pairs; use OFFSET".into());
+            }
+        }
+        if self.is_aggregate() {
+            self.columns().map(|_| ())
+        } else if self.order_by.is_some() {
+            Err("ORDER BY needs an aggregate or GROUP BY query".into())
+        } else if !self.select.is_empty() {
+            Err("SELECT lists fields without an aggregate or GROUP BY; use SELECT *".into())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// One `SELECT` column.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectItem {
+    /// The value of a `GROUP BY` field.
+    Field(String),
+    Aggregate(Aggregate),
+}
+
+impl SelectItem {
+    /// Name of the column in [`QueryTable::columns`], e.g. `count` or
+    /// `avg(payload.amount)`, unless renamed with `AS`.
+    pub fn column_name(&self) -> String {
+        match self {
+            SelectItem::Field(field) => field.clone(),
+            SelectItem::Aggregate(agg) => match (&agg.alias, &agg.field) {
+                (Some(alias), _) => alias.clone(),
+                (None, None) => agg.func.name().to_string(),
+                (None, Some(field)) => format!("{}({})", agg.func.name(), field),
+            },
+        }
+    }
+}
+
+/// An aggregate over the events of a group, e.g. `avg(payload.amount)`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Aggregate {
+    pub func: AggregateFn,
+    /// Field aggregated; `None` only for `count(*)`.
+    #[serde(default)]
+    pub field: Option<String>,
+    #[serde(default)]
+    pub alias: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AggregateFn {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl AggregateFn {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AggregateFn::Count => "count",
+            AggregateFn::Sum => "sum",
+            AggregateFn::Avg => "avg",
+            AggregateFn::Min => "min",
+            AggregateFn::Max => "max",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            AggregateFn::Count,
+            AggregateFn::Sum,
+            AggregateFn::Avg,
+            AggregateFn::Min,
+            AggregateFn::Max,
+        ]
+        .into_iter()
+        .find(|f| f.name().eq_ignore_ascii_case(name))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OrderBy {
+    /// A [`QueryTable`] column name.
+    pub column: String,
+    #[serde(default)]
+    pub descending: bool,
+}
+
+/// `JOIN stream ON field WITHIN window`: the events of `stream` whose
+/// `field` equals that of a matching event, at most `window` apart.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Join {
+    /// Stream or connector name, matched like a `FROM` entry.
+    pub stream: String,
+    /// Correlation key, resolved on both sides like a `WHERE` field.
+    pub on: String,
+    /// Most time between paired events, e.g. `5m`; none pairs events
+    /// whenever they were seen.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub within: Option<String>,
+}
+
+impl Join {
+    fn check(&self) -> Result<(), String> {
+        if self.stream.is_empty() || self.on.is_empty() {
+            return Err("JOIN needs a stream and an ON field".into());
+        }
+        match &self.within {
+            Some(within) if executor::relative_window(within).is_none() => Err(format!(
+                "WITHIN '{}' is not a duration such as 30s or 5m",
+                within
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether `stream` of connector `connector_id` is the joined stream.
+    pub fn names(&self, connector_id: &str, stream: &str) -> bool {
+        stream.contains(self.stream.as_str()) || connector_id.contains(self.stream.as_str())
+    }
+}
+
+/// One row of a `JOIN` query: an event the query matched and an event of
+/// the joined stream with the same key.
+#[derive(Debug, Clone, Serialize)]
+pub struct JoinedPair {
+    pub left: crate::connectors::StreamEvent,
+    pub right: crate::connectors::StreamEvent,
+}
+
+/// Rows of an aggregate query, one per group.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct QueryTable {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<serde_json::Value>>,
+}
+
+/// Where a page of [`QueryResult`] ended: the buffer position of the last
+/// event it returned. Positions count every event ever buffered, so a
+/// cursor stays put while new events arrive and old ones are evicted.
+///
+/// Opaque to clients: URL-safe base64 of the position, little-endian.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct QueryCursor(pub u64);
+
+impl QueryCursor {
+    pub fn encode(&self) -> String {
+        use base64::Engine;
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(self.0.to_le_bytes())
+    }
+
+    pub fn decode(raw: &str) -> Option<Self> {
+        use base64::Engine;
+        let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(raw)
+            .ok()?;
+        Some(Self(u64::from_le_bytes(bytes.try_into().ok()?)))
+    }
+}
+
+impl TryFrom<String> for QueryCursor {
+    type Error = String;
+
+    fn try_from(raw: String) -> Result<Self, String> {
+        Self::decode(&raw).ok_or_else(|| format!("Invalid query cursor '{}'", raw))
+    }
+}
+
+impl From<QueryCursor> for String {
+    fn from(cursor: QueryCursor) -> Self {
+        cursor.encode()
+    }
+}
+
+/// A boolean filter over event fields, e.g. the `WHERE` clause
+/// `a = 1 AND (b > 2 OR NOT c = 3)`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Expr {
+    Condition(Condition),
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Not(Box<Expr>),
+}
+
+impl Expr {
+    /// Reject a comparison whose value its operator cannot use, see
+    /// [`Condition::check`].
+    pub fn check(&self) -> Result<(), String> {
+        match self {
+            Expr::Condition(condition) => condition.check(),
+            Expr::And(terms) | Expr::Or(terms) => terms.iter().try_for_each(Expr::check),
+            Expr::Not(inner) => inner.check(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Condition {
     pub field: String,
     pub op: CompareOp,
     pub value: serde_json::Value,
 }
 
+impl Condition {
+    /// `IN` needs a list of values and `MATCHES` a pattern that compiles;
+    /// anything else would quietly match nothing.
+    pub fn check(&self) -> Result<(), String> {
+        match (&self.op, &self.value) {
+            (CompareOp::In, serde_json::Value::Array(_)) => Ok(()),
+            (CompareOp::In, _) => Err(format!("IN on '{}' needs a list of values", self.field)),
+            (CompareOp::Matches, serde_json::Value::String(pattern)) => {
+                regex::Regex::new(pattern).map(|_| ()).map_err(|e| {
+                    // Syntax errors draw the pattern over several lines;
+                    // the last says what is wrong.
+                    let e = e.to_string();
+                    let reason = e.lines().last().unwrap_or_default();
+                    format!(
+                        "Invalid MATCHES pattern '{}': {}",
+                        pattern,
+                        reason.trim_start_matches("error: ")
+                    )
+                })
+            }
+            (CompareOp::Matches, _) => Err(format!(
+                "MATCHES on '{}' needs a string pattern",
+                self.field
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum CompareOp {
@@ -43,15 +348,61 @@ pub enum CompareOp {
     Lte,
     Contains,
     StartsWith,
+    /// Equal to any value of the array `value`.
+    In,
+    /// Matches the regular expression `value`, anywhere in the text.
+    Matches,
 }
 
 /// Query execution result.
 #[derive(Debug, Clone, Serialize)]
 pub struct QueryResult {
+    /// Matching events; empty for an aggregate query.
     pub events: Vec<crate::connectors::StreamEvent>,
+    /// Rows of an aggregate query, paginated like `events`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub table: Option<QueryTable>,
+    /// Pairs of a `JOIN` query, paginated like `events`, which is then
+    /// empty.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub pairs: Vec<JoinedPair>,
+    /// Matching events, groups for an aggregate query, or pairs for a
+    /// `JOIN`.
     pub total: usize,
     pub query_time_ms: u64,
     pub streams_searched: Vec<String>,
+    /// Whether more matching events follow this page.
+    pub has_more: bool,
+    /// Pass back as `cursor` to continue after this page; stable however
+    /// many events arrive in between, unlike `offset`. Present once a page
+    /// has returned an event, so it can also be polled for new ones.
+    pub next_cursor: Option<QueryCursor>,
+    /// Events the query looked at, among the streams the caller may read.
+    pub rows_scanned: usize,
+    /// Events that matched, before paging or grouping.
+    pub rows_matched: usize,
+    /// Whether the result was served from the [`cache`].
+    pub cache_hit: bool,
+}
+
+/// One journal event matched by [`executor::execute_journal`].
+#[derive(Debug, Clone, Serialize)]
+pub struct JournalEventRecord {
+    pub slot: usize,
+    pub lamport_ts: u64,
+    pub node_id: u32,
+    pub stream_id: u16,
+    pub flags: u16,
+    pub payload_offset: u64,
+    pub checksum: u32,
+}
+
+/// Result of running a query straight against a journal file.
+#[derive(Debug, Clone, Serialize)]
+pub struct JournalQueryResult {
+    pub events: Vec<JournalEventRecord>,
+    pub total: usize,
+    pub query_time_ms: u64,
 }
 
 /// Request body for executing a query.
@@ -61,4 +412,9 @@ pub struct QueryRequest {
     pub query: Option<String>,
     /// Structured query (alternative to raw text).
     pub structured: Option<Query>,
+    /// `next_cursor` of the previous page; overrides the query's own.
+    pub cursor: Option<QueryCursor>,
+    /// Answer with the [`explain::QueryExplain`] instead of running it.
+    #[serde(default)]
+    pub explain: bool,
 }
@@ -5,27 +5,115 @@
 //! Syntax:
 //! ```text
 //! SELECT * FROM stream1, stream2 WHERE field > 100 AND field2 = "value" SINCE 5m LIMIT 100
+//! SELECT count(*), avg(payload.amount) FROM orders GROUP BY stream ORDER BY count DESC LIMIT 10
+//! SELECT * FROM webhook:github JOIN kafka:deploys ON metadata.trace_id WITHIN 5m
 //! ```
+//!
+//! `WHERE` conditions combine with `AND`/`&&`, `OR`/`||`, `NOT` and
+//! parentheses into an [`Expr`] tree. `NOT` binds tightest, then `AND`,
+//! then `OR`. `field BETWEEN a AND b` is shorthand for
+//! `field >= a AND field <= b`. `field IN (a, "b")` matches any value in
+//! the list, and `field MATCHES "regex"` a pattern, checked here so a bad
+//! one is an error rather than a filter that never matches.
+//!
+//! A leading `EXPLAIN` sets [`Query::explain`], asking for a description
+//! of how the query would run rather than its results.
+//!
+//! Errors are [`ParseError`]s carrying the byte span of the offending text
+//! in the input, so a client can underline it.
+
+use super::{Aggregate, AggregateFn, CompareOp, Condition, Expr, Join, OrderBy, Query, SelectItem};
+use serde::Serialize;
+use std::fmt;
+use std::ops::Range;
+
+/// Why a query did not parse, and where.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ParseError {
+    pub message: String,
+    /// Byte offsets of the offending text in the input; `None` for errors
+    /// about the query as a whole, such as an aggregate without `GROUP BY`.
+    pub span: Option<Range<usize>>,
+}
+
+impl ParseError {
+    fn at(message: impl Into<String>, span: Range<usize>) -> Self {
+        Self {
+            message: message.into(),
+            span: Some(span),
+        }
+    }
+}
 
-use super::{CompareOp, Condition, Query};
+impl From<String> for ParseError {
+    fn from(message: String) -> Self {
+        Self {
+            message,
+            span: None,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)?;
+        match &self.span {
+            Some(span) => write!(f, " at {}..{}", span.start, span.end),
+            None => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
 
 /// Parse a raw query string into a [`Query`] struct.
-pub fn parse(input: &str) -> Result<Query, String> {
-    let input = input.trim();
-    let upper = input.to_uppercase();
+pub fn parse(input: &str) -> Result<Query, ParseError> {
+    parse_with_warnings(input).map(|(query, _)| query)
+}
+
+/// [`parse`], also saying what it had to ignore: text before the first
+/// clause, or a `LIMIT`/`OFFSET` that is not a number.
+pub fn parse_with_warnings(input: &str) -> Result<(Query, Vec<String>), ParseError> {
+    let mut warnings = Vec::new();
 
     let mut query = Query {
+        select: Vec::new(),
         from: Vec::new(),
-        conditions: Vec::new(),
+        filter: None,
         since: None,
         until: None,
         limit: 100,
         offset: 0,
+        cursor: None,
+        group_by: Vec::new(),
+        order_by: None,
+        join: None,
+        explain: false,
     };
 
+    let first_clause = std::iter::once(&["SELECT"][..])
+        .chain(CLAUSES)
+        .filter_map(|words| find_keyword(input, words))
+        .map(|(pos, _)| pos)
+        .min()
+        .unwrap_or(input.len());
+    let ignored = input[..first_clause].trim();
+    if ignored.eq_ignore_ascii_case("EXPLAIN") {
+        query.explain = true;
+    } else if !ignored.is_empty() {
+        warnings.push(format!("Ignored '{}' before the first clause", ignored));
+    }
+
+    // Extract SELECT list
+    if let Some(select_start) = find_clause(input, &["SELECT"]) {
+        let after_select = &input[select_start..];
+        let end = find_keyword_pos(after_select);
+        query.select = parse_select(&after_select[..end], select_start)?;
+    }
+
     // Extract FROM clause
-    if let Some(from_pos) = upper.find("FROM ") {
-        let after_from = &input[from_pos + 5..];
+    if let Some(from_start) = find_clause(input, &["FROM"]) {
+        let after_from = &input[from_start..];
         let end = find_keyword_pos(after_from);
         let from_str = after_from[..end].trim();
         query.from = from_str
@@ -35,129 +123,645 @@ pub fn parse(input: &str) -> Result<Query, String> {
             .collect();
     }
 
+    // Extract JOIN clause
+    if let Some(join_start) = find_clause(input, &["JOIN"]) {
+        let after_join = &input[join_start..];
+        let end = find_keyword_pos(after_join);
+        query.join = Some(parse_join(&after_join[..end], join_start)?);
+    }
+
     // Extract WHERE clause
-    if let Some(where_pos) = upper.find("WHERE ") {
-        let after_where = &input[where_pos + 6..];
+    if let Some(where_start) = find_clause(input, &["WHERE"]) {
+        let after_where = &input[where_start..];
         let end = find_keyword_pos(after_where);
-        let where_str = after_where[..end].trim();
-        query.conditions = parse_conditions(where_str)?;
+        query.filter = parse_filter_at(&after_where[..end], where_start)?;
     }
 
     // Extract SINCE clause
-    if let Some(since_pos) = upper.find("SINCE ") {
-        let after_since = &input[since_pos + 6..];
+    if let Some(since_start) = find_clause(input, &["SINCE"]) {
+        let after_since = &input[since_start..];
         let end = find_keyword_pos(after_since);
         let since_str = after_since[..end].trim();
         query.since = Some(since_str.to_string());
     }
 
     // Extract UNTIL clause
-    if let Some(until_pos) = upper.find("UNTIL ") {
-        let after_until = &input[until_pos + 6..];
+    if let Some(until_start) = find_clause(input, &["UNTIL"]) {
+        let after_until = &input[until_start..];
         let end = find_keyword_pos(after_until);
         let until_str = after_until[..end].trim();
         query.until = Some(until_str.to_string());
     }
 
     // Extract LIMIT clause
-    if let Some(limit_pos) = upper.find("LIMIT ") {
-        let after_limit = &input[limit_pos + 6..];
+    if let Some(limit_start) = find_clause(input, &["LIMIT"]) {
+        let after_limit = &input[limit_start..];
         let end = find_keyword_pos(after_limit);
         let limit_str = after_limit[..end].trim();
-        if let Ok(n) = limit_str.parse::<usize>() {
-            query.limit = n;
+        match limit_str.parse::<usize>() {
+            Ok(n) => query.limit = n,
+            Err(_) => warnings.push(format!(
+                "LIMIT '{}' is not a number; using {}",
+                limit_str, query.limit
+            )),
         }
     }
 
     // Extract OFFSET clause
-    if let Some(offset_pos) = upper.find("OFFSET ") {
-        let after_offset = &input[offset_pos + 7..];
+    if let Some(offset_start) = find_clause(input, &["OFFSET"]) {
+        let after_offset = &input[offset_start..];
         let end = find_keyword_pos(after_offset);
         let offset_str = after_offset[..end].trim();
-        if let Ok(n) = offset_str.parse::<usize>() {
-            query.offset = n;
+        match offset_str.parse::<usize>() {
+            Ok(n) => query.offset = n,
+            Err(_) => warnings.push(format!("OFFSET '{}' is not a number; using 0", offset_str)),
         }
     }
 
-    Ok(query)
+    // Extract GROUP BY clause
+    if let Some(group_start) = find_clause(input, &["GROUP", "BY"]) {
+        let after_group = &input[group_start..];
+        let end = find_keyword_pos(after_group);
+        query.group_by = after_group[..end]
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect();
+    }
+
+    // Extract ORDER BY clause
+    if let Some(order_start) = find_clause(input, &["ORDER", "BY"]) {
+        let after_order = &input[order_start..];
+        let end = find_keyword_pos(after_order);
+        query.order_by = Some(parse_order_by(&after_order[..end], order_start)?);
+    }
+
+    query.validate()?;
+    Ok((query, warnings))
 }
 
+/// Parse the `SELECT` list: `*`, or fields and aggregates such as
+/// `count(*)` and `avg(payload.amount) AS mean`. `s` starts `base` bytes
+/// into the query text.
+fn parse_select(s: &str, base: usize) -> Result<Vec<SelectItem>, ParseError> {
+    if s.trim() == "*" {
+        return Ok(Vec::new());
+    }
+    let mut items = Vec::new();
+    let mut start = base;
+    for item in s.split(',') {
+        let span = trimmed_span(item, start);
+        items.push(parse_select_item(item.trim(), span)?);
+        start += item.len() + 1;
+    }
+    Ok(items)
+}
+
+/// Parse one `SELECT` column, `item` spanning `span` of the query text.
+fn parse_select_item(item: &str, span: Range<usize>) -> Result<SelectItem, ParseError> {
+    if item == "*" {
+        return Err(ParseError::at(
+            "SELECT * cannot be combined with other columns",
+            span,
+        ));
+    }
+    let (expr, alias) = match find_keyword(item, &["AS"]) {
+        Some((pos, end)) if pos > 0 => (item[..pos].trim(), Some(item[end..].trim().to_string())),
+        _ => (item, None),
+    };
+    let Some(open) = expr.find('(') else {
+        if expr.is_empty() || expr.contains(char::is_whitespace) || alias.is_some() {
+            return Err(ParseError::at(
+                format!("Cannot parse SELECT column '{}'", item),
+                span,
+            ));
+        }
+        return Ok(SelectItem::Field(expr.to_string()));
+    };
+    let expr_span = span.start..span.start + expr.len();
+    let name = expr[..open].trim();
+    let func = AggregateFn::from_name(name).ok_or_else(|| {
+        ParseError::at(
+            format!("Unknown aggregate '{}'", name),
+            span.start..span.start + name.len(),
+        )
+    })?;
+    let arg = expr[open + 1..]
+        .strip_suffix(')')
+        .ok_or_else(|| ParseError::at(format!("Missing ')' in '{}'", expr), expr_span.clone()))?
+        .trim();
+    let field = match arg {
+        "*" if func == AggregateFn::Count => None,
+        "" | "*" => {
+            return Err(ParseError::at(
+                format!("{}() needs a field", func.name()),
+                expr_span,
+            ))
+        }
+        field => Some(field.to_string()),
+    };
+    Ok(SelectItem::Aggregate(Aggregate { func, field, alias }))
+}
+
+/// Parse an `ORDER BY` body, starting `base` bytes into the query text: a
+/// column name, then `ASC` (default) or `DESC`.
+fn parse_order_by(s: &str, base: usize) -> Result<OrderBy, ParseError> {
+    let error = || {
+        ParseError::at(
+            format!("Cannot parse ORDER BY '{}'", s.trim()),
+            trimmed_span(s, base),
+        )
+    };
+    let words: Vec<&str> = s.split_whitespace().collect();
+    let descending = match words.get(1).map(|w| w.to_ascii_uppercase()).as_deref() {
+        None | Some("ASC") => false,
+        Some("DESC") => true,
+        Some(_) => return Err(error()),
+    };
+    match words.as_slice() {
+        [column] | [column, _] => Ok(OrderBy {
+            column: column.to_string(),
+            descending,
+        }),
+        _ => Err(error()),
+    }
+}
+
+/// Parse a `JOIN` body, starting `base` bytes into the query text:
+/// `stream ON field`, then optionally `WITHIN window`.
+fn parse_join(s: &str, base: usize) -> Result<Join, ParseError> {
+    let error = || {
+        ParseError::at(
+            "JOIN needs 'stream ON field', optionally followed by 'WITHIN window'",
+            trimmed_span(s, base),
+        )
+    };
+    let (on, on_end) = find_keyword(s, &["ON"]).ok_or_else(error)?;
+    let stream = s[..on].trim();
+    let rest = &s[on_end..];
+    let (field, within) = match find_keyword(rest, &["WITHIN"]) {
+        Some((pos, end)) => (rest[..pos].trim(), Some((on_end + end, &rest[end..]))),
+        None => (rest.trim(), None),
+    };
+    let single_word = |w: &str| !w.is_empty() && !w.contains(char::is_whitespace);
+    if !single_word(stream) || !single_word(field) {
+        return Err(error());
+    }
+    let within = match within {
+        Some((at, window)) if super::executor::relative_window(window).is_none() => {
+            return Err(ParseError::at(
+                format!(
+                    "WITHIN '{}' is not a duration such as 30s or 5m",
+                    window.trim()
+                ),
+                trimmed_span(window, base + at),
+            ));
+        }
+        window => window.map(|(_, w)| w.trim().to_string()),
+    };
+    Ok(Join {
+        stream: stream.to_string(),
+        on: field.to_string(),
+        within,
+    })
+}
+
+/// Span of `s.trim()` for an `s` starting `base` bytes into the query text.
+fn trimmed_span(s: &str, base: usize) -> Range<usize> {
+    let start = base + s.len() - s.trim_start().len();
+    start..start + s.trim().len()
+}
+
+/// Keywords that start a clause, and so end the one before.
+const CLAUSES: [&[&str]; 9] = [
+    &["WHERE"],
+    &["FROM"],
+    &["JOIN"],
+    &["SINCE"],
+    &["UNTIL"],
+    &["LIMIT"],
+    &["OFFSET"],
+    &["GROUP", "BY"],
+    &["ORDER", "BY"],
+];
+
+/// Where the clause ending `s` ends: at the next clause keyword, or the
+/// end of `s`.
 fn find_keyword_pos(s: &str) -> usize {
-    let upper = s.to_uppercase();
-    let keywords = [
-        "WHERE ", "FROM ", "SINCE ", "UNTIL ", "LIMIT ", "OFFSET ", "ORDER ",
-    ];
-    let mut min = s.len();
-    for kw in &keywords {
-        if let Some(pos) = upper.find(kw) {
-            if pos < min {
-                min = pos;
+    CLAUSES
+        .iter()
+        .filter_map(|words| find_keyword(s, words))
+        .map(|(pos, _)| pos)
+        .min()
+        .unwrap_or(s.len())
+}
+
+/// Offset just past the first occurrence in `s` of the clause keyword
+/// `words`, e.g. `["GROUP", "BY"]`.
+fn find_clause(s: &str, words: &[&str]) -> Option<usize> {
+    find_keyword(s, words).map(|(_, end)| end)
+}
+
+/// Start and end of the first occurrence in `s` of the keyword `words`, in
+/// any case and with any whitespace between them. Only whole words match,
+/// so `transform` holds no `FROM`, and quoted literals are skipped, so
+/// `"from cache"` holds none either.
+fn find_keyword(s: &str, words: &[&str]) -> Option<(usize, usize)> {
+    let mut quote = None;
+    let mut boundary = true;
+    for (i, c) in s.char_indices() {
+        if let Some(q) = quote {
+            if c == q {
+                quote = None;
+            }
+            boundary = false;
+            continue;
+        }
+        if boundary {
+            if let Some(end) = match_words(&s[i..], words) {
+                return Some((i, i + end));
             }
         }
+        if c == '"' || c == '\'' {
+            quote = Some(c);
+        }
+        boundary = c.is_whitespace() || c == '(' || c == ')' || c == ',';
     }
-    min
+    None
 }
 
-fn parse_conditions(s: &str) -> Result<Vec<Condition>, String> {
-    let mut conditions = Vec::new();
+/// Length of `words` at the start of `s`, each a whole word and separated
+/// by whitespace, or `None` if `s` does not start with them.
+fn match_words(s: &str, words: &[&str]) -> Option<usize> {
+    let mut len = 0;
+    for (n, word) in words.iter().enumerate() {
+        if n > 0 {
+            let rest = &s[len..];
+            let gap = rest.len() - rest.trim_start().len();
+            if gap == 0 {
+                return None;
+            }
+            len += gap;
+        }
+        if !word_at(&s[len..], word) {
+            return None;
+        }
+        len += word.len();
+    }
+    Some(len)
+}
 
-    // Split on AND (case insensitive)
-    let parts: Vec<&str> = split_and(s);
+/// Parse a bare `WHERE` clause body, e.g. `node_id = 1 AND ts > 5`.
+/// An empty body is no filter. Errors name the byte span of the offending
+/// text.
+pub fn parse_filter(s: &str) -> Result<Option<Expr>, ParseError> {
+    parse_filter_at(s, 0)
+}
 
-    for part in parts {
-        let part = part.trim();
-        if part.is_empty() {
+/// [`parse_filter`] for a body starting `base` bytes into the query text,
+/// so error spans point into the whole query.
+fn parse_filter_at(s: &str, base: usize) -> Result<Option<Expr>, ParseError> {
+    let tokens = tokenize(s, base)?;
+    if tokens.is_empty() {
+        return Ok(None);
+    }
+    let mut parser = FilterParser {
+        tokens,
+        pos: 0,
+        end: base + s.trim_end().len(),
+    };
+    let expr = parser.or()?;
+    match parser.tokens.get(parser.pos) {
+        None => Ok(Some(expr)),
+        Some((token, span)) => Err(ParseError::at(
+            format!("Unexpected '{}'", describe(token)),
+            span.clone(),
+        )),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    /// A `field op value` comparison.
+    Cond(String),
+}
+
+fn describe(token: &Token) -> &str {
+    match token {
+        Token::Open => "(",
+        Token::Close => ")",
+        Token::And => "AND",
+        Token::Or => "OR",
+        Token::Not => "NOT",
+        Token::Cond(text) => text,
+    }
+}
+
+/// `AND`, `OR` or `NOT` as a whole word at the start of `s`.
+fn keyword_at(s: &str) -> Option<(Token, usize)> {
+    [("AND", Token::And), ("OR", Token::Or), ("NOT", Token::Not)]
+        .into_iter()
+        .find(|(word, _)| word_at(s, word))
+        .map(|(word, token)| (token, word.len()))
+}
+
+/// Whether `s` starts with `word` (any case) followed by a word boundary.
+fn word_at(s: &str, word: &str) -> bool {
+    let Some(head) = s.get(..word.len()) else {
+        return false;
+    };
+    head.eq_ignore_ascii_case(word)
+        && s[word.len()..]
+            .chars()
+            .next()
+            .is_none_or(|c| c.is_whitespace() || c == '(' || c == ')')
+}
+
+/// Split a `WHERE` body into parentheses, boolean operators and the
+/// comparisons between them, each with its span. Quoted values are never
+/// split. Offsets are shifted by `base`.
+fn tokenize(s: &str, base: usize) -> Result<Vec<(Token, Range<usize>)>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < s.len() {
+        let rest = &s[i..];
+        let c = rest.chars().next().unwrap();
+        if c.is_whitespace() {
+            i += c.len_utf8();
+            continue;
+        }
+        let (token, len) = match c {
+            '(' => (Token::Open, 1),
+            ')' => (Token::Close, 1),
+            _ if rest.starts_with("&&") => (Token::And, 2),
+            _ if rest.starts_with("||") => (Token::Or, 2),
+            _ => match keyword_at(rest) {
+                Some(keyword) => keyword,
+                None => {
+                    let len = condition_len(rest).map_err(|at| {
+                        ParseError::at("Unterminated string", base + i + at..base + s.len())
+                    })?;
+                    (Token::Cond(rest[..len].trim_end().to_string()), len)
+                }
+            },
+        };
+        let width = match &token {
+            Token::Cond(text) => text.len(),
+            _ => len,
+        };
+        tokens.push((token, base + i..base + i + width));
+        i += len;
+    }
+    Ok(tokens)
+}
+
+/// Length of the comparison at the start of `s`: up to the next
+/// parenthesis or boolean operator outside quotes, except for the `AND`
+/// of a `BETWEEN` and the parenthesized list of an `IN`. `Err` holds the
+/// offset of an unterminated quote.
+fn condition_len(s: &str) -> Result<usize, usize> {
+    let mut quote: Option<(char, usize)> = None;
+    let mut after_space = false;
+    let mut in_between = false;
+    // Where the list of an `IN` may open, and whether it is open.
+    let mut list_from: Option<usize> = None;
+    let mut in_list = false;
+    for (i, c) in s.char_indices() {
+        if let Some((q, _)) = quote {
+            if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        let rest = &s[i..];
+        if in_list {
+            if c == ')' {
+                in_list = false;
+            } else if c == '"' || c == '\'' {
+                quote = Some((c, i));
+            }
             continue;
         }
+        if list_from.is_some_and(|from| i >= from) {
+            if c == '(' {
+                list_from = None;
+                in_list = true;
+                continue;
+            }
+            if !c.is_whitespace() {
+                list_from = None;
+            }
+        }
+        if after_space && word_at(rest, "BETWEEN") {
+            in_between = true;
+        } else if after_space && in_between && word_at(rest, "AND") {
+            in_between = false;
+            after_space = false;
+            continue;
+        } else if after_space && word_at(rest, "IN") {
+            list_from = Some(i + "IN".len());
+        }
+        let ends = c == '('
+            || c == ')'
+            || rest.starts_with("&&")
+            || rest.starts_with("||")
+            || (after_space && matches!(keyword_at(rest), Some((Token::And | Token::Or, _))));
+        if ends {
+            return Ok(i);
+        }
+        if c == '"' || c == '\'' {
+            quote = Some((c, i));
+        }
+        after_space = c.is_whitespace();
+    }
+    match quote {
+        Some((_, at)) => Err(at),
+        None => Ok(s.len()),
+    }
+}
+
+/// Recursive descent over [`tokenize`]d input:
+/// `or := and (OR and)*`, `and := unary (AND unary)*`,
+/// `unary := NOT unary | '(' or ')' | comparison`.
+struct FilterParser {
+    tokens: Vec<(Token, Range<usize>)>,
+    pos: usize,
+    /// Offset just past the body, for errors at the end of input.
+    end: usize,
+}
+
+impl FilterParser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(t, _)| t)
+    }
 
-        // Try operators in order of specificity
-        let (field, op, value) = if let Some(pos) = part.find(">=") {
-            (&part[..pos], CompareOp::Gte, &part[pos + 2..])
-        } else if let Some(pos) = part.find("<=") {
-            (&part[..pos], CompareOp::Lte, &part[pos + 2..])
-        } else if let Some(pos) = part.find("!=") {
-            (&part[..pos], CompareOp::Neq, &part[pos + 2..])
-        } else if let Some(pos) = part.find('>') {
-            (&part[..pos], CompareOp::Gt, &part[pos + 1..])
-        } else if let Some(pos) = part.find('<') {
-            (&part[..pos], CompareOp::Lt, &part[pos + 1..])
-        } else if let Some(pos) = part.find('=') {
-            (&part[..pos], CompareOp::Eq, &part[pos + 1..])
-        } else if part.to_uppercase().contains(" CONTAINS ") {
-            let idx = part.to_uppercase().find(" CONTAINS ").unwrap();
-            (&part[..idx], CompareOp::Contains, &part[idx + 10..])
-        } else if part.to_uppercase().contains(" STARTSWITH ") {
-            let idx = part.to_uppercase().find(" STARTSWITH ").unwrap();
-            (&part[..idx], CompareOp::StartsWith, &part[idx + 12..])
+    fn or(&mut self) -> Result<Expr, ParseError> {
+        let mut terms = vec![self.and()?];
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            terms.push(self.and()?);
+        }
+        Ok(if terms.len() == 1 {
+            terms.pop().unwrap()
         } else {
-            return Err(format!("Cannot parse condition: '{}'", part));
-        };
+            Expr::Or(terms)
+        })
+    }
 
-        let field = field.trim().to_string();
-        let value_str = value.trim().trim_matches('"').trim_matches('\'');
-        let value = parse_value(value_str);
+    fn and(&mut self) -> Result<Expr, ParseError> {
+        let mut terms = vec![self.unary()?];
+        while self.peek() == Some(&Token::And) {
+            self.pos += 1;
+            terms.push(self.unary()?);
+        }
+        Ok(if terms.len() == 1 {
+            terms.pop().unwrap()
+        } else {
+            Expr::And(terms)
+        })
+    }
 
-        conditions.push(Condition { field, op, value });
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        let Some((token, span)) = self.tokens.get(self.pos).cloned() else {
+            return Err(ParseError::at("Expected a condition", self.end..self.end));
+        };
+        self.pos += 1;
+        match token {
+            Token::Not => Ok(Expr::Not(Box::new(self.unary()?))),
+            Token::Open => {
+                let expr = self.or()?;
+                if self.peek() != Some(&Token::Close) {
+                    return Err(ParseError::at("Unclosed '('", span));
+                }
+                self.pos += 1;
+                Ok(expr)
+            }
+            Token::Cond(text) => {
+                let expr = parse_comparison(&text).ok_or_else(|| {
+                    ParseError::at(format!("Cannot parse condition '{}'", text), span.clone())
+                })?;
+                expr.check().map_err(|e| ParseError::at(e, span))?;
+                Ok(expr)
+            }
+            token => Err(ParseError::at(
+                format!("Expected a condition, found '{}'", describe(&token)),
+                span,
+            )),
+        }
     }
+}
+
+/// Parse a `field op value` comparison, or a `field BETWEEN a AND b`
+/// range as its two inclusive bounds.
+fn parse_comparison(part: &str) -> Option<Expr> {
+    let Some((pos, end)) = find_keyword(part, &["BETWEEN"]) else {
+        return parse_condition(part).map(Expr::Condition);
+    };
+    let field = part[..pos].trim();
+    let bounds = &part[end..];
+    let (and, and_end) = find_keyword(bounds, &["AND"])?;
+    let (low, high) = (bounds[..and].trim(), bounds[and_end..].trim());
+    if field.is_empty() || low.is_empty() || high.is_empty() {
+        return None;
+    }
+    let bound = |op, value: &str| {
+        Expr::Condition(Condition {
+            field: field.to_string(),
+            op,
+            value: parse_value(value.trim_matches('"').trim_matches('\'')),
+        })
+    };
+    Some(Expr::And(vec![
+        bound(CompareOp::Gte, low),
+        bound(CompareOp::Lte, high),
+    ]))
+}
+
+/// Parse a single `field op value` comparison.
+fn parse_condition(part: &str) -> Option<Condition> {
+    // Symbols in a quoted value, such as a MATCHES pattern, are not
+    // operators.
+    let head = &part[..part.find(['"', '\'']).unwrap_or(part.len())];
+    // Try operators in order of specificity
+    let (field, op, value) = if let Some(pos) = head.find("==") {
+        (&part[..pos], CompareOp::Eq, &part[pos + 2..])
+    } else if let Some(pos) = head.find(">=") {
+        (&part[..pos], CompareOp::Gte, &part[pos + 2..])
+    } else if let Some(pos) = head.find("<=") {
+        (&part[..pos], CompareOp::Lte, &part[pos + 2..])
+    } else if let Some(pos) = head.find("!=") {
+        (&part[..pos], CompareOp::Neq, &part[pos + 2..])
+    } else if let Some(pos) = head.find('>') {
+        (&part[..pos], CompareOp::Gt, &part[pos + 1..])
+    } else if let Some(pos) = head.find('<') {
+        (&part[..pos], CompareOp::Lt, &part[pos + 1..])
+    } else if let Some(pos) = head.find('=') {
+        (&part[..pos], CompareOp::Eq, &part[pos + 1..])
+    } else if let Some((start, end)) = find_keyword(part, &["CONTAINS"]) {
+        (&part[..start], CompareOp::Contains, &part[end..])
+    } else if let Some((start, end)) = find_keyword(part, &["STARTSWITH"]) {
+        (&part[..start], CompareOp::StartsWith, &part[end..])
+    } else if let Some((start, end)) = find_keyword(part, &["MATCHES"]) {
+        (&part[..start], CompareOp::Matches, &part[end..])
+    } else if let Some((start, end)) = find_keyword(part, &["IN"]) {
+        (&part[..start], CompareOp::In, &part[end..])
+    } else {
+        return None;
+    };
 
-    Ok(conditions)
+    let field = field.trim().to_string();
+    if field.is_empty() {
+        return None;
+    }
+    let value = match op {
+        CompareOp::In => parse_list(value)?,
+        CompareOp::Matches => serde_json::Value::String(unquote(value.trim())?.to_string()),
+        _ => parse_value(value.trim().trim_matches('"').trim_matches('\'')),
+    };
+    Some(Condition { field, op, value })
 }
 
-fn split_and(s: &str) -> Vec<&str> {
-    let mut parts = Vec::new();
-    let upper = s.to_uppercase();
-    let mut last = 0;
-    let pattern = " AND ";
-    let mut search_pos = 0;
+/// Parse the `(a, "b", 3)` list of an `IN`. Quoted items stay strings;
+/// bare ones are read like any other value.
+fn parse_list(s: &str) -> Option<serde_json::Value> {
+    let inner = s.trim().strip_prefix('(')?.strip_suffix(')')?;
+    let mut items = Vec::new();
+    let mut quote = None;
+    let mut start = 0;
+    for (i, c) in inner.char_indices().chain([(inner.len(), ',')]) {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == ',' => {
+                let item = inner[start..i].trim();
+                if item.is_empty() {
+                    return None;
+                }
+                items.push(match item.chars().next() {
+                    Some('"' | '\'') => serde_json::Value::String(unquote(item)?.to_string()),
+                    _ => parse_value(item),
+                });
+                start = i + 1;
+            }
+            None => {}
+        }
+    }
+    Some(serde_json::Value::Array(items))
+}
 
-    while let Some(pos) = upper[search_pos..].find(pattern) {
-        let absolute_pos = search_pos + pos;
-        parts.push(&s[last..absolute_pos]);
-        last = absolute_pos + pattern.len();
-        search_pos = last;
+/// `s` without one pair of surrounding quotes, if it has them; `None` if
+/// they do not match.
+fn unquote(s: &str) -> Option<&str> {
+    match s.chars().next() {
+        Some(q @ ('"' | '\'')) => s[1..].strip_suffix(q),
+        _ => Some(s),
     }
-    parts.push(&s[last..]);
-    parts
 }
 
 fn parse_value(s: &str) -> serde_json::Value {
@@ -179,14 +783,112 @@ fn parse_value(s: &str) -> serde_json::Value {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use serde_json::json;
+
+    fn cond(field: &str, op: CompareOp, value: serde_json::Value) -> Expr {
+        Expr::Condition(Condition {
+            field: field.into(),
+            op,
+            value,
+        })
+    }
+
+    fn filter(s: &str) -> Expr {
+        parse_filter(s).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_non_ascii_input_keeps_clause_positions() {
+        // Full Unicode uppercasing turns `ß` into `SS`, which would shift
+        // every keyword position found after it.
+        let q = parse("SELECT * FROM straße WHERE größe > 3 LIMIT 5").unwrap();
+        assert_eq!(q.from, vec!["straße"]);
+        assert_eq!(q.filter, Some(cond("größe", CompareOp::Gt, json!(3))));
+        assert_eq!(q.limit, 5);
+        assert!(parse("ßß FROM ß").is_ok());
+    }
+
+    #[test]
+    fn test_keywords_in_quotes_and_words_are_not_clauses() {
+        let q = parse(r#"SELECT * FROM cache_events WHERE status CONTAINS "from cache" LIMIT 5"#)
+            .unwrap();
+        assert_eq!(q.from, vec!["cache_events"]);
+        assert_eq!(
+            q.filter,
+            Some(cond("status", CompareOp::Contains, json!("from cache")))
+        );
+        assert_eq!(q.limit, 5);
+
+        let q = parse(
+            "SELECT * FROM transform, platforms WHERE note = 'ORDER confirmed WHERE x' \
+             AND reorder_by = 'a AND b' AND sinceday > 1 OFFSET 2",
+        )
+        .unwrap();
+        assert_eq!(q.from, vec!["transform", "platforms"]);
+        assert_eq!(
+            q.filter,
+            Some(Expr::And(vec![
+                cond("note", CompareOp::Eq, json!("ORDER confirmed WHERE x")),
+                cond("reorder_by", CompareOp::Eq, json!("a AND b")),
+                cond("sinceday", CompareOp::Gt, json!(1)),
+            ]))
+        );
+        assert_eq!((q.order_by, q.since, q.offset), (None, None, 2));
+
+        // Keywords match in any case and with any spacing.
+        let q = parse("select count(*) as n from orders\twhere amount > 1 group  by kind").unwrap();
+        assert_eq!(q.from, vec!["orders"]);
+        assert_eq!(q.group_by, vec!["kind"]);
+        assert_eq!(q.columns().unwrap(), ["n"]);
+
+        // A quoted BETWEEN or CONTAINS is a value, not an operator.
+        assert_eq!(
+            filter(r#"title STARTSWITH "contains BETWEEN""#),
+            cond("title", CompareOp::StartsWith, json!("contains BETWEEN"))
+        );
+    }
+
+    #[test]
+    fn test_ignored_text_is_warned_about() {
+        let (q, warnings) = parse_with_warnings("SELEC * FROM orders LIMIT ten OFFSET -1").unwrap();
+        assert_eq!(q.from, vec!["orders"]);
+        assert_eq!((q.limit, q.offset), (100, 0));
+        assert_eq!(
+            warnings,
+            [
+                "Ignored 'SELEC *' before the first clause",
+                "LIMIT 'ten' is not a number; using 100",
+                "OFFSET '-1' is not a number; using 0",
+            ]
+        );
+        assert!(parse_with_warnings("SELECT * FROM orders LIMIT 5")
+            .unwrap()
+            .1
+            .is_empty());
+    }
+
+    #[test]
+    fn test_explain_prefix() {
+        let (q, warnings) =
+            parse_with_warnings("  explain SELECT * FROM orders WHERE amount > 5").unwrap();
+        assert!(q.explain);
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert_eq!(q.from, vec!["orders"]);
+        assert!(!parse("SELECT * FROM orders").unwrap().explain);
+        // Only as a prefix; anywhere else it is text like any other.
+        let (q, warnings) = parse_with_warnings("EXPLAIN ANALYZE SELECT * FROM orders").unwrap();
+        assert!(!q.explain);
+        assert_eq!(
+            warnings,
+            ["Ignored 'EXPLAIN ANALYZE' before the first clause"]
+        );
+    }
 
     #[test]
     fn test_simple_query() {
         let q = parse("SELECT * FROM orders WHERE amount > 100 SINCE 5m LIMIT 50").unwrap();
         assert_eq!(q.from, vec!["orders"]);
-        assert_eq!(q.conditions.len(), 1);
-        assert_eq!(q.conditions[0].field, "amount");
-        assert_eq!(q.conditions[0].op, CompareOp::Gt);
+        assert_eq!(q.filter, Some(cond("amount", CompareOp::Gt, json!(100))));
         assert_eq!(q.limit, 50);
         assert_eq!(q.since, Some("5m".to_string()));
     }
@@ -195,20 +897,385 @@ mod tests {
     fn test_multi_stream_query() {
         let q = parse("SELECT * FROM journal, kafka_orders").unwrap();
         assert_eq!(q.from, vec!["journal", "kafka_orders"]);
+        assert_eq!(q.filter, None);
     }
 
     #[test]
     fn test_complex_where() {
         let q = parse("SELECT * FROM events WHERE status >= 500 AND method = \"POST\"").unwrap();
-        assert_eq!(q.conditions.len(), 2);
-        assert_eq!(q.conditions[0].op, CompareOp::Gte);
-        assert_eq!(q.conditions[1].op, CompareOp::Eq);
+        assert_eq!(
+            q.filter,
+            Some(Expr::And(vec![
+                cond("status", CompareOp::Gte, json!(500)),
+                cond("method", CompareOp::Eq, json!("POST")),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_c_style_operators() {
+        assert_eq!(
+            filter("node_id == 1 && stream_id > 0 || ts < 3"),
+            Expr::Or(vec![
+                Expr::And(vec![
+                    cond("node_id", CompareOp::Eq, json!(1)),
+                    cond("stream_id", CompareOp::Gt, json!(0)),
+                ]),
+                cond("ts", CompareOp::Lt, json!(3)),
+            ])
+        );
     }
 
     #[test]
     fn test_startswith_operator() {
         let q = parse("SELECT * FROM events WHERE path STARTSWITH \"/api\"").unwrap();
-        assert_eq!(q.conditions.len(), 1);
-        assert_eq!(q.conditions[0].op, CompareOp::StartsWith);
+        assert_eq!(
+            q.filter,
+            Some(cond("path", CompareOp::StartsWith, json!("/api")))
+        );
+    }
+
+    #[test]
+    fn test_precedence_not_and_or() {
+        let (a, b, c) = (
+            cond("a", CompareOp::Eq, json!(1)),
+            cond("b", CompareOp::Eq, json!(2)),
+            cond("c", CompareOp::Eq, json!(3)),
+        );
+        // AND binds tighter than OR, on either side.
+        assert_eq!(
+            filter("a = 1 OR b = 2 AND c = 3"),
+            Expr::Or(vec![a.clone(), Expr::And(vec![b.clone(), c.clone()])])
+        );
+        assert_eq!(
+            filter("a = 1 and b = 2 or c = 3"),
+            Expr::Or(vec![Expr::And(vec![a.clone(), b.clone()]), c.clone()])
+        );
+        // Parentheses override it.
+        assert_eq!(
+            filter("(a = 1 OR b = 2) AND c = 3"),
+            Expr::And(vec![Expr::Or(vec![a.clone(), b.clone()]), c.clone()])
+        );
+        // NOT binds tightest and nests.
+        assert_eq!(
+            filter("NOT a = 1 AND b = 2"),
+            Expr::And(vec![Expr::Not(Box::new(a.clone())), b.clone()])
+        );
+        assert_eq!(
+            filter("not (a = 1 or not b = 2)"),
+            Expr::Not(Box::new(Expr::Or(vec![
+                a.clone(),
+                Expr::Not(Box::new(b.clone())),
+            ])))
+        );
+        assert_eq!(filter("((a = 1))"), a);
+    }
+
+    #[test]
+    fn test_operators_inside_words_and_quotes_do_not_split() {
+        assert_eq!(
+            filter("order_id = 7 AND note = \"x AND (y OR z)\""),
+            Expr::And(vec![
+                cond("order_id", CompareOp::Eq, json!(7)),
+                cond("note", CompareOp::Eq, json!("x AND (y OR z)")),
+            ])
+        );
+        assert_eq!(
+            filter("android = 1"),
+            cond("android", CompareOp::Eq, json!(1))
+        );
+    }
+
+    #[test]
+    fn test_errors_name_the_offending_span() {
+        let error = |result: Result<Option<Expr>, ParseError>| {
+            let e = result.unwrap_err();
+            (e.message, e.span.unwrap())
+        };
+        assert_eq!(
+            error(parse_filter("ts > 1 AND node_id")),
+            ("Cannot parse condition 'node_id'".into(), 11..18)
+        );
+        assert_eq!(
+            error(parse_filter("ts > 1 AND")),
+            ("Expected a condition".into(), 10..10)
+        );
+        assert_eq!(
+            error(parse_filter("(ts > 1 OR ts < 0")),
+            ("Unclosed '('".into(), 0..1)
+        );
+        assert_eq!(
+            error(parse_filter("ts > 1) AND ts < 5")),
+            ("Unexpected ')'".into(), 6..7)
+        );
+        assert_eq!(
+            error(parse_filter("AND ts > 1")),
+            ("Expected a condition, found 'AND'".into(), 0..3)
+        );
+        assert_eq!(
+            error(parse_filter("name = \"open")),
+            ("Unterminated string".into(), 7..12)
+        );
+        assert_eq!(parse_filter("   ").unwrap(), None);
+    }
+
+    #[test]
+    fn test_error_span_points_into_the_whole_query() {
+        let text = "  SELECT * FROM orders WHERE amount > 5 OR amount ~ 3 LIMIT 5";
+        let err = parse(text).unwrap_err();
+        let span = err.span.clone().unwrap();
+        assert_eq!(&text[span], "amount ~ 3");
+        assert_eq!(
+            err.to_string(),
+            "Cannot parse condition 'amount ~ 3' at 43..53"
+        );
+
+        for (text, token) in [
+            ("SELECT count(*), median(amount) GROUP BY stream", "median"),
+            ("SELECT stream,  sum() GROUP BY stream", "sum()"),
+            ("SELECT count(*) ORDER BY count SIDEWAYS", "count SIDEWAYS"),
+            ("SELECT * WHERE (ts > 1", "("),
+        ] {
+            let span = parse(text).unwrap_err().span.unwrap();
+            assert_eq!(&text[span], token, "{}", text);
+        }
+
+        // Errors about the query as a whole have no span.
+        let err = parse("SELECT count(*) ORDER BY total").unwrap_err();
+        assert_eq!(err.span, None);
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            json!({"message": err.message, "span": null})
+        );
+    }
+
+    #[test]
+    fn test_in_and_matches() {
+        assert_eq!(
+            filter("stream_id IN (1,2, 3) AND payload.path MATCHES \"^/api/v[0-9]+\""),
+            Expr::And(vec![
+                cond("stream_id", CompareOp::In, json!([1, 2, 3])),
+                cond("payload.path", CompareOp::Matches, json!("^/api/v[0-9]+")),
+            ])
+        );
+        // Quoted items stay strings, commas and parentheses in them too.
+        assert_eq!(
+            filter("method in ('GET', \"a, (b)\", 7) OR x = 1"),
+            Expr::Or(vec![
+                cond("method", CompareOp::In, json!(["GET", "a, (b)", 7])),
+                cond("x", CompareOp::Eq, json!(1)),
+            ])
+        );
+        // Operator symbols in a pattern do not split the comparison.
+        assert_eq!(
+            filter("query MATCHES 'a=b|c<=d' AND NOT index IN(4)"),
+            Expr::And(vec![
+                cond("query", CompareOp::Matches, json!("a=b|c<=d")),
+                Expr::Not(Box::new(cond("index", CompareOp::In, json!([4])))),
+            ])
+        );
+
+        // Each form survives a trip through the structured JSON form.
+        for text in [
+            "SELECT * WHERE stream_id IN (1, 2, 3)",
+            "SELECT * WHERE kind IN (\"push\", 'pull') OR path MATCHES \"^/api/v[0-9]+$\"",
+            "SELECT * WHERE lamport_ts BETWEEN 100 AND 200",
+        ] {
+            let query = parse(text).unwrap();
+            let json = serde_json::to_string(&query).unwrap();
+            let back: crate::query::Query = serde_json::from_str(&json).unwrap();
+            assert_eq!(back.filter, query.filter, "{}", json);
+        }
+        let json = serde_json::to_value(filter("id IN (1)")).unwrap();
+        assert_eq!(json["condition"]["op"], "in");
+
+        for bad in ["x IN ()", "x IN (1, )", "x IN 1, 2", "x IN (1, 2"] {
+            assert!(parse_filter(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_invalid_pattern_is_an_error_at_its_condition() {
+        let text = "SELECT * WHERE stream = 'a' AND path MATCHES \"[unclosed\" LIMIT 5";
+        let err = parse(text).unwrap_err();
+        assert_eq!(&text[err.span.unwrap()], "path MATCHES \"[unclosed\"");
+        assert_eq!(
+            err.message,
+            "Invalid MATCHES pattern '[unclosed': unclosed character class"
+        );
+
+        // Structured queries skip the parser but are validated all the same.
+        let mut query = parse("SELECT *").unwrap();
+        query.filter = Some(cond("path", CompareOp::Matches, json!("(")));
+        assert!(query.validate().is_err());
+        query.filter = Some(cond("id", CompareOp::In, json!(3)));
+        assert_eq!(
+            query.validate().unwrap_err(),
+            "IN on 'id' needs a list of values"
+        );
+    }
+
+    #[test]
+    fn test_join_clause() {
+        let q = parse(
+            "SELECT * FROM webhook:github JOIN kafka:deploys ON metadata.trace_id WITHIN 5m \
+             WHERE action = 'closed' LIMIT 20",
+        )
+        .unwrap();
+        assert_eq!(q.from, vec!["webhook:github"]);
+        assert_eq!(
+            q.join,
+            Some(Join {
+                stream: "kafka:deploys".into(),
+                on: "metadata.trace_id".into(),
+                within: Some("5m".into()),
+            })
+        );
+        assert_eq!(
+            q.filter,
+            Some(cond("action", CompareOp::Eq, json!("closed")))
+        );
+        assert_eq!(q.limit, 20);
+
+        let q = parse("select * from a join b on key").unwrap();
+        assert_eq!(q.join.unwrap().within, None);
+
+        let text = "SELECT * FROM a JOIN b ON key WITHIN soon LIMIT 5";
+        let err = parse(text).unwrap_err();
+        assert_eq!(&text[err.span.unwrap()], "soon");
+        for text in [
+            "SELECT * FROM a JOIN b",
+            "SELECT * FROM a JOIN ON key",
+            "SELECT * FROM a JOIN b ON two words",
+        ] {
+            let err = parse(text).unwrap_err();
+            assert!(err.message.starts_with("JOIN needs"), "{}: {}", text, err);
+        }
+        assert!(parse("SELECT count(*) FROM a JOIN b ON key").is_err());
+    }
+
+    #[test]
+    fn test_between() {
+        let range = |field: &str, low, high| {
+            Expr::And(vec![
+                cond(field, CompareOp::Gte, low),
+                cond(field, CompareOp::Lte, high),
+            ])
+        };
+        assert_eq!(
+            filter("ts BETWEEN 3 AND 7"),
+            range("ts", json!(3), json!(7))
+        );
+        // The AND inside BETWEEN is not a conjunction; the next one is.
+        assert_eq!(
+            filter("amount between 1.5 and 10 AND node_id = 2 OR ts Between 0 AnD 1"),
+            Expr::Or(vec![
+                Expr::And(vec![
+                    range("amount", json!(1.5), json!(10)),
+                    cond("node_id", CompareOp::Eq, json!(2)),
+                ]),
+                range("ts", json!(0), json!(1)),
+            ])
+        );
+        assert_eq!(
+            filter("NOT (day BETWEEN \"2024-01-01\" AND \"2024-01-31\")"),
+            Expr::Not(Box::new(range(
+                "day",
+                json!("2024-01-01"),
+                json!("2024-01-31")
+            )))
+        );
+        // Words merely containing BETWEEN are ordinary fields.
+        assert_eq!(
+            filter("betweenness > 1 AND x = 2"),
+            Expr::And(vec![
+                cond("betweenness", CompareOp::Gt, json!(1)),
+                cond("x", CompareOp::Eq, json!(2)),
+            ])
+        );
+        assert_eq!(
+            parse_filter("ts BETWEEN 3 AND").unwrap_err().to_string(),
+            "Cannot parse condition 'ts BETWEEN 3 AND' at 0..16"
+        );
+        assert!(parse_filter("ts BETWEEN 3").is_err());
+    }
+
+    #[test]
+    fn test_time_literals_pass_through() {
+        let q = parse("SELECT * FROM orders SINCE -1h UNTIL now").unwrap();
+        assert_eq!(q.since.as_deref(), Some("-1h"));
+        assert_eq!(q.until.as_deref(), Some("now"));
+        let q = parse("SELECT * SINCE 2024-01-01 LIMIT 5").unwrap();
+        assert_eq!((q.since.as_deref(), q.limit), (Some("2024-01-01"), 5));
+    }
+
+    #[test]
+    fn test_aggregate_query() {
+        let q = parse(
+            "SELECT stream, count(*), AVG(payload.amount) AS mean FROM orders \
+             WHERE amount > 1 GROUP BY stream ORDER BY count DESC LIMIT 10",
+        )
+        .unwrap();
+        assert_eq!(
+            q.select,
+            vec![
+                SelectItem::Field("stream".into()),
+                SelectItem::Aggregate(Aggregate {
+                    func: AggregateFn::Count,
+                    field: None,
+                    alias: None,
+                }),
+                SelectItem::Aggregate(Aggregate {
+                    func: AggregateFn::Avg,
+                    field: Some("payload.amount".into()),
+                    alias: Some("mean".into()),
+                }),
+            ]
+        );
+        assert_eq!(q.from, vec!["orders"]);
+        assert_eq!(q.filter, Some(cond("amount", CompareOp::Gt, json!(1))));
+        assert_eq!(q.group_by, vec!["stream"]);
+        assert_eq!(
+            q.order_by,
+            Some(OrderBy {
+                column: "count".into(),
+                descending: true,
+            })
+        );
+        assert_eq!(q.limit, 10);
+        assert_eq!(q.columns().unwrap(), ["stream", "count", "mean"]);
+
+        // GROUP BY alone counts per group.
+        let q = parse("SELECT * GROUP BY stream, payload.kind").unwrap();
+        assert!(q.is_aggregate());
+        assert_eq!(q.columns().unwrap(), ["stream", "payload.kind", "count"]);
+        assert!(!parse("SELECT * FROM orders").unwrap().is_aggregate());
+    }
+
+    #[test]
+    fn test_aggregate_query_errors() {
+        for (text, error) in [
+            ("SELECT amount FROM orders", "SELECT lists fields"),
+            (
+                "SELECT amount, count(*) GROUP BY stream",
+                "'amount' must appear",
+            ),
+            ("SELECT median(amount)", "Unknown aggregate 'median'"),
+            ("SELECT sum(*)", "sum() needs a field"),
+            ("SELECT count(*", "Missing ')'"),
+            ("SELECT *, count(*)", "cannot be combined"),
+            (
+                "SELECT count(*) ORDER BY total",
+                "ORDER BY 'total' names no column",
+            ),
+            (
+                "SELECT count(*) ORDER BY count SIDEWAYS",
+                "Cannot parse ORDER BY",
+            ),
+            ("SELECT * ORDER BY ts", "ORDER BY needs an aggregate"),
+        ] {
+            let err = parse(text).unwrap_err().to_string();
+            assert!(err.contains(error), "{}: {}", text, err);
+        }
     }
 }
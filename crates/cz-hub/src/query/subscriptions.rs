@@ -0,0 +1,191 @@
+//! # Query Subscriptions
+//!
+//! A subscription keeps a query running: rather than a snapshot, each new
+//! event that matches it is pushed to the WebSocket that attached it.
+//! Subscriptions are created over HTTP, attached by exactly one WS
+//! connection and dropped when it disconnects; one that is never attached
+//! expires after [`ATTACH_TIMEOUT`]. Each API key holds a limited number.
+
+use super::Query;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Subscriptions one API key may hold by default.
+pub const DEFAULT_MAX_PER_KEY: usize = 16;
+/// How long a subscription waits for a WS connection to attach it.
+pub const ATTACH_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubscribeError {
+    /// The query cannot run as a subscription.
+    Invalid(String),
+    /// The key already holds this many subscriptions.
+    TooMany(usize),
+}
+
+impl fmt::Display for SubscribeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SubscribeError::Invalid(reason) => f.write_str(reason),
+            SubscribeError::TooMany(max) => {
+                write!(f, "At most {} query subscriptions per API key", max)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SubscribeError {}
+
+struct Entry {
+    /// Id of the API key that created it; only that key may attach it.
+    owner: String,
+    query: Arc<Query>,
+    created: Instant,
+    attached: bool,
+}
+
+pub struct QuerySubscriptions {
+    max_per_key: usize,
+    entries: Mutex<HashMap<String, Entry>>,
+}
+
+impl Default for QuerySubscriptions {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_PER_KEY)
+    }
+}
+
+impl QuerySubscriptions {
+    pub fn new(max_per_key: usize) -> Self {
+        Self {
+            max_per_key,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Register `query` for `owner` and return its id. Aggregates, joins,
+    /// cursors and `EXPLAIN` have no meaning for a feed of single events, so
+    /// they are refused, as is a key already at its limit.
+    pub fn create(&self, owner: &str, query: Query) -> Result<String, SubscribeError> {
+        query.validate().map_err(SubscribeError::Invalid)?;
+        if query.is_aggregate() {
+            return Err(SubscribeError::Invalid(
+                "Aggregate queries cannot be subscribed to".into(),
+            ));
+        }
+        if query.cursor.is_some() {
+            return Err(SubscribeError::Invalid(
+                "A subscription streams new events and takes no cursor".into(),
+            ));
+        }
+        if query.join.is_some() {
+            return Err(SubscribeError::Invalid(
+                "JOIN queries cannot be subscribed to".into(),
+            ));
+        }
+        if query.explain {
+            return Err(SubscribeError::Invalid(
+                "EXPLAIN describes a query once and cannot be subscribed to".into(),
+            ));
+        }
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, e| e.attached || e.created.elapsed() < ATTACH_TIMEOUT);
+        if entries.values().filter(|e| e.owner == owner).count() >= self.max_per_key {
+            return Err(SubscribeError::TooMany(self.max_per_key));
+        }
+        let id = format!("qsub-{}", uuid::Uuid::new_v4().as_simple());
+        entries.insert(
+            id.clone(),
+            Entry {
+                owner: owner.to_string(),
+                query: Arc::new(query),
+                created: Instant::now(),
+                attached: false,
+            },
+        );
+        Ok(id)
+    }
+
+    /// Hand subscription `id` to a connection authenticated as `owner`,
+    /// unless another connection already holds it or it has expired.
+    pub fn attach(&self, id: &str, owner: &str) -> Option<Arc<Query>> {
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries.get_mut(id)?;
+        if entry.owner != owner || entry.attached || entry.created.elapsed() >= ATTACH_TIMEOUT {
+            return None;
+        }
+        entry.attached = true;
+        Some(entry.query.clone())
+    }
+
+    /// Drop subscription `id`, e.g. when its connection closes.
+    pub fn remove(&self, id: &str) {
+        self.entries.lock().unwrap().remove(id);
+    }
+
+    /// Subscriptions `owner` holds, attached or still waiting.
+    pub fn count(&self, owner: &str) -> usize {
+        self.entries
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|e| e.owner == owner)
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::parser::parse;
+
+    #[test]
+    fn test_create_attach_and_remove() {
+        let subs = QuerySubscriptions::new(2);
+        let query = parse("SELECT * FROM webhook WHERE action = 'opened'").unwrap();
+        let id = subs.create("key-a", query.clone()).unwrap();
+
+        assert!(subs.attach(&id, "key-b").is_none());
+        let attached = subs.attach(&id, "key-a").unwrap();
+        assert_eq!(attached.from, vec!["webhook"]);
+        // One connection at a time.
+        assert!(subs.attach(&id, "key-a").is_none());
+
+        subs.create("key-a", query.clone()).unwrap();
+        assert_eq!(
+            subs.create("key-a", query.clone()),
+            Err(SubscribeError::TooMany(2))
+        );
+        // The cap is per key.
+        subs.create("key-b", query.clone()).unwrap();
+
+        subs.remove(&id);
+        assert_eq!(subs.count("key-a"), 1);
+        assert!(subs.attach(&id, "key-a").is_none());
+        subs.create("key-a", query).unwrap();
+    }
+
+    #[test]
+    fn test_snapshot_only_queries_are_refused() {
+        let subs = QuerySubscriptions::default();
+        let aggregate = parse("SELECT count(*) FROM orders").unwrap();
+        assert!(matches!(
+            subs.create("key-a", aggregate),
+            Err(SubscribeError::Invalid(_))
+        ));
+        let mut paged = parse("SELECT * FROM orders").unwrap();
+        paged.cursor = Some(crate::query::QueryCursor(3));
+        assert!(matches!(
+            subs.create("key-a", paged),
+            Err(SubscribeError::Invalid(_))
+        ));
+        let explain = parse("EXPLAIN SELECT * FROM orders").unwrap();
+        assert!(matches!(
+            subs.create("key-a", explain),
+            Err(SubscribeError::Invalid(_))
+        ));
+        assert_eq!(subs.count("key-a"), 0);
+    }
+}
@@ -0,0 +1,139 @@
+//! # Journal Replay
+//!
+//! Copies events from a range of Index Ring slots in one journal onto the
+//! head of another. `POST /api/replay` runs it against journals the hub has
+//! open; `cz replay --local` runs it against journal files directly.
+//!
+//! The target ring must stay sorted by `lamport_ts` from tail to head, so a
+//! replay is stamped with [`cz_core::append`]: it either keeps the source
+//! timestamps (and is rejected if they would go backward) or restamps every
+//! event after the target's newest one.
+
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use cz_core::append::{self, AppendMode};
+use cz_core::CausalEvent;
+use cz_io::cursor::{Cursor, Reservation};
+use cz_io::journal::Journal;
+
+use crate::query::executor::is_empty_event;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayError {
+    /// `start_slot` comes after `end_slot`.
+    InvertedRange { start: usize, end: usize },
+    /// Keeping the source timestamps would put `lamport_ts` after `after`.
+    OutOfOrder { lamport_ts: u64, after: u64 },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::InvertedRange { .. } => write!(f, "start_slot must be <= end_slot"),
+            ReplayError::OutOfOrder { lamport_ts, after } => write!(
+                f,
+                "Replaying lamport_ts {} after {} would break ordering in the target; retry \
+                 with resequence",
+                lamport_ts, after
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+/// The events read for a replay, stamped and ready to write.
+#[derive(Debug)]
+pub struct ReplayBatch {
+    pub events: Vec<CausalEvent>,
+    pub mode: AppendMode,
+    /// Source slots asked for, including empty ones.
+    pub requested: usize,
+    /// Events given a new `lamport_ts` (`resequence` only).
+    pub resequenced: usize,
+}
+
+impl ReplayBatch {
+    /// Empty source slots plus events that did not fit in the target.
+    pub fn skipped(&self) -> usize {
+        self.requested - self.events.len()
+    }
+}
+
+/// Source slots `start..=end`, each clamped to a ring of `capacity` slots.
+pub fn slot_range(
+    start: usize,
+    end: usize,
+    capacity: usize,
+) -> Result<RangeInclusive<usize>, ReplayError> {
+    let last_slot = capacity.saturating_sub(1);
+    let (start, end) = (start.min(last_slot), end.min(last_slot));
+    if start > end {
+        return Err(ReplayError::InvertedRange { start, end });
+    }
+    Ok(start..=end)
+}
+
+/// Read the events in `slots` from `source` for a target with `available`
+/// free slots whose newest event has timestamp `floor`.
+pub fn prepare(
+    source: &Journal,
+    slots: impl IntoIterator<Item = usize>,
+    available: usize,
+    floor: Option<u64>,
+    resequence: bool,
+) -> Result<ReplayBatch, ReplayError> {
+    let mut requested = 0;
+    let mut events: Vec<CausalEvent> = slots
+        .into_iter()
+        .inspect(|_| requested += 1)
+        .map(|slot| unsafe { source.read_event_at(slot) })
+        .filter(|event| !is_empty_event(event))
+        .collect();
+    events.truncate(available);
+
+    let mode = if resequence {
+        AppendMode::Resequence
+    } else {
+        AppendMode::Preserve
+    };
+    let resequenced = append::stamp_for_append(&mut events, floor, mode).map_err(|e| {
+        ReplayError::OutOfOrder {
+            lamport_ts: e.lamport_ts,
+            after: e.after,
+        }
+    })?;
+
+    Ok(ReplayBatch {
+        events,
+        mode,
+        requested,
+        resequenced,
+    })
+}
+
+/// Write `events` into the slots of `reservation` and commit it, calling
+/// `progress` with the number written so far. Returns the new head; the
+/// caller publishes the cursor.
+pub fn write(
+    target: &mut Journal,
+    reservation: Reservation<'_>,
+    events: &[CausalEvent],
+    mut progress: impl FnMut(usize),
+) -> usize {
+    debug_assert_eq!(reservation.len(), events.len());
+    for (written, (slot, event)) in reservation.slots().zip(events).enumerate() {
+        // Apart from a restamped `lamport_ts`, the source event as is.
+        unsafe { target.write_event_at(slot, event) };
+        progress(written + 1);
+    }
+    reservation.commit()
+}
+
+/// Timestamp of the newest live event, which is the largest since the
+/// ring is ordered by `lamport_ts`.
+pub fn lamport_floor(journal: &Journal, cursor: &Cursor) -> Option<u64> {
+    let newest = cursor.peek_head_slot()?;
+    Some(unsafe { journal.read_event_at(newest) }.lamport_ts)
+}
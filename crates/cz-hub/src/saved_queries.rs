@@ -0,0 +1,114 @@
+//! # Saved Queries
+//!
+//! Named query DSL texts that can be re-run by id, e.g. from a dashboard
+//! widget. The text is parsed when saved, so a stored query always parses;
+//! it is parsed again on every run.
+
+use cz_hub::query::parser;
+use cz_hub::store::StateStore;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// Collection the saved queries are saved under.
+const STORE_KEY: &str = "saved_queries";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedQuery {
+    pub id: String,
+    pub name: String,
+    pub query_text: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SaveQueryRequest {
+    pub name: String,
+    pub query_text: String,
+}
+
+pub struct SavedQueryManager {
+    queries: RwLock<Vec<SavedQuery>>,
+    store: Option<Arc<StateStore>>,
+}
+
+impl SavedQueryManager {
+    pub fn new() -> Self {
+        Self {
+            queries: RwLock::new(Vec::new()),
+            store: None,
+        }
+    }
+
+    /// Start from the queries saved in `store` and save every change.
+    pub fn with_store(mut self, store: Arc<StateStore>) -> Self {
+        self.queries = RwLock::new(store.load(STORE_KEY).unwrap_or_default());
+        self.store = Some(store);
+        self
+    }
+
+    fn persist(&self, queries: &[SavedQuery]) {
+        if let Some(store) = &self.store {
+            store.save(STORE_KEY, queries);
+        }
+    }
+
+    pub async fn list(&self) -> Vec<SavedQuery> {
+        self.queries.read().await.clone()
+    }
+
+    /// Save `query_text` under `name`; an error if it does not parse.
+    pub async fn create(&self, name: String, query_text: String) -> Result<SavedQuery, String> {
+        parser::parse(&query_text).map_err(|e| e.to_string())?;
+        let query = SavedQuery {
+            id: format!("query-{}", uuid::Uuid::new_v4().as_simple()),
+            name,
+            query_text,
+            created_at: chrono::Utc::now().to_rfc3339(),
+        };
+        let mut queries = self.queries.write().await;
+        queries.push(query.clone());
+        self.persist(&queries);
+        Ok(query)
+    }
+
+    pub async fn get(&self, id: &str) -> Option<SavedQuery> {
+        self.queries
+            .read()
+            .await
+            .iter()
+            .find(|q| q.id == id)
+            .cloned()
+    }
+
+    /// Rename or rewrite a saved query; `Ok(None)` if there is none with
+    /// `id`, an error if `query_text` does not parse.
+    pub async fn update(
+        &self,
+        id: &str,
+        name: String,
+        query_text: String,
+    ) -> Result<Option<SavedQuery>, String> {
+        parser::parse(&query_text).map_err(|e| e.to_string())?;
+        let mut queries = self.queries.write().await;
+        let Some(query) = queries.iter_mut().find(|q| q.id == id) else {
+            return Ok(None);
+        };
+        query.name = name;
+        query.query_text = query_text;
+        let updated = query.clone();
+        self.persist(&queries);
+        Ok(Some(updated))
+    }
+
+    pub async fn delete(&self, id: &str) -> Result<(), String> {
+        let mut queries = self.queries.write().await;
+        let idx = queries
+            .iter()
+            .position(|q| q.id == id)
+            .ok_or("Saved query not found")?;
+        queries.remove(idx);
+        self.persist(&queries);
+        Ok(())
+    }
+}
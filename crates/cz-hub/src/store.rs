@@ -0,0 +1,188 @@
+//! # State Store
+//!
+//! Persistence for the hub's managers (dashboards, saved queries,
+//! pipelines, alert rules, connectors, API keys) under `--data-dir`, one JSON document per
+//! collection.
+//!
+//! Managers load their collection when they are built and hand the store a
+//! fresh snapshot after every mutation. Writes happen behind the caller on
+//! a dedicated thread, which keeps only the newest snapshot of each
+//! collection and replaces files atomically (write to a temp file, then
+//! rename), so a crash leaves either the old or the new document.
+//!
+//! A document that fails to parse is moved aside as
+//! `<name>.json.corrupt-<unix secs>` with a warning and the manager starts
+//! empty, rather than refusing to start.
+
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+
+enum Request {
+    Save { name: String, bytes: Vec<u8> },
+    Flush(mpsc::Sender<()>),
+}
+
+/// A directory of JSON documents with write-behind saves.
+pub struct StateStore {
+    dir: PathBuf,
+    writes: mpsc::Sender<Request>,
+}
+
+impl StateStore {
+    /// Open (creating if needed) the store in `dir`.
+    pub fn open(dir: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        let (writes, requests) = mpsc::channel();
+        let writer_dir = dir.clone();
+        std::thread::Builder::new()
+            .name("cz-hub-state".into())
+            .spawn(move || write_behind(&writer_dir, requests))?;
+        Ok(Self { dir, writes })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", name))
+    }
+
+    /// The stored `name` collection; `None` if it was never saved or had
+    /// to be quarantined.
+    pub fn load<T: DeserializeOwned>(&self, name: &str) -> Option<T> {
+        let path = self.path(name);
+        let bytes = match std::fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+            Err(e) => {
+                tracing::warn!("Cannot read hub state {:?}: {}", path, e);
+                return None;
+            }
+        };
+        match serde_json::from_slice(&bytes) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                let secs = chrono::Utc::now().timestamp();
+                let quarantine = self.dir.join(format!("{}.json.corrupt-{}", name, secs));
+                tracing::warn!(
+                    "Hub state {:?} is corrupt ({}); moved to {:?}, starting empty",
+                    path,
+                    e,
+                    quarantine
+                );
+                if let Err(e) = std::fs::rename(&path, &quarantine) {
+                    tracing::warn!("Cannot quarantine {:?}: {}", path, e);
+                }
+                None
+            }
+        }
+    }
+
+    /// Queue `value` as the new `name` collection.
+    pub fn save<T: Serialize + ?Sized>(&self, name: &str, value: &T) {
+        match serde_json::to_vec_pretty(value) {
+            Ok(bytes) => {
+                let _ = self.writes.send(Request::Save {
+                    name: name.to_string(),
+                    bytes,
+                });
+            }
+            Err(e) => tracing::warn!("Cannot serialize hub state '{}': {}", name, e),
+        }
+    }
+
+    /// Create and remove a file in the store's directory, to tell whether
+    /// saves can still succeed.
+    pub fn check_writable(&self) -> std::io::Result<()> {
+        let probe = self.dir.join(".write-probe");
+        std::fs::write(&probe, b"")?;
+        std::fs::remove_file(&probe)
+    }
+
+    /// Block until every queued save has reached disk.
+    pub fn flush(&self) {
+        let (done, wait) = mpsc::channel();
+        if self.writes.send(Request::Flush(done)).is_ok() {
+            let _ = wait.recv();
+        }
+    }
+}
+
+/// Writer thread: coalesce queued saves per collection, then write them.
+fn write_behind(dir: &Path, requests: mpsc::Receiver<Request>) {
+    while let Ok(first) = requests.recv() {
+        let mut pending: HashMap<String, Vec<u8>> = HashMap::new();
+        let mut flushed = Vec::new();
+        for request in std::iter::once(first).chain(requests.try_iter()) {
+            match request {
+                Request::Save { name, bytes } => {
+                    pending.insert(name, bytes);
+                }
+                Request::Flush(done) => flushed.push(done),
+            }
+        }
+        for (name, bytes) in pending {
+            let path = dir.join(format!("{}.json", name));
+            let tmp = dir.join(format!(".{}.json.tmp", name));
+            let written = std::fs::write(&tmp, &bytes).and_then(|()| std::fs::rename(&tmp, &path));
+            if let Err(e) = written {
+                tracing::warn!("Cannot persist hub state {:?}: {}", path, e);
+            }
+        }
+        for done in flushed {
+            let _ = done.send(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("cz-hub-store-{}-{}", name, std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        dir
+    }
+
+    #[test]
+    fn test_saves_coalesce_and_round_trip() {
+        let dir = temp_dir("roundtrip");
+        let store = StateStore::open(&dir).unwrap();
+        assert_eq!(store.load::<Vec<u32>>("numbers"), None);
+        for n in 1..=50u32 {
+            store.save("numbers", &(1..=n).collect::<Vec<_>>());
+        }
+        store.flush();
+
+        let reopened = StateStore::open(&dir).unwrap();
+        assert_eq!(
+            reopened.load::<Vec<u32>>("numbers"),
+            Some((1..=50).collect())
+        );
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_corrupt_document_is_quarantined() {
+        let dir = temp_dir("corrupt");
+        let store = StateStore::open(&dir).unwrap();
+        std::fs::write(dir.join("numbers.json"), b"[1, 2,").unwrap();
+
+        assert_eq!(store.load::<Vec<u32>>("numbers"), None);
+        assert!(!dir.join("numbers.json").exists());
+        let quarantined: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .map(|e| e.file_name().to_string_lossy().into_owned())
+            .filter(|name| name.starts_with("numbers.json.corrupt-"))
+            .collect();
+        assert_eq!(quarantined.len(), 1);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
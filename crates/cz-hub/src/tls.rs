@@ -0,0 +1,220 @@
+//! # TLS Termination (optional — requires `--features tls`)
+//!
+//! Serves the hub over HTTPS when `cz-hub.toml` has a `[tls]` section with
+//! `cert_path` and `key_path` (PEM). With `client_ca_path`, clients may
+//! present a certificate signed by that CA: its subject CN is matched to
+//! the API key bound to it, standing in for the bearer token. `require_client_cert`
+//! also refuses clients without one at the handshake. A certificate from
+//! another CA is always refused.
+//!
+//! The certificate, key and CA are re-read on every config reload, so a
+//! rotated certificate is picked up without dropping connections; new
+//! handshakes use it.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// `[tls]` in `cz-hub.toml`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+    /// CA whose client certificates are accepted.
+    #[serde(default)]
+    pub client_ca_path: Option<PathBuf>,
+    /// Refuse clients without a certificate (needs `client_ca_path`).
+    #[serde(default)]
+    pub require_client_cert: bool,
+}
+
+impl TlsConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.require_client_cert && self.client_ca_path.is_none() {
+            return Err("tls.require_client_cert needs tls.client_ca_path".into());
+        }
+        Ok(())
+    }
+}
+
+/// The verified client certificate of a request's connection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientIdentity {
+    /// Subject common name, matched to an API key label.
+    pub common_name: String,
+}
+
+#[cfg(feature = "tls")]
+pub use server::{serve, TlsAcceptorHandle};
+
+#[cfg(feature = "tls")]
+mod server {
+    use super::{ClientIdentity, TlsConfig};
+    use axum::extract::ConnectInfo;
+    use axum::Router;
+    use std::path::Path;
+    use std::sync::{Arc, RwLock};
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+    use tokio_rustls::rustls::server::{
+        AllowAnyAnonymousOrAuthenticatedClient, AllowAnyAuthenticatedClient,
+    };
+    use tokio_rustls::rustls::{Certificate, PrivateKey, RootCertStore, ServerConfig};
+    use tokio_rustls::TlsAcceptor;
+
+    /// Handshakes slower than this are dropped.
+    const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+    fn read_pem(path: &Path) -> Result<Vec<rustls_pemfile::Item>, String> {
+        let file = std::fs::File::open(path)
+            .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        rustls_pemfile::read_all(&mut std::io::BufReader::new(file))
+            .map_err(|e| format!("Invalid PEM in {}: {}", path.display(), e))
+    }
+
+    fn certificates(path: &Path) -> Result<Vec<Certificate>, String> {
+        let certs: Vec<Certificate> = read_pem(path)?
+            .into_iter()
+            .filter_map(|item| match item {
+                rustls_pemfile::Item::X509Certificate(der) => Some(Certificate(der)),
+                _ => None,
+            })
+            .collect();
+        if certs.is_empty() {
+            return Err(format!("No certificate in {}", path.display()));
+        }
+        Ok(certs)
+    }
+
+    fn private_key(path: &Path) -> Result<PrivateKey, String> {
+        read_pem(path)?
+            .into_iter()
+            .find_map(|item| match item {
+                rustls_pemfile::Item::PKCS8Key(der)
+                | rustls_pemfile::Item::RSAKey(der)
+                | rustls_pemfile::Item::ECKey(der) => Some(PrivateKey(der)),
+                _ => None,
+            })
+            .ok_or_else(|| format!("No private key in {}", path.display()))
+    }
+
+    /// Build the rustls config `config` describes, reading its files.
+    fn server_config(config: &TlsConfig) -> Result<Arc<ServerConfig>, String> {
+        config.validate()?;
+        let builder = ServerConfig::builder().with_safe_defaults();
+        let builder = match &config.client_ca_path {
+            Some(ca_path) => {
+                let mut roots = RootCertStore::empty();
+                for cert in certificates(ca_path)? {
+                    roots
+                        .add(&cert)
+                        .map_err(|e| format!("Invalid CA in {}: {}", ca_path.display(), e))?;
+                }
+                if config.require_client_cert {
+                    builder
+                        .with_client_cert_verifier(AllowAnyAuthenticatedClient::new(roots).boxed())
+                } else {
+                    builder.with_client_cert_verifier(
+                        AllowAnyAnonymousOrAuthenticatedClient::new(roots).boxed(),
+                    )
+                }
+            }
+            None => builder.with_no_client_auth(),
+        };
+        let mut server = builder
+            .with_single_cert(
+                certificates(&config.cert_path)?,
+                private_key(&config.key_path)?,
+            )
+            .map_err(|e| format!("Invalid certificate or key: {}", e))?;
+        server.alpn_protocols = vec![b"http/1.1".to_vec()];
+        Ok(Arc::new(server))
+    }
+
+    /// The acceptor new connections handshake with, swapped on reload.
+    pub struct TlsAcceptorHandle {
+        acceptor: RwLock<TlsAcceptor>,
+    }
+
+    impl TlsAcceptorHandle {
+        pub fn new(config: &TlsConfig) -> Result<Self, String> {
+            Ok(Self {
+                acceptor: RwLock::new(TlsAcceptor::from(server_config(config)?)),
+            })
+        }
+
+        /// Re-read the certificate, key and CA; on error the current ones
+        /// stay in use.
+        pub fn reload(&self, config: &TlsConfig) -> Result<(), String> {
+            let acceptor = TlsAcceptor::from(server_config(config)?);
+            *self.acceptor.write().unwrap() = acceptor;
+            Ok(())
+        }
+
+        fn current(&self) -> TlsAcceptor {
+            self.acceptor.read().unwrap().clone()
+        }
+    }
+
+    /// Subject CN of a DER certificate.
+    fn common_name(der: &[u8]) -> Option<String> {
+        let (_, cert) = x509_parser::parse_x509_certificate(der).ok()?;
+        let cn = cert.subject().iter_common_name().next()?;
+        cn.as_str().ok().map(str::to_string)
+    }
+
+    /// Accept HTTPS connections on `listener` until the task is dropped.
+    /// Requests carry `ConnectInfo<SocketAddr>` like `axum::serve`, and a
+    /// [`ClientIdentity`] when the client presented a certificate.
+    pub async fn serve(listener: TcpListener, tls: Arc<TlsAcceptorHandle>, app: Router) {
+        loop {
+            let (stream, addr) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    tracing::warn!("TLS accept failed: {}", e);
+                    continue;
+                }
+            };
+            let acceptor = tls.current();
+            let app = app.clone();
+            tokio::spawn(async move {
+                let stream =
+                    match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                        Ok(Ok(stream)) => stream,
+                        Ok(Err(e)) => {
+                            tracing::debug!("TLS handshake with {} failed: {}", addr, e);
+                            return;
+                        }
+                        Err(_) => {
+                            tracing::debug!("TLS handshake with {} timed out", addr);
+                            return;
+                        }
+                    };
+                let identity = stream
+                    .get_ref()
+                    .1
+                    .peer_certificates()
+                    .and_then(|certs| certs.first())
+                    .and_then(|cert| common_name(&cert.0))
+                    .map(|common_name| ClientIdentity { common_name });
+
+                let service = hyper::service::service_fn(
+                    move |mut req: hyper::Request<hyper::body::Incoming>| {
+                        req.extensions_mut().insert(ConnectInfo(addr));
+                        if let Some(identity) = &identity {
+                            req.extensions_mut().insert(identity.clone());
+                        }
+                        tower::Service::call(&mut app.clone(), req.map(axum::body::Body::new))
+                    },
+                );
+                if let Err(e) = hyper::server::conn::http1::Builder::new()
+                    .serve_connection(hyper_util::rt::TokioIo::new(stream), service)
+                    .with_upgrades()
+                    .await
+                {
+                    tracing::debug!("TLS connection from {} ended: {}", addr, e);
+                }
+            });
+        }
+    }
+}
@@ -0,0 +1,455 @@
+//! # Trace Analytics
+//!
+//! Latency and error totals per service and operation, kept up to date as
+//! spans arrive: each span lands in a one-minute [`Summary`], so a query
+//! merges a handful of summaries instead of walking every stored span.
+//! Durations are counted in a [`LatencySketch`], whose quantiles are within
+//! 1% of the true value whatever the number of spans.
+
+use super::{Span, SpanStatus};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Width of the summaries spans are recorded in; query buckets are
+/// multiples of it.
+pub const BUCKET_SECONDS: i64 = 60;
+/// Widest bucket a query may ask for.
+pub const MAX_BUCKET_SECONDS: i64 = 86_400;
+/// Most (service, operation) pairs tracked; spans of further ones are not
+/// counted.
+pub const MAX_SERIES: usize = 10_000;
+
+/// Largest relative error of a [`LatencySketch`] quantile.
+const RELATIVE_ACCURACY: f64 = 0.01;
+/// Durations (in ms) below this count as zero.
+const MIN_DURATION_MS: f64 = 1e-3;
+
+/// A mergeable quantile sketch over durations: each value is counted in a
+/// logarithmic bin `(γ^(i-1), γ^i]`, so a quantile is answered within
+/// [`RELATIVE_ACCURACY`] of the true value from a few hundred counters.
+#[derive(Debug, Clone, Default)]
+pub struct LatencySketch {
+    bins: BTreeMap<i32, u64>,
+    zeros: u64,
+    count: u64,
+}
+
+impl LatencySketch {
+    fn gamma() -> f64 {
+        (1.0 + RELATIVE_ACCURACY) / (1.0 - RELATIVE_ACCURACY)
+    }
+
+    pub fn add(&mut self, value: f64) {
+        self.count += 1;
+        if value < MIN_DURATION_MS {
+            self.zeros += 1;
+            return;
+        }
+        let index = (value.ln() / Self::gamma().ln()).ceil() as i32;
+        *self.bins.entry(index).or_default() += 1;
+    }
+
+    pub fn merge(&mut self, other: &LatencySketch) {
+        for (index, n) in &other.bins {
+            *self.bins.entry(*index).or_default() += n;
+        }
+        self.zeros += other.zeros;
+        self.count += other.count;
+    }
+
+    /// The value at rank `q * (count - 1)`, rounded, for `q` in `0..=1`;
+    /// `None` when empty.
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = (q.clamp(0.0, 1.0) * (self.count - 1) as f64).round() as u64;
+        if rank < self.zeros {
+            return Some(0.0);
+        }
+        let gamma = Self::gamma();
+        let mut seen = self.zeros;
+        for (index, n) in &self.bins {
+            seen += n;
+            if seen > rank {
+                // The point of the bin within `RELATIVE_ACCURACY` of both ends.
+                return Some(2.0 * gamma.powi(*index) / (gamma + 1.0));
+            }
+        }
+        None
+    }
+}
+
+/// Spans of one operation within one bucket.
+#[derive(Debug, Clone, Default)]
+pub struct Summary {
+    count: u64,
+    errors: u64,
+    total_ms: f64,
+    sketch: LatencySketch,
+}
+
+impl Summary {
+    fn add(&mut self, duration_ms: f64, error: bool) {
+        self.count += 1;
+        self.errors += u64::from(error);
+        self.total_ms += duration_ms;
+        self.sketch.add(duration_ms);
+    }
+
+    fn merge(&mut self, other: &Summary) {
+        self.count += other.count;
+        self.errors += other.errors;
+        self.total_ms += other.total_ms;
+        self.sketch.merge(&other.sketch);
+    }
+
+    fn stats(&self) -> LatencyStats {
+        let ms = |q| self.sketch.quantile(q).map(round_ms);
+        LatencyStats {
+            count: self.count,
+            errors: self.errors,
+            error_rate: ratio(self.errors, self.count),
+            avg_ms: round_ms(self.total_ms / self.count.max(1) as f64),
+            p50_ms: ms(0.5),
+            p90_ms: ms(0.9),
+            p99_ms: ms(0.99),
+        }
+    }
+}
+
+/// One-minute [`Summary`]s per service and operation.
+#[derive(Default)]
+pub struct TraceAnalytics {
+    series: HashMap<(String, String), BTreeMap<i64, Summary>>,
+}
+
+#[derive(Deserialize)]
+pub struct AnalyticsParams {
+    pub service: Option<String>,
+    pub operation: Option<String>,
+    /// Bucket width such as `1m`, `15m` or `1h` (default `1m`).
+    pub bucket: Option<String>,
+    pub since: Option<String>, // ISO8601
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnalyticsReport {
+    pub bucket_seconds: i64,
+    pub operations: Vec<OperationAnalytics>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OperationAnalytics {
+    pub service: String,
+    pub operation: String,
+    /// Over every bucket.
+    #[serde(flatten)]
+    pub totals: LatencyStats,
+    /// Oldest first; buckets without spans are left out.
+    pub buckets: Vec<BucketStats>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BucketStats {
+    pub timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    pub stats: LatencyStats,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LatencyStats {
+    pub count: u64,
+    pub errors: u64,
+    pub error_rate: f64,
+    pub avg_ms: f64,
+    pub p50_ms: Option<f64>,
+    pub p90_ms: Option<f64>,
+    pub p99_ms: Option<f64>,
+}
+
+impl TraceAnalytics {
+    /// Count `span` in the minute it started.
+    pub fn record(&mut self, span: &Span) {
+        let key = (span.service_name.clone(), span.name.clone());
+        if !self.series.contains_key(&key) && self.series.len() >= MAX_SERIES {
+            return;
+        }
+        let started = (span.start_time_unix_nano / 1_000_000_000) as i64;
+        self.series
+            .entry(key)
+            .or_default()
+            .entry(started - started.rem_euclid(BUCKET_SECONDS))
+            .or_default()
+            .add(
+                duration_ms(span),
+                matches!(span.status, SpanStatus::Error(_)),
+            );
+    }
+
+    /// Forget the minutes that started before `cutoff`, in Unix seconds.
+    pub fn prune_before(&mut self, cutoff: i64) {
+        self.series.retain(|_, buckets| {
+            *buckets = buckets.split_off(&(cutoff - cutoff.rem_euclid(BUCKET_SECONDS)));
+            !buckets.is_empty()
+        });
+    }
+
+    pub fn report(&self, params: &AnalyticsParams) -> Result<AnalyticsReport, String> {
+        let bucket_seconds = match params.bucket.as_deref() {
+            None => BUCKET_SECONDS,
+            Some(raw) => parse_bucket(raw)?,
+        };
+        let since = match params.since.as_deref() {
+            None => None,
+            Some(raw) => Some(
+                super::parse_since(raw)
+                    .ok_or_else(|| format!("since '{}' is not an RFC 3339 timestamp", raw))?
+                    .timestamp(),
+            ),
+        };
+
+        let mut operations: Vec<OperationAnalytics> = self
+            .series
+            .iter()
+            .filter(|((service, operation), _)| {
+                params.service.as_ref().is_none_or(|s| s == service)
+                    && params.operation.as_ref().is_none_or(|o| o == operation)
+            })
+            .filter_map(|((service, operation), minutes)| {
+                let mut totals = Summary::default();
+                let mut buckets: BTreeMap<i64, Summary> = BTreeMap::new();
+                let first = since.map_or(i64::MIN, |s| s - s.rem_euclid(BUCKET_SECONDS));
+                for (minute, summary) in minutes.range(first..) {
+                    totals.merge(summary);
+                    buckets
+                        .entry(minute - minute.rem_euclid(bucket_seconds))
+                        .or_default()
+                        .merge(summary);
+                }
+                (totals.count > 0).then(|| OperationAnalytics {
+                    service: service.clone(),
+                    operation: operation.clone(),
+                    totals: totals.stats(),
+                    buckets: buckets
+                        .into_iter()
+                        .map(|(start, summary)| BucketStats {
+                            timestamp: DateTime::from_timestamp(start, 0).unwrap_or_default(),
+                            stats: summary.stats(),
+                        })
+                        .collect(),
+                })
+            })
+            .collect();
+        operations.sort_by(|a, b| (&a.service, &a.operation).cmp(&(&b.service, &b.operation)));
+        Ok(AnalyticsReport {
+            bucket_seconds,
+            operations,
+        })
+    }
+}
+
+pub(super) fn duration_ms(span: &Span) -> f64 {
+    span.end_time_unix_nano
+        .saturating_sub(span.start_time_unix_nano) as f64
+        / 1_000_000.0
+}
+
+pub(super) fn ratio(part: u64, whole: u64) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f64 / whole as f64
+    }
+}
+
+pub(super) fn round_ms(ms: f64) -> f64 {
+    (ms * 1000.0).round() / 1000.0
+}
+
+/// A bucket width such as `5m`: whole minutes, up to a day.
+fn parse_bucket(raw: &str) -> Result<i64, String> {
+    let invalid = || {
+        format!(
+            "bucket '{}' must be whole minutes up to a day, such as 1m, 15m or 1h",
+            raw
+        )
+    };
+    let raw = raw.trim();
+    let unit_at = raw
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(invalid)?;
+    let amount: i64 = raw[..unit_at].parse().map_err(|_| invalid())?;
+    let seconds = match &raw[unit_at..] {
+        "s" => amount,
+        "m" => amount * 60,
+        "h" => amount * 3600,
+        "d" => amount * 86_400,
+        _ => return Err(invalid()),
+    };
+    if seconds <= 0 || seconds > MAX_BUCKET_SECONDS || seconds % BUCKET_SECONDS != 0 {
+        return Err(invalid());
+    }
+    Ok(seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traces::test_span;
+
+    fn span(
+        service: &str,
+        operation: &str,
+        start_secs: u64,
+        duration_ms: f64,
+        error: bool,
+    ) -> Span {
+        let start = start_secs * 1_000_000_000;
+        Span {
+            name: operation.into(),
+            service_name: service.into(),
+            start_time_unix_nano: start,
+            end_time_unix_nano: start + (duration_ms * 1_000_000.0) as u64,
+            status: if error {
+                SpanStatus::Error("boom".into())
+            } else {
+                SpanStatus::Ok
+            },
+            ..test_span("t", "s")
+        }
+    }
+
+    fn params(bucket: Option<&str>) -> AnalyticsParams {
+        AnalyticsParams {
+            service: None,
+            operation: None,
+            bucket: bucket.map(Into::into),
+            since: None,
+        }
+    }
+
+    #[test]
+    fn test_sketch_quantiles_are_within_accuracy() {
+        // Uniform and heavy-tailed durations, in a scrambled order.
+        let uniform: Vec<f64> = (1..=1000).map(|i| ((i * 7919) % 1000 + 1) as f64).collect();
+        let tailed: Vec<f64> = (0..5000)
+            .map(|i| (((i * 4813) % 5000) as f64 / 500.0).exp() * 0.05)
+            .collect();
+        for values in [uniform, tailed] {
+            let mut sketch = LatencySketch::default();
+            for v in &values {
+                sketch.add(*v);
+            }
+            let mut sorted = values.clone();
+            sorted.sort_by(f64::total_cmp);
+            for q in [0.0, 0.5, 0.9, 0.99, 1.0] {
+                let exact = sorted[(q * (sorted.len() - 1) as f64).round() as usize];
+                let estimate = sketch.quantile(q).unwrap();
+                assert!(
+                    (estimate - exact).abs() <= exact * RELATIVE_ACCURACY + 1e-9,
+                    "q{}: {} vs {}",
+                    q,
+                    estimate,
+                    exact
+                );
+            }
+        }
+        assert_eq!(LatencySketch::default().quantile(0.5), None);
+
+        // Merging is the same as adding everything to one sketch.
+        let (mut a, mut b, mut all) = Default::default();
+        for v in 1..=200 {
+            let sketch: &mut LatencySketch = if v % 3 == 0 { &mut a } else { &mut b };
+            sketch.add(v as f64);
+            LatencySketch::add(&mut all, v as f64);
+        }
+        a.merge(&b);
+        assert_eq!(a.count, 200);
+        assert_eq!(a.quantile(0.9), all.quantile(0.9));
+    }
+
+    #[test]
+    fn test_report_per_operation_and_bucket() {
+        let mut analytics = TraceAnalytics::default();
+        // checkout.place_order: 1..=100 ms in the first minute, 10 of them
+        // failing; 1000 ms twice in the next.
+        for ms in 1..=100 {
+            analytics.record(&span(
+                "checkout",
+                "place_order",
+                120 + ms % 60,
+                ms as f64,
+                ms % 10 == 0,
+            ));
+        }
+        analytics.record(&span("checkout", "place_order", 185, 1000.0, false));
+        analytics.record(&span("checkout", "place_order", 190, 1000.0, true));
+        analytics.record(&span("cart", "add", 130, 5.0, false));
+
+        let report = analytics.report(&params(None)).unwrap();
+        assert_eq!(report.bucket_seconds, 60);
+        let names: Vec<(&str, &str)> = report
+            .operations
+            .iter()
+            .map(|o| (o.service.as_str(), o.operation.as_str()))
+            .collect();
+        assert_eq!(names, [("cart", "add"), ("checkout", "place_order")]);
+
+        let order = &report.operations[1];
+        assert_eq!((order.totals.count, order.totals.errors), (102, 11));
+        assert!((order.totals.error_rate - 11.0 / 102.0).abs() < 1e-9);
+        assert_eq!(order.buckets.len(), 2);
+        let first = &order.buckets[0].stats;
+        assert_eq!(order.buckets[0].timestamp.timestamp(), 120);
+        assert_eq!(
+            (first.count, first.errors, first.error_rate),
+            (100, 10, 0.1)
+        );
+        assert!((first.avg_ms - 50.5).abs() < 1e-9);
+        for (got, want) in [
+            (first.p50_ms, 51.0),
+            (first.p90_ms, 90.0),
+            (first.p99_ms, 99.0),
+        ] {
+            let got = got.unwrap();
+            assert!(
+                (got - want).abs() <= want * RELATIVE_ACCURACY,
+                "{} vs {}",
+                got,
+                want
+            );
+        }
+        let second = &order.buckets[1].stats;
+        assert_eq!((second.count, second.error_rate), (2, 0.5));
+        assert!((second.p50_ms.unwrap() - 1000.0).abs() <= 10.0);
+
+        // Wider buckets merge the minutes; filters and `since` narrow it.
+        let mut wide = params(Some("5m"));
+        wide.service = Some("checkout".into());
+        let report = analytics.report(&wide).unwrap();
+        assert_eq!(report.bucket_seconds, 300);
+        assert_eq!(report.operations.len(), 1);
+        assert_eq!(report.operations[0].buckets.len(), 1);
+        assert_eq!(report.operations[0].buckets[0].stats.count, 102);
+        let mut recent = params(None);
+        recent.since = Some("1970-01-01T00:03:00Z".into());
+        let report = analytics.report(&recent).unwrap();
+        assert_eq!(report.operations.len(), 1);
+        assert_eq!(report.operations[0].totals.count, 2);
+
+        for bucket in ["90s", "0m", "2d", "fast", "m"] {
+            assert!(
+                analytics.report(&params(Some(bucket))).is_err(),
+                "{}",
+                bucket
+            );
+        }
+
+        analytics.prune_before(180);
+        let report = analytics.report(&params(None)).unwrap();
+        assert_eq!(report.operations.len(), 1);
+        assert_eq!(report.operations[0].totals.count, 2);
+    }
+}
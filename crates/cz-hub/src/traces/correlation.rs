@@ -0,0 +1,208 @@
+//! # Trace Correlation
+//!
+//! Links causal events to the traces they took part in. An event names its
+//! trace with a `trace_id` field of its JSON payload, or a `trace_id` or
+//! W3C `traceparent` entry of its metadata. The index maps each trace id
+//! to its events, and each journal slot back to its trace; a slot the ring
+//! has since reused no longer resolves.
+
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use tokio::sync::RwLock;
+
+/// Trace ids indexed; the first seen is dropped to make room.
+pub const MAX_CORRELATED_TRACES: usize = 10_000;
+/// Events kept per trace; later ones are not indexed.
+pub const MAX_EVENTS_PER_TRACE: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum CorrelatedEvent {
+    /// An event in a journal's Index Ring.
+    Journal {
+        journal: String,
+        slot: usize,
+        lamport_ts: u64,
+    },
+    /// An event received by a connector.
+    Stream {
+        connector_id: String,
+        stream: String,
+        id: String,
+        sequence: u64,
+    },
+}
+
+/// The trace id an event carries, if any.
+pub fn trace_id_of(
+    payload: &serde_json::Value,
+    metadata: &HashMap<String, String>,
+) -> Option<String> {
+    let from_payload = payload.get("trace_id").and_then(|v| v.as_str());
+    let from_metadata = metadata.get("trace_id").map(String::as_str);
+    // traceparent: version-trace_id-parent_id-flags
+    let from_traceparent = metadata
+        .get("traceparent")
+        .and_then(|tp| tp.split('-').nth(1));
+    from_payload
+        .or(from_metadata)
+        .or(from_traceparent)
+        .filter(|id| !id.is_empty())
+        .map(str::to_string)
+}
+
+#[derive(Default)]
+pub struct CorrelationIndex {
+    inner: RwLock<Index>,
+}
+
+#[derive(Default)]
+struct Index {
+    by_trace: HashMap<String, Vec<CorrelatedEvent>>,
+    /// Trace ids, first seen first.
+    order: VecDeque<String>,
+    /// `(journal, slot)` to the Lamport timestamp of the event indexed
+    /// there and its trace.
+    by_slot: HashMap<(String, usize), (u64, String)>,
+}
+
+impl CorrelationIndex {
+    /// Index `event` under `trace_id`. Recording a journal event again is a
+    /// no-op; one in a reused slot replaces the event indexed there.
+    pub async fn record(&self, trace_id: String, event: CorrelatedEvent) {
+        let mut index = self.inner.write().await;
+        if let CorrelatedEvent::Journal {
+            journal,
+            slot,
+            lamport_ts,
+        } = &event
+        {
+            let key = (journal.clone(), *slot);
+            match index.by_slot.get(&key) {
+                Some((ts, _)) if ts == lamport_ts => return,
+                Some((_, previous)) => {
+                    let previous = previous.clone();
+                    index.unlink(&previous, journal, *slot);
+                }
+                None => {}
+            }
+            index.by_slot.insert(key, (*lamport_ts, trace_id.clone()));
+        }
+
+        if !index.by_trace.contains_key(&trace_id) {
+            if index.order.len() >= MAX_CORRELATED_TRACES {
+                if let Some(oldest) = index.order.pop_front() {
+                    index.forget(&oldest);
+                }
+            }
+            index.order.push_back(trace_id.clone());
+        }
+        let events = index.by_trace.entry(trace_id).or_default();
+        if events.len() < MAX_EVENTS_PER_TRACE {
+            events.push(event);
+        }
+    }
+
+    /// The events indexed under `trace_id`, in the order they were seen.
+    pub async fn events(&self, trace_id: &str) -> Vec<CorrelatedEvent> {
+        self.inner
+            .read()
+            .await
+            .by_trace
+            .get(trace_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// The trace of the event at `slot` of `journal`, if it is still the
+    /// one with `lamport_ts`.
+    pub async fn trace_of_slot(
+        &self,
+        journal: &str,
+        slot: usize,
+        lamport_ts: u64,
+    ) -> Option<String> {
+        let index = self.inner.read().await;
+        let (ts, trace_id) = index.by_slot.get(&(journal.to_string(), slot))?;
+        (*ts == lamport_ts).then(|| trace_id.clone())
+    }
+}
+
+impl Index {
+    /// Drop the journal event at `slot` from `trace_id`'s events.
+    fn unlink(&mut self, trace_id: &str, journal: &str, at: usize) {
+        if let Some(events) = self.by_trace.get_mut(trace_id) {
+            events.retain(|e| match e {
+                CorrelatedEvent::Journal {
+                    journal: j, slot, ..
+                } => j != journal || *slot != at,
+                CorrelatedEvent::Stream { .. } => true,
+            });
+        }
+    }
+
+    fn forget(&mut self, trace_id: &str) {
+        for event in self.by_trace.remove(trace_id).unwrap_or_default() {
+            if let CorrelatedEvent::Journal { journal, slot, .. } = event {
+                self.by_slot.remove(&(journal, slot));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn journal_event(slot: usize, lamport_ts: u64) -> CorrelatedEvent {
+        CorrelatedEvent::Journal {
+            journal: "/tmp/a.db".into(),
+            slot,
+            lamport_ts,
+        }
+    }
+
+    #[test]
+    fn test_trace_id_sources() {
+        let none = HashMap::new();
+        let payload = serde_json::json!({"trace_id": "abc", "n": 1});
+        assert_eq!(trace_id_of(&payload, &none).as_deref(), Some("abc"));
+
+        let headers: HashMap<String, String> = [(
+            "traceparent".to_string(),
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01".to_string(),
+        )]
+        .into();
+        assert_eq!(
+            trace_id_of(&serde_json::Value::Null, &headers).as_deref(),
+            Some("4bf92f3577b34da6a3ce929d0e0e4736")
+        );
+        assert_eq!(
+            trace_id_of(&serde_json::json!({"trace_id": ""}), &none),
+            None
+        );
+        assert_eq!(trace_id_of(&serde_json::json!("trace_id"), &none), None);
+    }
+
+    #[tokio::test]
+    async fn test_reused_slots_move_to_their_new_trace() {
+        let index = CorrelationIndex::default();
+        index.record("t1".into(), journal_event(3, 10)).await;
+        // Seen again, e.g. by a scan after the commit notification.
+        index.record("t1".into(), journal_event(3, 10)).await;
+        assert_eq!(index.events("t1").await, [journal_event(3, 10)]);
+        assert_eq!(
+            index.trace_of_slot("/tmp/a.db", 3, 10).await.as_deref(),
+            Some("t1")
+        );
+
+        // The ring laps and slot 3 holds an event of another trace.
+        index.record("t2".into(), journal_event(3, 90)).await;
+        assert!(index.events("t1").await.is_empty());
+        assert_eq!(index.trace_of_slot("/tmp/a.db", 3, 10).await, None);
+        assert_eq!(
+            index.trace_of_slot("/tmp/a.db", 3, 90).await.as_deref(),
+            Some("t2")
+        );
+    }
+}
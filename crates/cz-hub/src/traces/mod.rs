@@ -1,8 +1,34 @@
+//! # Trace Store
+//!
+//! Spans grouped into traces by `trace_id`, held in memory. The store keeps
+//! at most `max_traces`, dropping the traces updated longest ago to make
+//! room, and [`TraceStore::expire`] drops those not updated within
+//! `max_age`, so a trace that is still receiving spans outlives idle ones.
+//! With [`sampling`] enabled, [`TraceStore::finalize`] keeps only some of
+//! the traces that have stopped receiving spans. Latency aggregates per
+//! operation are kept alongside, see [`analytics`]; the causal events of
+//! each trace are indexed in [`correlation`]. [`tree`] nests a trace's
+//! spans for display.
+
+pub mod analytics;
+pub mod correlation;
+pub mod sampling;
+pub mod tree;
+
+use analytics::{AnalyticsParams, AnalyticsReport, TraceAnalytics};
 use chrono::{DateTime, Utc};
+use sampling::{Decision, SamplingPolicy};
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 
+/// Traces kept unless configured otherwise.
+pub const DEFAULT_MAX_TRACES: usize = 1000;
+/// How long a trace is kept after its last span unless configured otherwise.
+pub const DEFAULT_MAX_AGE: Duration = Duration::from_secs(3600);
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Span {
     pub trace_id: String,
@@ -13,9 +39,32 @@ pub struct Span {
     pub start_time_unix_nano: u64,
     pub end_time_unix_nano: u64,
     pub attributes: HashMap<String, serde_json::Value>,
+    #[serde(default)]
+    pub events: Vec<SpanEvent>,
+    #[serde(default)]
+    pub links: Vec<SpanLink>,
     pub status: SpanStatus,
 }
 
+/// Something that happened during a span, such as a log line or an
+/// exception.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SpanEvent {
+    #[serde(alias = "ts")]
+    pub time_unix_nano: u64,
+    pub name: String,
+    #[serde(default, alias = "attrs")]
+    pub attributes: HashMap<String, serde_json::Value>,
+}
+
+/// A span of this or another trace that this one relates to, other than
+/// its parent.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SpanLink {
+    pub trace_id: String,
+    pub span_id: String,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum SpanStatus {
     Unset,
@@ -34,9 +83,62 @@ pub struct Trace {
     pub error_count: usize,
 }
 
+/// A trace with its spans nested, and the journal and connector events
+/// that name it.
+#[derive(Serialize)]
+pub struct TraceDetail {
+    #[serde(flatten)]
+    pub trace: Trace,
+    pub tree: Vec<tree::SpanNode>,
+    pub events: Vec<correlation::CorrelatedEvent>,
+}
+
 pub struct TraceStore {
-    traces: RwLock<HashMap<String, Trace>>,
-    max_traces: usize,
+    traces: RwLock<Traces>,
+    max_traces: AtomicUsize,
+    max_age_ms: AtomicU64,
+    /// Traces dropped to stay within `max_traces`.
+    evicted: AtomicU64,
+    /// Traces dropped for outliving `max_age`.
+    expired: AtomicU64,
+    sampling: std::sync::RwLock<SamplingPolicy>,
+    /// Complete traces by sampling decision.
+    kept_errors: AtomicU64,
+    kept_slow: AtomicU64,
+    kept_sampled: AtomicU64,
+    sampled_out: AtomicU64,
+}
+
+/// Complete traces the sampling policy kept, by rule, and dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SamplingCounts {
+    pub kept_errors: u64,
+    pub kept_slow: u64,
+    pub kept_sampled: u64,
+    pub dropped: u64,
+}
+
+/// Traces with the order they were last updated in.
+#[derive(Default)]
+struct Traces {
+    by_id: HashMap<String, Stored>,
+    /// Trace ids by update sequence number, least recently updated first.
+    by_update: BTreeMap<u64, String>,
+    /// Last sequence number handed out; 0 is never used.
+    last_seq: u64,
+    /// Kept per minute up to `max_age`, so evicting a trace for capacity
+    /// leaves its spans counted.
+    analytics: TraceAnalytics,
+}
+
+struct Stored {
+    trace: Trace,
+    updated: Instant,
+    /// Key of the trace in `by_update`.
+    seq: u64,
+    /// Kept by the sampling policy; later spans join it without another
+    /// decision.
+    sampled: bool,
 }
 
 #[derive(Deserialize)]
@@ -54,43 +156,213 @@ pub struct TraceSearchParams {
 }
 
 impl TraceStore {
-    pub fn new(max_traces: usize) -> Self {
+    pub fn new(max_traces: usize, max_age: Duration) -> Self {
         Self {
-            traces: RwLock::new(HashMap::new()),
-            max_traces,
+            traces: RwLock::new(Traces::default()),
+            max_traces: AtomicUsize::new(max_traces),
+            max_age_ms: AtomicU64::new(max_age.as_millis() as u64),
+            evicted: AtomicU64::new(0),
+            expired: AtomicU64::new(0),
+            sampling: Default::default(),
+            kept_errors: AtomicU64::new(0),
+            kept_slow: AtomicU64::new(0),
+            kept_sampled: AtomicU64::new(0),
+            sampled_out: AtomicU64::new(0),
+        }
+    }
+
+    pub fn sampling(&self) -> SamplingPolicy {
+        self.sampling.read().unwrap().clone()
+    }
+
+    /// Apply `policy` from the next [`finalize`](Self::finalize).
+    pub fn set_sampling(&self, policy: SamplingPolicy) {
+        *self.sampling.write().unwrap() = policy;
+    }
+
+    pub fn sampling_counts(&self) -> SamplingCounts {
+        SamplingCounts {
+            kept_errors: self.kept_errors.load(Ordering::Relaxed),
+            kept_slow: self.kept_slow.load(Ordering::Relaxed),
+            kept_sampled: self.kept_sampled.load(Ordering::Relaxed),
+            dropped: self.sampled_out.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Decide the traces without a span for the completion timeout, if
+    /// sampling is enabled, returning how many were dropped. Their spans
+    /// stay in the analytics; spans arriving after a trace was dropped
+    /// start it anew.
+    pub async fn finalize(&self) -> usize {
+        let policy = self.sampling();
+        if !policy.enabled {
+            return 0;
+        }
+        match Instant::now().checked_sub(policy.completion_timeout()) {
+            Some(cutoff) => self.finalize_before(&policy, cutoff).await,
+            None => 0,
         }
     }
 
+    /// Decide the traces last updated before `cutoff`.
+    async fn finalize_before(&self, policy: &SamplingPolicy, cutoff: Instant) -> usize {
+        let mut store = self.traces.write().await;
+        let Traces {
+            by_id, by_update, ..
+        } = &mut *store;
+        let quiet: Vec<(u64, String)> = by_update
+            .iter()
+            .take_while(|(_, id)| by_id[*id].updated < cutoff)
+            .map(|(seq, id)| (*seq, id.clone()))
+            .collect();
+        let mut dropped = 0;
+        for (seq, id) in quiet {
+            let stored = by_id.get_mut(&id).expect("indexed trace");
+            if stored.sampled {
+                continue;
+            }
+            let counter = match policy.decide(&stored.trace) {
+                Decision::KeptError => &self.kept_errors,
+                Decision::KeptSlow => &self.kept_slow,
+                Decision::KeptSampled => &self.kept_sampled,
+                Decision::Dropped => {
+                    by_id.remove(&id);
+                    by_update.remove(&seq);
+                    dropped += 1;
+                    continue;
+                }
+            };
+            stored.sampled = true;
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+        self.sampled_out
+            .fetch_add(dropped as u64, Ordering::Relaxed);
+        dropped
+    }
+
+    /// Change the limits, applying them to the traces held right away.
+    pub async fn set_limits(&self, max_traces: usize, max_age: Duration) {
+        self.max_traces.store(max_traces, Ordering::Relaxed);
+        self.max_age_ms
+            .store(max_age.as_millis() as u64, Ordering::Relaxed);
+        self.evict(&mut *self.traces.write().await);
+        self.expire().await;
+    }
+
     pub async fn ingest(&self, spans: Vec<Span>) {
+        let now = Instant::now();
         let mut store = self.traces.write().await;
+        let Traces {
+            by_id,
+            by_update,
+            last_seq,
+            analytics,
+        } = &mut *store;
 
         for span in spans {
-            let trace = store.entry(span.trace_id.clone()).or_insert_with(|| Trace {
-                trace_id: span.trace_id.clone(),
-                spans: Vec::new(),
-                root_span: None,
-                start_time: DateTime::from_timestamp_nanos(span.start_time_unix_nano as i64),
-                duration_ms: 0,
-                services: HashSet::new(),
-                error_count: 0,
-            });
-
-            trace.spans.push(span);
-            recompute_trace_summary(trace);
+            let stored = by_id
+                .entry(span.trace_id.clone())
+                .or_insert_with(|| Stored {
+                    trace: Trace {
+                        trace_id: span.trace_id.clone(),
+                        spans: Vec::new(),
+                        root_span: None,
+                        start_time: DateTime::from_timestamp_nanos(
+                            span.start_time_unix_nano as i64,
+                        ),
+                        duration_ms: 0,
+                        services: HashSet::new(),
+                        error_count: 0,
+                    },
+                    updated: now,
+                    seq: 0,
+                    sampled: false,
+                });
+
+            *last_seq += 1;
+            by_update.remove(&stored.seq);
+            by_update.insert(*last_seq, span.trace_id.clone());
+            stored.seq = *last_seq;
+            stored.updated = now;
+
+            analytics.record(&span);
+            stored.trace.spans.push(span);
+            recompute_trace_summary(&mut stored.trace);
         }
 
-        // Re-calculate durations for updated traces
-        // Pruning logic would go here (LRU or random drop when > max_traces)
-        if store.len() > self.max_traces {
-            // Simple random prune for now
-            if let Some(k) = store.keys().next().cloned() {
-                store.remove(&k);
+        self.evict(&mut store);
+    }
+
+    /// Drop the least recently updated traces beyond `max_traces`.
+    fn evict(&self, store: &mut Traces) {
+        let max_traces = self.max_traces.load(Ordering::Relaxed);
+        while store.by_id.len() > max_traces {
+            let Some((_, id)) = store.by_update.pop_first() else {
+                break;
+            };
+            store.by_id.remove(&id);
+            self.evicted.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Drop every trace not updated within `max_age`, returning how many,
+    /// along with the analytics for minutes that ended before it.
+    pub async fn expire(&self) -> usize {
+        let max_age = Duration::from_millis(self.max_age_ms.load(Ordering::Relaxed));
+        let oldest = Utc::now().timestamp() - max_age.as_secs() as i64;
+        self.traces.write().await.analytics.prune_before(oldest);
+        match Instant::now().checked_sub(max_age) {
+            Some(cutoff) => self.expire_before(cutoff).await,
+            None => 0,
+        }
+    }
+
+    /// Drop every trace last updated before `cutoff`.
+    async fn expire_before(&self, cutoff: Instant) -> usize {
+        let mut store = self.traces.write().await;
+        let Traces {
+            by_id, by_update, ..
+        } = &mut *store;
+        let mut dropped = 0;
+        while let Some(entry) = by_update.first_entry() {
+            if by_id[entry.get()].updated >= cutoff {
+                break;
             }
+            by_id.remove(&entry.remove());
+            dropped += 1;
         }
+        self.expired.fetch_add(dropped as u64, Ordering::Relaxed);
+        dropped
+    }
+
+    /// Duration percentiles, counts and error rates per service and
+    /// operation; see [`TraceAnalytics::report`].
+    pub async fn analytics(&self, params: &AnalyticsParams) -> Result<AnalyticsReport, String> {
+        self.traces.read().await.analytics.report(params)
+    }
+
+    /// Traces held.
+    pub async fn count(&self) -> usize {
+        self.traces.read().await.by_id.len()
+    }
+
+    /// Traces dropped so far to stay within `max_traces`.
+    pub fn evicted(&self) -> u64 {
+        self.evicted.load(Ordering::Relaxed)
+    }
+
+    /// Traces dropped so far for outliving `max_age`.
+    pub fn expired(&self) -> u64 {
+        self.expired.load(Ordering::Relaxed)
     }
 
     pub async fn get_trace(&self, trace_id: &str) -> Option<Trace> {
-        self.traces.read().await.get(trace_id).cloned()
+        self.traces
+            .read()
+            .await
+            .by_id
+            .get(trace_id)
+            .map(|stored| stored.trace.clone())
     }
 
     pub async fn search(&self, params: TraceSearchParams) -> Vec<Trace> {
@@ -98,7 +370,9 @@ impl TraceStore {
         let since_filter = params.since.as_deref().and_then(parse_since);
 
         let mut results: Vec<Trace> = store
+            .by_id
             .values()
+            .map(|stored| &stored.trace)
             .filter(|t| {
                 if let Some(svc) = &params.service {
                     if !t.services.contains(svc) {
@@ -135,7 +409,7 @@ impl TraceStore {
             .collect();
 
         // Sort by time desc
-        results.sort_by(|a, b| b.start_time.cmp(&a.start_time));
+        results.sort_by_key(|t| std::cmp::Reverse(t.start_time));
 
         results
             .into_iter()
@@ -145,9 +419,9 @@ impl TraceStore {
 
     pub async fn get_service_graph(&self) -> Vec<ServiceDependency> {
         let store = self.traces.read().await;
-        let mut edges = HashMap::<(String, String), usize>::new();
+        let mut edges = HashMap::<(String, String), EdgeStats>::new();
 
-        for trace in store.values() {
+        for trace in store.by_id.values().map(|stored| &stored.trace) {
             // Map span_id -> Span for quick lookup
             let span_map: HashMap<&String, &Span> =
                 trace.spans.iter().map(|s| (&s.span_id, s)).collect();
@@ -156,9 +430,12 @@ impl TraceStore {
                 if let Some(parent_id) = &span.parent_span_id {
                     if let Some(parent) = span_map.get(parent_id) {
                         if parent.service_name != span.service_name {
-                            *edges
+                            let edge = edges
                                 .entry((parent.service_name.clone(), span.service_name.clone()))
-                                .or_insert(0) += 1;
+                                .or_default();
+                            edge.count += 1;
+                            edge.total_ms += analytics::duration_ms(span);
+                            edge.errors += usize::from(matches!(span.status, SpanStatus::Error(_)));
                         }
                     }
                 }
@@ -167,16 +444,34 @@ impl TraceStore {
 
         edges
             .into_iter()
-            .map(|((from, to), count)| ServiceDependency { from, to, count })
+            .map(|((from, to), edge)| ServiceDependency {
+                from,
+                to,
+                count: edge.count,
+                avg_latency_ms: analytics::round_ms(edge.total_ms / edge.count as f64),
+                error_ratio: analytics::ratio(edge.errors as u64, edge.count as u64),
+            })
             .collect()
     }
 }
 
+/// Calls from one service to another, measured by the callee's spans.
+#[derive(Default)]
+struct EdgeStats {
+    count: usize,
+    total_ms: f64,
+    errors: usize,
+}
+
 #[derive(Serialize)]
 pub struct ServiceDependency {
     pub from: String,
     pub to: String,
     pub count: usize,
+    /// Mean duration of the calls.
+    pub avg_latency_ms: f64,
+    /// Share of the calls whose span failed.
+    pub error_ratio: f64,
 }
 
 fn recompute_trace_summary(trace: &mut Trace) {
@@ -218,3 +513,225 @@ fn parse_since(value: &str) -> Option<DateTime<Utc>> {
         .ok()
         .map(|dt| dt.with_timezone(&Utc))
 }
+
+/// A root span `op` of `svc` from 1 ms to 2 ms, for tests to adjust with
+/// struct update syntax.
+#[cfg(test)]
+pub(crate) fn test_span(trace_id: &str, span_id: &str) -> Span {
+    Span {
+        trace_id: trace_id.into(),
+        span_id: span_id.into(),
+        parent_span_id: None,
+        name: "op".into(),
+        service_name: "svc".into(),
+        start_time_unix_nano: 1_000_000,
+        end_time_unix_nano: 2_000_000,
+        attributes: HashMap::new(),
+        events: Vec::new(),
+        links: Vec::new(),
+        status: SpanStatus::Ok,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_most_recently_updated_traces_survive() {
+        let store = TraceStore::new(3, DEFAULT_MAX_AGE);
+        for id in ["a", "b", "c"] {
+            store.ingest(vec![test_span(id, "root")]).await;
+        }
+        // A span for `a` makes it the most recent, so `b` goes first.
+        store.ingest(vec![test_span("a", "child")]).await;
+        store.ingest(vec![test_span("d", "root")]).await;
+        assert!(store.get_trace("b").await.is_none());
+        assert_eq!(store.get_trace("a").await.unwrap().spans.len(), 2);
+
+        // One batch beyond capacity keeps its last traces.
+        store
+            .ingest(
+                (0..5)
+                    .map(|i| test_span(&format!("x{}", i), "root"))
+                    .collect(),
+            )
+            .await;
+        assert_eq!(store.count().await, 3);
+        for id in ["x2", "x3", "x4"] {
+            assert!(store.get_trace(id).await.is_some(), "{} was evicted", id);
+        }
+        assert_eq!(store.evicted(), 6);
+        assert_eq!(store.expired(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_traces_expire_by_last_update() {
+        let store = TraceStore::new(10, DEFAULT_MAX_AGE);
+        store.ingest(vec![test_span("old", "root")]).await;
+        store.ingest(vec![test_span("busy", "root")]).await;
+        tokio::time::sleep(Duration::from_millis(2)).await;
+        let cutoff = Instant::now();
+        tokio::time::sleep(Duration::from_millis(2)).await;
+        store.ingest(vec![test_span("busy", "child")]).await;
+        store.ingest(vec![test_span("new", "root")]).await;
+
+        assert_eq!(store.expire_before(cutoff).await, 1);
+        assert!(store.get_trace("old").await.is_none());
+        assert_eq!(store.get_trace("busy").await.unwrap().spans.len(), 2);
+        assert_eq!((store.count().await, store.expired()), (2, 1));
+        // Nothing is old enough under the default age.
+        assert_eq!(store.expire().await, 0);
+
+        // Tighter limits apply at once.
+        store.set_limits(1, DEFAULT_MAX_AGE).await;
+        let remaining: Vec<Trace> = store
+            .search(TraceSearchParams {
+                service: None,
+                operation: None,
+                min_duration_ms: None,
+                limit: None,
+                since: None,
+            })
+            .await;
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].trace_id, "new");
+        store.set_limits(1, Duration::ZERO).await;
+        assert_eq!(store.count().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_service_graph_edges_carry_latency_and_errors() {
+        let store = TraceStore::new(10, DEFAULT_MAX_AGE);
+        let call = |trace: &str, id: &str, service: &str, ms: u64, error: bool| Span {
+            parent_span_id: Some("root".into()),
+            service_name: service.into(),
+            end_time_unix_nano: 1_000_000 + ms * 1_000_000,
+            status: if error {
+                SpanStatus::Error("timeout".into())
+            } else {
+                SpanStatus::Ok
+            },
+            ..test_span(trace, id)
+        };
+        store
+            .ingest(vec![
+                Span {
+                    service_name: "gateway".into(),
+                    ..test_span("t1", "root")
+                },
+                call("t1", "a", "orders", 10, false),
+                call("t1", "b", "orders", 30, true),
+                // Calls within a service are not edges.
+                call("t1", "c", "gateway", 500, true),
+            ])
+            .await;
+        store
+            .ingest(vec![
+                Span {
+                    service_name: "gateway".into(),
+                    ..test_span("t2", "root")
+                },
+                call("t2", "a", "orders", 20, false),
+                call("t2", "b", "orders", 40, false),
+            ])
+            .await;
+
+        let graph = store.get_service_graph().await;
+        assert_eq!(graph.len(), 1);
+        let edge = &graph[0];
+        assert_eq!(
+            (edge.from.as_str(), edge.to.as_str()),
+            ("gateway", "orders")
+        );
+        assert_eq!(edge.count, 4);
+        assert_eq!(edge.avg_latency_ms, 25.0);
+        assert_eq!(edge.error_ratio, 0.25);
+
+        // The same spans feed the per-operation analytics.
+        let report = store
+            .analytics(&AnalyticsParams {
+                service: Some("orders".into()),
+                operation: None,
+                bucket: None,
+                since: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(report.operations[0].totals.count, 4);
+        assert_eq!(report.operations[0].totals.error_rate, 0.25);
+    }
+
+    #[tokio::test]
+    async fn test_quiet_traces_are_sampled_but_stay_in_analytics() {
+        let store = TraceStore::new(10, DEFAULT_MAX_AGE);
+        let policy = SamplingPolicy {
+            enabled: true,
+            latency_threshold_ms: Some(100),
+            sample_rate: 0.0,
+            ..Default::default()
+        };
+        store.set_sampling(policy.clone());
+        store
+            .ingest(vec![
+                Span {
+                    status: SpanStatus::Error("boom".into()),
+                    ..test_span("failed", "root")
+                },
+                Span {
+                    end_time_unix_nano: 500_000_000,
+                    ..test_span("slow", "root")
+                },
+                test_span("fast", "root"),
+            ])
+            .await;
+        tokio::time::sleep(Duration::from_millis(2)).await;
+        let cutoff = Instant::now();
+        store.ingest(vec![test_span("busy", "root")]).await;
+
+        assert_eq!(store.finalize_before(&policy, cutoff).await, 1);
+        assert!(store.get_trace("fast").await.is_none());
+        for id in ["failed", "slow", "busy"] {
+            assert!(store.get_trace(id).await.is_some(), "{} was dropped", id);
+        }
+        assert_eq!(
+            store.sampling_counts(),
+            SamplingCounts {
+                kept_errors: 1,
+                kept_slow: 1,
+                kept_sampled: 0,
+                dropped: 1,
+            }
+        );
+        // The dropped trace's span is still counted.
+        let report = store
+            .analytics(&AnalyticsParams {
+                service: None,
+                operation: None,
+                bucket: None,
+                since: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(report.operations[0].totals.count, 4);
+
+        // A kept trace is not decided again, whatever the policy.
+        store.ingest(vec![test_span("slow", "child")]).await;
+        let cutoff = Instant::now() + Duration::from_secs(1);
+        store.set_sampling(SamplingPolicy {
+            latency_threshold_ms: None,
+            keep_errors: false,
+            ..policy
+        });
+        assert_eq!(store.finalize_before(&store.sampling(), cutoff).await, 1);
+        assert!(store.get_trace("busy").await.is_none());
+        assert_eq!(store.get_trace("slow").await.unwrap().spans.len(), 2);
+        assert_eq!(store.count().await, 2);
+
+        // Disabled, nothing is decided.
+        store.set_sampling(SamplingPolicy::default());
+        store.ingest(vec![test_span("later", "root")]).await;
+        assert_eq!(store.finalize().await, 0);
+        assert_eq!(store.count().await, 3);
+    }
+}
@@ -0,0 +1,250 @@
+//! # Tail Sampling
+//!
+//! Which complete traces the store keeps. A trace is complete once no span
+//! has arrived for `completion_timeout_secs`; it is then kept if it has an
+//! error or lasted at least `latency_threshold_ms`, and otherwise with
+//! probability `sample_rate`. The service of the root span may override
+//! the threshold and the rate. The roll is a hash of the trace id, so a
+//! trace is decided the same way every time.
+
+use super::Trace;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// `[traces.sampling]` in `cz-hub.toml`, also `/api/traces/sampling`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SamplingPolicy {
+    /// Off, every trace is kept until evicted or expired.
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_true")]
+    pub keep_errors: bool,
+    /// Keep traces lasting at least this long.
+    #[serde(default)]
+    pub latency_threshold_ms: Option<u64>,
+    /// Share of the other traces kept, within 0..=1.
+    #[serde(default = "default_sample_rate")]
+    pub sample_rate: f64,
+    #[serde(default = "default_completion_timeout")]
+    pub completion_timeout_secs: u64,
+    /// Overrides by the service of the root span.
+    #[serde(default)]
+    pub services: BTreeMap<String, ServiceSampling>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ServiceSampling {
+    #[serde(default)]
+    pub latency_threshold_ms: Option<u64>,
+    #[serde(default)]
+    pub sample_rate: Option<f64>,
+}
+
+impl Default for SamplingPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            keep_errors: true,
+            latency_threshold_ms: None,
+            sample_rate: default_sample_rate(),
+            completion_timeout_secs: default_completion_timeout(),
+            services: BTreeMap::new(),
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+fn default_sample_rate() -> f64 {
+    0.1
+}
+fn default_completion_timeout() -> u64 {
+    30
+}
+
+/// What became of a complete trace, by the rule that decided it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    KeptError,
+    KeptSlow,
+    KeptSampled,
+    Dropped,
+}
+
+impl SamplingPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        let rates = std::iter::once(("sample_rate".to_string(), self.sample_rate)).chain(
+            self.services.iter().filter_map(|(service, o)| {
+                o.sample_rate
+                    .map(|rate| (format!("services.{}.sample_rate", service), rate))
+            }),
+        );
+        for (name, rate) in rates {
+            if !(0.0..=1.0).contains(&rate) {
+                return Err(format!("{} must be within 0..=1, got {}", name, rate));
+            }
+        }
+        if self.completion_timeout_secs == 0 {
+            return Err("completion_timeout_secs must be at least 1".into());
+        }
+        Ok(())
+    }
+
+    pub fn completion_timeout(&self) -> Duration {
+        Duration::from_secs(self.completion_timeout_secs)
+    }
+
+    pub fn decide(&self, trace: &Trace) -> Decision {
+        let service = trace
+            .root_span
+            .as_ref()
+            .and_then(|root| self.services.get(&root.service_name));
+        let threshold = service
+            .and_then(|s| s.latency_threshold_ms)
+            .or(self.latency_threshold_ms);
+        let rate = service
+            .and_then(|s| s.sample_rate)
+            .unwrap_or(self.sample_rate);
+
+        if self.keep_errors && trace.error_count > 0 {
+            Decision::KeptError
+        } else if threshold.is_some_and(|ms| trace.duration_ms >= ms) {
+            Decision::KeptSlow
+        } else if roll(&trace.trace_id) < rate {
+            Decision::KeptSampled
+        } else {
+            Decision::Dropped
+        }
+    }
+}
+
+/// `trace_id` hashed to a number in `0..1`: FNV-1a, then the MurmurHash3
+/// finalizer so that ids differing only in their last characters spread
+/// over the whole range.
+fn roll(trace_id: &str) -> f64 {
+    let mut hash = trace_id.bytes().fold(0xcbf2_9ce4_8422_2325u64, |h, b| {
+        (h ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    });
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    hash ^= hash >> 33;
+    (hash >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traces::{test_span, Span};
+    use chrono::DateTime;
+    use std::collections::HashSet;
+
+    fn trace(id: &str, service: &str, duration_ms: u64, errors: usize) -> Trace {
+        let root = Span {
+            service_name: service.into(),
+            start_time_unix_nano: 0,
+            end_time_unix_nano: duration_ms * 1_000_000,
+            ..test_span(id, "root")
+        };
+        Trace {
+            trace_id: id.into(),
+            spans: vec![root.clone()],
+            root_span: Some(root),
+            start_time: DateTime::from_timestamp_nanos(0),
+            duration_ms,
+            services: HashSet::from([service.to_string()]),
+            error_count: errors,
+        }
+    }
+
+    fn policy(rate: f64) -> SamplingPolicy {
+        SamplingPolicy {
+            enabled: true,
+            latency_threshold_ms: Some(500),
+            sample_rate: rate,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_errors_and_slow_traces_are_always_kept() {
+        let never = policy(0.0);
+        assert_eq!(
+            never.decide(&trace("a", "shop", 10, 1)),
+            Decision::KeptError
+        );
+        assert_eq!(
+            never.decide(&trace("b", "shop", 500, 0)),
+            Decision::KeptSlow
+        );
+        assert_eq!(never.decide(&trace("c", "shop", 499, 0)), Decision::Dropped);
+
+        let no_errors = SamplingPolicy {
+            keep_errors: false,
+            ..never.clone()
+        };
+        assert_eq!(
+            no_errors.decide(&trace("a", "shop", 10, 1)),
+            Decision::Dropped
+        );
+        let no_threshold = SamplingPolicy {
+            latency_threshold_ms: None,
+            ..never
+        };
+        assert_eq!(
+            no_threshold.decide(&trace("b", "shop", 60_000, 0)),
+            Decision::Dropped
+        );
+    }
+
+    #[test]
+    fn test_sample_rate_keeps_that_share_of_the_rest() {
+        let quarter = policy(0.25);
+        let kept = (0..10_000)
+            .filter(|i| {
+                quarter.decide(&trace(&format!("{:032x}", i), "shop", 10, 0))
+                    == Decision::KeptSampled
+            })
+            .count();
+        assert!((2300..=2700).contains(&kept), "{}", kept);
+        // The same trace is always decided the same way.
+        let t = trace("4bf92f3577b34da6a3ce929d0e0e4736", "shop", 10, 0);
+        assert!((0..10).all(|_| quarter.decide(&t) == quarter.decide(&t)));
+        assert_eq!(policy(1.0).decide(&t), Decision::KeptSampled);
+    }
+
+    #[test]
+    fn test_root_service_overrides() {
+        let mut overridden = policy(0.0);
+        overridden.services.insert(
+            "payments".into(),
+            ServiceSampling {
+                latency_threshold_ms: Some(50),
+                sample_rate: Some(1.0),
+            },
+        );
+        assert_eq!(
+            overridden.decide(&trace("a", "payments", 60, 0)),
+            Decision::KeptSlow
+        );
+        assert_eq!(
+            overridden.decide(&trace("b", "payments", 5, 0)),
+            Decision::KeptSampled
+        );
+        assert_eq!(
+            overridden.decide(&trace("c", "shop", 60, 0)),
+            Decision::Dropped
+        );
+
+        overridden.services.get_mut("payments").unwrap().sample_rate = Some(1.5);
+        assert_eq!(
+            overridden.validate(),
+            Err("services.payments.sample_rate must be within 0..=1, got 1.5".into())
+        );
+    }
+}
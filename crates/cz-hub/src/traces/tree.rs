@@ -0,0 +1,217 @@
+//! # Span Tree
+//!
+//! The spans of a trace nested under their parents, ready to draw as a
+//! waterfall. Spans whose parent never arrived hang under one synthetic
+//! root at the end, as do spans caught in a parent cycle or nested deeper
+//! than [`MAX_TREE_DEPTH`]. Siblings are ordered by start time.
+
+use super::analytics::round_ms;
+use super::{Span, SpanStatus};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Levels nested before the rest of a branch moves to the synthetic root.
+pub const MAX_TREE_DEPTH: usize = 128;
+
+/// Name of the synthetic root holding the spans without a parent.
+pub const ORPHANS_NAME: &str = "(missing parent)";
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SpanNode {
+    /// `None` for the synthetic root.
+    pub span_id: Option<String>,
+    pub name: String,
+    pub service_name: String,
+    /// From the start of the trace.
+    pub start_offset_ms: f64,
+    pub duration_ms: f64,
+    /// Time not covered by any child.
+    pub self_time_ms: f64,
+    pub error: bool,
+    pub children: Vec<SpanNode>,
+}
+
+/// Build the tree of `spans`: the roots, then the synthetic root if any
+/// span could not be placed under one.
+pub fn build(spans: &[Span]) -> Vec<SpanNode> {
+    let Some(trace_start) = spans.iter().map(|s| s.start_time_unix_nano).min() else {
+        return Vec::new();
+    };
+    let mut order: Vec<usize> = (0..spans.len()).collect();
+    order.sort_by_key(|&i| spans[i].start_time_unix_nano);
+
+    // The first span with an id wins if it is repeated.
+    let mut by_id = HashMap::new();
+    for (i, span) in spans.iter().enumerate() {
+        by_id.entry(span.span_id.as_str()).or_insert(i);
+    }
+    let mut children: HashMap<usize, Vec<usize>> = HashMap::new();
+    let (mut roots, mut orphans) = (Vec::new(), Vec::new());
+    for &i in &order {
+        match spans[i].parent_span_id.as_deref() {
+            None => roots.push(i),
+            Some(parent) => match by_id.get(parent) {
+                Some(&p) => children.entry(p).or_default().push(i),
+                None => orphans.push(i),
+            },
+        }
+    }
+
+    let mut tree = Tree {
+        spans,
+        children,
+        trace_start,
+        placed: vec![false; spans.len()],
+    };
+    let mut nodes: Vec<SpanNode> = roots.into_iter().map(|i| tree.node(i, 0)).collect();
+    // Orphans first, so their descendants stay under them; what is left
+    // sits in a cycle or too deep.
+    let stranded: Vec<usize> = orphans.into_iter().chain(order).collect();
+    let mut adopted = Vec::new();
+    for i in stranded {
+        if !tree.placed[i] {
+            adopted.push(tree.node(i, 1));
+        }
+    }
+    if !adopted.is_empty() {
+        nodes.push(orphans_root(adopted));
+    }
+    nodes
+}
+
+struct Tree<'a> {
+    spans: &'a [Span],
+    children: HashMap<usize, Vec<usize>>,
+    trace_start: u64,
+    placed: Vec<bool>,
+}
+
+impl Tree<'_> {
+    fn node(&mut self, i: usize, depth: usize) -> SpanNode {
+        self.placed[i] = true;
+        let (mut children, mut intervals) = (Vec::new(), Vec::new());
+        if depth + 1 < MAX_TREE_DEPTH {
+            for child in self.children.get(&i).cloned().unwrap_or_default() {
+                if !self.placed[child] {
+                    let c = &self.spans[child];
+                    intervals.push((c.start_time_unix_nano, c.end_time_unix_nano));
+                    children.push(self.node(child, depth + 1));
+                }
+            }
+        }
+
+        let span = &self.spans[i];
+        let (start, end) = (span.start_time_unix_nano, span.end_time_unix_nano);
+        let covered = covered_nanos(start, end, intervals);
+        SpanNode {
+            span_id: Some(span.span_id.clone()),
+            name: span.name.clone(),
+            service_name: span.service_name.clone(),
+            start_offset_ms: nanos_to_ms(start - self.trace_start),
+            duration_ms: nanos_to_ms(end.saturating_sub(start)),
+            self_time_ms: nanos_to_ms(end.saturating_sub(start) - covered),
+            error: matches!(span.status, SpanStatus::Error(_)),
+            children,
+        }
+    }
+}
+
+fn orphans_root(children: Vec<SpanNode>) -> SpanNode {
+    let start = children
+        .iter()
+        .map(|c| c.start_offset_ms)
+        .fold(f64::INFINITY, f64::min);
+    let end = children
+        .iter()
+        .map(|c| c.start_offset_ms + c.duration_ms)
+        .fold(0.0, f64::max);
+    SpanNode {
+        span_id: None,
+        name: ORPHANS_NAME.into(),
+        service_name: String::new(),
+        start_offset_ms: start,
+        duration_ms: round_ms(end - start),
+        self_time_ms: 0.0,
+        error: false,
+        children,
+    }
+}
+
+/// Nanoseconds of `start..end` covered by the union of `intervals`.
+fn covered_nanos(start: u64, end: u64, mut intervals: Vec<(u64, u64)>) -> u64 {
+    intervals.sort_unstable();
+    let mut covered = 0;
+    let mut reached = start;
+    for (from, to) in intervals {
+        let (from, to) = (from.max(reached), to.min(end));
+        if from < to {
+            covered += to - from;
+            reached = to;
+        }
+    }
+    covered
+}
+
+fn nanos_to_ms(nanos: u64) -> f64 {
+    round_ms(nanos as f64 / 1_000_000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traces::test_span;
+
+    fn span(id: &str, parent: Option<&str>, start_ms: u64, end_ms: u64) -> Span {
+        Span {
+            parent_span_id: parent.map(str::to_string),
+            start_time_unix_nano: start_ms * 1_000_000,
+            end_time_unix_nano: end_ms * 1_000_000,
+            ..test_span("t", id)
+        }
+    }
+
+    fn ids(nodes: &[SpanNode]) -> Vec<Option<&str>> {
+        nodes.iter().map(|n| n.span_id.as_deref()).collect()
+    }
+
+    #[test]
+    fn test_three_levels_nest_with_self_time() {
+        // Arrival order does not matter; siblings sort by start.
+        let tree = build(&[
+            span("db", Some("api"), 30, 60),
+            span("cache", Some("api"), 20, 40),
+            span("api", Some("root"), 10, 90),
+            span("root", None, 0, 100),
+        ]);
+        assert_eq!(ids(&tree), [Some("root")]);
+        let root = &tree[0];
+        assert_eq!((root.duration_ms, root.self_time_ms), (100.0, 20.0));
+        let api = &root.children[0];
+        assert_eq!(api.start_offset_ms, 10.0);
+        assert_eq!(ids(&api.children), [Some("cache"), Some("db")]);
+        // The overlapping children cover 20..60.
+        assert_eq!(api.self_time_ms, 40.0);
+        assert!(api.children.iter().all(|c| c.children.is_empty()));
+        assert_eq!(api.children[1].self_time_ms, 30.0);
+    }
+
+    #[test]
+    fn test_orphans_and_cycles_go_under_the_synthetic_root() {
+        let tree = build(&[
+            span("root", None, 0, 10),
+            span("lost", Some("gone"), 20, 50),
+            span("child", Some("lost"), 25, 30),
+            // a and b are each other's parent.
+            span("a", Some("b"), 40, 45),
+            span("b", Some("a"), 41, 44),
+        ]);
+        assert_eq!(ids(&tree), [Some("root"), None]);
+        let orphans = &tree[1];
+        assert_eq!(orphans.name, ORPHANS_NAME);
+        assert_eq!((orphans.start_offset_ms, orphans.duration_ms), (20.0, 30.0));
+        assert_eq!(ids(&orphans.children), [Some("lost"), Some("a")]);
+        assert_eq!(ids(&orphans.children[0].children), [Some("child")]);
+        assert_eq!(ids(&orphans.children[1].children), [Some("b")]);
+        assert!(build(&[]).is_empty());
+    }
+}
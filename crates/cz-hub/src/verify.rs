@@ -0,0 +1,355 @@
+//! # Verification Reports
+//!
+//! Runs the Kani harnesses and turns `cargo kani` output into a
+//! [`VerificationReport`]: one entry per harness with its status, how long
+//! CBMC took, and the failed checks (plus any concrete playback test Kani
+//! printed) as counterexample text. `cz verify --json` prints the report;
+//! the hub runs it as a background job behind `POST /api/verify`.
+
+use std::path::Path;
+use std::process::Command;
+use std::time::Instant;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Packages whose Kani harnesses make up a full verification run.
+pub const KANI_PACKAGES: [&str; 2] = ["cz-verify", "cz-io"];
+
+/// Outcome of a single harness.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HarnessStatus {
+    Passed,
+    Failed,
+    /// Kani started the harness but never reported a verdict (e.g. CBMC
+    /// crashed or was killed).
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HarnessResult {
+    pub package: String,
+    pub name: String,
+    pub status: HarnessStatus,
+    pub duration_ms: Option<u64>,
+    /// Failed checks and, if requested, the concrete playback test.
+    pub counterexample: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerificationReport {
+    pub success: bool,
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    pub harnesses: Vec<HarnessResult>,
+    /// Problems that kept a package from reporting per-harness results,
+    /// such as Kani not being installed or the crate failing to build.
+    pub errors: Vec<String>,
+}
+
+impl VerificationReport {
+    pub fn passed(&self) -> usize {
+        self.count(HarnessStatus::Passed)
+    }
+
+    pub fn failed(&self) -> usize {
+        self.harnesses.len() - self.passed()
+    }
+
+    fn count(&self, status: HarnessStatus) -> usize {
+        self.harnesses.iter().filter(|h| h.status == status).count()
+    }
+}
+
+/// Run `cargo kani` for each of `packages` in `workdir` and collect the
+/// results. Blocks for as long as Kani takes, which is minutes.
+pub fn run_kani(packages: &[&str], workdir: &Path) -> VerificationReport {
+    let started_at = Utc::now();
+    let start = Instant::now();
+    let mut harnesses = Vec::new();
+    let mut errors = Vec::new();
+
+    for package in packages {
+        let output = Command::new("cargo")
+            .args(["kani", "--package", package])
+            .current_dir(workdir)
+            .output();
+        let output = match output {
+            Ok(output) => output,
+            Err(e) => {
+                errors.push(format!("{}: failed to run cargo kani: {}", package, e));
+                continue;
+            }
+        };
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let results = parse_kani_output(package, &stdout);
+        if !output.status.success() && results.iter().all(|r| r.status == HarnessStatus::Passed) {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            errors.push(format!(
+                "{}: cargo kani exited with {}: {}",
+                package,
+                output.status,
+                tail(&stderr, 20)
+            ));
+        }
+        harnesses.extend(results);
+    }
+
+    let success = errors.is_empty()
+        && !harnesses.is_empty()
+        && harnesses.iter().all(|h| h.status == HarnessStatus::Passed);
+    VerificationReport {
+        success,
+        started_at,
+        duration_ms: start.elapsed().as_millis() as u64,
+        harnesses,
+        errors,
+    }
+}
+
+/// Parse the stdout of one `cargo kani --package <package>` run.
+pub fn parse_kani_output(package: &str, output: &str) -> Vec<HarnessResult> {
+    let mut results = Vec::new();
+    let mut current: Option<Section> = None;
+
+    for line in output.lines() {
+        if let Some(name) = line
+            .strip_prefix("Checking harness ")
+            .map(|rest| rest.trim_end().trim_end_matches("..."))
+        {
+            if let Some(section) = current.take() {
+                results.push(section.finish(package));
+            }
+            current = Some(Section::new(name));
+            continue;
+        }
+        if let Some(section) = current.as_mut() {
+            section.feed(line);
+        }
+    }
+    if let Some(section) = current {
+        results.push(section.finish(package));
+    }
+    results
+}
+
+/// Output of one harness, from its `Checking harness` line to the next.
+struct Section {
+    name: String,
+    status: HarnessStatus,
+    duration_ms: Option<u64>,
+    failed_checks: Vec<String>,
+    in_failed_checks: bool,
+    playback: Vec<String>,
+    in_playback: u8,
+}
+
+impl Section {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            status: HarnessStatus::Unknown,
+            duration_ms: None,
+            failed_checks: Vec::new(),
+            in_failed_checks: false,
+            playback: Vec::new(),
+            in_playback: 0,
+        }
+    }
+
+    fn feed(&mut self, line: &str) {
+        let trimmed = line.trim();
+
+        // The playback test is a fenced block: header, ```, code, ```.
+        if self.in_playback > 0 {
+            self.playback.push(line.to_string());
+            if trimmed.starts_with("```") {
+                self.in_playback -= 1;
+            }
+            return;
+        }
+        if trimmed.starts_with("Concrete playback unit test for") {
+            self.playback.push(line.to_string());
+            self.in_playback = 2;
+            return;
+        }
+
+        if let Some(verdict) = trimmed.strip_prefix("VERIFICATION:-") {
+            self.in_failed_checks = false;
+            self.status = if verdict.trim_start().starts_with("SUCCESSFUL") {
+                HarnessStatus::Passed
+            } else {
+                HarnessStatus::Failed
+            };
+        } else if let Some(time) = trimmed.strip_prefix("Verification Time:") {
+            self.duration_ms = time
+                .trim()
+                .trim_end_matches('s')
+                .parse::<f64>()
+                .ok()
+                .map(|secs| (secs * 1000.0).round() as u64);
+        } else if trimmed.starts_with("Failed Checks:") {
+            self.in_failed_checks = true;
+            self.failed_checks.push(trimmed.to_string());
+        } else if self.in_failed_checks && !trimmed.is_empty() {
+            self.failed_checks.push(trimmed.to_string());
+        }
+    }
+
+    fn finish(self, package: &str) -> HarnessResult {
+        let mut text = self.failed_checks;
+        text.extend(self.playback);
+        HarnessResult {
+            package: package.to_string(),
+            name: self.name,
+            status: self.status,
+            duration_ms: self.duration_ms,
+            counterexample: (!text.is_empty()).then(|| text.join("\n")),
+        }
+    }
+}
+
+/// The last `lines` lines of `text`.
+fn tail(text: &str, lines: usize) -> String {
+    let all: Vec<&str> = text.trim_end().lines().collect();
+    all[all.len().saturating_sub(lines)..].join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `cargo kani --package cz-verify`, trimmed to two harnesses.
+    const PASSING: &str = r#"Kani Rust Verifier 0.50.0 (cargo plugin)
+   Compiling cz-core v0.1.0 (/work/crates/cz-core)
+   Compiling cz-verify v0.1.0 (/work/crates/cz-verify)
+    Finished `dev` profile [unoptimized + debuginfo] target(s) in 4.21s
+Checking harness verify_monotonicity...
+CBMC 5.95.1 (cbmc-5.95.1)
+CBMC version 5.95.1 (cbmc-5.95.1) 64-bit x86_64 linux
+Reading GOTO program from file /work/target/kani/x86_64-unknown-linux-gnu/debug/deps/cz_verify-3f1c.out
+Generating GOTO Program
+Running with 16 object bits, 48 offset bits (user-specified)
+Starting Bounded Model Checking
+Runtime Symex: 0.0213487s
+RESULTS:
+Check 1: verify_monotonicity.assertion.1
+	 - Status: SUCCESS
+	 - Description: "Sort violated monotonicity"
+	 - Location: crates/cz-verify/src/lib.rs:118:9 in function verify_monotonicity
+
+
+SUMMARY:
+ ** 0 of 1 failed
+
+VERIFICATION:- SUCCESSFUL
+Verification Time: 0.4321s
+
+Checking harness verify_hlc_observe...
+CBMC 5.95.1 (cbmc-5.95.1)
+RESULTS:
+Check 1: verify_hlc_observe.assertion.1
+	 - Status: SUCCESS
+	 - Description: "Observed timestamp not after remote"
+
+SUMMARY:
+ ** 0 of 1 failed
+
+VERIFICATION:- SUCCESSFUL
+Verification Time: 12.75s
+
+Complete - 2 successfully verified harnesses, 0 failures, 2 total.
+"#;
+
+    /// A failing harness run with `--concrete-playback=print`.
+    const FAILING: &str = r#"Checking harness verify_tail_never_passes_head...
+CBMC 5.95.1 (cbmc-5.95.1)
+RESULTS:
+Check 1: cz_io::cursor::Cursor::advance_tail.assertion.1
+	 - Status: FAILURE
+	 - Description: "Tail passed head"
+	 - Location: crates/cz-io/src/cursor.rs:88:9 in function cz_io::cursor::Cursor::advance_tail
+
+SUMMARY:
+ ** 1 of 31 failed
+Failed Checks: Tail passed head
+ File: "crates/cz-io/src/cursor.rs", line 88, in cz_io::cursor::Cursor::advance_tail
+
+VERIFICATION:- FAILED
+Concrete playback unit test for `verify_tail_never_passes_head`:
+```
+#[test]
+fn kani_concrete_playback_verify_tail_never_passes_head_1234() {
+    let concrete_vals: Vec<Vec<u8>> = vec![
+        // 3ul
+        vec![3, 0, 0, 0, 0, 0, 0, 0],
+    ];
+    kani::concrete_playback_run(concrete_vals, verify_tail_never_passes_head);
+}
+```
+INFO: To automatically add the concrete playback unit test(s) to the src code, run Kani with `--concrete-playback=inplace`.
+Verification Time: 1.08s
+
+Checking harness verify_reserve_commit...
+CBMC 5.95.1 (cbmc-5.95.1)
+Starting Bounded Model Checking
+"#;
+
+    #[test]
+    fn test_parse_passing_run() {
+        let results = parse_kani_output("cz-verify", PASSING);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].name, "verify_monotonicity");
+        assert_eq!(results[0].package, "cz-verify");
+        assert_eq!(results[0].status, HarnessStatus::Passed);
+        assert_eq!(results[0].duration_ms, Some(432));
+        assert_eq!(results[1].name, "verify_hlc_observe");
+        assert_eq!(results[1].duration_ms, Some(12_750));
+        assert!(results.iter().all(|r| r.counterexample.is_none()));
+    }
+
+    #[test]
+    fn test_parse_failure_keeps_counterexample() {
+        let results = parse_kani_output("cz-io", FAILING);
+        assert_eq!(results.len(), 2);
+
+        let failed = &results[0];
+        assert_eq!(failed.status, HarnessStatus::Failed);
+        assert_eq!(failed.duration_ms, Some(1_080));
+        let text = failed.counterexample.as_deref().unwrap();
+        assert!(text.starts_with(
+            "Failed Checks: Tail passed head\nFile: \"crates/cz-io/src/cursor.rs\", line 88"
+        ));
+        assert!(text.contains("fn kani_concrete_playback_verify_tail_never_passes_head_1234()"));
+        assert!(text.trim_end().ends_with("```"));
+        assert!(!text.contains("INFO:"));
+
+        // Cut off mid-run: started, but no verdict.
+        assert_eq!(results[1].name, "verify_reserve_commit");
+        assert_eq!(results[1].status, HarnessStatus::Unknown);
+        assert_eq!(results[1].duration_ms, None);
+    }
+
+    #[test]
+    fn test_report_serializes_per_harness() {
+        let harnesses = parse_kani_output("cz-io", FAILING);
+        let report = VerificationReport {
+            success: false,
+            started_at: Utc::now(),
+            duration_ms: 1_500,
+            harnesses,
+            errors: Vec::new(),
+        };
+        assert_eq!((report.passed(), report.failed()), (0, 2));
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["harnesses"][0]["status"], "failed");
+        assert_eq!(json["harnesses"][1]["status"], "unknown");
+        assert_eq!(
+            json["harnesses"][1]["counterexample"],
+            serde_json::Value::Null
+        );
+    }
+}
@@ -39,7 +39,7 @@ export default function App() {
 
   // Data State
   const [metrics, setMetrics] = useState({
-    events: 0, bytes: 0, tps: 0, bps: 0, utilization_pct: 0, uptime_seconds: 0, head: 0, tail: 0
+    schema: 2, events: 0, bytes: 0, tps: 0, bps: 0, utilization_pct: 0, uptime_seconds: 0, journals: {}
   });
   const [history, setHistory] = useState([]);
   const [system, setSystem] = useState(null);
@@ -204,13 +204,20 @@ export default function App() {
   // --- ACTIONS ---
   const handleVerify = async () => {
     setIsVerifying(true);
-    addToast('Starting formal verification build...', 'info');
+    addToast('Starting Kani verification...', 'info');
     try {
       const res = await fetch('/api/verify', { method: 'POST' });
       if (!res.ok) throw new Error(`HTTP ${res.status}`);
-      const data = await res.json();
-      setVerifyResults(data);
-      if (data.success) addToast('Verification PASSED', 'success');
+      let job = await res.json();
+      // Kani takes minutes; poll the job until it finishes.
+      while (job.status === 'running') {
+        await new Promise(resolve => setTimeout(resolve, 3000));
+        const poll = await fetch(`/api/verify/jobs/${job.id}`);
+        if (!poll.ok) throw new Error(`HTTP ${poll.status}`);
+        job = await poll.json();
+      }
+      setVerifyResults(job.report);
+      if (job.report.success) addToast('Verification PASSED', 'success');
       else addToast('Verification FAILED', 'error');
     } catch (e) {
       addToast('Verification process failed to start', 'error');
@@ -219,6 +226,14 @@ export default function App() {
     }
   };
 
+  // Show the last finished run, if any, when the dashboard opens.
+  useEffect(() => {
+    fetch('/api/verify/latest')
+      .then(res => (res.ok ? res.json() : null))
+      .then(report => report && setVerifyResults(report))
+      .catch(() => {});
+  }, []);
+
   const handleSimulate = async () => {
     try {
       addToast('Injecting synthetic load...', 'info');
@@ -237,8 +252,15 @@ export default function App() {
 
   // --- WEBSOCKET ---
   useEffect(() => {
-    const ws = new WebSocket(`ws://${window.location.host}/ws`);
-    ws.onopen = () => { setStatus('Connected'); addToast('Sequencer Uplink Active', 'success'); };
+    if (!apiKey) return undefined;
+    const scheme = window.location.protocol === 'https:' ? 'wss' : 'ws';
+    // Browsers cannot set headers on the handshake; the key rides along as a subprotocol.
+    const ws = new WebSocket(`${scheme}://${window.location.host}/ws`, ['cz.bearer', apiKey]);
+    ws.onopen = () => {
+      ws.send(JSON.stringify({ subscribe: ['metrics', 'alerts'] }));
+      setStatus('Connected');
+      addToast('Sequencer Uplink Active', 'success');
+    };
     ws.onclose = () => { setStatus('Disconnected'); addToast('Uplink Interrupted', 'error'); };
     ws.onmessage = (e) => {
       const msg = JSON.parse(e.data);
@@ -250,10 +272,12 @@ export default function App() {
           const next = [...prev, newPoint];
           return next.slice(-100);
         });
+      } else if (msg.type === 'alert') {
+        addToast(`${msg.data.severity.toUpperCase()}: ${msg.data.message}`, 'error');
       }
     };
     return () => ws.close();
-  }, []);
+  }, [apiKey]);
 
   // --- KEYBOARD SHORTCUTS ---
   useEffect(() => {
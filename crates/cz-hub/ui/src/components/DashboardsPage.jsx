@@ -16,10 +16,101 @@ import {
     FileText
 } from 'lucide-react';
 import _ from 'lodash';
+import { LineChart, Line, XAxis, YAxis, Tooltip, ResponsiveContainer } from 'recharts';
 
 // Wrap ResponsiveGridLayout with WidthProvider to make it responsive
 const ResponsiveGridLayout = WidthProvider(Responsive);
 
+const LINE_COLORS = ['#60a5fa', '#34d399', '#f472b6', '#fbbf24'];
+const WIDGET_REFRESH_MS = 10000;
+
+// Runs a widget's query through /api/query and keeps its result fresh.
+const useWidgetQuery = (query) => {
+    const [result, setResult] = useState(null);
+    const [error, setError] = useState(null);
+
+    useEffect(() => {
+        if (!query) return;
+        let cancelled = false;
+        const run = async () => {
+            try {
+                const res = await fetch('/api/query', {
+                    method: 'POST',
+                    headers: { 'Content-Type': 'application/json' },
+                    body: JSON.stringify({ query })
+                });
+                if (!res.ok) throw new Error(await res.text());
+                const data = await res.json();
+                if (!cancelled) { setResult(data); setError(null); }
+            } catch (e) {
+                if (!cancelled) setError(e.message);
+            }
+        };
+        run();
+        const timer = setInterval(run, WIDGET_REFRESH_MS);
+        return () => { cancelled = true; clearInterval(timer); };
+    }, [query]);
+
+    return { result, error };
+};
+
+// Aggregate queries answer with a table; plain ones are shown as one row per event.
+const toTable = (result) => {
+    if (result.table) return result.table;
+    return {
+        columns: ['timestamp', 'stream', 'payload'],
+        rows: result.events.map(e => [e.timestamp, e.stream, JSON.stringify(e.payload)]),
+    };
+};
+
+const TableWidget = ({ widget }) => {
+    const { result, error } = useWidgetQuery(widget.query);
+    if (error) return <div className="text-xs text-red-400 font-mono">{error}</div>;
+    if (!result) return <div className="text-xs text-fg-muted">Loading…</div>;
+    const table = toTable(result);
+    const shown = widget.columns?.length
+        ? table.columns.map((c, i) => [c, i]).filter(([c]) => widget.columns.includes(c))
+        : table.columns.map((c, i) => [c, i]);
+    return (
+        <table className="w-full text-left text-xs font-mono">
+            <thead className="text-fg-muted uppercase">
+                <tr>{shown.map(([c]) => <th key={c} className="px-2 py-1 border-b border-border">{c}</th>)}</tr>
+            </thead>
+            <tbody>
+                {table.rows.map((row, r) => (
+                    <tr key={r} className="border-b border-border/50">
+                        {shown.map(([c, i]) => <td key={c} className="px-2 py-1 truncate max-w-[12rem]">{String(row[i] ?? '')}</td>)}
+                    </tr>
+                ))}
+            </tbody>
+        </table>
+    );
+};
+
+// The first column is the x axis; every other column is plotted as a line.
+const TimeSeriesWidget = ({ widget }) => {
+    const { result, error } = useWidgetQuery(widget.query);
+    if (error) return <div className="text-xs text-red-400 font-mono">{error}</div>;
+    if (!result) return <div className="text-xs text-fg-muted">Loading…</div>;
+    if (!result.table) {
+        return <div className="text-xs text-fg-muted">Use an aggregate query, e.g. SELECT stream, count(*) GROUP BY stream</div>;
+    }
+    const [x, ...series] = result.table.columns;
+    const data = result.table.rows.map(row => Object.fromEntries(result.table.columns.map((c, i) => [c, row[i]])));
+    return (
+        <ResponsiveContainer width="100%" height="100%">
+            <LineChart data={data}>
+                <XAxis dataKey={x} tick={{ fontSize: 10 }} />
+                <YAxis tick={{ fontSize: 10 }} />
+                <Tooltip />
+                {series.map((c, i) => (
+                    <Line key={c} dataKey={c} stroke={LINE_COLORS[i % LINE_COLORS.length]} dot={false} isAnimationActive={false} />
+                ))}
+            </LineChart>
+        </ResponsiveContainer>
+    );
+};
+
 // Widget Component
 const WidgetItem = ({ widget, onDelete, isEditing }) => {
     return (
@@ -42,10 +133,14 @@ const WidgetItem = ({ widget, onDelete, isEditing }) => {
                 )}
             </div>
             <div className="flex-1 p-4 overflow-auto">
-                <div className="text-xs text-fg-muted font-mono">
-                    {/* Placeholder for actual visualization */}
-                    {JSON.stringify(widget, null, 2)}
-                </div>
+                {widget.type === 'table' && <TableWidget widget={widget} />}
+                {widget.type === 'time_series' && <TimeSeriesWidget widget={widget} />}
+                {(widget.type === 'value' || widget.type === 'log_stream') && (
+                    <div className="text-xs text-fg-muted font-mono">
+                        {/* Placeholder for actual visualization */}
+                        {JSON.stringify(widget, null, 2)}
+                    </div>
+                )}
             </div>
         </div>
     );
@@ -131,9 +226,9 @@ export const DashboardsPage = () => {
             type,
             title: `New ${type}`,
             // Default configs
-            ...(type === 'time_series' ? { query: 'SELECT * FROM metrics' } : {}),
+            ...(type === 'time_series' ? { query: 'SELECT stream, count(*) GROUP BY stream' } : {}),
             ...(type === 'value' ? { stream: 'cpu', field: 'usage', unit: '%' } : {}),
-            ...(type === 'table' ? { query: 'SELECT * FROM logs LIMIT 10', columns: [] } : {}),
+            ...(type === 'table' ? { query: 'SELECT stream, count(*) GROUP BY stream ORDER BY count DESC LIMIT 10', columns: [] } : {}),
             ...(type === 'log_stream' ? { stream: 'app-logs' } : {}),
         };
 
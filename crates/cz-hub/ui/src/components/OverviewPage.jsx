@@ -5,7 +5,9 @@ import { MainThroughputChart } from './Charts';
 import { RingGaugeLarge } from './Gauges';
 import { PageHeader } from './Headers';
 
-export const OverviewPage = ({ metrics, history, system, triggerSimulate }) => (
+export const OverviewPage = ({ metrics, history, system, triggerSimulate }) => {
+    const ring = Object.values(metrics.journals || {})[0] || { head: 0, tail: 0 };
+    return (
     <div className="animate-fade-in">
         <PageHeader
             title="Overview"
@@ -50,12 +52,13 @@ export const OverviewPage = ({ metrics, history, system, triggerSimulate }) => (
             </div>
             <RingGaugeLarge
                 value={metrics.utilization_pct}
-                head={metrics.head} tail={metrics.tail}
-                capacity={Math.floor(1024 * 1024 * 1024 / 32)}
+                head={ring.head} tail={ring.tail}
+                capacity={ring.capacity}
             />
         </div>
     </div>
-);
+    );
+};
 
 const fmtNum = n => {
     if (n >= 1e9) return `${(n / 1e9).toFixed(2)}B`;
@@ -87,7 +87,7 @@ export const QueryConsolePage = () => {
                         <div className="flex-1 bg-[#0A0A0A] border border-white/10 rounded-lg flex flex-col min-h-0 overflow-hidden">
                             <div className="flex-none flex items-center justify-between px-4 py-2 border-b border-white/5 bg-white/[0.02]">
                                 <div className="text-xs text-white/50 flex gap-4 font-mono">
-                                    <span>{results.total} events</span>
+                                    <span>{results.total} {results.table ? 'groups' : 'events'}</span>
                                     <span>{results.query_time_ms}ms</span>
                                     <span>Sources: {(results.streams_searched || []).join(', ') || 'all'}</span>
                                 </div>
@@ -97,6 +97,26 @@ export const QueryConsolePage = () => {
                             </div>
 
                             <div className="flex-1 overflow-auto">
+                                {results.table ? (
+                                <table className="w-full text-left border-collapse">
+                                    <thead className="bg-[#111] sticky top-0 z-10 text-xs font-semibold text-white/50 uppercase tracking-wider">
+                                        <tr>
+                                            {results.table.columns.map(c => (
+                                                <th key={c} className="px-4 py-3 border-b border-white/5">{c}</th>
+                                            ))}
+                                        </tr>
+                                    </thead>
+                                    <tbody className="divide-y divide-white/5 text-sm font-mono text-white/80">
+                                        {results.table.rows.map((row, r) => (
+                                            <tr key={r} className="hover:bg-white/[0.04]">
+                                                {row.map((cell, i) => (
+                                                    <td key={i} className="px-4 py-2">{cell === null ? '—' : String(cell)}</td>
+                                                ))}
+                                            </tr>
+                                        ))}
+                                    </tbody>
+                                </table>
+                                ) : (
                                 <table className="w-full text-left border-collapse">
                                     <thead className="bg-[#111] sticky top-0 z-10 text-xs font-semibold text-white/50 uppercase tracking-wider">
                                         <tr>
@@ -112,6 +132,7 @@ export const QueryConsolePage = () => {
                                         ))}
                                     </tbody>
                                 </table>
+                                )}
                             </div>
                         </div>
                     )}
@@ -1,5 +1,5 @@
 import React from 'react';
-import { ShieldCheck, Play, History, CheckCircle2, Loader2, FileCode } from 'lucide-react';
+import { ShieldCheck, Play, History, CheckCircle2, XCircle, AlertTriangle, Loader2, FileCode } from 'lucide-react';
 import { PageHeader } from './Headers';
 
 export const VerifyPage = ({ onRunTests, results, running }) => (
@@ -7,7 +7,7 @@ export const VerifyPage = ({ onRunTests, results, running }) => (
         <div className="col-span-8 flex flex-col gap-4 overflow-hidden">
             <PageHeader
                 title="Verification"
-                subtitle="Kani proofs and workspace unit tests"
+                subtitle="Kani proofs, one result per harness"
                 actions={
                     <button
                         disabled={running}
@@ -15,7 +15,7 @@ export const VerifyPage = ({ onRunTests, results, running }) => (
                         className="flex items-center gap-1.5 px-3 py-1.5 rounded-md bg-green/90 hover:bg-green transition-colors text-[13px] font-medium text-bg disabled:opacity-40 cursor-pointer"
                     >
                         {running ? <Loader2 size={14} className="animate-spin" /> : <Play size={14} />}
-                        {running ? 'Running…' : 'Run Tests'}
+                        {running ? 'Running…' : 'Run Proofs'}
                     </button>
                 }
             />
@@ -48,7 +48,20 @@ export const VerifyPage = ({ onRunTests, results, running }) => (
                             <p>Validating zero-copy alignment…</p>
                         </div>
                     ) : (
-                        <pre className="whitespace-pre-wrap">{results.output}</pre>
+                        <div className="space-y-3">
+                            <p>
+                                {results.harnesses.filter(h => h.status === 'passed').length} of {results.harnesses.length} harnesses passed in {(results.duration_ms / 1000).toFixed(1)}s
+                            </p>
+                            {results.errors.map((error, i) => (
+                                <pre key={i} className="whitespace-pre-wrap text-red">{error}</pre>
+                            ))}
+                            {results.harnesses.filter(h => h.counterexample).map(h => (
+                                <div key={`${h.package}::${h.name}`}>
+                                    <p className="text-red">{h.package}::{h.name}</p>
+                                    <pre className="whitespace-pre-wrap">{h.counterexample}</pre>
+                                </div>
+                            ))}
+                        </div>
                     )}
                 </div>
             </div>
@@ -57,20 +70,30 @@ export const VerifyPage = ({ onRunTests, results, running }) => (
         <div className="col-span-4 flex flex-col gap-4 overflow-hidden">
             <div className="flex items-center gap-2 pt-0.5">
                 <History size={14} strokeWidth={1.6} className="text-fg-subtle" />
-                <h3 className="text-[12px] font-semibold text-fg-subtle uppercase tracking-wide">History</h3>
+                <h3 className="text-[12px] font-semibold text-fg-subtle uppercase tracking-wide">Harnesses</h3>
             </div>
 
             <div className="flex-1 overflow-y-auto space-y-2">
-                {[1, 2, 3].map(i => (
-                    <div key={i} className="bg-bg-elevated border border-border rounded-lg p-3 flex items-center gap-3 opacity-50 hover:opacity-100 transition-opacity">
-                        <CheckCircle2 size={14} className="text-green shrink-0" />
+                {(results?.harnesses ?? []).map(h => (
+                    <div key={`${h.package}::${h.name}`} className="bg-bg-elevated border border-border rounded-lg p-3 flex items-center gap-3">
+                        {h.status === 'passed' ? (
+                            <CheckCircle2 size={14} className="text-green shrink-0" />
+                        ) : h.status === 'failed' ? (
+                            <XCircle size={14} className="text-red shrink-0" />
+                        ) : (
+                            <AlertTriangle size={14} className="text-fg-subtle shrink-0" />
+                        )}
                         <div className="flex-1 min-w-0">
-                            <div className="text-[11px] font-medium text-fg">Test Group #A{i}2</div>
-                            <div className="text-[11px] font-mono text-fg-subtle">PASSED · 42ms</div>
+                            <div className="text-[11px] font-medium text-fg truncate">{h.name}</div>
+                            <div className="text-[11px] font-mono text-fg-subtle">
+                                {h.package} · {h.status.toUpperCase()}{h.duration_ms != null && ` · ${h.duration_ms}ms`}
+                            </div>
                         </div>
                     </div>
                 ))}
-                <p className="text-center py-3 text-[11px] text-fg-faint">End of session</p>
+                {results && results.harnesses.length === 0 && (
+                    <p className="text-center py-3 text-[11px] text-fg-faint">No harness results</p>
+                )}
             </div>
         </div>
     </div>
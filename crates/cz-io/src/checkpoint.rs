@@ -0,0 +1,431 @@
+//! # Checkpoints — Self-Describing Recovery Markers
+//!
+//! The sequencer periodically writes a checkpoint into the Index Ring: a
+//! `CausalEvent` with `FLAG_CHECKPOINT` set whose payload records the cursor
+//! position, the Lamport watermark, and a CRC32 over the ring slots written
+//! since the previous checkpoint.
+//!
+//! On restart the newest checkpoint whose payload checksum and segment CRC
+//! both validate is restored: the one whose slot the journal header
+//! records, or the first valid one scanning back from there. Everything up
+//! to that point is trusted.
+//!
+//! # Blob Record Layout
+//!
+//! Checkpoints are stored like received packets: `payload_offset` points at
+//! a copy of the 32-byte event header, followed by the payload below.
+//!
+//! | Offset | Size | Field               |
+//! |--------|------|---------------------|
+//! | 0      | 8    | `head`              |
+//! | 8      | 8    | `tail`              |
+//! | 16     | 8    | `lamport_watermark` |
+//! | 24     | 8    | `segment_start`     |
+//! | 32     | 4    | `segment_crc`       |
+//! | 36     | 4    | magic (`CZCP`)      |
+
+use std::time::{Duration, Instant};
+
+use crc32fast::Hasher;
+
+use cz_core::{CausalEvent, FLAG_CHECKPOINT};
+
+/// Size of the encoded checkpoint payload in bytes.
+pub const CHECKPOINT_PAYLOAD_SIZE: usize = 40;
+
+/// Size of a full checkpoint record in blob storage (header + payload).
+pub const CHECKPOINT_RECORD_SIZE: usize = CausalEvent::size_bytes() + CHECKPOINT_PAYLOAD_SIZE;
+
+/// Node ID the sequencer stamps on events it writes itself.
+pub const SEQUENCER_NODE_ID: u32 = u32::MAX;
+
+const CHECKPOINT_MAGIC: [u8; 4] = *b"CZCP";
+
+/// State captured by a checkpoint event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint {
+    /// Ring head immediately after the checkpoint slot was claimed.
+    pub head: u64,
+    /// Ring tail at checkpoint time.
+    pub tail: u64,
+    /// Next Lamport timestamp the sequencer will hand out.
+    pub lamport_watermark: u64,
+    /// First slot covered by `segment_crc` (the slot after the previous checkpoint).
+    pub segment_start: u64,
+    /// CRC32 over the raw ring bytes in `[segment_start, checkpoint_slot)`.
+    pub segment_crc: u32,
+}
+
+impl Checkpoint {
+    /// Encode the checkpoint payload (little-endian).
+    pub fn encode(&self) -> [u8; CHECKPOINT_PAYLOAD_SIZE] {
+        let mut buf = [0u8; CHECKPOINT_PAYLOAD_SIZE];
+        buf[0..8].copy_from_slice(&self.head.to_le_bytes());
+        buf[8..16].copy_from_slice(&self.tail.to_le_bytes());
+        buf[16..24].copy_from_slice(&self.lamport_watermark.to_le_bytes());
+        buf[24..32].copy_from_slice(&self.segment_start.to_le_bytes());
+        buf[32..36].copy_from_slice(&self.segment_crc.to_le_bytes());
+        buf[36..40].copy_from_slice(&CHECKPOINT_MAGIC);
+        buf
+    }
+
+    /// Decode a checkpoint payload. Returns `None` if the buffer is too
+    /// short or the magic does not match.
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < CHECKPOINT_PAYLOAD_SIZE || bytes[36..40] != CHECKPOINT_MAGIC {
+            return None;
+        }
+        let u64_at = |at: usize| u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap());
+        Some(Self {
+            head: u64_at(0),
+            tail: u64_at(8),
+            lamport_watermark: u64_at(16),
+            segment_start: u64_at(24),
+            segment_crc: u32::from_le_bytes(bytes[32..36].try_into().unwrap()),
+        })
+    }
+}
+
+/// When the sequencer should emit a checkpoint.
+#[derive(Debug, Clone, Copy)]
+pub struct CheckpointPolicy {
+    /// Emit after this many sequenced events (0 disables the count trigger).
+    pub every_events: u64,
+    /// Emit after this much time has passed (only if events arrived since
+    /// the last checkpoint — an idle ring is already covered).
+    pub every: Duration,
+}
+
+impl Default for CheckpointPolicy {
+    fn default() -> Self {
+        Self {
+            every_events: 10_000,
+            every: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Tracks progress since the last checkpoint and decides when the next is due.
+pub struct CheckpointTracker {
+    policy: CheckpointPolicy,
+    events_since: u64,
+    last_at: Instant,
+    segment_start: usize,
+}
+
+impl CheckpointTracker {
+    /// Start tracking with the segment beginning at `segment_start`.
+    pub fn new(policy: CheckpointPolicy, segment_start: usize, now: Instant) -> Self {
+        Self {
+            policy,
+            events_since: 0,
+            last_at: now,
+            segment_start,
+        }
+    }
+
+    /// Record one sequenced event.
+    #[inline]
+    pub fn record_event(&mut self) {
+        self.events_since += 1;
+    }
+
+    /// Returns `true` if a checkpoint should be written now.
+    pub fn is_due(&self, now: Instant) -> bool {
+        if self.events_since == 0 {
+            return false;
+        }
+        if self.policy.every_events > 0 && self.events_since >= self.policy.every_events {
+            return true;
+        }
+        now.duration_since(self.last_at) >= self.policy.every
+    }
+
+    /// Reset after a checkpoint was written. `next_segment_start` is the
+    /// ring head after the checkpoint slot.
+    pub fn mark(&mut self, next_segment_start: usize, now: Instant) {
+        self.events_since = 0;
+        self.last_at = now;
+        self.segment_start = next_segment_start;
+    }
+
+    /// First slot of the current (not yet checkpointed) segment.
+    #[inline]
+    pub fn segment_start(&self) -> usize {
+        self.segment_start
+    }
+}
+
+/// CRC32 over the raw ring bytes for slots `[from, to)`, wrapping at the
+/// end of the ring.
+pub fn segment_crc(ring: &[u8], from: usize, to: usize) -> u32 {
+    let slot_size = CausalEvent::size_bytes();
+    let capacity = ring.len() / slot_size;
+    let mut hasher = Hasher::new();
+    if capacity == 0 {
+        return hasher.finalize();
+    }
+
+    let mut slot = from % capacity;
+    let end = to % capacity;
+    while slot != end {
+        hasher.update(&ring[slot * slot_size..(slot + 1) * slot_size]);
+        slot = (slot + 1) % capacity;
+    }
+    hasher.finalize()
+}
+
+/// Build the checkpoint event and its blob record.
+///
+/// The record must be written to blob storage at `blob_offset`, and the
+/// returned event into the claimed ring slot.
+pub fn encode_event(
+    node_id: u32,
+    lamport_ts: u64,
+    blob_offset: u64,
+    checkpoint: &Checkpoint,
+) -> (CausalEvent, [u8; CHECKPOINT_RECORD_SIZE]) {
+    let payload = checkpoint.encode();
+    let mut hasher = Hasher::new();
+    hasher.update(&payload);
+
+    let event = CausalEvent::with_flags(
+        lamport_ts,
+        node_id,
+        0,
+        blob_offset,
+        hasher.finalize(),
+        FLAG_CHECKPOINT,
+    );
+
+    let header_len = CausalEvent::size_bytes();
+    let mut record = [0u8; CHECKPOINT_RECORD_SIZE];
+    record[..header_len].copy_from_slice(event_bytes(&event));
+    record[header_len..].copy_from_slice(&payload);
+    (event, record)
+}
+
+/// Read the checkpoint payload referenced by `event` from blob storage.
+///
+/// Returns `None` if the event is not a checkpoint, the record is out of
+/// bounds, the payload checksum does not match, or the magic is wrong.
+pub fn read(blob: &[u8], event: &CausalEvent) -> Option<Checkpoint> {
+    if !event.is_checkpoint() {
+        return None;
+    }
+    let start = (event.payload_offset as usize).checked_add(CausalEvent::size_bytes())?;
+    let payload = blob.get(start..start.checked_add(CHECKPOINT_PAYLOAD_SIZE)?)?;
+
+    let mut hasher = Hasher::new();
+    hasher.update(payload);
+    if hasher.finalize() != event.checksum {
+        return None;
+    }
+    Checkpoint::decode(payload)
+}
+
+/// Fully validate the checkpoint stored at `slot`: the payload must decode
+/// and the ring segment it covers must still match its CRC.
+pub fn verify(ring: &[u8], blob: &[u8], slot: usize, event: &CausalEvent) -> Option<Checkpoint> {
+    let checkpoint = read(blob, event)?;
+    let capacity = ring.len() / CausalEvent::size_bytes();
+    if capacity == 0 || checkpoint.head as usize != (slot + 1) % capacity {
+        return None;
+    }
+    if segment_crc(ring, checkpoint.segment_start as usize, slot) != checkpoint.segment_crc {
+        return None;
+    }
+    Some(checkpoint)
+}
+
+/// Scan the ring backwards from `newest`, wrapping, for the first valid
+/// checkpoint. Slots are written in order, so it is the newest one at or
+/// before `newest`; the scan stops there rather than reading the whole
+/// ring.
+///
+/// Returns the slot it lives in together with the decoded checkpoint.
+pub fn find_last_valid(ring: &[u8], blob: &[u8], newest: usize) -> Option<(usize, Checkpoint)> {
+    let slot_size = CausalEvent::size_bytes();
+    let capacity = ring.len() / slot_size;
+    (0..capacity)
+        .map(|back| (newest % capacity + capacity - back) % capacity)
+        .find_map(|slot| {
+            let bytes = &ring[slot * slot_size..][..slot_size];
+            // SAFETY: `bytes` is exactly one `CausalEvent` wide; every bit
+            // pattern is a valid `CausalEvent`.
+            let event = unsafe { std::ptr::read_unaligned(bytes.as_ptr() as *const CausalEvent) };
+            if !event.is_checkpoint() {
+                return None;
+            }
+            verify(ring, blob, slot, &event).map(|checkpoint| (slot, checkpoint))
+        })
+}
+
+fn event_bytes(event: &CausalEvent) -> &[u8] {
+    // SAFETY: `CausalEvent` is `#[repr(C)]` plain data.
+    unsafe {
+        std::slice::from_raw_parts(
+            event as *const CausalEvent as *const u8,
+            CausalEvent::size_bytes(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SLOT: usize = 32;
+
+    fn put_event(ring: &mut [u8], slot: usize, event: &CausalEvent) {
+        ring[slot * SLOT..(slot + 1) * SLOT].copy_from_slice(event_bytes(event));
+    }
+
+    /// Write a checkpoint into `slot` covering `[segment_start, slot)`.
+    fn put_checkpoint(
+        ring: &mut [u8],
+        blob: &mut [u8],
+        slot: usize,
+        segment_start: usize,
+        lamport_ts: u64,
+        blob_offset: usize,
+    ) -> Checkpoint {
+        let capacity = ring.len() / SLOT;
+        let checkpoint = Checkpoint {
+            head: ((slot + 1) % capacity) as u64,
+            tail: 0,
+            lamport_watermark: lamport_ts + 1,
+            segment_start: segment_start as u64,
+            segment_crc: segment_crc(ring, segment_start, slot),
+        };
+        let (event, record) = encode_event(
+            SEQUENCER_NODE_ID,
+            lamport_ts,
+            blob_offset as u64,
+            &checkpoint,
+        );
+        blob[blob_offset..blob_offset + CHECKPOINT_RECORD_SIZE].copy_from_slice(&record);
+        put_event(ring, slot, &event);
+        checkpoint
+    }
+
+    #[test]
+    fn test_payload_round_trip() {
+        let cp = Checkpoint {
+            head: 7,
+            tail: 2,
+            lamport_watermark: 99,
+            segment_start: 3,
+            segment_crc: 0xDEAD_BEEF,
+        };
+        assert_eq!(Checkpoint::decode(&cp.encode()), Some(cp));
+        assert_eq!(Checkpoint::decode(&[0u8; CHECKPOINT_PAYLOAD_SIZE]), None);
+    }
+
+    #[test]
+    fn test_cadence_by_event_count() {
+        let now = Instant::now();
+        let policy = CheckpointPolicy {
+            every_events: 3,
+            every: Duration::from_secs(3600),
+        };
+        let mut tracker = CheckpointTracker::new(policy, 0, now);
+
+        tracker.record_event();
+        tracker.record_event();
+        assert!(!tracker.is_due(now));
+        tracker.record_event();
+        assert!(tracker.is_due(now));
+
+        tracker.mark(4, now);
+        assert!(!tracker.is_due(now));
+        assert_eq!(tracker.segment_start(), 4);
+    }
+
+    #[test]
+    fn test_cadence_by_time_requires_activity() {
+        let start = Instant::now();
+        let policy = CheckpointPolicy {
+            every_events: 0,
+            every: Duration::from_secs(5),
+        };
+        let mut tracker = CheckpointTracker::new(policy, 0, start);
+        let later = start + Duration::from_secs(6);
+
+        // Idle ring: nothing to checkpoint.
+        assert!(!tracker.is_due(later));
+
+        tracker.record_event();
+        assert!(!tracker.is_due(start + Duration::from_secs(1)));
+        assert!(tracker.is_due(later));
+    }
+
+    #[test]
+    fn test_recovers_last_valid_checkpoint() {
+        let mut ring = vec![0u8; 16 * SLOT];
+        let mut blob = vec![0u8; 4096];
+
+        for slot in 0..3 {
+            put_event(&mut ring, slot, &CausalEvent::new(slot as u64, 1, 0, 0, 0));
+        }
+        put_checkpoint(&mut ring, &mut blob, 3, 0, 3, 0);
+        for slot in 4..6 {
+            put_event(&mut ring, slot, &CausalEvent::new(slot as u64, 1, 0, 0, 0));
+        }
+        let second = put_checkpoint(&mut ring, &mut blob, 6, 4, 6, 128);
+
+        let (slot, cp) = find_last_valid(&ring, &blob, 9).unwrap();
+        assert_eq!(slot, 6);
+        assert_eq!(cp, second);
+        assert_eq!(cp.head, 7);
+        assert_eq!(cp.lamport_watermark, 7);
+        // The scan only looks back from where it starts.
+        assert_eq!(find_last_valid(&ring, &blob, 5).unwrap().0, 3);
+    }
+
+    #[test]
+    fn test_corrupt_segment_falls_back_to_previous_checkpoint() {
+        let mut ring = vec![0u8; 16 * SLOT];
+        let mut blob = vec![0u8; 4096];
+
+        put_event(&mut ring, 0, &CausalEvent::new(1, 1, 0, 0, 0));
+        let first = put_checkpoint(&mut ring, &mut blob, 1, 0, 2, 0);
+        put_event(&mut ring, 2, &CausalEvent::new(3, 1, 0, 0, 0));
+        put_checkpoint(&mut ring, &mut blob, 3, 2, 4, 128);
+
+        // Tear a slot covered by the second checkpoint.
+        ring[2 * SLOT] ^= 0xFF;
+
+        let (slot, cp) = find_last_valid(&ring, &blob, 3).unwrap();
+        assert_eq!(slot, 1);
+        assert_eq!(cp, first);
+    }
+
+    #[test]
+    fn test_corrupt_payload_is_rejected() {
+        let mut ring = vec![0u8; 8 * SLOT];
+        let mut blob = vec![0u8; 1024];
+        put_checkpoint(&mut ring, &mut blob, 0, 0, 1, 0);
+
+        blob[CausalEvent::size_bytes()] ^= 0xFF;
+        assert!(find_last_valid(&ring, &blob, 0).is_none());
+    }
+
+    #[test]
+    fn test_segment_crc_wraps() {
+        let mut ring = vec![0u8; 4 * SLOT];
+        for slot in 0..4 {
+            put_event(
+                &mut ring,
+                slot,
+                &CausalEvent::new(slot as u64 + 1, 1, 0, 0, 0),
+            );
+        }
+
+        let mut hasher = Hasher::new();
+        hasher.update(&ring[3 * SLOT..]);
+        hasher.update(&ring[..SLOT]);
+        assert_eq!(segment_crc(&ring, 3, 1), hasher.finalize());
+        assert_eq!(segment_crc(&ring, 2, 2), Hasher::new().finalize());
+    }
+}
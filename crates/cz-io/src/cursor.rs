@@ -6,6 +6,10 @@
 //!
 //! This invariant is formally verified with Kani in `cz-verify`.
 
+use std::ops::Deref;
+
+use crate::checkpoint::Checkpoint;
+
 /// Ring buffer cursor tracking write (head) and commit (tail) positions.
 ///
 /// The ring has `capacity` slots, each holding one `CausalEvent`.
@@ -16,6 +20,13 @@
 /// `head` can never advance to equal `tail` (that would mean the buffer
 /// wrapped around and overwrote uncommitted data). The ring has
 /// `capacity - 1` usable slots to maintain this invariant.
+///
+/// # Sub-rings
+///
+/// A cursor may track one partition of a larger Index Ring: the slots
+/// `[base, base + capacity)`. `head` and `tail` stay positions within the
+/// sub-ring, while every method that takes or returns a *slot* works in
+/// Index Ring slots, so they can be read and written directly.
 pub struct Cursor {
     /// Current write position (next slot to write into).
     head: usize,
@@ -25,6 +36,9 @@ pub struct Cursor {
 
     /// Total number of slots in the ring.
     capacity: usize,
+
+    /// Index Ring slot of position 0.
+    base: usize,
 }
 
 impl Cursor {
@@ -38,13 +52,37 @@ impl Cursor {
             head: 0,
             tail: 0,
             capacity,
+            base: 0,
+        }
+    }
+
+    /// Restore a cursor from positions recorded by a checkpoint.
+    ///
+    /// # Panics
+    /// Panics if `capacity < 2` or either position is out of range.
+    pub fn restore(capacity: usize, head: usize, tail: usize) -> Self {
+        assert!(capacity >= 2, "Ring buffer must have at least 2 slots");
+        assert!(
+            head < capacity && tail < capacity,
+            "Cursor positions out of range"
+        );
+        Self {
+            head,
+            tail,
+            capacity,
+            base: 0,
         }
     }
 
-    /// Create a cursor sized to fill the Index Ring region.
-    pub fn for_index_ring() -> Self {
-        let capacity = super::journal::INDEX_RING_CAPACITY;
-        Self::new(capacity)
+    /// Place the ring at slots `[base, base + capacity)` of the Index Ring.
+    pub fn with_base(self, base: usize) -> Self {
+        Self { base, ..self }
+    }
+
+    /// Restore the cursor of an Index Ring with `capacity` slots from a
+    /// checkpoint.
+    pub fn from_checkpoint(checkpoint: &Checkpoint, capacity: usize) -> Self {
+        Self::restore(capacity, checkpoint.head as usize, checkpoint.tail as usize)
     }
 
     /// Returns `true` if the ring buffer is full.
@@ -88,6 +126,91 @@ impl Cursor {
         self.capacity
     }
 
+    /// Share of the slots holding events, as a percentage.
+    #[inline]
+    pub fn utilization_pct(&self) -> f64 {
+        self.len() as f64 / self.capacity as f64 * 100.0
+    }
+
+    /// Index Ring slot of position 0: nonzero only for a sub-ring.
+    #[inline]
+    pub fn base(&self) -> usize {
+        self.base
+    }
+
+    /// Slot holding the live event at `logical_index`, counting from
+    /// `tail`, or `None` if fewer than `logical_index + 1` events are live.
+    #[inline]
+    pub fn slot_at(&self, logical_index: usize) -> Option<usize> {
+        if logical_index >= self.len() {
+            return None;
+        }
+        Some(self.base + (self.tail + logical_index) % self.capacity)
+    }
+
+    /// Slot of the newest live event, just behind `head`, or `None` if the
+    /// ring is empty.
+    #[inline]
+    pub fn peek_head_slot(&self) -> Option<usize> {
+        self.slot_at(self.len().checked_sub(1)?)
+    }
+
+    /// Slot of the oldest live event, at `tail`, or `None` if the ring is
+    /// empty.
+    #[inline]
+    pub fn peek_tail_slot(&self) -> Option<usize> {
+        self.slot_at(0)
+    }
+
+    /// Inverse of [`slot_at`](Self::slot_at): how far `slot`
+    /// sits from `tail`, or `None` if it is not live.
+    #[inline]
+    pub fn logical_index(&self, slot: usize) -> Option<usize> {
+        if !self.contains_slot(slot) {
+            return None;
+        }
+        Some((slot - self.base + self.capacity - self.tail) % self.capacity)
+    }
+
+    /// Live slots from `tail` (oldest) up to but excluding `head`.
+    pub fn slots(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.len()).map(move |i| self.base + (self.tail + i) % self.capacity)
+    }
+
+    /// Returns `true` if `slot` lies in the live `[tail, head)` window.
+    ///
+    /// Slots outside the window may still hold bytes from an earlier lap
+    /// of the ring, but those events are no longer valid.
+    #[inline]
+    pub fn contains_slot(&self, slot: usize) -> bool {
+        let Some(pos) = slot.checked_sub(self.base) else {
+            return false;
+        };
+        pos < self.capacity && (pos + self.capacity - self.tail) % self.capacity < self.len()
+    }
+
+    /// Returns how many more events fit before the ring is full.
+    #[inline]
+    pub fn available(&self) -> usize {
+        self.capacity - 1 - self.len()
+    }
+
+    /// Reserve `n` consecutive slots starting at `head` for a batch write.
+    ///
+    /// `head` does not move until [`Reservation::commit`] is called, so a
+    /// reader never observes a partially written batch. Returns `None` if
+    /// fewer than `n` slots are free.
+    pub fn reserve(&mut self, n: usize) -> Option<Reservation<'_>> {
+        if n > self.available() {
+            return None;
+        }
+        Some(Reservation {
+            start: self.head,
+            len: n,
+            cursor: self,
+        })
+    }
+
     /// Advance the head pointer by one slot.
     ///
     /// Returns the slot index that was claimed for writing,
@@ -97,9 +220,9 @@ impl Cursor {
         if self.is_full() {
             return None;
         }
-        let slot = self.head;
+        let pos = self.head;
         self.head = self.next_pos(self.head);
-        Some(slot)
+        Some(self.base + pos)
     }
 
     /// Advance the tail pointer by one slot (mark oldest event as consumed).
@@ -111,9 +234,9 @@ impl Cursor {
         if self.is_empty() {
             return None;
         }
-        let slot = self.tail;
+        let pos = self.tail;
         self.tail = self.next_pos(self.tail);
-        Some(slot)
+        Some(self.base + pos)
     }
 
     /// Compute the next position with wrap-around.
@@ -123,6 +246,265 @@ impl Cursor {
     }
 }
 
+// =============================================================================
+// Reservation: Batch Slot Claims
+// =============================================================================
+
+/// A run of slots claimed by [`Cursor::reserve`].
+///
+/// Slots are in logical order and may wrap past the end of the ring.
+/// Dropping the reservation without committing is equivalent to
+/// [`abort`](Self::abort).
+pub struct Reservation<'a> {
+    cursor: &'a mut Cursor,
+    start: usize,
+    len: usize,
+}
+
+impl Reservation<'_> {
+    /// Number of reserved slots.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if no slots were reserved.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Ring slot of the `i`-th reserved event.
+    ///
+    /// # Panics
+    /// Panics if `i >= len()`.
+    #[inline]
+    pub fn slot(&self, i: usize) -> usize {
+        assert!(i < self.len, "Reservation index out of range");
+        self.cursor.base + (self.start + i) % self.cursor.capacity
+    }
+
+    /// Reserved ring slots in write order.
+    pub fn slots(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.len).map(|i| self.slot(i))
+    }
+
+    /// Publish the batch by moving `head` past every reserved slot.
+    ///
+    /// Returns the new head position.
+    pub fn commit(self) -> usize {
+        self.cursor.head = (self.start + self.len) % self.cursor.capacity;
+        self.cursor.head
+    }
+
+    /// Release the reservation, leaving the cursor untouched.
+    pub fn abort(self) {}
+}
+
+// =============================================================================
+// MultiCursor: Named Consumer Offsets
+// =============================================================================
+
+/// Read position of a named consumer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsumerOffset {
+    pub name: String,
+    /// Next slot this consumer will read.
+    pub position: usize,
+}
+
+/// A [`Cursor`] shared by several independent readers.
+///
+/// Each registered consumer keeps its own read position. The underlying
+/// cursor's tail is the position of the consumer furthest behind `head`,
+/// so the ring never reclaims a slot that any consumer has yet to read.
+/// With no consumers registered it behaves exactly like a plain `Cursor`.
+///
+/// Read-only cursor accessors are available through `Deref`; all mutation
+/// goes through this type so the tail cannot skip past a consumer.
+pub struct MultiCursor {
+    cursor: Cursor,
+    consumers: Vec<ConsumerOffset>,
+}
+
+impl MultiCursor {
+    /// Wrap a cursor with no consumers registered.
+    pub fn new(cursor: Cursor) -> Self {
+        Self {
+            cursor,
+            consumers: Vec::new(),
+        }
+    }
+
+    /// Wrap a cursor and restore previously persisted consumer positions.
+    ///
+    /// Positions outside the live `[tail, head]` range are clamped to the tail.
+    pub fn with_consumers(cursor: Cursor, consumers: Vec<(String, usize)>) -> Self {
+        let mut multi = Self::new(cursor);
+        for (name, position) in consumers {
+            let position = if multi.in_live_range(position) {
+                position
+            } else {
+                multi.cursor.tail()
+            };
+            multi.consumers.push(ConsumerOffset { name, position });
+        }
+        multi
+    }
+
+    /// Replace the consumers with positions another process persisted,
+    /// clamped like [`with_consumers`](Self::with_consumers). The tail is
+    /// left where it is.
+    pub fn set_consumers(&mut self, consumers: Vec<(String, usize)>) {
+        let tail = self.cursor.tail();
+        self.consumers = consumers
+            .into_iter()
+            .map(|(name, position)| ConsumerOffset {
+                position: if self.in_live_range(position) {
+                    position
+                } else {
+                    tail
+                },
+                name,
+            })
+            .collect();
+    }
+
+    /// Drop the oldest events until at most `max_len` are left, stopping
+    /// at the first slot a consumer has yet to read. Returns how many were
+    /// dropped.
+    pub fn trim_to(&mut self, max_len: usize) -> usize {
+        let mut dropped = 0;
+        while self.cursor.len() > max_len
+            && !self
+                .consumers
+                .iter()
+                .any(|c| c.position == self.cursor.tail())
+        {
+            self.cursor.advance_tail();
+            dropped += 1;
+        }
+        dropped
+    }
+
+    /// Register a consumer starting at the current tail.
+    ///
+    /// Returns `false` if a consumer with this name already exists.
+    pub fn register_consumer(&mut self, name: &str) -> bool {
+        if self.consumer(name).is_some() {
+            return false;
+        }
+        self.consumers.push(ConsumerOffset {
+            name: name.to_string(),
+            position: self.cursor.tail(),
+        });
+        true
+    }
+
+    /// Remove a consumer, releasing any slots only it was holding.
+    ///
+    /// Returns `false` if the consumer does not exist.
+    pub fn unregister_consumer(&mut self, name: &str) -> bool {
+        let Some(idx) = self.consumers.iter().position(|c| c.name == name) else {
+            return false;
+        };
+        self.consumers.remove(idx);
+        self.sync_tail();
+        true
+    }
+
+    /// Advance a consumer by one slot.
+    ///
+    /// Returns the slot it consumed, or `None` if the consumer is unknown
+    /// or has already caught up with `head`.
+    pub fn advance_consumer(&mut self, name: &str) -> Option<usize> {
+        let head = self.cursor.head();
+        let capacity = self.cursor.capacity();
+        let consumer = self.consumers.iter_mut().find(|c| c.name == name)?;
+        if consumer.position == head {
+            return None;
+        }
+        let position = consumer.position;
+        consumer.position = (position + 1) % capacity;
+        self.sync_tail();
+        Some(self.cursor.base() + position)
+    }
+
+    /// Number of events between a consumer's position and `head`.
+    pub fn consumer_lag(&self, name: &str) -> Option<usize> {
+        self.consumer(name).map(|c| self.lag(c.position))
+    }
+
+    /// All registered consumers.
+    pub fn consumers(&self) -> &[ConsumerOffset] {
+        &self.consumers
+    }
+
+    /// Consumer positions in the form persisted by the journal header.
+    pub fn consumer_offsets(&self) -> Vec<(String, usize)> {
+        self.consumers
+            .iter()
+            .map(|c| (c.name.clone(), c.position))
+            .collect()
+    }
+
+    /// Reserve slots for a batch write. See [`Cursor::reserve`].
+    #[inline]
+    pub fn reserve(&mut self, n: usize) -> Option<Reservation<'_>> {
+        self.cursor.reserve(n)
+    }
+
+    /// Advance the head pointer by one slot. See [`Cursor::advance_head`].
+    #[inline]
+    pub fn advance_head(&mut self) -> Option<usize> {
+        self.cursor.advance_head()
+    }
+
+    /// Advance the tail directly.
+    ///
+    /// Only permitted when no consumers are registered; otherwise the tail
+    /// is driven by [`advance_consumer`](Self::advance_consumer) and this
+    /// returns `None`.
+    pub fn advance_tail(&mut self) -> Option<usize> {
+        if !self.consumers.is_empty() {
+            return None;
+        }
+        self.cursor.advance_tail()
+    }
+
+    fn consumer(&self, name: &str) -> Option<&ConsumerOffset> {
+        self.consumers.iter().find(|c| c.name == name)
+    }
+
+    fn lag(&self, position: usize) -> usize {
+        let capacity = self.cursor.capacity();
+        (self.cursor.head() + capacity - position) % capacity
+    }
+
+    fn in_live_range(&self, position: usize) -> bool {
+        position < self.cursor.capacity() && self.lag(position) <= self.cursor.len()
+    }
+
+    /// Move the underlying tail up to the slowest consumer.
+    fn sync_tail(&mut self) {
+        let target = match self.consumers.iter().map(|c| self.lag(c.position)).max() {
+            Some(max_lag) => max_lag,
+            None => return,
+        };
+        while self.cursor.len() > target {
+            self.cursor.advance_tail();
+        }
+    }
+}
+
+impl Deref for MultiCursor {
+    type Target = Cursor;
+
+    fn deref(&self) -> &Cursor {
+        &self.cursor
+    }
+}
+
 // =============================================================================
 // Kani Proofs: Ring Buffer Invariants
 // =============================================================================
@@ -177,11 +559,161 @@ mod proofs {
 
         assert!(cursor.len() <= cursor.capacity());
     }
+
+    /// **Proof: Tail never passes head, and `len` counts advances exactly**
+    ///
+    /// Over any interleaving of up to 6 head/tail advances on a 4-slot
+    /// ring, `len()` equals successful head advances minus successful tail
+    /// advances, a tail advance fails only on an empty ring, and full and
+    /// empty are never reported together (a ring has at least 2 slots).
+    #[kani::proof]
+    #[kani::unwind(7)]
+    fn verify_tail_never_passes_head() {
+        let mut cursor = Cursor::new(4);
+        let mut head_advances = 0usize;
+        let mut tail_advances = 0usize;
+
+        for _ in 0..6 {
+            if kani::any() {
+                if cursor.advance_head().is_some() {
+                    head_advances += 1;
+                }
+            } else {
+                let was_empty = cursor.is_empty();
+                match cursor.advance_tail() {
+                    Some(_) => tail_advances += 1,
+                    None => assert!(was_empty, "tail refused to advance over live events"),
+                }
+            }
+
+            assert!(tail_advances <= head_advances, "tail passed head");
+            assert!(cursor.len() == head_advances - tail_advances);
+            assert!(!(cursor.is_full() && cursor.is_empty()));
+        }
+    }
+
+    /// **Proof: `slot_at` addresses exactly the live window**
+    ///
+    /// Over any interleaving of up to 6 head/tail advances on a 4-slot
+    /// ring, `slot_at(len() - 1)` is the slot the last head advance
+    /// returned, the slots below `len()` are distinct and inside the ring,
+    /// and `slot_at(len())` is `None`.
+    #[kani::proof]
+    #[kani::unwind(7)]
+    fn verify_slot_at_consistent_with_advances() {
+        let mut cursor = Cursor::new(4);
+        let mut last_written = None;
+
+        for _ in 0..6 {
+            if kani::any() {
+                if let Some(slot) = cursor.advance_head() {
+                    last_written = Some(slot);
+                }
+            } else {
+                let _ = cursor.advance_tail();
+            }
+
+            let len = cursor.len();
+            assert!(cursor.slot_at(len).is_none());
+            if len == 0 {
+                continue;
+            }
+            assert!(cursor.slot_at(len - 1) == last_written);
+            assert!(cursor.peek_head_slot() == last_written);
+            for i in 0..len {
+                let slot = cursor.slot_at(i).unwrap();
+                assert!(slot < cursor.capacity());
+                for j in 0..i {
+                    assert!(cursor.slot_at(j) != Some(slot), "two indices share a slot");
+                }
+            }
+        }
+    }
+
+    /// **Proof: Head never passes the slowest consumer**
+    ///
+    /// For any interleaving of writes and reads by two consumers, every
+    /// consumer's unread range stays inside the ring's live range, so no
+    /// write can land on a slot a consumer has not read yet.
+    #[kani::proof]
+    #[kani::unwind(7)]
+    fn verify_head_never_passes_minimum_consumer() {
+        let mut multi = MultiCursor::new(Cursor::new(4));
+        multi.register_consumer("a");
+        multi.register_consumer("b");
+
+        for _ in 0..6 {
+            let op: u8 = kani::any();
+            match op % 3 {
+                0 => {
+                    let _ = multi.advance_head();
+                }
+                1 => {
+                    let _ = multi.advance_consumer("a");
+                }
+                _ => {
+                    let _ = multi.advance_consumer("b");
+                }
+            }
+
+            assert!(multi.len() < multi.capacity());
+            for name in ["a", "b"] {
+                let lag = multi.consumer_lag(name).unwrap();
+                assert!(lag <= multi.len(), "consumer fell outside the live range");
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
+
+    proptest! {
+        /// Regular-CI counterpart of the Kani advance proofs, over larger
+        /// rings and longer interleavings than the model checker can take.
+        #[test]
+        fn test_prop_advances_keep_len_exact(
+            capacity in 2usize..16,
+            ops in proptest::collection::vec(any::<bool>(), 0..64),
+        ) {
+            let mut cursor = Cursor::new(capacity);
+            let (mut head_advances, mut tail_advances) = (0usize, 0usize);
+            let mut last_written = None;
+            for advance_head in ops {
+                if advance_head {
+                    let was_full = cursor.is_full();
+                    match cursor.advance_head() {
+                        Some(slot) => {
+                            head_advances += 1;
+                            last_written = Some(slot);
+                        }
+                        None => prop_assert!(was_full),
+                    }
+                } else {
+                    let was_empty = cursor.is_empty();
+                    match cursor.advance_tail() {
+                        Some(_) => tail_advances += 1,
+                        None => prop_assert!(was_empty),
+                    }
+                }
+                prop_assert!(tail_advances <= head_advances);
+                prop_assert_eq!(cursor.len(), head_advances - tail_advances);
+                prop_assert!(cursor.len() < capacity);
+                prop_assert!(!(cursor.is_full() && cursor.is_empty()));
+
+                let slots: std::collections::HashSet<usize> =
+                    (0..cursor.len()).filter_map(|i| cursor.slot_at(i)).collect();
+                prop_assert_eq!(slots.len(), cursor.len());
+                prop_assert!(slots.iter().all(|&slot| slot < capacity));
+                prop_assert_eq!(cursor.slot_at(cursor.len()), None);
+                if !cursor.is_empty() {
+                    prop_assert_eq!(cursor.slot_at(cursor.len() - 1), last_written);
+                }
+            }
+        }
+    }
 
     #[test]
     fn test_new_cursor_is_empty() {
@@ -191,6 +723,22 @@ mod tests {
         assert_eq!(c.len(), 0);
     }
 
+    #[test]
+    fn test_utilization_pct() {
+        let mut c = Cursor::new(8);
+        assert_eq!(c.utilization_pct(), 0.0);
+        for _ in 0..4 {
+            c.advance_head();
+        }
+        assert_eq!(c.utilization_pct(), 50.0);
+        // Wrapped around, the live range is what counts.
+        for _ in 0..3 {
+            c.advance_tail();
+            c.advance_head();
+        }
+        assert_eq!(c.utilization_pct(), 50.0);
+    }
+
     #[test]
     fn test_advance_head_returns_slot() {
         let mut c = Cursor::new(4);
@@ -228,9 +776,170 @@ mod tests {
         assert_eq!(c.advance_head(), Some(2)); // head=0 (wrapped)
     }
 
+    #[test]
+    fn test_restore_resumes_positions() {
+        let mut c = Cursor::restore(4, 3, 1);
+        assert_eq!(c.len(), 2);
+        assert_eq!(c.advance_head(), Some(3));
+        assert!(c.is_full());
+        assert_eq!(c.advance_tail(), Some(1));
+    }
+
+    #[test]
+    fn test_slowest_consumer_pins_tail() {
+        let mut m = MultiCursor::new(Cursor::new(4));
+        assert!(m.register_consumer("hub"));
+        assert!(m.register_consumer("mirror"));
+        assert!(!m.register_consumer("hub"));
+
+        m.advance_head();
+        m.advance_head();
+        m.advance_head();
+        assert!(m.is_full());
+
+        // The fast consumer reads everything; the slow one pins the tail.
+        assert_eq!(m.advance_consumer("hub"), Some(0));
+        assert_eq!(m.advance_consumer("hub"), Some(1));
+        assert_eq!(m.advance_consumer("hub"), Some(2));
+        assert_eq!(m.advance_consumer("hub"), None);
+        assert!(m.is_full());
+        assert_eq!(m.advance_head(), None);
+        assert_eq!(m.consumer_lag("hub"), Some(0));
+        assert_eq!(m.consumer_lag("mirror"), Some(3));
+
+        assert_eq!(m.advance_consumer("mirror"), Some(0));
+        assert_eq!(m.tail(), 1);
+        assert_eq!(m.advance_head(), Some(3));
+    }
+
+    #[test]
+    fn test_unregister_releases_slots() {
+        let mut m = MultiCursor::new(Cursor::new(4));
+        m.register_consumer("slow");
+        m.advance_head();
+        m.advance_head();
+        assert_eq!(m.advance_tail(), None);
+
+        assert!(m.unregister_consumer("slow"));
+        assert_eq!(m.len(), 2);
+        assert_eq!(m.advance_tail(), Some(0));
+    }
+
+    #[test]
+    fn test_trim_stops_at_the_slowest_consumer() {
+        let mut m = MultiCursor::new(Cursor::new(8));
+        for _ in 0..6 {
+            m.advance_head();
+        }
+        // Persisted by a reader: one consumer has read two events.
+        m.set_consumers(vec![("mirror".into(), 2), ("gone".into(), 7)]);
+        assert_eq!(m.consumers()[1].position, 0);
+        m.set_consumers(vec![("mirror".into(), 2)]);
+
+        assert_eq!(m.trim_to(1), 2);
+        assert_eq!((m.tail(), m.len()), (2, 4));
+        m.set_consumers(Vec::new());
+        assert_eq!(m.trim_to(1), 3);
+        assert_eq!(m.len(), 1);
+    }
+
+    #[test]
+    fn test_restored_consumers_are_clamped_to_live_range() {
+        let cursor = Cursor::restore(8, 5, 2);
+        let m = MultiCursor::with_consumers(cursor, vec![("a".into(), 4), ("b".into(), 7)]);
+        assert_eq!(m.consumer_lag("a"), Some(1));
+        assert_eq!(m.consumers()[1].position, 2);
+    }
+
     #[test]
     fn test_empty_tail_returns_none() {
         let mut c = Cursor::new(4);
         assert_eq!(c.advance_tail(), None);
     }
+
+    #[test]
+    fn test_slot_helpers_unwrapped() {
+        let c = Cursor::restore(8, 5, 2);
+        assert_eq!(c.slots().collect::<Vec<_>>(), vec![2, 3, 4]);
+        assert_eq!(c.slot_at(0), Some(2));
+        assert_eq!(c.slot_at(2), Some(4));
+        assert_eq!(c.slot_at(3), None);
+        assert!(c.contains_slot(2) && c.contains_slot(4));
+        assert!(!c.contains_slot(1) && !c.contains_slot(5) && !c.contains_slot(8));
+    }
+
+    #[test]
+    fn test_slot_helpers_wrapped() {
+        let c = Cursor::restore(8, 2, 6);
+        assert_eq!(c.slots().collect::<Vec<_>>(), vec![6, 7, 0, 1]);
+        assert_eq!(c.slot_at(2), Some(0));
+        assert_eq!(c.slot_at(4), None);
+        assert!(c.contains_slot(7) && c.contains_slot(0) && c.contains_slot(1));
+        assert!(!c.contains_slot(2) && !c.contains_slot(5));
+
+        let empty = Cursor::restore(8, 3, 3);
+        assert_eq!(empty.slots().count(), 0);
+        assert!(!empty.contains_slot(3));
+    }
+
+    #[test]
+    fn test_slot_at_matches_a_walk_of_the_wrapped_ring() {
+        let c = Cursor::restore(8, 3, 5);
+        // Walk a copy from the tail, releasing one slot at a time.
+        let mut walker = Cursor::restore(8, 3, 5);
+        let walked: Vec<usize> = std::iter::from_fn(|| walker.advance_tail()).collect();
+        assert_eq!(walked, [5, 6, 7, 0, 1, 2]);
+        for (i, slot) in walked.iter().enumerate() {
+            assert_eq!(c.slot_at(i), Some(*slot));
+        }
+        assert_eq!(c.slot_at(walked.len()), None);
+        assert_eq!(c.slot_at(usize::MAX), None);
+        assert_eq!((c.peek_tail_slot(), c.peek_head_slot()), (Some(5), Some(2)));
+
+        let empty = Cursor::restore(8, 4, 4);
+        assert_eq!(
+            (empty.peek_tail_slot(), empty.peek_head_slot()),
+            (None, None)
+        );
+    }
+
+    #[test]
+    fn test_sub_ring_slots_are_offset_by_base() {
+        let mut c = Cursor::restore(4, 3, 2).with_base(8);
+        assert_eq!(c.slots().collect::<Vec<_>>(), vec![10]);
+        assert_eq!(c.advance_head(), Some(11));
+        assert_eq!(c.advance_tail(), Some(10));
+        assert_eq!((c.head(), c.tail()), (0, 3));
+        assert_eq!(c.slot_at(0), Some(11));
+        assert_eq!(c.logical_index(11), Some(0));
+        assert!(!c.contains_slot(3) && !c.contains_slot(12) && !c.contains_slot(0));
+        assert_eq!(
+            c.reserve(2).unwrap().slots().collect::<Vec<_>>(),
+            vec![8, 9]
+        );
+    }
+
+    #[test]
+    fn test_reserve_wraps_and_commits() {
+        let mut c = Cursor::restore(5, 3, 2);
+        assert_eq!(c.available(), 3);
+        assert!(c.reserve(4).is_none());
+
+        let r = c.reserve(3).unwrap();
+        assert_eq!(r.slots().collect::<Vec<_>>(), vec![3, 4, 0]);
+        assert_eq!(r.commit(), 1);
+        assert!(c.is_full());
+    }
+
+    #[test]
+    fn test_aborted_reservation_leaves_cursor_unchanged() {
+        let mut c = Cursor::new(8);
+        c.advance_head();
+        c.reserve(4).unwrap().abort();
+        {
+            let _dropped = c.reserve(2).unwrap();
+        }
+        assert_eq!(c.head(), 1);
+        assert_eq!(c.available(), 6);
+    }
 }
@@ -7,15 +7,22 @@
 use std::net::UdpSocket;
 use std::os::fd::AsRawFd;
 use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use std::time::Instant;
 
 use crc32fast::Hasher;
 use io_uring::{opcode, types, IoUring};
 
-use cz_core::CausalEvent;
+use cz_core::{CausalEvent, FLAG_COMPRESSED};
 
-use crate::cursor::Cursor;
-use crate::ipc::IpcServer;
-use crate::journal::Journal;
+use crate::checkpoint::{
+    CheckpointPolicy, CheckpointTracker, CHECKPOINT_RECORD_SIZE, SEQUENCER_NODE_ID,
+};
+use crate::cursor::{Cursor, MultiCursor};
+use crate::ipc::{
+    socket_for_journal, BackpressurePolicy, IpcCommand, IpcMessage, IpcServer, SequencerStats,
+};
+use crate::journal::{Journal, PAYLOAD_LEN_PREFIX};
+use crate::watermark::{WatermarkPolicy, Watermarks};
 
 /// Maximum UDP packet size we expect to receive.
 const MAX_PACKET_SIZE: usize = 65535;
@@ -23,6 +30,13 @@ const MAX_PACKET_SIZE: usize = 65535;
 /// Number of concurrent receive operations to keep in flight.
 const PIPELINE_DEPTH: usize = 16;
 
+/// Smallest blob region the event loop runs on: room for every in-flight
+/// receive to land in its own length-prefixed packet buffer.
+pub const MIN_BLOB_CAPACITY: usize = PIPELINE_DEPTH * (PAYLOAD_LEN_PREFIX + MAX_PACKET_SIZE);
+
+/// `user_data` of the read parked on the IPC command eventfd.
+const COMMAND_TOKEN: usize = PIPELINE_DEPTH;
+
 /// Global statistics for telemetry.
 pub static EVENTS_PROCESSED: AtomicU64 = AtomicU64::new(0);
 pub static BYTES_PROCESSED: AtomicU64 = AtomicU64::new(0);
@@ -30,10 +44,27 @@ pub static BYTES_PROCESSED: AtomicU64 = AtomicU64::new(0);
 /// Global monotonic Lamport timestamp counter.
 static LAMPORT_COUNTER: AtomicU64 = AtomicU64::new(0);
 
+/// Resume the Lamport clock after recovery so new events sort after
+/// everything the restored checkpoint covers.
+pub fn restore_lamport(watermark: u64) {
+    LAMPORT_COUNTER.fetch_max(watermark, AtomicOrdering::Relaxed);
+}
+
 /// Configuration for the event loop.
 pub struct EventLoopConfig {
     pub bind_addr: String,
     pub ring_depth: u32,
+    /// Node ID stamped on events the sequencer writes itself (checkpoints).
+    pub node_id: u32,
+    /// When to emit checkpoint events.
+    pub checkpoint: CheckpointPolicy,
+    /// Ring utilization thresholds pushed to IPC observers on crossing.
+    pub watermarks: WatermarkPolicy,
+    /// Limits for slow IPC observers.
+    pub ipc_backpressure: BackpressurePolicy,
+    /// Push-socket address; `None` derives one from the journal with
+    /// [`socket_for_journal`](crate::ipc::socket_for_journal).
+    pub ipc_socket: Option<String>,
 }
 
 impl Default for EventLoopConfig {
@@ -41,6 +72,11 @@ impl Default for EventLoopConfig {
         Self {
             bind_addr: "0.0.0.0:9000".to_string(),
             ring_depth: 256,
+            node_id: SEQUENCER_NODE_ID,
+            checkpoint: CheckpointPolicy::default(),
+            watermarks: WatermarkPolicy::default(),
+            ipc_backpressure: BackpressurePolicy::default(),
+            ipc_socket: None,
         }
     }
 }
@@ -50,8 +86,20 @@ pub struct EventLoop {
     socket: UdpSocket,
     /// Next available offset in blob storage for receiving.
     next_blob_offset: usize,
-    /// IPC server for real-time notifications.
+    /// IPC server for real-time notifications, started by `run` once the
+    /// journal (and so the default address) is known.
     ipc: Option<IpcServer>,
+    ipc_socket: Option<String>,
+    ipc_backpressure: BackpressurePolicy,
+    node_id: u32,
+    checkpoint_policy: CheckpointPolicy,
+    watermarks: Watermarks,
+    /// Oldest events are dropped beyond this many per partition (set over
+    /// IPC).
+    retention: Option<usize>,
+    /// Landing buffer for the command eventfd counter. Boxed so its
+    /// address stays fixed while the kernel owns it.
+    command_signal: Box<[u8; 8]>,
 }
 
 impl EventLoop {
@@ -60,28 +108,88 @@ impl EventLoop {
         let socket = UdpSocket::bind(&config.bind_addr)?;
         socket.set_nonblocking(true)?;
 
-        let ipc = IpcServer::start("/tmp/cz-io.sock").ok();
-
         Ok(Self {
             ring,
             socket,
             next_blob_offset: 0,
-            ipc,
+            ipc: None,
+            ipc_socket: config.ipc_socket.clone(),
+            ipc_backpressure: config.ipc_backpressure,
+            node_id: config.node_id,
+            checkpoint_policy: config.checkpoint,
+            watermarks: Watermarks::new(config.watermarks),
+            retention: None,
+            command_signal: Box::new([0; 8]),
         })
     }
 
-    pub fn run(&mut self, journal: &mut Journal, cursor: &mut Cursor) -> std::io::Result<()> {
+    pub fn run(&mut self, journal: &mut Journal, cursor: Cursor) -> std::io::Result<()> {
+        self.run_partitioned(journal, vec![cursor])
+    }
+
+    /// [`run`](Self::run) with one cursor per partition of the journal,
+    /// as from [`Journal::partition_cursor_view`]. Each event is sequenced
+    /// into the partition of its stream, and retention applies to each
+    /// partition on its own. A checkpoint records a single ring, so only an
+    /// unpartitioned journal gets them; the same goes for the consumer
+    /// offsets in the header, which hold back its tail.
+    ///
+    /// # Errors
+    /// `InvalidInput` if the journal's blob region is smaller than
+    /// [`MIN_BLOB_CAPACITY`], as after a ring that takes up nearly all of
+    /// the file.
+    ///
+    /// # Panics
+    /// If there is not exactly one cursor per partition.
+    pub fn run_partitioned(
+        &mut self,
+        journal: &mut Journal,
+        cursors: Vec<Cursor>,
+    ) -> std::io::Result<()> {
+        assert_eq!(
+            cursors.len(),
+            journal.partitions(),
+            "one cursor per partition"
+        );
+        if journal.blob_capacity() < MIN_BLOB_CAPACITY {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "blob region of {} bytes is below the {} bytes the event loop needs",
+                    journal.blob_capacity(),
+                    MIN_BLOB_CAPACITY
+                ),
+            ));
+        }
+        let checkpointing = cursors.len() == 1;
+        let mut cursors: Vec<MultiCursor> = if checkpointing {
+            let consumers = journal.consumer_offsets();
+            cursors
+                .into_iter()
+                .map(|c| MultiCursor::with_consumers(c, consumers.clone()))
+                .collect()
+        } else {
+            cursors.into_iter().map(MultiCursor::new).collect()
+        };
         let fd = types::Fd(self.socket.as_raw_fd());
-        let _blob_capacity = journal.blob_capacity();
 
-        // Track the blob storage offsets assigned to each in-flight request.
-        // We use user_data in io_uring to index into this array.
+        // Track the blob storage offsets assigned to each in-flight request
+        // (where its packet lands, past the length prefix). We use
+        // user_data in io_uring to index into this array.
         let mut in_flight_offsets = [0usize; PIPELINE_DEPTH];
 
+        let mut checkpoints =
+            CheckpointTracker::new(self.checkpoint_policy, cursors[0].head(), Instant::now());
+        for cursor in cursors.iter() {
+            journal.publish_cursor(cursor);
+        }
+        self.start_ipc(journal)?;
+
         // === INITIAL SUBMISSION: Fill the pipeline ===
         for i in 0..PIPELINE_DEPTH {
             self.submit_recv(fd, journal, i, &mut in_flight_offsets)?;
         }
+        self.submit_command_wait()?;
 
         loop {
             // Wait for at least 1 completion.
@@ -89,13 +197,12 @@ impl EventLoop {
 
             // 1. COLLECT COMPLETIONS: Decouple from &mut self to satisfy borrow checker.
             // We use a small local buffer to avoid heap allocation in the hot loop.
-            let mut completed_slots = [None::<(usize, i32)>; PIPELINE_DEPTH];
+            let mut completed_slots = [None::<(usize, i32)>; PIPELINE_DEPTH + 1];
             let mut count = 0;
 
             {
-                let mut completions = self.ring.completion();
-                while let Some(cqe) = completions.next() {
-                    if count < PIPELINE_DEPTH {
+                for cqe in self.ring.completion() {
+                    if count < completed_slots.len() {
                         completed_slots[count] = Some((cqe.user_data() as usize, cqe.result()));
                         count += 1;
                     }
@@ -103,8 +210,14 @@ impl EventLoop {
             } // completions borrow ends here
 
             // 2. PROCESS & RE-SUBMIT
-            for i in 0..count {
-                let (slot_idx, result) = completed_slots[i].unwrap();
+            for completed in completed_slots.iter().take(count) {
+                let (slot_idx, result) = completed.unwrap();
+
+                if slot_idx == COMMAND_TOKEN {
+                    self.handle_commands(journal, &mut cursors, &mut checkpoints);
+                    self.submit_command_wait()?;
+                    continue;
+                }
 
                 if result < 0 {
                     // Ignore transient errors
@@ -119,8 +232,10 @@ impl EventLoop {
                     let blob = journal.blob_storage();
                     let packet_data = &blob[offset..offset + bytes_received];
 
-                    let event =
-                        unsafe { std::ptr::read(packet_data.as_ptr() as *const CausalEvent) };
+                    // The packet may sit at any blob offset, so decode it
+                    // field by field rather than reading it in place.
+                    let event = CausalEvent::from_bytes(packet_data)
+                        .expect("packet holds a full event header");
 
                     let payload = &packet_data[CausalEvent::size_bytes()..];
                     let mut hasher = Hasher::new();
@@ -128,27 +243,37 @@ impl EventLoop {
                     let computed = hasher.finalize();
 
                     if computed == event.checksum {
+                        journal.write_payload_len(offset, bytes_received as u32);
                         let ts = LAMPORT_COUNTER.fetch_add(1, AtomicOrdering::Relaxed);
-                        let sequenced_event = CausalEvent::new(
+                        // Only the compression bit describes the payload;
+                        // every other flag is the sequencer's to set.
+                        let sequenced_event = CausalEvent::with_flags(
                             ts,
                             event.node_id,
                             event.stream_id,
                             offset as u64,
                             event.checksum,
+                            event.flags & FLAG_COMPRESSED,
                         );
 
+                        let cursor = &mut cursors[journal.partition_of(event.stream_id)];
                         if let Some(ring_slot) = cursor.advance_head() {
                             unsafe {
                                 journal.write_event_at(ring_slot, &sequenced_event);
                             }
+                            self.enforce_retention(journal, cursor, checkpointing);
+                            journal.publish_cursor(cursor);
+                            checkpoints.record_event();
                             EVENTS_PROCESSED.fetch_add(1, AtomicOrdering::Relaxed);
                             BYTES_PROCESSED
                                 .fetch_add(bytes_received as u64, AtomicOrdering::Relaxed);
 
                             // Real-time notification
                             if let Some(ipc) = &self.ipc {
-                                // Send slot index (4 bytes)
-                                ipc.broadcast(&ring_slot.to_le_bytes());
+                                ipc.broadcast(&IpcMessage::SlotCommitted {
+                                    slot: ring_slot as u64,
+                                    lamport_ts: ts,
+                                });
                             }
                         }
                     }
@@ -156,6 +281,202 @@ impl EventLoop {
 
                 self.submit_recv(fd, journal, slot_idx, &mut in_flight_offsets)?;
             }
+
+            // 3. CHECKPOINT
+            if checkpointing && checkpoints.is_due(Instant::now()) {
+                self.emit_checkpoint(journal, &mut cursors[0], &mut checkpoints);
+            }
+
+            // 4. WATERMARKS: the fullest partition decides.
+            let fullest = cursors.iter().max_by_key(|c| c.len()).expect("a cursor");
+            if let Some(crossing) = self.watermarks.observe(fullest) {
+                if let Some(ipc) = &self.ipc {
+                    ipc.broadcast(&IpcMessage::WatermarkCrossed(crossing));
+                }
+            }
+        }
+    }
+
+    /// Write a checkpoint event covering everything since the last one.
+    fn emit_checkpoint(
+        &mut self,
+        journal: &mut Journal,
+        cursor: &mut MultiCursor,
+        tracker: &mut CheckpointTracker,
+    ) {
+        let Some(ring_slot) = cursor.advance_head() else {
+            return;
+        };
+        let ts = LAMPORT_COUNTER.fetch_add(1, AtomicOrdering::Relaxed);
+
+        // Claim blob space for the record, wrapping like `submit_recv`.
+        if self.next_blob_offset + CHECKPOINT_RECORD_SIZE > journal.blob_capacity() {
+            self.next_blob_offset = 0;
+        }
+        let offset = self.next_blob_offset;
+        self.next_blob_offset += CHECKPOINT_RECORD_SIZE;
+
+        journal.write_checkpoint(
+            cursor,
+            ring_slot,
+            self.node_id,
+            ts,
+            tracker.segment_start(),
+            offset,
+        );
+        journal.publish_cursor(cursor);
+        tracker.mark(cursor.head(), Instant::now());
+
+        if let Some(ipc) = &self.ipc {
+            ipc.broadcast(&IpcMessage::Checkpoint {
+                slot: ring_slot as u64,
+                lamport_ts: ts,
+            });
+            ipc.broadcast(&IpcMessage::StatsSnapshot(stats(
+                std::slice::from_ref(cursor),
+                ipc,
+            )));
+        }
+    }
+
+    /// Drop the oldest events until the ring is within the retention limit,
+    /// short of any slot a consumer has yet to read. With `consumers` set,
+    /// the positions readers last persisted to the header are picked up
+    /// first, so a slow reader holds back the tail here too.
+    fn enforce_retention(&self, journal: &Journal, cursor: &mut MultiCursor, consumers: bool) {
+        let Some(max_events) = self.retention else {
+            return;
+        };
+        if cursor.len() <= max_events {
+            return;
+        }
+        if consumers {
+            cursor.set_consumers(journal.consumer_offsets());
+        }
+        cursor.trim_to(max_events);
+    }
+
+    /// Listen for observers and record the address in the journal header.
+    ///
+    /// A sequencer that cannot bind still runs, just without observers;
+    /// another live sequencer on the same address is reported and skipped.
+    fn start_ipc(&mut self, journal: &mut Journal) -> std::io::Result<()> {
+        if self.ipc.is_some() {
+            return Ok(());
+        }
+        let address = self
+            .ipc_socket
+            .clone()
+            .unwrap_or_else(|| socket_for_journal(journal.path()));
+        match IpcServer::start_with(&address, self.ipc_backpressure) {
+            Ok(ipc) => {
+                journal.publish_ipc_address(&address)?;
+                self.ipc = Some(ipc);
+            }
+            Err(e) => tracing::warn!("IPC disabled: cannot listen on {}: {}", address, e),
+        }
+        Ok(())
+    }
+
+    /// Park a read on the command eventfd so queued IPC commands wake the
+    /// loop like any other completion.
+    fn submit_command_wait(&mut self) -> std::io::Result<()> {
+        let Some(ipc) = &self.ipc else {
+            return Ok(());
+        };
+        let read_entry = opcode::Read::new(
+            types::Fd(ipc.command_fd()),
+            self.command_signal.as_mut_ptr(),
+            8,
+        )
+        .build()
+        .user_data(COMMAND_TOKEN as u64);
+
+        unsafe {
+            self.ring
+                .submission()
+                .push(&read_entry)
+                .map_err(|_| std::io::Error::other("io_uring submission queue full"))?;
+        }
+        Ok(())
+    }
+
+    /// Execute queued IPC commands and acknowledge each to its sender.
+    fn handle_commands(
+        &mut self,
+        journal: &mut Journal,
+        cursors: &mut [MultiCursor],
+        tracker: &mut CheckpointTracker,
+    ) {
+        let Some(ipc) = &self.ipc else {
+            return;
+        };
+        for pending in ipc.take_commands() {
+            let outcome = match pending.command {
+                IpcCommand::Flush => journal.flush().map(|()| "flushed".to_string()),
+                IpcCommand::PersistCursor => {
+                    for cursor in cursors.iter() {
+                        journal.publish_cursor(cursor);
+                    }
+                    journal.flush().map(|()| {
+                        cursors
+                            .iter()
+                            .map(|c| format!("head={} tail={}", c.head(), c.tail()))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    })
+                }
+                IpcCommand::Rollover { path } => {
+                    journal.snapshot_to(std::path::Path::new(&path)).map(|()| {
+                        let mut archived = 0;
+                        for cursor in cursors.iter_mut() {
+                            archived += cursor.len();
+                            // Consumers clamp to the new, empty range.
+                            *cursor = MultiCursor::with_consumers(
+                                Cursor::restore(cursor.capacity(), cursor.head(), cursor.head())
+                                    .with_base(cursor.base()),
+                                cursor.consumer_offsets(),
+                            );
+                            journal.publish_cursor(cursor);
+                        }
+                        tracker.mark(cursors[0].head(), Instant::now());
+                        format!("archived {} events to {}", archived, path)
+                    })
+                }
+                IpcCommand::SetRetention { max_events } => {
+                    self.retention = (max_events > 0).then_some(max_events as usize);
+                    match self.retention {
+                        Some(max) => Ok(format!("retaining at most {} events", max)),
+                        None => Ok("retention disabled".to_string()),
+                    }
+                }
+                IpcCommand::QueryStats => {
+                    ipc.respond(
+                        pending.client,
+                        &IpcMessage::StatsSnapshot(stats(cursors, ipc)),
+                    );
+                    Ok("stats".to_string())
+                }
+            };
+            let (ok, detail) = match outcome {
+                Ok(detail) => (true, detail),
+                Err(e) => (false, e.to_string()),
+            };
+            ipc.respond(
+                pending.client,
+                &IpcMessage::CommandResult {
+                    id: pending.id,
+                    ok,
+                    detail,
+                },
+            );
+        }
+        if self.retention.is_some() {
+            let consumers = cursors.len() == 1;
+            for cursor in cursors.iter_mut() {
+                self.enforce_retention(journal, cursor, consumers);
+                journal.publish_cursor(cursor);
+            }
         }
     }
 
@@ -167,17 +488,26 @@ impl EventLoop {
         slot_idx: usize,
         in_flight_offsets: &mut [usize],
     ) -> std::io::Result<()> {
-        let offset = self.next_blob_offset;
+        let mut offset = self.next_blob_offset + PAYLOAD_LEN_PREFIX;
         let blob = journal.blob_storage_mut();
 
         // Wrap blob offset if we're at the end (circular blob buffer)
         if offset + MAX_PACKET_SIZE > blob.len() {
-            self.next_blob_offset = 0;
-            return self.submit_recv(fd, journal, slot_idx, in_flight_offsets);
+            offset = PAYLOAD_LEN_PREFIX;
+        }
+        // `run_partitioned` refuses smaller blobs, so this only guards
+        // against a caller that skipped it.
+        if offset + MAX_PACKET_SIZE > blob.len() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "blob region cannot hold a packet",
+            ));
         }
 
+        // The packet lands past its length prefix, filled in once it has
+        // been received and verified.
         in_flight_offsets[slot_idx] = offset;
-        self.next_blob_offset += MAX_PACKET_SIZE;
+        self.next_blob_offset = offset + MAX_PACKET_SIZE;
 
         // Pointer directly to the mmap'd region. Zero-copy!
         let buf_ptr = unsafe { blob.as_mut_ptr().add(offset) };
@@ -195,3 +525,40 @@ impl EventLoop {
         Ok(())
     }
 }
+
+/// Current global counters, ring occupancy across every partition, and IPC
+/// delivery state.
+fn stats(cursors: &[MultiCursor], ipc: &IpcServer) -> SequencerStats {
+    SequencerStats {
+        events_processed: EVENTS_PROCESSED.load(AtomicOrdering::Relaxed),
+        bytes_processed: BYTES_PROCESSED.load(AtomicOrdering::Relaxed),
+        ring_len: cursors.iter().map(|c| c.len() as u64).sum(),
+        queue_dropped: ipc.queue_dropped(),
+        clients: ipc.client_stats(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::journal::HEADER_SIZE;
+
+    #[test]
+    fn test_refuses_a_blob_too_small_for_the_pipeline() {
+        let path = std::env::temp_dir().join(format!("cz-io-small-blob-{}.db", std::process::id()));
+        std::fs::remove_file(&path).ok();
+        let ring = 1024 * CausalEvent::size_bytes();
+        let size = HEADER_SIZE + ring + MIN_BLOB_CAPACITY - 1;
+        let mut journal = Journal::open_with_ring_size(&path, size as u64, ring).unwrap();
+        let cursor = journal.live_cursor_view();
+
+        let mut event_loop = EventLoop::new(&EventLoopConfig {
+            bind_addr: "127.0.0.1:0".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        let err = event_loop.run(&mut journal, cursor).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -1,47 +1,1193 @@
+//! # IPC — Framed Push Notifications over a Unix Socket
+//!
+//! The sequencer pushes typed messages to every connected observer. Each
+//! message is a self-describing frame, all integers little-endian:
+//!
+//! | Offset | Size | Field                         |
+//! |--------|------|-------------------------------|
+//! | 0      | 4    | magic `"CZIP"`                |
+//! | 4      | 1    | protocol version              |
+//! | 5      | 1    | message type                  |
+//! | 6      | 2    | reserved (zero)               |
+//! | 8      | 4    | payload length in bytes       |
+//! | 12     | len  | payload                       |
+//!
+//! The channel is bidirectional: clients may send [`IpcMessage::Command`]
+//! frames, which the event loop executes between submissions and answers
+//! with an [`IpcMessage::CommandResult`] to that client only.
+//!
+//! Readers that lose framing (partial writes, a restarted peer) scan
+//! forward to the next magic and carry on. Frames with an unknown version
+//! or message type are skipped by length so the format can grow.
+//!
+//! # Slow Observers
+//!
+//! The event loop never writes to a socket itself. Frames go through a
+//! bounded queue to a writer thread that keeps a backlog per client; a
+//! client whose backlog is full loses new frames, and one that accepts no
+//! bytes for [`BackpressurePolicy::stall_timeout`] is disconnected. Drop
+//! counts travel in [`IpcMessage::StatsSnapshot`].
+//!
+//! # Addresses
+//!
+//! Each sequencer listens on its own address, normally derived from its
+//! journal by [`socket_for_journal`] and recorded in the journal header so
+//! readers can find it. An address starting with `@` names a socket in
+//! the Linux abstract namespace, which needs no file and vanishes with its
+//! owner. Any other address is a filesystem path; the owner writes its PID
+//! to `<path>.pid` so a restart only removes a socket whose owner is dead.
+//!
+//! # Compatibility
+//!
+//! Earlier sequencers wrote bare little-endian slot indices with no framing.
+//! A framed reader treats that stream as noise and resyncs forever, and a
+//! legacy reader misparses frames as slots, so the sequencer and hub must be
+//! upgraded together.
+
+use std::collections::VecDeque;
 use std::fs;
-use std::io::Write;
-use std::os::unix::net::{UnixListener, UnixStream};
+use std::io::{ErrorKind, Read, Write};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::os::linux::net::SocketAddrExt;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::net::{SocketAddr, UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, RecvTimeoutError, SyncSender};
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::watermark::WatermarkCrossing;
+
+/// Leading character of an abstract-namespace address.
+pub const ABSTRACT_PREFIX: char = '@';
+/// Longest address that fits in `sockaddr_un`, prefix included.
+pub const MAX_SOCKET_ADDRESS_LEN: usize = 107;
+/// Marks the start of every frame.
+pub const FRAME_MAGIC: [u8; 4] = *b"CZIP";
+/// Protocol version written by this build.
+pub const PROTOCOL_VERSION: u8 = 1;
+/// Bytes before the payload.
+pub const FRAME_HEADER_SIZE: usize = 12;
+/// Larger lengths are treated as corruption rather than buffered.
+pub const MAX_PAYLOAD_SIZE: usize = 64 * 1024;
+
+const TYPE_SLOT_COMMITTED: u8 = 1;
+const TYPE_CHECKPOINT: u8 = 2;
+const TYPE_WATERMARK_CROSSED: u8 = 3;
+const TYPE_STATS_SNAPSHOT: u8 = 4;
+const TYPE_SHUTDOWN: u8 = 5;
+const TYPE_COMMAND: u8 = 6;
+const TYPE_COMMAND_RESULT: u8 = 7;
+
+const COMMAND_FLUSH: u8 = 1;
+const COMMAND_PERSIST_CURSOR: u8 = 2;
+const COMMAND_ROLLOVER: u8 = 3;
+const COMMAND_SET_RETENTION: u8 = 4;
+const COMMAND_QUERY_STATS: u8 = 5;
+
+/// Sequencer counters carried by [`IpcMessage::StatsSnapshot`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SequencerStats {
+    pub events_processed: u64,
+    pub bytes_processed: u64,
+    pub ring_len: u64,
+    /// Frames dropped because the writer thread's queue was full.
+    pub queue_dropped: u64,
+    pub clients: Vec<ClientStats>,
+}
+
+/// Delivery state of one connected observer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ClientStats {
+    pub client: u64,
+    /// Frames waiting for the client's socket to drain.
+    pub queue_depth: u32,
+    /// Frames discarded because the backlog was full.
+    pub dropped: u64,
+}
+
+/// Bytes per [`ClientStats`] entry in a stats payload.
+const CLIENT_STATS_SIZE: usize = 20;
+
+/// A control request sent by a client to the sequencer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IpcCommand {
+    /// Flush the journal mmap to disk.
+    Flush,
+    /// Publish the in-memory cursor to the journal header and flush.
+    PersistCursor,
+    /// Copy the journal to `path`, then start the ring over empty.
+    Rollover { path: String },
+    /// Keep at most `max_events` in the ring, dropping the oldest
+    /// (0 disables retention).
+    SetRetention { max_events: u64 },
+    /// Report current counters.
+    QueryStats,
+}
+
+impl IpcCommand {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            IpcCommand::Flush => out.push(COMMAND_FLUSH),
+            IpcCommand::PersistCursor => out.push(COMMAND_PERSIST_CURSOR),
+            IpcCommand::Rollover { path } => {
+                out.push(COMMAND_ROLLOVER);
+                out.extend_from_slice(path.as_bytes());
+            }
+            IpcCommand::SetRetention { max_events } => {
+                out.push(COMMAND_SET_RETENTION);
+                out.extend_from_slice(&max_events.to_le_bytes());
+            }
+            IpcCommand::QueryStats => out.push(COMMAND_QUERY_STATS),
+        }
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        let (&code, args) = bytes.split_first()?;
+        match code {
+            COMMAND_FLUSH => Some(IpcCommand::Flush),
+            COMMAND_PERSIST_CURSOR => Some(IpcCommand::PersistCursor),
+            COMMAND_ROLLOVER => Some(IpcCommand::Rollover {
+                path: String::from_utf8(args.to_vec()).ok()?,
+            }),
+            COMMAND_SET_RETENTION => Some(IpcCommand::SetRetention {
+                max_events: u64::from_le_bytes(args.get(..8)?.try_into().ok()?),
+            }),
+            COMMAND_QUERY_STATS => Some(IpcCommand::QueryStats),
+            _ => None,
+        }
+    }
+}
+
+/// A message exchanged over the IPC socket.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IpcMessage {
+    /// An event was written to the Index Ring.
+    SlotCommitted { slot: u64, lamport_ts: u64 },
+    /// A checkpoint event was written to the Index Ring.
+    Checkpoint { slot: u64, lamport_ts: u64 },
+    /// Ring utilization crossed a watermark.
+    WatermarkCrossed(WatermarkCrossing),
+    /// Sequencer counters, pushed periodically and in reply to
+    /// [`IpcCommand::QueryStats`].
+    StatsSnapshot(SequencerStats),
+    /// The sequencer is going away; observers should reconnect later.
+    Shutdown,
+    /// Client to sequencer: run `command`, answering with the same `id`.
+    Command { id: u32, command: IpcCommand },
+    /// Sequencer to client: outcome of the command with this `id`.
+    CommandResult { id: u32, ok: bool, detail: String },
+}
+
+impl IpcMessage {
+    /// Encode as a complete frame.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut payload = Vec::with_capacity(24);
+        let kind = match self {
+            &IpcMessage::SlotCommitted { slot, lamport_ts } => {
+                payload.extend_from_slice(&slot.to_le_bytes());
+                payload.extend_from_slice(&lamport_ts.to_le_bytes());
+                TYPE_SLOT_COMMITTED
+            }
+            &IpcMessage::Checkpoint { slot, lamport_ts } => {
+                payload.extend_from_slice(&slot.to_le_bytes());
+                payload.extend_from_slice(&lamport_ts.to_le_bytes());
+                TYPE_CHECKPOINT
+            }
+            IpcMessage::WatermarkCrossed(crossing) => {
+                let (rising, pct) = match *crossing {
+                    WatermarkCrossing::High { utilization_pct } => (1u8, utilization_pct),
+                    WatermarkCrossing::Low { utilization_pct } => (0u8, utilization_pct),
+                };
+                payload.push(rising);
+                payload.extend_from_slice(&pct.to_le_bytes());
+                TYPE_WATERMARK_CROSSED
+            }
+            IpcMessage::StatsSnapshot(stats) => {
+                payload.extend_from_slice(&stats.events_processed.to_le_bytes());
+                payload.extend_from_slice(&stats.bytes_processed.to_le_bytes());
+                payload.extend_from_slice(&stats.ring_len.to_le_bytes());
+                payload.extend_from_slice(&stats.queue_dropped.to_le_bytes());
+                let max_clients = (MAX_PAYLOAD_SIZE - 36) / CLIENT_STATS_SIZE;
+                let clients = &stats.clients[..stats.clients.len().min(max_clients)];
+                payload.extend_from_slice(&(clients.len() as u32).to_le_bytes());
+                for c in clients {
+                    payload.extend_from_slice(&c.client.to_le_bytes());
+                    payload.extend_from_slice(&c.queue_depth.to_le_bytes());
+                    payload.extend_from_slice(&c.dropped.to_le_bytes());
+                }
+                TYPE_STATS_SNAPSHOT
+            }
+            IpcMessage::Shutdown => TYPE_SHUTDOWN,
+            IpcMessage::Command { id, command } => {
+                payload.extend_from_slice(&id.to_le_bytes());
+                command.encode(&mut payload);
+                TYPE_COMMAND
+            }
+            IpcMessage::CommandResult { id, ok, detail } => {
+                payload.extend_from_slice(&id.to_le_bytes());
+                payload.push(*ok as u8);
+                // Keep the frame under the decoder's limit.
+                let mut end = detail.len().min(MAX_PAYLOAD_SIZE - 5);
+                while !detail.is_char_boundary(end) {
+                    end -= 1;
+                }
+                payload.extend_from_slice(&detail.as_bytes()[..end]);
+                TYPE_COMMAND_RESULT
+            }
+        };
+
+        let mut frame = Vec::with_capacity(FRAME_HEADER_SIZE + payload.len());
+        frame.extend_from_slice(&FRAME_MAGIC);
+        frame.push(PROTOCOL_VERSION);
+        frame.push(kind);
+        frame.extend_from_slice(&[0, 0]);
+        frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        frame.extend_from_slice(&payload);
+        frame
+    }
+
+    /// Decode a payload of a known type, or `None` if it is malformed.
+    fn decode(kind: u8, payload: &[u8]) -> Option<Self> {
+        let u64_at = |i: usize| -> Option<u64> {
+            Some(u64::from_le_bytes(payload.get(i..i + 8)?.try_into().ok()?))
+        };
+        let u32_at = |i: usize| -> Option<u32> {
+            Some(u32::from_le_bytes(payload.get(i..i + 4)?.try_into().ok()?))
+        };
+        match kind {
+            TYPE_SLOT_COMMITTED => Some(IpcMessage::SlotCommitted {
+                slot: u64_at(0)?,
+                lamport_ts: u64_at(8)?,
+            }),
+            TYPE_CHECKPOINT => Some(IpcMessage::Checkpoint {
+                slot: u64_at(0)?,
+                lamport_ts: u64_at(8)?,
+            }),
+            TYPE_WATERMARK_CROSSED => {
+                let utilization_pct = f64::from_bits(u64_at(1)?);
+                Some(IpcMessage::WatermarkCrossed(match payload[0] {
+                    0 => WatermarkCrossing::Low { utilization_pct },
+                    _ => WatermarkCrossing::High { utilization_pct },
+                }))
+            }
+            TYPE_STATS_SNAPSHOT => {
+                let count = u32_at(32)? as usize;
+                let clients = (0..count)
+                    .map(|i| {
+                        let at = 36 + i * CLIENT_STATS_SIZE;
+                        Some(ClientStats {
+                            client: u64_at(at)?,
+                            queue_depth: u32_at(at + 8)?,
+                            dropped: u64_at(at + 12)?,
+                        })
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                Some(IpcMessage::StatsSnapshot(SequencerStats {
+                    events_processed: u64_at(0)?,
+                    bytes_processed: u64_at(8)?,
+                    ring_len: u64_at(16)?,
+                    queue_dropped: u64_at(24)?,
+                    clients,
+                }))
+            }
+            TYPE_SHUTDOWN => Some(IpcMessage::Shutdown),
+            TYPE_COMMAND => Some(IpcMessage::Command {
+                id: u32_at(0)?,
+                command: IpcCommand::decode(&payload[4..])?,
+            }),
+            TYPE_COMMAND_RESULT => Some(IpcMessage::CommandResult {
+                id: u32_at(0)?,
+                ok: *payload.get(4)? != 0,
+                detail: String::from_utf8_lossy(&payload[5..]).into_owned(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Incremental frame decoder for a byte stream.
+///
+/// Feed it whatever the socket returns and pull complete messages out.
+#[derive(Debug, Default)]
+pub struct FrameDecoder {
+    buf: Vec<u8>,
+    /// Bytes discarded while searching for a frame boundary.
+    skipped: u64,
+}
+
+impl FrameDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append bytes read from the stream.
+    pub fn feed(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// Total bytes dropped to resynchronize or skip unknown frames.
+    pub fn skipped_bytes(&self) -> u64 {
+        self.skipped
+    }
+
+    /// Next complete message, or `None` until more bytes arrive.
+    pub fn next_message(&mut self) -> Option<IpcMessage> {
+        loop {
+            self.resync();
+            if self.buf.len() < FRAME_HEADER_SIZE {
+                return None;
+            }
+
+            let version = self.buf[4];
+            let kind = self.buf[5];
+            let len = u32::from_le_bytes(self.buf[8..12].try_into().unwrap()) as usize;
+            if len > MAX_PAYLOAD_SIZE {
+                // A magic match inside garbage; step past it.
+                self.discard(1);
+                continue;
+            }
+            if self.buf.len() < FRAME_HEADER_SIZE + len {
+                return None;
+            }
+
+            let message = if version == PROTOCOL_VERSION {
+                IpcMessage::decode(kind, &self.buf[FRAME_HEADER_SIZE..FRAME_HEADER_SIZE + len])
+            } else {
+                None
+            };
+            if message.is_some() {
+                self.buf.drain(..FRAME_HEADER_SIZE + len);
+                return message;
+            }
+            self.discard(FRAME_HEADER_SIZE + len);
+        }
+    }
+
+    /// Drop bytes until the buffer starts with the frame magic, keeping a
+    /// trailing partial match in case the rest is still in flight.
+    fn resync(&mut self) {
+        let start = self
+            .buf
+            .windows(FRAME_MAGIC.len())
+            .position(|w| w == FRAME_MAGIC)
+            .unwrap_or_else(|| {
+                let keep = (1..FRAME_MAGIC.len())
+                    .rev()
+                    .find(|&n| self.buf.ends_with(&FRAME_MAGIC[..n]))
+                    .unwrap_or(0);
+                self.buf.len().saturating_sub(keep)
+            });
+        self.discard(start);
+    }
+
+    fn discard(&mut self, n: usize) {
+        let n = n.min(self.buf.len());
+        self.buf.drain(..n);
+        self.skipped += n as u64;
+    }
+}
+
+/// A command received from a connected client, awaiting execution.
+#[derive(Debug)]
+pub struct PendingCommand {
+    /// Connection to answer on via [`IpcServer::respond`].
+    pub client: u64,
+    pub id: u32,
+    pub command: IpcCommand,
+}
+
+/// Limits that keep slow observers from stalling the event loop.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BackpressurePolicy {
+    /// Frames buffered between the event loop and the writer thread.
+    pub queue_capacity: usize,
+    /// Frames held for one client before new ones are dropped for it.
+    pub max_client_backlog: usize,
+    /// A client that accepts no bytes for this long is disconnected.
+    pub stall_timeout: Duration,
+}
+
+impl Default for BackpressurePolicy {
+    fn default() -> Self {
+        Self {
+            queue_capacity: 4096,
+            max_client_backlog: 1024,
+            stall_timeout: Duration::from_millis(500),
+        }
+    }
+}
+
+/// How often the writer thread retries blocked clients when idle.
+const WRITER_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// A frame handed from the event loop to the writer thread.
+enum Outgoing {
+    Broadcast(Arc<[u8]>),
+    To(u64, Arc<[u8]>),
+}
+
+/// Default push-socket address for the sequencer writing `journal`.
+///
+/// The file name keeps the address readable; a hash of the canonical path
+/// keeps two journals that share a file name apart.
+pub fn socket_for_journal(journal: &Path) -> String {
+    let canonical = fs::canonicalize(journal)
+        .or_else(|_| std::path::absolute(journal))
+        .unwrap_or_else(|_| journal.to_path_buf());
+    let mut name = journal
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "journal".into());
+    while name.len() > 64 {
+        name.pop();
+    }
+    format!(
+        "{}cz-io/{}-{:08x}",
+        ABSTRACT_PREFIX,
+        name,
+        crc32fast::hash(canonical.as_os_str().as_bytes())
+    )
+}
+
+/// Address of the sequencer serving `journal`: the one recorded in its
+/// header, or the derived default for a journal no sequencer has run on.
+pub fn resolve_socket(journal: &Path) -> String {
+    crate::journal::read_ipc_address(journal)
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| socket_for_journal(journal))
+}
+
+/// Connect to the sequencer listening on `address`.
+pub fn connect(address: &str) -> std::io::Result<UnixStream> {
+    match address.strip_prefix(ABSTRACT_PREFIX) {
+        Some(name) => UnixStream::connect_addr(&SocketAddr::from_abstract_name(name)?),
+        None => UnixStream::connect(address),
+    }
+}
+
+fn bind(address: &str) -> std::io::Result<UnixListener> {
+    if address.len() > MAX_SOCKET_ADDRESS_LEN {
+        return Err(std::io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("socket address exceeds {} bytes", MAX_SOCKET_ADDRESS_LEN),
+        ));
+    }
+    if let Some(name) = address.strip_prefix(ABSTRACT_PREFIX) {
+        return UnixListener::bind_addr(&SocketAddr::from_abstract_name(name)?);
+    }
+    remove_stale_socket(Path::new(address))?;
+    let listener = UnixListener::bind(address)?;
+    fs::write(pid_file(address), std::process::id().to_string())?;
+    Ok(listener)
+}
+
+fn pid_file(path: &str) -> PathBuf {
+    PathBuf::from(format!("{}.pid", path))
+}
+
+/// Remove a socket file left behind by a sequencer that is gone.
+///
+/// The owner is whoever the pidfile names; a socket without one (written
+/// by an older build) counts as owned if something still accepts on it.
+fn remove_stale_socket(path: &Path) -> std::io::Result<()> {
+    if fs::symlink_metadata(path).is_err() {
+        return Ok(());
+    }
+    let pid_path = pid_file(&path.to_string_lossy());
+    let owner = fs::read_to_string(&pid_path)
+        .ok()
+        .and_then(|pid| pid.trim().parse::<libc::pid_t>().ok());
+    let in_use = match owner {
+        Some(pid) => process_alive(pid),
+        None => UnixStream::connect(path).is_ok(),
+    };
+    if in_use {
+        return Err(std::io::Error::new(
+            ErrorKind::AddrInUse,
+            format!("{} belongs to a running sequencer", path.display()),
+        ));
+    }
+    fs::remove_file(path)?;
+    fs::remove_file(pid_path).ok();
+    Ok(())
+}
+
+fn process_alive(pid: libc::pid_t) -> bool {
+    // SAFETY: signal 0 performs the permission and existence checks only.
+    let delivered = unsafe { libc::kill(pid, 0) } == 0;
+    delivered || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
 
 /// A simple broadcast server using Unix Domain Sockets.
-/// Pushes notifications to all connected observers (like cz-hub).
+/// Pushes notifications to all connected observers (like cz-hub) and
+/// collects commands they send back.
+///
+/// Sending never blocks: frames go through a bounded queue to a writer
+/// thread, which buffers per client and drops for clients that fall behind.
 pub struct IpcServer {
-    clients: Arc<Mutex<Vec<UnixStream>>>,
+    outgoing: Option<SyncSender<Outgoing>>,
+    writer: Option<thread::JoinHandle<()>>,
+    queue_dropped: AtomicU64,
+    client_stats: Arc<Mutex<Vec<ClientStats>>>,
+    commands: Arc<Mutex<VecDeque<PendingCommand>>>,
+    /// Signalled once per queued command so the event loop can wait on it
+    /// inside io_uring instead of polling.
+    command_fd: Arc<OwnedFd>,
+    address: String,
+    /// Handle on the accept thread's listener, shut down on drop so the
+    /// address is released.
+    listener: UnixListener,
+    closing: Arc<AtomicBool>,
 }
 
 impl IpcServer {
-    pub fn start(path: &str) -> std::io::Result<Self> {
-        // Clean up existing socket file
-        if fs::metadata(path).is_ok() {
-            fs::remove_file(path)?;
+    pub fn start(address: &str) -> std::io::Result<Self> {
+        Self::start_with(address, BackpressurePolicy::default())
+    }
+
+    /// Listen on `address`, a filesystem path or `@`-prefixed abstract name.
+    ///
+    /// Fails with [`ErrorKind::AddrInUse`] if another live sequencer owns it.
+    pub fn start_with(address: &str, policy: BackpressurePolicy) -> std::io::Result<Self> {
+        let listener = bind(address)?;
+
+        // SAFETY: eventfd returns a fresh descriptor we take ownership of.
+        let raw = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC) };
+        if raw < 0 {
+            return Err(std::io::Error::last_os_error());
         }
+        let command_fd = Arc::new(unsafe { OwnedFd::from_raw_fd(raw) });
 
-        let listener = UnixListener::bind(path)?;
-        let clients = Arc::new(Mutex::new(Vec::new()));
-        let clients_clone = clients.clone();
+        let handle = listener.try_clone()?;
+        let closing = Arc::new(AtomicBool::new(false));
+        let closing_clone = closing.clone();
+        let accepted = Arc::new(Mutex::new(Vec::new()));
+        let commands = Arc::new(Mutex::new(VecDeque::new()));
+        let accepted_clone = accepted.clone();
+        let commands_clone = commands.clone();
+        let command_fd_clone = command_fd.clone();
 
         // Background thread to accept connections
         thread::spawn(move || {
-            for stream in listener.incoming() {
-                if let Ok(s) = stream {
-                    let _ = s.set_nonblocking(true);
-                    let mut lock = clients_clone.lock().unwrap();
-                    lock.push(s);
+            let next_id = AtomicU64::new(0);
+            for s in listener.incoming() {
+                if closing_clone.load(Ordering::Acquire) {
+                    break;
+                }
+                let Ok(s) = s else {
+                    continue;
+                };
+                let client = next_id.fetch_add(1, Ordering::Relaxed);
+                if let Ok(reader) = s.try_clone() {
+                    let commands = commands_clone.clone();
+                    let command_fd = command_fd_clone.clone();
+                    thread::spawn(move || read_commands(client, reader, &commands, &command_fd));
                 }
+                let _ = s.set_nonblocking(true);
+                accepted_clone.lock().unwrap().push((client, s));
             }
         });
 
-        Ok(Self { clients })
+        let (outgoing, queue) = sync_channel(policy.queue_capacity);
+        let client_stats = Arc::new(Mutex::new(Vec::new()));
+        let stats_clone = client_stats.clone();
+        let writer = thread::spawn(move || write_frames(queue, &accepted, &stats_clone, policy));
+
+        Ok(Self {
+            outgoing: Some(outgoing),
+            writer: Some(writer),
+            queue_dropped: AtomicU64::new(0),
+            client_stats,
+            commands,
+            command_fd,
+            address: address.to_string(),
+            listener: handle,
+            closing,
+        })
+    }
+
+    /// Address this server listens on.
+    pub fn address(&self) -> &str {
+        &self.address
     }
 
     /// Sends a message to all connected clients.
-    /// Removes clients that have disconnected.
-    pub fn broadcast(&self, msg: &[u8]) {
-        let mut lock = self.clients.lock().unwrap();
-        lock.retain_mut(|client| {
-            // We use write_all. If it fails (e.g. Broken pipe), the client is dropped.
-            client.write_all(msg).is_ok()
+    /// Never blocks; the frame is dropped if the writer queue is full.
+    pub fn broadcast(&self, msg: &IpcMessage) {
+        self.enqueue(Outgoing::Broadcast(msg.encode().into()));
+    }
+
+    /// Sends a message to a single client, if it is still connected.
+    pub fn respond(&self, client: u64, msg: &IpcMessage) {
+        self.enqueue(Outgoing::To(client, msg.encode().into()));
+    }
+
+    fn enqueue(&self, frame: Outgoing) {
+        let Some(outgoing) = &self.outgoing else {
+            return;
+        };
+        if outgoing.try_send(frame).is_err() {
+            self.queue_dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Frames dropped because the writer queue was full.
+    pub fn queue_dropped(&self) -> u64 {
+        self.queue_dropped.load(Ordering::Relaxed)
+    }
+
+    /// Per-client backlog and drop counts as of the writer's last pass.
+    pub fn client_stats(&self) -> Vec<ClientStats> {
+        self.client_stats.lock().unwrap().clone()
+    }
+
+    /// Eventfd that becomes readable when commands are queued.
+    pub fn command_fd(&self) -> RawFd {
+        self.command_fd.as_raw_fd()
+    }
+
+    /// Drain all queued commands.
+    pub fn take_commands(&self) -> Vec<PendingCommand> {
+        self.commands.lock().unwrap().drain(..).collect()
+    }
+}
+
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        self.broadcast(&IpcMessage::Shutdown);
+        // Closing the queue lets the writer flush what it can and exit.
+        self.outgoing = None;
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+
+        // Wake the accept thread so it drops the listener.
+        self.closing.store(true, Ordering::Release);
+        // SAFETY: shutdown on a descriptor we own; it stays open until drop.
+        unsafe { libc::shutdown(self.listener.as_raw_fd(), libc::SHUT_RDWR) };
+        if !self.address.starts_with(ABSTRACT_PREFIX) {
+            fs::remove_file(&self.address).ok();
+            fs::remove_file(pid_file(&self.address)).ok();
+        }
+    }
+}
+
+/// A connected observer as seen by the writer thread.
+struct Client {
+    id: u64,
+    stream: UnixStream,
+    backlog: VecDeque<Arc<[u8]>>,
+    /// Bytes of the front frame already written.
+    written: usize,
+    dropped: u64,
+    /// When the socket last refused bytes with frames still pending.
+    blocked_since: Option<Instant>,
+    dead: bool,
+}
+
+impl Client {
+    fn push(&mut self, frame: &Arc<[u8]>, max_backlog: usize) {
+        if self.backlog.len() >= max_backlog {
+            self.dropped += 1;
+        } else {
+            self.backlog.push_back(frame.clone());
+        }
+    }
+
+    /// Write as much of the backlog as the socket accepts right now.
+    fn flush(&mut self) {
+        while let Some(front) = self.backlog.front() {
+            match self.stream.write(&front[self.written..]) {
+                Ok(0) => {
+                    self.dead = true;
+                    return;
+                }
+                Ok(n) => {
+                    self.blocked_since = None;
+                    self.written += n;
+                    if self.written == front.len() {
+                        self.backlog.pop_front();
+                        self.written = 0;
+                    }
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                    self.blocked_since.get_or_insert_with(Instant::now);
+                    return;
+                }
+                Err(_) => {
+                    self.dead = true;
+                    return;
+                }
+            }
+        }
+        self.blocked_since = None;
+    }
+
+    fn stats(&self) -> ClientStats {
+        ClientStats {
+            client: self.id,
+            queue_depth: self.backlog.len() as u32,
+            dropped: self.dropped,
+        }
+    }
+}
+
+/// Writer thread: fan frames out to clients without ever blocking on one.
+fn write_frames(
+    queue: Receiver<Outgoing>,
+    accepted: &Mutex<Vec<(u64, UnixStream)>>,
+    stats: &Mutex<Vec<ClientStats>>,
+    policy: BackpressurePolicy,
+) {
+    let mut clients: Vec<Client> = Vec::new();
+    loop {
+        let first = match queue.recv_timeout(WRITER_POLL_INTERVAL) {
+            Ok(frame) => Some(frame),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+
+        clients.extend(
+            accepted
+                .lock()
+                .unwrap()
+                .drain(..)
+                .map(|(id, stream)| Client {
+                    id,
+                    stream,
+                    backlog: VecDeque::new(),
+                    written: 0,
+                    dropped: 0,
+                    blocked_since: None,
+                    dead: false,
+                }),
+        );
+
+        for frame in first
+            .into_iter()
+            .chain(std::iter::from_fn(|| queue.try_recv().ok()))
+        {
+            match frame {
+                Outgoing::Broadcast(frame) => {
+                    for client in &mut clients {
+                        client.push(&frame, policy.max_client_backlog);
+                    }
+                }
+                Outgoing::To(id, frame) => {
+                    if let Some(client) = clients.iter_mut().find(|c| c.id == id) {
+                        client.push(&frame, policy.max_client_backlog);
+                    }
+                }
+            }
+        }
+
+        for client in &mut clients {
+            client.flush();
+        }
+        clients.retain(|c| {
+            let stalled = c
+                .blocked_since
+                .is_some_and(|t| t.elapsed() > policy.stall_timeout);
+            if stalled {
+                let _ = c.stream.shutdown(std::net::Shutdown::Both);
+            }
+            !c.dead && !stalled
+        });
+        *stats.lock().unwrap() = clients.iter().map(Client::stats).collect();
+    }
+
+    // Best effort: deliver what is already buffered (e.g. Shutdown).
+    for client in &mut clients {
+        client.flush();
+    }
+}
+
+/// Per-client thread: decode command frames and queue them.
+///
+/// The socket is shared with the non-blocking writer, so wait for
+/// readability with `poll` rather than relying on blocking reads.
+fn read_commands(
+    client: u64,
+    mut stream: UnixStream,
+    queue: &Mutex<VecDeque<PendingCommand>>,
+    command_fd: &OwnedFd,
+) {
+    let mut decoder = FrameDecoder::new();
+    let mut buf = [0u8; 4096];
+    loop {
+        let mut pollfd = libc::pollfd {
+            fd: stream.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // SAFETY: `pollfd` is a valid, initialized array of length 1.
+        if unsafe { libc::poll(&mut pollfd, 1, -1) } < 0 {
+            if std::io::Error::last_os_error().kind() == ErrorKind::Interrupted {
+                continue;
+            }
+            return;
+        }
+        match stream.read(&mut buf) {
+            Ok(0) => return,
+            Ok(n) => decoder.feed(&buf[..n]),
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::Interrupted) => {
+                continue
+            }
+            Err(_) => return,
+        }
+        while let Some(message) = decoder.next_message() {
+            if let IpcMessage::Command { id, command } = message {
+                queue.lock().unwrap().push_back(PendingCommand {
+                    client,
+                    id,
+                    command,
+                });
+                let one = 1u64.to_ne_bytes();
+                // SAFETY: writes 8 bytes from a live buffer to our eventfd.
+                unsafe { libc::write(command_fd.as_raw_fd(), one.as_ptr().cast(), one.len()) };
+            }
+        }
+    }
+}
+
+/// Reply to a command sent with [`send_command`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandReply {
+    pub ok: bool,
+    pub detail: String,
+    /// Counters, for [`IpcCommand::QueryStats`].
+    pub stats: Option<SequencerStats>,
+}
+
+/// Send `command` to the sequencer at `address` and wait for its reply.
+pub fn send_command(
+    address: &str,
+    command: IpcCommand,
+    timeout: Duration,
+) -> std::io::Result<CommandReply> {
+    static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+
+    let mut stream = connect(address)?;
+    stream.write_all(&IpcMessage::Command { id, command }.encode())?;
+
+    let deadline = Instant::now() + timeout;
+    let mut decoder = FrameDecoder::new();
+    let mut stats = None;
+    let mut buf = [0u8; 4096];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(std::io::Error::new(
+                ErrorKind::TimedOut,
+                "sequencer did not answer",
+            ));
+        }
+        stream.set_read_timeout(Some(remaining))?;
+        let n = stream.read(&mut buf)?;
+        if n == 0 {
+            return Err(std::io::Error::new(
+                ErrorKind::UnexpectedEof,
+                "sequencer closed the connection",
+            ));
+        }
+        decoder.feed(&buf[..n]);
+        // Broadcasts interleave with the reply; keep only what is ours.
+        while let Some(message) = decoder.next_message() {
+            match message {
+                IpcMessage::StatsSnapshot(snapshot) => stats = Some(snapshot),
+                IpcMessage::CommandResult {
+                    id: reply_id,
+                    ok,
+                    detail,
+                } if reply_id == id => return Ok(CommandReply { ok, detail, stats }),
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all_messages() -> Vec<IpcMessage> {
+        vec![
+            IpcMessage::SlotCommitted {
+                slot: 7,
+                lamport_ts: 1 << 40,
+            },
+            IpcMessage::Checkpoint {
+                slot: 8,
+                lamport_ts: 99,
+            },
+            IpcMessage::WatermarkCrossed(WatermarkCrossing::High {
+                utilization_pct: 91.25,
+            }),
+            IpcMessage::WatermarkCrossed(WatermarkCrossing::Low {
+                utilization_pct: 12.5,
+            }),
+            IpcMessage::StatsSnapshot(SequencerStats {
+                events_processed: 1_000,
+                bytes_processed: 64_000,
+                ring_len: 512,
+                queue_dropped: 2,
+                clients: vec![ClientStats {
+                    client: 1,
+                    queue_depth: 3,
+                    dropped: 4,
+                }],
+            }),
+            IpcMessage::Shutdown,
+            IpcMessage::Command {
+                id: 9,
+                command: IpcCommand::Rollover {
+                    path: "/var/lib/cz/journal.1".into(),
+                },
+            },
+            IpcMessage::Command {
+                id: 10,
+                command: IpcCommand::SetRetention { max_events: 4096 },
+            },
+            IpcMessage::CommandResult {
+                id: 10,
+                ok: false,
+                detail: "ring is empty".into(),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_frame_roundtrip() {
+        for message in all_messages() {
+            let mut decoder = FrameDecoder::new();
+            decoder.feed(&message.encode());
+            assert_eq!(decoder.next_message(), Some(message.clone()));
+            assert_eq!(decoder.next_message(), None);
+        }
+    }
+
+    #[test]
+    fn test_decoder_handles_split_reads() {
+        let stream: Vec<u8> = all_messages().iter().flat_map(|m| m.encode()).collect();
+        let mut decoder = FrameDecoder::new();
+        let mut decoded = Vec::new();
+        for byte in stream {
+            decoder.feed(&[byte]);
+            decoded.extend(decoder.next_message());
+        }
+        assert_eq!(decoded, all_messages());
+        assert_eq!(decoder.skipped_bytes(), 0);
+    }
+
+    #[test]
+    fn test_decoder_resyncs_after_garbage() {
+        let message = IpcMessage::SlotCommitted {
+            slot: 3,
+            lamport_ts: 4,
+        };
+        let mut decoder = FrameDecoder::new();
+        // Legacy bare slot words, a truncated frame, and a bogus magic hit.
+        decoder.feed(&42u64.to_le_bytes());
+        decoder.feed(&message.encode()[..6]);
+        decoder.feed(b"CZIP\x01\x01\0\0\xff\xff\xff\xff");
+        decoder.feed(&message.encode());
+
+        assert_eq!(decoder.next_message(), Some(message.clone()));
+        assert_eq!(decoder.next_message(), None);
+        assert!(decoder.skipped_bytes() > 0);
+    }
+
+    #[test]
+    fn test_unknown_frames_are_skipped() {
+        let mut unknown = IpcMessage::Shutdown.encode();
+        unknown[5] = 0xEE;
+        let mut future = IpcMessage::Shutdown.encode();
+        future[4] = PROTOCOL_VERSION + 1;
+
+        let mut decoder = FrameDecoder::new();
+        decoder.feed(&unknown);
+        decoder.feed(&future);
+        decoder.feed(&IpcMessage::Shutdown.encode());
+        assert_eq!(decoder.next_message(), Some(IpcMessage::Shutdown));
+        assert_eq!(decoder.skipped_bytes(), 2 * FRAME_HEADER_SIZE as u64);
+    }
+
+    #[test]
+    fn test_command_roundtrip_over_socket() {
+        let path = std::env::temp_dir().join(format!("cz-ipc-{}.sock", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        let server = IpcServer::start(&path).unwrap();
+
+        // Stand-in for the event loop: wait on the eventfd, then answer.
+        let sequencer = thread::spawn(move || {
+            let mut signal = [0u8; 8];
+            // SAFETY: reads 8 bytes into a live buffer from our eventfd.
+            unsafe { libc::read(server.command_fd(), signal.as_mut_ptr().cast(), 8) };
+            for pending in server.take_commands() {
+                assert_eq!(pending.command, IpcCommand::QueryStats);
+                server.respond(
+                    pending.client,
+                    &IpcMessage::StatsSnapshot(SequencerStats {
+                        events_processed: 3,
+                        ..Default::default()
+                    }),
+                );
+                server.respond(
+                    pending.client,
+                    &IpcMessage::CommandResult {
+                        id: pending.id,
+                        ok: true,
+                        detail: "stats".into(),
+                    },
+                );
+            }
         });
+
+        let reply = send_command(&path, IpcCommand::QueryStats, Duration::from_secs(5)).unwrap();
+        sequencer.join().unwrap();
+        assert!(reply.ok);
+        assert_eq!(reply.stats.unwrap().events_processed, 3);
+        fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_unread_client_does_not_stall_broadcast() {
+        let path = std::env::temp_dir().join(format!("cz-ipc-slow-{}.sock", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        let policy = BackpressurePolicy {
+            queue_capacity: 1024,
+            max_client_backlog: 64,
+            stall_timeout: Duration::from_millis(750),
+        };
+        let server = IpcServer::start_with(&path, policy).unwrap();
+        let _stuck = UnixStream::connect(&path).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while server.client_stats().is_empty() {
+            assert!(Instant::now() < deadline, "client never registered");
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        // Far more than the socket buffer holds; none of it is ever read.
+        let start = Instant::now();
+        for slot in 0..200_000 {
+            server.broadcast(&IpcMessage::SlotCommitted {
+                slot,
+                lamport_ts: slot,
+            });
+        }
+        assert!(
+            start.elapsed() < Duration::from_secs(2),
+            "broadcast blocked on a slow client: {:?}",
+            start.elapsed()
+        );
+
+        // The writer sheds load for the client, then cuts it off.
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut saw_drops = false;
+        while Instant::now() < deadline {
+            let stats = server.client_stats();
+            saw_drops |= stats.iter().any(|c| c.dropped > 0);
+            if saw_drops && stats.is_empty() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(saw_drops, "no frames were dropped for the stalled client");
+        assert!(
+            server.client_stats().is_empty(),
+            "stalled client was not disconnected"
+        );
+        fs::remove_file(&path).ok();
+    }
+
+    /// Stand-in for an event loop: answer one `QueryStats` per signal.
+    fn answer_stats(server: &IpcServer, events_processed: u64) {
+        let mut signal = [0u8; 8];
+        // SAFETY: reads 8 bytes into a live buffer from our eventfd.
+        unsafe { libc::read(server.command_fd(), signal.as_mut_ptr().cast(), 8) };
+        for pending in server.take_commands() {
+            server.respond(
+                pending.client,
+                &IpcMessage::StatsSnapshot(SequencerStats {
+                    events_processed,
+                    ..Default::default()
+                }),
+            );
+            server.respond(
+                pending.client,
+                &IpcMessage::CommandResult {
+                    id: pending.id,
+                    ok: true,
+                    detail: "stats".into(),
+                },
+            );
+        }
+    }
+
+    #[test]
+    fn test_sequencers_side_by_side_on_abstract_sockets() {
+        let dir = std::env::temp_dir().join(format!("cz-ipc-pair-{}", std::process::id()));
+        let (a, b) = (dir.join("a/journal.db"), dir.join("b/journal.db"));
+        let (addr_a, addr_b) = (socket_for_journal(&a), socket_for_journal(&b));
+        assert!(addr_a.starts_with("@cz-io/journal.db-"));
+        assert_ne!(addr_a, addr_b, "same file name in two directories");
+
+        let server_a = IpcServer::start(&addr_a).unwrap();
+        let server_b = IpcServer::start(&addr_b).unwrap();
+        let taken = IpcServer::start(&addr_a).err().unwrap();
+        assert_eq!(taken.kind(), ErrorKind::AddrInUse);
+
+        let sequencers = [
+            thread::spawn(move || answer_stats(&server_a, 1)),
+            thread::spawn(move || answer_stats(&server_b, 2)),
+        ];
+        for (address, expected) in [(&addr_a, 1), (&addr_b, 2)] {
+            let reply =
+                send_command(address, IpcCommand::QueryStats, Duration::from_secs(5)).unwrap();
+            assert_eq!(reply.stats.unwrap().events_processed, expected);
+        }
+        for sequencer in sequencers {
+            sequencer.join().unwrap();
+        }
+
+        // Dropping a server releases its name for the next run.
+        drop(IpcServer::start(&addr_a).unwrap());
+    }
+
+    #[test]
+    fn test_stale_socket_removed_only_when_owner_dead() {
+        let path = std::env::temp_dir().join(format!("cz-ipc-stale-{}.sock", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        fs::remove_file(&path).ok();
+        // A socket file nobody listens on any more.
+        drop(UnixListener::bind(&path).unwrap());
+
+        // Its recorded owner (us) is alive: leave it alone.
+        fs::write(pid_file(&path), std::process::id().to_string()).unwrap();
+        let err = IpcServer::start(&path).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::AddrInUse);
+        assert!(Path::new(&path).exists());
+
+        // Once the owner has exited the socket is reclaimed.
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let dead = child.id();
+        child.wait().unwrap();
+        fs::write(pid_file(&path), dead.to_string()).unwrap();
+        let server = IpcServer::start(&path).unwrap();
+        assert_eq!(
+            fs::read_to_string(pid_file(&path)).unwrap(),
+            std::process::id().to_string()
+        );
+
+        drop(server);
+        assert!(!Path::new(&path).exists());
+        assert!(!pid_file(&path).exists());
     }
 }
@@ -1,38 +1,268 @@
 //! # Journal — Memory-Mapped Persistent Storage
 //!
 //! The journal is a single contiguous file mapped into virtual memory via `mmap`.
-//! It is split into two regions:
+//! It is split into three regions:
 //!
-//! - **Index Ring** (first 1 GiB): Fixed-size `CausalEvent` structs in a ring buffer.
-//! - **Blob Storage** (remainder): Variable-length payload data.
+//! - **Header** (first 4 KiB): Journal metadata such as the writer's live
+//!   cursor and named consumer offsets, starting with the magic `CZJH` and
+//!   the format version.
+//! - **Index Ring** (1 GiB unless chosen otherwise at creation): Fixed-size
+//!   `CausalEvent` structs in a ring buffer. Its size is recorded in the
+//!   header, so every process mapping the journal agrees on it. A
+//!   partitioned journal splits it into equal sub-rings, one per
+//!   `stream_id % partitions`, each with its own live cursor, so a flood
+//!   on one stream cannot evict another partition's events.
+//! - **Blob Storage** (remainder): Variable-length payload data. Each
+//!   payload is preceded by a 4-byte little-endian length prefix, so a
+//!   reader knows exactly where it ends.
 //!
 //! The file is pre-allocated at startup and never resized during operation.
 //! All I/O goes through the kernel's page cache — we do not copy data.
+//!
+//! Journals from before format version 1 have no header: their Index Ring
+//! starts at byte 0. They are refused with [`JournalError::Headerless`]
+//! (or [`JournalError::BadMagic`] if the first slot is in use) and left
+//! untouched, since stamping a header would overwrite live slots.
 
 use std::fs::{File, OpenOptions};
-use std::path::Path;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use memmap2::MmapMut;
 
 use cz_core::CausalEvent;
 
+use crate::checkpoint::{self, Checkpoint, CHECKPOINT_RECORD_SIZE};
+use crate::cursor::Cursor;
+use crate::ipc::MAX_SOCKET_ADDRESS_LEN;
+
 /// Default journal size: 100 GiB.
 pub const DEFAULT_JOURNAL_SIZE: u64 = 100 * 1024 * 1024 * 1024;
 
-/// Index ring size: 1 GiB.
-/// Contains `INDEX_RING_CAPACITY` events.
-pub const INDEX_RING_SIZE: usize = 1024 * 1024 * 1024;
+/// Header region size: 4 KiB.
+pub const HEADER_SIZE: usize = 4096;
+
+/// Maximum number of named consumers the header can persist.
+pub const MAX_CONSUMERS: usize = 32;
+
+/// Maximum consumer name length in bytes.
+pub const MAX_CONSUMER_NAME_LEN: usize = 24;
+
+/// Header magic: `CZJH`.
+const HEADER_MAGIC: [u8; 4] = *b"CZJH";
+const HEADER_VERSION: u32 = 1;
+/// Byte offset of the live cursor word within the header (8-byte aligned).
+const LIVE_CURSOR_OFFSET: usize = 16;
+/// Byte offset of the consumer table within the header.
+const CONSUMER_TABLE_OFFSET: usize = 64;
+/// Size of one consumer entry: name (24 bytes) + position (u64).
+const CONSUMER_ENTRY_SIZE: usize = MAX_CONSUMER_NAME_LEN + 8;
+/// Byte offset of the Index Ring size (u64 bytes) within the header.
+const INDEX_RING_SIZE_OFFSET: usize = 24;
+/// Byte offset of the IPC address field, just past the consumer table.
+const IPC_ADDRESS_OFFSET: usize = CONSUMER_TABLE_OFFSET + MAX_CONSUMERS * CONSUMER_ENTRY_SIZE;
+/// Byte offset of the partition count (u32) within the header.
+const PARTITION_COUNT_OFFSET: usize = 32;
+/// Byte offset of the slot of the newest checkpoint plus one (u64), 0 if
+/// none was recorded.
+const LAST_CHECKPOINT_OFFSET: usize = 40;
+/// Byte offset of the live cursor words of partitions 1 and up, past the
+/// IPC address. Partition 0 uses [`LIVE_CURSOR_OFFSET`].
+const PARTITION_CURSOR_OFFSET: usize =
+    (IPC_ADDRESS_OFFSET + 2 + MAX_SOCKET_ADDRESS_LEN).next_multiple_of(8);
+
+/// Most partitions an Index Ring may be split into.
+pub const MAX_PARTITIONS: usize = 64;
+
+/// Start of the Index Ring region.
+const INDEX_RING_START: usize = HEADER_SIZE;
+
+/// Size of the length prefix stored just before each payload in Blob
+/// Storage: the payload length as a little-endian `u32`.
+pub const PAYLOAD_LEN_PREFIX: usize = 4;
 
-/// Number of events that fit in the index ring.
-pub const INDEX_RING_CAPACITY: usize = INDEX_RING_SIZE / CausalEvent::size_bytes();
+/// Index Ring size of journals created by [`Journal::open`]: 1 GiB.
+/// Contains `DEFAULT_INDEX_RING_CAPACITY` events.
+pub const DEFAULT_INDEX_RING_SIZE: usize = 1024 * 1024 * 1024;
+
+/// Number of events that fit in the default Index Ring.
+pub const DEFAULT_INDEX_RING_CAPACITY: usize = DEFAULT_INDEX_RING_SIZE / CausalEvent::size_bytes();
+
+/// Most events an Index Ring may hold: the live cursor packs head and tail
+/// into one 64-bit word.
+pub const MAX_INDEX_RING_CAPACITY: usize = u32::MAX as usize;
+
+/// Smallest journal that holds the header and a default Index Ring (the
+/// blob region may be empty).
+pub const MIN_JOURNAL_SIZE: u64 = (HEADER_SIZE + DEFAULT_INDEX_RING_SIZE) as u64;
+
+/// Ordering of the writer's store to the live cursor word. Paired with
+/// [`LIVE_CURSOR_VIEW`]; the loom model in `loom_tests` checks the pair.
+pub(crate) const LIVE_CURSOR_PUBLISH: Ordering = Ordering::Release;
+
+/// Ordering of a reader's load of the live cursor word.
+pub(crate) const LIVE_CURSOR_VIEW: Ordering = Ordering::Acquire;
+
+/// Head in the low 32 bits, tail in the high 32.
+#[inline]
+pub(crate) fn pack_live_cursor(head: usize, tail: usize) -> u64 {
+    head as u64 | (tail as u64) << 32
+}
+
+/// Inverse of [`pack_live_cursor`]: `(head, tail)`.
+#[inline]
+pub(crate) fn unpack_live_cursor(word: u64) -> (usize, usize) {
+    ((word & u32::MAX as u64) as usize, (word >> 32) as usize)
+}
+
+/// Most failing slots a [`ScanReport`] lists per kind; the counts go on.
+pub const MAX_REPORTED_FAILURES: usize = 1000;
+
+/// Result of [`Journal::integrity_scan`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanReport {
+    /// Live events checked.
+    pub scanned: u64,
+    /// Events whose payload matches their checksum.
+    pub matched: u64,
+    /// Payload read, but its checksum differs.
+    pub mismatched: ScanFailures,
+    /// No payload could be read: its length prefix points outside Blob
+    /// Storage, or it is too short for the sender's header.
+    pub unreadable: ScanFailures,
+}
+
+impl ScanReport {
+    /// No event failed.
+    pub fn is_clean(&self) -> bool {
+        self.mismatched.count == 0 && self.unreadable.count == 0
+    }
+}
+
+/// Result of [`Journal::check_payload`] for one event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadCheck {
+    /// The payload matches the event's checksum.
+    Matched,
+    /// The payload was read, but its checksum differs.
+    Mismatched,
+    /// The length prefix or payload lies outside Blob Storage.
+    OutOfBounds,
+    /// The packet is too short to hold the sender's header.
+    Truncated,
+}
+
+/// One kind of scan failure.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanFailures {
+    pub count: u64,
+    /// The first [`MAX_REPORTED_FAILURES`] failing slots, in ring order.
+    pub slots: Vec<usize>,
+}
+
+/// Why a journal could not be opened.
+#[derive(Debug)]
+pub enum JournalError {
+    /// The journal would be (or on disk is) too small for the header and
+    /// the Index Ring.
+    TooSmall {
+        required: u64,
+        actual: u64,
+    },
+    /// The Index Ring size is not a whole number of slots between 2 and
+    /// [`MAX_INDEX_RING_CAPACITY`].
+    BadIndexRingSize(u64),
+    /// The partition count is not between 1 and [`MAX_PARTITIONS`], or
+    /// leaves a partition fewer than 2 slots.
+    BadPartitionCount(u64),
+    /// The header does not start with `CZJH`: not a journal file, or a
+    /// headerless one whose first slot holds an event.
+    BadMagic,
+    /// A journal without a header, written before format version 1 put
+    /// one in front of the Index Ring.
+    Headerless,
+    /// The header was written by an incompatible journal format.
+    UnsupportedVersion(u32),
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for JournalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JournalError::TooSmall { required, actual } => write!(
+                f,
+                "journal size {} bytes is too small: the header and Index Ring need at \
+                 least {} bytes",
+                actual, required
+            ),
+            JournalError::BadIndexRingSize(size) => write!(
+                f,
+                "invalid Index Ring size {} bytes: must be a multiple of {} holding 2 to {} \
+                 events",
+                size,
+                CausalEvent::size_bytes(),
+                MAX_INDEX_RING_CAPACITY
+            ),
+            JournalError::BadPartitionCount(count) => write!(
+                f,
+                "invalid partition count {}: must be 1 to {} with at least 2 slots each",
+                count, MAX_PARTITIONS
+            ),
+            JournalError::BadMagic => write!(f, "not a journal file (bad header magic)"),
+            JournalError::Headerless => write!(
+                f,
+                "journal has no header: it predates format version {} and its Index Ring \
+                 starts where the header now goes; re-create it to use it with this version",
+                HEADER_VERSION
+            ),
+            JournalError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported journal format version {} (expected {})",
+                version, HEADER_VERSION
+            ),
+            JournalError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for JournalError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            JournalError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for JournalError {
+    fn from(e: std::io::Error) -> Self {
+        JournalError::Io(e)
+    }
+}
 
 /// The memory-mapped journal file.
 ///
 /// Layout:
 /// ```text
-/// [0 .. INDEX_RING_SIZE)                → Index Ring (CausalEvent structs)
-/// [INDEX_RING_SIZE .. journal_size)      → Blob Storage (payload bytes)
+/// [0 .. HEADER_SIZE)                                → Header
+/// [HEADER_SIZE .. HEADER_SIZE + index_ring_size)    → Index Ring (CausalEvent structs)
+/// [HEADER_SIZE + index_ring_size .. journal_size)   → Blob Storage (payload bytes)
 /// ```
+///
+/// # Header Layout
+///
+/// | Offset | Size     | Field                                   |
+/// |--------|----------|-----------------------------------------|
+/// | 0      | 4        | magic (`CZJH`)                          |
+/// | 4      | 4        | version                                 |
+/// | 8      | 4        | consumer count                          |
+/// | 16     | 8        | live cursor: head (low 32) / tail (high 32) |
+/// | 24     | 8        | Index Ring size in bytes (0: 1 GiB)     |
+/// | 32     | 4        | partition count (0: 1)                  |
+/// | 64     | 32 × 32  | consumers: name (24, NUL-padded) + u64  |
+/// | 1088   | 2 + 107  | IPC address: length (u16) + UTF-8 bytes |
+/// | 1200   | 8 × 63   | live cursors of partitions 1 and up     |
 pub struct Journal {
     /// The mutable memory map over the journal file.
     mmap: MmapMut,
@@ -40,6 +270,19 @@ pub struct Journal {
     /// Total size of the journal in bytes.
     size: u64,
 
+    /// Size of the Index Ring region in bytes, as recorded in the header.
+    index_ring_size: usize,
+
+    /// Number of event slots in the Index Ring.
+    index_ring_capacity: usize,
+
+    /// Number of sub-rings the Index Ring is split into, as recorded in
+    /// the header.
+    partitions: usize,
+
+    /// Where the journal file lives.
+    path: PathBuf,
+
     /// The backing file (kept open for the lifetime of the journal).
     _file: File,
 }
@@ -47,55 +290,150 @@ pub struct Journal {
 impl Journal {
     /// Open (or create) a journal file at `path` with the given `size`.
     ///
-    /// The file is pre-allocated to `size` bytes and memory-mapped.
-    /// If the file already exists, it is opened and mapped as-is.
-    pub fn open(path: &Path, size: u64) -> std::io::Result<Self> {
+    /// A new (or empty) file is pre-allocated to `size` bytes and
+    /// memory-mapped. If the file already exists, it is opened and mapped
+    /// as-is: `size` never grows or truncates a journal another process may
+    /// have mapped. Either way the journal must be at least
+    /// [`MIN_JOURNAL_SIZE`] bytes, and an existing header must carry this
+    /// format's magic and version.
+    pub fn open(path: &Path, size: u64) -> Result<Self, JournalError> {
+        Self::open_with_ring_size(path, size, DEFAULT_INDEX_RING_SIZE)
+    }
+
+    /// [`Journal::open`], creating a new journal with an Index Ring of
+    /// `index_ring_size` bytes. An existing journal keeps the ring size in
+    /// its header; `index_ring_size` only applies to new files.
+    pub fn open_with_ring_size(
+        path: &Path,
+        size: u64,
+        index_ring_size: usize,
+    ) -> Result<Self, JournalError> {
+        Self::open_partitioned(path, size, index_ring_size, 1)
+    }
+
+    /// [`Journal::open_with_ring_size`], creating a new journal whose Index
+    /// Ring is split into `partitions` sub-rings. An existing journal keeps
+    /// the partitions in its header.
+    pub fn open_partitioned(
+        path: &Path,
+        size: u64,
+        index_ring_size: usize,
+        partitions: usize,
+    ) -> Result<Self, JournalError> {
+        let existing = match std::fs::metadata(path) {
+            Ok(metadata) => metadata.len(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => 0,
+            Err(e) => return Err(e.into()),
+        };
+        let size = if existing == 0 { size } else { existing };
+        let index_ring_size = if existing == 0 {
+            index_ring_size
+        } else if existing <= HEADER_SIZE as u64 {
+            return Err(JournalError::TooSmall {
+                required: (HEADER_SIZE + DEFAULT_INDEX_RING_SIZE) as u64,
+                actual: existing,
+            });
+        } else {
+            stored_index_ring_size(path)?
+        };
+        let index_ring_capacity = index_ring_capacity(index_ring_size)?;
+        if existing == 0 {
+            check_partitions(partitions, index_ring_capacity)?;
+        }
+        let required = (HEADER_SIZE + index_ring_size) as u64;
+        if size < required {
+            return Err(JournalError::TooSmall {
+                required,
+                actual: size,
+            });
+        }
+
         let file = OpenOptions::new()
             .read(true)
             .write(true)
             .create(true)
             .truncate(false)
             .open(path)?;
+        if existing == 0 {
+            file.set_len(size)?;
+        }
 
-        // Pre-allocate the file to the requested size.
-        file.set_len(size)?;
+        // SAFETY: Only one writer maps the file at a time. Readers in other
+        // processes (e.g. cz-hub) may map it concurrently and synchronise
+        // with the writer through the live cursor word in the header. The
+        // mmap is valid for the lifetime of `_file`.
+        let mut mmap = unsafe { MmapMut::map_mut(&file)? };
 
-        // SAFETY: We own the file exclusively. No other process should
-        // map the same file concurrently. The mmap is valid for the
-        // lifetime of `_file`.
-        let mmap = unsafe { MmapMut::map_mut(&file)? };
+        let magic: [u8; 4] = mmap[0..4].try_into().unwrap();
+        let partitions = match magic {
+            // Only a file this call created; an existing one was checked by
+            // `stored_index_ring_size` and is never stamped.
+            [0, 0, 0, 0] if existing == 0 => {
+                mmap[0..4].copy_from_slice(&HEADER_MAGIC);
+                mmap[4..8].copy_from_slice(&HEADER_VERSION.to_le_bytes());
+                mmap[INDEX_RING_SIZE_OFFSET..][..8]
+                    .copy_from_slice(&(index_ring_size as u64).to_le_bytes());
+                mmap[PARTITION_COUNT_OFFSET..][..4]
+                    .copy_from_slice(&(partitions as u32).to_le_bytes());
+                partitions
+            }
+            HEADER_MAGIC => {
+                let version = u32::from_le_bytes(mmap[4..8].try_into().unwrap());
+                if version != HEADER_VERSION {
+                    return Err(JournalError::UnsupportedVersion(version));
+                }
+                let stored =
+                    u32::from_le_bytes(mmap[PARTITION_COUNT_OFFSET..][..4].try_into().unwrap());
+                let partitions = stored.max(1) as usize;
+                check_partitions(partitions, index_ring_capacity)?;
+                partitions
+            }
+            _ => return Err(JournalError::BadMagic),
+        };
 
         Ok(Self {
             mmap,
             size,
+            index_ring_size,
+            index_ring_capacity,
+            partitions,
+            path: path.to_path_buf(),
             _file: file,
         })
     }
 
+    /// Start of the Blob Storage region.
+    #[inline]
+    fn blob_start(&self) -> usize {
+        INDEX_RING_START + self.index_ring_size
+    }
+
     /// Returns a mutable slice over the Index Ring region.
     /// This region contains `CausalEvent` structs packed contiguously.
     #[inline]
     pub fn index_ring_mut(&mut self) -> &mut [u8] {
-        &mut self.mmap[..INDEX_RING_SIZE]
+        let end = self.blob_start();
+        &mut self.mmap[INDEX_RING_START..end]
     }
 
     /// Returns a slice over the Index Ring region.
     #[inline]
     pub fn index_ring(&self) -> &[u8] {
-        &self.mmap[..INDEX_RING_SIZE]
+        &self.mmap[INDEX_RING_START..self.blob_start()]
     }
 
     /// Returns a mutable slice over the Blob Storage region.
     /// Payload data is written here, pointed to by `CausalEvent::payload_offset`.
     #[inline]
     pub fn blob_storage_mut(&mut self) -> &mut [u8] {
-        &mut self.mmap[INDEX_RING_SIZE..]
+        let start = self.blob_start();
+        &mut self.mmap[start..]
     }
 
     /// Returns a slice over the Blob Storage region.
     #[inline]
     pub fn blob_storage(&self) -> &[u8] {
-        &self.mmap[INDEX_RING_SIZE..]
+        &self.mmap[self.blob_start()..]
     }
 
     /// Returns the total journal size in bytes.
@@ -104,42 +442,1046 @@ impl Journal {
         self.size
     }
 
+    /// Returns the Index Ring size in bytes.
+    #[inline]
+    pub fn index_ring_size(&self) -> usize {
+        self.index_ring_size
+    }
+
+    /// Returns the number of event slots in the Index Ring.
+    #[inline]
+    pub fn index_ring_capacity(&self) -> usize {
+        self.index_ring_capacity
+    }
+
+    /// Number of partitions the Index Ring is split into; 1 for a single
+    /// ring.
+    #[inline]
+    pub fn partitions(&self) -> usize {
+        self.partitions
+    }
+
+    /// Number of event slots in each partition. Slots left over when the
+    /// Index Ring does not divide evenly are unused.
+    #[inline]
+    pub fn partition_capacity(&self) -> usize {
+        self.index_ring_capacity / self.partitions
+    }
+
+    /// Partition that events of `stream_id` are sequenced into.
+    #[inline]
+    pub fn partition_of(&self, stream_id: u16) -> usize {
+        stream_id as usize % self.partitions
+    }
+
     /// Returns the blob storage capacity in bytes.
     #[inline]
     pub fn blob_capacity(&self) -> usize {
-        self.size as usize - INDEX_RING_SIZE
+        self.size as usize - self.blob_start()
+    }
+
+    /// Record the length of the payload at `payload_offset` in its prefix.
+    /// Writers that receive a payload in place (the event loop) leave
+    /// [`PAYLOAD_LEN_PREFIX`] bytes free in front of it for this.
+    ///
+    /// # Panics
+    /// If `payload_offset` is less than [`PAYLOAD_LEN_PREFIX`] or past the
+    /// end of Blob Storage.
+    #[inline]
+    pub fn write_payload_len(&mut self, payload_offset: usize, len: u32) {
+        self.blob_storage_mut()[payload_offset - PAYLOAD_LEN_PREFIX..payload_offset]
+            .copy_from_slice(&len.to_le_bytes());
+    }
+
+    /// Copy `payload` into Blob Storage behind its length prefix, which
+    /// starts at `blob_offset`. Returns the `payload_offset` to record in
+    /// the event.
+    ///
+    /// # Panics
+    /// If the prefix and payload do not fit in Blob Storage at
+    /// `blob_offset`, or `payload` is longer than `u32::MAX` bytes.
+    pub fn write_payload(&mut self, blob_offset: usize, payload: &[u8]) -> u64 {
+        let len = u32::try_from(payload.len()).expect("payload length fits its prefix");
+        let start = blob_offset + PAYLOAD_LEN_PREFIX;
+        self.blob_storage_mut()[start..start + payload.len()].copy_from_slice(payload);
+        self.write_payload_len(start, len);
+        start as u64
+    }
+
+    /// The exact payload bytes of `event`: the length its prefix records,
+    /// or a checkpoint's record. A payload whose prefix or bytes lie outside
+    /// Blob Storage reads as empty.
+    pub fn read_payload(&self, event: &CausalEvent) -> &[u8] {
+        self.payload_range(event)
+            .map_or(&[], |range| &self.blob_storage()[range])
+    }
+
+    /// Where `event`'s packet lies in Blob Storage, or `None` if its length
+    /// prefix or bytes fall outside it.
+    fn payload_range(&self, event: &CausalEvent) -> Option<std::ops::Range<usize>> {
+        let blob = self.blob_storage();
+        let start = event.payload_offset as usize;
+        let len = if event.is_checkpoint() {
+            CHECKPOINT_RECORD_SIZE
+        } else {
+            match blob.get(start.checked_sub(PAYLOAD_LEN_PREFIX)?..start)? {
+                &[a, b, c, d] => u32::from_le_bytes([a, b, c, d]) as usize,
+                _ => return None,
+            }
+        };
+        let end = start.checked_add(len)?;
+        (end <= blob.len()).then_some(start..end)
+    }
+
+    /// Check one event against the bytes on disk, as
+    /// [`integrity_scan`](Self::integrity_scan) does for every live event.
+    pub fn check_payload(&self, event: &CausalEvent) -> PayloadCheck {
+        let Some(range) = self.payload_range(event) else {
+            return PayloadCheck::OutOfBounds;
+        };
+        match self.blob_storage()[range].get(CausalEvent::size_bytes()..) {
+            None => PayloadCheck::Truncated,
+            Some(payload) if crc32fast::hash(payload) != event.checksum => PayloadCheck::Mismatched,
+            Some(_) => PayloadCheck::Matched,
+        }
+    }
+
+    /// Check every live event against the bytes on disk: read its payload
+    /// by length prefix and recompute the checksum the sequencer verified
+    /// (CRC32 over the payload after the sender's 32-byte header).
+    ///
+    /// Checkpoints are checked the same way, over their record.
+    pub fn integrity_scan(&self, cursor: &Cursor) -> ScanReport {
+        let mut report = ScanReport::default();
+        for slot in cursor.slots() {
+            // SAFETY: live slots are in range and fully written.
+            let event = unsafe { self.read_event_at(slot) };
+            report.scanned += 1;
+            let failures = match self.check_payload(&event) {
+                PayloadCheck::OutOfBounds | PayloadCheck::Truncated => &mut report.unreadable,
+                PayloadCheck::Mismatched => &mut report.mismatched,
+                PayloadCheck::Matched => {
+                    report.matched += 1;
+                    continue;
+                }
+            };
+            failures.count += 1;
+            if failures.slots.len() < MAX_REPORTED_FAILURES {
+                failures.slots.push(slot);
+            }
+        }
+        report
     }
 
     /// Write a `CausalEvent` at a specific slot index in the Index Ring.
     ///
     /// # Safety
-    /// Caller must ensure `slot < INDEX_RING_CAPACITY`.
+    /// Caller must ensure `slot < self.index_ring_capacity()`.
     #[inline]
     pub unsafe fn write_event_at(&mut self, slot: usize, event: &CausalEvent) {
-        let offset = slot * CausalEvent::size_bytes();
+        let offset = INDEX_RING_START + slot * CausalEvent::size_bytes();
         let dst = &mut self.mmap[offset..offset + CausalEvent::size_bytes()];
-        // Zero-copy: reinterpret the struct as bytes and copy into mmap.
-        let src = std::slice::from_raw_parts(
-            event as *const CausalEvent as *const u8,
-            CausalEvent::size_bytes(),
-        );
-        dst.copy_from_slice(src);
+        // Same bytes as the in-memory struct on little-endian targets, but
+        // with the trailing pad zeroed instead of left uninitialised.
+        dst.copy_from_slice(&event.to_bytes());
     }
 
     /// Read a `CausalEvent` from a specific slot index in the Index Ring.
     ///
     /// # Safety
-    /// Caller must ensure `slot < INDEX_RING_CAPACITY` and that valid
+    /// Caller must ensure `slot < self.index_ring_capacity()` and that valid
     /// data was previously written at this slot.
     #[inline]
     pub unsafe fn read_event_at(&self, slot: usize) -> CausalEvent {
-        let offset = slot * CausalEvent::size_bytes();
+        let offset = INDEX_RING_START + slot * CausalEvent::size_bytes();
         let src = &self.mmap[offset..offset + CausalEvent::size_bytes()];
         std::ptr::read(src.as_ptr() as *const CausalEvent)
     }
 
+    /// Bounds-checked read of the event at `slot`.
+    ///
+    /// Returns `None` if `slot` lies outside the Index Ring. A slot that was
+    /// never written reads as the all-zero event.
+    pub fn event_at(&self, slot: usize) -> Option<CausalEvent> {
+        if slot >= self.index_ring_capacity {
+            return None;
+        }
+        // SAFETY: `slot` is in range, and every bit pattern is a valid
+        // `CausalEvent` (it holds only integers).
+        Some(unsafe { self.read_event_at(slot) })
+    }
+
+    /// The header word holding the packed head/tail of `partition`.
+    #[inline]
+    fn live_cursor_word(&self, partition: usize) -> &AtomicU64 {
+        assert!(partition < self.partitions, "partition out of range");
+        let offset = match partition {
+            0 => LIVE_CURSOR_OFFSET,
+            p => PARTITION_CURSOR_OFFSET + (p - 1) * 8,
+        };
+        // SAFETY: the mapping is page-aligned and at least HEADER_SIZE long,
+        // so the word is in bounds and 8-byte aligned. It is only ever
+        // accessed atomically, including from other processes mapping the
+        // same file.
+        unsafe { &*(self.mmap.as_ptr().add(offset) as *const AtomicU64) }
+    }
+
+    /// Publish the writer's cursor to readers of this journal. A sub-ring
+    /// cursor from [`partition_cursor_view`](Self::partition_cursor_view)
+    /// is published as its partition's.
+    ///
+    /// Call only after every slot in `[tail, head)` has been written; the
+    /// release store makes those writes visible to any reader that observes
+    /// the new positions.
+    #[inline]
+    pub fn publish_cursor(&self, cursor: &Cursor) {
+        let word = pack_live_cursor(cursor.head(), cursor.tail());
+        let partition = cursor.base() / self.partition_capacity();
+        self.live_cursor_word(partition)
+            .store(word, LIVE_CURSOR_PUBLISH);
+    }
+
+    /// Snapshot of the cursor last published by the writer; partition 0's
+    /// in a partitioned journal.
+    ///
+    /// Every slot in the returned live window was fully written before the
+    /// snapshot was taken. A journal that was never published (or holds
+    /// out-of-range positions) reads as an empty ring.
+    pub fn live_cursor_view(&self) -> Cursor {
+        self.partition_cursor_view(0)
+            .expect("every journal has partition 0")
+    }
+
+    /// [`live_cursor_view`](Self::live_cursor_view) of one partition, whose
+    /// slots start at `partition * partition_capacity()`, or `None` if the
+    /// journal has no such partition.
+    pub fn partition_cursor_view(&self, partition: usize) -> Option<Cursor> {
+        if partition >= self.partitions {
+            return None;
+        }
+        let word = self.live_cursor_word(partition).load(LIVE_CURSOR_VIEW);
+        let (head, tail) = unpack_live_cursor(word);
+        let capacity = self.partition_capacity();
+        let cursor = if head >= capacity || tail >= capacity {
+            Cursor::new(capacity)
+        } else {
+            Cursor::restore(capacity, head, tail)
+        };
+        Some(cursor.with_base(partition * capacity))
+    }
+
+    /// Named consumer offsets persisted in the header.
+    ///
+    /// Returns an empty list if the header has never been written.
+    pub fn consumer_offsets(&self) -> Vec<(String, usize)> {
+        let header = &self.mmap[..HEADER_SIZE];
+        if header[0..4] != HEADER_MAGIC {
+            return Vec::new();
+        }
+        let count = u32::from_le_bytes(header[8..12].try_into().unwrap()) as usize;
+
+        (0..count.min(MAX_CONSUMERS))
+            .map(|i| {
+                let entry = &header[CONSUMER_TABLE_OFFSET + i * CONSUMER_ENTRY_SIZE..]
+                    [..CONSUMER_ENTRY_SIZE];
+                let name_bytes = &entry[..MAX_CONSUMER_NAME_LEN];
+                let name_len = name_bytes
+                    .iter()
+                    .position(|&b| b == 0)
+                    .unwrap_or(MAX_CONSUMER_NAME_LEN);
+                let name = String::from_utf8_lossy(&name_bytes[..name_len]).into_owned();
+                let position =
+                    u64::from_le_bytes(entry[MAX_CONSUMER_NAME_LEN..].try_into().unwrap()) as usize;
+                (name, position)
+            })
+            .collect()
+    }
+
+    /// Persist named consumer offsets into the header, replacing any
+    /// previously stored table.
+    pub fn persist_consumer_offsets(
+        &mut self,
+        consumers: &[(String, usize)],
+    ) -> std::io::Result<()> {
+        if consumers.len() > MAX_CONSUMERS {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("at most {} consumers can be persisted", MAX_CONSUMERS),
+            ));
+        }
+        if let Some((name, _)) = consumers
+            .iter()
+            .find(|(name, _)| name.len() > MAX_CONSUMER_NAME_LEN)
+        {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "consumer name '{}' exceeds {} bytes",
+                    name, MAX_CONSUMER_NAME_LEN
+                ),
+            ));
+        }
+
+        let header = &mut self.mmap[..HEADER_SIZE];
+        header[0..4].copy_from_slice(&HEADER_MAGIC);
+        header[4..8].copy_from_slice(&HEADER_VERSION.to_le_bytes());
+        header[8..12].copy_from_slice(&(consumers.len() as u32).to_le_bytes());
+
+        let table = &mut header[CONSUMER_TABLE_OFFSET..];
+        table[..MAX_CONSUMERS * CONSUMER_ENTRY_SIZE].fill(0);
+        for (i, (name, position)) in consumers.iter().enumerate() {
+            let entry = &mut table[i * CONSUMER_ENTRY_SIZE..(i + 1) * CONSUMER_ENTRY_SIZE];
+            entry[..name.len()].copy_from_slice(name.as_bytes());
+            entry[MAX_CONSUMER_NAME_LEN..].copy_from_slice(&(*position as u64).to_le_bytes());
+        }
+        Ok(())
+    }
+
+    /// Path the journal was opened from.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Record the address the sequencer serving this journal listens on.
+    pub fn publish_ipc_address(&mut self, address: &str) -> std::io::Result<()> {
+        if address.len() > MAX_SOCKET_ADDRESS_LEN {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("IPC address exceeds {} bytes", MAX_SOCKET_ADDRESS_LEN),
+            ));
+        }
+        let field = &mut self.mmap[IPC_ADDRESS_OFFSET..][..2 + MAX_SOCKET_ADDRESS_LEN];
+        field.fill(0);
+        field[..2].copy_from_slice(&(address.len() as u16).to_le_bytes());
+        field[2..2 + address.len()].copy_from_slice(address.as_bytes());
+        Ok(())
+    }
+
+    /// Address published by the sequencer, if one ever ran on this journal.
+    pub fn ipc_address(&self) -> Option<String> {
+        decode_ipc_address(&self.mmap[..HEADER_SIZE])
+    }
+
+    /// Logical index (counted from the tail) of the first live event with a
+    /// Lamport timestamp of at least `lamport_ts`, or `cursor.len()` if
+    /// there is none.
+    ///
+    /// The live window is ordered by `lamport_ts` from tail to head, so
+    /// this is a binary search. Probing by logical index rather than slot
+    /// keeps a window that wraps past the end of the ring contiguous.
+    pub fn find_first_ge(&self, cursor: &Cursor, lamport_ts: u64) -> usize {
+        let (mut lo, mut hi) = (0, cursor.len());
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let slot = cursor.slot_at(mid).expect("mid is below len");
+            // SAFETY: live slots are in range and fully written.
+            let event = unsafe { self.read_event_at(slot) };
+            if event.lamport_ts < lamport_ts {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        lo
+    }
+
+    /// Write a checkpoint into `slot`, the slot just claimed at the head of
+    /// `cursor`, covering the ring from `segment_start` up to it. The blob
+    /// record goes to `blob_offset`.
+    pub fn write_checkpoint(
+        &mut self,
+        cursor: &Cursor,
+        slot: usize,
+        node_id: u32,
+        lamport_ts: u64,
+        segment_start: usize,
+        blob_offset: usize,
+    ) -> Checkpoint {
+        let state = Checkpoint {
+            head: cursor.head() as u64,
+            tail: cursor.tail() as u64,
+            lamport_watermark: lamport_ts + 1,
+            segment_start: segment_start as u64,
+            segment_crc: checkpoint::segment_crc(self.index_ring(), segment_start, slot),
+        };
+        let (event, record) =
+            checkpoint::encode_event(node_id, lamport_ts, blob_offset as u64, &state);
+        self.blob_storage_mut()[blob_offset..blob_offset + CHECKPOINT_RECORD_SIZE]
+            .copy_from_slice(&record);
+        // SAFETY: the caller claimed `slot`, so it is in range and unread.
+        unsafe { self.write_event_at(slot, &event) };
+        self.mmap[LAST_CHECKPOINT_OFFSET..][..8].copy_from_slice(&(slot as u64 + 1).to_le_bytes());
+        state
+    }
+
+    /// Newest live slot holding a readable checkpoint, scanning back from
+    /// the head.
+    pub fn last_checkpoint(&self, cursor: &Cursor) -> Option<usize> {
+        (0..cursor.len()).rev().find_map(|i| {
+            let slot = cursor.slot_at(i)?;
+            // SAFETY: live slots are in range and fully written.
+            let event = unsafe { self.read_event_at(slot) };
+            checkpoint::read(self.blob_storage(), &event).map(|_| slot)
+        })
+    }
+
+    /// Find the newest valid checkpoint: the one the header records, or
+    /// if that no longer validates (or none is recorded), the first valid
+    /// one scanning back from there or from the published head.
+    ///
+    /// Returns the slot holding it and its decoded state. State up to that
+    /// checkpoint can be trusted; anything written after it is discarded
+    /// when a cursor is restored from it.
+    pub fn recover_checkpoint(&self) -> Option<(usize, Checkpoint)> {
+        let recorded =
+            u64::from_le_bytes(self.mmap[LAST_CHECKPOINT_OFFSET..][..8].try_into().unwrap());
+        let from = match recorded {
+            0 => self.live_cursor_view().head(),
+            slot_after => slot_after as usize,
+        };
+        // The slot before `from`, wrapping.
+        let newest = (from + self.index_ring_capacity - 1) % self.index_ring_capacity;
+        checkpoint::find_last_valid(self.index_ring(), self.blob_storage(), newest)
+    }
+
     /// Flush the mmap to disk.
     pub fn flush(&self) -> std::io::Result<()> {
         self.mmap.flush()
     }
+
+    /// Flush, then write a byte-for-byte copy of the journal to `dest`.
+    pub fn snapshot_to(&self, dest: &Path) -> std::io::Result<()> {
+        self.flush()?;
+        std::fs::write(dest, &self.mmap[..])
+    }
+
+    /// Create a journal at `dest` holding only the live events of this one
+    /// and their payloads, at the same slots and blob offsets, with the same
+    /// published cursors, so a checkpoint whose segment is still live stays
+    /// valid. Returns the number of events copied.
+    ///
+    /// `dest` must not exist yet. Freed slots and blob space read as zeros,
+    /// so the new file is sparse where this one holds old data.
+    pub fn copy_live_to(&self, dest: &Path) -> Result<usize, JournalError> {
+        if dest.exists() {
+            return Err(JournalError::Io(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("{} already exists", dest.display()),
+            )));
+        }
+        let mut copy =
+            Journal::open_partitioned(dest, self.size, self.index_ring_size, self.partitions)?;
+        let mut copied = 0;
+        for partition in 0..self.partitions {
+            let cursor = self
+                .partition_cursor_view(partition)
+                .expect("partition in range");
+            for slot in cursor.slots() {
+                // SAFETY: live slots are in range and fully written.
+                let event = unsafe { self.read_event_at(slot) };
+                let payload = self.read_payload(&event);
+                let start = event.payload_offset as usize;
+                if event.is_checkpoint() {
+                    copy.blob_storage_mut()[start..start + payload.len()].copy_from_slice(payload);
+                } else if !payload.is_empty() {
+                    copy.write_payload(start - PAYLOAD_LEN_PREFIX, payload);
+                }
+                // SAFETY: both journals have the same Index Ring.
+                unsafe { copy.write_event_at(slot, &event) };
+                copied += 1;
+            }
+            copy.publish_cursor(&cursor);
+        }
+        copy.flush()?;
+        Ok(copied)
+    }
+}
+
+/// Slots in an Index Ring of `index_ring_size` bytes, if that is a valid size.
+fn index_ring_capacity(index_ring_size: usize) -> Result<usize, JournalError> {
+    let slot = CausalEvent::size_bytes();
+    let capacity = index_ring_size / slot;
+    if !index_ring_size.is_multiple_of(slot) || !(2..=MAX_INDEX_RING_CAPACITY).contains(&capacity) {
+        return Err(JournalError::BadIndexRingSize(index_ring_size as u64));
+    }
+    Ok(capacity)
+}
+
+/// Whether an Index Ring of `capacity` slots can be split `partitions` ways.
+fn check_partitions(partitions: usize, capacity: usize) -> Result<(), JournalError> {
+    if !(1..=MAX_PARTITIONS).contains(&partitions) || capacity / partitions < 2 {
+        return Err(JournalError::BadPartitionCount(partitions as u64));
+    }
+    Ok(())
+}
+
+/// The Index Ring size of an existing journal of `len` bytes, read from
+/// its header. Journals written before the size was recorded have the
+/// default 1 GiB ring.
+///
+/// A file starting with zeros rather than the header magic is taken for a
+/// headerless journal, whose first slot is empty or holds Lamport time 0.
+fn stored_index_ring_size(path: &Path) -> Result<usize, JournalError> {
+    let mut header = [0u8; INDEX_RING_SIZE_OFFSET + 8];
+    File::open(path)?.read_exact(&mut header)?;
+    let magic: [u8; 4] = header[0..4].try_into().unwrap();
+    let size = match magic {
+        HEADER_MAGIC => u64::from_le_bytes(header[INDEX_RING_SIZE_OFFSET..].try_into().unwrap()),
+        [0, 0, 0, 0] => return Err(JournalError::Headerless),
+        _ => return Err(JournalError::BadMagic),
+    };
+    match size {
+        0 => Ok(DEFAULT_INDEX_RING_SIZE),
+        size => usize::try_from(size).map_err(|_| JournalError::BadIndexRingSize(size)),
+    }
+}
+
+/// Read the published IPC address straight from a journal file's header,
+/// without mapping (or resizing) the journal.
+pub fn read_ipc_address(path: &Path) -> std::io::Result<Option<String>> {
+    let mut header = vec![0u8; HEADER_SIZE];
+    File::open(path)?.read_exact(&mut header)?;
+    Ok(decode_ipc_address(&header))
+}
+
+fn decode_ipc_address(header: &[u8]) -> Option<String> {
+    let field = &header[IPC_ADDRESS_OFFSET..][..2 + MAX_SOCKET_ADDRESS_LEN];
+    let len = u16::from_le_bytes([field[0], field[1]]) as usize;
+    if len == 0 || len > MAX_SOCKET_ADDRESS_LEN {
+        return None;
+    }
+    String::from_utf8(field[2..2 + len].to_vec()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_journal(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("cz-io-{}-{}.db", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_consumer_offsets_survive_reopen() {
+        let path = temp_journal("consumers");
+        let size = MIN_JOURNAL_SIZE + 4096;
+        {
+            let mut journal = Journal::open(&path, size).unwrap();
+            assert!(journal.consumer_offsets().is_empty());
+            journal
+                .persist_consumer_offsets(&[("hub".into(), 7), ("mirror".into(), 3)])
+                .unwrap();
+            journal.flush().unwrap();
+        }
+
+        let journal = Journal::open(&path, size).unwrap();
+        assert_eq!(
+            journal.consumer_offsets(),
+            vec![("hub".to_string(), 7), ("mirror".to_string(), 3)]
+        );
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_ipc_address_readable_without_mapping() {
+        let path = temp_journal("ipc-address");
+        let size = MIN_JOURNAL_SIZE + 4096;
+        let mut journal = Journal::open(&path, size).unwrap();
+        assert_eq!(journal.ipc_address(), None);
+        assert_eq!(read_ipc_address(&path).unwrap(), None);
+
+        journal
+            .publish_ipc_address("@cz-io/journal.db-0badf00d")
+            .unwrap();
+        journal.flush().unwrap();
+        assert_eq!(
+            read_ipc_address(&path).unwrap().as_deref(),
+            Some("@cz-io/journal.db-0badf00d")
+        );
+        assert!(journal
+            .publish_ipc_address(&"x".repeat(MAX_SOCKET_ADDRESS_LEN + 1))
+            .is_err());
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_find_first_ge_matches_linear_scan() {
+        let path = temp_journal("find-first-ge");
+        let size = MIN_JOURNAL_SIZE + 4096;
+        let mut journal = Journal::open(&path, size).unwrap();
+
+        // xorshift keeps the data random but reproducible.
+        let mut state = 0x9e37_79b9_7f4a_7c15u64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        for round in 0..20 {
+            let len = (next() % 300) as usize;
+            // Every other round the window straddles the end of the ring.
+            let tail = if round % 2 == 0 {
+                DEFAULT_INDEX_RING_CAPACITY - len / 2 - 1
+            } else {
+                (next() % 1000) as usize
+            };
+            let cursor = Cursor::restore(
+                DEFAULT_INDEX_RING_CAPACITY,
+                (tail + len) % DEFAULT_INDEX_RING_CAPACITY,
+                tail,
+            );
+
+            // Sorted timestamps with runs of duplicates.
+            let mut ts = next() % 10;
+            let mut written = Vec::with_capacity(len);
+            for slot in cursor.slots() {
+                ts += next() % 3;
+                unsafe { journal.write_event_at(slot, &CausalEvent::new(ts, 1, 0, 0, 0)) };
+                written.push(ts);
+            }
+
+            let max = written.last().copied().unwrap_or(0);
+            for target in 0..=max + 2 {
+                let linear = written
+                    .iter()
+                    .position(|&t| t >= target)
+                    .unwrap_or(written.len());
+                assert_eq!(
+                    journal.find_first_ge(&cursor, target),
+                    linear,
+                    "round {} target {}",
+                    round,
+                    target
+                );
+            }
+        }
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_payloads_read_back_exactly_by_prefix() {
+        let path = temp_journal("payload-prefix");
+        let mut journal = Journal::open(&path, MIN_JOURNAL_SIZE + 4096).unwrap();
+        let first = b"first payload";
+        let second = [0u8; 3];
+
+        let first_offset = journal.write_payload(0, first);
+        assert_eq!(first_offset, PAYLOAD_LEN_PREFIX as u64);
+        let next = first_offset as usize + first.len();
+        let second_offset = journal.write_payload(next, &second);
+
+        let read = |journal: &Journal, offset| {
+            journal
+                .read_payload(&CausalEvent::new(1, 1, 0, offset, 0))
+                .to_vec()
+        };
+        assert_eq!(read(&journal, first_offset), first);
+        assert_eq!(read(&journal, second_offset), second);
+
+        // The event loop receives in place and fills the prefix afterwards.
+        let offset = 100;
+        journal.blob_storage_mut()[offset..offset + 5].copy_from_slice(b"abcde");
+        journal.write_payload_len(offset, 4);
+        assert_eq!(read(&journal, offset as u64), b"abcd");
+
+        // Lengths never reach past Blob Storage.
+        journal.write_payload_len(4092, 5);
+        assert!(read(&journal, 4092).is_empty());
+        assert!(read(&journal, 2).is_empty());
+        assert!(read(&journal, 1 << 20).is_empty());
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_integrity_scan_flags_corrupted_slots() {
+        let path = temp_journal("integrity-scan");
+        let mut journal = Journal::open(&path, MIN_JOURNAL_SIZE + 4096).unwrap();
+        let mut cursor = Cursor::new(journal.index_ring_capacity());
+        let mut blob_offset = 0;
+        let mut offsets = Vec::new();
+        for ts in 0..4u8 {
+            let payload = [ts; 20];
+            let mut packet = CausalEvent::new(0, 1, 0, 0, 0).to_bytes().to_vec();
+            packet.extend(payload);
+            let offset = journal.write_payload(blob_offset, &packet);
+            blob_offset = offset as usize + packet.len();
+            offsets.push(offset as usize);
+
+            let event = CausalEvent::new(ts as u64, 1, 0, offset, crc32fast::hash(&payload));
+            let slot = cursor.advance_head().unwrap();
+            unsafe { journal.write_event_at(slot, &event) };
+        }
+        let slot = cursor.advance_head().unwrap();
+        journal.write_checkpoint(&cursor, slot, 9, 10, 0, blob_offset);
+
+        let clean = journal.integrity_scan(&cursor);
+        assert!(clean.is_clean());
+        assert_eq!((clean.scanned, clean.matched), (5, 5));
+
+        // Flip one payload byte, past the header copy.
+        journal.blob_storage_mut()[offsets[2] + 40] ^= 0x01;
+        // And point another event's prefix past the end of Blob Storage.
+        journal.write_payload_len(offsets[0], 1 << 20);
+
+        let report = journal.integrity_scan(&cursor);
+        assert_eq!(report.matched, 3);
+        assert_eq!(report.mismatched.slots, [2]);
+        assert_eq!(report.unreadable.slots, [0]);
+        assert_eq!((report.mismatched.count, report.unreadable.count), (1, 1));
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_copy_live_to_keeps_only_live_events() {
+        let path = temp_journal("copy-live-source");
+        let dest = temp_journal("copy-live-dest");
+        let size = MIN_JOURNAL_SIZE + 4096;
+        let mut journal = Journal::open(&path, size).unwrap();
+        let mut cursor = Cursor::new(journal.index_ring_capacity());
+        let mut blob_offset = 0;
+        for ts in 0..4u8 {
+            let payload = [ts; 20];
+            let offset = journal.write_payload(blob_offset, &payload);
+            blob_offset = offset as usize + payload.len();
+            let event = CausalEvent::new(ts as u64, 1, 0, offset, crc32fast::hash(&payload));
+            let slot = cursor.advance_head().unwrap();
+            unsafe { journal.write_event_at(slot, &event) };
+        }
+        let slot = cursor.advance_head().unwrap();
+        journal.write_checkpoint(&cursor, slot, 9, 10, 1, blob_offset);
+        cursor.advance_tail();
+        journal.publish_cursor(&cursor);
+
+        assert_eq!(journal.copy_live_to(&dest).unwrap(), 4);
+        assert!(journal.copy_live_to(&dest).is_err());
+
+        let copy = Journal::open(&dest, 0).unwrap();
+        let live = copy.live_cursor_view();
+        assert_eq!((live.tail(), live.head()), (1, 5));
+        for slot in live.slots() {
+            let (a, b) = unsafe { (journal.read_event_at(slot), copy.read_event_at(slot)) };
+            assert_eq!(a.to_bytes(), b.to_bytes());
+            assert_eq!(journal.read_payload(&a), copy.read_payload(&b));
+        }
+        // The dropped event and its payload were not copied.
+        assert_eq!(copy.event_at(0).unwrap().to_bytes(), [0; 32]);
+        assert!(copy.blob_storage()[..24].iter().all(|&b| b == 0));
+        assert_eq!(copy.recover_checkpoint().map(|(slot, _)| slot), Some(4));
+        std::fs::remove_file(&path).ok();
+        std::fs::remove_file(&dest).ok();
+    }
+
+    #[test]
+    fn test_last_checkpoint_finds_newest_marker() {
+        let path = temp_journal("last-checkpoint");
+        let size = MIN_JOURNAL_SIZE + 4096;
+        let mut journal = Journal::open(&path, size).unwrap();
+        let mut cursor = Cursor::new(DEFAULT_INDEX_RING_CAPACITY);
+        let write = |journal: &mut Journal, cursor: &mut Cursor, n: u64| {
+            for ts in 0..n {
+                let slot = cursor.advance_head().unwrap();
+                unsafe { journal.write_event_at(slot, &CausalEvent::new(ts, 1, 0, 0, 0)) };
+            }
+        };
+
+        write(&mut journal, &mut cursor, 3);
+        assert_eq!(journal.last_checkpoint(&cursor), None);
+
+        let mut segment_start = 0;
+        for blob_offset in [0, CHECKPOINT_RECORD_SIZE] {
+            let slot = cursor.advance_head().unwrap();
+            journal.write_checkpoint(&cursor, slot, 9, 100, segment_start, blob_offset);
+            segment_start = slot + 1;
+            write(&mut journal, &mut cursor, 2);
+        }
+
+        let slot = journal.last_checkpoint(&cursor).unwrap();
+        assert_eq!(slot, 6);
+        let event = unsafe { journal.read_event_at(slot) };
+        let checkpoint =
+            checkpoint::verify(journal.index_ring(), journal.blob_storage(), slot, &event).unwrap();
+        assert_eq!((checkpoint.segment_start, checkpoint.head), (4, 7));
+        // Recovery starts at the slot the header recorded, not the ring's end.
+        assert_eq!(journal.recover_checkpoint(), Some((6, checkpoint)));
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_live_cursor_view_never_ahead_of_writes() {
+        const EVENTS: usize = 5_000;
+        let path = temp_journal("live-cursor");
+        let size = MIN_JOURNAL_SIZE + 4096;
+        // Two independent mappings stand in for the sequencer and the hub.
+        let mut writer = Journal::open(&path, size).unwrap();
+        let reader = Journal::open(&path, size).unwrap();
+        assert!(reader.live_cursor_view().is_empty());
+
+        let handle = std::thread::spawn(move || {
+            let mut cursor = Cursor::new(DEFAULT_INDEX_RING_CAPACITY);
+            for i in 0..EVENTS {
+                let slot = cursor.advance_head().unwrap();
+                let event = CausalEvent::new(i as u64 + 1, 1, 0, 0, 0);
+                unsafe { writer.write_event_at(slot, &event) };
+                writer.publish_cursor(&cursor);
+            }
+        });
+
+        loop {
+            let view = reader.live_cursor_view();
+            for slot in view.slots() {
+                let event = unsafe { reader.read_event_at(slot) };
+                assert_eq!(
+                    event.lamport_ts,
+                    slot as u64 + 1,
+                    "slot {} not committed",
+                    slot
+                );
+            }
+            if view.len() == EVENTS {
+                break;
+            }
+        }
+        handle.join().unwrap();
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_event_at_is_bounds_checked() {
+        let path = temp_journal("event-at");
+        let mut journal = Journal::open(&path, MIN_JOURNAL_SIZE + 4096).unwrap();
+        let event = CausalEvent::new(9, 2, 1, 64, 0xFEED);
+        unsafe { journal.write_event_at(5, &event) };
+
+        let read = journal.event_at(5).unwrap();
+        assert_eq!((read, read.checksum), (event, 0xFEED));
+        assert_eq!(journal.event_at(6).unwrap().lamport_ts, 0);
+        assert!(journal.event_at(DEFAULT_INDEX_RING_CAPACITY).is_none());
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_open_rejects_too_small_journals() {
+        let path = temp_journal("too-small");
+        std::fs::remove_file(&path).ok();
+
+        // The hub's old default of 2 MiB cannot hold the Index Ring.
+        let err = Journal::open(&path, 2 * 1024 * 1024).err().unwrap();
+        assert!(matches!(
+            err,
+            JournalError::TooSmall { required: MIN_JOURNAL_SIZE, actual } if actual == 2 * 1024 * 1024
+        ));
+        assert!(!path.exists(), "a rejected journal is not created");
+
+        std::fs::write(&path, [0u8; 4096]).unwrap();
+        let err = Journal::open(&path, DEFAULT_JOURNAL_SIZE).err().unwrap();
+        assert!(matches!(err, JournalError::TooSmall { actual: 4096, .. }));
+        assert!(err.to_string().contains("too small"), "{}", err);
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_open_checks_header_and_keeps_existing_size() {
+        let path = temp_journal("header");
+        let size = MIN_JOURNAL_SIZE + 4096;
+        drop(Journal::open(&path, size).unwrap());
+
+        // Reopening with another size maps the file as it is.
+        let journal = Journal::open(&path, 2 * 1024 * 1024).unwrap();
+        assert_eq!(journal.size(), size);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), size);
+        drop(journal);
+
+        let mut header = std::fs::read(&path).unwrap();
+        header[4..8].copy_from_slice(&7u32.to_le_bytes());
+        std::fs::write(&path, &header).unwrap();
+        let err = Journal::open(&path, size).err().unwrap();
+        assert!(matches!(err, JournalError::UnsupportedVersion(7)));
+
+        header[0..4].copy_from_slice(b"ELF\x7f");
+        std::fs::write(&path, &header).unwrap();
+        let err = Journal::open(&path, size).err().unwrap();
+        assert!(matches!(err, JournalError::BadMagic));
+        assert_eq!(err.to_string(), "not a journal file (bad header magic)");
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_headerless_journal_is_refused_untouched() {
+        let path = temp_journal("headerless");
+        // The pre-header layout: slot 0 of the Index Ring at byte 0, here
+        // an event with Lamport timestamp 0, so the file starts with zeros.
+        let file = File::create(&path).unwrap();
+        file.set_len(DEFAULT_INDEX_RING_SIZE as u64 + 4096).unwrap();
+        drop(file);
+        let event = CausalEvent::new(0, 3, 7, 0, 0xabcd);
+        std::fs::OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .and_then(|mut f| std::io::Write::write_all(&mut f, &event.to_bytes()))
+            .unwrap();
+
+        let err = Journal::open(&path, DEFAULT_JOURNAL_SIZE).err().unwrap();
+        assert!(matches!(err, JournalError::Headerless));
+        assert!(err.to_string().contains("no header"), "{}", err);
+
+        let mut head = [0u8; 64];
+        File::open(&path).unwrap().read_exact(&mut head).unwrap();
+        assert_eq!(head[..CausalEvent::size_bytes()], event.to_bytes());
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_index_ring_size_is_per_journal() {
+        let path = temp_journal("ring-size");
+        std::fs::remove_file(&path).ok();
+        let ring = 64 * CausalEvent::size_bytes();
+        let size = (HEADER_SIZE + ring + 4096) as u64;
+        {
+            let mut journal = Journal::open_with_ring_size(&path, size, ring).unwrap();
+            assert_eq!(journal.index_ring_size(), ring);
+            assert_eq!(journal.index_ring_capacity(), 64);
+            assert_eq!(journal.index_ring().len(), ring);
+            assert_eq!(journal.blob_capacity(), 4096);
+            assert_eq!(journal.live_cursor_view().capacity(), 64);
+
+            let event = CausalEvent::new(3, 1, 0, 0, 0);
+            unsafe { journal.write_event_at(63, &event) };
+            assert_eq!(journal.event_at(63), Some(event));
+            assert!(journal.event_at(64).is_none());
+            journal.blob_storage_mut()[0] = 0xAB;
+            journal.flush().unwrap();
+        }
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(
+            bytes[HEADER_SIZE + ring],
+            0xAB,
+            "blobs start after the ring"
+        );
+
+        // The header's ring size wins over the one asked for.
+        let journal = Journal::open(&path, size).unwrap();
+        assert_eq!(journal.index_ring_capacity(), 64);
+        assert_eq!(journal.event_at(63).unwrap().lamport_ts, 3);
+        drop(journal);
+        std::fs::remove_file(&path).ok();
+
+        // Journals from before the size was recorded have the default ring.
+        drop(Journal::open(&path, MIN_JOURNAL_SIZE).unwrap());
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[INDEX_RING_SIZE_OFFSET..][..8].fill(0);
+        std::fs::write(&path, &bytes).unwrap();
+        let journal = Journal::open_with_ring_size(&path, MIN_JOURNAL_SIZE, ring).unwrap();
+        assert_eq!(journal.index_ring_capacity(), DEFAULT_INDEX_RING_CAPACITY);
+        drop(journal);
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_open_validates_index_ring_size() {
+        let path = temp_journal("bad-ring-size");
+        std::fs::remove_file(&path).ok();
+        let slot = CausalEvent::size_bytes();
+
+        for bad in [0, slot, 10 * slot + 1] {
+            let err = Journal::open_with_ring_size(&path, MIN_JOURNAL_SIZE, bad)
+                .err()
+                .unwrap();
+            assert!(
+                matches!(err, JournalError::BadIndexRingSize(size) if size == bad as u64),
+                "{}",
+                err
+            );
+        }
+
+        // The ring has to fit in the file along with the header.
+        let ring = 1024 * slot;
+        let err = Journal::open_with_ring_size(&path, (HEADER_SIZE + ring - 1) as u64, ring)
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err,
+            JournalError::TooSmall { required, .. } if required == (HEADER_SIZE + ring) as u64
+        ));
+        assert!(!path.exists());
+        let journal = Journal::open_with_ring_size(&path, (HEADER_SIZE + ring) as u64, ring);
+        assert_eq!(journal.unwrap().blob_capacity(), 0);
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_flooded_partition_keeps_other_partitions() {
+        let path = temp_journal("partitions");
+        std::fs::remove_file(&path).ok();
+        let ring = 64 * CausalEvent::size_bytes();
+        let size = (HEADER_SIZE + ring) as u64;
+        for bad in [0, 33, MAX_PARTITIONS + 1] {
+            let err = Journal::open_partitioned(&path, size, ring, bad)
+                .err()
+                .unwrap();
+            assert!(matches!(err, JournalError::BadPartitionCount(_)), "{}", err);
+        }
+        assert!(!path.exists());
+
+        let journal = Journal::open_partitioned(&path, size, ring, 3).unwrap();
+        assert_eq!(
+            (journal.partitions(), journal.partition_capacity()),
+            (3, 21)
+        );
+        assert_eq!(journal.partition_of(7), 1);
+        let mut cursors: Vec<Cursor> = (0..3)
+            .map(|p| journal.partition_cursor_view(p).unwrap())
+            .collect();
+        assert!(journal.partition_cursor_view(3).is_none());
+        drop(journal);
+
+        // Reopened with default options, the header's partitions win.
+        let mut journal = Journal::open(&path, size).unwrap();
+        assert_eq!(journal.partitions(), 3);
+
+        // A quiet stream in partition 0, then a flood on stream 1 that
+        // drops its own oldest events as the sequencer's retention would.
+        let mut ts = 0;
+        for stream_id in std::iter::repeat_n(3, 5).chain(std::iter::repeat_n(1, 500)) {
+            ts += 1;
+            let cursor = &mut cursors[journal.partition_of(stream_id)];
+            if cursor.is_full() {
+                cursor.advance_tail();
+            }
+            let slot = cursor.advance_head().unwrap();
+            unsafe { journal.write_event_at(slot, &CausalEvent::new(ts, 1, stream_id, 0, 0)) };
+            journal.publish_cursor(cursor);
+        }
+
+        let quiet = journal.partition_cursor_view(0).unwrap();
+        let kept: Vec<u64> = quiet
+            .slots()
+            .map(|slot| journal.event_at(slot).unwrap().lamport_ts)
+            .collect();
+        assert_eq!(kept, [1, 2, 3, 4, 5]);
+        assert_eq!(journal.live_cursor_view().len(), 5);
+
+        let flooded = journal.partition_cursor_view(1).unwrap();
+        assert_eq!(flooded.len(), 20);
+        assert!(flooded.slots().all(|slot| (21..42).contains(&slot)));
+        assert_eq!(journal.find_first_ge(&flooded, 0), 0);
+        let newest = flooded.slot_at(19).unwrap();
+        assert_eq!(journal.event_at(newest).unwrap().lamport_ts, 505);
+        assert!(journal.partition_cursor_view(2).unwrap().is_empty());
+        drop(journal);
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_rejects_oversized_consumer_name() {
+        let path = temp_journal("long-name");
+        let mut journal = Journal::open(&path, MIN_JOURNAL_SIZE + 4096).unwrap();
+        let name = "x".repeat(MAX_CONSUMER_NAME_LEN + 1);
+        assert!(journal.persist_consumer_offsets(&[(name, 0)]).is_err());
+        std::fs::remove_file(&path).ok();
+    }
 }
@@ -3,7 +3,12 @@
 //! Single-threaded event loop that treats the disk as RAM.
 //! Memory-mapped journal, ring buffer topology, raw io_uring I/O.
 
+pub mod checkpoint;
 pub mod cursor;
 pub mod event_loop;
 pub mod ipc;
 pub mod journal;
+pub mod watermark;
+
+#[cfg(all(test, loom))]
+mod loom_tests;
@@ -0,0 +1,159 @@
+//! # Loom Model of the Live Cursor
+//!
+//! The sequencer writes Index Ring slots with plain stores into the mmap
+//! and then publishes its head with [`LIVE_CURSOR_PUBLISH`]; the hub takes
+//! snapshots with [`LIVE_CURSOR_VIEW`] and reads every slot in the window.
+//! Nothing else orders the two, so those two orderings carry the whole
+//! protocol. Loom explores every interleaving and weak-memory outcome of a
+//! small model of it: slot contents are `loom::cell::UnsafeCell`s, so a
+//! read that is not ordered after the write it observes fails the model
+//! as a data race, exactly the torn read the hub must never do.
+//!
+//! Each slot also carries the commit word from `cz_core::slot`, written
+//! after the event as the framing prescribes. Run with:
+//!
+//! ```text
+//! RUSTFLAGS="--cfg loom" cargo test -p cz-io --release test_loom
+//! ```
+//!
+//! Slot reuse after the tail advances is outside this model: a reader
+//! holding an old snapshot can race a rewrite of a slot it still sees as
+//! live, which is what the slot commit words are for.
+
+use loom::cell::UnsafeCell;
+use loom::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use loom::sync::Arc;
+use loom::thread;
+
+use cz_core::slot::commit_word;
+
+use crate::cursor::Cursor;
+use crate::journal::{pack_live_cursor, unpack_live_cursor, LIVE_CURSOR_PUBLISH, LIVE_CURSOR_VIEW};
+
+/// Large enough that `EVENTS` appends never wrap.
+const CAPACITY: usize = 4;
+const EVENTS: usize = 2;
+
+struct Ring {
+    live: AtomicU64,
+    events: [UnsafeCell<u64>; CAPACITY],
+    commits: [AtomicU32; CAPACITY],
+}
+
+/// What the reader does beyond the protocol, to show the model catches it.
+#[derive(Clone, Copy, PartialEq)]
+enum Reader {
+    /// Only read slots inside the snapshot window.
+    FollowsProtocol,
+    /// Also read the slot at the head once its commit word shows up.
+    TrustsCommitWordPastHead,
+}
+
+fn model(publish: Ordering, view: Ordering, reader: Reader) {
+    loom::model(move || {
+        let ring = Arc::new(Ring {
+            live: AtomicU64::new(pack_live_cursor(0, 0)),
+            events: std::array::from_fn(|_| UnsafeCell::new(0)),
+            commits: std::array::from_fn(|_| AtomicU32::new(0)),
+        });
+
+        let writer = {
+            let ring = ring.clone();
+            thread::spawn(move || {
+                let mut cursor = Cursor::new(CAPACITY);
+                for i in 0..EVENTS {
+                    let slot = cursor.advance_head().unwrap();
+                    // Plain stores into the mmap: the event, then its
+                    // commit word. Neither orders anything on its own.
+                    ring.events[slot].with_mut(|event| unsafe { *event = i as u64 + 1 });
+                    ring.commits[slot].store(commit_word(i as u32), Ordering::Relaxed);
+                    // Release: every store above happens-before any load
+                    // that reads this value of the cursor word.
+                    ring.live
+                        .store(pack_live_cursor(cursor.head(), cursor.tail()), publish);
+                }
+            })
+        };
+
+        let mut last_head = 0;
+        for _ in 0..2 {
+            // Acquire: pairs with the writer's release, so everything the
+            // writer did before publishing `head` is visible from here on.
+            let (head, tail) = unpack_live_cursor(ring.live.load(view));
+            let snapshot = Cursor::restore(CAPACITY, head, tail);
+
+            // A later snapshot never shows a head before slots already
+            // read as committed: the word's modification order is total
+            // and a thread never reads backwards in it.
+            assert!(
+                head >= last_head,
+                "head went back from {} to {}",
+                last_head,
+                head
+            );
+
+            for slot in snapshot.slots() {
+                // Inside the window the acquire above already covers the
+                // slot's writes: its commit word is there even with a
+                // relaxed load, and reading the event is not a race.
+                assert_eq!(
+                    ring.commits[slot].load(Ordering::Relaxed),
+                    commit_word(slot as u32),
+                    "slot {} below head {} is not committed",
+                    slot,
+                    head
+                );
+                let event = ring.events[slot].with(|event| unsafe { *event });
+                assert_eq!(event, slot as u64 + 1);
+            }
+
+            // Past the head the commit word may already be visible, but
+            // seeing a relaxed store orders nothing: reading the event
+            // here races the writer, and loom reports it.
+            if reader == Reader::TrustsCommitWordPastHead
+                && head < EVENTS
+                && ring.commits[head].load(Ordering::Relaxed) == commit_word(head as u32)
+            {
+                ring.events[head].with(|event| unsafe { *event });
+            }
+            last_head = head;
+        }
+
+        writer.join().unwrap();
+    });
+}
+
+#[test]
+fn test_loom_live_cursor_snapshots_see_committed_slots() {
+    model(
+        LIVE_CURSOR_PUBLISH,
+        LIVE_CURSOR_VIEW,
+        Reader::FollowsProtocol,
+    );
+}
+
+#[test]
+#[should_panic(expected = "Causality violation")]
+fn test_loom_reading_past_the_head_races_the_writer() {
+    model(
+        LIVE_CURSOR_PUBLISH,
+        LIVE_CURSOR_VIEW,
+        Reader::TrustsCommitWordPastHead,
+    );
+}
+
+#[test]
+#[should_panic(expected = "is not committed")]
+fn test_loom_relaxed_publish_lets_readers_see_unwritten_slots() {
+    model(Ordering::Relaxed, LIVE_CURSOR_VIEW, Reader::FollowsProtocol);
+}
+
+#[test]
+#[should_panic(expected = "is not committed")]
+fn test_loom_relaxed_view_lets_readers_see_unwritten_slots() {
+    model(
+        LIVE_CURSOR_PUBLISH,
+        Ordering::Relaxed,
+        Reader::FollowsProtocol,
+    );
+}
@@ -0,0 +1,158 @@
+//! # Watermarks — Ring Utilization Threshold Crossings
+//!
+//! Tracks Index Ring utilization against a high and a low watermark and
+//! reports each crossing exactly once. The two thresholds form a hysteresis
+//! band: after crossing the high mark, nothing more fires until utilization
+//! has dropped to the low mark, so a ring hovering around a single threshold
+//! does not flap.
+
+use crate::cursor::Cursor;
+
+/// Utilization thresholds, in percent of ring capacity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WatermarkPolicy {
+    /// Crossing back down to this level clears a high-watermark condition.
+    pub low_pct: f64,
+    /// Reaching this level raises a high-watermark condition.
+    pub high_pct: f64,
+}
+
+impl Default for WatermarkPolicy {
+    fn default() -> Self {
+        Self {
+            low_pct: 70.0,
+            high_pct: 90.0,
+        }
+    }
+}
+
+/// A single threshold crossing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WatermarkCrossing {
+    /// Utilization rose to or above the high watermark.
+    High { utilization_pct: f64 },
+    /// Utilization fell to or below the low watermark after a high crossing.
+    Low { utilization_pct: f64 },
+}
+
+/// Debounced watermark tracker.
+pub struct Watermarks {
+    policy: WatermarkPolicy,
+    /// `true` between a high crossing and the following low crossing.
+    elevated: bool,
+    callback: Option<Box<dyn FnMut(WatermarkCrossing) + Send>>,
+}
+
+impl Watermarks {
+    /// # Panics
+    /// Panics unless `low_pct < high_pct`.
+    pub fn new(policy: WatermarkPolicy) -> Self {
+        assert!(
+            policy.low_pct < policy.high_pct,
+            "Low watermark must be below the high watermark"
+        );
+        Self {
+            policy,
+            elevated: false,
+            callback: None,
+        }
+    }
+
+    /// Invoke `callback` on every crossing in addition to returning it.
+    pub fn on_crossing(mut self, callback: impl FnMut(WatermarkCrossing) + Send + 'static) -> Self {
+        self.callback = Some(Box::new(callback));
+        self
+    }
+
+    /// Switch to `policy`, keeping whether the tracker is elevated; the
+    /// next observation is judged against the new thresholds.
+    ///
+    /// # Panics
+    /// Panics unless `low_pct < high_pct`.
+    pub fn set_policy(&mut self, policy: WatermarkPolicy) {
+        assert!(
+            policy.low_pct < policy.high_pct,
+            "Low watermark must be below the high watermark"
+        );
+        self.policy = policy;
+    }
+
+    /// Returns `true` while above the high watermark and not yet cleared.
+    pub fn is_elevated(&self) -> bool {
+        self.elevated
+    }
+
+    /// Check the cursor's utilization, reporting a crossing if one occurred.
+    pub fn observe(&mut self, cursor: &Cursor) -> Option<WatermarkCrossing> {
+        self.observe_pct(cursor.utilization_pct())
+    }
+
+    /// Like [`observe`](Self::observe) for an already computed utilization.
+    pub fn observe_pct(&mut self, utilization_pct: f64) -> Option<WatermarkCrossing> {
+        let crossing = if !self.elevated && utilization_pct >= self.policy.high_pct {
+            self.elevated = true;
+            WatermarkCrossing::High { utilization_pct }
+        } else if self.elevated && utilization_pct <= self.policy.low_pct {
+            self.elevated = false;
+            WatermarkCrossing::Low { utilization_pct }
+        } else {
+            return None;
+        };
+
+        if let Some(callback) = self.callback.as_mut() {
+            callback(crossing);
+        }
+        Some(crossing)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_one_notification_per_crossing() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        let mut marks = Watermarks::new(WatermarkPolicy {
+            low_pct: 30.0,
+            high_pct: 70.0,
+        })
+        .on_crossing(move |c| sink.lock().unwrap().push(c));
+
+        let mut cursor = Cursor::new(11);
+        for _round in 0..3 {
+            // Fill to 100%, passing the high mark once.
+            while cursor.advance_head().is_some() {
+                marks.observe(&cursor);
+            }
+            // Drain to empty, passing the low mark once.
+            while cursor.advance_tail().is_some() {
+                marks.observe(&cursor);
+            }
+        }
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 6);
+        for pair in seen.chunks(2) {
+            assert!(
+                matches!(pair[0], WatermarkCrossing::High { utilization_pct } if utilization_pct >= 70.0)
+            );
+            assert!(
+                matches!(pair[1], WatermarkCrossing::Low { utilization_pct } if utilization_pct <= 30.0)
+            );
+        }
+    }
+
+    #[test]
+    fn test_hovering_between_marks_does_not_refire() {
+        let mut marks = Watermarks::new(WatermarkPolicy::default());
+        assert!(marks.observe_pct(95.0).is_some());
+        assert!(marks.observe_pct(85.0).is_none());
+        assert!(marks.observe_pct(92.0).is_none());
+        assert!(marks.is_elevated());
+        assert!(marks.observe_pct(60.0).is_some());
+        assert!(marks.observe_pct(10.0).is_none());
+    }
+}
@@ -1,7 +1,9 @@
 //! # cz-verify — The "Law" of LACRIMOSA
 //!
 //! Formal verification proofs using Kani model checker.
-//! We do not write unit tests; we write mathematical proofs.
+//! We do not write unit tests; we write mathematical proofs. Each proof is
+//! mirrored by a property test in [`properties`] that runs under plain
+//! `cargo test`, at input sizes far beyond what Kani can explore.
 //!
 //! # Proof: Monotonicity
 //!
@@ -9,11 +11,66 @@
 //! guarantees that Lamport timestamps are monotonically non-decreasing.
 //! This proves that no matter what random garbage the network throws at us,
 //! our sorting algorithm **cannot** violate causality.
+//!
+//! # Proof: Sorting Moves Whole Events
+//!
+//! Sorting never separates an event's ordering key from its
+//! `payload_offset` and `checksum`, and never drops or duplicates events,
+//! even among events whose keys tie.
+//!
+//! # Proof: Slot Occupancy
+//!
+//! A reader only ever sees a framed Index Ring slot as occupied when its
+//! commit word and CRC both check out: corrupting any one byte of a
+//! committed slot, or tearing a write at any byte boundary, never yields a
+//! slot that passes for a complete one.
+//!
+//! # Proof: HLC Merge Monotonicity
+//!
+//! Every timestamp the hybrid logical clock issues, whether ticking
+//! locally or merging a remote timestamp, is strictly after the previous
+//! one and no more than the configured drift ahead of wall time, however
+//! the wall clock moves, backwards included.
+//!
+//! # Proof: Tail Never Passes Head
+//!
+//! However head and tail advances interleave, the tail stays behind the
+//! head and `len()` is exactly the number of successful head advances
+//! minus successful tail advances.
+//!
+//! # Proof: Batch Reservations
+//!
+//! A batch reserved on the Index Ring cursor only ever covers free slots,
+//! so committing it can never overwrite unread events, and aborting it
+//! leaves the cursor exactly as it was.
+//! Across a whole sequence of reservations, committed or aborted in any
+//! order, no slot is ever handed out twice while it is live.
+//!
+//! # Proof: Replays Keep the Target Ordered
+//!
+//! A batch appended to an ordered ring (as `/api/replay` does), whether
+//! its timestamps are preserved or resequenced, leaves the ring's
+//! `lamport_ts` non-decreasing in commit order, from tail to head, wherever
+//! the ring wraps. A preserved batch that would break the order is refused
+//! untouched, and stamping never changes anything but `lamport_ts` and the
+//! resequenced flag.
 
 extern crate cz_core;
+extern crate cz_io;
+
+#[cfg(test)]
+mod properties;
 
 #[cfg(kani)]
-use cz_core::CausalEvent;
+use cz_core::append::{stamp_for_append, AppendMode};
+#[cfg(kani)]
+use cz_core::hlc::{self, HlcClock};
+#[cfg(kani)]
+use cz_core::slot::{self, SLOT_SIZE};
+#[cfg(kani)]
+use cz_core::{CausalEvent, FLAG_RESEQUENCED};
+#[cfg(kani)]
+use cz_io::cursor::Cursor;
 
 /// Kani proof harness: verify that sorting CausalEvents by our Ord
 /// implementation produces a monotonically non-decreasing sequence
@@ -94,6 +151,354 @@ mod proofs {
             );
         }
     }
+
+    /// **Proof: Sorting Moves Whole Events**
+    ///
+    /// `Ord` looks only at `(lamport_ts, node_id, stream_id)`, so a sort is
+    /// free to reorder events whose keys tie. Whatever order it picks, each
+    /// event must travel as a unit: tagging 3 symbolic events with distinct
+    /// `payload_offset`s, every sorted event still carries the key and
+    /// checksum it started with, and each tag appears exactly once.
+    #[kani::proof]
+    fn verify_sort_keeps_payload_pairing() {
+        let original = [0u64, 1, 2]
+            .map(|tag| CausalEvent::new(kani::any(), kani::any(), kani::any(), tag, kani::any()));
+        let mut events = original;
+
+        events.sort();
+
+        assert!(events.len() == original.len(), "Sort changed event count");
+        let mut seen = [false; 3];
+        for event in &events {
+            let tag = event.payload_offset as usize;
+            assert!(tag < original.len(), "Sort invented a payload_offset");
+            assert!(!seen[tag], "Sort duplicated an event");
+            seen[tag] = true;
+
+            let before = &original[tag];
+            assert!(
+                event.lamport_ts == before.lamport_ts
+                    && event.node_id == before.node_id
+                    && event.stream_id == before.stream_id
+                    && event.checksum == before.checksum,
+                "Sort split an event's key from its payload"
+            );
+        }
+    }
+
+    /// **Proof: Occupied Means Commit Word and CRC Check Out**
+    ///
+    /// For any 40 symbolic slot bytes, `slot_occupied` holds only if the
+    /// trailing word is this sequence's commit word and the embedded CRC
+    /// is the CRC32C of the 32 event bytes, checked byte by byte.
+    #[kani::proof]
+    #[kani::unwind(33)]
+    fn verify_slot_occupied_requires_commit_and_crc() {
+        let bytes: [u8; SLOT_SIZE] = kani::any();
+        let seq: u32 = kani::any();
+
+        if slot::slot_occupied(&bytes, seq) {
+            let crc = slot::crc32c(&bytes[..32]).to_le_bytes();
+            let word = slot::commit_word(seq).to_le_bytes();
+            assert!(bytes[32..36] == crc, "Occupied slot with a bad CRC");
+            assert!(bytes[36..40] == word, "Occupied slot for another lap");
+        }
+    }
+
+    /// **Proof: Corrupted Slots Are Never Occupied**
+    ///
+    /// Flipping any bits of any single byte of a committed slot, whether
+    /// in the event, the CRC, or the commit word, makes it unoccupied.
+    #[kani::proof]
+    #[kani::unwind(33)]
+    fn verify_corrupted_slot_not_occupied() {
+        let seq: u32 = kani::any();
+        let mut bytes = slot::encode_slot(&any_event(), seq);
+        let index: usize = kani::any();
+        let mask: u8 = kani::any();
+        kani::assume(index < SLOT_SIZE && mask != 0);
+
+        bytes[index] ^= mask;
+
+        assert!(
+            !slot::slot_occupied(&bytes, seq),
+            "Corruption went unnoticed"
+        );
+    }
+
+    /// **Proof: Torn Writes Are Never Half-Read**
+    ///
+    /// Writing a slot for `seq` over arbitrary bytes not committed for
+    /// `seq`, and stopping after any prefix, leaves a slot that is either
+    /// unoccupied or already byte-for-byte the complete new slot.
+    #[kani::proof]
+    #[kani::unwind(33)]
+    fn verify_torn_slot_never_half_read() {
+        let seq: u32 = kani::any();
+        let new = slot::encode_slot(&any_event(), seq);
+        let old: [u8; SLOT_SIZE] = kani::any();
+        kani::assume(old[36..40] != slot::commit_word(seq).to_le_bytes());
+        let written: usize = kani::any();
+        kani::assume(written < SLOT_SIZE);
+
+        let mut torn = old;
+        torn[..written].copy_from_slice(&new[..written]);
+
+        if slot::slot_occupied(&torn, seq) {
+            assert!(torn == new, "Torn slot reported occupied");
+        }
+    }
+
+    /// **Proof: HLC Merges Are Monotonic and Drift-Bounded**
+    ///
+    /// From any clock state, observing any remote timestamp at any wall
+    /// time either issues a timestamp strictly after the previous output
+    /// and within `max_drift_ms` of the wall clock, or refuses and leaves
+    /// the clock exactly as it was.
+    #[kani::proof]
+    fn verify_hlc_observe() {
+        let max_drift_ms: u64 = kani::any();
+        let mut clock = HlcClock::resume(kani::any(), max_drift_ms);
+        let before = clock;
+        let remote: u64 = kani::any();
+        let wall_ms: u64 = kani::any();
+
+        match clock.observe(remote, wall_ms) {
+            Ok(ts) => {
+                assert!(ts > before.last(), "HLC went backwards");
+                assert!(ts > remote, "HLC not after the remote timestamp");
+                assert!(
+                    hlc::millis(ts) <= wall_ms.saturating_add(max_drift_ms),
+                    "HLC ran past the drift bound"
+                );
+                assert!(clock.last() == ts);
+            }
+            Err(_) => assert!(clock == before, "Refusal changed the clock"),
+        }
+    }
+
+    /// **Proof: HLC Stays Monotonic Across Wall-Clock Jumps**
+    ///
+    /// Three steps, each a tick or a merge, with wall readings that may
+    /// jump forwards or backwards by up to a second between steps: every
+    /// issued timestamp is above all earlier ones.
+    #[kani::proof]
+    #[kani::unwind(4)]
+    fn verify_hlc_sequence() {
+        let max_drift_ms: u64 = kani::any();
+        kani::assume(max_drift_ms <= 1_000);
+        let mut clock = HlcClock::new(max_drift_ms);
+        let mut wall_ms: u64 = kani::any();
+        kani::assume(wall_ms <= hlc::MAX_MILLIS);
+        let mut previous = 0u64;
+
+        for _ in 0..3 {
+            let step: i16 = kani::any();
+            kani::assume((-1_000..=1_000).contains(&step));
+            wall_ms = wall_ms.saturating_add_signed(step as i64);
+
+            let result = if kani::any() {
+                clock.tick(wall_ms)
+            } else {
+                clock.observe(kani::any(), wall_ms)
+            };
+            if let Ok(ts) = result {
+                assert!(ts > previous, "HLC issued a non-increasing timestamp");
+                assert!(hlc::millis(ts) <= wall_ms.saturating_add(max_drift_ms));
+                previous = ts;
+            }
+            assert!(clock.last() == previous);
+        }
+    }
+
+    /// Generate a cursor over a small ring with arbitrary valid positions.
+    fn any_cursor() -> Cursor {
+        let capacity: usize = kani::any();
+        kani::assume((2..=5).contains(&capacity));
+        let head: usize = kani::any();
+        let tail: usize = kani::any();
+        kani::assume(head < capacity && tail < capacity);
+        Cursor::restore(capacity, head, tail)
+    }
+
+    /// **Proof: Tail Never Passes Head**
+    ///
+    /// Mirrors the harness in `cz_io::cursor` through the public API only:
+    /// up to 6 symbolic head/tail advances on a 4-slot ring.
+    #[kani::proof]
+    #[kani::unwind(7)]
+    fn verify_tail_never_passes_head() {
+        let mut cursor = Cursor::new(4);
+        let mut head_advances = 0usize;
+        let mut tail_advances = 0usize;
+
+        for _ in 0..6 {
+            if kani::any() {
+                if cursor.advance_head().is_some() {
+                    head_advances += 1;
+                }
+            } else {
+                let was_empty = cursor.is_empty();
+                match cursor.advance_tail() {
+                    Some(_) => tail_advances += 1,
+                    None => assert!(was_empty, "Tail refused to advance over live events"),
+                }
+            }
+
+            assert!(tail_advances <= head_advances, "Tail passed head");
+            assert!(cursor.len() == head_advances - tail_advances);
+            assert!(!(cursor.is_full() && cursor.is_empty()));
+        }
+    }
+
+    /// **Proof: Committed Reservations Never Overlap the Tail**
+    ///
+    /// Every reserved slot lies outside the live `[tail, head)` range, and
+    /// after commit the ring still satisfies `head != tail` unless empty.
+    #[kani::proof]
+    #[kani::unwind(6)]
+    fn verify_reservation_commit() {
+        let mut cursor = any_cursor();
+        let capacity = cursor.capacity();
+        let tail = cursor.tail();
+        let len_before = cursor.len();
+        let n: usize = kani::any();
+        kani::assume(n <= capacity);
+
+        let Some(reservation) = cursor.reserve(n) else {
+            return;
+        };
+        for i in 0..reservation.len() {
+            let slot = reservation.slot(i);
+            let offset = (slot + capacity - tail) % capacity;
+            assert!(offset >= len_before, "Reserved slot overlaps unread events");
+        }
+        reservation.commit();
+
+        assert!(cursor.len() == len_before + n);
+        assert!(cursor.len() < capacity, "Commit wrapped head onto tail");
+        assert!(cursor.tail() == tail);
+    }
+
+    /// **Proof: Reservation Sequences Never Double-Allocate a Slot**
+    ///
+    /// Models a 4-slot ring (3 usable) through up to 4 operations, each a
+    /// reservation of symbolic size that is then symbolically committed or
+    /// aborted. The bound of 4 operations already exceeds the ring's
+    /// usable capacity, so every way of filling it is covered while the
+    /// state space stays small enough to check exhaustively.
+    #[kani::proof]
+    #[kani::unwind(5)]
+    fn verify_reservation_sequence() {
+        let mut cursor = Cursor::new(4);
+        let mut live = [false; 4];
+        let mut committed = 0usize;
+
+        for _ in 0..4 {
+            let n: usize = kani::any();
+            kani::assume(n <= 4);
+            let commit: bool = kani::any();
+            let available = cursor.available();
+
+            let Some(reservation) = cursor.reserve(n) else {
+                assert!(n > available, "Reservation refused with room left");
+                continue;
+            };
+            if commit {
+                for i in 0..reservation.len() {
+                    let slot = reservation.slot(i);
+                    assert!(!live[slot], "Slot allocated twice");
+                    live[slot] = true;
+                }
+                committed += reservation.len();
+                reservation.commit();
+            } else {
+                reservation.abort();
+            }
+
+            assert!(cursor.len() == committed);
+            assert!(cursor.len() < cursor.capacity(), "Head collided with tail");
+            assert!(cursor.is_full() == (committed == cursor.capacity() - 1));
+            assert!(cursor.is_empty() == (committed == 0));
+        }
+    }
+
+    /// **Proof: Aborted Reservations Leave the Cursor Unchanged**
+    #[kani::proof]
+    fn verify_reservation_abort() {
+        let mut cursor = any_cursor();
+        let head = cursor.head();
+        let tail = cursor.tail();
+        let n: usize = kani::any();
+        kani::assume(n <= cursor.capacity());
+
+        if let Some(reservation) = cursor.reserve(n) {
+            reservation.abort();
+        }
+
+        assert!(cursor.head() == head && cursor.tail() == tail);
+    }
+
+    /// **Proof: Replays Keep the Target Ordered**
+    ///
+    /// Any ring of up to 5 slots at any head/tail position, holding live
+    /// events in `lamport_ts` order, takes a batch of up to 3 symbolic
+    /// events stamped in either mode. Whatever the batch, the ring read
+    /// back from tail to head is still non-decreasing.
+    #[kani::proof]
+    #[kani::unwind(6)]
+    fn verify_replay_keeps_target_ordered() {
+        let mut cursor = any_cursor();
+        let mut ring = [0u64; 5];
+        for slot in ring.iter_mut() {
+            *slot = kani::any();
+        }
+        for i in 1..cursor.len() {
+            let (a, b) = (cursor.slot_at(i - 1), cursor.slot_at(i));
+            kani::assume(ring[a.unwrap()] <= ring[b.unwrap()]);
+        }
+        let floor = cursor.peek_head_slot().map(|slot| ring[slot]);
+
+        let mut events = [any_event(), any_event(), any_event()];
+        let original = events;
+        let n: usize = kani::any();
+        kani::assume(n <= events.len() && n <= cursor.available());
+        let batch = &mut events[..n];
+        let mode = if kani::any() {
+            AppendMode::Preserve
+        } else {
+            AppendMode::Resequence
+        };
+
+        match stamp_for_append(batch, floor, mode) {
+            Ok(_) => {
+                let reservation = cursor.reserve(n).unwrap();
+                for (i, event) in batch.iter().enumerate() {
+                    ring[reservation.slot(i)] = event.lamport_ts;
+                }
+                reservation.commit();
+            }
+            Err(_) => assert!(mode == AppendMode::Preserve, "Resequencing refused a batch"),
+        }
+
+        for (event, before) in batch.iter().zip(&original) {
+            assert!(event.node_id == before.node_id && event.stream_id == before.stream_id);
+            assert!(event.payload_offset == before.payload_offset);
+            assert!(event.checksum == before.checksum);
+            if mode == AppendMode::Preserve || event.lamport_ts == before.lamport_ts {
+                assert!(event.lamport_ts == before.lamport_ts && event.flags == before.flags);
+            } else {
+                assert!(event.flags == before.flags | FLAG_RESEQUENCED);
+            }
+        }
+        for i in 1..cursor.len() {
+            let (a, b) = (cursor.slot_at(i - 1), cursor.slot_at(i));
+            assert!(
+                ring[a.unwrap()] <= ring[b.unwrap()],
+                "Replay broke commit order in the target"
+            );
+        }
+    }
 }
 
 // Compile-time assertion that the proof module exists when building with Kani.
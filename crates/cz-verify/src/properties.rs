@@ -0,0 +1,286 @@
+//! # Runtime Mirror of the Proofs
+//!
+//! Every Kani harness in this crate proves its property for tiny, bounded
+//! inputs, and only when someone runs `cargo kani`. These property tests
+//! check the same statements on every `cargo test`, over randomly
+//! generated inputs far larger than the model checker can explore:
+//! thousands of events, ring walks of up to 100k steps, and real journal
+//! files, including replays that wrap the Index Ring.
+
+use std::collections::{HashMap, VecDeque};
+
+use cz_core::append::{stamp_for_append, AppendMode};
+use cz_core::hlc::{self, HlcClock};
+use cz_core::slot::{self, SLOT_SIZE};
+use cz_core::CausalEvent;
+use cz_io::cursor::Cursor;
+use cz_io::journal::{Journal, DEFAULT_INDEX_RING_CAPACITY, HEADER_SIZE, MIN_JOURNAL_SIZE};
+use proptest::prelude::*;
+
+fn any_event() -> impl Strategy<Value = CausalEvent> {
+    // Narrow key ranges so that ties, which the ordering must break
+    // consistently, actually occur.
+    (
+        0u64..64,
+        0u32..4,
+        0u16..4,
+        any::<u64>(),
+        any::<u32>(),
+        any::<u16>(),
+    )
+        .prop_map(|(ts, node, stream, offset, checksum, flags)| {
+            CausalEvent::with_flags(ts, node, stream, offset, checksum, flags)
+        })
+}
+
+fn fields(e: &CausalEvent) -> (u64, u32, u16, u16, u64, u32) {
+    (
+        e.lamport_ts,
+        e.node_id,
+        e.stream_id,
+        e.flags,
+        e.payload_offset,
+        e.checksum,
+    )
+}
+
+#[derive(Debug, Clone)]
+enum RingOp {
+    Head,
+    Tail,
+    Reserve { n: usize, commit: bool },
+}
+
+fn any_ring_op() -> impl Strategy<Value = RingOp> {
+    prop_oneof![
+        Just(RingOp::Head),
+        Just(RingOp::Tail),
+        (0usize..8, any::<bool>()).prop_map(|(n, commit)| RingOp::Reserve { n, commit }),
+    ]
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(16))]
+
+    /// Mirrors `verify_monotonicity`, `verify_antisymmetry` and
+    /// `verify_sort_keeps_payload_pairing` over 10k events.
+    #[test]
+    fn test_prop_sort_is_monotonic_and_moves_whole_events(
+        events in proptest::collection::vec(any_event(), 10_000),
+    ) {
+        let mut sorted: Vec<(usize, CausalEvent)> = events.iter().copied().enumerate().collect();
+        sorted.sort_by_key(|(_, event)| *event);
+
+        let mut seen = vec![false; events.len()];
+        for (index, event) in &sorted {
+            prop_assert!(!std::mem::replace(&mut seen[*index], true));
+            prop_assert_eq!(fields(event), fields(&events[*index]));
+        }
+        for pair in sorted.windows(2) {
+            let (a, b) = (&pair[0].1, &pair[1].1);
+            prop_assert!(a.lamport_ts <= b.lamport_ts);
+            if a <= b && b <= a {
+                prop_assert_eq!(
+                    (a.lamport_ts, a.node_id, a.stream_id),
+                    (b.lamport_ts, b.node_id, b.stream_id)
+                );
+            }
+        }
+    }
+
+    /// Mirrors `verify_transitivity`.
+    #[test]
+    fn test_prop_ordering_is_transitive(a in any_event(), b in any_event(), c in any_event()) {
+        if a <= b && b <= c {
+            prop_assert!(a <= c);
+        }
+    }
+
+    /// Mirrors `verify_tail_never_passes_head` and the reservation proofs:
+    /// however head/tail advances and committed or aborted batches
+    /// interleave, `len()` matches a model of the live slots, full/empty
+    /// agree with it, and no live slot is ever handed out again.
+    #[test]
+    fn test_prop_ring_walk_matches_model(
+        capacity in 2usize..64,
+        ops in proptest::collection::vec(any_ring_op(), 0..=100_000),
+    ) {
+        let mut cursor = Cursor::new(capacity);
+        let mut live = VecDeque::new();
+        let mut is_live = vec![false; capacity];
+
+        for op in ops {
+            match op {
+                RingOp::Head => match cursor.advance_head() {
+                    Some(slot) => {
+                        prop_assert!(!std::mem::replace(&mut is_live[slot], true));
+                        live.push_back(slot);
+                    }
+                    None => prop_assert_eq!(live.len(), capacity - 1),
+                },
+                RingOp::Tail => match cursor.advance_tail() {
+                    Some(slot) => {
+                        prop_assert_eq!(live.pop_front(), Some(slot));
+                        is_live[slot] = false;
+                    }
+                    None => prop_assert!(live.is_empty()),
+                },
+                RingOp::Reserve { n, commit } => {
+                    let free = capacity - 1 - live.len();
+                    match cursor.reserve(n) {
+                        Some(reservation) if commit => {
+                            for slot in reservation.slots() {
+                                prop_assert!(!std::mem::replace(&mut is_live[slot], true));
+                                live.push_back(slot);
+                            }
+                            reservation.commit();
+                        }
+                        Some(reservation) => reservation.abort(),
+                        None => prop_assert!(n > free),
+                    }
+                }
+            }
+            prop_assert_eq!(cursor.len(), live.len());
+            prop_assert_eq!(cursor.is_empty(), live.is_empty());
+            prop_assert_eq!(cursor.is_full(), live.len() == capacity - 1);
+        }
+    }
+
+    /// Mirrors the HLC proofs over long runs of ticks and merges with a
+    /// wall clock that jitters backwards and forwards.
+    #[test]
+    fn test_prop_hlc_is_monotonic_and_drift_bounded(
+        max_drift_ms in 0u64..1_000,
+        start in 0u64..1 << 40,
+        steps in proptest::collection::vec(
+            (-2_000i64..2_000, proptest::option::of(any::<u64>())),
+            0..10_000,
+        ),
+    ) {
+        let mut clock = HlcClock::new(max_drift_ms);
+        let mut wall_ms = start;
+        for (jump, remote) in steps {
+            wall_ms = wall_ms.saturating_add_signed(jump);
+            let previous = clock.last();
+            let result = match remote {
+                Some(remote) => clock.observe(remote, wall_ms),
+                None => clock.tick(wall_ms),
+            };
+            match result {
+                Ok(ts) => {
+                    prop_assert!(ts > previous);
+                    prop_assert!(remote.is_none_or(|r| ts > r));
+                    prop_assert!(hlc::millis(ts) <= wall_ms.saturating_add(max_drift_ms));
+                }
+                Err(_) => prop_assert_eq!(clock.last(), previous),
+            }
+        }
+    }
+
+    /// Mirrors the slot occupancy proofs: framed slots round-trip, and
+    /// flipping bits in any one byte means the slot is no longer occupied.
+    #[test]
+    fn test_prop_framed_slots_round_trip_and_detect_corruption(
+        event in any_event(),
+        seq in any::<u32>(),
+        index in 0..SLOT_SIZE,
+        mask in 1u8..,
+    ) {
+        let mut bytes = slot::encode_slot(&event, seq);
+        let decoded = slot::decode_slot(&bytes, seq);
+        prop_assert_eq!(decoded.map(|e| fields(&e)), Some(fields(&event)));
+
+        bytes[index] ^= mask;
+        prop_assert!(!slot::slot_occupied(&bytes, seq));
+    }
+}
+
+/// Index Ring slots of the journal the replay property writes to; large
+/// enough for every generated batch on top of the existing events.
+const REPLAY_RING_SLOTS: usize = 512;
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(8))]
+
+    /// Events written to a real journal read back field for field, with
+    /// the last write to a slot winning.
+    #[test]
+    fn test_prop_journal_round_trips_events(
+        writes in proptest::collection::vec((0..DEFAULT_INDEX_RING_CAPACITY, any_event()), 1..512),
+    ) {
+        let path = std::env::temp_dir().join(format!(
+            "cz-verify-roundtrip-{}.db",
+            std::process::id()
+        ));
+        let size = MIN_JOURNAL_SIZE + 4096;
+        let mut journal = Journal::open(&path, size).unwrap();
+
+        let mut expected = HashMap::new();
+        for (slot, event) in &writes {
+            unsafe { journal.write_event_at(*slot, event) };
+            expected.insert(*slot, fields(event));
+        }
+        for (slot, want) in expected {
+            let got = unsafe { journal.read_event_at(slot) };
+            prop_assert_eq!(fields(&got), want);
+        }
+
+        drop(journal);
+        std::fs::remove_file(&path).ok();
+    }
+
+    /// Mirrors `verify_replay_keeps_target_ordered` on a real journal whose
+    /// ring wraps during the replay: the events read back from tail to
+    /// head are non-decreasing, and a refused batch writes nothing.
+    #[test]
+    fn test_prop_replay_keeps_target_ordered(
+        mut existing in proptest::collection::vec(0u64..128, 0..64),
+        mut batch in proptest::collection::vec(any_event(), 0..256),
+        sort_batch in any::<bool>(),
+        resequence in any::<bool>(),
+        start in REPLAY_RING_SLOTS - 128..REPLAY_RING_SLOTS,
+    ) {
+        let path = std::env::temp_dir().join(format!(
+            "cz-verify-replay-{}.db",
+            std::process::id()
+        ));
+        let ring = REPLAY_RING_SLOTS * CausalEvent::size_bytes();
+        let size = (HEADER_SIZE + ring + 4096) as u64;
+        let mut journal = Journal::open_with_ring_size(&path, size, ring).unwrap();
+
+        let mut cursor = Cursor::restore(REPLAY_RING_SLOTS, start, start);
+        existing.sort_unstable();
+        for &ts in &existing {
+            let slot = cursor.advance_head().unwrap();
+            unsafe { journal.write_event_at(slot, &CausalEvent::new(ts, 1, 1, 0, 0)) };
+        }
+        journal.publish_cursor(&cursor);
+
+        if sort_batch {
+            batch.sort_by_key(|event| event.lamport_ts);
+        }
+        let mode = if resequence { AppendMode::Resequence } else { AppendMode::Preserve };
+        match stamp_for_append(&mut batch, existing.last().copied(), mode) {
+            Ok(_) => {
+                let reservation = cursor.reserve(batch.len()).unwrap();
+                for (slot, event) in reservation.slots().zip(&batch) {
+                    unsafe { journal.write_event_at(slot, event) };
+                }
+                reservation.commit();
+                journal.publish_cursor(&cursor);
+            }
+            Err(_) => prop_assert_eq!(mode, AppendMode::Preserve),
+        }
+
+        let live = journal.live_cursor_view();
+        let timestamps: Vec<u64> = live
+            .slots()
+            .map(|slot| journal.event_at(slot).unwrap().lamport_ts)
+            .collect();
+        prop_assert!(timestamps.len() == existing.len() || timestamps.len() == existing.len() + batch.len());
+        prop_assert!(timestamps.windows(2).all(|pair| pair[0] <= pair[1]), "{:?}", timestamps);
+
+        drop(journal);
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -0,0 +1,12 @@
+//! Feeds arbitrary UTF-8 to the CQL parser. It must return `Ok` or `Err`
+//! for every input, never panic.
+
+#![no_main]
+
+use cz_hub::query::parser;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &str| {
+    let _ = parser::parse(input);
+    let _ = parser::parse_conditions(input);
+});
@@ -0,0 +1,36 @@
+//! The hub decompresses any payload flagged `FLAG_COMPRESSED` straight out
+//! of blob storage, so `compress::decompress` sees arbitrary bytes. It must
+//! reject them with an error rather than panic, and anything it accepts
+//! must survive another compress → decompress round trip.
+//!
+//! Input: a compressed payload as the sequencer stores it, the 4-byte
+//! decompressed length included. The length is capped so a fuzzed header
+//! cannot ask for a huge buffer.
+
+#![no_main]
+
+use cz_core::compress;
+use libfuzzer_sys::fuzz_target;
+
+const MAX_LEN: usize = 1 << 16;
+
+fuzz_target!(|data: &[u8]| {
+    let Some(header) = compress::read_header(data) else {
+        assert!(compress::decompress(data, &mut []).is_err());
+        return;
+    };
+    let mut out = vec![0u8; header.decompressed_len.min(MAX_LEN)];
+    let Ok(len) = compress::decompress(data, &mut out) else {
+        return;
+    };
+    assert_eq!(len, header.decompressed_len);
+
+    let mut frame = vec![0u8; compress::max_compressed_len(len)];
+    let frame_len = compress::compress(&out, &mut frame).unwrap();
+    let mut again = vec![0u8; len];
+    assert_eq!(
+        compress::decompress(&frame[..frame_len], &mut again),
+        Ok(len)
+    );
+    assert_eq!(again, out);
+});
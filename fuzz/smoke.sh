@@ -0,0 +1,23 @@
+#!/bin/bash
+# Run every fuzz target briefly, e.g. in CI: fails on the first crash.
+#
+#   ./fuzz/smoke.sh            # 5 seconds per target
+#   FUZZ_SECONDS=60 ./fuzz/smoke.sh
+#
+# Needs a nightly toolchain and cargo-fuzz (`cargo install cargo-fuzz`).
+set -euo pipefail
+
+cd "$(dirname "$0")"
+SECONDS_PER_TARGET="${FUZZ_SECONDS:-5}"
+
+for target in $(cargo +nightly fuzz list); do
+    echo "--- fuzz: $target (${SECONDS_PER_TARGET}s) ---"
+    mkdir -p "corpus/$target"
+    if [ -d "seeds/$target" ]; then
+        cp seeds/"$target"/* "corpus/$target/"
+    fi
+    cargo +nightly fuzz run "$target" "corpus/$target" -- \
+        -max_total_time="$SECONDS_PER_TARGET" -print_final_stats=1
+done
+
+echo "All fuzz targets ran without crashing."